@@ -0,0 +1,130 @@
+//! Serialization throughput of `Message::to_binary`/`from_binary`.
+//!
+//! `cargo bench --bench message_serialization` measures; `cargo test --benches` runs every
+//! benchmark once as a smoke test.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use signal_manager_service::message::{ConnectPayload, Message, MessageType, Payload, PayloadType, RegisterPayload, SignalPayload};
+
+fn connect() -> Message {
+    Message::new(
+        MessageType::Connect,
+        Payload::Connect(ConnectPayload {
+            client_id: "bench_client".to_string(),
+            auth_token: "bench_token".to_string(),
+        }),
+    )
+}
+
+fn large_register() -> Message {
+    Message::new(
+        MessageType::Register,
+        Payload::Register(RegisterPayload {
+            version: "1.0.0".to_string(),
+            client_id: "bench_client".to_string(),
+            auth_token: "bench_token".to_string(),
+            capabilities: Some((0..32).map(|i| format!("capability_{}", i)).collect()),
+            metadata: Some(serde_json::json!({
+                "device": "x".repeat(100),
+                "labels": (0..10).map(|i| format!("label_{}", i)).collect::<Vec<_>>(),
+            })),
+        }),
+    )
+}
+
+fn large_signal_offer() -> Message {
+    Message::new(
+        MessageType::SignalOffer,
+        Payload::SignalOffer(SignalPayload {
+            target_client_id: "bench_peer".to_string(),
+            // Roughly the size of a real SDP offer with a few media sections
+            signal_data: "v=0\r\na=candidate:1 1 udp 2122260223 192.0.2.1 54400 typ host\r\n".repeat(100),
+            room_id: Some("bench_room".to_string()),
+            sequence: None,
+        }),
+    )
+}
+
+/// About 200 KB of SDP, large enough to be worth compressing
+fn huge_signal_offer() -> Message {
+    Message::new(
+        MessageType::SignalOffer,
+        Payload::SignalOffer(SignalPayload {
+            target_client_id: "bench_peer".to_string(),
+            signal_data: (0..3500)
+                .map(|i| format!("a=candidate:{} 1 udp 2122260223 192.0.2.{} {} typ host\r\n", i, i % 250, 50000 + i))
+                .collect(),
+            room_id: Some("bench_room".to_string()),
+            sequence: None,
+        }),
+    )
+}
+
+struct Case {
+    name: String,
+    message: Message,
+    frame: Vec<u8>,
+}
+
+/// Every message/payload-type pair the codec can encode, with its encoded frame.
+/// Panics if a frame does not decode, so the smoke run catches codec regressions.
+fn cases() -> Vec<Case> {
+    let mut cases = Vec::new();
+    for (name, message) in [("connect", connect()), ("large_register", large_register()), ("large_signal_offer", large_signal_offer())] {
+        for payload_type in PayloadType::SUPPORTED {
+            let message = Message { payload_type, ..message.clone() };
+            // Not every payload has a Binary or Text encoding
+            let Ok(frame) = message.to_binary() else { continue };
+            if let Err(e) = Message::from_binary(&frame) {
+                panic!("{} as {:?} does not decode: {}", name, payload_type, e);
+            }
+            cases.push(Case { name: format!("{}/{:?}", name, payload_type), message, frame });
+        }
+    }
+    for payload_type in [PayloadType::Json, PayloadType::Cbor] {
+        assert_eq!(cases.iter().filter(|case| case.name.ends_with(&format!("/{:?}", payload_type))).count(), 3);
+    }
+    cases
+}
+
+fn serialization(c: &mut Criterion) {
+    let cases = cases();
+
+    let mut group = c.benchmark_group("to_binary");
+    for case in &cases {
+        group.throughput(Throughput::Bytes(case.frame.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(&case.name), &case.message, |b, message| {
+            b.iter(|| black_box(message).to_binary().unwrap())
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("from_binary");
+    for case in &cases {
+        group.throughput(Throughput::Bytes(case.frame.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(&case.name), &case.frame, |b, frame| {
+            b.iter(|| Message::from_binary(black_box(frame)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+/// zstd compression of a 200 KB offer at the default 4096-byte threshold
+fn compression(c: &mut Criterion) {
+    let message = huge_signal_offer();
+    let frame = message.to_binary_compressed(4096).unwrap();
+    assert!(frame.len() < message.to_binary().unwrap().len() / 4);
+
+    let mut group = c.benchmark_group("compressed");
+    group.throughput(Throughput::Bytes(frame.len() as u64));
+    group.bench_function("to_binary_compressed/huge_signal_offer", |b| {
+        b.iter(|| black_box(&message).to_binary_compressed(4096).unwrap())
+    });
+    group.bench_function("from_binary/huge_signal_offer", |b| {
+        b.iter(|| Message::from_binary(black_box(&frame)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, serialization, compression);
+criterion_main!(benches);
@@ -0,0 +1,9 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-changed=proto/signal.proto");
+    // Use the bundled protoc unless one is configured, so builds don't need it installed
+    if std::env::var_os("PROTOC").is_none() {
+        std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    }
+    prost_build::compile_protos(&["proto/signal.proto"], &["proto"])?;
+    Ok(())
+}
@@ -1,6 +1,9 @@
-use crate::config::Config;
+use crate::config::{AuthConfig, ClientToken, Config, JwtAlgorithm};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::sync::RwLock;
 use tracing::{debug, warn};
 
@@ -8,28 +11,57 @@ pub struct AuthManager {
     config: Arc<Config>,
     // In a real implementation, this would be replaced with a proper token store
     // or integration with an authentication service
-    valid_tokens: Arc<RwLock<HashMap<String, String>>>,
+    valid_tokens: Arc<RwLock<HashMap<String, Vec<ClientToken>>>>,
+    jwt_key: Option<DecodingKey>,
+}
+
+/// Claims the "jwt" auth method reads; `exp` and `sub` must be present
+#[derive(Debug, Deserialize)]
+struct JwtClaims {
+    sub: String,
+    exp: u64,
 }
 
 impl AuthManager {
     pub fn new(config: Arc<Config>) -> Self {
-        let mut valid_tokens = HashMap::new();
-        
         // Load tokens from configuration
-        let api_keys = config.parse_api_keys();
-        for (client_id, token) in api_keys {
-            valid_tokens.insert(client_id, token);
-        }
+        let mut valid_tokens = config.parse_client_tokens();
         
         // For development/testing, add some sample tokens if none configured
         if valid_tokens.is_empty() {
-            valid_tokens.insert("test_client_1".to_string(), "test_token_1".to_string());
-            valid_tokens.insert("test_client_2".to_string(), "test_token_2".to_string());
+            valid_tokens.insert("test_client_1".to_string(), vec![ClientToken { token: "test_token_1".to_string(), valid_until: None }]);
+            valid_tokens.insert("test_client_2".to_string(), vec![ClientToken { token: "test_token_2".to_string(), valid_until: None }]);
         }
         
+        let jwt_key = if config.auth.auth_method == "jwt" { Self::load_jwt_key(&config.auth) } else { None };
+
         Self {
             config,
             valid_tokens: Arc::new(RwLock::new(valid_tokens)),
+            jwt_key,
+        }
+    }
+
+    /// Key JWTs are verified with; `None` (every token rejected) if it can't be loaded
+    fn load_jwt_key(auth: &AuthConfig) -> Option<DecodingKey> {
+        match auth.jwt_algorithm {
+            JwtAlgorithm::Hs256 => Some(DecodingKey::from_secret(auth.token_secret.as_bytes())),
+            JwtAlgorithm::Rs256 => {
+                let Some(path) = &auth.jwt_public_key_path else {
+                    warn!("RS256 JWT authentication needs auth.jwt_public_key_path");
+                    return None;
+                };
+                let key = std::fs::read(path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|pem| DecodingKey::from_rsa_pem(&pem).map_err(|e| e.to_string()));
+                match key {
+                    Ok(key) => Some(key),
+                    Err(e) => {
+                        warn!("Failed to load JWT public key from {}: {}", path, e);
+                        None
+                    }
+                }
+            }
         }
     }
 
@@ -39,6 +71,7 @@ impl AuthManager {
         match self.config.auth.auth_method.as_str() {
             "token" => self.authenticate_with_token(client_id, auth_token).await,
             "api_key" => self.authenticate_with_api_key(client_id, auth_token).await,
+            "jwt" => self.authenticate_with_jwt(client_id, auth_token),
 
             _ => {
                 warn!("Unknown authentication method: {}", self.config.auth.auth_method);
@@ -50,14 +83,18 @@ impl AuthManager {
     async fn authenticate_with_token(&self, client_id: &str, auth_token: &str) -> Result<bool, crate::Error> {
         let tokens = self.valid_tokens.read().await;
         
-        if let Some(expected_token) = tokens.get(client_id) {
-            if expected_token == auth_token {
-                debug!("Token authentication successful for client: {}", client_id);
-                return Ok(true);
-            } else {
+        if let Some(client_tokens) = tokens.get(client_id) {
+            // Any of the client's tokens will do, so a rotation can overlap old and new keys
+            let Some(matched) = client_tokens.iter().find(|candidate| candidate.token == auth_token) else {
                 warn!("Invalid token for client: {}", client_id);
                 return Ok(false);
+            };
+            if !matched.is_valid_at(chrono::Utc::now()) {
+                warn!("Expired token for client: {} (valid until {:?})", client_id, matched.valid_until);
+                return Ok(false);
             }
+            debug!("Token authentication successful for client: {}", client_id);
+            return Ok(true);
         }
         
         warn!("Unknown client: {}", client_id);
@@ -69,11 +106,46 @@ impl AuthManager {
         self.authenticate_with_token(client_id, api_key).await
     }
 
+    /// Accept a JWT signed with the configured key whose `sub` is `client_id` and whose `exp`
+    /// is neither past nor more than `token_expiry` seconds away
+    fn authenticate_with_jwt(&self, client_id: &str, token: &str) -> Result<bool, crate::Error> {
+        let Some(key) = &self.jwt_key else {
+            warn!("No JWT verification key loaded, rejecting client: {}", client_id);
+            return Ok(false);
+        };
+        let algorithm = match self.config.auth.jwt_algorithm {
+            JwtAlgorithm::Hs256 => Algorithm::HS256,
+            JwtAlgorithm::Rs256 => Algorithm::RS256,
+        };
+        let mut validation = Validation::new(algorithm);
+        validation.set_required_spec_claims(&["exp", "sub"]);
+
+        let claims = match jsonwebtoken::decode::<JwtClaims>(token, key, &validation) {
+            Ok(data) => data.claims,
+            Err(e) => {
+                warn!("Invalid JWT for client {}: {}", client_id, e);
+                return Ok(false);
+            }
+        };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or(0);
+        if claims.exp > now.saturating_add(self.config.auth.token_expiry) {
+            warn!("JWT for client {} expires beyond the {}s token_expiry", client_id, self.config.auth.token_expiry);
+            return Ok(false);
+        }
+        if claims.sub != client_id {
+            warn!("JWT subject {} does not match client: {}", claims.sub, client_id);
+            return Ok(false);
+        }
+
+        debug!("JWT authentication successful for client: {}", client_id);
+        Ok(true)
+    }
+
 
 
     pub async fn add_valid_token(&self, client_id: String, token: String) {
         let mut tokens = self.valid_tokens.write().await;
-        tokens.insert(client_id, token);
+        tokens.insert(client_id, vec![ClientToken { token, valid_until: None }]);
     }
 
     pub async fn remove_token(&self, client_id: &str) {
@@ -0,0 +1,83 @@
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Exponential backoff parameters shared by every retry loop in the service
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackoffConfig {
+    /// Delay before the first retry, in milliseconds
+    pub base_ms: u64,
+    /// Upper bound for any single delay, in milliseconds
+    pub max_ms: u64,
+    /// Growth factor applied to the delay after each attempt
+    pub multiplier: f64,
+    /// Fraction of each delay randomised in either direction (0.0 to 1.0)
+    pub jitter: f64,
+    /// Retries before giving up; 0 disables retrying
+    pub max_retries: u32,
+}
+
+impl Default for BackoffConfig {
+    fn default() -> Self {
+        Self {
+            base_ms: 200,
+            max_ms: 10_000,
+            multiplier: 2.0,
+            jitter: 0.2,
+            max_retries: 3,
+        }
+    }
+}
+
+impl BackoffConfig {
+    /// Delay before retry `attempt` (starting at 0) for a uniform `sample` in [0, 1).
+    /// The result lies within `jitter` of the nominal exponential delay and never exceeds `max_ms`.
+    pub fn delay(&self, attempt: u32, sample: f64) -> Duration {
+        let max_ms = self.max_ms as f64;
+        let nominal = (self.base_ms as f64 * self.multiplier.powi(attempt.min(1024) as i32)).min(max_ms);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let factor = 1.0 - jitter + 2.0 * jitter * sample.clamp(0.0, 1.0);
+        Duration::from_millis((nominal * factor).min(max_ms) as u64)
+    }
+
+    /// Jittered delays for each retry, ending after `max_retries`
+    pub fn delays(&self) -> Backoff {
+        Backoff {
+            config: self.clone(),
+            attempt: 0,
+            rng: SystemRandom::new(),
+        }
+    }
+}
+
+/// Iterator over the retry delays of a [`BackoffConfig`]
+pub struct Backoff {
+    config: BackoffConfig,
+    attempt: u32,
+    rng: SystemRandom,
+}
+
+impl Backoff {
+    fn sample(&self) -> f64 {
+        let mut bytes = [0u8; 8];
+        if self.rng.fill(&mut bytes).is_err() {
+            return 0.5;
+        }
+        // Top 53 bits give a uniform f64 in [0, 1)
+        (u64::from_be_bytes(bytes) >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl Iterator for Backoff {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Duration> {
+        if self.attempt >= self.config.max_retries {
+            return None;
+        }
+        let delay = self.config.delay(self.attempt, self.sample());
+        self.attempt += 1;
+        Some(delay)
+    }
+}
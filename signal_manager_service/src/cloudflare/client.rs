@@ -1,8 +1,9 @@
 use crate::config::Config;
 use crate::cloudflare::models::*;
-use reqwest::Client;
+use reqwest::{Client, StatusCode};
 use serde_json::Value;
 use std::sync::Arc;
+use std::sync::Mutex;
 use tracing::{debug, error, info, warn};
 use async_trait::async_trait;
 
@@ -14,6 +15,40 @@ pub trait CloudflareClientTrait: Send + Sync {
     async fn terminate_session(&self, session_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
     async fn get_session(&self, session_id: &str) -> Result<Value, Box<dyn std::error::Error + Send + Sync>>;
     async fn validate_credentials(&self) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+    async fn generate_turn_credentials(&self) -> Result<TurnCredentials, Box<dyn std::error::Error + Send + Sync>>;
+}
+
+/// Which failures of a Cloudflare request are retried
+#[derive(Debug, Clone, Copy)]
+enum Retry {
+    /// Safe to repeat: transport errors, 429 and 5xx
+    Idempotent,
+    /// Only repeated when Cloudflare cannot have acted on it: connection failures, 429 and 503
+    Unprocessed,
+}
+
+impl Retry {
+    fn retries_status(self, status: StatusCode) -> bool {
+        match self {
+            Retry::Idempotent => status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS,
+            Retry::Unprocessed => status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+
+    fn retries_error(self, error: &reqwest::Error) -> bool {
+        match self {
+            Retry::Idempotent => true,
+            Retry::Unprocessed => error.is_connect(),
+        }
+    }
+}
+
+/// A Cloudflare request still failing transiently after every `cloudflare.retry` attempt
+#[derive(Debug, thiserror::Error)]
+#[error("Cloudflare request failed after {attempts} attempts: {last_error}")]
+pub struct RetriesExhausted {
+    pub attempts: u32,
+    pub last_error: String,
 }
 
 /// Cloudflare Realtime API client
@@ -22,7 +57,10 @@ pub struct CloudflareClient {
     app_secret: String,
     base_url: String,
     http_client: Client,
-    _config: Arc<Config>,
+    config: Arc<Config>,
+    turn_credentials: Mutex<Option<TurnCredentials>>,
+    /// Held while minting TURN credentials, so only one request is in flight
+    turn_refresh: tokio::sync::Mutex<()>,
 }
 
 #[async_trait]
@@ -50,6 +88,10 @@ impl CloudflareClientTrait for CloudflareClient {
     async fn validate_credentials(&self) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
         self.validate_credentials_impl().await
     }
+
+    async fn generate_turn_credentials(&self) -> Result<TurnCredentials, Box<dyn std::error::Error + Send + Sync>> {
+        self.generate_turn_credentials_impl().await
+    }
 }
 
 impl CloudflareClient {
@@ -68,10 +110,51 @@ impl CloudflareClient {
             app_secret,
             base_url,
             http_client,
-            _config: config,
+            config,
+            turn_credentials: Mutex::new(None),
+            turn_refresh: tokio::sync::Mutex::new(()),
         })
     }
 
+    /// Send a request, retrying the failures `retry` allows with the `cloudflare.retry` backoff.
+    /// A request still failing transiently once the retries are used up yields [`RetriesExhausted`].
+    async fn send_with_retry(
+        &self,
+        retry: Retry,
+        build_request: impl Fn() -> reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, Box<dyn std::error::Error + Send + Sync>> {
+        let mut retry_delays = self.config.cloudflare.retry.delays();
+        let mut attempts = 1;
+        loop {
+            let result = build_request().send().await;
+            let transient = match &result {
+                Ok(response) => retry.retries_status(response.status()),
+                Err(e) => retry.retries_error(e),
+            };
+            if !transient {
+                return Ok(result?);
+            }
+            match retry_delays.next() {
+                Some(delay) => {
+                    warn!("Cloudflare request failed transiently (attempt {}), retrying in {:?}", attempts, delay);
+                    tokio::time::sleep(delay).await;
+                    attempts += 1;
+                }
+                None => {
+                    let last_error = match result {
+                        Ok(response) => {
+                            let status = response.status();
+                            format!("{status}: {}", response.text().await.unwrap_or_default())
+                        }
+                        Err(e) => e.to_string(),
+                    };
+                    error!("Cloudflare request failed after {} attempts: {}", attempts, last_error);
+                    return Err(Box::new(RetriesExhausted { attempts, last_error }));
+                }
+            }
+        }
+    }
+
     /// Create a new WebRTC session with Cloudflare
     async fn create_session_impl(&self, offer_sdp: String) -> Result<CloudflareSessionResponse, Box<dyn std::error::Error + Send + Sync>> {
         let url = format!("{}/apps/{}/sessions/new", self.base_url, self.app_id);
@@ -86,13 +169,13 @@ impl CloudflareClient {
         debug!("Sending Cloudflare session creation request to URL: {}", url);
         debug!("Cloudflare session creation request body: {}", body);
         
-        let response = self.http_client
-            .post(&url)
-            .header("Content-Type", "application/json")
-            .header("Authorization", format!("Bearer {}", self.app_secret))
-            .json(&body)
-            .send()
-            .await?;
+        let response = self.send_with_retry(Retry::Unprocessed, || {
+            self.http_client
+                .post(&url)
+                .header("Content-Type", "application/json")
+                .header("Authorization", format!("Bearer {}", self.app_secret))
+                .json(&body)
+        }).await?;
 
         if !response.status().is_success() {
             let error_text = response.text().await?;
@@ -141,13 +224,13 @@ impl CloudflareClient {
 
         debug!("Adding tracks to session {} with URL: {}", session_id, url);
         
-        let response = self.http_client
-            .post(&url)
-            .header("Content-Type", "application/json")
-            .header("Authorization", format!("Bearer {}", self.app_secret))
-            .json(&body)
-            .send()
-            .await?;
+        let response = self.send_with_retry(Retry::Unprocessed, || {
+            self.http_client
+                .post(&url)
+                .header("Content-Type", "application/json")
+                .header("Authorization", format!("Bearer {}", self.app_secret))
+                .json(&body)
+        }).await?;
 
         if !response.status().is_success() {
             let error_text = response.text().await?;
@@ -178,13 +261,13 @@ impl CloudflareClient {
 
         debug!("Sending answer SDP to session {} with URL: {}", session_id, url);
         
-        let response = self.http_client
-            .put(&url)
-            .header("Content-Type", "application/json")
-            .header("Authorization", format!("Bearer {}", self.app_secret))
-            .json(&body)
-            .send()
-            .await?;
+        let response = self.send_with_retry(Retry::Idempotent, || {
+            self.http_client
+                .put(&url)
+                .header("Content-Type", "application/json")
+                .header("Authorization", format!("Bearer {}", self.app_secret))
+                .json(&body)
+        }).await?;
 
         if !response.status().is_success() {
             let error_text = response.text().await?;
@@ -202,11 +285,11 @@ impl CloudflareClient {
         
         debug!("Terminating session {} with URL: {}", session_id, url);
         
-        let response = self.http_client
-            .delete(&url)
-            .header("Authorization", format!("Bearer {}", self.app_secret))
-            .send()
-            .await?;
+        let response = self.send_with_retry(Retry::Idempotent, || {
+            self.http_client
+                .delete(&url)
+                .header("Authorization", format!("Bearer {}", self.app_secret))
+        }).await?;
 
         if !response.status().is_success() {
             let error_text = response.text().await?;
@@ -224,11 +307,11 @@ impl CloudflareClient {
         
         debug!("Getting session info for {} with URL: {}", session_id, url);
         
-        let response = self.http_client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", self.app_secret))
-            .send()
-            .await?;
+        let response = self.send_with_retry(Retry::Idempotent, || {
+            self.http_client
+                .get(&url)
+                .header("Authorization", format!("Bearer {}", self.app_secret))
+        }).await?;
 
         if !response.status().is_success() {
             let error_text = response.text().await?;
@@ -246,11 +329,11 @@ impl CloudflareClient {
         
         debug!("Validating Cloudflare credentials with URL: {}", url);
         
-        let response = self.http_client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", self.app_secret))
-            .send()
-            .await?;
+        let response = self.send_with_retry(Retry::Idempotent, || {
+            self.http_client
+                .get(&url)
+                .header("Authorization", format!("Bearer {}", self.app_secret))
+        }).await?;
 
         let is_valid = response.status().is_success();
         
@@ -262,4 +345,69 @@ impl CloudflareClient {
         
         Ok(is_valid)
     }
+
+    /// Cached TURN credentials, minting new ones from the `cloudflare.turn` key when absent
+    /// or within `refresh_margin_secs` of expiry. While one call mints, others are served the
+    /// cached credentials if they have not expired yet, and otherwise wait for the new ones.
+    async fn generate_turn_credentials_impl(&self) -> Result<TurnCredentials, Box<dyn std::error::Error + Send + Sync>> {
+        let turn = &self.config.cloudflare.turn;
+        if !turn.is_enabled() {
+            return Err("Cloudflare TURN key not configured".into());
+        }
+        let margin = chrono::Duration::from_std(std::time::Duration::from_secs(turn.refresh_margin_secs))
+            .unwrap_or(chrono::Duration::MAX);
+        let ttl = chrono::Duration::from_std(std::time::Duration::from_secs(turn.credential_ttl_secs))
+            .map_err(|_| "Cloudflare TURN credential_ttl_secs is out of range")?;
+
+        let cached = self.turn_credentials.lock().unwrap().clone();
+        if let Some(credentials) = &cached {
+            if !credentials.expires_within(margin) {
+                return Ok(credentials.clone());
+            }
+        }
+        let _refreshing = match self.turn_refresh.try_lock() {
+            Ok(guard) => guard,
+            Err(_) => match cached.filter(|credentials| !credentials.expires_within(chrono::Duration::zero())) {
+                Some(credentials) => return Ok(credentials),
+                None => self.turn_refresh.lock().await,
+            },
+        };
+        // A refresh that finished while this call waited leaves fresh credentials behind
+        if let Some(credentials) = self.turn_credentials.lock().unwrap().as_ref() {
+            if !credentials.expires_within(margin) {
+                return Ok(credentials.clone());
+            }
+            debug!("Cloudflare TURN credentials expiring, generating new ones");
+        }
+
+        let url = format!("{}/turn/keys/{}/credentials/generate", self.base_url, turn.key_id);
+        let body = serde_json::json!({ "ttl": turn.credential_ttl_secs });
+
+        debug!("Generating Cloudflare TURN credentials with URL: {}", url);
+
+        let response = self.send_with_retry(Retry::Idempotent, || {
+            self.http_client
+                .post(&url)
+                .header("Authorization", format!("Bearer {}", turn.api_token))
+                .json(&body)
+        }).await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            error!("Cloudflare TURN credential generation failed: {}", error_text);
+            return Err(format!("Cloudflare API error: {error_text}").into());
+        }
+
+        let result: CloudflareTurnResponse = response.json().await?;
+        let credentials = TurnCredentials {
+            ice_servers: vec![result.ice_servers],
+            expires_at: chrono::Utc::now()
+                .checked_add_signed(ttl)
+                .ok_or("Cloudflare TURN credential_ttl_secs is out of range")?,
+        };
+
+        info!("Generated Cloudflare TURN credentials valid until {}", credentials.expires_at);
+        *self.turn_credentials.lock().unwrap() = Some(credentials.clone());
+        Ok(credentials)
+    }
 } 
\ No newline at end of file
@@ -2,6 +2,6 @@ pub mod client;
 pub mod models;
 pub mod session;
 
-pub use client::{CloudflareClient, CloudflareClientTrait};
+pub use client::{CloudflareClient, CloudflareClientTrait, RetriesExhausted};
 pub use models::*;
 pub use session::CloudflareSession; 
\ No newline at end of file
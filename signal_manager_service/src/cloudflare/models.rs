@@ -137,6 +137,43 @@ pub struct CloudflareTracksResponse {
     pub requires_immediate_renegotiation: Option<bool>,
 }
 
+/// Cloudflare TURN API response for credential generation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CloudflareTurnResponse {
+    #[serde(rename = "iceServers")]
+    pub ice_servers: IceServer,
+}
+
+/// ICE server entry in the shape of the browser `RTCIceServer` dictionary
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IceServer {
+    pub urls: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential: Option<String>,
+}
+
+impl From<IceServer> for crate::message::IceServer {
+    fn from(server: IceServer) -> Self {
+        Self { urls: server.urls, username: server.username, credential: server.credential }
+    }
+}
+
+/// Short-lived TURN credentials together with their expiry
+#[derive(Debug, Clone, PartialEq)]
+pub struct TurnCredentials {
+    pub ice_servers: Vec<IceServer>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl TurnCredentials {
+    /// Whether the credentials have expired or will within `margin`
+    pub fn expires_within(&self, margin: chrono::Duration) -> bool {
+        self.expires_at.checked_sub_signed(margin).is_none_or(|refresh_at| refresh_at <= Utc::now())
+    }
+}
+
 /// Cloudflare API error response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CloudflareError {
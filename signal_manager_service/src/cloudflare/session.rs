@@ -181,6 +181,16 @@ impl CloudflareSession {
         Ok(is_valid)
     }
 
+    /// TURN servers from the configured Cloudflare TURN key; none when no key is configured
+    pub async fn ice_servers(&self) -> Result<Vec<IceServer>, Box<dyn std::error::Error + Send + Sync>> {
+        if !self.config.cloudflare.turn.is_enabled() {
+            return Ok(Vec::new());
+        }
+
+        let credentials = self.client.generate_turn_credentials().await?;
+        Ok(credentials.ice_servers)
+    }
+
     /// Generate a new room UUID
     pub fn generate_room_id() -> String {
         Uuid::new_v4().to_string()
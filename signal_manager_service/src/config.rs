@@ -1,7 +1,11 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
 use std::sync::OnceLock;
 use std::collections::HashMap;
+use crate::backoff::BackoffConfig;
+use crate::message::{MessageType, PayloadType};
+use crate::database::DEFAULT_MAX_PARTICIPANTS;
 
 static CONFIG: OnceLock<Config> = OnceLock::new();
 
@@ -16,6 +20,12 @@ pub struct Config {
     pub gcp: GcpConfig,
     pub firestore: FirestoreConfig,
     pub cloudflare: CloudflareConfig,
+    #[serde(default)]
+    pub events: EventsConfig,
+    #[serde(default)]
+    pub webrtc: WebRTCConfig,
+    #[serde(default)]
+    pub database: DatabaseConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,12 +34,239 @@ pub struct ServerConfig {
     pub port: u16,
     pub max_connections: usize,
     pub heartbeat_interval: u64,
+    /// Close connections that send no heartbeat for this many `heartbeat_interval`s, ending their
+    /// session; 0 disables the watchdog. WebSocket Ping and Pong frames count as heartbeats.
+    #[serde(default = "default_heartbeat_timeout_multiplier")]
+    pub heartbeat_timeout_multiplier: u32,
+    /// Send every connection a WebSocket Ping this often, in seconds, so clients that only
+    /// answer transport-level pings stay alive; 0 sends none
+    #[serde(default)]
+    pub ping_interval_secs: u64,
     pub tls_enabled: bool,
     pub tls_cert_path: String,
     pub tls_key_path: String,
+    /// TLS implementation used while `tls_enabled` is set
+    #[serde(default)]
+    pub tls_backend: TlsBackend,
     pub read_buffer_size: usize,
     pub write_buffer_size: usize,
     pub max_message_size: usize,
+    /// Maximum size of a single WebSocket frame accepted by the protocol layer.
+    /// Clamped to `max_message_size`, plus a fixed slack, when building the tungstenite config.
+    #[serde(default = "default_max_frame_size")]
+    pub max_frame_size: usize,
+    /// Number of frames buffered per client before the overflow policy applies
+    #[serde(default = "default_outbound_queue_depth")]
+    pub outbound_queue_depth: usize,
+    /// What to do when a client's outbound queue is full
+    #[serde(default)]
+    pub outbound_overflow_policy: OverflowPolicy,
+    /// Deliver heartbeats, heartbeat acks, errors and disconnects ahead of queued bulk frames
+    #[serde(default = "default_prioritize_control_frames")]
+    pub prioritize_control_frames: bool,
+    /// What to do when an already-connected socket sends another Connect
+    #[serde(default)]
+    pub duplicate_connect_policy: DuplicateConnectPolicy,
+    /// Whether a successful Connect is acked before or after the socket is registered as the
+    /// client's connection
+    #[serde(default)]
+    pub connect_ack_order: ConnectAckOrder,
+    /// What to do with new sockets while `max_connections` connections are open; 0 means no limit
+    #[serde(default)]
+    pub connection_limit_policy: ConnectionLimitPolicy,
+    /// Reject timestamped messages whose `created_at` differs from server time by more than
+    /// this many milliseconds; 0 disables the check
+    #[serde(default = "default_max_clock_skew_ms")]
+    pub max_clock_skew_ms: u64,
+    /// Close connections that have not completed the TLS handshake, or the WebSocket upgrade,
+    /// within this many seconds
+    #[serde(default = "default_tls_handshake_timeout_secs")]
+    pub tls_handshake_timeout_secs: u64,
+    /// Serve the `/readyz` health report over plain HTTP on this port; 0 disables it
+    #[serde(default)]
+    pub readyz_port: u16,
+    /// Ping each client after a successful Connect and only add it to the connections map
+    /// (and send its ConnectAck) once the pong arrives, so routed messages never go to a
+    /// socket that can receive but not send
+    #[serde(default)]
+    pub require_warmup_pong: bool,
+    /// How long a client has to answer the warm-up ping before its connection is closed
+    #[serde(default = "default_warmup_pong_timeout_ms")]
+    pub warmup_pong_timeout_ms: u64,
+    /// Accepted sockets allowed in the TLS/WebSocket handshake at the same time
+    #[serde(default = "default_max_concurrent_handshakes")]
+    pub max_concurrent_handshakes: usize,
+    /// Accepted sockets allowed to wait for a handshake slot, for at most
+    /// `tls_handshake_timeout_secs`; sockets beyond this are closed immediately
+    #[serde(default = "default_max_queued_handshakes")]
+    pub max_queued_handshakes: usize,
+    /// Outbound payloads longer than this many bytes are sent compressed to clients that send
+    /// compressed frames themselves, the same way: zstd-flagged by `PAYLOAD_TYPE_ZSTD_FLAG`, or
+    /// JSON as `JsonGzip`. 0 never compresses
+    #[serde(default = "default_compression_threshold_bytes", alias = "compress_threshold_bytes")]
+    pub compression_threshold_bytes: usize,
+    /// Verify the CRC32 trailer on received frames that carry one, and append one to the frames
+    /// sent to each client once it sends them. Frames without a trailer are still accepted, so
+    /// clients can adopt it one at a time.
+    #[serde(default)]
+    pub frame_checksums: bool,
+    /// Payload encodings accepted from and sent to clients; a frame in any other encoding
+    /// is rejected rather than decoded or sent. Must include `JSON`, which server replies use.
+    #[serde(default = "default_enabled_codecs")]
+    pub enabled_codecs: Vec<PayloadType>,
+    /// Longest client or room id, in bytes, accepted from Connect, Register and room
+    /// requests; 0 means no limit
+    #[serde(default = "default_max_id_length")]
+    pub max_id_length: usize,
+    /// On shutdown, seconds to wait for connections to close after they are sent a close frame
+    #[serde(default = "default_shutdown_grace_secs")]
+    pub shutdown_grace_secs: u64,
+    /// Log a warning tagged with the message type and uuid when handling one client message
+    /// takes longer than this many milliseconds; 0 never warns
+    #[serde(default = "default_slow_handler_threshold_ms")]
+    pub slow_handler_threshold_ms: u64,
+    /// Identifies this instance in log lines and published events when several run behind
+    /// a load balancer. Defaults to the hostname, or a random UUID if it cannot be read.
+    #[serde(default = "default_instance_id")]
+    pub instance_id: String,
+}
+
+fn default_heartbeat_timeout_multiplier() -> u32 {
+    2
+}
+
+fn default_max_frame_size() -> usize {
+    1048576
+}
+
+fn default_outbound_queue_depth() -> usize {
+    100
+}
+
+fn default_prioritize_control_frames() -> bool {
+    true
+}
+
+fn default_max_clock_skew_ms() -> u64 {
+    30000
+}
+
+fn default_tls_handshake_timeout_secs() -> u64 {
+    10
+}
+
+fn default_warmup_pong_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_max_concurrent_handshakes() -> usize {
+    64
+}
+
+fn default_instance_id() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|hostname| hostname.trim().to_string())
+        .filter(|hostname| !hostname.is_empty())
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+}
+
+fn default_enabled_codecs() -> Vec<PayloadType> {
+    PayloadType::SUPPORTED.to_vec()
+}
+
+fn default_max_id_length() -> usize {
+    128
+}
+
+fn default_shutdown_grace_secs() -> u64 {
+    10
+}
+
+fn default_slow_handler_threshold_ms() -> u64 {
+    1000
+}
+
+fn default_compression_threshold_bytes() -> usize {
+    4096
+}
+
+fn default_max_queued_handshakes() -> usize {
+    1024
+}
+
+/// TLS implementation terminating `wss://` connections
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TlsBackend {
+    /// The platform TLS library (OpenSSL on Linux); takes one certificate and a PKCS#8 key
+    #[default]
+    NativeTls,
+    /// Pure-Rust rustls; takes a PEM certificate chain and a PKCS#8, PKCS#1 or SEC1 key
+    Rustls,
+}
+
+/// Behaviour when a client's outbound queue is saturated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Discard the frame being enqueued
+    #[default]
+    DropNewest,
+    /// Evict the oldest queued frame to make room
+    DropOldest,
+    /// Close the client's connection
+    Disconnect,
+}
+
+/// Behaviour when a socket that already completed Connect sends another one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateConnectPolicy {
+    /// Answer with an error and keep the existing session
+    #[default]
+    Reject,
+    /// Authenticate again and drop the previous session and connection entry
+    Replace,
+}
+
+/// When the ConnectAck for a successful Connect is queued relative to registering the socket
+/// as the client's connection. The session is recorded before either.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectAckOrder {
+    /// Register first, so anything the client or its peers send once it has the ack finds it
+    /// connected
+    #[default]
+    AfterPersist,
+    /// Ack first; a relay sent to the client in the moment before it is registered finds it offline
+    BeforePersist,
+}
+
+/// Behaviour when a socket arrives while `max_connections` connections are already open
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionLimitPolicy {
+    /// Complete the WebSocket upgrade and close with 1013 (Try Again Later)
+    #[default]
+    Reject,
+    /// Hold the socket before the upgrade until a connection closes, for at most
+    /// `tls_handshake_timeout_secs`, then reject it
+    Queue,
+}
+
+/// Behaviour when a Connect would give a client more than `max_sessions_per_client` sessions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionLimitPolicy {
+    /// Answer the new Connect with error code 17 and keep the existing sessions
+    Reject,
+    /// End the oldest session, sending its socket a `Disconnect` and a close frame, and accept
+    /// the new one. The default, so a client reconnecting before its old socket is noticed
+    /// as dead gets in.
+    #[default]
+    EvictOldest,
 }
 
 
@@ -40,6 +277,53 @@ pub struct AuthConfig {
     pub token_expiry: u64,
     pub auth_method: String,
     pub api_keys: Vec<String>,
+    /// Extra tokens clients may still authenticate with while their `api_keys` entry is rotated
+    #[serde(default)]
+    pub secondary_api_keys: Vec<SecondaryApiKey>,
+    /// Store only a salted SHA-256 hash of client auth tokens in repositories
+    #[serde(default)]
+    pub hash_tokens_at_rest: bool,
+    /// Algorithm the "jwt" auth method verifies tokens with. HS256 uses `token_secret` as the key.
+    #[serde(default)]
+    pub jwt_algorithm: JwtAlgorithm,
+    /// PEM file with the RSA public key RS256 tokens are verified against
+    #[serde(default)]
+    pub jwt_public_key_path: Option<String>,
+}
+
+/// A grace-period token accepted alongside a client's primary `api_keys` token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecondaryApiKey {
+    pub client_id: String,
+    pub token: String,
+    /// RFC 3339 instant after which the token is rejected; accepted indefinitely when unset
+    #[serde(default)]
+    pub valid_until: Option<DateTime<Utc>>,
+}
+
+/// A token a client may authenticate with, from `api_keys` or `secondary_api_keys`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientToken {
+    pub token: String,
+    pub valid_until: Option<DateTime<Utc>>,
+}
+
+impl ClientToken {
+    /// Whether the token is still accepted at `now`
+    pub fn is_valid_at(&self, now: DateTime<Utc>) -> bool {
+        self.valid_until.is_none_or(|valid_until| now < valid_until)
+    }
+}
+
+/// Signature algorithm of the JWTs accepted by the "jwt" auth method
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum JwtAlgorithm {
+    /// HMAC-SHA256 keyed with `auth.token_secret`
+    #[default]
+    Hs256,
+    /// RSA PKCS#1 v1.5 with SHA-256, verified with the key at `auth.jwt_public_key_path`
+    Rs256,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -58,23 +342,86 @@ pub struct MetricsConfig {
     pub enabled: bool,
     pub port: u16,
     pub host: String,
+    /// Seconds between connection count log lines while metrics are enabled; 0 disables them
     pub connection_stats_interval: u64,
+    /// Seconds between per-type message count log lines while metrics are enabled; 0 disables them
     pub message_stats_interval: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionConfig {
+    /// Sessions with no heartbeat for this many seconds are ended by a sweep run every
+    /// `cleanup_interval` seconds, and their client is sent a `Disconnect`; 0 disables the sweep
     pub session_timeout: u64,
     pub cleanup_interval: u64,
+    /// Sessions one client id may hold on open sockets at once; 0 means no limit. The server
+    /// routes each client id to a single socket, so any value above 1 behaves as no limit.
     pub max_sessions_per_client: usize,
+    /// What a Connect over `max_sessions_per_client` does
+    #[serde(default)]
+    pub session_limit_policy: SessionLimitPolicy,
+    /// Terminated rooms and their former clients' records older than this are deleted by a
+    /// sweep run every `cleanup_interval` seconds; 0 keeps them forever
+    #[serde(default)]
+    pub terminated_room_retention_secs: u64,
+    /// Keep a client's session, including its room roles, this long after its socket drops so a
+    /// reconnect can take it over. While set, a Connect from a second socket while the first is
+    /// still open is rejected. 0 tears sessions down immediately.
+    #[serde(default)]
+    pub reconnect_grace_secs: u64,
+    /// How long a disconnected client's last-seen time is kept for `ClientStatusQuery`; older
+    /// entries are pruned every `cleanup_interval` seconds. 0 keeps them forever.
+    #[serde(default = "default_last_seen_retention_secs")]
+    pub last_seen_retention_secs: u64,
+}
+
+fn default_last_seen_retention_secs() -> u64 {
+    86400
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SecurityConfig {
+    /// Enforce `max_messages_per_minute` and `max_connections_per_ip`. The connection limit and
+    /// the pre-Connect message limit are keyed on the peer address, which behind a proxy or load
+    /// balancer is the proxy's.
     pub rate_limit_enabled: bool,
+    /// Messages each client may send per minute, counted per peer address until it has
+    /// connected; 0 means no limit
     pub max_messages_per_minute: usize,
+    /// Connections allowed open from one address at a time; 0 means no limit
     pub max_connections_per_ip: usize,
+    /// Browser origins, e.g. `https://app.example.com`, allowed to open a WebSocket; `*` allows
+    /// any. Upgrades from other origins get a 403. Clients sending no `Origin` are always allowed.
     pub allowed_origins: Vec<String>,
+    /// Reject signal messages whose `signal_data` is not valid standard base64
+    #[serde(default)]
+    pub validate_signal_base64: bool,
+    /// Message types each room role ("sender", "receiver", "observer") may send. Roles
+    /// without an entry, and clients not in a room, may send anything.
+    #[serde(default)]
+    pub role_message_allowlist: HashMap<String, Vec<MessageType>>,
+    /// Reject JSON payloads that deserialize but fail `Payload::validate` (empty ids,
+    /// unknown roles) when the frame is parsed, instead of leaving it to each handler
+    #[serde(default)]
+    pub strict_payload_validation: bool,
+    /// Only let a socket send the message types listed in `required_capabilities` once it has
+    /// registered with the capability they require
+    #[serde(default)]
+    pub enforce_capabilities: bool,
+    /// Capability a client must declare in Register to send each message type when
+    /// `enforce_capabilities` is on; message types without an entry need none
+    #[serde(default = "default_required_capabilities")]
+    pub required_capabilities: HashMap<MessageType, String>,
+    /// Client IDs allowed to send operator requests such as `WebRTCRoomList`
+    #[serde(default)]
+    pub admin_clients: Vec<String>,
+}
+
+fn default_required_capabilities() -> HashMap<MessageType, String> {
+    HashMap::from([
+        (MessageType::WebRTCRoomCreate, "webrtc".to_string()),
+        (MessageType::WebRTCRoomJoin, "webrtc".to_string()),
+    ])
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -97,6 +444,128 @@ pub struct FirestoreConfig {
     pub region: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EventsConfig {
+    /// Event sink backend ("memory" or "gcp_pubsub")
+    pub backend: String,
+    /// Number of recent events retained by the in-memory backend
+    pub memory_capacity: usize,
+    /// Pub/Sub REST endpoint; override to target the Pub/Sub emulator
+    pub pubsub_endpoint: String,
+    /// Topic used for event types without an explicit mapping
+    pub default_topic: String,
+    /// Per event type topic overrides, e.g. "room_created" -> "room-events"
+    pub topics: HashMap<String, String>,
+    /// Refresh Pub/Sub access tokens this many seconds before they expire
+    pub token_refresh_margin_secs: u64,
+    /// Backoff for publishes that fail with a transport error, 429 or 5xx
+    pub publish_retry: BackoffConfig,
+    /// Pub/Sub events are buffered and published together once this many accumulate (at most 1000)
+    pub batch_max_messages: usize,
+    /// Buffered Pub/Sub events are also published every this many milliseconds; 0 flushes only by count and on shutdown
+    pub batch_flush_interval_ms: u64,
+}
+
+impl Default for EventsConfig {
+    fn default() -> Self {
+        Self {
+            backend: "memory".to_string(),
+            memory_capacity: 1000,
+            pubsub_endpoint: "https://pubsub.googleapis.com/v1".to_string(),
+            default_topic: "signal-manager-events".to_string(),
+            topics: HashMap::new(),
+            token_refresh_margin_secs: 300,
+            publish_retry: BackoffConfig::default(),
+            batch_max_messages: 100,
+            batch_flush_interval_ms: 200,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebRTCConfig {
+    /// Total ICE candidates one client session may have relayed before further ones are dropped,
+    /// whatever room they name; 0 disables the cap
+    pub max_ice_candidates_per_room: u64,
+    /// Rooms older than this are terminated by a background sweep run every
+    /// `session.cleanup_interval` seconds, regardless of activity; 0 means unlimited
+    pub max_room_lifetime_secs: u64,
+    /// Rooms whose sender has waited this long without a receiver joining are terminated
+    /// by the same sweep and the sender is told; 0 disables the timeout
+    pub answer_timeout_secs: u64,
+    /// Largest `AppRelay` data accepted, in bytes; 0 means limited only by the frame size
+    pub app_relay_max_bytes: usize,
+    /// `AppRelay` messages a connection may send per second before further ones are rejected; 0 means unlimited
+    pub app_relay_max_per_sec: u32,
+    /// Keep the offer/answer SDP and ICE candidates exchanged in each room, in memory while
+    /// the room lasts, for diagnosing failed connections. Off by default since SDP carries network addresses.
+    pub persist_sdp: bool,
+    /// A client joining a room it is already recorded in, with a valid token, re-attaches to its
+    /// membership if that was last active within this many seconds; older memberships are replaced
+    /// by a fresh join. 0 disables rejoining, so such joins are rejected as duplicates.
+    pub rejoin_grace_secs: u64,
+    /// Largest `max_participants` a room creator may ask for
+    pub max_room_participants: u32,
+    /// Media backend room create, join and leave open and close sessions on
+    pub provider: SignalingProviderKind,
+}
+
+/// Media backend behind the WebRTC room handlers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignalingProviderKind {
+    /// Cloudflare Realtime sessions, configured under `[cloudflare]`
+    #[default]
+    Cloudflare,
+}
+
+impl Default for WebRTCConfig {
+    fn default() -> Self {
+        Self {
+            max_ice_candidates_per_room: 500,
+            max_room_lifetime_secs: 0,
+            answer_timeout_secs: 0,
+            app_relay_max_bytes: 4096,
+            app_relay_max_per_sec: 20,
+            persist_sdp: false,
+            rejoin_grace_secs: 60,
+            max_room_participants: 16,
+            provider: SignalingProviderKind::Cloudflare,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DatabaseConfig {
+    /// Clients the in-memory client store holds before `eviction_policy` applies; 0 means unlimited
+    pub max_clients: usize,
+    /// What to do when a registration arrives and the client store is full
+    pub eviction_policy: EvictionPolicy,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            max_clients: 100_000,
+            eviction_policy: EvictionPolicy::Reject,
+        }
+    }
+}
+
+/// Behaviour when the in-memory client store is at `max_clients`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EvictionPolicy {
+    /// Refuse the new registration with a capacity error
+    #[default]
+    Reject,
+    /// Evict the least recently seen client to make room
+    Lru,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CloudflareConfig {
     /// Cloudflare Realtime App ID
@@ -107,20 +576,276 @@ pub struct CloudflareConfig {
     pub base_url: String,
     /// Cloudflare STUN server URL
     pub stun_url: String,
+    /// Backoff for Cloudflare requests that fail transiently; session and track creation
+    /// only retry failures Cloudflare cannot have acted on
+    #[serde(default)]
+    pub retry: BackoffConfig,
+    /// Cloudflare TURN key used to mint short-lived relay credentials for room acks
+    #[serde(default)]
+    pub turn: TurnConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TurnConfig {
+    /// Cloudflare TURN key ID; empty disables TURN credentials
+    pub key_id: String,
+    /// API token of the TURN key
+    pub api_token: String,
+    /// Lifetime requested for each set of TURN credentials
+    pub credential_ttl_secs: u64,
+    /// Mint new TURN credentials this many seconds before the cached ones expire
+    pub refresh_margin_secs: u64,
+}
+
+impl TurnConfig {
+    /// Whether a TURN key is configured
+    pub fn is_enabled(&self) -> bool {
+        !self.key_id.is_empty()
+    }
+}
+
+impl Default for TurnConfig {
+    fn default() -> Self {
+        Self {
+            key_id: String::new(),
+            api_token: String::new(),
+            credential_ttl_secs: 86_400,
+            refresh_margin_secs: 3_600,
+        }
+    }
+}
+
+/// Longest period, in seconds, a room age or retention setting may span: a century, well inside
+/// what a timestamp can be moved back by
+pub const MAX_PERIOD_SECS: u64 = 100 * 365 * 24 * 60 * 60;
+
+/// `secs` as a chrono duration, or `None` past `MAX_PERIOD_SECS`
+pub fn checked_period(secs: u64) -> Option<chrono::Duration> {
+    if secs > MAX_PERIOD_SECS {
+        return None;
+    }
+    i64::try_from(secs).ok().and_then(chrono::Duration::try_seconds)
+}
+
+/// The `auth.token_secret` the defaults and sample configs ship with; HS256 JWT authentication
+/// refuses to start with it
+pub const PLACEHOLDER_TOKEN_SECRET: &str = "your-secret-key-change-in-production";
+
+/// Prefix of environment variables overriding configuration values
+pub const ENV_PREFIX: &str = "SMS";
+/// Separates nested keys in environment variable names, e.g. `SMS_SERVER__PORT` for `server.port`
+pub const ENV_SEPARATOR: &str = "__";
+
+/// Settings a running server picks up from a reloaded configuration; any other change
+/// needs a restart
+pub const RELOADABLE_SETTINGS: &[&str] = &[
+    "security.rate_limit_enabled",
+    "security.max_messages_per_minute",
+    "security.max_connections_per_ip",
+    "security.allowed_origins",
+    "logging.level",
+    "session.session_timeout",
+    "session.cleanup_interval",
+    "session.reconnect_grace_secs",
+];
+
+/// What reloading the configuration changed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigReload {
+    /// Reloadable settings that changed, as `key: old -> new`
+    pub applied: Vec<String>,
+    /// Keys of changed settings left at their running values until a restart
+    pub ignored: Vec<String>,
+}
+
+impl ConfigReload {
+    pub fn is_empty(&self) -> bool {
+        self.applied.is_empty() && self.ignored.is_empty()
+    }
 }
 
 impl Config {
+    /// Load the configuration in layers, each overriding the one before: `Config::default()`,
+    /// then `app-config` and `config` files in the working directory, then the file at `path`,
+    /// then `SMS_`-prefixed environment variables
     pub fn load(path: &str) -> Result<Self, config::ConfigError> {
+        Self::load_with_environment(path, Self::environment())
+    }
+
+    /// Like `load`, with overrides read from `environment` instead of the process environment
+    pub fn load_with_environment(path: &str, environment: config::Environment) -> Result<Self, config::ConfigError> {
         let settings = config::Config::builder()
-            .add_source(config::File::with_name(path).required(false))
+            .add_source(config::Config::try_from(&Config::default())?)
             .add_source(config::File::with_name("app-config").required(false))
             .add_source(config::File::with_name("config").required(false))
-            .add_source(config::Environment::with_prefix("SIGNAL_MANAGER"))
+            .add_source(config::File::with_name(path).required(false))
+            .add_source(environment)
             .build()?;
 
         settings.try_deserialize()
     }
 
+    /// Check for settings that cannot work together, so a bad deployment fails at startup rather
+    /// than at bind or connect time. The error message starts with the offending field.
+    pub fn validate(&self) -> Result<(), config::ConfigError> {
+        let server = &self.server;
+        if server.port == 0 {
+            return Err(invalid("server.port", "must not be 0"));
+        }
+        if server.tls_enabled && server.tls_cert_path.trim().is_empty() {
+            return Err(invalid("server.tls_cert_path", "must be set when server.tls_enabled is true"));
+        }
+        if server.tls_enabled && server.tls_key_path.trim().is_empty() {
+            return Err(invalid("server.tls_key_path", "must be set when server.tls_enabled is true"));
+        }
+        if !server.enabled_codecs.contains(&PayloadType::Json) {
+            return Err(invalid("server.enabled_codecs", "must include JSON, the encoding of server replies"));
+        }
+        if self.metrics.enabled && self.metrics.port == server.port {
+            return Err(invalid("metrics.port", &format!("must differ from server.port ({})", server.port)));
+        }
+        if server.readyz_port == server.port {
+            return Err(invalid("server.readyz_port", &format!("must differ from server.port ({})", server.port)));
+        }
+        if server.readyz_port != 0 && self.metrics.enabled && server.readyz_port == self.metrics.port {
+            return Err(invalid("server.readyz_port", &format!("must differ from metrics.port ({})", self.metrics.port)));
+        }
+
+        let timeouts = [
+            ("server.heartbeat_interval", server.heartbeat_interval),
+            ("server.tls_handshake_timeout_secs", server.tls_handshake_timeout_secs),
+            ("auth.token_expiry", self.auth.token_expiry),
+            ("session.cleanup_interval", self.session.cleanup_interval),
+        ];
+        if let Some((field, _)) = timeouts.iter().find(|(_, value)| *value == 0) {
+            return Err(invalid(field, "must be greater than 0"));
+        }
+        let periods = [
+            ("session.terminated_room_retention_secs", self.session.terminated_room_retention_secs),
+            ("session.last_seen_retention_secs", self.session.last_seen_retention_secs),
+            ("webrtc.max_room_lifetime_secs", self.webrtc.max_room_lifetime_secs),
+            ("webrtc.answer_timeout_secs", self.webrtc.answer_timeout_secs),
+        ];
+        if let Some((field, _)) = periods.iter().find(|(_, value)| checked_period(*value).is_none()) {
+            return Err(invalid(field, &format!("must be at most {MAX_PERIOD_SECS} seconds")));
+        }
+        if self.webrtc.max_room_participants < DEFAULT_MAX_PARTICIPANTS {
+            return Err(invalid("webrtc.max_room_participants", &format!("must be at least {DEFAULT_MAX_PARTICIPANTS}, the participants of a room with no limit of its own")));
+        }
+        if server.require_warmup_pong && server.warmup_pong_timeout_ms == 0 {
+            return Err(invalid("server.warmup_pong_timeout_ms", "must be greater than 0 when server.require_warmup_pong is true"));
+        }
+
+        let auth = &self.auth;
+        match auth.auth_method.as_str() {
+            "jwt" => match auth.jwt_algorithm {
+                JwtAlgorithm::Hs256 if auth.token_secret.is_empty() => {
+                    return Err(invalid("auth.token_secret", "must be set for HS256 JWT authentication"));
+                }
+                JwtAlgorithm::Hs256 if auth.token_secret == PLACEHOLDER_TOKEN_SECRET => {
+                    return Err(invalid("auth.token_secret", "must be changed from the placeholder for HS256 JWT authentication"));
+                }
+                JwtAlgorithm::Rs256 if auth.jwt_public_key_path.as_deref().is_none_or(|path| path.trim().is_empty()) => {
+                    return Err(invalid("auth.jwt_public_key_path", "must be set for RS256 JWT authentication"));
+                }
+                _ => {}
+            },
+            _ => {
+                if auth.api_keys.is_empty() && auth.secondary_api_keys.is_empty() {
+                    return Err(invalid("auth.api_keys", "must list at least one client_id:token pair"));
+                }
+                for (index, key_pair) in auth.api_keys.iter().enumerate() {
+                    if !key_pair.split_once(':').is_some_and(|(client_id, token)| !client_id.is_empty() && !token.is_empty()) {
+                        return Err(invalid(&format!("auth.api_keys[{index}]"), "must be a non-empty client_id:token pair"));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The effective settings an operator usually checks before deploying, one `key = value`
+    /// per line. Secrets and tokens are left out.
+    pub fn summary(&self) -> String {
+        let server = &self.server;
+        let security = &self.security;
+        let tls = if server.tls_enabled {
+            format!("{:?} (cert {}, key {})", server.tls_backend, server.tls_cert_path, server.tls_key_path)
+        } else {
+            "disabled".to_string()
+        };
+        let metrics = if self.metrics.enabled { self.metrics_addr().to_string() } else { "disabled".to_string() };
+        let readyz = match server.readyz_port {
+            0 => "disabled".to_string(),
+            port => format!("{}:{}", server.host, port),
+        };
+        let rate_limits = if security.rate_limit_enabled {
+            format!("{} messages per minute, {} connections per address", security.max_messages_per_minute, security.max_connections_per_ip)
+        } else {
+            "disabled".to_string()
+        };
+        let lines = [
+            ("server.address", self.socket_addr().to_string()),
+            ("server.instance_id", server.instance_id.clone()),
+            ("server.tls", tls),
+            ("server.max_connections", server.max_connections.to_string()),
+            ("server.readyz", readyz),
+            ("auth.auth_method", format!(
+                "{} ({} api keys, {} secondary)",
+                self.auth.auth_method,
+                self.auth.api_keys.len(),
+                self.auth.secondary_api_keys.len()
+            )),
+            ("metrics", metrics),
+            ("logging.level", self.logging.level.clone()),
+            ("session.session_timeout", format!("{}s", self.session.session_timeout)),
+            ("security.rate_limits", rate_limits),
+            ("security.allowed_origins", security.allowed_origins.join(", ")),
+            ("events.backend", self.events.backend.clone()),
+            ("webrtc.provider", format!("{:?}", self.webrtc.provider)),
+            ("gcp.project_id", self.gcp.project_id.clone()),
+        ];
+        lines.iter().map(|(key, value)| format!("{key} = {value}\n")).collect()
+    }
+
+    /// Environment variables named `SMS_<SECTION>__<FIELD>`, e.g. `SMS_METRICS__ENABLED=true`
+    pub fn environment() -> config::Environment {
+        config::Environment::with_prefix(ENV_PREFIX)
+            .prefix_separator("_")
+            .separator(ENV_SEPARATOR)
+            .try_parsing(true)
+    }
+
+    /// A copy of `self` with the `RELOADABLE_SETTINGS` of `reloaded` applied, and which
+    /// settings differ between the two
+    pub fn reload_from(&self, reloaded: &Config) -> (Config, ConfigReload) {
+        let mut changed = Vec::new();
+        if let (Ok(old), Ok(new)) = (serde_json::to_value(self), serde_json::to_value(reloaded)) {
+            changed_settings("", &old, &new, &mut changed);
+        }
+
+        let mut report = ConfigReload::default();
+        for (key, old, new) in changed {
+            if RELOADABLE_SETTINGS.contains(&key.as_str()) {
+                report.applied.push(format!("{key}: {old} -> {new}"));
+            } else {
+                report.ignored.push(key);
+            }
+        }
+
+        let mut applied = self.clone();
+        applied.security.rate_limit_enabled = reloaded.security.rate_limit_enabled;
+        applied.security.max_messages_per_minute = reloaded.security.max_messages_per_minute;
+        applied.security.max_connections_per_ip = reloaded.security.max_connections_per_ip;
+        applied.security.allowed_origins = reloaded.security.allowed_origins.clone();
+        applied.logging.level = reloaded.logging.level.clone();
+        applied.session.session_timeout = reloaded.session.session_timeout;
+        applied.session.cleanup_interval = reloaded.session.cleanup_interval;
+        applied.session.reconnect_grace_secs = reloaded.session.reconnect_grace_secs;
+        (applied, report)
+    }
+
     pub fn socket_addr(&self) -> SocketAddr {
         format!("{}:{}", self.server.host, self.server.port)
             .parse()
@@ -143,6 +868,21 @@ impl Config {
         keys
     }
 
+    /// Every token each client may authenticate with: its `api_keys` entries, then its
+    /// `secondary_api_keys`
+    pub fn parse_client_tokens(&self) -> HashMap<String, Vec<ClientToken>> {
+        let mut tokens: HashMap<String, Vec<ClientToken>> = HashMap::new();
+        for key_pair in &self.auth.api_keys {
+            if let Some((client_id, token)) = key_pair.split_once(':') {
+                tokens.entry(client_id.to_string()).or_default().push(ClientToken { token: token.to_string(), valid_until: None });
+            }
+        }
+        for key in &self.auth.secondary_api_keys {
+            tokens.entry(key.client_id.clone()).or_default().push(ClientToken { token: key.token.clone(), valid_until: key.valid_until });
+        }
+        tokens
+    }
+
     /// Set up GCP authentication using the configured credentials path
     pub fn setup_gcp_auth(&self) -> Result<(), std::env::VarError> {
         std::env::set_var("GOOGLE_APPLICATION_CREDENTIALS", &self.gcp.credentials_path);
@@ -158,22 +898,50 @@ impl Default for Config {
                 port: 8080,
                 max_connections: 1000,
                 heartbeat_interval: 30,
+                heartbeat_timeout_multiplier: default_heartbeat_timeout_multiplier(),
+                ping_interval_secs: 0,
                 tls_enabled: false,
                 tls_cert_path: "".to_string(),
                 tls_key_path: "".to_string(),
+                tls_backend: TlsBackend::NativeTls,
                 read_buffer_size: 8192,
                 write_buffer_size: 8192,
                 max_message_size: 1048576,
+                max_frame_size: 1048576,
+                outbound_queue_depth: 100,
+                outbound_overflow_policy: OverflowPolicy::DropNewest,
+                prioritize_control_frames: true,
+                duplicate_connect_policy: DuplicateConnectPolicy::Reject,
+                connect_ack_order: ConnectAckOrder::AfterPersist,
+                connection_limit_policy: ConnectionLimitPolicy::Reject,
+                max_clock_skew_ms: 30000,
+                tls_handshake_timeout_secs: 10,
+                readyz_port: 0,
+                require_warmup_pong: false,
+                warmup_pong_timeout_ms: 5000,
+                max_concurrent_handshakes: 64,
+                max_queued_handshakes: 1024,
+                compression_threshold_bytes: default_compression_threshold_bytes(),
+                frame_checksums: false,
+                enabled_codecs: default_enabled_codecs(),
+                max_id_length: default_max_id_length(),
+                shutdown_grace_secs: default_shutdown_grace_secs(),
+                slow_handler_threshold_ms: default_slow_handler_threshold_ms(),
+                instance_id: default_instance_id(),
             },
 
             auth: AuthConfig {
-                token_secret: "your-secret-key-change-in-production".to_string(),
+                token_secret: PLACEHOLDER_TOKEN_SECRET.to_string(),
                 token_expiry: 3600,
                 auth_method: "token".to_string(),
                 api_keys: vec![
                     "test_client_1:test_token_1".to_string(),
                     "test_client_2:test_token_2".to_string(),
                 ],
+                secondary_api_keys: Vec::new(),
+                hash_tokens_at_rest: false,
+                jwt_algorithm: JwtAlgorithm::Hs256,
+                jwt_public_key_path: None,
             },
             logging: LoggingConfig {
                 level: "info".to_string(),
@@ -195,12 +963,22 @@ impl Default for Config {
                 session_timeout: 3600,
                 cleanup_interval: 300,
                 max_sessions_per_client: 1,
+                session_limit_policy: SessionLimitPolicy::EvictOldest,
+                terminated_room_retention_secs: 0,
+                reconnect_grace_secs: 0,
+                last_seen_retention_secs: default_last_seen_retention_secs(),
             },
             security: SecurityConfig {
                 rate_limit_enabled: true,
                 max_messages_per_minute: 1000,
                 max_connections_per_ip: 10,
                 allowed_origins: vec!["*".to_string()],
+                validate_signal_base64: false,
+                role_message_allowlist: HashMap::new(),
+                strict_payload_validation: false,
+                enforce_capabilities: false,
+                required_capabilities: default_required_capabilities(),
+                admin_clients: Vec::new(),
             },
             gcp: GcpConfig {
                 credentials_path: "/home/keith/Downloads/keahi-ambient-agent-service-d9c5c0e3f93a.json".to_string(),
@@ -217,11 +995,40 @@ impl Default for Config {
                 app_secret: "your-cloudflare-app-secret".to_string(),
                 base_url: "https://rtc.live.cloudflare.com/v1".to_string(),
                 stun_url: "stun:stun.cloudflare.com:3478".to_string(),
+                retry: BackoffConfig::default(),
+                turn: TurnConfig::default(),
             },
+            events: EventsConfig::default(),
+            webrtc: WebRTCConfig::default(),
+            database: DatabaseConfig::default(),
         }
     }
 }
 
+/// Error for a setting that failed `Config::validate`
+fn invalid(field: &str, reason: &str) -> config::ConfigError {
+    config::ConfigError::Message(format!("{field} {reason}"))
+}
+
+/// Collect `(key, old, new)` for every leaf value that differs between `old` and `new`,
+/// keyed by its dotted path below `prefix`
+fn changed_settings(prefix: &str, old: &serde_json::Value, new: &serde_json::Value, changed: &mut Vec<(String, serde_json::Value, serde_json::Value)>) {
+    match (old, new) {
+        (serde_json::Value::Object(old), serde_json::Value::Object(new)) => {
+            let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let path = if prefix.is_empty() { key.clone() } else { format!("{prefix}.{key}") };
+                let null = serde_json::Value::Null;
+                changed_settings(&path, old.get(key).unwrap_or(&null), new.get(key).unwrap_or(&null), changed);
+            }
+        }
+        (old, new) if old != new => changed.push((prefix.to_string(), old.clone(), new.clone())),
+        _ => {}
+    }
+}
+
 // Global configuration accessor
 pub fn get_config() -> &'static Config {
     CONFIG.get_or_init(|| {
@@ -231,14 +1038,43 @@ pub fn get_config() -> &'static Config {
     })
 }
 
-pub fn init_config(path: Option<&str>) -> Result<(), config::ConfigError> {
+/// Load and validate the configuration the server starts with: `path` if given, otherwise
+/// `app-config.toml`, then `config.toml`, then the defaults
+pub fn load_config(path: Option<&str>) -> Result<Config, config::ConfigError> {
     let config = match path {
         Some(p) => Config::load(p),
         None => Config::load("app-config.toml")
             .or_else(|_| Config::load("config.toml"))
             .or_else(|_| Ok(Config::default())),
     }?;
-    
+    config.validate()?;
+    Ok(config)
+}
+
+/// The `validate` command: load the configuration from `path` as `load_config` does and write
+/// a summary of the effective settings, or why it is invalid, to `out`. Binds no sockets and
+/// touches no GCP service. Returns the exit code: 0 when the configuration is valid, else 1.
+pub fn run_validate(path: Option<&str>, out: &mut dyn std::io::Write) -> i32 {
+    if let Some(path) = path.filter(|path| !std::path::Path::new(path).is_file()) {
+        let _ = writeln!(out, "Invalid configuration: {path} is not a file");
+        return 1;
+    }
+    match load_config(path) {
+        Ok(config) => {
+            let _ = writeln!(out, "Configuration is valid");
+            let _ = write!(out, "{}", config.summary());
+            0
+        }
+        Err(e) => {
+            let _ = writeln!(out, "Invalid configuration: {e}");
+            1
+        }
+    }
+}
+
+pub fn init_config(path: Option<&str>) -> Result<(), config::ConfigError> {
+    let config = load_config(path)?;
+
     CONFIG.set(config).map_err(|_| {
         config::ConfigError::NotFound("Configuration already initialized".to_string())
     })?;
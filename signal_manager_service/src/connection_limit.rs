@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Caps how many WebSocket connections may be open at once, in total and from one address.
+/// A limit of 0 disables that cap.
+pub struct ConnectionLimiter {
+    permits: Option<Arc<Semaphore>>,
+    max_per_address: AtomicUsize,
+    per_address: Arc<Mutex<HashMap<IpAddr, usize>>>,
+    live: Arc<AtomicUsize>,
+    queued: AtomicUsize,
+}
+
+/// Why a socket was not given a connection slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionRefused {
+    /// `max_connections` connections are already open
+    AtCapacity,
+    /// `max_connections_per_ip` connections are already open from the socket's address
+    AddressLimit,
+}
+
+/// A connection slot, held for the life of the connection; releases it when dropped
+pub struct ConnectionSlot {
+    _permit: Option<OwnedSemaphorePermit>,
+    address: AddressClaim,
+    live: Arc<AtomicUsize>,
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.live.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl ConnectionSlot {
+    /// Address of the peer holding the slot
+    pub fn address(&self) -> IpAddr {
+        self.address.address
+    }
+}
+
+/// One connection counted against its address; uncounted again when dropped
+struct AddressClaim {
+    address: IpAddr,
+    per_address: Option<Arc<Mutex<HashMap<IpAddr, usize>>>>,
+}
+
+impl Drop for AddressClaim {
+    fn drop(&mut self) {
+        let Some(per_address) = &self.per_address else { return };
+        let mut per_address = per_address.lock().unwrap();
+        if let Some(count) = per_address.get_mut(&self.address) {
+            *count -= 1;
+            if *count == 0 {
+                per_address.remove(&self.address);
+            }
+        }
+    }
+}
+
+impl ConnectionLimiter {
+    pub fn new(max_connections: usize, max_per_address: usize) -> Self {
+        Self {
+            permits: (max_connections > 0).then(|| Arc::new(Semaphore::new(max_connections))),
+            max_per_address: AtomicUsize::new(max_per_address),
+            per_address: Arc::new(Mutex::new(HashMap::new())),
+            live: Arc::new(AtomicUsize::new(0)),
+            queued: AtomicUsize::new(0),
+        }
+    }
+
+    /// Take a slot for a socket from `address` if one is free right now
+    pub fn try_acquire(&self, address: IpAddr) -> Result<ConnectionSlot, ConnectionRefused> {
+        let claim = self.claim_address(address)?;
+        let permit = match &self.permits {
+            Some(permits) => Some(permits.clone().try_acquire_owned().map_err(|_| ConnectionRefused::AtCapacity)?),
+            None => None,
+        };
+        Ok(self.slot(permit, claim))
+    }
+
+    /// Wait up to `wait` for a slot. A socket over the per-address cap is refused straight away.
+    pub async fn acquire(&self, address: IpAddr, wait: Duration) -> Result<ConnectionSlot, ConnectionRefused> {
+        let claim = self.claim_address(address)?;
+        let permit = match &self.permits {
+            Some(permits) => Some(match permits.clone().try_acquire_owned() {
+                Ok(permit) => permit,
+                Err(_) => {
+                    self.queued.fetch_add(1, Ordering::SeqCst);
+                    let permit = tokio::time::timeout(wait, permits.clone().acquire_owned()).await;
+                    self.queued.fetch_sub(1, Ordering::SeqCst);
+                    permit.ok().and_then(|permit| permit.ok()).ok_or(ConnectionRefused::AtCapacity)?
+                }
+            }),
+            None => None,
+        };
+        Ok(self.slot(permit, claim))
+    }
+
+    /// Connections currently holding a slot
+    pub fn live(&self) -> usize {
+        self.live.load(Ordering::SeqCst)
+    }
+
+    /// Connections currently holding a slot from `address`; always 0 without a per-address cap
+    pub fn live_from(&self, address: IpAddr) -> usize {
+        self.per_address.lock().unwrap().get(&address).copied().unwrap_or(0)
+    }
+
+    /// Change the per-address cap for sockets connecting from now on; open connections keep
+    /// their slots even when over the new cap
+    pub fn set_max_per_address(&self, max_per_address: usize) {
+        self.max_per_address.store(max_per_address, Ordering::SeqCst);
+    }
+
+    /// Sockets waiting for a slot
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::SeqCst)
+    }
+
+    fn claim_address(&self, address: IpAddr) -> Result<AddressClaim, ConnectionRefused> {
+        let max_per_address = self.max_per_address.load(Ordering::SeqCst);
+        if max_per_address == 0 {
+            return Ok(AddressClaim { address, per_address: None });
+        }
+        let mut per_address = self.per_address.lock().unwrap();
+        let count = per_address.entry(address).or_insert(0);
+        if *count >= max_per_address {
+            return Err(ConnectionRefused::AddressLimit);
+        }
+        *count += 1;
+        Ok(AddressClaim { address, per_address: Some(self.per_address.clone()) })
+    }
+
+    fn slot(&self, permit: Option<OwnedSemaphorePermit>, address: AddressClaim) -> ConnectionSlot {
+        self.live.fetch_add(1, Ordering::SeqCst);
+        ConnectionSlot { _permit: permit, address, live: self.live.clone() }
+    }
+}
@@ -16,6 +16,9 @@ pub trait ClientInRoomRepository: Send + Sync {
     /// Get all clients in a specific room
     async fn get_clients_in_room(&self, room_id: &str) -> Result<Vec<ClientInRoom>, DatabaseError>;
 
+    /// Get every room membership held by a client
+    async fn get_rooms_for_client(&self, client_id: &str) -> Result<Vec<ClientInRoom>, DatabaseError>;
+
     /// Get all clients in rooms
     async fn list_clients_in_rooms(&self) -> Result<Vec<ClientInRoom>, DatabaseError>;
 
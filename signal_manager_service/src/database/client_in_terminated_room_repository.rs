@@ -55,4 +55,7 @@ pub trait ClientInTerminatedRoomRepository: Send + Sync {
         start_date: DateTime<Utc>,
         end_date: DateTime<Utc>,
     ) -> Result<Vec<ClientInTerminatedRoom>, DatabaseError>;
+
+    /// Delete client records that left their terminated room before `cutoff`, returning how many were deleted
+    async fn delete_clients_in_terminated_rooms_before(&self, cutoff: DateTime<Utc>) -> Result<usize, DatabaseError>;
 } 
\ No newline at end of file
@@ -8,10 +8,16 @@ pub trait ClientRepository: Send + Sync {
     /// Create a new client registration
     async fn create_client(&self, payload: RegistrationPayload) -> DatabaseResult<RegisteredClient>;
     
+    /// Register a client, or replace its registration if it already exists, in one step.
+    /// A replacement keeps the original `id` and `registered_at`, and is only allowed with the
+    /// token the client was registered with; any other token fails with `DatabaseError::Authentication`.
+    async fn upsert_client(&self, payload: RegistrationPayload) -> DatabaseResult<RegisteredClient>;
+
     /// Get a client by ID
     async fn get_client(&self, client_id: &str) -> DatabaseResult<Option<RegisteredClient>>;
     
-    /// Get a client by authentication token
+    /// Get a client by authentication token.
+    /// The plaintext token is verified against stored values, which may be salted hashes.
     async fn get_client_by_token(&self, auth_token: &str) -> DatabaseResult<Option<RegisteredClient>>;
     
     /// Update a client's information
@@ -29,6 +35,12 @@ pub trait ClientRepository: Send + Sync {
     /// Check if a client exists
     async fn client_exists(&self, client_id: &str) -> DatabaseResult<bool>;
     
-    /// Validate client authentication
+    /// Validate client authentication against the stored (possibly hashed) token
     async fn validate_auth(&self, client_id: &str, auth_token: &str) -> DatabaseResult<bool>;
+
+    /// Ids of the clients evicted to make room since the last call, for the server to
+    /// disconnect. Stores that never evict have none.
+    async fn take_evicted(&self) -> Vec<String> {
+        Vec::new()
+    }
 } 
\ No newline at end of file
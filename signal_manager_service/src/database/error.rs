@@ -4,6 +4,10 @@ use thiserror::Error;
 pub enum DatabaseError {
     #[error("Connection error: {0}")]
     Connection(String),
+
+    /// The database could not be reached; the operation may succeed if retried
+    #[error("Database unavailable: {0}")]
+    Unavailable(String),
     
     #[error("Authentication error: {0}")]
     Authentication(String),
@@ -34,6 +38,16 @@ pub enum DatabaseError {
     
     #[error("Write error: {0}")]
     Write(String),
+
+    #[error("Capacity exceeded: {0}")]
+    Capacity(String),
+}
+
+impl DatabaseError {
+    /// Whether retrying the operation may succeed
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, DatabaseError::Unavailable(_))
+    }
 }
 
 pub type DatabaseResult<T> = Result<T, DatabaseError>; 
\ No newline at end of file
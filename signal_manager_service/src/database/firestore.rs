@@ -1,11 +1,13 @@
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use std::sync::Arc;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use tokio::sync::Mutex;
-use tracing::info;
+use tracing::{info, warn};
 use crate::database::RepositoryFactory;
+use crate::database::token_hash;
 
-use crate::config::Config;
+use crate::config::{Config, EvictionPolicy};
 use crate::database::{
     ClientRepository, DatabaseResult, RegisteredClient, RegistrationPayload,
     TerminatedRoomRepository, TerminatedRoom, TerminationPayload,
@@ -18,7 +20,52 @@ use crate::database::{
 /// Firestore implementation of the ClientRepository
 /// Note: Using in-memory storage for testing real database operations
 pub struct FirestoreClientRepository {
-    clients: Arc<Mutex<HashMap<String, RegisteredClient>>>,
+    clients: Arc<Mutex<ClientStore>>,
+    hash_tokens: bool,
+    max_clients: usize,
+    eviction_policy: EvictionPolicy,
+}
+
+/// Registered clients, indexed by when each was last seen so eviction need not scan them all
+#[derive(Default)]
+struct ClientStore {
+    clients: HashMap<String, RegisteredClient>,
+    by_recency: BTreeSet<(DateTime<Utc>, String)>,
+    /// Clients evicted since the last `take_evicted`
+    evicted: Vec<String>,
+}
+
+impl ClientStore {
+    fn recency(client: &RegisteredClient) -> (DateTime<Utc>, String) {
+        (client.last_seen.unwrap_or(client.registered_at), client.client_id.clone())
+    }
+
+    fn insert(&mut self, client: RegisteredClient) {
+        self.remove(&client.client_id);
+        self.by_recency.insert(Self::recency(&client));
+        self.clients.insert(client.client_id.clone(), client);
+    }
+
+    fn remove(&mut self, client_id: &str) -> Option<RegisteredClient> {
+        let client = self.clients.remove(client_id)?;
+        self.by_recency.remove(&Self::recency(&client));
+        Some(client)
+    }
+
+    fn update_last_seen(&mut self, client_id: &str) -> bool {
+        let Some(mut client) = self.remove(client_id) else { return false };
+        client.update_last_seen();
+        self.insert(client);
+        true
+    }
+
+    /// Remove the least recently seen client
+    fn evict_oldest(&mut self) -> Option<String> {
+        let (_, client_id) = self.by_recency.pop_first()?;
+        self.clients.remove(&client_id);
+        self.evicted.push(client_id.clone());
+        Some(client_id)
+    }
 }
 
 /// Firestore implementation of the TerminatedRoomRepository
@@ -62,11 +109,65 @@ pub struct FirestoreRepositoryFactory {
 
 impl FirestoreClientRepository {
     /// Create a new Firestore client repository
-    pub async fn new(_config: &Config) -> DatabaseResult<Self> {
+    pub async fn new(config: &Config) -> DatabaseResult<Self> {
         Ok(Self {
-            clients: Arc::new(Mutex::new(HashMap::new())),
+            clients: Arc::new(Mutex::new(ClientStore::default())),
+            hash_tokens: config.auth.hash_tokens_at_rest,
+            max_clients: config.database.max_clients,
+            eviction_policy: config.database.eviction_policy,
         })
     }
+
+    /// Number of clients currently held in the store
+    pub async fn client_count(&self) -> usize {
+        self.clients.lock().await.clients.len()
+    }
+
+    /// Free a slot for `client_id` when the store is at `max_clients`, by rejecting it or by
+    /// evicting the least recently seen clients
+    fn make_room(&self, store: &mut ClientStore, client_id: &str) -> DatabaseResult<()> {
+        if self.max_clients == 0 || store.clients.len() < self.max_clients {
+            return Ok(());
+        }
+        if self.eviction_policy == EvictionPolicy::Reject {
+            warn!("Client store full ({} clients), rejecting {}", store.clients.len(), client_id);
+            return Err(crate::database::DatabaseError::Capacity(
+                format!("Client store is full ({} clients)", self.max_clients)
+            ));
+        }
+        while store.clients.len() >= self.max_clients {
+            let Some(evicted) = store.evict_oldest() else { break };
+            info!("Evicted least recently seen client {} to make room for {}", evicted, client_id);
+        }
+        Ok(())
+    }
+
+    /// The record stored for `payload`
+    fn client_record(&self, payload: RegistrationPayload) -> RegisteredClient {
+        // Only the salted hash is persisted when hashing at rest is enabled
+        let auth_token = if self.hash_tokens {
+            token_hash::hash_token(&payload.auth_token)
+        } else {
+            payload.auth_token
+        };
+
+        if let Some(room_id) = payload.room_id {
+            RegisteredClient::new_with_room(
+                payload.client_id,
+                auth_token,
+                room_id,
+                payload.capabilities.unwrap_or_default(),
+                payload.metadata.unwrap_or_default(),
+            )
+        } else {
+            RegisteredClient::new(
+                payload.client_id,
+                auth_token,
+                payload.capabilities.unwrap_or_default(),
+                payload.metadata.unwrap_or_default(),
+            )
+        }
+    }
 }
 
 impl FirestoreTerminatedRoomRepository {
@@ -122,66 +223,77 @@ impl FirestoreWebRTCClientRepository {
 #[async_trait]
 impl ClientRepository for FirestoreClientRepository {
     async fn create_client(&self, payload: RegistrationPayload) -> DatabaseResult<RegisteredClient> {
-        let mut clients = self.clients.lock().await;
+        let mut store = self.clients.lock().await;
         
         // Check if client already exists
-        if clients.contains_key(&payload.client_id) {
+        if store.clients.contains_key(&payload.client_id) {
             return Err(crate::database::DatabaseError::Validation(
                 format!("Client {} already exists", payload.client_id)
             ));
         }
 
-        let client = if let Some(room_id) = payload.room_id {
-            RegisteredClient::new_with_room(
-                payload.client_id.clone(),
-                payload.auth_token,
-                room_id,
-                payload.capabilities.unwrap_or_default(),
-                payload.metadata.unwrap_or_default(),
-            )
-        } else {
-            RegisteredClient::new(
-                payload.client_id.clone(),
-                payload.auth_token,
-                payload.capabilities.unwrap_or_default(),
-                payload.metadata.unwrap_or_default(),
-            )
+        self.make_room(&mut store, &payload.client_id)?;
+        let client = self.client_record(payload);
+        store.insert(client.clone());
+        info!("Created new client: {} ({} stored)", client.client_id, store.clients.len());
+        Ok(client)
+    }
+
+    async fn upsert_client(&self, payload: RegistrationPayload) -> DatabaseResult<RegisteredClient> {
+        let mut store = self.clients.lock().await;
+
+        let client = match store.clients.get(&payload.client_id) {
+            Some(existing) => {
+                if !token_hash::verify_token(&existing.auth_token, &payload.auth_token) {
+                    warn!("Rejected re-registration of {} with a different token", payload.client_id);
+                    return Err(crate::database::DatabaseError::Authentication(
+                        format!("Client {} is registered with a different token", payload.client_id)
+                    ));
+                }
+                let existing = existing.clone();
+                self.client_record(payload).replacing(&existing)
+            }
+            None => {
+                self.make_room(&mut store, &payload.client_id)?;
+                self.client_record(payload)
+            }
         };
 
-        clients.insert(payload.client_id, client.clone());
-        info!("Created new client: {}", client.client_id);
+        store.insert(client.clone());
+        info!("Upserted client: {} ({} stored)", client.client_id, store.clients.len());
         Ok(client)
     }
 
     async fn get_client(&self, client_id: &str) -> DatabaseResult<Option<RegisteredClient>> {
-        let clients = self.clients.lock().await;
-        Ok(clients.get(client_id).cloned())
+        let store = self.clients.lock().await;
+        Ok(store.clients.get(client_id).cloned())
     }
 
     async fn get_client_by_token(&self, auth_token: &str) -> DatabaseResult<Option<RegisteredClient>> {
-        let clients = self.clients.lock().await;
-        Ok(clients.values().find(|c| c.auth_token == auth_token).cloned())
+        let store = self.clients.lock().await;
+        // Salted hashes can't be looked up directly, so verify against each stored token
+        Ok(store.clients.values().find(|c| token_hash::verify_token(&c.auth_token, auth_token)).cloned())
     }
 
     async fn update_client(&self, client: RegisteredClient) -> DatabaseResult<RegisteredClient> {
-        let mut clients = self.clients.lock().await;
+        let mut store = self.clients.lock().await;
         let mut updated_client = client;
         updated_client.update_last_seen();
-        clients.insert(updated_client.client_id.clone(), updated_client.clone());
+        store.insert(updated_client.clone());
         info!("Updated client: {}", updated_client.client_id);
         Ok(updated_client)
     }
 
     async fn delete_client(&self, client_id: &str) -> DatabaseResult<bool> {
-        let mut clients = self.clients.lock().await;
-        let removed = clients.remove(client_id).is_some();
+        let mut store = self.clients.lock().await;
+        let removed = store.remove(client_id).is_some();
         info!("Deleted client: {}", client_id);
         Ok(removed)
     }
 
     async fn list_clients(&self, limit: Option<usize>) -> DatabaseResult<Vec<RegisteredClient>> {
-        let clients = self.clients.lock().await;
-        let mut result: Vec<_> = clients.values().cloned().collect();
+        let store = self.clients.lock().await;
+        let mut result: Vec<_> = store.clients.values().cloned().collect();
         
         if let Some(limit) = limit {
             result.truncate(limit);
@@ -191,26 +303,25 @@ impl ClientRepository for FirestoreClientRepository {
     }
 
     async fn update_last_seen(&self, client_id: &str) -> DatabaseResult<bool> {
-        let mut clients = self.clients.lock().await;
-        if let Some(client) = clients.get_mut(client_id) {
-            client.update_last_seen();
-            Ok(true)
-        } else {
-            Ok(false)
-        }
+        let mut store = self.clients.lock().await;
+        Ok(store.update_last_seen(client_id))
     }
 
     async fn client_exists(&self, client_id: &str) -> DatabaseResult<bool> {
-        let clients = self.clients.lock().await;
-        Ok(clients.contains_key(client_id))
+        let store = self.clients.lock().await;
+        Ok(store.clients.contains_key(client_id))
     }
 
     async fn validate_auth(&self, client_id: &str, auth_token: &str) -> DatabaseResult<bool> {
-        let clients = self.clients.lock().await;
-        Ok(clients.get(client_id)
-            .map(|c| c.auth_token == auth_token && c.is_active())
+        let store = self.clients.lock().await;
+        Ok(store.clients.get(client_id)
+            .map(|c| token_hash::verify_token(&c.auth_token, auth_token) && c.is_active())
             .unwrap_or(false))
     }
+
+    async fn take_evicted(&self) -> Vec<String> {
+        std::mem::take(&mut self.clients.lock().await.evicted)
+    }
 }
 
 #[async_trait]
@@ -271,6 +382,17 @@ impl TerminatedRoomRepository for FirestoreTerminatedRoomRepository {
             .collect();
         Ok(result)
     }
+
+    async fn delete_terminated_rooms_before(&self, cutoff: chrono::DateTime<chrono::Utc>) -> DatabaseResult<usize> {
+        let mut rooms = self.terminated_rooms.lock().await;
+        let before = rooms.len();
+        rooms.retain(|_, room| room.terminated_at >= cutoff);
+        let deleted = before - rooms.len();
+        if deleted > 0 {
+            info!("Deleted {} terminated room records older than {}", deleted, cutoff);
+        }
+        Ok(deleted)
+    }
 }
 
 #[async_trait]
@@ -363,6 +485,15 @@ impl ClientInRoomRepository for FirestoreClientInRoomRepository {
         Ok(result)
     }
 
+    async fn get_rooms_for_client(&self, client_id: &str) -> DatabaseResult<Vec<ClientInRoom>> {
+        let clients_in_rooms = self.clients_in_rooms.lock().await;
+        let result: Vec<_> = clients_in_rooms.values()
+            .filter(|c| c.client_id == client_id)
+            .cloned()
+            .collect();
+        Ok(result)
+    }
+
     async fn list_clients_in_rooms(&self) -> DatabaseResult<Vec<ClientInRoom>> {
         let clients_in_rooms = self.clients_in_rooms.lock().await;
         Ok(clients_in_rooms.values().cloned().collect())
@@ -549,6 +680,17 @@ impl ClientInTerminatedRoomRepository for FirestoreClientInTerminatedRoomReposit
             .collect();
         Ok(result)
     }
+
+    async fn delete_clients_in_terminated_rooms_before(&self, cutoff: chrono::DateTime<chrono::Utc>) -> DatabaseResult<usize> {
+        let mut clients_in_terminated_rooms = self.clients_in_terminated_rooms.lock().await;
+        let before = clients_in_terminated_rooms.len();
+        clients_in_terminated_rooms.retain(|_, c| c.left_at >= cutoff);
+        let deleted = before - clients_in_terminated_rooms.len();
+        if deleted > 0 {
+            info!("Deleted {} client in terminated room records older than {}", deleted, cutoff);
+        }
+        Ok(deleted)
+    }
 }
 
 impl FirestoreRepositoryFactory {
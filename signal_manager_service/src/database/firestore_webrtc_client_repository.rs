@@ -1,25 +1,25 @@
 use firestore::paths;
-use firestore::FirestoreDb;
+use firestore::{FirestoreConsistencySelector, FirestoreDb, FirestoreWritePrecondition};
 use std::sync::Arc;
 use tracing::{debug, error, info};
 
 use crate::config::Config;
 use crate::database::error::DatabaseError;
+use crate::database::firestore_webrtc_room_repository::{connect_firestore, firestore_error};
 use crate::database::models::{WebRTCClient, WebRTCClientRegistrationPayload, WebRTCClientStatus, ClientRole};
+use crate::database::reconnect::ReconnectingClient;
 use crate::database::webrtc_client_repository::WebRTCClientRepository;
 
 const COLLECTION_NAME: &str = "webrtc_clients";
 
 pub struct FirestoreWebRTCClientRepository {
-    db: FirestoreDb,
+    db: ReconnectingClient<FirestoreDb>,
     _collection_name: String,
 }
 
 impl FirestoreWebRTCClientRepository {
     pub async fn new(config: Arc<Config>) -> Result<Self, DatabaseError> {
-        let db = FirestoreDb::new(&config.gcp.project_id)
-            .await
-            .map_err(|e| DatabaseError::Connection(format!("Failed to create Firestore client: {e}")))?;
+        let db = connect_firestore(&config).await?;
         
         Ok(Self {
             db,
@@ -41,7 +41,9 @@ impl WebRTCClientRepository for FirestoreWebRTCClientRepository {
         
         let doc_id = client.client_id.clone();
         
-        match self.db.fluent()
+        let db = self.db.client().await?;
+        
+        match db.fluent()
             .insert()
             .into(COLLECTION_NAME)
             .document_id(&doc_id)
@@ -54,13 +56,71 @@ impl WebRTCClientRepository for FirestoreWebRTCClientRepository {
             }
             Err(e) => {
                 error!("Failed to register WebRTC client: {}", e);
-                Err(DatabaseError::Write(format!("Failed to register WebRTC client: {e}")))
+                Err(self.db.fail(&db, firestore_error(&e, DatabaseError::Write(format!("Failed to register WebRTC client: {e}")))).await)
+            }
+        }
+    }
+
+    async fn admit_client(&self, payload: WebRTCClientRegistrationPayload, max_participants: u32) -> Result<Option<WebRTCClient>, DatabaseError> {
+        let mut client = WebRTCClient::new(
+            payload.client_id,
+            payload.room_id,
+            payload.role,
+            payload.session_id,
+            payload.metadata,
+        );
+        client.update_status(WebRTCClientStatus::Active);
+
+        let doc_id = client.client_id.clone();
+
+        let db = self.db.client().await?;
+
+        // Reading the room's clients inside the transaction makes a concurrent admission to the
+        // same room fail to commit, so two joins cannot both take its last place
+        let admitted: firestore::FirestoreResult<bool> = async {
+            let mut transaction = db.begin_transaction().await?;
+            let transaction_db = db.clone_with_consistency_selector(FirestoreConsistencySelector::Transaction(transaction.transaction_id().clone()));
+            let room_clients = transaction_db.fluent()
+                .select()
+                .from(COLLECTION_NAME)
+                .filter(|q| q.field("room_id").eq(client.room_id.as_str()))
+                .obj::<WebRTCClient>()
+                .query()
+                .await?;
+            if !client.fits_in_room(&room_clients, max_participants) {
+                transaction.rollback().await?;
+                return Ok(false);
+            }
+            transaction_db.fluent()
+                .update()
+                .in_col(COLLECTION_NAME)
+                .precondition(FirestoreWritePrecondition::Exists(false))
+                .document_id(&doc_id)
+                .object(&client)
+                .add_to_transaction(&mut transaction)?;
+            transaction.commit().await?;
+            Ok(true)
+        }.await;
+
+        match admitted {
+            Ok(true) => {
+                info!("Admitted WebRTC client: {}", doc_id);
+                Ok(Some(client))
+            }
+            Ok(false) => {
+                debug!("Room {} has no place for WebRTC client {}", client.room_id, doc_id);
+                Ok(None)
+            }
+            Err(e) => {
+                error!("Failed to admit WebRTC client: {}", e);
+                Err(self.db.fail(&db, firestore_error(&e, DatabaseError::Write(format!("Failed to admit WebRTC client: {e}")))).await)
             }
         }
     }
 
     async fn get_client_by_id(&self, client_id: &str) -> Result<Option<WebRTCClient>, DatabaseError> {
-        let result = self.db.fluent()
+        let db = self.db.client().await?;
+        let result = db.fluent()
             .select()
             .by_id_in(COLLECTION_NAME)
             .obj::<WebRTCClient>()
@@ -78,14 +138,15 @@ impl WebRTCClientRepository for FirestoreWebRTCClientRepository {
                     Ok(None)
                 } else {
                     error!("Failed to get WebRTC client: {}", e);
-                    Err(DatabaseError::Read(format!("Failed to get WebRTC client: {e}")))
+                    Err(self.db.fail(&db, firestore_error(&e, DatabaseError::Read(format!("Failed to get WebRTC client: {e}")))).await)
                 }
             }
         }
     }
 
     async fn get_clients_by_room_id(&self, room_id: &str) -> Result<Vec<WebRTCClient>, DatabaseError> {
-        let query = self.db.fluent()
+        let db = self.db.client().await?;
+        let query = db.fluent()
             .select()
             .from(COLLECTION_NAME)
             .filter(|q| q.field("room_id").eq(room_id))
@@ -99,7 +160,7 @@ impl WebRTCClientRepository for FirestoreWebRTCClientRepository {
             }
             Err(e) => {
                 error!("Failed to get clients by room ID: {}", e);
-                Err(DatabaseError::Read(format!("Failed to get clients by room ID: {e}")))
+                Err(self.db.fail(&db, firestore_error(&e, DatabaseError::Read(format!("Failed to get clients by room ID: {e}")))).await)
             }
         }
     }
@@ -108,9 +169,12 @@ impl WebRTCClientRepository for FirestoreWebRTCClientRepository {
         let role_str = match role {
             ClientRole::Sender => "sender",
             ClientRole::Receiver => "receiver",
+            ClientRole::Observer => "observer",
         };
 
-        let query = self.db.fluent()
+        let db = self.db.client().await?;
+
+        let query = db.fluent()
             .select()
             .from(COLLECTION_NAME)
             .filter(|q| q.field("room_id").eq(room_id).and(Some(q.field("role").eq(role_str).expect("role filter must be valid"))))
@@ -124,7 +188,7 @@ impl WebRTCClientRepository for FirestoreWebRTCClientRepository {
             }
             Err(e) => {
                 error!("Failed to get clients by role: {}", e);
-                Err(DatabaseError::Read(format!("Failed to get clients by role: {e}")))
+                Err(self.db.fail(&db, firestore_error(&e, DatabaseError::Read(format!("Failed to get clients by role: {e}")))).await)
             }
         }
     }
@@ -139,7 +203,9 @@ impl WebRTCClientRepository for FirestoreWebRTCClientRepository {
         updated_client.status = status.clone();
         let status_for_log = status;
         
-        match self.db.fluent()
+        let db = self.db.client().await?;
+        
+        match db.fluent()
             .update()
             .fields(paths!(WebRTCClient::status))
             .in_col(COLLECTION_NAME)
@@ -153,7 +219,7 @@ impl WebRTCClientRepository for FirestoreWebRTCClientRepository {
             }
             Err(e) => {
                 error!("Failed to update client status: {}", e);
-                Err(DatabaseError::Write(format!("Failed to update client status: {e}")))
+                Err(self.db.fail(&db, firestore_error(&e, DatabaseError::Write(format!("Failed to update client status: {e}")))).await)
             }
         }
     }
@@ -167,7 +233,9 @@ impl WebRTCClientRepository for FirestoreWebRTCClientRepository {
         let mut updated_client = client;
         updated_client.session_id = Some(session_id.to_string());
         
-        match self.db.fluent()
+        let db = self.db.client().await?;
+        
+        match db.fluent()
             .update()
             .fields(paths!(WebRTCClient::session_id))
             .in_col(COLLECTION_NAME)
@@ -181,13 +249,14 @@ impl WebRTCClientRepository for FirestoreWebRTCClientRepository {
             }
             Err(e) => {
                 error!("Failed to set session ID: {}", e);
-                Err(DatabaseError::Write(format!("Failed to set session ID: {e}")))
+                Err(self.db.fail(&db, firestore_error(&e, DatabaseError::Write(format!("Failed to set session ID: {e}")))).await)
             }
         }
     }
 
     async fn get_client_by_session_id(&self, session_id: &str) -> Result<Option<WebRTCClient>, DatabaseError> {
-        let query = self.db.fluent()
+        let db = self.db.client().await?;
+        let query = db.fluent()
             .select()
             .from(COLLECTION_NAME)
             .filter(|q| q.field("session_id").eq(session_id))
@@ -206,13 +275,14 @@ impl WebRTCClientRepository for FirestoreWebRTCClientRepository {
             }
             Err(e) => {
                 error!("Failed to get client by session ID: {}", e);
-                Err(DatabaseError::Read(format!("Failed to get client by session ID: {e}")))
+                Err(self.db.fail(&db, firestore_error(&e, DatabaseError::Read(format!("Failed to get client by session ID: {e}")))).await)
             }
         }
     }
 
     async fn get_active_clients(&self) -> Result<Vec<WebRTCClient>, DatabaseError> {
-        let query = self.db.fluent()
+        let db = self.db.client().await?;
+        let query = db.fluent()
             .select()
             .from(COLLECTION_NAME)
             .filter(|q| q.field("status").eq("Active"))
@@ -226,13 +296,14 @@ impl WebRTCClientRepository for FirestoreWebRTCClientRepository {
             }
             Err(e) => {
                 error!("Failed to get active clients: {}", e);
-                Err(DatabaseError::Read(format!("Failed to get active clients: {e}")))
+                Err(self.db.fail(&db, firestore_error(&e, DatabaseError::Read(format!("Failed to get active clients: {e}")))).await)
             }
         }
     }
 
     async fn get_active_clients_in_room(&self, room_id: &str) -> Result<Vec<WebRTCClient>, DatabaseError> {
-        let query = self.db.fluent()
+        let db = self.db.client().await?;
+        let query = db.fluent()
             .select()
             .from(COLLECTION_NAME)
             .filter(|q| q.field("room_id").eq(room_id).and(Some(q.field("status").eq("Active").expect("status filter must be valid"))))
@@ -246,7 +317,7 @@ impl WebRTCClientRepository for FirestoreWebRTCClientRepository {
             }
             Err(e) => {
                 error!("Failed to get active clients in room: {}", e);
-                Err(DatabaseError::Read(format!("Failed to get active clients in room: {e}")))
+                Err(self.db.fail(&db, firestore_error(&e, DatabaseError::Read(format!("Failed to get active clients in room: {e}")))).await)
             }
         }
     }
@@ -260,7 +331,9 @@ impl WebRTCClientRepository for FirestoreWebRTCClientRepository {
         let mut updated_client = client;
         updated_client.status = WebRTCClientStatus::Disconnected;
         
-        match self.db.fluent()
+        let db = self.db.client().await?;
+        
+        match db.fluent()
             .update()
             .fields(paths!(WebRTCClient::status))
             .in_col(COLLECTION_NAME)
@@ -274,7 +347,7 @@ impl WebRTCClientRepository for FirestoreWebRTCClientRepository {
             }
             Err(e) => {
                 error!("Failed to disconnect client: {}", e);
-                Err(DatabaseError::Write(format!("Failed to disconnect client: {e}")))
+                Err(self.db.fail(&db, firestore_error(&e, DatabaseError::Write(format!("Failed to disconnect client: {e}")))).await)
             }
         }
     }
@@ -288,7 +361,9 @@ impl WebRTCClientRepository for FirestoreWebRTCClientRepository {
         let mut updated_client = client;
         updated_client.room_id = String::new();
         
-        match self.db.fluent()
+        let db = self.db.client().await?;
+        
+        match db.fluent()
             .update()
             .fields(paths!(WebRTCClient::room_id))
             .in_col(COLLECTION_NAME)
@@ -302,13 +377,14 @@ impl WebRTCClientRepository for FirestoreWebRTCClientRepository {
             }
             Err(e) => {
                 error!("Failed to remove client from room: {}", e);
-                Err(DatabaseError::Write(format!("Failed to remove client from room: {e}")))
+                Err(self.db.fail(&db, firestore_error(&e, DatabaseError::Write(format!("Failed to remove client from room: {e}")))).await)
             }
         }
     }
 
     async fn delete_client(&self, client_id: &str) -> Result<(), DatabaseError> {
-        match self.db.fluent()
+        let db = self.db.client().await?;
+        match db.fluent()
             .delete()
             .from(COLLECTION_NAME)
             .document_id(client_id)
@@ -320,13 +396,14 @@ impl WebRTCClientRepository for FirestoreWebRTCClientRepository {
             }
             Err(e) => {
                 error!("Failed to delete WebRTC client: {}", e);
-                Err(DatabaseError::Write(format!("Failed to delete WebRTC client: {e}")))
+                Err(self.db.fail(&db, firestore_error(&e, DatabaseError::Write(format!("Failed to delete WebRTC client: {e}")))).await)
             }
         }
     }
 
     async fn get_client_count(&self) -> Result<usize, DatabaseError> {
-        let query = self.db.fluent()
+        let db = self.db.client().await?;
+        let query = db.fluent()
             .select()
             .from(COLLECTION_NAME)
             .obj::<WebRTCClient>()
@@ -339,13 +416,14 @@ impl WebRTCClientRepository for FirestoreWebRTCClientRepository {
             }
             Err(e) => {
                 error!("Failed to get client count: {}", e);
-                Err(DatabaseError::Read(format!("Failed to get client count: {e}")))
+                Err(self.db.fail(&db, firestore_error(&e, DatabaseError::Read(format!("Failed to get client count: {e}")))).await)
             }
         }
     }
 
     async fn get_client_count_in_room(&self, room_id: &str) -> Result<usize, DatabaseError> {
-        let query = self.db.fluent()
+        let db = self.db.client().await?;
+        let query = db.fluent()
             .select()
             .from(COLLECTION_NAME)
             .filter(|q| q.field("room_id").eq(room_id))
@@ -359,7 +437,7 @@ impl WebRTCClientRepository for FirestoreWebRTCClientRepository {
             }
             Err(e) => {
                 error!("Failed to get client count in room: {}", e);
-                Err(DatabaseError::Read(format!("Failed to get client count in room: {e}")))
+                Err(self.db.fail(&db, firestore_error(&e, DatabaseError::Read(format!("Failed to get client count in room: {e}")))).await)
             }
         }
     }
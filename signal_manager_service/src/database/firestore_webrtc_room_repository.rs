@@ -1,3 +1,4 @@
+use firestore::errors::FirestoreError;
 use firestore::paths;
 use firestore::FirestoreDb;
 use std::sync::Arc;
@@ -5,21 +6,46 @@ use tracing::{debug, error, info};
 
 use crate::config::Config;
 use crate::database::error::DatabaseError;
-use crate::database::models::{WebRTCRoom, WebRTCRoomCreationPayload, WebRTCRoomStatus};
+use crate::database::models::{WebRTCRoom, WebRTCRoomCreationPayload, WebRTCRoomStatus, DEFAULT_MAX_PARTICIPANTS};
+use crate::database::reconnect::ReconnectingClient;
 use crate::database::webrtc_room_repository::WebRTCRoomRepository;
 
 const COLLECTION_NAME: &str = "webrtc_rooms";
 
+/// Client for the project in `config`, re-created after a dropped connection
+pub(crate) async fn connect_firestore(config: &Config) -> Result<ReconnectingClient<FirestoreDb>, DatabaseError> {
+    let project_id = config.gcp.project_id.clone();
+    let db = ReconnectingClient::new(move || {
+        let project_id = project_id.clone();
+        async move {
+            FirestoreDb::new(&project_id)
+                .await
+                .map_err(|e| DatabaseError::Unavailable(format!("Failed to create Firestore client: {e}")))
+        }
+    });
+    // Connect up front so a misconfigured project still fails at startup
+    db.client().await?;
+    Ok(db)
+}
+
+/// Map a failed Firestore call to `otherwise`, unless the failure is a lost connection or
+/// a transient server error, which are reported as retryable `Unavailable`
+pub(crate) fn firestore_error(e: &FirestoreError, otherwise: DatabaseError) -> DatabaseError {
+    match e {
+        FirestoreError::NetworkError(_) => DatabaseError::Unavailable(e.to_string()),
+        FirestoreError::DatabaseError(db_err) if db_err.retry_possible => DatabaseError::Unavailable(e.to_string()),
+        _ => otherwise,
+    }
+}
+
 pub struct FirestoreWebRTCRoomRepository {
-    db: FirestoreDb,
+    db: ReconnectingClient<FirestoreDb>,
     _collection_name: String,
 }
 
 impl FirestoreWebRTCRoomRepository {
     pub async fn new(config: Arc<Config>) -> Result<Self, DatabaseError> {
-        let db = FirestoreDb::new(&config.gcp.project_id)
-            .await
-            .map_err(|e| DatabaseError::Connection(format!("Failed to create Firestore client: {e}")))?;
+        let db = connect_firestore(&config).await?;
         
         Ok(Self {
             db,
@@ -38,11 +64,13 @@ impl WebRTCRoomRepository for FirestoreWebRTCRoomRepository {
             payload.receiver_client_id,
             payload.session_id,
             payload.metadata,
-        );
+        ).with_max_participants(payload.max_participants.unwrap_or(DEFAULT_MAX_PARTICIPANTS));
         
         let doc_id = room.room_id.clone();
         
-        match self.db.fluent()
+        let db = self.db.client().await?;
+        
+        match db.fluent()
             .insert()
             .into(COLLECTION_NAME)
             .document_id(&doc_id)
@@ -55,13 +83,14 @@ impl WebRTCRoomRepository for FirestoreWebRTCRoomRepository {
             }
             Err(e) => {
                 error!("Failed to create WebRTC room: {}", e);
-                Err(DatabaseError::Write(format!("Failed to create WebRTC room: {e}")))
+                Err(self.db.fail(&db, firestore_error(&e, DatabaseError::Write(format!("Failed to create WebRTC room: {e}")))).await)
             }
         }
     }
 
     async fn get_room_by_id(&self, room_id: &str) -> Result<Option<WebRTCRoom>, DatabaseError> {
-        let result = self.db.fluent()
+        let db = self.db.client().await?;
+        let result = db.fluent()
             .select()
             .by_id_in(COLLECTION_NAME)
             .obj::<WebRTCRoom>()
@@ -79,14 +108,15 @@ impl WebRTCRoomRepository for FirestoreWebRTCRoomRepository {
                     Ok(None)
                 } else {
                     error!("Failed to get WebRTC room: {}", e);
-                    Err(DatabaseError::Read(format!("Failed to get WebRTC room: {e}")))
+                    Err(self.db.fail(&db, firestore_error(&e, DatabaseError::Read(format!("Failed to get WebRTC room: {e}")))).await)
                 }
             }
         }
     }
 
     async fn get_room_by_uuid(&self, room_uuid: &str) -> Result<Option<WebRTCRoom>, DatabaseError> {
-        let query = self.db.fluent()
+        let db = self.db.client().await?;
+        let query = db.fluent()
             .select()
             .from(COLLECTION_NAME)
             .filter(|q| q.field("room_id").eq(room_uuid))
@@ -105,7 +135,7 @@ impl WebRTCRoomRepository for FirestoreWebRTCRoomRepository {
             }
             Err(e) => {
                 error!("Failed to get WebRTC room by UUID: {}", e);
-                Err(DatabaseError::Read(format!("Failed to get WebRTC room by UUID: {e}")))
+                Err(self.db.fail(&db, firestore_error(&e, DatabaseError::Read(format!("Failed to get WebRTC room by UUID: {e}")))).await)
             }
         }
     }
@@ -120,7 +150,9 @@ impl WebRTCRoomRepository for FirestoreWebRTCRoomRepository {
         updated_room.status = status.clone();
         let status_for_log = status;
         
-        match self.db.fluent()
+        let db = self.db.client().await?;
+        
+        match db.fluent()
             .update()
             .fields(paths!(WebRTCRoom::status))
             .in_col(COLLECTION_NAME)
@@ -134,7 +166,7 @@ impl WebRTCRoomRepository for FirestoreWebRTCRoomRepository {
             }
             Err(e) => {
                 error!("Failed to update room status: {}", e);
-                Err(DatabaseError::Write(format!("Failed to update room status: {e}")))
+                Err(self.db.fail(&db, firestore_error(&e, DatabaseError::Write(format!("Failed to update room status: {e}")))).await)
             }
         }
     }
@@ -148,7 +180,9 @@ impl WebRTCRoomRepository for FirestoreWebRTCRoomRepository {
         let mut updated_room = room;
         updated_room.sender_client_id = Some(client_id.to_string());
         
-        match self.db.fluent()
+        let db = self.db.client().await?;
+        
+        match db.fluent()
             .update()
             .fields(paths!(WebRTCRoom::sender_client_id))
             .in_col(COLLECTION_NAME)
@@ -162,7 +196,7 @@ impl WebRTCRoomRepository for FirestoreWebRTCRoomRepository {
             }
             Err(e) => {
                 error!("Failed to set sender client ID: {}", e);
-                Err(DatabaseError::Write(format!("Failed to set sender client ID: {e}")))
+                Err(self.db.fail(&db, firestore_error(&e, DatabaseError::Write(format!("Failed to set sender client ID: {e}")))).await)
             }
         }
     }
@@ -176,7 +210,9 @@ impl WebRTCRoomRepository for FirestoreWebRTCRoomRepository {
         let mut updated_room = room;
         updated_room.receiver_client_id = Some(client_id.to_string());
         
-        match self.db.fluent()
+        let db = self.db.client().await?;
+        
+        match db.fluent()
             .update()
             .fields(paths!(WebRTCRoom::receiver_client_id))
             .in_col(COLLECTION_NAME)
@@ -190,7 +226,7 @@ impl WebRTCRoomRepository for FirestoreWebRTCRoomRepository {
             }
             Err(e) => {
                 error!("Failed to set receiver client ID: {}", e);
-                Err(DatabaseError::Write(format!("Failed to set receiver client ID: {e}")))
+                Err(self.db.fail(&db, firestore_error(&e, DatabaseError::Write(format!("Failed to set receiver client ID: {e}")))).await)
             }
         }
     }
@@ -204,7 +240,9 @@ impl WebRTCRoomRepository for FirestoreWebRTCRoomRepository {
         let mut updated_room = room;
         updated_room.session_id = Some(session_id.to_string());
         
-        match self.db.fluent()
+        let db = self.db.client().await?;
+        
+        match db.fluent()
             .update()
             .fields(paths!(WebRTCRoom::session_id))
             .in_col(COLLECTION_NAME)
@@ -218,13 +256,14 @@ impl WebRTCRoomRepository for FirestoreWebRTCRoomRepository {
             }
             Err(e) => {
                 error!("Failed to set session ID: {}", e);
-                Err(DatabaseError::Write(format!("Failed to set session ID: {e}")))
+                Err(self.db.fail(&db, firestore_error(&e, DatabaseError::Write(format!("Failed to set session ID: {e}")))).await)
             }
         }
     }
 
     async fn get_active_rooms(&self) -> Result<Vec<WebRTCRoom>, DatabaseError> {
-        let query = self.db.fluent()
+        let db = self.db.client().await?;
+        let query = db.fluent()
             .select()
             .from(COLLECTION_NAME)
             .filter(|q| q.field("status").eq("Active"))
@@ -238,13 +277,35 @@ impl WebRTCRoomRepository for FirestoreWebRTCRoomRepository {
             }
             Err(e) => {
                 error!("Failed to get active rooms: {}", e);
-                Err(DatabaseError::Read(format!("Failed to get active rooms: {e}")))
+                Err(self.db.fail(&db, firestore_error(&e, DatabaseError::Read(format!("Failed to get active rooms: {e}")))).await)
+            }
+        }
+    }
+
+    async fn get_rooms_created_before(&self, cutoff: chrono::DateTime<chrono::Utc>) -> Result<Vec<WebRTCRoom>, DatabaseError> {
+        let db = self.db.client().await?;
+        let query = db.fluent()
+            .select()
+            .from(COLLECTION_NAME)
+            .filter(|q| q.field("created_at").less_than(firestore::FirestoreTimestamp(cutoff)))
+            .obj::<WebRTCRoom>()
+            .query();
+
+        match query.await {
+            Ok(rooms) => {
+                debug!("Found {} rooms created before {}", rooms.len(), cutoff);
+                Ok(rooms)
+            }
+            Err(e) => {
+                error!("Failed to get rooms created before {}: {}", cutoff, e);
+                Err(self.db.fail(&db, firestore_error(&e, DatabaseError::Read(format!("Failed to get rooms created before {cutoff}: {e}")))).await)
             }
         }
     }
 
     async fn get_rooms_by_client_id(&self, client_id: &str) -> Result<Vec<WebRTCRoom>, DatabaseError> {
-        let query = self.db.fluent()
+        let db = self.db.client().await?;
+        let query = db.fluent()
             .select()
             .from(COLLECTION_NAME)
             .filter(|q| q.field("sender_client_id").eq(client_id).or(Some(q.field("receiver_client_id").eq(client_id).expect("receiver_client_id filter must be valid"))))
@@ -258,7 +319,7 @@ impl WebRTCRoomRepository for FirestoreWebRTCRoomRepository {
             }
             Err(e) => {
                 error!("Failed to get rooms by client ID: {}", e);
-                Err(DatabaseError::Read(format!("Failed to get rooms by client ID: {e}")))
+                Err(self.db.fail(&db, firestore_error(&e, DatabaseError::Read(format!("Failed to get rooms by client ID: {e}")))).await)
             }
         }
     }
@@ -272,7 +333,9 @@ impl WebRTCRoomRepository for FirestoreWebRTCRoomRepository {
         let mut updated_room = room;
         updated_room.status = WebRTCRoomStatus::Terminated;
         
-        match self.db.fluent()
+        let db = self.db.client().await?;
+        
+        match db.fluent()
             .update()
             .fields(paths!(WebRTCRoom::status))
             .in_col(COLLECTION_NAME)
@@ -286,13 +349,14 @@ impl WebRTCRoomRepository for FirestoreWebRTCRoomRepository {
             }
             Err(e) => {
                 error!("Failed to terminate room: {}", e);
-                Err(DatabaseError::Write(format!("Failed to terminate room: {e}")))
+                Err(self.db.fail(&db, firestore_error(&e, DatabaseError::Write(format!("Failed to terminate room: {e}")))).await)
             }
         }
     }
 
     async fn delete_room(&self, room_id: &str) -> Result<(), DatabaseError> {
-        match self.db.fluent()
+        let db = self.db.client().await?;
+        match db.fluent()
             .delete()
             .from(COLLECTION_NAME)
             .document_id(room_id)
@@ -304,13 +368,14 @@ impl WebRTCRoomRepository for FirestoreWebRTCRoomRepository {
             }
             Err(e) => {
                 error!("Failed to delete WebRTC room: {}", e);
-                Err(DatabaseError::Write(format!("Failed to delete WebRTC room: {e}")))
+                Err(self.db.fail(&db, firestore_error(&e, DatabaseError::Write(format!("Failed to delete WebRTC room: {e}")))).await)
             }
         }
     }
 
     async fn get_room_count(&self) -> Result<usize, DatabaseError> {
-        let query = self.db.fluent()
+        let db = self.db.client().await?;
+        let query = db.fluent()
             .select()
             .from(COLLECTION_NAME)
             .obj::<WebRTCRoom>()
@@ -323,7 +388,7 @@ impl WebRTCRoomRepository for FirestoreWebRTCRoomRepository {
             }
             Err(e) => {
                 error!("Failed to get room count: {}", e);
-                Err(DatabaseError::Read(format!("Failed to get room count: {e}")))
+                Err(self.db.fail(&db, firestore_error(&e, DatabaseError::Read(format!("Failed to get room count: {e}")))).await)
             }
         }
     }
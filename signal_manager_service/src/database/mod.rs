@@ -11,6 +11,10 @@ pub mod webrtc_client_repository;
 pub mod firestore_webrtc_room_repository;
 pub mod firestore_webrtc_client_repository;
 pub mod repository_factory;
+pub mod reconnect;
+pub mod retention;
+pub mod room_report;
+pub mod token_hash;
 
 pub use models::*;
 pub use firestore::*;
@@ -22,4 +26,7 @@ pub use client_in_room_repository::*;
 pub use client_in_terminated_room_repository::*;
 pub use webrtc_room_repository::*;
 pub use webrtc_client_repository::*;
-pub use repository_factory::*; 
\ No newline at end of file
+pub use repository_factory::*;
+pub use reconnect::*;
+pub use retention::*;
+pub use room_report::*; 
\ No newline at end of file
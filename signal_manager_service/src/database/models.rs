@@ -2,6 +2,12 @@ use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
+/// Termination reason recorded when a client leaves a room without giving one
+pub const VOLUNTARY_LEAVE_REASON: &str = "Left room";
+
+/// `terminated_by` of records for clients removed by the server rather than by themselves
+pub const SERVER_TERMINATOR: &str = "server";
+
 /// Represents a registered client in the system
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RegisteredClient {
@@ -9,7 +15,7 @@ pub struct RegisteredClient {
     pub id: String,
     /// Client name or identifier
     pub client_id: String,
-    /// Authentication token for the client
+    /// Authentication token for the client, or its salted hash when hashing at rest is enabled
     pub auth_token: String,
     /// Room identifier that the client is associated with
     pub room_id: Option<String>,
@@ -255,6 +261,15 @@ impl RegisteredClient {
         }
     }
 
+    /// Take over the identity and registration time of `previous`, which this record replaces
+    pub fn replacing(mut self, previous: &RegisteredClient) -> Self {
+        self.id = previous.id.clone();
+        self.registered_at = previous.registered_at;
+        self.record_created_at = previous.record_created_at;
+        self.update_last_seen();
+        self
+    }
+
     /// Update the last seen timestamp
     pub fn update_last_seen(&mut self) {
         self.last_seen = Some(Utc::now());
@@ -492,6 +507,35 @@ impl ClientInTerminatedRoom {
         }
     }
 
+    /// Record of a client that left `room_id` with `WebRTCRoomLeave`, keeping the reason it gave
+    pub fn voluntary_leave(client_id: String, room_id: String, joined_at: DateTime<Utc>, reason: Option<String>) -> Self {
+        let terminated_by = client_id.clone();
+        Self::new(
+            client_id,
+            room_id,
+            joined_at,
+            reason.unwrap_or_else(|| VOLUNTARY_LEAVE_REASON.to_string()),
+            terminated_by,
+            ClientTerminationStatus::VoluntaryDisconnect,
+            Vec::new(),
+            None,
+        )
+    }
+
+    /// Record of a client removed from `room_id` because the server terminated the room
+    pub fn forced_termination(client_id: String, room_id: String, joined_at: DateTime<Utc>, reason: &str) -> Self {
+        Self::new(
+            client_id,
+            room_id,
+            joined_at,
+            reason.to_string(),
+            SERVER_TERMINATOR.to_string(),
+            ClientTerminationStatus::Disconnected,
+            Vec::new(),
+            None,
+        )
+    }
+
     /// Builder for creating ClientInTerminatedRoom
     pub fn builder() -> ClientInTerminatedRoomBuilder {
         ClientInTerminatedRoomBuilder::default()
@@ -627,6 +671,13 @@ impl ClientInTerminatedRoom {
 
 
 
+/// Participants a room admits when its creator does not choose a limit: one sender and one receiver
+pub const DEFAULT_MAX_PARTICIPANTS: u32 = 2;
+
+fn default_max_participants() -> u32 {
+    DEFAULT_MAX_PARTICIPANTS
+}
+
 /// WebRTC room information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WebRTCRoom {
@@ -636,7 +687,8 @@ pub struct WebRTCRoom {
     pub room_id: String,
     /// Cloudflare app ID
     pub app_id: String,
-    /// Room creation timestamp
+    /// Room creation timestamp, stored as a Firestore timestamp so queries can compare it
+    #[serde(with = "firestore::serialize_as_timestamp")]
     pub created_at: DateTime<Utc>,
     /// Room status
     pub status: WebRTCRoomStatus,
@@ -646,6 +698,9 @@ pub struct WebRTCRoom {
     pub receiver_client_id: Option<String>,
     /// Cloudflare session ID
     pub session_id: Option<String>,
+    /// Senders and receivers the room admits at once; observers are not counted
+    #[serde(default = "default_max_participants")]
+    pub max_participants: u32,
     /// Room metadata
     pub metadata: serde_json::Value,
     /// When the record was created in the database
@@ -668,6 +723,9 @@ pub enum WebRTCRoomStatus {
 pub enum ClientRole {
     Sender,
     Receiver,
+    /// Read-only member that receives media and signaling but never sends;
+    /// does not occupy the room's sender or receiver slot
+    Observer,
 }
 
 /// WebRTC client information
@@ -679,7 +737,7 @@ pub struct WebRTCClient {
     pub client_id: String,
     /// Room ID the client is in
     pub room_id: String,
-    /// Client role (sender/receiver)
+    /// Client role (sender/receiver/observer)
     pub role: ClientRole,
     /// Session ID if active
     pub session_id: Option<String>,
@@ -712,6 +770,9 @@ pub struct WebRTCRoomCreationPayload {
     pub sender_client_id: Option<String>,
     pub receiver_client_id: Option<String>,
     pub session_id: Option<String>,
+    /// Participant cap; `None` means [`DEFAULT_MAX_PARTICIPANTS`]
+    #[serde(default)]
+    pub max_participants: Option<u32>,
     pub metadata: Option<serde_json::Value>,
 }
 
@@ -744,11 +805,18 @@ impl WebRTCRoom {
             sender_client_id,
             receiver_client_id,
             session_id,
+            max_participants: DEFAULT_MAX_PARTICIPANTS,
             metadata: metadata.unwrap_or_default(),
             record_created_at: Utc::now(),
         }
     }
 
+    /// Admit at most `max_participants` senders and receivers
+    pub fn with_max_participants(mut self, max_participants: u32) -> Self {
+        self.max_participants = max_participants;
+        self
+    }
+
     /// Get the room ID
     pub fn get_room_id(&self) -> &str {
         &self.room_id
@@ -846,6 +914,18 @@ impl WebRTCClient {
     pub fn is_active(&self) -> bool {
         matches!(self.status, WebRTCClientStatus::Active)
     }
+
+    /// Whether the client takes one of its room's participant places: an active sender or receiver
+    pub fn holds_participant_slot(&self) -> bool {
+        self.role != ClientRole::Observer && self.is_active()
+    }
+
+    /// Whether a room whose clients are `room_clients` has a place for this client under
+    /// `max_participants`; observers always do
+    pub fn fits_in_room(&self, room_clients: &[WebRTCClient], max_participants: u32) -> bool {
+        self.role == ClientRole::Observer
+            || room_clients.iter().filter(|client| client.holds_participant_slot()).count() < max_participants as usize
+    }
 }
 
 
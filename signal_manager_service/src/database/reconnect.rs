@@ -0,0 +1,68 @@
+use futures::future::BoxFuture;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::database::{DatabaseError, DatabaseResult};
+
+/// Database client that is dropped once an operation reports its connection gone
+/// (`DatabaseError::Unavailable`) and re-created on the next call, so a transient outage
+/// does not permanently break every later operation
+pub struct ReconnectingClient<C> {
+    connect: Box<dyn Fn() -> BoxFuture<'static, DatabaseResult<C>> + Send + Sync>,
+    client: Mutex<Option<Arc<C>>>,
+    connects: AtomicU64,
+}
+
+impl<C: Send + Sync> ReconnectingClient<C> {
+    /// Create a client that connects with `connect` on first use
+    pub fn new<F, Fut>(connect: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = DatabaseResult<C>> + Send + 'static,
+    {
+        Self {
+            connect: Box::new(move || Box::pin(connect())),
+            client: Mutex::new(None),
+            connects: AtomicU64::new(0),
+        }
+    }
+
+    /// The current client, connecting first if there is none. Concurrent callers share one
+    /// connection attempt, and a failed attempt is reported as `Unavailable`.
+    pub async fn client(&self) -> DatabaseResult<Arc<C>> {
+        let mut current = self.client.lock().await;
+        if let Some(client) = current.as_ref() {
+            return Ok(client.clone());
+        }
+        let client = Arc::new((self.connect)().await.map_err(|e| match e {
+            DatabaseError::Unavailable(_) => e,
+            other => DatabaseError::Unavailable(other.to_string()),
+        })?);
+        if self.connects.fetch_add(1, Ordering::Relaxed) > 0 {
+            info!("Re-established database connection");
+        }
+        *current = Some(client.clone());
+        Ok(client)
+    }
+
+    /// Pass `error` through, first dropping `client` if the error means its connection is
+    /// gone so the next call reconnects. A client already replaced by another caller is left alone.
+    pub async fn fail(&self, client: &Arc<C>, error: DatabaseError) -> DatabaseError {
+        if error.is_retryable() {
+            let mut current = self.client.lock().await;
+            if current.as_ref().is_some_and(|current| Arc::ptr_eq(current, client)) {
+                warn!("Database connection lost, reconnecting on next call: {}", error);
+                *current = None;
+            }
+        }
+        error
+    }
+
+    /// Successful connections made so far, including the first
+    pub fn connects(&self) -> u64 {
+        self.connects.load(Ordering::Relaxed)
+    }
+}
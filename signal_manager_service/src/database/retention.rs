@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use crate::database::{ClientInTerminatedRoomRepository, DatabaseResult, RepositoryFactory, TerminatedRoomRepository};
+
+/// Repositories pruned by the terminated-room retention sweep
+#[derive(Clone)]
+pub struct RetentionRepositories {
+    pub terminated_rooms: Arc<dyn TerminatedRoomRepository + Send + Sync>,
+    pub terminated_room_clients: Arc<dyn ClientInTerminatedRoomRepository + Send + Sync>,
+}
+
+impl RetentionRepositories {
+    pub async fn from_factory(factory: &dyn RepositoryFactory) -> DatabaseResult<Self> {
+        Ok(Self {
+            terminated_rooms: factory.create_terminated_room_repository().await?,
+            terminated_room_clients: factory.create_client_in_terminated_room_repository().await?,
+        })
+    }
+}
+
+/// Records removed by one retention sweep
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetentionSweep {
+    pub rooms_deleted: usize,
+    pub clients_deleted: usize,
+}
+
+/// Delete terminated rooms, and their former clients' records, older than `cutoff`
+pub async fn delete_terminated_records_before(
+    repositories: &RetentionRepositories,
+    cutoff: DateTime<Utc>,
+) -> DatabaseResult<RetentionSweep> {
+    Ok(RetentionSweep {
+        rooms_deleted: repositories.terminated_rooms.delete_terminated_rooms_before(cutoff).await?,
+        clients_deleted: repositories.terminated_room_clients.delete_clients_in_terminated_rooms_before(cutoff).await?,
+    })
+}
@@ -0,0 +1,44 @@
+use std::time::Duration;
+
+use crate::database::{
+    ClientInTerminatedRoom, ClientInTerminatedRoomRepository, DatabaseError, DatabaseResult, TerminatedRoom,
+    TerminatedRoomRepository,
+};
+
+/// A terminated room together with every client that was in it and how long they stayed
+#[derive(Debug, Clone)]
+pub struct RoomReport {
+    pub room: TerminatedRoom,
+    /// Former clients, earliest joiner first
+    pub clients: Vec<ClientInTerminatedRoom>,
+    /// Sum of every client's time in the room
+    pub total_session_duration: Duration,
+    /// Mean time a client spent in the room; `None` when the room had no clients
+    pub average_session_duration: Option<Duration>,
+}
+
+impl RoomReport {
+    pub fn new(room: TerminatedRoom, mut clients: Vec<ClientInTerminatedRoom>) -> Self {
+        clients.sort_by_key(|client| client.joined_at);
+        let total_session_duration: Duration = clients.iter().map(|client| client.get_session_duration()).sum();
+        let average_session_duration = u32::try_from(clients.len())
+            .ok()
+            .filter(|count| *count > 0)
+            .map(|count| total_session_duration / count);
+        Self { room, clients, total_session_duration, average_session_duration }
+    }
+}
+
+/// Stitch a terminated room together with its former clients and their session durations
+pub async fn get_room_report(
+    terminated_rooms: &dyn TerminatedRoomRepository,
+    terminated_room_clients: &dyn ClientInTerminatedRoomRepository,
+    room_id: &str,
+) -> DatabaseResult<RoomReport> {
+    let room = terminated_rooms
+        .get_terminated_room(room_id)
+        .await?
+        .ok_or_else(|| DatabaseError::NotFound(format!("Terminated room {room_id}")))?;
+    let clients = terminated_room_clients.get_clients_from_terminated_room(room_id).await?;
+    Ok(RoomReport::new(room, clients))
+}
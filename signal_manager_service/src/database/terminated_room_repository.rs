@@ -23,4 +23,7 @@ pub trait TerminatedRoomRepository: Send + Sync {
         start_date: chrono::DateTime<chrono::Utc>,
         end_date: chrono::DateTime<chrono::Utc>,
     ) -> DatabaseResult<Vec<TerminatedRoom>>;
+
+    /// Delete terminated rooms terminated before `cutoff`, returning how many were deleted
+    async fn delete_terminated_rooms_before(&self, cutoff: chrono::DateTime<chrono::Utc>) -> DatabaseResult<usize>;
 } 
\ No newline at end of file
@@ -0,0 +1,61 @@
+use ring::digest::{Context, SHA256};
+use ring::rand::{SecureRandom, SystemRandom};
+
+/// Prefix marking a stored token as a salted hash rather than plaintext
+const HASH_PREFIX: &str = "sha256$";
+const SALT_LEN: usize = 16;
+
+/// Hash a token with a fresh random salt.
+/// Output format: `sha256$<salt hex>$<digest hex>`
+pub fn hash_token(token: &str) -> String {
+    let mut salt = [0u8; SALT_LEN];
+    SystemRandom::new()
+        .fill(&mut salt)
+        .expect("system random number generator failed");
+    format!("{}{}${}", HASH_PREFIX, to_hex(&salt), to_hex(&digest(&salt, token)))
+}
+
+/// Whether a stored token value is a salted hash produced by `hash_token`
+pub fn is_hashed(stored: &str) -> bool {
+    stored.starts_with(HASH_PREFIX)
+}
+
+/// Compare a presented token against a stored value, which may be either a
+/// salted hash or a legacy plaintext token.
+pub fn verify_token(stored: &str, candidate: &str) -> bool {
+    let Some(encoded) = stored.strip_prefix(HASH_PREFIX) else {
+        return constant_time_eq(stored.as_bytes(), candidate.as_bytes());
+    };
+    let Some((salt_hex, digest_hex)) = encoded.split_once('$') else {
+        return false;
+    };
+    let Some(salt) = from_hex(salt_hex) else {
+        return false;
+    };
+    constant_time_eq(to_hex(&digest(&salt, candidate)).as_bytes(), digest_hex.as_bytes())
+}
+
+fn digest(salt: &[u8], token: &str) -> Vec<u8> {
+    let mut context = Context::new(&SHA256);
+    context.update(salt);
+    context.update(token.as_bytes());
+    context.finish().as_ref().to_vec()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
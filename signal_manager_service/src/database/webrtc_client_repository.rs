@@ -7,6 +7,11 @@ use crate::database::error::DatabaseError;
 pub trait WebRTCClientRepository {
     /// Register a new WebRTC client
     async fn register_client(&self, payload: WebRTCClientRegistrationPayload) -> Result<WebRTCClient, DatabaseError>;
+
+    /// Register a client as active in its room unless the room already holds `max_participants`
+    /// active senders and receivers, in which case nothing is stored and `None` is returned.
+    /// The count and the insert are one atomic step, so concurrent joins cannot overfill a room.
+    async fn admit_client(&self, payload: WebRTCClientRegistrationPayload, max_participants: u32) -> Result<Option<WebRTCClient>, DatabaseError>;
     
     /// Get a client by its ID
     async fn get_client_by_id(&self, client_id: &str) -> Result<Option<WebRTCClient>, DatabaseError>;
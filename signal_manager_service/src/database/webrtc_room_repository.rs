@@ -29,6 +29,9 @@ pub trait WebRTCRoomRepository {
     /// Get all active rooms
     async fn get_active_rooms(&self) -> Result<Vec<WebRTCRoom>, DatabaseError>;
     
+    /// Get rooms of any status created before `cutoff`
+    async fn get_rooms_created_before(&self, cutoff: chrono::DateTime<chrono::Utc>) -> Result<Vec<WebRTCRoom>, DatabaseError>;
+    
     /// Get rooms by client ID
     async fn get_rooms_by_client_id(&self, client_id: &str) -> Result<Vec<WebRTCRoom>, DatabaseError>;
     
@@ -29,6 +29,9 @@ pub enum Error {
     #[error("UUID error: {0}")]
     Uuid(#[from] uuid::Error),
 
+    #[error("Invalid message UUID: {0}")]
+    InvalidUuid(uuid::Error),
+
     #[error("Slice conversion error: {0}")]
     Slice(#[from] std::array::TryFromSliceError),
 
@@ -38,9 +41,23 @@ pub enum Error {
     #[error("Invalid payload type: {0}")]
     InvalidPayloadType(u8),
 
+    #[error("Payload type {0:?} is disabled on this server")]
+    CodecDisabled(crate::message::PayloadType),
+
+    #[error("Invalid payload field '{field}': {reason}")]
+    InvalidPayload { field: String, reason: String },
+
+    #[error(
+        "Unsupported protocol version: start byte {0:#04X} frames carry a 2-byte payload length"
+    )]
+    UnsupportedProtocolVersion(u8),
+
     #[error("Payload length mismatch: expected {expected}, got {actual}")]
     PayloadLengthMismatch { expected: usize, actual: usize },
 
+    #[error("Checksum mismatch: expected {expected:#010X}, got {actual:#010X}")]
+    ChecksumMismatch { expected: u32, actual: u32 },
+
     #[error("Client not found: {0}")]
     ClientNotFound(String),
 
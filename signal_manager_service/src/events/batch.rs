@@ -0,0 +1,118 @@
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+use tracing::warn;
+
+use super::{EventClient, EventMessage, EventResult};
+
+/// Most messages Pub/Sub accepts in a single publish request
+pub const MAX_PUBSUB_BATCH_MESSAGES: usize = 1000;
+
+/// Backend able to deliver several events in one call
+#[async_trait]
+pub trait BatchPublisher: Send + Sync {
+    /// Deliver `events`, reporting which were published and which failed, in their original order
+    async fn publish_batch(&self, events: Vec<EventMessage>) -> EventResult;
+}
+
+/// Buffers published events and hands them to a `BatchPublisher` together, once
+/// `max_messages` have accumulated or every `flush_interval`, whichever comes first.
+/// Flushes run one at a time, so batches reach the publisher in the order their events
+/// were published. Events in a batch that fails are logged and dropped.
+pub struct BatchingEventClient {
+    publisher: Arc<dyn BatchPublisher>,
+    max_messages: usize,
+    buffer: Mutex<Vec<EventMessage>>,
+    flush_lock: tokio::sync::Mutex<()>,
+}
+
+impl BatchingEventClient {
+    /// A client flushing to `publisher` every `max_messages` events, and every `flush_interval`
+    /// when that is non-zero. The interval timer stops once the client is dropped.
+    pub fn new(publisher: Arc<dyn BatchPublisher>, max_messages: usize, flush_interval: Duration) -> Arc<Self> {
+        let client = Arc::new(Self {
+            publisher,
+            max_messages: max_messages.max(1),
+            buffer: Mutex::new(Vec::new()),
+            flush_lock: tokio::sync::Mutex::new(()),
+        });
+
+        if !flush_interval.is_zero() {
+            match tokio::runtime::Handle::try_current() {
+                Ok(runtime) => {
+                    runtime.spawn(flush_periodically(Arc::downgrade(&client), flush_interval));
+                }
+                Err(_) => warn!("[EVENTS] No async runtime, buffered events are flushed only by count and on shutdown"),
+            }
+        }
+        client
+    }
+
+    /// Number of events waiting for the next flush
+    pub fn pending(&self) -> usize {
+        self.buffer.lock().unwrap().len()
+    }
+
+    /// Publish everything currently buffered as one batch
+    async fn flush_buffered(&self) -> EventResult {
+        let _flushing = self.flush_lock.lock().await;
+        let batch = std::mem::take(&mut *self.buffer.lock().unwrap());
+        if batch.is_empty() {
+            return EventResult::default();
+        }
+
+        let size = batch.len();
+        let result = self.publisher.publish_batch(batch).await;
+        if !result.is_success() {
+            warn!("[EVENTS] {} of {} events in batch could not be published", result.failed.len(), size);
+            for failed in &result.failed {
+                warn!("[EVENTS] Dropped {} event {}: {}", failed.event_type, failed.event_id, failed.error);
+            }
+        }
+        result
+    }
+}
+
+async fn flush_periodically(client: Weak<BatchingEventClient>, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        let Some(client) = client.upgrade() else { break };
+        if client.pending() > 0 {
+            client.flush_buffered().await;
+        }
+    }
+}
+
+#[async_trait]
+impl EventClient for BatchingEventClient {
+    /// Buffer `event`, flushing the buffer if it is now full. Fails only if that flush
+    /// left some of the batch undelivered.
+    async fn publish(&self, event: EventMessage) -> Result<(), crate::Error> {
+        let full = {
+            let mut buffer = self.buffer.lock().unwrap();
+            buffer.push(event);
+            buffer.len() >= self.max_messages
+        };
+        if !full {
+            return Ok(());
+        }
+
+        let result = self.flush_buffered().await;
+        if result.is_success() {
+            Ok(())
+        } else {
+            Err(crate::Error::PublishError(format!(
+                "{} of {} events in batch could not be published",
+                result.failed.len(),
+                result.failed.len() + result.published.len()
+            )))
+        }
+    }
+
+    async fn flush(&self) -> EventResult {
+        self.flush_buffered().await
+    }
+}
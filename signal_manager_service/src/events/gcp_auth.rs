@@ -0,0 +1,203 @@
+use async_trait::async_trait;
+use base64::Engine;
+use chrono::{DateTime, Duration, Utc};
+use reqwest::Client;
+use ring::rand::SystemRandom;
+use ring::signature::{RsaKeyPair, RSA_PKCS1_SHA256};
+use serde::Deserialize;
+
+const PUBSUB_SCOPE: &str = "https://www.googleapis.com/auth/pubsub";
+const DEFAULT_TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+const JWT_BEARER_GRANT: &str = "urn:ietf:params:oauth:grant-type:jwt-bearer";
+
+/// OAuth2 access token together with its expiry, when known
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessToken {
+    pub token: String,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl AccessToken {
+    pub fn new(token: impl Into<String>, expires_at: Option<DateTime<Utc>>) -> Self {
+        Self { token: token.into(), expires_at }
+    }
+
+    /// Whether the token has expired or will within `margin`. Tokens without an expiry never do.
+    pub fn expires_within(&self, margin: Duration) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at - margin <= Utc::now())
+    }
+}
+
+/// Source of OAuth2 access tokens for GCP APIs
+#[async_trait]
+pub trait TokenSource: Send + Sync {
+    /// Fetch a fresh bearer token valid for the Pub/Sub scope
+    async fn token(&self) -> Result<AccessToken, crate::Error>;
+}
+
+/// Fixed token, e.g. for the Pub/Sub emulator which ignores auth
+pub struct StaticTokenSource {
+    token: String,
+}
+
+impl StaticTokenSource {
+    pub fn new(token: impl Into<String>) -> Self {
+        Self { token: token.into() }
+    }
+}
+
+#[async_trait]
+impl TokenSource for StaticTokenSource {
+    async fn token(&self) -> Result<AccessToken, crate::Error> {
+        Ok(AccessToken::new(self.token.clone(), None))
+    }
+}
+
+const METADATA_TOKEN_URL: &str =
+    "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token";
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: Option<i64>,
+}
+
+impl TokenResponse {
+    fn into_access_token(self) -> AccessToken {
+        let expires_at = self.expires_in.map(|secs| Utc::now() + Duration::seconds(secs));
+        AccessToken::new(self.access_token, expires_at)
+    }
+}
+
+/// Fetches tokens for the default service account from the GCE/Cloud Run metadata server
+pub struct MetadataTokenSource {
+    http_client: Client,
+}
+
+impl MetadataTokenSource {
+    pub fn new() -> Self {
+        Self { http_client: Client::new() }
+    }
+}
+
+impl Default for MetadataTokenSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl TokenSource for MetadataTokenSource {
+    async fn token(&self) -> Result<AccessToken, crate::Error> {
+        let response = self.http_client
+            .get(METADATA_TOKEN_URL)
+            .header("Metadata-Flavor", "Google")
+            .send()
+            .await
+            .map_err(|e| crate::Error::Auth(format!("Metadata token request failed: {e}")))?;
+
+        if !response.status().is_success() {
+            return Err(crate::Error::Auth(format!("Metadata token request failed with status {}", response.status())));
+        }
+
+        let body: TokenResponse = response.json().await
+            .map_err(|e| crate::Error::Auth(format!("Invalid metadata token response: {e}")))?;
+        Ok(body.into_access_token())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ServiceAccountKey {
+    client_email: String,
+    private_key: String,
+    #[serde(default)]
+    token_uri: Option<String>,
+}
+
+/// Exchanges a self-signed JWT for access tokens using a service account key file,
+/// as found at `config.gcp.credentials_path`
+pub struct ServiceAccountTokenSource {
+    http_client: Client,
+    client_email: String,
+    token_uri: String,
+    key_pair: RsaKeyPair,
+    rng: SystemRandom,
+}
+
+impl ServiceAccountTokenSource {
+    pub fn from_file(path: &str) -> Result<Self, crate::Error> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| crate::Error::Auth(format!("Failed to read service account key {path}: {e}")))?;
+        let key: ServiceAccountKey = serde_json::from_str(&contents)
+            .map_err(|e| crate::Error::Auth(format!("Invalid service account key {path}: {e}")))?;
+
+        let der = pem_to_der(&key.private_key)?;
+        let key_pair = RsaKeyPair::from_pkcs8(&der)
+            .map_err(|e| crate::Error::Auth(format!("Invalid service account private key: {e}")))?;
+
+        Ok(Self {
+            http_client: Client::new(),
+            client_email: key.client_email,
+            token_uri: key.token_uri.unwrap_or_else(|| DEFAULT_TOKEN_URI.to_string()),
+            key_pair,
+            rng: SystemRandom::new(),
+        })
+    }
+
+    fn signed_assertion(&self) -> Result<String, crate::Error> {
+        let engine = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        let now = Utc::now().timestamp();
+        let header = serde_json::json!({"alg": "RS256", "typ": "JWT"});
+        let claims = serde_json::json!({
+            "iss": self.client_email,
+            "scope": PUBSUB_SCOPE,
+            "aud": self.token_uri,
+            "iat": now,
+            "exp": now + 3600,
+        });
+        let signing_input = format!(
+            "{}.{}",
+            engine.encode(serde_json::to_vec(&header)?),
+            engine.encode(serde_json::to_vec(&claims)?)
+        );
+
+        let mut signature = vec![0u8; self.key_pair.public().modulus_len()];
+        self.key_pair
+            .sign(&RSA_PKCS1_SHA256, &self.rng, signing_input.as_bytes(), &mut signature)
+            .map_err(|_| crate::Error::Auth("Failed to sign service account assertion".to_string()))?;
+
+        Ok(format!("{}.{}", signing_input, engine.encode(signature)))
+    }
+}
+
+#[async_trait]
+impl TokenSource for ServiceAccountTokenSource {
+    async fn token(&self) -> Result<AccessToken, crate::Error> {
+        let assertion = self.signed_assertion()?;
+        let response = self.http_client
+            .post(&self.token_uri)
+            .form(&[("grant_type", JWT_BEARER_GRANT), ("assertion", assertion.as_str())])
+            .send()
+            .await
+            .map_err(|e| crate::Error::Auth(format!("Service account token request failed: {e}")))?;
+
+        if !response.status().is_success() {
+            return Err(crate::Error::Auth(format!("Service account token request failed with status {}", response.status())));
+        }
+
+        let body: TokenResponse = response.json().await
+            .map_err(|e| crate::Error::Auth(format!("Invalid service account token response: {e}")))?;
+        Ok(body.into_access_token())
+    }
+}
+
+fn pem_to_der(pem: &str) -> Result<Vec<u8>, crate::Error> {
+    let body: String = pem
+        .lines()
+        .filter(|line| !line.starts_with("-----"))
+        .map(str::trim)
+        .collect();
+    base64::engine::general_purpose::STANDARD
+        .decode(body)
+        .map_err(|e| crate::Error::Auth(format!("Invalid PEM private key: {e}")))
+}
@@ -0,0 +1,200 @@
+use async_trait::async_trait;
+use base64::Engine;
+use reqwest::Client;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{debug, error, warn};
+
+use super::gcp_auth::{AccessToken, TokenSource};
+use super::{BatchPublisher, EventClient, EventMessage, EventResult, FailedEvent};
+use crate::backoff::BackoffConfig;
+use crate::config::Config;
+
+/// Publishes events to GCP Pub/Sub through its REST API.
+/// The destination topic is chosen per event type, falling back to the default topic.
+/// Access tokens are cached and refreshed shortly before they expire; a publish rejected
+/// with 401 is retried once with a freshly fetched token, and transient failures
+/// (transport errors, 429, 5xx) are retried with `events.publish_retry` backoff.
+pub struct GcpPubSubClient {
+    http_client: Client,
+    endpoint: String,
+    project_id: String,
+    default_topic: String,
+    topics: HashMap<String, String>,
+    token_source: Arc<dyn TokenSource>,
+    cached_token: Mutex<Option<AccessToken>>,
+    refresh_margin: chrono::Duration,
+    publish_retry: BackoffConfig,
+    instance_id: String,
+}
+
+impl GcpPubSubClient {
+    pub fn new(config: &Config, token_source: Arc<dyn TokenSource>) -> Result<Self, crate::Error> {
+        let http_client = Client::builder()
+            .timeout(std::time::Duration::from_secs(30))
+            .build()
+            .map_err(|e| crate::Error::PublishError(format!("Failed to build HTTP client: {e}")))?;
+
+        Ok(Self {
+            http_client,
+            endpoint: config.events.pubsub_endpoint.trim_end_matches('/').to_string(),
+            project_id: config.gcp.project_id.clone(),
+            default_topic: config.events.default_topic.clone(),
+            topics: config.events.topics.clone(),
+            token_source,
+            cached_token: Mutex::new(None),
+            refresh_margin: chrono::Duration::seconds(config.events.token_refresh_margin_secs as i64),
+            publish_retry: config.events.publish_retry.clone(),
+            instance_id: config.server.instance_id.clone(),
+        })
+    }
+
+    /// Topic an event type is published to
+    pub fn topic_for(&self, event_type: &str) -> &str {
+        self.topics.get(event_type).map(String::as_str).unwrap_or(&self.default_topic)
+    }
+
+    fn publish_url(&self, topic: &str) -> String {
+        format!("{}/projects/{}/topics/{}:publish", self.endpoint, self.project_id, topic)
+    }
+
+    /// Cached access token, fetching a new one when absent or close to expiry
+    async fn access_token(&self) -> Result<String, crate::Error> {
+        let mut cached = self.cached_token.lock().await;
+        if let Some(token) = cached.as_ref() {
+            if !token.expires_within(self.refresh_margin) {
+                return Ok(token.token.clone());
+            }
+            debug!("[EVENTS] Pub/Sub access token expiring, refreshing");
+        }
+
+        let token = self.token_source.token().await?;
+        let value = token.token.clone();
+        *cached = Some(token);
+        Ok(value)
+    }
+
+    async fn invalidate_token(&self) {
+        *self.cached_token.lock().await = None;
+    }
+
+    /// Publish once, refreshing the access token and retrying once if it was rejected
+    async fn send_authenticated_publish(&self, topic: &str, body: &serde_json::Value) -> Result<reqwest::Response, crate::Error> {
+        let response = self.send_publish(topic, body).await?;
+        if response.status() != reqwest::StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+        warn!("[EVENTS] Pub/Sub rejected access token for topic {}, refreshing and retrying", topic);
+        self.invalidate_token().await;
+        self.send_publish(topic, body).await
+    }
+
+    async fn send_publish(&self, topic: &str, body: &serde_json::Value) -> Result<reqwest::Response, crate::Error> {
+        let token = self.access_token().await?;
+        self.http_client
+            .post(self.publish_url(topic))
+            .bearer_auth(token)
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| crate::Error::PublishError(format!("Pub/Sub request to topic {topic} failed: {e}")))
+    }
+
+    /// Publish `events` to `topic` in a single request, retrying transient failures
+    async fn publish_to_topic(&self, topic: &str, events: &[EventMessage]) -> Result<(), crate::Error> {
+        let messages = events
+            .iter()
+            .map(|event| {
+                Ok(serde_json::json!({
+                    "data": base64::engine::general_purpose::STANDARD.encode(serde_json::to_vec(event)?),
+                    "attributes": {
+                        "event_type": event.event_type,
+                        "event_id": event.id,
+                        "instance_id": event.instance_id,
+                    }
+                }))
+            })
+            .collect::<Result<Vec<_>, crate::Error>>()?;
+        let body = serde_json::json!({ "messages": messages });
+
+        let mut retry_delays = self.publish_retry.delays();
+        let response = loop {
+            let result = self.send_authenticated_publish(topic, &body).await;
+            let transient = match &result {
+                Ok(response) => response.status().is_server_error() || response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS,
+                Err(_) => true,
+            };
+            match retry_delays.next() {
+                Some(delay) if transient => {
+                    warn!("[EVENTS] Pub/Sub publish to {} failed transiently, retrying in {:?}", topic, delay);
+                    tokio::time::sleep(delay).await;
+                }
+                _ => break result?,
+            }
+        };
+
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            error!("[EVENTS] Pub/Sub publish to {} failed: {} {}", topic, status, text);
+            return Err(crate::Error::PublishError(format!("Pub/Sub publish to topic {topic} failed with status {status}")));
+        }
+
+        debug!("[EVENTS] Published {} event(s) to topic {}", events.len(), topic);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl BatchPublisher for GcpPubSubClient {
+    /// One publish request per topic the batch touches, in the order each topic first appears.
+    /// Events keep their relative order within each request.
+    async fn publish_batch(&self, events: Vec<EventMessage>) -> EventResult {
+        let mut groups: Vec<(String, Vec<EventMessage>)> = Vec::new();
+        // Topic group of each event, in the order the events were handed in
+        let mut placement = Vec::with_capacity(events.len());
+        for event in events {
+            let event = event.attributed_to(&self.instance_id);
+            let topic = self.topic_for(&event.event_type);
+            let group = match groups.iter().position(|(t, _)| t == topic) {
+                Some(group) => group,
+                None => {
+                    groups.push((topic.to_string(), Vec::new()));
+                    groups.len() - 1
+                }
+            };
+            placement.push((group, event.id.clone(), event.event_type.clone()));
+            groups[group].1.push(event);
+        }
+
+        let mut outcomes = Vec::with_capacity(groups.len());
+        for (topic, group) in &groups {
+            outcomes.push(self.publish_to_topic(topic, group).await.map_err(|e| match e {
+                crate::Error::PublishError(message) => message,
+                other => other.to_string(),
+            }));
+        }
+
+        let mut result = EventResult::default();
+        for (group, event_id, event_type) in placement {
+            match &outcomes[group] {
+                Ok(()) => result.published.push(event_id),
+                Err(error) => result.failed.push(FailedEvent { event_id, event_type, error: error.clone() }),
+            }
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl EventClient for GcpPubSubClient {
+    /// Publish `event` on its own, bypassing any batching
+    async fn publish(&self, event: EventMessage) -> Result<(), crate::Error> {
+        match self.publish_batch(vec![event]).await.failed.pop() {
+            Some(failed) => Err(crate::Error::PublishError(failed.error)),
+            None => Ok(()),
+        }
+    }
+}
+
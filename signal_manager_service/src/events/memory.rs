@@ -0,0 +1,72 @@
+use async_trait::async_trait;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use tracing::debug;
+
+use super::{EventClient, EventMessage};
+
+/// Event client that keeps the most recent events in a bounded ring buffer.
+/// Intended for tests and local development where GCP is unavailable.
+pub struct InMemoryEventClient {
+    events: Mutex<VecDeque<EventMessage>>,
+    capacity: usize,
+    instance_id: Option<String>,
+}
+
+impl InMemoryEventClient {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            events: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+            instance_id: None,
+        }
+    }
+
+    /// Attribute stored events to `instance_id`, as the config-built client does
+    pub fn with_instance_id(mut self, instance_id: &str) -> Self {
+        self.instance_id = Some(instance_id.to_string());
+        self
+    }
+
+    /// Up to `limit` of the most recent events, oldest first
+    pub fn recent_events(&self, limit: usize) -> Vec<EventMessage> {
+        let events = self.events.lock().unwrap();
+        let skip = events.len().saturating_sub(limit);
+        events.iter().skip(skip).cloned().collect()
+    }
+
+    /// All retained events, oldest first
+    pub fn events(&self) -> Vec<EventMessage> {
+        self.events.lock().unwrap().iter().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.events.lock().unwrap().clear();
+    }
+}
+
+#[async_trait]
+impl EventClient for InMemoryEventClient {
+    async fn publish(&self, event: EventMessage) -> Result<(), crate::Error> {
+        let event = match &self.instance_id {
+            Some(instance_id) => event.attributed_to(instance_id),
+            None => event,
+        };
+        let mut events = self.events.lock().unwrap();
+        if events.len() >= self.capacity {
+            events.pop_front();
+        }
+        debug!("[EVENTS] Stored event {} ({})", event.id, event.event_type);
+        events.push_back(event);
+        Ok(())
+    }
+}
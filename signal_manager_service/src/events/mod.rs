@@ -0,0 +1,179 @@
+pub mod batch;
+pub mod gcp_auth;
+pub mod gcp_pubsub;
+pub mod memory;
+pub mod ordered;
+
+pub use batch::{BatchPublisher, BatchingEventClient, MAX_PUBSUB_BATCH_MESSAGES};
+
+pub use gcp_auth::{AccessToken, MetadataTokenSource, ServiceAccountTokenSource, StaticTokenSource, TokenSource};
+pub use gcp_pubsub::GcpPubSubClient;
+pub use memory::InMemoryEventClient;
+pub use ordered::OrderedEventClient;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, OnceLock};
+use tracing::{warn, Instrument};
+use uuid::Uuid;
+
+use crate::config::Config;
+
+/// `event_type` of the lifecycle events published by the handlers
+pub const CLIENT_REGISTERED: &str = "client_registered";
+pub const CLIENT_UNREGISTERED: &str = "client_unregistered";
+pub const ROOM_CREATED: &str = "room_created";
+pub const ROOM_TERMINATED: &str = "room_terminated";
+pub const CLIENT_JOINED_ROOM: &str = "client_joined_room";
+pub const CLIENT_LEFT_ROOM: &str = "client_left_room";
+
+/// An event emitted by the service for downstream consumers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventMessage {
+    /// Unique identifier for the event
+    pub id: String,
+    /// Event type, e.g. "client_registered" or "room_created"
+    pub event_type: String,
+    /// When the event was produced
+    pub timestamp: DateTime<Utc>,
+    /// Event-specific data
+    pub data: serde_json::Value,
+    /// `server.instance_id` of the instance that emitted the event, set by the event client
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance_id: Option<String>,
+}
+
+impl EventMessage {
+    pub fn new(event_type: impl Into<String>, data: serde_json::Value) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            event_type: event_type.into(),
+            timestamp: Utc::now(),
+            data,
+            instance_id: None,
+        }
+    }
+
+    /// Attribute the event to `instance_id` unless it already names an instance
+    pub fn attributed_to(mut self, instance_id: &str) -> Self {
+        self.instance_id.get_or_insert_with(|| instance_id.to_string());
+        self
+    }
+}
+
+/// An event a batch publish could not deliver
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailedEvent {
+    pub event_id: String,
+    pub event_type: String,
+    pub error: String,
+}
+
+/// Outcome of publishing a batch of events. A batch may partially fail, e.g. when its
+/// events go to several topics and only some of them accept the publish.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EventResult {
+    /// Ids of the events delivered, in the order they were published
+    pub published: Vec<String>,
+    /// Events that could not be delivered, in the order they were published
+    pub failed: Vec<FailedEvent>,
+}
+
+impl EventResult {
+    /// Whether every event in the batch was delivered
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    pub fn merge(&mut self, other: EventResult) {
+        self.published.extend(other.published);
+        self.failed.extend(other.failed);
+    }
+}
+
+/// Sink for service events. Implementations decide where events are delivered.
+#[async_trait]
+pub trait EventClient: Send + Sync {
+    /// Publish a single event
+    async fn publish(&self, event: EventMessage) -> Result<(), crate::Error>;
+
+    /// Deliver any events still buffered by the client. Called on shutdown; clients that
+    /// publish immediately have nothing to flush.
+    async fn flush(&self) -> EventResult {
+        EventResult::default()
+    }
+
+    /// Hand `event` to a queue that publishes it after every event queued before it, without
+    /// waiting. Clients with no such queue hand the event back.
+    fn enqueue(&self, event: EventMessage) -> Result<(), EventMessage> {
+        Err(event)
+    }
+}
+
+/// Event client that drops every event, used by handlers that were not given one
+pub struct NoopEventClient;
+
+#[async_trait]
+impl EventClient for NoopEventClient {
+    async fn publish(&self, _event: EventMessage) -> Result<(), crate::Error> {
+        Ok(())
+    }
+}
+
+/// Publish `event` on `client` without waiting for it, so a slow or unreachable sink never
+/// holds up the handler that produced it. A client that queues events (`OrderedEventClient`)
+/// keeps them in order; others are published from a task per event. Failures are logged and
+/// the event is dropped.
+pub fn publish_in_background(client: &Arc<dyn EventClient>, event: EventMessage) {
+    let Err(event) = client.enqueue(event) else { return };
+    let client = client.clone();
+    tokio::spawn(async move {
+        let event_type = event.event_type.clone();
+        if let Err(e) = client.publish(event).await {
+            warn!("[EVENTS] Failed to publish {} event: {}", event_type, e);
+        }
+    }.in_current_span());
+}
+
+/// Build the event client selected by `config.events.backend`
+pub fn create_event_client(config: &Config) -> Result<Arc<dyn EventClient>, crate::Error> {
+    match config.events.backend.as_str() {
+        "memory" => Ok(Arc::new(
+            InMemoryEventClient::new(config.events.memory_capacity).with_instance_id(&config.server.instance_id),
+        )),
+        "gcp_pubsub" => Ok(BatchingEventClient::new(
+            Arc::new(GcpPubSubClient::new(config, gcp_token_source(config)?)?),
+            config.events.batch_max_messages.clamp(1, MAX_PUBSUB_BATCH_MESSAGES),
+            std::time::Duration::from_millis(config.events.batch_flush_interval_ms),
+        )),
+        other => Err(crate::Error::Config(config::ConfigError::Message(
+            format!("Unsupported events backend: {other}")
+        ))),
+    }
+}
+
+/// The event client `config.events.backend` selects, or a `NoopEventClient` if it can't be built
+pub fn event_client_or_noop(config: &Config) -> Arc<dyn EventClient> {
+    create_event_client(config).unwrap_or_else(|e| {
+        warn!("[EVENTS] Event publisher unavailable, lifecycle events will be dropped: {}", e);
+        Arc::new(NoopEventClient)
+    })
+}
+
+/// Process-wide ordered event client for the free-standing register and unregister handlers,
+/// built from the `config` of the first call
+pub fn shared_event_client(config: &Config) -> Arc<dyn EventClient> {
+    static SHARED: OnceLock<Arc<dyn EventClient>> = OnceLock::new();
+    SHARED.get_or_init(|| OrderedEventClient::new(event_client_or_noop(config))).clone()
+}
+
+/// Service account key from `config.gcp.credentials_path` when present, else the metadata server
+fn gcp_token_source(config: &Config) -> Result<Arc<dyn TokenSource>, crate::Error> {
+    let credentials_path = &config.gcp.credentials_path;
+    if !credentials_path.is_empty() && std::path::Path::new(credentials_path).is_file() {
+        Ok(Arc::new(ServiceAccountTokenSource::from_file(credentials_path)?))
+    } else {
+        Ok(Arc::new(MetadataTokenSource::new()))
+    }
+}
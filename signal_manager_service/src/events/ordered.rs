@@ -0,0 +1,77 @@
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{warn, Instrument};
+
+use super::{EventClient, EventMessage, EventResult};
+
+/// Queues events on a channel drained by a single task, which publishes them to the wrapped
+/// client one at a time. Events therefore reach the sink in the order they were queued, and
+/// queueing never waits on it. The task is started by the first event, so the client can be
+/// built outside an async runtime. `flush` waits for the events queued before it.
+pub struct OrderedEventClient {
+    inner: Arc<dyn EventClient>,
+    sender: mpsc::UnboundedSender<Queued>,
+    receiver: Mutex<Option<mpsc::UnboundedReceiver<Queued>>>,
+}
+
+enum Queued {
+    Event(EventMessage),
+    /// Flush the wrapped client once everything queued earlier is published
+    Flush(oneshot::Sender<EventResult>),
+}
+
+impl OrderedEventClient {
+    pub fn new(inner: Arc<dyn EventClient>) -> Arc<Self> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        Arc::new(Self { inner, sender, receiver: Mutex::new(Some(receiver)) })
+    }
+}
+
+async fn publish_in_order(mut receiver: mpsc::UnboundedReceiver<Queued>, client: Arc<dyn EventClient>) {
+    while let Some(queued) = receiver.recv().await {
+        match queued {
+            Queued::Event(event) => {
+                let event_type = event.event_type.clone();
+                if let Err(e) = client.publish(event).await {
+                    warn!("[EVENTS] Failed to publish {} event: {}", event_type, e);
+                }
+            }
+            Queued::Flush(done) => {
+                let _ = done.send(client.flush().await);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl EventClient for OrderedEventClient {
+    async fn publish(&self, event: EventMessage) -> Result<(), crate::Error> {
+        self.enqueue(event)
+            .map_err(|event| crate::Error::PublishError(format!("Event queue closed, dropped {} event", event.event_type)))
+    }
+
+    fn enqueue(&self, event: EventMessage) -> Result<(), EventMessage> {
+        if let Some(receiver) = self.receiver.lock().unwrap().take() {
+            tokio::spawn(publish_in_order(receiver, self.inner.clone()).in_current_span());
+        }
+        self.sender.send(Queued::Event(event)).map_err(|e| match e.0 {
+            Queued::Event(event) => event,
+            Queued::Flush(_) => unreachable!("only events are sent here"),
+        })
+    }
+
+    /// Wait for every event queued so far to be published, then flush the wrapped client
+    async fn flush(&self) -> EventResult {
+        let started = self.receiver.lock().unwrap().is_none();
+        if started {
+            let (done, flushed) = oneshot::channel();
+            if self.sender.send(Queued::Flush(done)).is_ok() {
+                if let Ok(result) = flushed.await {
+                    return result;
+                }
+            }
+        }
+        self.inner.flush().await
+    }
+}
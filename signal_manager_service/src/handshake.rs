@@ -0,0 +1,73 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Caps how many accepted sockets may be in the TLS/WebSocket handshake at once.
+/// Sockets beyond the cap wait for a slot; once `max_queued` are already waiting,
+/// further sockets are rejected straight away.
+pub struct HandshakeLimiter {
+    permits: Arc<Semaphore>,
+    max_queued: usize,
+    queued: AtomicUsize,
+    in_flight: Arc<AtomicUsize>,
+    peak_in_flight: Arc<AtomicUsize>,
+}
+
+/// A handshake slot; releases it when dropped
+pub struct HandshakeSlot {
+    _permit: OwnedSemaphorePermit,
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl Drop for HandshakeSlot {
+    fn drop(&mut self) {
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl HandshakeLimiter {
+    pub fn new(max_concurrent: usize, max_queued: usize) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(max_concurrent.max(1))),
+            max_queued,
+            queued: AtomicUsize::new(0),
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            peak_in_flight: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Wait up to `wait` for a handshake slot. None when the queue is full or the wait ran out.
+    pub async fn acquire(&self, wait: Duration) -> Option<HandshakeSlot> {
+        let permit = match self.permits.clone().try_acquire_owned() {
+            Ok(permit) => permit,
+            Err(_) => {
+                if self.queued.fetch_add(1, Ordering::SeqCst) >= self.max_queued {
+                    self.queued.fetch_sub(1, Ordering::SeqCst);
+                    return None;
+                }
+                let permit = tokio::time::timeout(wait, self.permits.clone().acquire_owned()).await;
+                self.queued.fetch_sub(1, Ordering::SeqCst);
+                permit.ok()?.ok()?
+            }
+        };
+        let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+        Some(HandshakeSlot { _permit: permit, in_flight: self.in_flight.clone() })
+    }
+
+    /// Handshakes currently holding a slot
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Most handshakes that ever held a slot at the same time
+    pub fn peak_in_flight(&self) -> usize {
+        self.peak_in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Sockets waiting for a slot
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::SeqCst)
+    }
+}
@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+
+/// State of one subsystem, ordered from best to worst
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    Ok,
+    /// Working, but some features that depend on it will fail
+    Degraded,
+    /// The server cannot serve clients
+    Down,
+}
+
+/// Health of one subsystem, with a human-readable reason when it is not `Ok`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComponentHealth {
+    pub status: HealthStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl ComponentHealth {
+    pub fn ok() -> Self {
+        Self { status: HealthStatus::Ok, detail: None }
+    }
+
+    pub fn degraded(detail: impl Into<String>) -> Self {
+        Self { status: HealthStatus::Degraded, detail: Some(detail.into()) }
+    }
+
+    pub fn down(detail: impl Into<String>) -> Self {
+        Self { status: HealthStatus::Down, detail: Some(detail.into()) }
+    }
+}
+
+/// Health of every subsystem the server depends on, as rendered by `/readyz`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthReport {
+    /// Worst status of any component
+    pub status: HealthStatus,
+    pub listener: ComponentHealth,
+    pub routing_task: ComponentHealth,
+    pub repositories: ComponentHealth,
+    pub cloudflare: ComponentHealth,
+    pub event_publisher: ComponentHealth,
+}
+
+impl HealthReport {
+    pub fn new(
+        listener: ComponentHealth,
+        routing_task: ComponentHealth,
+        repositories: ComponentHealth,
+        cloudflare: ComponentHealth,
+        event_publisher: ComponentHealth,
+    ) -> Self {
+        let status = [&listener, &routing_task, &repositories, &cloudflare, &event_publisher]
+            .iter()
+            .map(|component| component.status)
+            .max()
+            .unwrap_or(HealthStatus::Ok);
+        Self { status, listener, routing_task, repositories, cloudflare, event_publisher }
+    }
+
+    /// Whether the server should receive traffic. Degraded components do not make it unready.
+    pub fn is_ready(&self) -> bool {
+        self.status != HealthStatus::Down
+    }
+}
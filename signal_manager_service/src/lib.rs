@@ -1,13 +1,24 @@
+pub mod backoff;
 pub mod config;
+pub mod connection_limit;
 pub mod error;
 pub mod message;
+pub mod outbound;
+pub mod protobuf;
+pub mod rate_limit;
 pub mod server;
 pub mod session;
+pub mod tls;
+pub mod signaling;
 pub mod auth;
 pub mod database;
 pub mod frame_handlers;
+pub mod handshake;
 pub mod type_two_handlers;
 pub mod cloudflare;
+pub mod events;
+pub mod health;
+pub mod metrics;
 pub mod webrtc_handlers;
 
 pub use error::Error;
@@ -1,14 +1,15 @@
 use anyhow::Result;
-use clap::Parser;
-use signal_manager_service::config::{init_config, get_config};
+use clap::{Parser, Subcommand};
+use signal_manager_service::config::{init_config, get_config, run_validate, Config};
 use signal_manager_service::server::WebSocketServer;
-use tracing::{error, info, Level};
+use tracing::{error, info, warn, Level};
 use tracing_subscriber::{fmt, EnvFilter};
 use tracing_appender::rolling::{RollingFileAppender, Rotation};
 use tracing_appender::non_blocking;
 use std::fs;
 use std::path::Path;
 use std::io::{self, Write};
+use tracing::Instrument;
 use tracing_subscriber::fmt::writer::BoxMakeWriter;
 
 struct MultiWriter<W1, W2> {
@@ -36,24 +37,37 @@ impl<W1: Write, W2: Write> Write for MultiWriter<W1, W2> {
 #[command(author, version, about, long_about = None)]
 struct Args {
     /// Configuration file path
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     config: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Load and validate the configuration, print the effective settings and exit 0 if it is
+    /// valid or 1 if not, without starting the server
+    Validate,
 }
 
 fn main() -> Result<()> {
+    // Parse command line arguments
+    let args = Args::parse();
+    if let Some(Command::Validate) = args.command {
+        std::process::exit(run_validate(args.config.as_deref(), &mut io::stdout()));
+    }
+
     // Start the tokio runtime manually
     let runtime = tokio::runtime::Builder::new_multi_thread()
         .enable_all()
         .build()
         .unwrap();
 
-    runtime.block_on(async_main())
+    runtime.block_on(async_main(args))
 }
 
-async fn async_main() -> Result<()> {
-    // Parse command line arguments
-    let args = Args::parse();
-
+async fn async_main(args: Args) -> Result<()> {
     // Initialize configuration
     init_config(args.config.as_deref())?;
     let config = get_config();
@@ -70,36 +84,38 @@ async fn async_main() -> Result<()> {
     }
 
     // Initialize logging based on configuration
-    let log_level = config.logging.level.parse::<Level>().unwrap_or(Level::INFO);
-    let env_filter = EnvFilter::from_default_env()
-        .add_directive(format!("signal_manager_service={log_level}").parse()?);
+    let env_filter = log_filter(&config.logging.level)?;
 
-    let subscriber = if config.logging.file_output && config.logging.console_output {
+    let (filter_handle, subscriber) = if config.logging.file_output && config.logging.console_output {
         let file_appender = RollingFileAppender::new(Rotation::DAILY, "logs", "signal-manager-service.log");
         let (non_blocking, _guard) = non_blocking(file_appender);
-        fmt()
+        let subscriber = fmt()
             .with_env_filter(env_filter)
             .with_writer(BoxMakeWriter::new(move || MultiWriter {
                 w1: std::io::stdout(),
                 w2: non_blocking.clone(),
             }))
-            .finish()
+            .with_filter_reloading();
+        (subscriber.reload_handle(), subscriber.finish())
     } else if config.logging.file_output {
         let file_appender = RollingFileAppender::new(Rotation::DAILY, "logs", "signal-manager-service.log");
         let (non_blocking, _guard) = non_blocking(file_appender);
-        fmt()
+        let subscriber = fmt()
             .with_env_filter(env_filter)
             .with_writer(BoxMakeWriter::new(move || non_blocking.clone()))
-            .finish()
+            .with_filter_reloading();
+        (subscriber.reload_handle(), subscriber.finish())
     } else {
-        fmt()
+        let subscriber = fmt()
             .with_env_filter(env_filter)
             .with_writer(BoxMakeWriter::new(|| std::io::stdout()))
-            .finish()
+            .with_filter_reloading();
+        (subscriber.reload_handle(), subscriber.finish())
     };
     tracing::subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
 
     info!("Starting Signal Manager Service...");
+    info!("Instance id: {}", config.server.instance_id);
     info!("Server will listen on: {}", config.socket_addr());
     info!("Metrics enabled: {}", config.metrics.enabled);
     if config.metrics.enabled {
@@ -108,13 +124,94 @@ async fn async_main() -> Result<()> {
 
     // Create and start the WebSocket server
     let server = WebSocketServer::new(config.clone())?;
-    
+
+    #[cfg(unix)]
+    {
+        let path = args.config.clone().unwrap_or_else(|| "app-config.toml".to_string());
+        let set_log_level = move |level: &str| match log_filter(level) {
+            Ok(filter) => {
+                if let Err(e) = filter_handle.reload(filter) {
+                    warn!("Failed to apply log level {}: {}", level, e);
+                }
+            }
+            Err(e) => warn!("Invalid log level {}: {}", level, e),
+        };
+        tokio::spawn(reload_on_sighup(server.clone(), path, set_log_level).instrument(signal_manager_service::server::instance_span(config)));
+    }
+    #[cfg(not(unix))]
+    drop(filter_handle);
+
     info!("WebSocket server initialized, starting to listen...");
     
-    if let Err(e) = server.run().await {
+    if let Err(e) = server.run_with_shutdown(shutdown_signal()).await {
         error!("Server error: {}", e);
         return Err(e.into());
     }
 
     Ok(())
 }
+
+/// Filter logging this crate at `level`, INFO if unrecognised, on top of `RUST_LOG`
+fn log_filter(level: &str) -> Result<EnvFilter> {
+    let log_level = level.parse::<Level>().unwrap_or(Level::INFO);
+    Ok(EnvFilter::from_default_env().add_directive(format!("signal_manager_service={log_level}").parse()?))
+}
+
+/// On every SIGHUP, load the configuration from `path` again and apply its reloadable
+/// settings to `server`, passing a changed log level to `set_log_level`
+#[cfg(unix)]
+async fn reload_on_sighup(server: WebSocketServer, path: String, set_log_level: impl Fn(&str)) {
+    let mut hangup = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+        Ok(signal) => signal,
+        Err(e) => {
+            warn!("Failed to listen for SIGHUP, configuration reloading is disabled: {}", e);
+            return;
+        }
+    };
+    while hangup.recv().await.is_some() {
+        info!("Received SIGHUP, reloading configuration from {}", path);
+        let reloaded = match Config::load(&path).and_then(|reloaded| reloaded.validate().map(|_| reloaded)) {
+            Ok(reloaded) => reloaded,
+            Err(e) => {
+                error!("Failed to reload configuration, keeping the running one: {}", e);
+                continue;
+            }
+        };
+        let previous_level = server.current_config().logging.level.clone();
+        server.reload_config(&reloaded);
+        let level = server.current_config().logging.level.clone();
+        if level != previous_level {
+            set_log_level(&level);
+        }
+    }
+}
+
+/// Completes on Ctrl-C or, on Unix, SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => info!("Received Ctrl-C, shutting down"),
+        _ = terminate => info!("Received SIGTERM, shutting down"),
+    }
+}
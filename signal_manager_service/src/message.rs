@@ -1,10 +1,187 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use uuid::Uuid;
 use crate::frame_handlers::type2_json;
 
-pub const START_BYTE: u8 = 0xAA;
+pub const START_BYTE: u8 = 0xAC;
+/// Start byte of frames whose header carries a client creation timestamp
+/// (8 bytes, big-endian milliseconds since the Unix epoch) after the UUID
+pub const START_BYTE_TIMESTAMPED: u8 = 0xAD;
+/// Start bytes of the previous protocol version, whose header has a 2-byte payload length.
+/// Such frames are rejected with `Error::UnsupportedProtocolVersion` rather than misread.
+pub const LEGACY_START_BYTE: u8 = 0xAA;
+pub const LEGACY_START_BYTE_TIMESTAMPED: u8 = 0xAB;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// Header bytes before the payload of a frame without a timestamp: start byte, message type,
+/// UUID, payload type and the 4-byte payload length
+pub const HEADER_LENGTH: usize = 23;
+
+/// Size of the CRC32 trailer appended to frames when `server.frame_checksums` is enabled
+pub const CHECKSUM_LENGTH: usize = 4;
+
+/// Reserved high bit of the payload-type byte, set when the payload bytes are zstd-compressed.
+/// The remaining bits still name the encoding of the decompressed payload.
+pub const PAYLOAD_TYPE_ZSTD_FLAG: u8 = 0x80;
+
+/// How frames are encoded and decoded on a connection, taken from the server config
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameOptions {
+    /// Decoded payloads must also pass `Payload::validate`, whatever their encoding
+    pub strict: bool,
+    /// Frames sent end with a big-endian CRC32 of the header and payload, and received frames
+    /// ending with one are verified. Received frames without one are still accepted, so peers
+    /// can start sending the trailer one at a time (see `PeerFraming`).
+    pub checksum: bool,
+    /// Outbound payloads longer than this are compressed with `compression`; 0 never compresses
+    pub compression_threshold: usize,
+    /// How outbound payloads are compressed: none until the peer shows, by sending a
+    /// compressed frame, which compression it decodes (see `PeerFraming`)
+    pub compression: Compression,
+    /// Compressed payloads may inflate to at most this many bytes; 0 falls back to
+    /// `MAX_DECOMPRESSED_PAYLOAD`
+    pub max_decompressed_size: usize,
+    /// Payload encodings that may be encoded or decoded; others fail with `Error::CodecDisabled`
+    pub enabled_codecs: CodecSet,
+}
+
+impl FrameOptions {
+    pub fn from_config(config: &crate::config::Config) -> Self {
+        Self {
+            strict: config.security.strict_payload_validation,
+            checksum: config.server.frame_checksums,
+            compression_threshold: config.server.compression_threshold_bytes,
+            compression: Compression::None,
+            max_decompressed_size: config.server.max_message_size,
+            enabled_codecs: CodecSet::from_payload_types(&config.server.enabled_codecs),
+        }
+    }
+
+    fn decompression_limit(&self) -> usize {
+        match self.max_decompressed_size {
+            0 => MAX_DECOMPRESSED_PAYLOAD,
+            limit => limit,
+        }
+    }
+}
+
+/// Compression applied to outbound payloads above the threshold
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    None,
+    /// zstd, flagged by `PAYLOAD_TYPE_ZSTD_FLAG`; applies to every encoding
+    Zstd,
+    /// gzip, as the `JsonGzip` payload type; applies to JSON payloads only
+    Gzip,
+}
+
+/// What a peer has shown it can decode, learned from the frames it sends. Shared by a
+/// connection's reader, which records each decoded frame, and its writer.
+#[derive(Debug, Default)]
+pub struct PeerFraming {
+    compression: std::sync::atomic::AtomicU8,
+    checksum: std::sync::atomic::AtomicBool,
+}
+
+impl PeerFraming {
+    /// Record the compression and checksum trailer used by `data`, a frame received from the
+    /// peer and decoded
+    pub fn observe(&self, data: &[u8]) {
+        let Some((payload_type_byte, frame_end)) = frame_layout(data) else {
+            return;
+        };
+        if data.len() >= frame_end + CHECKSUM_LENGTH {
+            self.checksum.store(true, std::sync::atomic::Ordering::Relaxed);
+        }
+        let compression = match payload_type_byte {
+            byte if byte & PAYLOAD_TYPE_ZSTD_FLAG != 0 => Compression::Zstd,
+            byte if byte == PayloadType::JsonGzip as u8 => Compression::Gzip,
+            _ => return,
+        };
+        self.compression.store(compression as u8, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// Whether the peer has sent a frame ending with a checksum trailer
+    pub fn checksum(&self) -> bool {
+        self.checksum.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// The compression the peer last sent, `None` until it sends a compressed frame
+    pub fn compression(&self) -> Compression {
+        match self.compression.load(std::sync::atomic::Ordering::Relaxed) {
+            x if x == Compression::Zstd as u8 => Compression::Zstd,
+            x if x == Compression::Gzip as u8 => Compression::Gzip,
+            _ => Compression::None,
+        }
+    }
+
+    /// `options` compressing outbound payloads only the way the peer does, and appending
+    /// checksum trailers only once the peer sends them too
+    pub fn options(&self, options: FrameOptions) -> FrameOptions {
+        FrameOptions {
+            compression: self.compression(),
+            checksum: options.checksum && self.checksum(),
+            ..options
+        }
+    }
+}
+
+/// A frame's UUID bytes, taken as sent; only bytes that cannot form a UUID fail, with `InvalidUuid`
+pub fn parse_uuid(bytes: &[u8]) -> Result<Uuid, crate::Error> {
+    Uuid::from_slice(bytes).map_err(crate::Error::InvalidUuid)
+}
+
+/// Payload-type byte of a frame, and where its payload ends, if it is long enough to carry both
+fn frame_layout(data: &[u8]) -> Option<(u8, usize)> {
+    let timestamp_length = match data.first()? {
+        &START_BYTE_TIMESTAMPED => 8,
+        _ => 0,
+    };
+    let header_length = HEADER_LENGTH + timestamp_length;
+    let length_bytes: [u8; 4] = data.get(header_length - 4..header_length)?.try_into().ok()?;
+    Some((data[18 + timestamp_length], header_length + u32::from_be_bytes(length_bytes) as usize))
+}
+
+/// Set of payload encodings, one bit per `PayloadType`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodecSet(u8);
+
+impl CodecSet {
+    /// Every encoding, the default
+    pub const ALL: CodecSet = CodecSet(u8::MAX);
+
+    pub fn from_payload_types(payload_types: &[PayloadType]) -> Self {
+        Self(payload_types.iter().fold(0, |bits, payload_type| bits | Self::bit(*payload_type)))
+    }
+
+    pub fn contains(self, payload_type: PayloadType) -> bool {
+        self.0 & Self::bit(payload_type) != 0
+    }
+
+    fn bit(payload_type: PayloadType) -> u8 {
+        1 << (payload_type as u8 - 1)
+    }
+}
+
+impl Default for CodecSet {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+/// Compressed payloads are rejected if they inflate past this when no other limit is
+/// configured, so a small frame can't expand into a huge allocation
+pub const MAX_DECOMPRESSED_PAYLOAD: usize = 1 << 20;
+
+/// Current wall-clock time in milliseconds since the Unix epoch, as carried in `created_at`
+pub fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum MessageType {
     Connect = 0x01,
@@ -12,6 +189,12 @@ pub enum MessageType {
     Disconnect = 0x03,
     Heartbeat = 0x04,
     HeartbeatAck = 0x05,
+    ServerInfo = 0x06,
+    ServerInfoAck = 0x07,
+    WhereAmI = 0x08,
+    WhereAmIAck = 0x09,
+    ClientStatusQuery = 0x0A,
+    ClientStatusQueryAck = 0x0B,
     SignalOffer = 0x10,
     SignalAnswer = 0x11,
     SignalIceCandidate = 0x12,
@@ -25,6 +208,11 @@ pub enum MessageType {
     WebRTCRoomJoinAck = 0x33,
     WebRTCRoomLeave = 0x34,
     WebRTCRoomLeaveAck = 0x35,
+    WebRTCRoomList = 0x36,
+    WebRTCRoomListAck = 0x37,
+    PeerJoined = 0x38,
+    PeerLeft = 0x39,
+    AppRelay = 0x40,
     Error = 0xFF,
 }
 
@@ -36,6 +224,8 @@ pub enum PayloadType {
     Text = 0x03,
     Protobuf = 0x04,
     Cbor = 0x05,
+    /// JSON compressed with gzip, for large payloads
+    JsonGzip = 0x06,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,6 +235,9 @@ pub struct Message {
     pub uuid: Uuid,
     pub payload_type: PayloadType,
     pub payload: Payload,
+    /// When the sender created the message, in milliseconds since the Unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -54,6 +247,12 @@ pub enum Payload {
     Disconnect(DisconnectPayload),
     Heartbeat(HeartbeatPayload),
     HeartbeatAck(HeartbeatAckPayload),
+    ServerInfo(ServerInfoPayload),
+    ServerInfoAck(ServerInfoAckPayload),
+    WhereAmI(WhereAmIPayload),
+    WhereAmIAck(WhereAmIAckPayload),
+    ClientStatusQuery(ClientStatusQueryPayload),
+    ClientStatusQueryAck(ClientStatusQueryAckPayload),
     SignalOffer(SignalPayload),
     SignalAnswer(SignalPayload),
     SignalIceCandidate(SignalPayload),
@@ -67,6 +266,11 @@ pub enum Payload {
     WebRTCRoomJoinAck(WebRTCRoomJoinAckPayload),
     WebRTCRoomLeave(WebRTCRoomLeavePayload),
     WebRTCRoomLeaveAck(WebRTCRoomLeaveAckPayload),
+    WebRTCRoomList(WebRTCRoomListPayload),
+    WebRTCRoomListAck(WebRTCRoomListAckPayload),
+    PeerJoined(PeerPayload),
+    PeerLeft(PeerPayload),
+    AppRelay(AppRelayPayload),
     Error(ErrorPayload),
 }
 
@@ -80,6 +284,12 @@ pub struct ConnectPayload {
 pub struct ConnectAckPayload {
     pub status: String,
     pub session_id: String,
+    /// Seconds between the heartbeats the server expects (`server.heartbeat_interval`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heartbeat_interval: Option<u64>,
+    /// Seconds without a heartbeat before the session is ended; absent when sessions never expire
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_timeout: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -98,10 +308,80 @@ pub struct HeartbeatAckPayload {
     pub timestamp: u64,
 }
 
+/// Capability query; may be sent before Connect
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ServerInfoPayload {}
+
+/// What the server supports, derived from its running config
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerInfoAckPayload {
+    pub server_version: String,
+    pub max_message_size: usize,
+    pub max_frame_size: usize,
+    pub payload_types: Vec<PayloadType>,
+    pub message_types: Vec<MessageType>,
+    pub tls_enabled: bool,
+    pub compression_enabled: bool,
+}
+
+/// Query for the rooms the authenticated client is currently in
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WhereAmIPayload {}
+
+/// Rooms the client is currently in; empty when it is in none
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WhereAmIAckPayload {
+    pub client_id: String,
+    pub rooms: Vec<ClientRoom>,
+}
+
+/// Query whether another client is currently connected
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientStatusQueryPayload {
+    pub client_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientStatusQueryAckPayload {
+    pub client_id: String,
+    /// Whether the client has a live connection to this server
+    pub online: bool,
+    /// Milliseconds since the Unix epoch of the client's last heartbeat while online, or of its
+    /// disconnect while offline; absent for clients not seen since the server started
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientRoom {
+    pub room_id: String,
+    /// "sender", "receiver" or "observer"; absent when the client holds no WebRTC role in the room
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SignalPayload {
     pub target_client_id: String,
     pub signal_data: String,
+    /// Room the signal is relayed within; enables per-room sequencing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub room_id: Option<String>,
+    /// Per-(room, sender) sequence number assigned by the server on relay
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<u64>,
+}
+
+impl SignalPayload {
+    /// Whether `signal_data` is valid standard (padded) base64
+    pub fn has_base64_signal_data(&self) -> bool {
+        use base64::Engine;
+        base64::engine::general_purpose::STANDARD.decode(&self.signal_data).is_ok()
+    }
+
+    pub fn validate(&self) -> Result<(), crate::Error> {
+        require_non_empty("target_client_id", &self.target_client_id)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -137,10 +417,61 @@ pub struct UnregisterAckPayload {
     pub client_id: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ErrorPayload {
     pub error_code: u8,
     pub error_message: String,
+    /// Request field a validation failure refers to, e.g. "client_id"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    /// Why the field was rejected, under "reason", plus any failure-specific entries
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<BTreeMap<String, String>>,
+    /// HTTP-style status of the failure, e.g. 400 for a rejected payload, when it has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+}
+
+impl ErrorPayload {
+    /// A validation failure naming the offending `field` and the `reason` it was rejected
+    pub fn for_field(error_code: u8, error_message: impl Into<String>, field: &str, reason: &str) -> Self {
+        Self {
+            error_code,
+            error_message: error_message.into(),
+            field: Some(field.to_string()),
+            details: Some(BTreeMap::from([("reason".to_string(), reason.to_string())])),
+            status: None,
+        }
+    }
+
+    /// The 400 reply to a request that failed validation: code 11 (invalid payload),
+    /// naming the field and reason when `error` is `Error::InvalidPayload`
+    pub fn invalid_payload(error: &crate::Error) -> Self {
+        Self { status: Some(400), ..Self::from_error(11, error) }
+    }
+
+    /// Protocol error code for the HTTP-style `status` a register or room handler failed with:
+    /// 400 is 11 (invalid payload), 401 is 1 (authentication failed), 403 is 8 (forbidden),
+    /// 404 is 18 (not found), 409 is 19 (conflict), 503 is 20 (unavailable) and anything else 21
+    pub fn code_for_status(status: u16) -> u8 {
+        match status {
+            400 => 11,
+            401 => 1,
+            403 => 8,
+            404 => 18,
+            409 => 19,
+            503 => 20,
+            _ => 21,
+        }
+    }
+
+    /// An error carrying `error`'s message, and its field and reason when it is `Error::InvalidPayload`
+    pub fn from_error(error_code: u8, error: &crate::Error) -> Self {
+        match error {
+            crate::Error::InvalidPayload { field, reason } => Self::for_field(error_code, error.to_string(), field, reason),
+            other => Self { error_code, error_message: other.to_string(), ..Default::default() },
+        }
+    }
 }
 
 // WebRTC Room Management Payloads
@@ -151,6 +482,9 @@ pub struct WebRTCRoomCreatePayload {
     pub auth_token: String,
     pub role: String, // "sender" or "receiver"
     pub offer_sdp: Option<String>, // Required for sender
+    /// Senders and receivers the room admits; the server default of 2 when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_participants: Option<u32>,
     pub metadata: Option<serde_json::Value>,
 }
 
@@ -164,6 +498,9 @@ pub struct WebRTCRoomCreateAckPayload {
     pub app_id: Option<String>,
     pub stun_url: Option<String>,
     pub connection_info: Option<serde_json::Value>,
+    /// Short-lived TURN servers with credentials, when the provider issues them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub turn_servers: Option<Vec<IceServer>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -172,7 +509,7 @@ pub struct WebRTCRoomJoinPayload {
     pub client_id: String,
     pub auth_token: String,
     pub room_id: String,
-    pub role: String, // "sender" or "receiver"
+    pub role: String, // "sender", "receiver" or "observer"
     pub offer_sdp: Option<String>, // Required for sender
     pub metadata: Option<serde_json::Value>,
 }
@@ -187,6 +524,28 @@ pub struct WebRTCRoomJoinAckPayload {
     pub app_id: Option<String>,
     pub stun_url: Option<String>,
     pub connection_info: Option<serde_json::Value>,
+    /// Short-lived TURN servers with credentials, when the provider issues them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub turn_servers: Option<Vec<IceServer>>,
+    /// Other clients already present in the room, excluding the joiner
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub participants: Option<Vec<RoomParticipant>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoomParticipant {
+    pub client_id: String,
+    pub role: String, // "sender", "receiver" or "observer"
+}
+
+/// ICE server offered in room acks, in the shape of the browser `RTCIceServer` dictionary
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IceServer {
+    pub urls: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -207,6 +566,317 @@ pub struct WebRTCRoomLeaveAckPayload {
     pub client_id: Option<String>,
 }
 
+/// Operator request for one page of the active rooms; only `security.admin_clients` may send it
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebRTCRoomListPayload {
+    /// Active rooms to skip, oldest first
+    #[serde(default)]
+    pub offset: u32,
+    /// Rooms to return; 0 means the server default page size
+    #[serde(default)]
+    pub limit: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebRTCRoomListAckPayload {
+    pub rooms: Vec<RoomSummary>,
+    /// Active rooms across all pages
+    #[serde(default)]
+    pub total: u32,
+    /// `offset` of the following page; absent on the last page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_offset: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoomSummary {
+    pub room_id: String,
+    /// "active", "inactive", "terminated" or "pending"
+    #[serde(default)]
+    pub status: String,
+    pub sender_client_id: Option<String>,
+    pub receiver_client_id: Option<String>,
+    /// Clients active in the room
+    #[serde(default)]
+    pub participant_count: u32,
+    /// Milliseconds since the Unix epoch
+    pub created_at: u64,
+}
+
+/// Server push telling room members that another client joined or left the room
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerPayload {
+    pub room_id: String,
+    /// Client that joined or left
+    pub client_id: String,
+    /// Role it holds or held in the room: "sender", "receiver" or "observer"
+    pub role: String,
+}
+
+/// Application message relayed to every other member of a room, for apps whose data
+/// channel is not up yet. `data` is opaque to the server and base64 in JSON.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppRelayPayload {
+    pub room_id: String,
+    /// Member that sent the message; set by the server on relay, ignored from clients
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_client_id: Option<String>,
+    #[serde(with = "base64_bytes")]
+    pub data: Vec<u8>,
+}
+
+mod base64_bytes {
+    use base64::Engine;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&base64::engine::general_purpose::STANDARD.encode(data))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        base64::engine::general_purpose::STANDARD.decode(encoded).map_err(serde::de::Error::custom)
+    }
+}
+
+impl Payload {
+    /// Message type this payload variant is normally sent as, for classifying a message
+    /// without cloning it. Handlers may still carry an `Error` under their ack type.
+    pub fn kind(&self) -> MessageType {
+        match self {
+            Payload::Connect(_) => MessageType::Connect,
+            Payload::ConnectAck(_) => MessageType::ConnectAck,
+            Payload::Disconnect(_) => MessageType::Disconnect,
+            Payload::Heartbeat(_) => MessageType::Heartbeat,
+            Payload::HeartbeatAck(_) => MessageType::HeartbeatAck,
+            Payload::ServerInfo(_) => MessageType::ServerInfo,
+            Payload::ServerInfoAck(_) => MessageType::ServerInfoAck,
+            Payload::WhereAmI(_) => MessageType::WhereAmI,
+            Payload::WhereAmIAck(_) => MessageType::WhereAmIAck,
+            Payload::ClientStatusQuery(_) => MessageType::ClientStatusQuery,
+            Payload::ClientStatusQueryAck(_) => MessageType::ClientStatusQueryAck,
+            Payload::SignalOffer(_) => MessageType::SignalOffer,
+            Payload::SignalAnswer(_) => MessageType::SignalAnswer,
+            Payload::SignalIceCandidate(_) => MessageType::SignalIceCandidate,
+            Payload::Register(_) => MessageType::Register,
+            Payload::RegisterAck(_) => MessageType::RegisterAck,
+            Payload::Unregister(_) => MessageType::Unregister,
+            Payload::UnregisterAck(_) => MessageType::UnregisterAck,
+            Payload::WebRTCRoomCreate(_) => MessageType::WebRTCRoomCreate,
+            Payload::WebRTCRoomCreateAck(_) => MessageType::WebRTCRoomCreateAck,
+            Payload::WebRTCRoomJoin(_) => MessageType::WebRTCRoomJoin,
+            Payload::WebRTCRoomJoinAck(_) => MessageType::WebRTCRoomJoinAck,
+            Payload::WebRTCRoomLeave(_) => MessageType::WebRTCRoomLeave,
+            Payload::WebRTCRoomLeaveAck(_) => MessageType::WebRTCRoomLeaveAck,
+            Payload::WebRTCRoomList(_) => MessageType::WebRTCRoomList,
+            Payload::WebRTCRoomListAck(_) => MessageType::WebRTCRoomListAck,
+            Payload::PeerJoined(_) => MessageType::PeerJoined,
+            Payload::PeerLeft(_) => MessageType::PeerLeft,
+            Payload::AppRelay(_) => MessageType::AppRelay,
+            Payload::Error(_) => MessageType::Error,
+        }
+    }
+
+    /// The room a client-sent payload acts in, when it names one
+    pub fn room_id(&self) -> Option<&str> {
+        match self {
+            Payload::SignalOffer(p) | Payload::SignalAnswer(p) | Payload::SignalIceCandidate(p) => p.room_id.as_deref(),
+            Payload::WebRTCRoomJoin(p) => Some(&p.room_id),
+            Payload::WebRTCRoomLeave(p) => Some(&p.room_id),
+            Payload::AppRelay(p) => Some(&p.room_id),
+            _ => None,
+        }
+    }
+
+    /// Check the semantic constraints serde cannot express, such as non-empty ids and
+    /// known roles. Payloads without such constraints always pass.
+    pub fn validate(&self) -> Result<(), crate::Error> {
+        match self {
+            Payload::Connect(p) => p.validate(),
+            Payload::ClientStatusQuery(p) => p.validate(),
+            Payload::SignalOffer(p) | Payload::SignalAnswer(p) | Payload::SignalIceCandidate(p) => {
+                p.validate()
+            }
+            Payload::Register(p) => p.validate(),
+            Payload::Unregister(p) => p.validate(),
+            Payload::WebRTCRoomCreate(p) => p.validate(),
+            Payload::WebRTCRoomJoin(p) => p.validate(),
+            Payload::WebRTCRoomLeave(p) => p.validate(),
+            Payload::AppRelay(p) => p.validate(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check every client and room id a client-sent payload carries with `validate_id`.
+    /// Payloads without ids always pass.
+    pub fn validate_ids(&self, max_length: usize) -> Result<(), crate::Error> {
+        match self {
+            Payload::Connect(p) => validate_id("client_id", &p.client_id, max_length),
+            Payload::ClientStatusQuery(p) => validate_id("client_id", &p.client_id, max_length),
+            Payload::SignalOffer(p) | Payload::SignalAnswer(p) | Payload::SignalIceCandidate(p) => {
+                validate_id("target_client_id", &p.target_client_id, max_length)?;
+                p.room_id.as_deref().map_or(Ok(()), |room_id| validate_id("room_id", room_id, max_length))
+            }
+            Payload::AppRelay(p) => validate_id("room_id", &p.room_id, max_length),
+            Payload::Register(p) => validate_id("client_id", &p.client_id, max_length),
+            Payload::Unregister(p) => validate_id("client_id", &p.client_id, max_length),
+            Payload::WebRTCRoomCreate(p) => validate_id("client_id", &p.client_id, max_length),
+            Payload::WebRTCRoomJoin(p) => {
+                validate_id("client_id", &p.client_id, max_length)?;
+                validate_id("room_id", &p.room_id, max_length)
+            }
+            Payload::WebRTCRoomLeave(p) => {
+                validate_id("client_id", &p.client_id, max_length)?;
+                validate_id("room_id", &p.room_id, max_length)
+            }
+            // These act under the connection's own id, which was checked when it connected
+            Payload::WhereAmI(_) | Payload::WebRTCRoomList(_) => Ok(()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Check an id that is used as a map key and a Firestore document id: at most `max_length`
+/// bytes (0 for no limit), and none of the forms Firestore rejects (`/`, `.`, `..`, `__*__`)
+pub fn validate_id(field: &str, value: &str, max_length: usize) -> Result<(), crate::Error> {
+    let invalid = |reason: String| Err(crate::Error::InvalidPayload { field: field.to_string(), reason });
+    if max_length > 0 && value.len() > max_length {
+        return invalid(format!("must be at most {max_length} bytes"));
+    }
+    if value.contains('/') {
+        return invalid("must not contain '/'".to_string());
+    }
+    if value == "." || value == ".." || (value.len() >= 4 && value.starts_with("__") && value.ends_with("__")) {
+        return invalid("is a reserved id".to_string());
+    }
+    Ok(())
+}
+
+fn require_non_empty(field: &str, value: &str) -> Result<(), crate::Error> {
+    if value.trim().is_empty() {
+        return Err(crate::Error::InvalidPayload {
+            field: field.to_string(),
+            reason: "must not be empty".to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Append `value` behind its one-byte length, refusing values a byte cannot describe
+fn push_short_field(buffer: &mut Vec<u8>, field: &str, value: &str) -> Result<(), crate::Error> {
+    let len = u8::try_from(value.len()).map_err(|_| crate::Error::InvalidPayload {
+        field: field.to_string(),
+        reason: format!("must be at most {} bytes in the binary encoding", u8::MAX),
+    })?;
+    buffer.push(len);
+    buffer.extend_from_slice(value.as_bytes());
+    Ok(())
+}
+
+/// Take one length-prefixed string off the front of `data`; None when it runs past the end
+fn read_short_field(data: &mut &[u8]) -> Option<String> {
+    let (&len, rest) = data.split_first()?;
+    let value = rest.get(..len as usize)?;
+    *data = &rest[len as usize..];
+    Some(String::from_utf8_lossy(value).into_owned())
+}
+
+fn require_role(value: &str, allowed: &[&str]) -> Result<(), crate::Error> {
+    if !allowed.iter().any(|role| value.eq_ignore_ascii_case(role)) {
+        return Err(crate::Error::InvalidPayload {
+            field: "role".to_string(),
+            reason: format!("must be one of {}", allowed.join(", ")),
+        });
+    }
+    Ok(())
+}
+
+impl ConnectPayload {
+    pub fn validate(&self) -> Result<(), crate::Error> {
+        require_non_empty("client_id", &self.client_id)?;
+        require_non_empty("auth_token", &self.auth_token)
+    }
+}
+
+impl ClientStatusQueryPayload {
+    pub fn validate(&self) -> Result<(), crate::Error> {
+        require_non_empty("client_id", &self.client_id)
+    }
+}
+
+impl RegisterPayload {
+    pub fn validate(&self) -> Result<(), crate::Error> {
+        require_non_empty("client_id", &self.client_id)?;
+        require_non_empty("auth_token", &self.auth_token)
+    }
+}
+
+impl UnregisterPayload {
+    pub fn validate(&self) -> Result<(), crate::Error> {
+        require_non_empty("client_id", &self.client_id)?;
+        require_non_empty("auth_token", &self.auth_token)
+    }
+}
+
+impl WebRTCRoomCreatePayload {
+    pub fn validate(&self) -> Result<(), crate::Error> {
+        require_non_empty("client_id", &self.client_id)?;
+        require_non_empty("auth_token", &self.auth_token)?;
+        require_role(&self.role, &["sender", "receiver"])?;
+        if self.role.eq_ignore_ascii_case("sender")
+            && self
+                .offer_sdp
+                .as_deref()
+                .is_none_or(|sdp| sdp.trim().is_empty())
+        {
+            return Err(crate::Error::InvalidPayload {
+                field: "offer_sdp".to_string(),
+                reason: "required for the sender role".to_string(),
+            });
+        }
+        if self.max_participants == Some(0) {
+            return Err(crate::Error::InvalidPayload {
+                field: "max_participants".to_string(),
+                reason: "must be at least 1".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Reject a `max_participants` above the server's `webrtc.max_room_participants`
+    pub fn validate_max_participants(&self, limit: u32) -> Result<(), crate::Error> {
+        match self.max_participants {
+            Some(max_participants) if max_participants > limit => Err(crate::Error::InvalidPayload {
+                field: "max_participants".to_string(),
+                reason: format!("must be at most {limit}"),
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl WebRTCRoomJoinPayload {
+    pub fn validate(&self) -> Result<(), crate::Error> {
+        require_non_empty("client_id", &self.client_id)?;
+        require_non_empty("auth_token", &self.auth_token)?;
+        require_non_empty("room_id", &self.room_id)?;
+        require_role(&self.role, &["sender", "receiver", "observer"])
+    }
+}
+
+impl WebRTCRoomLeavePayload {
+    pub fn validate(&self) -> Result<(), crate::Error> {
+        require_non_empty("client_id", &self.client_id)?;
+        require_non_empty("room_id", &self.room_id)
+    }
+}
+
+impl AppRelayPayload {
+    pub fn validate(&self) -> Result<(), crate::Error> {
+        require_non_empty("room_id", &self.room_id)
+    }
+}
+
 impl Message {
     pub fn new(message_type: MessageType, payload: Payload) -> Self {
         Self {
@@ -214,77 +884,220 @@ impl Message {
             uuid: Uuid::new_v4(),
             payload_type: PayloadType::Json,
             payload,
+            created_at: None,
         }
     }
 
+    /// Stamp the message with its creation time in milliseconds since the Unix epoch
+    pub fn with_created_at(mut self, created_at: u64) -> Self {
+        self.created_at = Some(created_at);
+        self
+    }
+
+    /// Milliseconds elapsed between creation and `now_ms`; None when unstamped or when the
+    /// sender's clock runs ahead of `now_ms`
+    pub fn latency_ms(&self, now_ms: u64) -> Option<u64> {
+        self.created_at.and_then(|created_at| now_ms.checked_sub(created_at))
+    }
+
+    /// Milliseconds between creation and `now_ms` in either direction; None when unstamped
+    pub fn clock_skew_ms(&self, now_ms: u64) -> Option<u64> {
+        self.created_at.map(|created_at| now_ms.abs_diff(created_at))
+    }
+
     pub fn to_binary(&self) -> Result<Vec<u8>, crate::Error> {
-        let mut buffer = Vec::new();
-        
-        // Start byte
-        buffer.push(START_BYTE);
-        
-        // Message type
-        buffer.push(self.message_type as u8);
-        
-        // UUID (16 bytes)
-        buffer.extend_from_slice(self.uuid.as_bytes());
-        
-        // Payload type
-        buffer.push(self.payload_type as u8);
-        
+        Ok(self.frame(self.payload_type as u8, &self.encode_payload()?))
+    }
+
+    fn encode_payload(&self) -> Result<Vec<u8>, crate::Error> {
         // Serialize payload based on type
         let payload_bytes = match &self.payload_type {
             PayloadType::Json => {
                 
                 serde_json::to_vec(&self.payload)?
             }
+            PayloadType::JsonGzip => {
+                gzip(&serde_json::to_vec(&self.payload)?)?
+            }
             PayloadType::Binary => {
                 self.payload_to_binary()?
             }
             PayloadType::Text => {
                 self.payload_to_text()?.into_bytes()
             }
-            _ => return Err(crate::Error::MessageParse("Unsupported payload type".to_string())),
+            PayloadType::Protobuf => {
+                crate::protobuf::encode_payload(&self.payload)?
+            }
+            PayloadType::Cbor => {
+                cbor(&self.payload)?
+            }
+        };
+        Ok(payload_bytes)
+    }
+
+    /// Like `to_binary`, but a payload longer than `threshold` bytes once serialized is
+    /// zstd-compressed and flagged with `PAYLOAD_TYPE_ZSTD_FLAG`. A threshold of 0 never
+    /// compresses, and `JsonGzip` payloads are already compressed.
+    pub fn to_binary_compressed(&self, threshold: usize) -> Result<Vec<u8>, crate::Error> {
+        let payload_bytes = self.encode_payload()?;
+        if threshold == 0 || payload_bytes.len() <= threshold || self.payload_type == PayloadType::JsonGzip {
+            return Ok(self.frame(self.payload_type as u8, &payload_bytes));
+        }
+        let compressed = zstd::bulk::compress(&payload_bytes, 0)?;
+        Ok(self.frame(self.payload_type as u8 | PAYLOAD_TYPE_ZSTD_FLAG, &compressed))
+    }
+
+    /// Encode with `options`: compressed per `options.compression` above the threshold, then
+    /// followed by the CRC32 trailer when checksums are enabled. Fails with `CodecDisabled` when the
+    /// message's payload type is not among `options.enabled_codecs`.
+    pub fn to_binary_with(&self, options: FrameOptions) -> Result<Vec<u8>, crate::Error> {
+        if !options.enabled_codecs.contains(self.payload_type) {
+            return Err(crate::Error::CodecDisabled(self.payload_type));
+        }
+        let mut buffer = match options.compression {
+            Compression::None => self.to_binary()?,
+            Compression::Zstd => self.to_binary_compressed(options.compression_threshold)?,
+            Compression::Gzip => self.to_binary_gzipped(options.compression_threshold)?,
         };
+        if options.checksum {
+            let checksum = crc32fast::hash(&buffer);
+            buffer.extend_from_slice(&checksum.to_be_bytes());
+        }
+        Ok(buffer)
+    }
+
+    /// Like `to_binary`, but a JSON payload longer than `threshold` bytes once serialized is
+    /// sent gzip-compressed as `JsonGzip`. Other encodings, and a threshold of 0, are sent as is.
+    pub fn to_binary_gzipped(&self, threshold: usize) -> Result<Vec<u8>, crate::Error> {
+        let payload_bytes = self.encode_payload()?;
+        if threshold == 0 || payload_bytes.len() <= threshold || self.payload_type != PayloadType::Json {
+            return Ok(self.frame(self.payload_type as u8, &payload_bytes));
+        }
+        Ok(self.frame(PayloadType::JsonGzip as u8, &gzip(&payload_bytes)?))
+    }
+
+    fn frame(&self, payload_type: u8, payload_bytes: &[u8]) -> Vec<u8> {
+        let mut buffer = Vec::new();
         
-        // Payload length (2 bytes, big endian)
-        let length = payload_bytes.len() as u16;
+        // Start byte
+        buffer.push(if self.created_at.is_some() { START_BYTE_TIMESTAMPED } else { START_BYTE });
+        
+        // Message type
+        buffer.push(self.message_type as u8);
+        
+        // UUID (16 bytes)
+        buffer.extend_from_slice(self.uuid.as_bytes());
+        
+        // Creation timestamp (8 bytes, big endian), only on timestamped frames
+        if let Some(created_at) = self.created_at {
+            buffer.extend_from_slice(&created_at.to_be_bytes());
+        }
+        
+        // Payload type
+        buffer.push(payload_type);
+        
+        // Payload length (4 bytes, big endian)
+        let length = payload_bytes.len() as u32;
         buffer.extend_from_slice(&length.to_be_bytes());
         
         // Payload
-        buffer.extend_from_slice(&payload_bytes);
+        buffer.extend_from_slice(payload_bytes);
         
-        Ok(buffer)
+        buffer
     }
 
     pub fn from_binary(data: &[u8]) -> Result<Self, crate::Error> {
-        if data.len() < 22 {
+        Self::from_binary_with(data, FrameOptions::default())
+    }
+
+    /// Like `from_binary`, but payloads must also pass `Payload::validate`, so
+    /// structurally valid but empty payloads are rejected before reaching a handler
+    pub fn from_binary_strict(data: &[u8]) -> Result<Self, crate::Error> {
+        Self::from_binary_with(
+            data,
+            FrameOptions {
+                strict: true,
+                ..FrameOptions::default()
+            },
+        )
+    }
+
+    /// Decode with `options`. With checksums enabled a frame without a trailer is accepted,
+    /// one cut short fails with `PayloadLengthMismatch`, and one whose bytes changed fails
+    /// with `ChecksumMismatch`.
+    /// A payload type missing from `options.enabled_codecs` fails with `CodecDisabled`
+    /// before the payload is read, and UUID bytes that do not parse fail with `InvalidUuid`.
+    pub fn from_binary_with(data: &[u8], options: FrameOptions) -> Result<Self, crate::Error> {
+        let strict = options.strict;
+        let timestamp_length = match data.first() {
+            Some(&START_BYTE) => 0,
+            Some(&START_BYTE_TIMESTAMPED) => 8,
+            Some(&start @ (LEGACY_START_BYTE | LEGACY_START_BYTE_TIMESTAMPED)) => {
+                return Err(crate::Error::UnsupportedProtocolVersion(start));
+            }
+            Some(_) => return Err(crate::Error::MessageParse("Invalid start byte".to_string())),
+            None => return Err(crate::Error::MessageParse("Message too short".to_string())),
+        };
+
+        if data.len() < HEADER_LENGTH + 1 {
             return Err(crate::Error::MessageParse("Message too short".to_string()));
         }
+        let header_length = HEADER_LENGTH + timestamp_length;
 
-        if data[0] != START_BYTE {
-            return Err(crate::Error::MessageParse("Invalid start byte".to_string()));
+        if data.len() < header_length + 1 {
+            return Err(crate::Error::MessageParse("Message too short".to_string()));
         }
 
         let message_type = MessageType::from_u8(data[1])?;
-        let uuid = Uuid::from_slice(&data[2..18])?;
-        let payload_type = PayloadType::from_u8(data[18])?;
+        let uuid = parse_uuid(&data[2..18])?;
+        let created_at = (timestamp_length > 0).then(|| {
+            let mut timestamp_bytes = [0u8; 8];
+            timestamp_bytes.copy_from_slice(&data[18..26]);
+            u64::from_be_bytes(timestamp_bytes)
+        });
+        let payload_type_byte = data[18 + timestamp_length];
+        let payload_type = PayloadType::from_u8(payload_type_byte & !PAYLOAD_TYPE_ZSTD_FLAG)?;
+        if !options.enabled_codecs.contains(payload_type) {
+            return Err(crate::Error::CodecDisabled(payload_type));
+        }
         
-        let length_bytes = [data[19], data[20]];
-        let payload_length = u16::from_be_bytes(length_bytes) as usize;
+        let length_bytes: [u8; 4] = data[19 + timestamp_length..header_length].try_into()?;
+        let payload_length = u32::from_be_bytes(length_bytes) as usize;
         
-        if data.len() < 21 + payload_length {
+        // With checksums on, a frame either ends at its payload (a peer not sending trailers
+        // yet) or carries a whole trailer; anything in between was cut short
+        let frame_end = header_length + payload_length;
+        let has_trailer = options.checksum && data.len() > frame_end;
+        let checksum_length = if has_trailer { CHECKSUM_LENGTH } else { 0 };
+        if data.len() < frame_end + checksum_length {
             return Err(crate::Error::PayloadLengthMismatch {
-                expected: 21 + payload_length,
+                expected: frame_end + checksum_length,
                 actual: data.len(),
             });
         }
 
-        let payload_data = &data[21..21 + payload_length];
+        if has_trailer {
+            let expected = u32::from_be_bytes(data[frame_end..frame_end + CHECKSUM_LENGTH].try_into()?);
+            let actual = crc32fast::hash(&data[..frame_end]);
+            if expected != actual {
+                return Err(crate::Error::ChecksumMismatch { expected, actual });
+            }
+        }
+
+        let payload_data = &data[header_length..header_length + payload_length];
+        let decompressed;
+        let payload_data = if payload_type_byte & PAYLOAD_TYPE_ZSTD_FLAG != 0 {
+            decompressed = unzstd(payload_data, options.decompression_limit())?;
+            decompressed.as_slice()
+        } else {
+            payload_data
+        };
         let payload = match payload_type {
             PayloadType::Json => {
-                let payload: Payload = serde_json::from_slice(payload_data)?;
-                payload
+                serde_json::from_slice(payload_data)?
+            }
+            PayloadType::JsonGzip => {
+                serde_json::from_slice(&gunzip(payload_data, options.decompression_limit())?)?
             }
             PayloadType::Binary => {
                 Self::payload_from_binary(payload_data, message_type)?
@@ -293,14 +1106,24 @@ impl Message {
                 let text = String::from_utf8_lossy(payload_data);
                 Self::payload_from_text(&text, message_type)?
             }
-            _ => return Err(crate::Error::MessageParse("Unsupported payload type".to_string())),
+            PayloadType::Protobuf => {
+                crate::protobuf::decode_payload(payload_data, message_type)?
+            }
+            PayloadType::Cbor => {
+                uncbor(payload_data)?
+            }
         };
+        // Every codec yields the same `Payload`, so strict checks do not depend on the encoding
+        if strict {
+            payload.validate()?;
+        }
 
         Ok(Self {
             message_type,
             uuid,
             payload_type,
             payload,
+            created_at,
         })
     }
 
@@ -316,28 +1139,25 @@ impl Message {
                 Ok(buffer)
             }
             Payload::Register(p) => {
+                // [version][client_id][auth_token][capability count][capabilities...][metadata JSON],
+                // each string behind its one-byte length; no metadata is a zero length
                 let mut buffer = Vec::new();
-                buffer.push(p.version.len() as u8);
-                buffer.extend_from_slice(p.version.as_bytes());
-                buffer.push(p.client_id.len() as u8);
-                buffer.extend_from_slice(p.client_id.as_bytes());
-                buffer.push(p.auth_token.len() as u8);
-                buffer.extend_from_slice(p.auth_token.as_bytes());
-                if let Some(capabilities) = &p.capabilities {
-                    buffer.push(capabilities.len() as u8);
-                    for cap in capabilities {
-                        buffer.extend_from_slice(cap.as_bytes());
-                    }
-                } else {
-                    buffer.push(0);
-                }
-                if let Some(metadata) = &p.metadata {
-                    let json = serde_json::to_vec(metadata)?;
-                    buffer.push(json.len() as u8);
-                    buffer.extend_from_slice(&json);
-                } else {
-                    buffer.push(0);
+                push_short_field(&mut buffer, "version", &p.version)?;
+                push_short_field(&mut buffer, "client_id", &p.client_id)?;
+                push_short_field(&mut buffer, "auth_token", &p.auth_token)?;
+                let capabilities = p.capabilities.as_deref().unwrap_or_default();
+                buffer.push(u8::try_from(capabilities.len()).map_err(|_| crate::Error::InvalidPayload {
+                    field: "capabilities".to_string(),
+                    reason: format!("must have at most {} entries in the binary encoding", u8::MAX),
+                })?);
+                for capability in capabilities {
+                    push_short_field(&mut buffer, "capabilities", capability)?;
                 }
+                let metadata = match &p.metadata {
+                    Some(metadata) => serde_json::to_string(metadata)?,
+                    None => String::new(),
+                };
+                push_short_field(&mut buffer, "metadata", &metadata)?;
                 Ok(buffer)
             }
             Payload::Unregister(p) => {
@@ -350,6 +1170,15 @@ impl Message {
                 buffer.extend_from_slice(p.auth_token.as_bytes());
                 Ok(buffer)
             }
+            Payload::AppRelay(p) => {
+                // [room_id len][room_id][from_client_id len, 0 when absent][from_client_id][data...]
+                let from_client_id = p.from_client_id.as_deref().unwrap_or("");
+                let mut buffer = Vec::new();
+                push_short_field(&mut buffer, "room_id", &p.room_id)?;
+                push_short_field(&mut buffer, "from_client_id", from_client_id)?;
+                buffer.extend_from_slice(&p.data);
+                Ok(buffer)
+            }
             _ => Err(crate::Error::MessageParse("Binary serialization not implemented".to_string())),
         }
     }
@@ -389,51 +1218,25 @@ impl Message {
                 Ok(Payload::Connect(ConnectPayload { client_id, auth_token }))
             }
             MessageType::Register => {
-                if data.len() < 2 {
-                    return Err(crate::Error::MessageParse("Invalid register payload".to_string()));
-                }
-                let version_len = data[0] as usize;
-                if data.len() < 1 + version_len + 1 {
-                    return Err(crate::Error::MessageParse("Invalid register payload".to_string()));
-                }
-                let version = String::from_utf8_lossy(&data[1..1 + version_len]).to_string();
-                let client_id_len = data[1 + version_len] as usize;
-                if data.len() < 1 + version_len + 1 + client_id_len + 1 {
-                    return Err(crate::Error::MessageParse("Invalid register payload".to_string()));
-                }
-                let client_id = String::from_utf8_lossy(&data[1 + version_len + 1..1 + version_len + 1 + client_id_len]).to_string();
-                let auth_token_len = data[1 + version_len + 1 + client_id_len] as usize;
-                if data.len() < 1 + version_len + 1 + client_id_len + 1 + auth_token_len {
-                    return Err(crate::Error::MessageParse("Invalid register payload".to_string()));
-                }
-                let auth_token = String::from_utf8_lossy(&data[1 + version_len + 1 + client_id_len + 1..1 + version_len + 1 + client_id_len + 1 + auth_token_len]).to_string();
-                let mut capabilities: Option<Vec<String>> = None;
-                let mut metadata: Option<serde_json::Value> = None;
-
-                let capabilities_start = 1 + version_len + 1 + client_id_len + 1 + auth_token_len;
-                let mut capabilities_len = 0;
-                if data.len() > capabilities_start {
-                    capabilities_len = data[capabilities_start] as usize;
-                    if data.len() < capabilities_start + 1 + capabilities_len {
-                        return Err(crate::Error::MessageParse("Invalid register payload".to_string()));
-                    }
-                    let mut caps = Vec::new();
-                    for i in 0..capabilities_len {
-                        caps.push(String::from_utf8_lossy(&data[capabilities_start + 1 + i..capabilities_start + 1 + i + 1]).to_string());
-                    }
+                let invalid = || crate::Error::MessageParse("Invalid register payload".to_string());
+                let mut rest = data;
+                let version = read_short_field(&mut rest).ok_or_else(invalid)?;
+                let client_id = read_short_field(&mut rest).ok_or_else(invalid)?;
+                let auth_token = read_short_field(&mut rest).ok_or_else(invalid)?;
+                // Frames from older encoders may end after the token
+                let mut capabilities = None;
+                let mut metadata = None;
+                if let Some((&count, after_count)) = rest.split_first() {
+                    rest = after_count;
+                    let caps = (0..count)
+                        .map(|_| read_short_field(&mut rest).ok_or_else(invalid))
+                        .collect::<Result<Vec<_>, _>>()?;
                     capabilities = Some(caps);
-                }
-
-                let metadata_start = capabilities_start + 1 + capabilities_len;
-                if data.len() > metadata_start {
-                    let metadata_len = data[metadata_start] as usize;
-                    if data.len() < metadata_start + 1 + metadata_len {
-                        return Err(crate::Error::MessageParse("Invalid register payload".to_string()));
+                    let json = read_short_field(&mut rest).ok_or_else(invalid)?;
+                    if !json.is_empty() {
+                        metadata = Some(serde_json::from_str(&json)?);
                     }
-                    let json: serde_json::Value = serde_json::from_slice(&data[metadata_start + 1..metadata_start + 1 + metadata_len])?;
-                    metadata = Some(json);
                 }
-
                 Ok(Payload::Register(RegisterPayload { version, client_id, auth_token, capabilities, metadata }))
             }
             MessageType::Unregister => {
@@ -457,6 +1260,24 @@ impl Message {
                 let auth_token = String::from_utf8_lossy(&data[1 + version_len + 1 + client_id_len + 1..1 + version_len + 1 + client_id_len + 1 + auth_token_len]).to_string();
                 Ok(Payload::Unregister(UnregisterPayload { version, client_id, auth_token }))
             }
+            MessageType::AppRelay => {
+                if data.len() < 2 {
+                    return Err(crate::Error::MessageParse("Invalid app relay payload".to_string()));
+                }
+                let room_id_len = data[0] as usize;
+                if data.len() < 1 + room_id_len + 1 {
+                    return Err(crate::Error::MessageParse("Invalid app relay payload".to_string()));
+                }
+                let room_id = String::from_utf8_lossy(&data[1..1 + room_id_len]).to_string();
+                let from_len = data[1 + room_id_len] as usize;
+                let data_start = 1 + room_id_len + 1 + from_len;
+                if data.len() < data_start {
+                    return Err(crate::Error::MessageParse("Invalid app relay payload".to_string()));
+                }
+                let from_client_id = (from_len > 0)
+                    .then(|| String::from_utf8_lossy(&data[1 + room_id_len + 1..data_start]).to_string());
+                Ok(Payload::AppRelay(AppRelayPayload { room_id, from_client_id, data: data[data_start..].to_vec() }))
+            }
             _ => Err(crate::Error::MessageParse("Binary deserialization not implemented".to_string())),
         }
     }
@@ -478,24 +1299,32 @@ impl Message {
                 Ok(Payload::ConnectAck(ConnectAckPayload {
                     status: parts[0].to_string(),
                     session_id: parts[1].to_string(),
+                    heartbeat_interval: None,
+                    session_timeout: None,
                 }))
             }
             MessageType::SignalOffer => {
                 Ok(Payload::SignalOffer(SignalPayload {
                     target_client_id: parts[0].to_string(),
                     signal_data: parts[1].to_string(),
+                    room_id: None,
+                    sequence: None,
                 }))
             }
             MessageType::SignalAnswer => {
                 Ok(Payload::SignalAnswer(SignalPayload {
                     target_client_id: parts[0].to_string(),
                     signal_data: parts[1].to_string(),
+                    room_id: None,
+                    sequence: None,
                 }))
             }
             MessageType::SignalIceCandidate => {
                 Ok(Payload::SignalIceCandidate(SignalPayload {
                     target_client_id: parts[0].to_string(),
                     signal_data: parts[1].to_string(),
+                    room_id: None,
+                    sequence: None,
                 }))
             }
             MessageType::Register => {
@@ -532,6 +1361,7 @@ impl Message {
                 Ok(Payload::Error(ErrorPayload {
                     error_code,
                     error_message: parts[1].to_string(),
+                    ..Default::default()
                 }))
             }
             _ => Err(crate::Error::MessageParse("Text deserialization not implemented".to_string())),
@@ -552,6 +1382,40 @@ impl Message {
 }
 
 impl MessageType {
+    /// Every message type understood by this protocol version
+    pub const ALL: [MessageType; 30] = [
+        MessageType::Connect,
+        MessageType::ConnectAck,
+        MessageType::Disconnect,
+        MessageType::Heartbeat,
+        MessageType::HeartbeatAck,
+        MessageType::ServerInfo,
+        MessageType::ServerInfoAck,
+        MessageType::WhereAmI,
+        MessageType::WhereAmIAck,
+        MessageType::ClientStatusQuery,
+        MessageType::ClientStatusQueryAck,
+        MessageType::SignalOffer,
+        MessageType::SignalAnswer,
+        MessageType::SignalIceCandidate,
+        MessageType::Register,
+        MessageType::RegisterAck,
+        MessageType::Unregister,
+        MessageType::UnregisterAck,
+        MessageType::WebRTCRoomCreate,
+        MessageType::WebRTCRoomCreateAck,
+        MessageType::WebRTCRoomJoin,
+        MessageType::WebRTCRoomJoinAck,
+        MessageType::WebRTCRoomLeave,
+        MessageType::WebRTCRoomLeaveAck,
+        MessageType::WebRTCRoomList,
+        MessageType::WebRTCRoomListAck,
+        MessageType::PeerJoined,
+        MessageType::PeerLeft,
+        MessageType::AppRelay,
+        MessageType::Error,
+    ];
+
     pub fn from_u8(value: u8) -> Result<Self, crate::Error> {
         match value {
             0x01 => Ok(MessageType::Connect),
@@ -559,6 +1423,12 @@ impl MessageType {
             0x03 => Ok(MessageType::Disconnect),
             0x04 => Ok(MessageType::Heartbeat),
             0x05 => Ok(MessageType::HeartbeatAck),
+            0x06 => Ok(MessageType::ServerInfo),
+            0x07 => Ok(MessageType::ServerInfoAck),
+            0x08 => Ok(MessageType::WhereAmI),
+            0x09 => Ok(MessageType::WhereAmIAck),
+            0x0A => Ok(MessageType::ClientStatusQuery),
+            0x0B => Ok(MessageType::ClientStatusQueryAck),
             0x10 => Ok(MessageType::SignalOffer),
             0x11 => Ok(MessageType::SignalAnswer),
             0x12 => Ok(MessageType::SignalIceCandidate),
@@ -572,6 +1442,11 @@ impl MessageType {
             0x33 => Ok(MessageType::WebRTCRoomJoinAck),
             0x34 => Ok(MessageType::WebRTCRoomLeave),
             0x35 => Ok(MessageType::WebRTCRoomLeaveAck),
+            0x36 => Ok(MessageType::WebRTCRoomList),
+            0x37 => Ok(MessageType::WebRTCRoomListAck),
+            0x38 => Ok(MessageType::PeerJoined),
+            0x39 => Ok(MessageType::PeerLeft),
+            0x40 => Ok(MessageType::AppRelay),
             0xFF => Ok(MessageType::Error),
             _ => Err(crate::Error::InvalidMessageType(value)),
         }
@@ -579,6 +1454,10 @@ impl MessageType {
 }
 
 impl PayloadType {
+    /// Payload encodings `Message::to_binary`/`from_binary` can handle, CBOR included.
+    /// Protobuf covers only the payloads mapped in `proto/signal.proto`.
+    pub const SUPPORTED: [PayloadType; 6] = [PayloadType::Binary, PayloadType::Json, PayloadType::Text, PayloadType::Protobuf, PayloadType::Cbor, PayloadType::JsonGzip];
+
     pub fn from_u8(value: u8) -> Result<Self, crate::Error> {
         match value {
             0x01 => Ok(PayloadType::Binary),
@@ -586,7 +1465,47 @@ impl PayloadType {
             0x03 => Ok(PayloadType::Text),
             0x04 => Ok(PayloadType::Protobuf),
             0x05 => Ok(PayloadType::Cbor),
+            0x06 => Ok(PayloadType::JsonGzip),
             _ => Err(crate::Error::InvalidPayloadType(value)),
         }
     }
-} 
\ No newline at end of file
+}
+
+fn gzip(data: &[u8]) -> Result<Vec<u8>, crate::Error> {
+    use std::io::Write;
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+fn cbor(payload: &Payload) -> Result<Vec<u8>, crate::Error> {
+    let mut buffer = Vec::new();
+    ciborium::into_writer(payload, &mut buffer)
+        .map_err(|e| crate::Error::MessageParse(format!("CBOR encoding failed: {e}")))?;
+    Ok(buffer)
+}
+
+fn uncbor(data: &[u8]) -> Result<Payload, crate::Error> {
+    ciborium::from_reader(data).map_err(|e| crate::Error::MessageParse(format!("Invalid CBOR payload: {e}")))
+}
+
+fn unzstd(data: &[u8], limit: usize) -> Result<Vec<u8>, crate::Error> {
+    read_at_most(zstd::stream::read::Decoder::new(data)?, limit)
+}
+
+fn gunzip(data: &[u8], limit: usize) -> Result<Vec<u8>, crate::Error> {
+    read_at_most(flate2::read::GzDecoder::new(data), limit)
+}
+
+/// Read all of `reader`, failing once it yields more than `limit` bytes
+fn read_at_most(reader: impl std::io::Read, limit: usize) -> Result<Vec<u8>, crate::Error> {
+    use std::io::Read;
+    let mut decompressed = Vec::new();
+    reader.take(limit as u64 + 1).read_to_end(&mut decompressed)?;
+    if decompressed.len() > limit {
+        return Err(crate::Error::MessageParse(format!(
+            "Compressed payload inflates past {limit} bytes"
+        )));
+    }
+    Ok(decompressed)
+}
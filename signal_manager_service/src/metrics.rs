@@ -0,0 +1,411 @@
+use std::fmt::Write;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::message::MessageType;
+
+/// Upper bounds, in bytes, of the inbound frame size histogram buckets; larger frames
+/// land in a final `+Inf` bucket
+pub const FRAME_SIZE_BUCKETS: [u64; 8] = [64, 256, 1024, 4096, 16384, 65536, 262144, 1048576];
+
+/// Upper bounds, in milliseconds, of the per-type handler duration histogram buckets; slower
+/// dispatches land in a final `+Inf` bucket
+pub const HANDLER_DURATION_BUCKETS_MS: [u64; 10] = [1, 5, 10, 25, 50, 100, 250, 500, 1000, 5000];
+
+/// Process-wide counters exported in Prometheus text format on `/metrics`.
+/// All counters are atomics so concurrent connections never lose updates.
+#[derive(Debug)]
+pub struct Metrics {
+    connections_total: AtomicU64,
+    /// WebSocket connections currently open
+    connections_active: AtomicU64,
+    connections_closed_total: AtomicU64,
+    /// Binary messages that could not be parsed into a protocol message
+    parse_errors_total: AtomicU64,
+    /// Indexed like `ParseErrorReason::ALL`
+    parse_errors_by_reason: Vec<AtomicU64>,
+    /// Connects refused because the client id and token did not authenticate
+    auth_failures_total: AtomicU64,
+    /// Reconnects that took over a session whose socket had dropped
+    sessions_replaced_total: AtomicU64,
+    /// Connects refused because the client's session was still held by an open socket
+    duplicate_sessions_rejected_total: AtomicU64,
+    /// Indexed like `MessageType::ALL`
+    messages_received: Vec<AtomicU64>,
+    /// Binary frames received per size bucket, indexed like `FRAME_SIZE_BUCKETS` plus `+Inf`;
+    /// not cumulative, unlike the exported series
+    frame_sizes: Vec<AtomicU64>,
+    frame_bytes_total: AtomicU64,
+    /// Per message type, dispatches per duration bucket indexed like `HANDLER_DURATION_BUCKETS_MS`
+    /// plus `+Inf`; indexed like `MessageType::ALL`
+    handler_durations: Vec<Vec<AtomicU64>>,
+    /// Per message type, total dispatch time in microseconds; indexed like `MessageType::ALL`
+    handler_micros_total: Vec<AtomicU64>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self {
+            connections_total: AtomicU64::new(0),
+            connections_active: AtomicU64::new(0),
+            connections_closed_total: AtomicU64::new(0),
+            parse_errors_total: AtomicU64::new(0),
+            parse_errors_by_reason: ParseErrorReason::ALL.iter().map(|_| AtomicU64::new(0)).collect(),
+            auth_failures_total: AtomicU64::new(0),
+            sessions_replaced_total: AtomicU64::new(0),
+            duplicate_sessions_rejected_total: AtomicU64::new(0),
+            messages_received: MessageType::ALL.iter().map(|_| AtomicU64::new(0)).collect(),
+            frame_sizes: (0..=FRAME_SIZE_BUCKETS.len()).map(|_| AtomicU64::new(0)).collect(),
+            frame_bytes_total: AtomicU64::new(0),
+            handler_durations: MessageType::ALL.iter()
+                .map(|_| (0..=HANDLER_DURATION_BUCKETS_MS.len()).map(|_| AtomicU64::new(0)).collect())
+                .collect(),
+            handler_micros_total: MessageType::ALL.iter().map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+
+    /// Count a WebSocket connection that completed its upgrade
+    pub fn record_connection(&self) {
+        self.connections_total.fetch_add(1, Ordering::Relaxed);
+        self.connections_active.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a connection recorded by `record_connection` as closed
+    pub fn record_connection_closed(&self) {
+        self.connections_active.fetch_sub(1, Ordering::Relaxed);
+        self.connections_closed_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_parse_error(&self, reason: ParseErrorReason) {
+        self.parse_errors_total.fetch_add(1, Ordering::Relaxed);
+        self.parse_errors_by_reason[reason as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_auth_failure(&self) {
+        self.auth_failures_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_session_replaced(&self) {
+        self.sessions_replaced_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_duplicate_session_rejected(&self) {
+        self.duplicate_sessions_rejected_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a well-formed message received from a client
+    pub fn record_message(&self, message_type: MessageType) {
+        if let Some(counter) = Self::index(message_type).map(|i| &self.messages_received[i]) {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Count a binary frame of `len` bytes from a client, before it is parsed
+    pub fn record_frame_size(&self, len: usize) {
+        let len = len as u64;
+        let bucket = FRAME_SIZE_BUCKETS.partition_point(|bound| *bound < len);
+        self.frame_sizes[bucket].fetch_add(1, Ordering::Relaxed);
+        self.frame_bytes_total.fetch_add(len, Ordering::Relaxed);
+    }
+
+    /// Count one dispatch of a `message_type` message that took `elapsed`
+    pub fn record_handler_duration(&self, message_type: MessageType, elapsed: Duration) {
+        let Some(i) = Self::index(message_type) else { return };
+        // Bucket on microseconds so a 1.9ms dispatch lands above the 1ms bound rather than at it
+        let micros = elapsed.as_micros().min(u64::MAX as u128) as u64;
+        let bucket = HANDLER_DURATION_BUCKETS_MS.partition_point(|bound| bound * 1000 < micros);
+        self.handler_durations[i][bucket].fetch_add(1, Ordering::Relaxed);
+        self.handler_micros_total[i].fetch_add(micros, Ordering::Relaxed);
+    }
+
+    pub fn connections_total(&self) -> u64 {
+        self.connections_total.load(Ordering::Relaxed)
+    }
+
+    pub fn connections_active(&self) -> u64 {
+        self.connections_active.load(Ordering::Relaxed)
+    }
+
+    pub fn connections_closed(&self) -> u64 {
+        self.connections_closed_total.load(Ordering::Relaxed)
+    }
+
+    pub fn parse_errors(&self) -> u64 {
+        self.parse_errors_total.load(Ordering::Relaxed)
+    }
+
+    pub fn parse_errors_by_reason(&self, reason: ParseErrorReason) -> u64 {
+        self.parse_errors_by_reason[reason as usize].load(Ordering::Relaxed)
+    }
+
+    pub fn auth_failures(&self) -> u64 {
+        self.auth_failures_total.load(Ordering::Relaxed)
+    }
+
+    pub fn sessions_replaced(&self) -> u64 {
+        self.sessions_replaced_total.load(Ordering::Relaxed)
+    }
+
+    pub fn duplicate_sessions_rejected(&self) -> u64 {
+        self.duplicate_sessions_rejected_total.load(Ordering::Relaxed)
+    }
+
+    pub fn messages_received(&self, message_type: MessageType) -> u64 {
+        Self::index(message_type).map_or(0, |i| self.messages_received[i].load(Ordering::Relaxed))
+    }
+
+    /// Frames received per size bucket, indexed like `FRAME_SIZE_BUCKETS` with `+Inf` last
+    pub fn frame_sizes(&self) -> Vec<u64> {
+        self.frame_sizes.iter().map(|count| count.load(Ordering::Relaxed)).collect()
+    }
+
+    pub fn frame_bytes(&self) -> u64 {
+        self.frame_bytes_total.load(Ordering::Relaxed)
+    }
+
+    /// Dispatches of `message_type` per duration bucket, indexed like `HANDLER_DURATION_BUCKETS_MS`
+    /// with `+Inf` last
+    pub fn handler_durations(&self, message_type: MessageType) -> Vec<u64> {
+        match Self::index(message_type) {
+            Some(i) => self.handler_durations[i].iter().map(|count| count.load(Ordering::Relaxed)).collect(),
+            None => vec![0; HANDLER_DURATION_BUCKETS_MS.len() + 1],
+        }
+    }
+
+    /// Prometheus text exposition of every counter
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# HELP signal_manager_connections_total WebSocket connections accepted");
+        let _ = writeln!(out, "# TYPE signal_manager_connections_total counter");
+        let _ = writeln!(out, "signal_manager_connections_total {}", self.connections_total());
+        let _ = writeln!(out, "# HELP signal_manager_connections_active WebSocket connections currently open");
+        let _ = writeln!(out, "# TYPE signal_manager_connections_active gauge");
+        let _ = writeln!(out, "signal_manager_connections_active {}", self.connections_active());
+        let _ = writeln!(out, "# HELP signal_manager_parse_errors_total Binary messages that could not be parsed");
+        let _ = writeln!(out, "# TYPE signal_manager_parse_errors_total counter");
+        let _ = writeln!(out, "signal_manager_parse_errors_total {}", self.parse_errors());
+        let _ = writeln!(out, "# HELP signal_manager_parse_errors_by_reason_total Binary messages that could not be parsed, by reason");
+        let _ = writeln!(out, "# TYPE signal_manager_parse_errors_by_reason_total counter");
+        for reason in ParseErrorReason::ALL {
+            let _ = writeln!(
+                out,
+                "signal_manager_parse_errors_by_reason_total{{reason=\"{}\"}} {}",
+                reason.label(),
+                self.parse_errors_by_reason(reason)
+            );
+        }
+        let _ = writeln!(out, "# HELP signal_manager_auth_failures_total Connects that failed authentication");
+        let _ = writeln!(out, "# TYPE signal_manager_auth_failures_total counter");
+        let _ = writeln!(out, "signal_manager_auth_failures_total {}", self.auth_failures());
+        let _ = writeln!(out, "# HELP signal_manager_sessions_replaced_total Reconnects that took over a stale session");
+        let _ = writeln!(out, "# TYPE signal_manager_sessions_replaced_total counter");
+        let _ = writeln!(out, "signal_manager_sessions_replaced_total {}", self.sessions_replaced());
+        let _ = writeln!(out, "# HELP signal_manager_duplicate_sessions_rejected_total Connects rejected because the session was held by an open socket");
+        let _ = writeln!(out, "# TYPE signal_manager_duplicate_sessions_rejected_total counter");
+        let _ = writeln!(out, "signal_manager_duplicate_sessions_rejected_total {}", self.duplicate_sessions_rejected());
+        let _ = writeln!(out, "# HELP signal_manager_messages_received_total Messages received from clients, by type");
+        let _ = writeln!(out, "# TYPE signal_manager_messages_received_total counter");
+        for message_type in MessageType::ALL {
+            let _ = writeln!(
+                out,
+                "signal_manager_messages_received_total{{type=\"{:?}\"}} {}",
+                message_type,
+                self.messages_received(message_type)
+            );
+        }
+        let _ = writeln!(out, "# HELP signal_manager_frame_size_bytes Size of binary frames received from clients, before parsing");
+        let _ = writeln!(out, "# TYPE signal_manager_frame_size_bytes histogram");
+        let mut cumulative = 0;
+        for (i, count) in self.frame_sizes().into_iter().enumerate() {
+            cumulative += count;
+            let bound = FRAME_SIZE_BUCKETS.get(i).map_or_else(|| "+Inf".to_string(), |bound| bound.to_string());
+            let _ = writeln!(out, "signal_manager_frame_size_bytes_bucket{{le=\"{}\"}} {}", bound, cumulative);
+        }
+        let _ = writeln!(out, "signal_manager_frame_size_bytes_sum {}", self.frame_bytes());
+        let _ = writeln!(out, "signal_manager_frame_size_bytes_count {}", cumulative);
+        let _ = writeln!(out, "# HELP signal_manager_handler_duration_seconds Time spent dispatching messages from clients, by type");
+        let _ = writeln!(out, "# TYPE signal_manager_handler_duration_seconds histogram");
+        for (t, message_type) in MessageType::ALL.iter().enumerate() {
+            let counts = self.handler_durations(*message_type);
+            if counts.iter().all(|count| *count == 0) {
+                continue;
+            }
+            let mut cumulative = 0;
+            for (i, count) in counts.into_iter().enumerate() {
+                cumulative += count;
+                let bound = HANDLER_DURATION_BUCKETS_MS.get(i)
+                    .map_or_else(|| "+Inf".to_string(), |bound| (*bound as f64 / 1000.0).to_string());
+                let _ = writeln!(
+                    out,
+                    "signal_manager_handler_duration_seconds_bucket{{type=\"{:?}\",le=\"{}\"}} {}",
+                    message_type, bound, cumulative
+                );
+            }
+            let micros = self.handler_micros_total[t].load(Ordering::Relaxed);
+            let _ = writeln!(out, "signal_manager_handler_duration_seconds_sum{{type=\"{:?}\"}} {}", message_type, micros as f64 / 1_000_000.0);
+            let _ = writeln!(out, "signal_manager_handler_duration_seconds_count{{type=\"{:?}\"}} {}", message_type, cumulative);
+        }
+        out
+    }
+
+    fn index(message_type: MessageType) -> Option<usize> {
+        MessageType::ALL.iter().position(|t| *t == message_type)
+    }
+}
+
+/// Await `dispatch`, the handling of a `message_type` message with id `uuid`. Its wall-clock
+/// duration is recorded in `metrics` when given, and a warning is logged when it took longer
+/// than `slow_threshold`; a zero threshold never warns.
+pub async fn time_dispatch<F: Future>(
+    message_type: MessageType,
+    uuid: Uuid,
+    slow_threshold: Duration,
+    metrics: Option<&Metrics>,
+    dispatch: F,
+) -> F::Output {
+    let started = Instant::now();
+    let output = dispatch.await;
+    let elapsed = started.elapsed();
+    if let Some(metrics) = metrics {
+        metrics.record_handler_duration(message_type, elapsed);
+    }
+    if !slow_threshold.is_zero() && elapsed > slow_threshold {
+        warn!(
+            message_type = ?message_type,
+            uuid = %uuid,
+            elapsed_ms = elapsed.as_millis() as u64,
+            "[MESSAGE_HANDLER] Slow handler: {:?} took {}ms, over the {}ms threshold",
+            message_type,
+            elapsed.as_millis(),
+            slow_threshold.as_millis()
+        );
+    }
+    output
+}
+
+/// Why an inbound frame could not be parsed, as labelled on `/metrics`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseErrorReason {
+    /// The UUID bytes could not be read as a UUID
+    InvalidUuid,
+    /// A payload field failed validation
+    InvalidPayload,
+    /// The frame uses a previous protocol version
+    UnsupportedProtocol,
+    /// The payload type is not in `server.enabled_codecs`
+    CodecDisabled,
+    /// Any other framing or payload decoding failure
+    Malformed,
+}
+
+impl ParseErrorReason {
+    pub const ALL: [ParseErrorReason; 5] = [
+        ParseErrorReason::InvalidUuid,
+        ParseErrorReason::InvalidPayload,
+        ParseErrorReason::UnsupportedProtocol,
+        ParseErrorReason::CodecDisabled,
+        ParseErrorReason::Malformed,
+    ];
+
+    /// Reason a `Message::from_binary_with` error is counted under
+    pub fn of(error: &crate::Error) -> Self {
+        match error {
+            crate::Error::InvalidUuid(_) => ParseErrorReason::InvalidUuid,
+            crate::Error::InvalidPayload { .. } => ParseErrorReason::InvalidPayload,
+            crate::Error::UnsupportedProtocolVersion(_) => ParseErrorReason::UnsupportedProtocol,
+            crate::Error::CodecDisabled(_) => ParseErrorReason::CodecDisabled,
+            _ => ParseErrorReason::Malformed,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ParseErrorReason::InvalidUuid => "invalid_uuid",
+            ParseErrorReason::InvalidPayload => "invalid_payload",
+            ParseErrorReason::UnsupportedProtocol => "unsupported_protocol",
+            ParseErrorReason::CodecDisabled => "codec_disabled",
+            ParseErrorReason::Malformed => "malformed",
+        }
+    }
+}
+
+/// Connection counts for one `connection_stats_interval` tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ConnectionStats {
+    /// Connections open at the tick
+    pub active: u64,
+    /// Connections opened since the previous tick
+    pub opened: u64,
+    /// Connections closed since the previous tick
+    pub closed: u64,
+}
+
+/// Messages received during one `message_stats_interval` tick
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MessageStats {
+    pub total: u64,
+    /// Types received at least once since the previous tick, in `MessageType::ALL` order
+    pub by_type: Vec<(MessageType, u64)>,
+    /// Binary frames received since the previous tick per size bucket, indexed like
+    /// `FRAME_SIZE_BUCKETS` with `+Inf` last
+    pub frame_sizes: Vec<u64>,
+}
+
+/// Turns the cumulative counters in `Metrics` into per-tick deltas for the periodic
+/// stats log. Each stats task keeps its own sampler, so reading is a handful of loads.
+#[derive(Debug, Default)]
+pub struct StatsSampler {
+    opened: u64,
+    closed: u64,
+    messages: Vec<u64>,
+    frame_sizes: Vec<u64>,
+}
+
+impl StatsSampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Connection counts since the previous call, or since start-up on the first
+    pub fn connection_stats(&mut self, metrics: &Metrics) -> ConnectionStats {
+        let (opened, closed) = (metrics.connections_total(), metrics.connections_closed());
+        let stats = ConnectionStats {
+            active: metrics.connections_active(),
+            opened: opened - self.opened,
+            closed: closed - self.closed,
+        };
+        (self.opened, self.closed) = (opened, closed);
+        stats
+    }
+
+    /// Messages and frame sizes received since the previous call, or since start-up on the first
+    pub fn message_stats(&mut self, metrics: &Metrics) -> MessageStats {
+        self.messages.resize(MessageType::ALL.len(), 0);
+        let mut stats = MessageStats::default();
+        for (message_type, last) in MessageType::ALL.into_iter().zip(self.messages.iter_mut()) {
+            let received = metrics.messages_received(message_type);
+            let delta = received - *last;
+            *last = received;
+            if delta > 0 {
+                stats.total += delta;
+                stats.by_type.push((message_type, delta));
+            }
+        }
+        let frame_sizes = metrics.frame_sizes();
+        self.frame_sizes.resize(frame_sizes.len(), 0);
+        stats.frame_sizes = frame_sizes.iter().zip(&self.frame_sizes).map(|(now, last)| now - last).collect();
+        self.frame_sizes = frame_sizes;
+        stats
+    }
+}
+
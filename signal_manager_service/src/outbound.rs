@@ -0,0 +1,179 @@
+use crate::config::OverflowPolicy;
+use crate::message::{Message, MessageType};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use tokio::sync::Notify;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tracing::warn;
+
+/// Bounded per-client queue of frames awaiting delivery on the WebSocket.
+/// Unlike an mpsc channel it can evict from the front, which `DropOldest` needs.
+///
+/// Control frames (heartbeats, errors, disconnects) wait in a lane of their own that is
+/// drained before bulk frames, so a backlog of signal relays cannot hold back a
+/// heartbeat ack. Both lanes share one budget of `capacity` frames; when it is spent, a
+/// control frame displaces the oldest bulk frame unless the policy is `Disconnect`.
+pub struct OutboundQueue {
+    lanes: Mutex<Lanes>,
+    notify: Notify,
+    capacity: usize,
+    policy: OverflowPolicy,
+    prioritize_control: bool,
+    dropped: AtomicU64,
+    closed: AtomicBool,
+    draining: AtomicBool,
+    close_frame: Mutex<Option<CloseFrame<'static>>>,
+}
+
+#[derive(Default)]
+struct Lanes {
+    control: VecDeque<Message>,
+    bulk: VecDeque<Message>,
+}
+
+impl Lanes {
+    fn len(&self) -> usize {
+        self.control.len() + self.bulk.len()
+    }
+}
+
+/// Whether `message` is delivered ahead of bulk frames when control priority is on
+pub fn is_control_frame(message: &Message) -> bool {
+    matches!(
+        message.message_type,
+        MessageType::Heartbeat | MessageType::HeartbeatAck | MessageType::Error | MessageType::Disconnect
+    )
+}
+
+impl OutboundQueue {
+    pub fn new(capacity: usize, policy: OverflowPolicy) -> Self {
+        Self {
+            lanes: Mutex::new(Lanes::default()),
+            notify: Notify::new(),
+            capacity: capacity.max(1),
+            policy,
+            prioritize_control: true,
+            dropped: AtomicU64::new(0),
+            closed: AtomicBool::new(false),
+            draining: AtomicBool::new(false),
+            close_frame: Mutex::new(None),
+        }
+    }
+
+    /// Deliver control frames ahead of bulk frames (the default); when off, every frame
+    /// shares one lane and is delivered in the order it was pushed
+    pub fn with_control_priority(mut self, prioritize_control: bool) -> Self {
+        self.prioritize_control = prioritize_control;
+        self
+    }
+
+    /// Enqueue a frame, applying the overflow policy when the queue is full.
+    /// Fails only if the queue is closed, including when `Disconnect` closes it.
+    pub fn push(&self, message: Message) -> Result<(), crate::Error> {
+        if self.is_closed() {
+            return Err(crate::Error::Connection("Outbound queue closed".to_string()));
+        }
+
+        {
+            let mut lanes = self.lanes.lock().unwrap();
+            let control = self.prioritize_control && is_control_frame(&message);
+            if lanes.len() >= self.capacity {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                let displace_bulk = control && !lanes.bulk.is_empty() && self.policy != OverflowPolicy::Disconnect;
+                match self.policy {
+                    _ if displace_bulk => {
+                        warn!("[WEBSOCKET_OUT] Outbound queue full, dropping oldest bulk frame for a control frame");
+                        lanes.bulk.pop_front();
+                    }
+                    OverflowPolicy::DropNewest => {
+                        warn!("[WEBSOCKET_OUT] Outbound queue full, dropping newest frame");
+                        return Ok(());
+                    }
+                    OverflowPolicy::DropOldest => {
+                        warn!("[WEBSOCKET_OUT] Outbound queue full, dropping oldest frame");
+                        if lanes.bulk.pop_front().is_none() {
+                            lanes.control.pop_front();
+                        }
+                    }
+                    OverflowPolicy::Disconnect => {
+                        warn!("[WEBSOCKET_OUT] Outbound queue full, disconnecting client");
+                        *lanes = Lanes::default();
+                        drop(lanes);
+                        self.close();
+                        return Err(crate::Error::Connection("Outbound queue overflow".to_string()));
+                    }
+                }
+            }
+            if control {
+                lanes.control.push_back(message);
+            } else {
+                lanes.bulk.push_back(message);
+            }
+        }
+
+        self.notify.notify_one();
+        Ok(())
+    }
+
+    /// Wait for the next frame. Returns `None` once the queue has been closed, or once a
+    /// draining queue is empty.
+    pub async fn pop(&self) -> Option<Message> {
+        loop {
+            if self.closed.load(Ordering::Acquire) {
+                return None;
+            }
+            let next = {
+                let mut lanes = self.lanes.lock().unwrap();
+                lanes.control.pop_front().or_else(|| lanes.bulk.pop_front())
+            };
+            if let Some(message) = next {
+                return Some(message);
+            }
+            if self.is_draining() {
+                return None;
+            }
+            self.notify.notified().await;
+        }
+    }
+
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+        self.notify.notify_one();
+    }
+
+    /// Stop accepting frames, deliver the ones already queued, then close the socket with `frame`
+    pub fn close_after_drain(&self, frame: CloseFrame<'static>) {
+        *self.close_frame.lock().unwrap() = Some(frame);
+        self.draining.store(true, Ordering::Release);
+        self.notify.notify_one();
+    }
+
+    /// Whether the queue no longer accepts frames
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire) || self.is_draining()
+    }
+
+    /// Whether `close_after_drain` has been called
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Acquire)
+    }
+
+    /// The close frame passed to `close_after_drain`, once
+    pub fn take_close_frame(&self) -> Option<CloseFrame<'static>> {
+        self.close_frame.lock().unwrap().take()
+    }
+
+    /// Number of frames discarded by the overflow policy
+    pub fn dropped_count(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    pub fn len(&self) -> usize {
+        self.lanes.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -0,0 +1,78 @@
+use prost::Message as _;
+
+use crate::message::{ConnectPayload, MessageType, Payload, SignalPayload, WebRTCRoomCreatePayload};
+
+/// Types generated from `proto/signal.proto`
+pub mod schema {
+    include!(concat!(env!("OUT_DIR"), "/signal_manager.rs"));
+}
+
+/// Encode a payload that has a protobuf mapping
+pub fn encode_payload(payload: &Payload) -> Result<Vec<u8>, crate::Error> {
+    match payload {
+        Payload::Connect(p) => Ok(schema::ConnectPayload {
+            client_id: p.client_id.clone(),
+            auth_token: p.auth_token.clone(),
+        }
+        .encode_to_vec()),
+        Payload::SignalOffer(p) | Payload::SignalAnswer(p) | Payload::SignalIceCandidate(p) => Ok(schema::SignalPayload {
+            target_client_id: p.target_client_id.clone(),
+            signal_data: p.signal_data.clone(),
+            room_id: p.room_id.clone(),
+            sequence: p.sequence,
+        }
+        .encode_to_vec()),
+        Payload::WebRTCRoomCreate(p) => Ok(schema::WebRtcRoomCreatePayload {
+            version: p.version.clone(),
+            client_id: p.client_id.clone(),
+            auth_token: p.auth_token.clone(),
+            role: p.role.clone(),
+            offer_sdp: p.offer_sdp.clone(),
+            metadata_json: p.metadata.as_ref().map(serde_json::to_string).transpose()?,
+            max_participants: p.max_participants,
+        }
+        .encode_to_vec()),
+        other => Err(crate::Error::MessageParse(format!(
+            "No protobuf mapping for {:?} payloads",
+            other.kind()
+        ))),
+    }
+}
+
+/// Decode a payload of `message_type` that has a protobuf mapping
+pub fn decode_payload(data: &[u8], message_type: MessageType) -> Result<Payload, crate::Error> {
+    let invalid = |e: prost::DecodeError| crate::Error::MessageParse(format!("Invalid protobuf payload: {e}"));
+    match message_type {
+        MessageType::Connect => {
+            let p = schema::ConnectPayload::decode(data).map_err(invalid)?;
+            Ok(Payload::Connect(ConnectPayload { client_id: p.client_id, auth_token: p.auth_token }))
+        }
+        MessageType::SignalOffer | MessageType::SignalAnswer | MessageType::SignalIceCandidate => {
+            let p = schema::SignalPayload::decode(data).map_err(invalid)?;
+            let signal = SignalPayload {
+                target_client_id: p.target_client_id,
+                signal_data: p.signal_data,
+                room_id: p.room_id,
+                sequence: p.sequence,
+            };
+            Ok(match message_type {
+                MessageType::SignalOffer => Payload::SignalOffer(signal),
+                MessageType::SignalAnswer => Payload::SignalAnswer(signal),
+                _ => Payload::SignalIceCandidate(signal),
+            })
+        }
+        MessageType::WebRTCRoomCreate => {
+            let p = schema::WebRtcRoomCreatePayload::decode(data).map_err(invalid)?;
+            Ok(Payload::WebRTCRoomCreate(WebRTCRoomCreatePayload {
+                version: p.version,
+                client_id: p.client_id,
+                auth_token: p.auth_token,
+                role: p.role,
+                offer_sdp: p.offer_sdp,
+                max_participants: p.max_participants,
+                metadata: p.metadata_json.as_deref().map(serde_json::from_str).transpose()?,
+            }))
+        }
+        other => Err(crate::Error::MessageParse(format!("No protobuf mapping for {:?} payloads", other))),
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Buckets kept before full (idle) ones are pruned as new keys arrive
+const PRUNE_THRESHOLD: usize = 4096;
+
+/// Token bucket per key (a client id, or a peer address before Connect) allowing
+/// `max_per_minute` messages per minute, with bursts of up to that many
+pub struct MessageRateLimiter {
+    max_per_minute: AtomicUsize,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+struct TokenBucket {
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl MessageRateLimiter {
+    /// A limit of 0 admits every message
+    pub fn new(max_per_minute: usize) -> Self {
+        Self { max_per_minute: AtomicUsize::new(max_per_minute), buckets: Mutex::new(HashMap::new()) }
+    }
+
+    /// Messages per minute currently allowed to each key; 0 when unlimited
+    pub fn max_per_minute(&self) -> usize {
+        self.max_per_minute.load(Ordering::Relaxed)
+    }
+
+    /// Change the limit; buckets already tracked are capped to the new burst size as they refill
+    pub fn set_max_per_minute(&self, max_per_minute: usize) {
+        self.max_per_minute.store(max_per_minute, Ordering::Relaxed);
+    }
+
+    /// Count one message for `key`; false when its bucket is empty
+    pub fn try_acquire(&self, key: &str) -> bool {
+        let max_per_minute = self.max_per_minute();
+        if max_per_minute == 0 {
+            return true;
+        }
+        let capacity = max_per_minute as f64;
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        if !buckets.contains_key(key) && buckets.len() >= PRUNE_THRESHOLD {
+            buckets.retain(|_, bucket| Self::refill(bucket, capacity, now) < capacity);
+        }
+        let bucket = buckets.entry(key.to_string()).or_insert(TokenBucket { tokens: capacity, refilled: now });
+        if Self::refill(bucket, capacity, now) < 1.0 {
+            return false;
+        }
+        bucket.tokens -= 1.0;
+        true
+    }
+
+    /// Keys currently holding a bucket
+    pub fn tracked_keys(&self) -> usize {
+        self.buckets.lock().unwrap().len()
+    }
+
+    /// Top `bucket` up for the time since its last refill and return its tokens
+    fn refill(bucket: &mut TokenBucket, capacity: f64, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(bucket.refilled).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * capacity / 60.0).min(capacity);
+        bucket.refilled = now;
+        bucket.tokens
+    }
+}
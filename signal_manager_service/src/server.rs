@@ -1,64 +1,284 @@
-use crate::config::Config;
-use crate::message::{Message, Payload};
-use crate::session::SessionManager;
+use crate::config::{checked_period, Config, ConfigReload, ConnectAckOrder, ConnectionLimitPolicy, DuplicateConnectPolicy, SessionLimitPolicy};
+use crate::message::{FrameOptions, Message, PeerFraming, MessageType, Payload, PayloadType, ServerInfoAckPayload};
+use crate::session::{ClientSession, SessionManager};
+use crate::outbound::OutboundQueue;
 use crate::auth::AuthManager;
-use futures::{SinkExt, StreamExt};
+use futures::{FutureExt, SinkExt, StreamExt};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::{RwLock, Mutex};
-use tokio_tungstenite::accept_async;
+use tokio::sync::{mpsc, watch, RwLock, Mutex};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::accept_hdr_async_with_config;
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::{header::ORIGIN, StatusCode};
+use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
 use tokio_tungstenite::tungstenite::Message as WsMessage;
-use tracing::{error, info, warn, debug};
-use native_tls::{TlsAcceptor, Identity};
-use tokio_native_tls::TlsAcceptor as TokioTlsAcceptor;
-use std::fs::File;
-use std::io::Read;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tracing::{error, info, warn, debug, Instrument};
+use crate::tls::TlsAcceptor;
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_tungstenite::WebSocketStream;
 use crate::frame_handlers;
 use crate::type_two_handlers::register::RegisterHandler;
-use crate::webrtc_handlers::{WebRTCRoomCreateHandler, WebRTCRoomJoinHandler, WebRTCRoomLeaveHandler};
+use crate::webrtc_handlers::{
+    WebRTCRoomCreateHandler, WebRTCRoomJoinHandler, WebRTCRoomLeaveHandler, WebRTCRoomListHandler, WhereAmIHandler,
+};
+use crate::webrtc_handlers::where_am_i::WhereAmIRepositories;
+use crate::webrtc_handlers::room_create::RoomCreateRepositories;
+use crate::webrtc_handlers::room_join::RoomJoinRepositories;
+use crate::events::EventClient;
+use crate::webrtc_handlers::room_leave::RoomLeaveRepositories;
+use crate::webrtc_handlers::room_list::RoomListRepositories;
+use crate::webrtc_handlers::room_expiry::{self, ExpiredRoom, RoomExpiryRepositories};
+use crate::database::{self, DatabaseResult, FirestoreRepositoryFactory, RepositoryFactory, RetentionRepositories, RetentionSweep};
+use crate::health::{ComponentHealth, HealthReport};
+use crate::metrics::{time_dispatch, Metrics, ParseErrorReason, StatsSampler, FRAME_SIZE_BUCKETS};
+use crate::handshake::{HandshakeLimiter, HandshakeSlot};
+use crate::connection_limit::{ConnectionLimiter, ConnectionRefused, ConnectionSlot};
+use crate::rate_limit::MessageRateLimiter;
+use crate::signaling::SignalingProvider;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// Longest a single `/readyz` dependency probe may take before it counts as unreachable
+const HEALTH_PROBE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// How long `/readyz` reuses the last repository and signaling provider probes
+const HEALTH_CACHE_TTL: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Headroom the protocol layer allows above the configured size limits, so a message slightly
+/// over `max_message_size` is answered with error 9 instead of a dropped connection
+const PROTOCOL_SIZE_SLACK: usize = 64 * 1024;
+
+/// Longest a timed-out connection may take to flush its queue and close frame before it is dropped
+const CLOSE_DRAIN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Reason sent in the `Disconnect` and close frame each client receives when the server shuts down
+pub const SHUTDOWN_REASON: &str = "Server shutting down";
+
+/// Reason in the 1013 close frame sent to sockets turned away at `max_connections`
+pub const CAPACITY_REASON: &str = "Server at capacity";
+
+/// Reason in the 1013 close frame sent to sockets over `security.max_connections_per_ip`
+pub const ADDRESS_LIMIT_REASON: &str = "Too many connections from this address";
+
+/// Reason in the `Disconnect` sent to a client whose session ended after `session.session_timeout`
+/// seconds without a heartbeat
+pub const SESSION_TIMEOUT_REASON: &str = "Session timed out";
+
+/// Reason in the `Disconnect` and 1008 close frame sent to a socket that sent no heartbeat, Ping
+/// or Pong for `server.heartbeat_timeout_multiplier` heartbeat intervals
+pub const HEARTBEAT_TIMEOUT_REASON: &str = "Heartbeat timed out";
+
+/// Reason in the `Disconnect` and 1008 close frame sent to a socket whose session was evicted by a
+/// newer Connect under `session.session_limit_policy = "evict_oldest"`
+pub const SESSION_EVICTED_REASON: &str = "Session replaced by a newer connection";
+
+/// Reason in the `Disconnect` and 1008 close frame sent to a client evicted from the full client
+/// store under `database.eviction_policy = "lru"`
+pub const CLIENT_EVICTED_REASON: &str = "Evicted from the full client store";
+
+/// Reason in the 1008 close frame sent to a socket whose first Connect failed authentication
+pub const AUTH_FAILED_REASON: &str = "Authentication failed";
+
+/// Body of the 403 response to a WebSocket upgrade whose `Origin` is not in `security.allowed_origins`
+pub const ORIGIN_REJECTED_REASON: &str = "Origin not allowed";
+
+/// Whether a WebSocket upgrade from `origin` passes `allowed_origins`, where `*` admits any origin.
+/// Origins are compared without case or a trailing slash. A request with no `Origin` header
+/// comes from a non-browser client and is always admitted.
+pub fn origin_allowed(allowed_origins: &[String], origin: Option<&str>) -> bool {
+    let Some(origin) = origin else { return true };
+    let origin = origin.trim_end_matches('/');
+    allowed_origins.iter().any(|allowed| allowed == "*" || allowed.trim_end_matches('/').eq_ignore_ascii_case(origin))
+}
+
+/// Span the server runs in, giving every log event its `instance_id` field so events from
+/// several instances can be told apart once aggregated
+pub fn instance_span(config: &Config) -> tracing::Span {
+    tracing::info_span!("instance", instance_id = %config.server.instance_id)
+}
+
+/// `tokio::spawn` in the caller's span, so the task's log events keep its `instance_id`
+fn spawn<F>(future: F) -> JoinHandle<F::Output>
+where
+    F: std::future::Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    tokio::spawn(future.in_current_span())
+}
+
+/// Outcome of the last repository and signaling provider probes behind `/readyz`
+struct DependencyHealth {
+    checked_at: std::time::Instant,
+    repositories: ComponentHealth,
+    provider: ComponentHealth,
+}
 
 /// Context for message handling operations
 struct MessageHandlerContext<'a> {
     session_manager: &'a Arc<SessionManager>,
     client_id: &'a Arc<Mutex<Option<String>>>,
-    connections: &'a Arc<RwLock<HashMap<String, tokio::sync::mpsc::Sender<Message>>>>,
-    tx: &'a tokio::sync::mpsc::Sender<Message>,
+    connections: &'a Arc<RwLock<HashMap<String, Arc<OutboundQueue>>>>,
+    tx: &'a Arc<OutboundQueue>,
+    duplicate_connect_policy: DuplicateConnectPolicy,
+    connect_ack_order: ConnectAckOrder,
+    validate_signal_base64: bool,
+    role_message_allowlist: &'a HashMap<String, Vec<MessageType>>,
+    required_capabilities: Option<&'a HashMap<MessageType, String>>,
+    capabilities: &'a std::sync::Mutex<Vec<String>>,
+    app_relay_max_bytes: usize,
+    app_relay_max_per_sec: u32,
+    app_relay_window: &'a std::sync::Mutex<RateWindow>,
+    require_warmup_pong: bool,
+    warmup_pong_timeout: std::time::Duration,
+    pending_warmup: &'a std::sync::Mutex<Option<PendingWarmup>>,
+    reconnect_grace: std::time::Duration,
+    max_sessions_per_client: usize,
+    session_limit_policy: SessionLimitPolicy,
+    max_id_length: usize,
+    metrics: &'a Metrics,
+    server_info: &'a ServerInfoAckPayload,
     register_handler: &'a RegisterHandler,
     webrtc_room_create_handler: &'a WebRTCRoomCreateHandler,
     webrtc_room_join_handler: &'a WebRTCRoomJoinHandler,
     webrtc_room_leave_handler: &'a WebRTCRoomLeaveHandler,
+    webrtc_room_list_handler: &'a WebRTCRoomListHandler,
+    where_am_i_handler: &'a WhereAmIHandler,
+}
+
+/// Messages counted against a per-second limit for one connection
+struct RateWindow {
+    started: std::time::Instant,
+    count: u32,
 }
 
+impl RateWindow {
+    fn new() -> Self {
+        Self { started: std::time::Instant::now(), count: 0 }
+    }
+
+    /// Count one message; false when `max` were already counted this second. 0 means unlimited.
+    fn try_acquire(&mut self, max: u32) -> bool {
+        if max == 0 {
+            return true;
+        }
+        if self.started.elapsed() >= std::time::Duration::from_secs(1) {
+            self.started = std::time::Instant::now();
+            self.count = 0;
+        }
+        if self.count >= max {
+            return false;
+        }
+        self.count += 1;
+        true
+    }
+}
+
+/// A connected client whose ConnectAck is held back until it answers the warm-up ping
+struct PendingWarmup {
+    client_id: String,
+    nonce: Vec<u8>,
+    ack: Message,
+    deadline: tokio::time::Instant,
+    ping_sent: bool,
+}
 
 #[derive(Clone)]
 pub struct WebSocketServer {
     config: Arc<Config>,
+    /// `config` with reloaded settings applied; see `reload_config`
+    live_config: Arc<std::sync::RwLock<Arc<Config>>>,
     #[allow(dead_code)]
     auth_manager: Arc<AuthManager>,
     session_manager: Arc<SessionManager>,
-    connections: Arc<RwLock<HashMap<String, tokio::sync::mpsc::Sender<Message>>>>,
-    tls_acceptor: Option<TokioTlsAcceptor>,
+    connections: Arc<RwLock<HashMap<String, Arc<OutboundQueue>>>>,
+    tls_acceptor: Option<TlsAcceptor>,
     register_handler: RegisterHandler,
     webrtc_room_create_handler: WebRTCRoomCreateHandler,
     webrtc_room_join_handler: WebRTCRoomJoinHandler,
     webrtc_room_leave_handler: WebRTCRoomLeaveHandler,
+    webrtc_room_list_handler: WebRTCRoomListHandler,
+    where_am_i_handler: WhereAmIHandler,
+    /// Sink for room terminations found by the sweeps; the handlers hold their own clone
+    event_client: Arc<dyn EventClient>,
+    /// Whether `event_client` is the publisher `events.backend` selects, reported by `/readyz`
+    event_publisher_health: ComponentHealth,
+    spawn_background_tasks: bool,
+    background_tasks: Arc<std::sync::Mutex<Vec<JoinHandle<()>>>>,
+    listening: Arc<AtomicBool>,
+    health_repository_factory: Option<Arc<dyn RepositoryFactory>>,
+    /// Repositories the retention sweep prunes; built from Firestore on the first sweep when unset
+    retention_repositories: Option<RetentionRepositories>,
+    /// Media backend shared by the room handlers, probed by the health report
+    provider: Arc<dyn SignalingProvider>,
+    /// Last dependency probes; held while probing so concurrent `/readyz` requests share one
+    dependency_health: Arc<Mutex<Option<DependencyHealth>>>,
+    metrics: Arc<Metrics>,
+    handshake_limiter: Arc<HandshakeLimiter>,
+    connection_limiter: Arc<ConnectionLimiter>,
+    message_rate_limiter: Arc<MessageRateLimiter>,
+    /// Set once the server is shutting down, telling every open connection to close
+    shutdown: Arc<watch::Sender<bool>>,
 }
 
 impl WebSocketServer {
     pub fn new(config: Config) -> Result<Self, crate::Error> {
+        Self::build(config, true)
+    }
+
+    /// Build a server that never spawns background tasks: no message routing and no
+    /// room sweeps, so signal messages are not relayed. For deterministic tests.
+    pub fn new_for_test(config: Config) -> Result<Self, crate::Error> {
+        Self::build(config, false)
+    }
+
+    fn build(config: Config, spawn_background_tasks: bool) -> Result<Self, crate::Error> {
+        if !config.server.enabled_codecs.contains(&PayloadType::Json) {
+            return Err(crate::Error::Config(config::ConfigError::Message(
+                "server.enabled_codecs must include JSON, the encoding of server replies".to_string(),
+            )));
+        }
         let config = Arc::new(config);
         let auth_manager = Arc::new(AuthManager::new(config.clone()));
+        let metrics = Arc::new(Metrics::new());
         let (session_manager, message_receiver) = SessionManager::new(auth_manager.clone());
-        let session_manager = Arc::new(session_manager);
+        let session_manager = Arc::new(
+            session_manager
+                .with_max_ice_candidates_per_room(config.webrtc.max_ice_candidates_per_room)
+                .with_persist_sdp(config.webrtc.persist_sdp)
+                .with_metrics(metrics.clone())
+                .with_heartbeat_interval(config.server.heartbeat_interval)
+                .with_session_timeout(config.session.session_timeout),
+        );
 
         // Initialize handlers
-        let register_handler = RegisterHandler::new(config.clone());
-        let webrtc_room_create_handler = WebRTCRoomCreateHandler::new(config.clone());
-        let webrtc_room_join_handler = WebRTCRoomJoinHandler::new(config.clone());
-        let webrtc_room_leave_handler = WebRTCRoomLeaveHandler::new(config.clone());
+        let (event_client, event_publisher_health) = match crate::events::create_event_client(&config) {
+            Ok(event_client) => (event_client, ComponentHealth::ok()),
+            Err(e) => {
+                warn!("[EVENTS] Event publisher unavailable, lifecycle events will be dropped: {}", e);
+                let noop: Arc<dyn EventClient> = Arc::new(crate::events::NoopEventClient);
+                (noop, ComponentHealth::degraded(format!("Event publisher unavailable: {e}")))
+            }
+        };
+        let event_client: Arc<dyn EventClient> = crate::events::OrderedEventClient::new(event_client);
+        let register_handler = RegisterHandler::new(config.clone()).with_event_client(event_client.clone());
+        let provider = crate::signaling::create_provider(config.clone())
+            .map_err(|e| crate::Error::Connection(format!("Failed to create signaling provider: {e}")))?;
+        let webrtc_room_create_handler = WebRTCRoomCreateHandler::new(config.clone())
+            .with_provider(provider.clone())
+            .with_event_client(event_client.clone());
+        let webrtc_room_join_handler = WebRTCRoomJoinHandler::new(config.clone())
+            .with_provider(provider.clone())
+            .with_auth_manager(auth_manager.clone())
+            .with_event_client(event_client.clone());
+        let webrtc_room_leave_handler = WebRTCRoomLeaveHandler::new(config.clone())
+            .with_provider(provider.clone())
+            .with_event_client(event_client.clone());
+        let webrtc_room_list_handler = WebRTCRoomListHandler::new(config.clone());
+        let where_am_i_handler = WhereAmIHandler::new(config.clone());
 
         // Initialize TLS if enabled
         let tls_acceptor = if config.server.tls_enabled {
@@ -72,12 +292,14 @@ impl WebSocketServer {
         let connections_clone = Arc::new(RwLock::new(HashMap::new()));
         let connections_for_task = connections_clone.clone();
         
-        tokio::spawn(async move {
-            Self::message_routing_task(message_receiver, session_manager_clone, connections_for_task).await;
-        });
+        let mut background_tasks = Vec::new();
+        if spawn_background_tasks {
+            background_tasks.push(spawn(async move {
+                Self::message_routing_task(message_receiver, session_manager_clone, connections_for_task).await;
+            }.instrument(instance_span(&config))));
+        }
 
         Ok(Self {
-            config,
             auth_manager,
             session_manager,
             connections: connections_clone,
@@ -86,55 +308,228 @@ impl WebSocketServer {
             webrtc_room_create_handler,
             webrtc_room_join_handler,
             webrtc_room_leave_handler,
+            webrtc_room_list_handler,
+            where_am_i_handler,
+            event_client,
+            event_publisher_health,
+            spawn_background_tasks,
+            background_tasks: Arc::new(std::sync::Mutex::new(background_tasks)),
+            listening: Arc::new(AtomicBool::new(false)),
+            health_repository_factory: None,
+            retention_repositories: None,
+            provider,
+            dependency_health: Arc::new(Mutex::new(None)),
+            metrics,
+            handshake_limiter: Arc::new(HandshakeLimiter::new(
+                config.server.max_concurrent_handshakes,
+                config.server.max_queued_handshakes,
+            )),
+            connection_limiter: Arc::new(ConnectionLimiter::new(
+                config.server.max_connections,
+                if config.security.rate_limit_enabled { config.security.max_connections_per_ip } else { 0 },
+            )),
+            message_rate_limiter: Arc::new(MessageRateLimiter::new(
+                if config.security.rate_limit_enabled { config.security.max_messages_per_minute } else { 0 },
+            )),
+            shutdown: Arc::new(watch::channel(false).0),
+            live_config: Arc::new(std::sync::RwLock::new(config.clone())),
+            config,
         })
     }
 
-    fn init_tls_acceptor(config: &Config) -> Result<Option<TokioTlsAcceptor>, crate::Error> {
-        if !config.server.tls_enabled {
-            return Ok(None);
-        }
+    /// The running configuration, including settings changed by `reload_config`
+    pub fn current_config(&self) -> Arc<Config> {
+        self.live_config.read().unwrap().clone()
+    }
 
-        if config.server.tls_cert_path.is_empty() || config.server.tls_key_path.is_empty() {
-            return Err(crate::Error::Config(config::ConfigError::NotFound(
-                "TLS certificate or key path not configured".to_string()
-            )));
+    /// Apply the `RELOADABLE_SETTINGS` of `reloaded` to the running server. Rate limits take
+    /// effect on the next message or connection, session timers on the next sweep or drop, and
+    /// the session timeout advertised in ConnectAck on the next Connect.
+    /// Other changed settings are reported in `ignored` and keep their running values.
+    pub fn reload_config(&self, reloaded: &Config) -> ConfigReload {
+        let mut live = self.live_config.write().unwrap();
+        let (applied, report) = live.reload_from(reloaded);
+        let security = &applied.security;
+        self.message_rate_limiter.set_max_per_minute(
+            if security.rate_limit_enabled { security.max_messages_per_minute } else { 0 },
+        );
+        self.connection_limiter.set_max_per_address(
+            if security.rate_limit_enabled { security.max_connections_per_ip } else { 0 },
+        );
+        self.session_manager.set_session_timeout(applied.session.session_timeout);
+        *live = Arc::new(applied);
+
+        if report.applied.is_empty() {
+            info!("[CONFIG] Reloaded configuration, no reloadable settings changed");
+        } else {
+            info!("[CONFIG] Reloaded configuration: {}", report.applied.join(", "));
         }
+        if !report.ignored.is_empty() {
+            warn!("[CONFIG] Changes to {} need a restart to take effect", report.ignored.join(", "));
+        }
+        report
+    }
 
-        // Load certificate and private key
-        let mut cert_file = File::open(&config.server.tls_cert_path)
-            .map_err(|e| crate::Error::Io(std::io::Error::other(e)))?;
-        let mut key_file = File::open(&config.server.tls_key_path)
-            .map_err(|e| crate::Error::Io(std::io::Error::other(e)))?;
+    /// Background tasks (message routing, room sweeps) spawned by this server that are still running
+    pub fn running_background_tasks(&self) -> usize {
+        self.background_tasks.lock().unwrap().iter().filter(|task| !task.is_finished()).count()
+    }
 
-        let mut cert_data = Vec::new();
-        let mut key_data = Vec::new();
-        
-        cert_file.read_to_end(&mut cert_data)
-            .map_err(crate::Error::Io)?;
-        key_file.read_to_end(&mut key_data)
-            .map_err(crate::Error::Io)?;
+    /// Answer `WhereAmI` queries from `repositories` instead of the Firestore-backed ones
+    pub fn with_where_am_i_repositories(mut self, repositories: WhereAmIRepositories) -> Self {
+        self.where_am_i_handler = self.where_am_i_handler.with_repositories(repositories);
+        self
+    }
 
-        let identity = Identity::from_pkcs8(&cert_data, &key_data)
-            .map_err(|e| crate::Error::Config(config::ConfigError::NotFound(e.to_string())))?;
+    /// Publish lifecycle events on `event_client` instead of the sink `events.backend` selects,
+    /// one at a time in the order they happen
+    pub fn with_event_client(mut self, event_client: Arc<dyn EventClient>) -> Self {
+        let event_client: Arc<dyn EventClient> = crate::events::OrderedEventClient::new(event_client);
+        self.register_handler = self.register_handler.with_event_client(event_client.clone());
+        self.webrtc_room_create_handler = self.webrtc_room_create_handler.with_event_client(event_client.clone());
+        self.webrtc_room_join_handler = self.webrtc_room_join_handler.with_event_client(event_client.clone());
+        self.webrtc_room_leave_handler = self.webrtc_room_leave_handler.with_event_client(event_client.clone());
+        self.event_client = event_client;
+        self.event_publisher_health = ComponentHealth::ok();
+        self
+    }
+
+    /// Handle `WebRTCRoomCreate` requests against `repositories` instead of the Firestore-backed ones
+    pub fn with_room_create_repositories(mut self, repositories: RoomCreateRepositories) -> Self {
+        self.webrtc_room_create_handler = self.webrtc_room_create_handler.with_repositories(repositories);
+        self
+    }
 
-        let acceptor = TlsAcceptor::builder(identity)
-            .build()
-            .map_err(|e| crate::Error::Config(config::ConfigError::NotFound(e.to_string())))?;
+    /// Handle `WebRTCRoomJoin` requests against `repositories` instead of the Firestore-backed ones
+    pub fn with_room_join_repositories(mut self, repositories: RoomJoinRepositories) -> Self {
+        self.webrtc_room_join_handler = self.webrtc_room_join_handler.with_repositories(repositories);
+        self
+    }
 
-        let tokio_acceptor = TokioTlsAcceptor::from(acceptor);
+    /// Handle `WebRTCRoomLeave` requests against `repositories` instead of the Firestore-backed ones
+    pub fn with_room_leave_repositories(mut self, repositories: RoomLeaveRepositories) -> Self {
+        self.webrtc_room_leave_handler = self.webrtc_room_leave_handler.with_repositories(repositories);
+        self
+    }
 
-        info!("TLS acceptor initialized successfully");
-        Ok(Some(tokio_acceptor))
+    /// Answer `WebRTCRoomList` requests from `repositories` instead of the Firestore-backed ones
+    pub fn with_room_list_repositories(mut self, repositories: RoomListRepositories) -> Self {
+        self.webrtc_room_list_handler = self.webrtc_room_list_handler.with_repositories(repositories);
+        self
+    }
+
+    /// Probe repository reachability for the health report through `factory` instead of the Firestore-backed one
+    pub fn with_health_repository_factory(mut self, factory: Arc<dyn RepositoryFactory>) -> Self {
+        self.health_repository_factory = Some(factory);
+        self
+    }
+
+    /// Prune terminated rooms from `repositories` instead of the Firestore-backed ones
+    pub fn with_retention_repositories(mut self, repositories: RetentionRepositories) -> Self {
+        self.retention_repositories = Some(repositories);
+        self
+    }
+
+    /// Open room sessions on, and probe the health of, `provider` instead of the one `webrtc.provider` selects
+    pub fn with_signaling_provider(mut self, provider: Arc<dyn SignalingProvider>) -> Self {
+        self.webrtc_room_create_handler = self.webrtc_room_create_handler.with_provider(provider.clone());
+        self.webrtc_room_join_handler = self.webrtc_room_join_handler.with_provider(provider.clone());
+        self.webrtc_room_leave_handler = self.webrtc_room_leave_handler.with_provider(provider.clone());
+        self.provider = provider;
+        self
+    }
+
+    fn init_tls_acceptor(config: &Config) -> Result<Option<TlsAcceptor>, crate::Error> {
+        if !config.server.tls_enabled {
+            return Ok(None);
+        }
+
+        let acceptor = TlsAcceptor::from_config(&config.server)?;
+        info!("TLS acceptor initialized successfully ({:?})", acceptor.backend());
+        Ok(Some(acceptor))
     }
 
     pub async fn run(&self) -> Result<(), crate::Error> {
+        self.run_with_shutdown(std::future::pending()).await
+    }
+
+    /// Like `run`, but shut down gracefully once `shutdown` completes: see `serve_with_shutdown`
+    pub async fn run_with_shutdown(&self, shutdown: impl std::future::Future<Output = ()>) -> Result<(), crate::Error> {
+        self.run_in_span(shutdown).instrument(instance_span(&self.config)).await
+    }
+
+    async fn run_in_span(&self, shutdown: impl std::future::Future<Output = ()>) -> Result<(), crate::Error> {
         let addr = self.config.socket_addr();
         let listener = TcpListener::bind(&addr).await?;
-        
+
+        if self.config.server.readyz_port > 0 {
+            let readyz_addr = format!("{}:{}", self.config.server.host, self.config.server.readyz_port);
+            let readyz_listener = TcpListener::bind(&readyz_addr).await?;
+            let server = self.clone();
+            spawn(async move { server.serve_readyz(readyz_listener).await });
+        }
+
+        if self.config.metrics.enabled {
+            let metrics_listener = TcpListener::bind(self.config.metrics_addr()).await?;
+            info!("Metrics available on http://{}/metrics", self.config.metrics_addr());
+            let server = self.clone();
+            spawn(async move { server.serve_metrics(metrics_listener).await });
+
+            let mut stats_tasks = Vec::new();
+            if self.config.metrics.connection_stats_interval > 0 {
+                stats_tasks.push(spawn(self.clone().connection_stats_task()));
+            }
+            if self.config.metrics.message_stats_interval > 0 {
+                stats_tasks.push(spawn(self.clone().message_stats_task()));
+            }
+            self.background_tasks.lock().unwrap().extend(stats_tasks);
+        }
+
+        self.serve_in_span(listener, shutdown).await
+    }
+
+    /// Accept connections on an already-bound listener. Lets callers bind an
+    /// ephemeral port (e.g. `127.0.0.1:0`) and read the address before serving.
+    pub async fn serve(&self, listener: TcpListener) -> Result<(), crate::Error> {
+        self.serve_with_shutdown(listener, std::future::pending()).await
+    }
+
+    /// Like `serve`, until `shutdown` completes. Then stop accepting connections, send every
+    /// open connection a `Disconnect` and a close frame, wait up to `server.shutdown_grace_secs`
+    /// for the connections to finish, stop the background tasks and return.
+    pub async fn serve_with_shutdown(&self, listener: TcpListener, shutdown: impl std::future::Future<Output = ()>) -> Result<(), crate::Error> {
+        self.serve_in_span(listener, shutdown).instrument(instance_span(&self.config)).await
+    }
+
+    async fn serve_in_span(&self, listener: TcpListener, shutdown: impl std::future::Future<Output = ()>) -> Result<(), crate::Error> {
+        let addr = listener.local_addr()?;
         info!("WebSocket server listening on {} (TLS: {})", addr, self.config.server.tls_enabled);
+        self.listening.store(true, Ordering::SeqCst);
 
+        let webrtc = &self.config.webrtc;
+        if self.spawn_background_tasks && (webrtc.max_room_lifetime_secs > 0 || webrtc.answer_timeout_secs > 0) {
+            let task = spawn(self.clone().room_expiry_task());
+            self.background_tasks.lock().unwrap().push(task);
+        }
+        // Spawned even while disabled, so a reloaded `session_timeout` can turn the sweep on
+        if self.spawn_background_tasks {
+            let task = spawn(self.clone().session_expiry_task());
+            self.background_tasks.lock().unwrap().push(task);
+        }
+        if self.spawn_background_tasks && self.config.session.terminated_room_retention_secs > 0 {
+            let task = spawn(self.clone().retention_task());
+            self.background_tasks.lock().unwrap().push(task);
+        }
+
+        // Every connection task holds a sender; the channel closes once they have all finished
+        let (connections_open, mut connections_closed) = mpsc::channel::<()>(1);
+        tokio::pin!(shutdown);
         loop {
-            match listener.accept().await {
+            let accepted = tokio::select! {
+                _ = &mut shutdown => break,
+                accepted = listener.accept() => accepted,
+            };
+            match accepted {
                 Ok((stream, addr)) => {
                     info!("[CONNECTION] New TCP connection from {}", addr);
                     
@@ -143,8 +538,26 @@ impl WebSocketServer {
                     let tls_acceptor = self.tls_acceptor.clone();
                     
                     let server = self.clone();
-                    tokio::spawn(async move {
-                        if let Err(e) = server.handle_connection(stream, session_manager, connections, tls_acceptor).await {
+                    let connection_open = connections_open.clone();
+                    spawn(async move {
+                        let _connection_open = connection_open;
+                        let queue_wait = std::time::Duration::from_secs(server.config.server.tls_handshake_timeout_secs);
+                        let connection = match server.config.server.connection_limit_policy {
+                            ConnectionLimitPolicy::Reject => server.connection_limiter.try_acquire(addr.ip()),
+                            ConnectionLimitPolicy::Queue => server.connection_limiter.acquire(addr.ip(), queue_wait).await,
+                        };
+                        match connection {
+                            Err(ConnectionRefused::AtCapacity) => warn!("[CONNECTION] {} connections already open, rejecting connection from {}",
+                                server.config.server.max_connections, addr),
+                            Err(ConnectionRefused::AddressLimit) => warn!("[CONNECTION] {} connections already open from {}, rejecting connection",
+                                server.config.security.max_connections_per_ip, addr.ip()),
+                            Ok(_) => {}
+                        }
+                        let Some(slot) = server.handshake_limiter.acquire(queue_wait).await else {
+                            warn!("[CONNECTION] Too many handshakes in progress, closing connection from {}", addr);
+                            return;
+                        };
+                        if let Err(e) = server.handle_connection(stream, slot, connection, session_manager, connections, tls_acceptor).await {
                             error!("[CONNECTION] Connection error from {}: {}", addr, e);
                         }
                     });
@@ -154,21 +567,456 @@ impl WebSocketServer {
                 }
             }
         }
+
+        info!("[SHUTDOWN] Shutting down, no longer accepting connections on {}", addr);
+        drop(listener);
+        self.listening.store(false, Ordering::SeqCst);
+        self.shutdown.send_replace(true);
+        drop(connections_open);
+        let grace = std::time::Duration::from_secs(self.config.server.shutdown_grace_secs);
+        if tokio::time::timeout(grace, connections_closed.recv()).await.is_err() {
+            warn!("[SHUTDOWN] Connections still open after {}s, shutting down anyway", grace.as_secs());
+        }
+        for task in self.background_tasks.lock().unwrap().drain(..) {
+            task.abort();
+        }
+        let flushed = self.event_client.flush().await;
+        if !flushed.is_success() {
+            warn!("[SHUTDOWN] {} buffered events could not be published", flushed.failed.len());
+        }
+        info!("[SHUTDOWN] Server stopped");
+        Ok(())
+    }
+
+    /// Number of outbound frames dropped for a connected client by the overflow policy
+    pub async fn dropped_frames(&self, client_id: &str) -> Option<u64> {
+        let connections = self.connections.read().await;
+        connections.get(client_id).map(|queue| queue.dropped_count())
+    }
+
+    /// Whether `client_id` has an entry in the connections map
+    pub async fn is_connected(&self, client_id: &str) -> bool {
+        self.connections.read().await.contains_key(client_id)
+    }
+
+    pub fn session_manager(&self) -> Arc<SessionManager> {
+        self.session_manager.clone()
+    }
+
+    /// Limiter bounding concurrent TLS/WebSocket handshakes
+    pub fn handshake_limiter(&self) -> Arc<HandshakeLimiter> {
+        self.handshake_limiter.clone()
+    }
+
+    /// Limiter shared by all connections counting messages against `max_messages_per_minute`
+    pub fn message_rate_limiter(&self) -> Arc<MessageRateLimiter> {
+        self.message_rate_limiter.clone()
+    }
+
+    /// Limiter bounding open connections to `max_connections`, and per address to
+    /// `max_connections_per_ip` when rate limiting is enabled
+    pub fn connection_limiter(&self) -> Arc<ConnectionLimiter> {
+        self.connection_limiter.clone()
+    }
+
+    /// Sessions currently held by connected clients
+    pub async fn active_sessions(&self) -> Vec<ClientSession> {
+        self.session_manager.get_active_sessions().await
+    }
+
+    /// Terminate rooms older than `webrtc.max_room_lifetime_secs` and tell their connected
+    /// participants with an unsolicited `WebRTCRoomLeaveAck`
+    pub async fn expire_rooms(&self, repositories: &RoomExpiryRepositories) -> DatabaseResult<Vec<ExpiredRoom>> {
+        let Some(max_lifetime) = checked_period(self.config.webrtc.max_room_lifetime_secs) else {
+            return Ok(Vec::new());
+        };
+        let expired = room_expiry::terminate_expired_rooms(repositories, max_lifetime, chrono::Utc::now()).await?;
+        self.notify_terminated_rooms(&expired, room_expiry::EXPIRY_REASON).await;
+        Ok(expired)
+    }
+
+    /// Terminate sender-only rooms no receiver joined within `webrtc.answer_timeout_secs`
+    /// and tell the waiting sender with an unsolicited `WebRTCRoomLeaveAck`
+    pub async fn expire_unanswered_rooms(&self, repositories: &RoomExpiryRepositories) -> DatabaseResult<Vec<ExpiredRoom>> {
+        let Some(answer_timeout) = checked_period(self.config.webrtc.answer_timeout_secs) else {
+            return Ok(Vec::new());
+        };
+        let expired = room_expiry::terminate_unanswered_rooms(repositories, answer_timeout, chrono::Utc::now()).await?;
+        self.notify_terminated_rooms(&expired, room_expiry::ANSWER_TIMEOUT_REASON).await;
+        Ok(expired)
+    }
+
+    /// Delete terminated rooms and their former clients' records older than
+    /// `session.terminated_room_retention_secs`
+    pub async fn sweep_terminated_room_retention(&self, repositories: &RetentionRepositories) -> DatabaseResult<RetentionSweep> {
+        // `Config::validate` rejects periods this cannot represent; a sweep with one deletes nothing
+        let cutoff = checked_period(self.config.session.terminated_room_retention_secs)
+            .and_then(|retention| chrono::Utc::now().checked_sub_signed(retention));
+        match cutoff {
+            Some(cutoff) => database::delete_terminated_records_before(repositories, cutoff).await,
+            None => Ok(RetentionSweep::default()),
+        }
+    }
+
+    async fn notify_terminated_rooms(&self, expired: &[ExpiredRoom], reason: &str) {
+        for room in expired {
+            self.session_manager.remove_room_state(&room.room_id).await;
+            for session_id in &room.session_ids {
+                match self.provider.close_session(session_id, &room.room_id).await {
+                    Ok(()) => info!("[ROOM_EXPIRY] Closed provider session {} of room {}", session_id, room.room_id),
+                    Err(e) => warn!("[ROOM_EXPIRY] Failed to close provider session {} of room {}: {}", session_id, room.room_id, e),
+                }
+            }
+            crate::events::publish_in_background(&self.event_client, crate::events::EventMessage::new(
+                crate::events::ROOM_TERMINATED,
+                serde_json::json!({ "room_id": room.room_id, "reason": reason }),
+            ));
+            let connections = self.connections.read().await;
+            for client_id in &room.participants {
+                let Some(tx) = connections.get(client_id) else { continue };
+                let notification = Message::new(
+                    MessageType::WebRTCRoomLeaveAck,
+                    Payload::WebRTCRoomLeaveAck(crate::message::WebRTCRoomLeaveAckPayload {
+                        version: crate::webrtc_handlers::room_leave::CURRENT_VERSION.to_string(),
+                        status: 200,
+                        message: Some(reason.to_string()),
+                        room_id: Some(room.room_id.clone()),
+                        client_id: Some(client_id.clone()),
+                    }),
+                );
+                if let Err(e) = tx.push(notification) {
+                    warn!("[ROOM_EXPIRY] Failed to notify {} of room {} termination: {}", client_id, room.room_id, e);
+                }
+            }
+        }
+    }
+
+    /// End sessions with no heartbeat for `session.session_timeout` seconds. Each client still
+    /// connected loses its connections entry and is sent a `Disconnect` followed by a 1008 close,
+    /// so the socket cannot keep relaying under the expired id. Returns the client ids whose
+    /// sessions ended.
+    pub async fn expire_idle_sessions(&self) -> Vec<String> {
+        let timeout = std::time::Duration::from_secs(self.current_config().session.session_timeout);
+        // Held across the sweep so a reconnect can't register between the session and its entry
+        let mut connections = self.connections.write().await;
+        let expired = self.session_manager.cleanup_expired_sessions(timeout).await;
+        for client_id in &expired {
+            let Some(tx) = connections.remove(client_id) else { continue };
+            let disconnect = Message::new(
+                MessageType::Disconnect,
+                Payload::Disconnect(crate::message::DisconnectPayload {
+                    client_id: client_id.clone(),
+                    reason: SESSION_TIMEOUT_REASON.to_string(),
+                }),
+            );
+            if let Err(e) = tx.push(disconnect) {
+                warn!("[SESSION_EXPIRY] Failed to notify {} of its session timing out: {}", client_id, e);
+            }
+            tx.close_after_drain(CloseFrame { code: CloseCode::Policy, reason: SESSION_TIMEOUT_REASON.into() });
+        }
+        expired
+    }
+
+    async fn session_expiry_task(self) {
+        loop {
+            let session = self.current_config().session.clone();
+            tokio::time::sleep(std::time::Duration::from_secs(session.cleanup_interval.max(1))).await;
+            let retention = self.config.session.last_seen_retention_secs;
+            if retention > 0 {
+                let pruned = self.session_manager.prune_disconnected(std::time::Duration::from_secs(retention)).await;
+                if pruned > 0 {
+                    debug!("[SESSION_EXPIRY] Forgot the last-seen time of {} disconnected clients", pruned);
+                }
+            }
+            if self.current_config().session.session_timeout == 0 {
+                continue;
+            }
+            let expired = self.expire_idle_sessions().await;
+            if !expired.is_empty() {
+                info!("[SESSION_EXPIRY] Ended {} idle sessions: {:?}", expired.len(), expired);
+            }
+        }
+    }
+
+    async fn room_expiry_task(self) {
+        loop {
+            let interval = std::time::Duration::from_secs(self.current_config().session.cleanup_interval.max(1));
+            tokio::time::sleep(interval).await;
+            let factory = FirestoreRepositoryFactory::new(self.config.clone());
+            let repositories = match RoomExpiryRepositories::from_factory(&factory).await {
+                Ok(repositories) => repositories,
+                Err(e) => {
+                    error!("[ROOM_EXPIRY] Room sweep failed: {}", e);
+                    continue;
+                }
+            };
+            if self.config.webrtc.max_room_lifetime_secs > 0 {
+                match self.expire_rooms(&repositories).await {
+                    Ok(expired) if !expired.is_empty() => info!("[ROOM_EXPIRY] Terminated {} expired rooms", expired.len()),
+                    Ok(_) => {}
+                    Err(e) => error!("[ROOM_EXPIRY] Room lifetime sweep failed: {}", e),
+                }
+            }
+            if self.config.webrtc.answer_timeout_secs > 0 {
+                match self.expire_unanswered_rooms(&repositories).await {
+                    Ok(expired) if !expired.is_empty() => info!("[ROOM_EXPIRY] Terminated {} unanswered rooms", expired.len()),
+                    Ok(_) => {}
+                    Err(e) => error!("[ROOM_EXPIRY] Answer timeout sweep failed: {}", e),
+                }
+            }
+        }
+    }
+
+    async fn retention_task(self) {
+        // Built once, on the first sweep that can reach the database, and shared by every later sweep
+        let mut shared = self.retention_repositories.clone();
+        loop {
+            let interval = std::time::Duration::from_secs(self.current_config().session.cleanup_interval.max(1));
+            tokio::time::sleep(interval).await;
+            let repositories = match &shared {
+                Some(repositories) => repositories,
+                None => {
+                    let factory = FirestoreRepositoryFactory::new(self.config.clone());
+                    match RetentionRepositories::from_factory(&factory).await {
+                        Ok(repositories) => shared.insert(repositories),
+                        Err(e) => {
+                            error!("[RETENTION] Retention sweep failed: {}", e);
+                            continue;
+                        }
+                    }
+                }
+            };
+            match self.sweep_terminated_room_retention(repositories).await {
+                Ok(sweep) if sweep != RetentionSweep::default() => info!(
+                    "[RETENTION] Deleted {} terminated rooms and {} client records",
+                    sweep.rooms_deleted, sweep.clients_deleted
+                ),
+                Ok(_) => {}
+                Err(e) => error!("[RETENTION] Retention sweep failed: {}", e),
+            }
+        }
+    }
+
+    /// Log connection counts every `metrics.connection_stats_interval` seconds
+    async fn connection_stats_task(self) {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(self.config.metrics.connection_stats_interval));
+        interval.tick().await;
+        let mut sampler = StatsSampler::new();
+        loop {
+            interval.tick().await;
+            let stats = sampler.connection_stats(&self.metrics);
+            info!(active = stats.active, opened = stats.opened, closed = stats.closed,
+                "[STATS] {} connections open, {} opened and {} closed since last report", stats.active, stats.opened, stats.closed);
+        }
+    }
+
+    /// Log messages received per type every `metrics.message_stats_interval` seconds
+    async fn message_stats_task(self) {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(self.config.metrics.message_stats_interval));
+        interval.tick().await;
+        let mut sampler = StatsSampler::new();
+        loop {
+            interval.tick().await;
+            let stats = sampler.message_stats(&self.metrics);
+            let by_type = stats.by_type.iter()
+                .map(|(message_type, count)| format!("{:?}={}", message_type, count))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let frame_sizes = stats.frame_sizes.iter().enumerate()
+                .filter(|(_, count)| **count > 0)
+                .map(|(i, count)| match FRAME_SIZE_BUCKETS.get(i) {
+                    Some(bound) => format!("le{}={}", bound, count),
+                    None => format!("inf={}", count),
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            info!(total = stats.total, by_type = %by_type, frame_sizes = %frame_sizes,
+                "[STATS] {} messages received since last report", stats.total);
+        }
+    }
+
+    /// Status of the listener, message routing, repositories, Cloudflare and the event publisher
+    pub async fn health_report(&self) -> HealthReport {
+        let listener = if self.listening.load(Ordering::SeqCst) {
+            ComponentHealth::ok()
+        } else {
+            ComponentHealth::down("Not accepting connections")
+        };
+
+        // The routing task is the first background task spawned by `build`
+        let routing_task = if !self.spawn_background_tasks {
+            ComponentHealth::degraded("Message routing disabled in test mode")
+        } else if self.background_tasks.lock().unwrap().first().is_some_and(|task| !task.is_finished()) {
+            ComponentHealth::ok()
+        } else {
+            ComponentHealth::down("Message routing task has stopped")
+        };
+
+        let (repositories, cloudflare) = self.dependency_health().await;
+        HealthReport::new(
+            listener,
+            routing_task,
+            repositories,
+            cloudflare,
+            self.event_publisher_health.clone(),
+        )
+    }
+
+    /// Repository and signaling provider health, probed at most once per `HEALTH_CACHE_TTL`
+    async fn dependency_health(&self) -> (ComponentHealth, ComponentHealth) {
+        let mut cached = self.dependency_health.lock().await;
+        if let Some(health) = cached.as_ref().filter(|health| health.checked_at.elapsed() < HEALTH_CACHE_TTL) {
+            return (health.repositories.clone(), health.provider.clone());
+        }
+        let (repositories, provider) = tokio::join!(self.repository_health(), self.provider_health());
+        *cached = Some(DependencyHealth {
+            checked_at: std::time::Instant::now(),
+            repositories: repositories.clone(),
+            provider: provider.clone(),
+        });
+        (repositories, provider)
+    }
+
+    async fn repository_health(&self) -> ComponentHealth {
+        let probe = async {
+            let rooms = match &self.health_repository_factory {
+                Some(factory) => factory.create_webrtc_room_repository().await?,
+                None => FirestoreRepositoryFactory::new(self.config.clone()).create_webrtc_room_repository().await?,
+            };
+            rooms.get_active_rooms().await
+        };
+        match tokio::time::timeout(HEALTH_PROBE_TIMEOUT, probe).await {
+            Ok(Ok(_)) => ComponentHealth::ok(),
+            Ok(Err(e)) => ComponentHealth::degraded(format!("Repository unreachable: {e}")),
+            Err(_) => ComponentHealth::degraded("Repository probe timed out"),
+        }
+    }
+
+    async fn provider_health(&self) -> ComponentHealth {
+        match tokio::time::timeout(HEALTH_PROBE_TIMEOUT, self.provider.check_health()).await {
+            Ok(Ok(true)) => ComponentHealth::ok(),
+            Ok(Ok(false)) => ComponentHealth::degraded("Signaling provider rejected the configured credentials"),
+            Ok(Err(e)) => ComponentHealth::degraded(format!("Signaling provider unreachable: {e}")),
+            Err(_) => ComponentHealth::degraded("Signaling provider probe timed out"),
+        }
+    }
+
+    /// Answer `GET /readyz` on `listener` with the health report as JSON: 200 while the
+    /// server is ready (possibly degraded), 503 when any component is down
+    pub async fn serve_readyz(&self, listener: TcpListener) {
+        loop {
+            let (mut stream, addr) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    error!("[READYZ] Accept error: {}", e);
+                    continue;
+                }
+            };
+            let server = self.clone();
+            spawn(async move {
+                let mut buf = [0u8; 1024];
+                let n = match stream.read(&mut buf).await {
+                    Ok(n) => n,
+                    Err(e) => {
+                        warn!("[READYZ] Failed to read request from {}: {}", addr, e);
+                        return;
+                    }
+                };
+                let request = String::from_utf8_lossy(&buf[..n]);
+                let (status_line, body) = if request.starts_with("GET /readyz ") {
+                    let report = server.health_report().await;
+                    let status_line = if report.is_ready() { "200 OK" } else { "503 Service Unavailable" };
+                    (status_line, serde_json::to_string(&report).unwrap_or_default())
+                } else {
+                    ("404 Not Found", String::new())
+                };
+                if let Err(e) = write_http_response(&mut stream, status_line, "application/json", &body).await {
+                    warn!("[READYZ] Failed to answer {}: {}", addr, e);
+                }
+            });
+        }
+    }
+
+    /// Counters shared by every connection this server accepts
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.metrics.clone()
+    }
+
+    /// Answer `GET /metrics` on `listener` with the counters in Prometheus text format
+    pub async fn serve_metrics(&self, listener: TcpListener) {
+        loop {
+            let (mut stream, addr) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    error!("[METRICS] Accept error: {}", e);
+                    continue;
+                }
+            };
+            let metrics = self.metrics.clone();
+            spawn(async move {
+                let mut buf = [0u8; 1024];
+                let n = match stream.read(&mut buf).await {
+                    Ok(n) => n,
+                    Err(e) => {
+                        warn!("[METRICS] Failed to read request from {}: {}", addr, e);
+                        return;
+                    }
+                };
+                let request = String::from_utf8_lossy(&buf[..n]);
+                let (status_line, body) = if request.starts_with("GET /metrics ") {
+                    ("200 OK", metrics.render())
+                } else {
+                    ("404 Not Found", String::new())
+                };
+                if let Err(e) = write_http_response(&mut stream, status_line, "text/plain; version=0.0.4", &body).await {
+                    warn!("[METRICS] Failed to answer {}: {}", addr, e);
+                }
+            });
+        }
+    }
+
+    /// Capabilities reported to clients in `ServerInfoAck`
+    pub fn server_info(config: &Config) -> ServerInfoAckPayload {
+        ServerInfoAckPayload {
+            server_version: env!("CARGO_PKG_VERSION").to_string(),
+            max_message_size: config.server.max_message_size,
+            max_frame_size: config.server.max_frame_size.min(config.server.max_message_size),
+            payload_types: PayloadType::SUPPORTED.into_iter()
+                .filter(|payload_type| config.server.enabled_codecs.contains(payload_type))
+                .collect(),
+            message_types: MessageType::ALL.to_vec(),
+            tls_enabled: config.server.tls_enabled,
+            compression_enabled: config.server.compression_threshold_bytes > 0,
+        }
+    }
+
+    /// Protocol-level size limits, `PROTOCOL_SIZE_SLACK` above the configured ones. Messages
+    /// within the slack reach the application check, which answers with error 9 and keeps the
+    /// connection; anything larger is refused by tungstenite before it is buffered.
+    fn websocket_config(config: &Config) -> WebSocketConfig {
+        let max_message_size = config.server.max_message_size;
+        WebSocketConfig {
+            max_message_size: Some(max_message_size.saturating_add(PROTOCOL_SIZE_SLACK)),
+            max_frame_size: Some(config.server.max_frame_size.min(max_message_size).saturating_add(PROTOCOL_SIZE_SLACK)),
+            ..Default::default()
+        }
     }
 
     async fn handle_connection(
         &self,
         stream: TcpStream,
+        slot: HandshakeSlot,
+        connection: Result<ConnectionSlot, ConnectionRefused>,
         session_manager: Arc<SessionManager>,
-        connections: Arc<RwLock<HashMap<String, tokio::sync::mpsc::Sender<Message>>>>,
-        tls_acceptor: Option<TokioTlsAcceptor>,
+        connections: Arc<RwLock<HashMap<String, Arc<OutboundQueue>>>>,
+        tls_acceptor: Option<TlsAcceptor>,
     ) -> Result<(), crate::Error> {
         info!("[CONNECTION] Processing connection - TLS enabled: {}", tls_acceptor.is_some());
         
         let result = if let Some(acceptor) = tls_acceptor {
-            self.handle_tls_connection(stream, session_manager, connections, acceptor).await
+            self.handle_tls_connection(stream, slot, connection, session_manager, connections, acceptor).await
         } else {
-            self.handle_plain_connection(stream, session_manager, connections).await
+            self.handle_plain_connection(stream, slot, connection, session_manager, connections).await
         };
         
         match &result {
@@ -182,52 +1030,122 @@ impl WebSocketServer {
     async fn handle_tls_connection(
         &self,
         stream: TcpStream,
+        slot: HandshakeSlot,
+        connection: Result<ConnectionSlot, ConnectionRefused>,
         session_manager: Arc<SessionManager>,
-        connections: Arc<RwLock<HashMap<String, tokio::sync::mpsc::Sender<Message>>>>,
-        acceptor: TokioTlsAcceptor,
+        connections: Arc<RwLock<HashMap<String, Arc<OutboundQueue>>>>,
+        acceptor: TlsAcceptor,
     ) -> Result<(), crate::Error> {
         info!("[CONNECTION] Attempting TLS handshake");
         
-        let tls_stream = acceptor.accept(stream).await
+        // Dropping the pending handshake on timeout closes the socket. The TLS handshake and the
+        // WebSocket upgrade after it share one deadline, so the socket holds its slot no longer
+        // than a plain one.
+        let handshake_timeout = std::time::Duration::from_secs(self.config.server.tls_handshake_timeout_secs);
+        let deadline = tokio::time::Instant::now() + handshake_timeout;
+        let tls_stream = tokio::time::timeout_at(deadline, acceptor.accept(stream)).await
+            .map_err(|_| {
+                warn!("[CONNECTION] TLS handshake timed out after {:?}", handshake_timeout);
+                crate::Error::Connection(format!("TLS handshake timed out after {handshake_timeout:?}"))
+            })?
             .map_err(|e| {
                 error!("[CONNECTION] TLS handshake failed: {}", e);
                 crate::Error::Connection(format!("TLS handshake failed: {e}"))
             })?;
         
         info!("[CONNECTION] TLS handshake successful, upgrading to WebSocket");
-        let ws_stream = accept_async(tls_stream).await
-            .map_err(|e| {
-                error!("[CONNECTION] WebSocket upgrade failed: {}", e);
-                crate::Error::Connection(format!("WebSocket upgrade failed: {e}"))
-            })?;
+        let ws_stream = self.accept_websocket(tls_stream, deadline).await?;
         
         info!("[CONNECTION] WebSocket connection established");
-        self.handle_ws_stream(ws_stream, session_manager, connections).await
+        drop(slot);
+        let connection = match connection {
+            Ok(connection) => connection,
+            Err(refused) => return self.reject_connection(ws_stream, refused).await,
+        };
+        self.handle_ws_stream(ws_stream, connection, session_manager, connections).await
     }
 
     async fn handle_plain_connection(
         &self,
         stream: TcpStream,
+        slot: HandshakeSlot,
+        connection: Result<ConnectionSlot, ConnectionRefused>,
         session_manager: Arc<SessionManager>,
-        connections: Arc<RwLock<HashMap<String, tokio::sync::mpsc::Sender<Message>>>>,
+        connections: Arc<RwLock<HashMap<String, Arc<OutboundQueue>>>>,
     ) -> Result<(), crate::Error> {
         info!("[CONNECTION] Upgrading plain TCP connection to WebSocket");
         
-        let ws_stream = accept_async(stream).await
+        let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(self.config.server.tls_handshake_timeout_secs);
+        let ws_stream = self.accept_websocket(stream, deadline).await?;
+        
+        info!("[CONNECTION] WebSocket connection established");
+        drop(slot);
+        let connection = match connection {
+            Ok(connection) => connection,
+            Err(refused) => return self.reject_connection(ws_stream, refused).await,
+        };
+        self.handle_ws_stream(ws_stream, connection, session_manager, connections).await
+    }
+
+    /// Complete the WebSocket upgrade on `stream` by `deadline`, answering 403 to a request whose
+    /// `Origin` is not in `security.allowed_origins`
+    async fn accept_websocket<S>(&self, stream: S, deadline: tokio::time::Instant) -> Result<WebSocketStream<S>, crate::Error>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let allowed_origins = self.current_config().security.allowed_origins.clone();
+        // tungstenite's handshake callback fixes the error type
+        #[allow(clippy::result_large_err)]
+        let check_origin = move |request: &Request, response: Response| {
+            let origin = request.headers().get(ORIGIN).map(|origin| origin.to_str().unwrap_or_default());
+            if origin_allowed(&allowed_origins, origin) {
+                return Ok(response);
+            }
+            warn!("[CONNECTION] Refusing WebSocket upgrade from origin {:?}", origin);
+            let mut refusal = ErrorResponse::new(Some(ORIGIN_REJECTED_REASON.to_string()));
+            *refusal.status_mut() = StatusCode::FORBIDDEN;
+            Err(refusal)
+        };
+        // A socket that never sends its upgrade request would otherwise hold a handshake slot forever
+        let upgrade = accept_hdr_async_with_config(stream, check_origin, Some(Self::websocket_config(&self.config)));
+        tokio::time::timeout_at(deadline, upgrade).await
+            .map_err(|_| {
+                warn!("[CONNECTION] WebSocket upgrade did not complete before the handshake deadline");
+                crate::Error::Connection("WebSocket upgrade timed out".to_string())
+            })?
             .map_err(|e| {
                 error!("[CONNECTION] WebSocket upgrade failed: {}", e);
                 crate::Error::Connection(format!("WebSocket upgrade failed: {e}"))
-            })?;
-        
-        info!("[CONNECTION] WebSocket connection established");
-        self.handle_ws_stream(ws_stream, session_manager, connections).await
+            })
+    }
+
+    /// Close an upgraded socket that was refused a connection slot with 1013 (Try Again Later),
+    /// waiting briefly for the client's close reply so the frame is not lost to a reset
+    async fn reject_connection<S>(&self, mut ws_stream: WebSocketStream<S>, refused: ConnectionRefused) -> Result<(), crate::Error>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let reason = match refused {
+            ConnectionRefused::AtCapacity => CAPACITY_REASON,
+            ConnectionRefused::AddressLimit => ADDRESS_LIMIT_REASON,
+        };
+        ws_stream.close(Some(CloseFrame {
+            code: CloseCode::Again,
+            reason: reason.into(),
+        })).await.map_err(|e| crate::Error::Connection(format!("Failed to send close frame: {e}")))?;
+        let close_wait = std::time::Duration::from_secs(self.config.server.tls_handshake_timeout_secs);
+        let _ = tokio::time::timeout(close_wait, async {
+            while let Some(Ok(_)) = ws_stream.next().await {}
+        }).await;
+        Ok(())
     }
 
     async fn handle_ws_stream<S>(
         &self,
         ws_stream: WebSocketStream<S>,
+        connection: ConnectionSlot,
         session_manager: Arc<SessionManager>,
-        connections: Arc<RwLock<HashMap<String, tokio::sync::mpsc::Sender<Message>>>>,
+        connections: Arc<RwLock<HashMap<String, Arc<OutboundQueue>>>>,
     ) -> Result<(), crate::Error>
     where
         S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
@@ -236,7 +1154,11 @@ impl WebSocketServer {
 
         let (ws_sender, mut ws_receiver) = ws_stream.split();
         let ws_sender = Arc::new(Mutex::new(ws_sender));
-        let (tx, mut rx) = tokio::sync::mpsc::channel::<Message>(100);
+        let tx = Arc::new(
+            OutboundQueue::new(self.config.server.outbound_queue_depth, self.config.server.outbound_overflow_policy)
+                .with_control_priority(self.config.server.prioritize_control_frames),
+        );
+        let rx = tx.clone();
         let client_id: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None));
         let session_manager_clone = session_manager.clone();
         let connections_clone = connections.clone();
@@ -247,47 +1169,285 @@ impl WebSocketServer {
         let webrtc_room_create_handler = self.webrtc_room_create_handler.clone();
         let webrtc_room_join_handler = self.webrtc_room_join_handler.clone();
         let webrtc_room_leave_handler = self.webrtc_room_leave_handler.clone();
-        let incoming_task = tokio::spawn(async move {
+        let webrtc_room_list_handler = self.webrtc_room_list_handler.clone();
+        let where_am_i_handler = self.where_am_i_handler.clone();
+        let duplicate_connect_policy = self.config.server.duplicate_connect_policy;
+        let connect_ack_order = self.config.server.connect_ack_order;
+        let validate_signal_base64 = self.config.security.validate_signal_base64;
+        let role_message_allowlist = self.config.security.role_message_allowlist.clone();
+        let required_capabilities = self.config.security.required_capabilities.clone();
+        let enforce_capabilities = self.config.security.enforce_capabilities;
+        // Declared by the socket's last successful Register
+        let capabilities: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+        let max_clock_skew_ms = self.config.server.max_clock_skew_ms;
+        let max_message_size = self.config.server.max_message_size;
+        let app_relay_max_bytes = self.config.webrtc.app_relay_max_bytes;
+        let app_relay_max_per_sec = self.config.webrtc.app_relay_max_per_sec;
+        let app_relay_window = std::sync::Mutex::new(RateWindow::new());
+        let message_rate_limiter = self.message_rate_limiter.clone();
+        let peer_address = connection.address();
+        let require_warmup_pong = self.config.server.require_warmup_pong;
+        let warmup_pong_timeout = std::time::Duration::from_millis(self.config.server.warmup_pong_timeout_ms);
+        let pending_warmup: std::sync::Mutex<Option<PendingWarmup>> = std::sync::Mutex::new(None);
+        let reconnect_grace = std::time::Duration::from_secs(self.current_config().session.reconnect_grace_secs);
+        let max_sessions_per_client = self.config.session.max_sessions_per_client;
+        let session_limit_policy = self.config.session.session_limit_policy;
+        let max_id_length = self.config.server.max_id_length;
+        let frame_options = FrameOptions::from_config(&self.config);
+        // Outbound frames are only compressed, and only carry checksum trailers, the way the
+        // client's own frames already are
+        let peer_framing = Arc::new(PeerFraming::default());
+        let peer_framing_in = peer_framing.clone();
+        let peer_framing_out = peer_framing.clone();
+        let server_info = Self::server_info(&self.config);
+        let metrics = self.metrics.clone();
+        let slow_handler_threshold = std::time::Duration::from_millis(self.config.server.slow_handler_threshold_ms);
+        let record_handler_durations = self.config.metrics.enabled;
+        let mut shutdown = self.shutdown.subscribe();
+        // Drop the borrowed value before the select arm awaits, keeping this future `Send`
+        let shutting_down = async move {
+            let _ = shutdown.wait_for(|shutting_down| *shutting_down).await;
+        };
+        // Counted from when the socket opened until its first heartbeat; Ping and Pong frames
+        // refresh it too, for clients relying on transport-level keepalive
+        let last_heartbeat = Arc::new(std::sync::Mutex::new(tokio::time::Instant::now()));
+        let last_heartbeat_in = last_heartbeat.clone();
+        let heartbeat_timeout = std::time::Duration::from_secs(self.config.server.heartbeat_interval)
+            .saturating_mul(self.config.server.heartbeat_timeout_multiplier);
+        let heartbeat_watchdog = spawn(async move {
+            if heartbeat_timeout.is_zero() {
+                return std::future::pending().await;
+            }
+            loop {
+                let deadline = *last_heartbeat.lock().unwrap() + heartbeat_timeout;
+                if tokio::time::Instant::now() >= deadline {
+                    break;
+                }
+                tokio::time::sleep_until(deadline).await;
+            }
+        });
+        metrics.record_connection();
+        let incoming_task = spawn(async move {
             info!("[WEBSOCKET] Starting incoming message processing task");
-            while let Some(msg) = ws_receiver.next().await {
+            loop {
+                let warmup_deadline = pending_warmup.lock().unwrap().as_ref().map(|warmup| warmup.deadline);
+                let next = match warmup_deadline {
+                    Some(deadline) => match tokio::time::timeout_at(deadline, ws_receiver.next()).await {
+                        Ok(next) => next,
+                        Err(_) => {
+                            warn!("[CONNECTION] Client {:?} did not answer the warm-up ping, closing connection",
+                                client_id_in.lock().await.as_deref());
+                            let error_message = Message::new(
+                                crate::message::MessageType::Error,
+                                crate::message::Payload::Error(crate::message::ErrorPayload {
+                                    error_code: 12,
+                                    error_message: "Warm-up ping was not answered".to_string(),
+                                    ..Default::default()
+                                })
+                            );
+                            if let Ok(binary) = error_message.to_binary_with(peer_framing_in.options(frame_options)) {
+                                let _ = ws_sender_in.lock().await.send(WsMessage::Binary(binary)).await;
+                            }
+                            break;
+                        }
+                    },
+                    None => ws_receiver.next().await,
+                };
+                let Some(msg) = next else { break };
                 match msg {
+                    // tungstenite reassembles fragmented messages (a first frame plus continuation
+                    // frames) before yielding them, so `data` is always whole
                     Ok(WsMessage::Binary(data)) => {
                         info!("[WEBSOCKET] Received binary message ({} bytes)", data.len());
-                        match Message::from_binary(&data) {
+                        metrics.record_frame_size(data.len());
+                        // tungstenite only refuses messages beyond the slack, so the limit is enforced here
+                        if data.len() > max_message_size {
+                            warn!("[WEBSOCKET] Rejected {}-byte message over max_message_size of {} bytes", data.len(), max_message_size);
+                            let error_message = Message::new(
+                                crate::message::MessageType::Error,
+                                crate::message::Payload::Error(crate::message::ErrorPayload {
+                                    error_code: 9,
+                                    error_message: format!("Message of {} bytes exceeds the {} byte limit", data.len(), max_message_size),
+                                    ..Default::default()
+                                })
+                            );
+                            if let Ok(binary) = error_message.to_binary_with(peer_framing_in.options(frame_options)) {
+                                let _ = ws_sender_in.lock().await.send(WsMessage::Binary(binary)).await;
+                            }
+                            continue;
+                        }
+                        // Counted per client once it has connected, and per peer address before that
+                        let rate_key = client_id_in.lock().await.clone().unwrap_or_else(|| peer_address.to_string());
+                        if !message_rate_limiter.try_acquire(&rate_key) {
+                            warn!("[WEBSOCKET] Rate limit exceeded for {}, dropping message", rate_key);
+                            let error_message = Message::new(
+                                crate::message::MessageType::Error,
+                                crate::message::Payload::Error(crate::message::ErrorPayload {
+                                    error_code: 10,
+                                    error_message: format!("Rate limit exceeded: at most {} messages per minute", message_rate_limiter.max_per_minute()),
+                                    ..Default::default()
+                                })
+                            );
+                            // Through the queue, so a flood is answered at the socket's pace and in order
+                            if tx_clone.push(error_message).is_err() {
+                                break;
+                            }
+                            continue;
+                        }
+                        match Message::from_binary_with(&data, frame_options) {
                             Ok(message) => {
+                                peer_framing_in.observe(&data);
+                                // An evicted or expired connection only waits for its close frame to go out
+                                if tx_clone.is_draining() {
+                                    debug!("[WEBSOCKET_IN] Ignored {:?} from a connection that is being closed", message.message_type);
+                                    continue;
+                                }
+                                metrics.record_message(message.message_type);
+                                if matches!(message.payload, Payload::Heartbeat(_)) {
+                                    *last_heartbeat_in.lock().unwrap() = tokio::time::Instant::now();
+                                }
                                 // Debug logging for incoming message
                                 debug!("[WEBSOCKET_IN] Received message: type={:?}, uuid={}, client_id={:?}", 
                                     message.message_type, message.uuid, client_id_in.lock().await.as_deref());
+
+                                let now_ms = crate::message::now_millis();
+                                if let Some(skew) = message.clock_skew_ms(now_ms) {
+                                    if max_clock_skew_ms > 0 && skew > max_clock_skew_ms {
+                                        warn!("[WEBSOCKET] Rejected message {} with created_at {:?}: clock skew {}ms exceeds {}ms",
+                                            message.uuid, message.created_at, skew, max_clock_skew_ms);
+                                        let error_message = Message::new(
+                                            crate::message::MessageType::Error,
+                                            crate::message::Payload::Error(crate::message::ErrorPayload {
+                                                error_code: 6,
+                                                error_message: format!("Message timestamp outside allowed clock skew ({}ms)", skew),
+                                                ..Default::default()
+                                            })
+                                        );
+                                        if let Ok(binary) = error_message.to_binary_with(peer_framing_in.options(frame_options)) {
+                                            let _ = ws_sender_in.lock().await.send(WsMessage::Binary(binary)).await;
+                                        }
+                                        continue;
+                                    }
+                                }
+                                if let Some(latency) = message.latency_ms(now_ms) {
+                                    debug!("[WEBSOCKET_IN] Message {} latency {}ms", message.uuid, latency);
+                                }
                                 
                                 let context = MessageHandlerContext {
                                     session_manager: &session_manager_clone,
                                     client_id: &client_id_in,
                                     connections: &connections_clone,
                                     tx: &tx_clone,
+                                    duplicate_connect_policy,
+                                    connect_ack_order,
+                                    validate_signal_base64,
+                                    role_message_allowlist: &role_message_allowlist,
+                                    required_capabilities: enforce_capabilities.then_some(&required_capabilities),
+                                    capabilities: &capabilities,
+                                    app_relay_max_bytes,
+                                    app_relay_max_per_sec,
+                                    app_relay_window: &app_relay_window,
+                                    require_warmup_pong,
+                                    warmup_pong_timeout,
+                                    pending_warmup: &pending_warmup,
+                                    reconnect_grace,
+                                    max_sessions_per_client,
+                                    session_limit_policy,
+                                    max_id_length,
+                                    metrics: &metrics,
+                                    server_info: &server_info,
                                     register_handler: &register_handler,
                                     webrtc_room_create_handler: &webrtc_room_create_handler,
                                     webrtc_room_join_handler: &webrtc_room_join_handler,
                                     webrtc_room_leave_handler: &webrtc_room_leave_handler,
+                                    webrtc_room_list_handler: &webrtc_room_list_handler,
+                                    where_am_i_handler: &where_am_i_handler,
                                 };
-                                if let Err(e) = Self::handle_message(&message, context).await {
-                                    error!("[WEBSOCKET] Error handling message: {}", e);
-                                    break;
+                                let message_type = message.message_type;
+                                // A panicking handler ends this connection like a failing one, so the
+                                // disconnect cleanup below still runs
+                                let uuid = message.uuid;
+                                let dispatch = std::panic::AssertUnwindSafe(Self::handle_message(message, context)).catch_unwind();
+                                let dispatch_metrics = record_handler_durations.then_some(&*metrics);
+                                match time_dispatch(message_type, uuid, slow_handler_threshold, dispatch_metrics, dispatch).await {
+                                    Ok(Ok(())) => {
+                                        let warmup_ping = pending_warmup.lock().unwrap().as_mut()
+                                            .filter(|warmup| !warmup.ping_sent)
+                                            .map(|warmup| {
+                                                warmup.ping_sent = true;
+                                                warmup.nonce.clone()
+                                            });
+                                        if let Some(nonce) = warmup_ping {
+                                            if let Err(e) = ws_sender_in.lock().await.send(WsMessage::Ping(nonce)).await {
+                                                error!("[WEBSOCKET] Failed to send warm-up ping: {}", e);
+                                                break;
+                                            }
+                                        }
+                                    }
+                                    Ok(Err(e)) => {
+                                        error!("[WEBSOCKET] Error handling message: {}", e);
+                                        break;
+                                    }
+                                    Err(panic) => {
+                                        let reason = panic.downcast_ref::<&str>().map(|s| s.to_string())
+                                            .or_else(|| panic.downcast_ref::<String>().cloned())
+                                            .unwrap_or_else(|| "unknown panic".to_string());
+                                        error!("[WEBSOCKET] Handler for {:?} panicked for client {:?}: {}",
+                                            message_type, client_id_in.lock().await.as_deref(), reason);
+                                        let error_message = Message::new(
+                                            crate::message::MessageType::Error,
+                                            crate::message::Payload::Error(crate::message::ErrorPayload {
+                                                error_code: 1,
+                                                error_message: "Internal server error".to_string(),
+                                                ..Default::default()
+                                            })
+                                        );
+                                        if let Ok(binary) = error_message.to_binary_with(peer_framing_in.options(frame_options)) {
+                                            let _ = ws_sender_in.lock().await.send(WsMessage::Binary(binary)).await;
+                                        }
+                                        break;
+                                    }
                                 }
                             }
                             Err(e) => {
+                                metrics.record_parse_error(ParseErrorReason::of(&e));
                                 let preview = data.iter().take(32).map(|b| format!("{b:02X}")).collect::<Vec<_>>().join(" ");
                                 error!("[WEBSOCKET][PARSE_ERROR] Dropped invalid frame: {} ({} bytes, preview: [{}])", e, data.len(), preview);
                                 // Optionally, send an error message back to the client
-                                let error_message = Message::new(
-                                    crate::message::MessageType::Error,
-                                    crate::message::Payload::Error(crate::message::ErrorPayload {
+                                let error_payload = match e {
+                                    crate::Error::InvalidPayload { .. } => crate::message::ErrorPayload::invalid_payload(&e),
+                                    crate::Error::UnsupportedProtocolVersion(_) => crate::message::ErrorPayload {
+                                        error_code: 14,
+                                        error_message: e.to_string(),
+                                        ..Default::default()
+                                    },
+                                    crate::Error::CodecDisabled(_) => crate::message::ErrorPayload {
+                                        error_code: 15,
+                                        error_message: e.to_string(),
+                                        ..Default::default()
+                                    },
+                                    crate::Error::InvalidUuid(_) => crate::message::ErrorPayload {
+                                        error_code: 16,
+                                        error_message: e.to_string(),
+                                        ..Default::default()
+                                    },
+                                    _ => crate::message::ErrorPayload {
                                         error_code: 2,
                                         error_message: format!("Malformed message: {}", e),
-                                    })
+                                        ..Default::default()
+                                    },
+                                };
+                                let error_message = Message::new(
+                                    crate::message::MessageType::Error,
+                                    crate::message::Payload::Error(error_payload)
                                 );
-                                if let Ok(binary) = error_message.to_binary() {
+                                if let Ok(binary) = error_message.to_binary_with(peer_framing_in.options(frame_options)) {
                                     let _ = ws_sender_in.lock().await.send(WsMessage::Binary(binary)).await;
                                 }
+                                // A peer on the old framing will misread every frame we send, so end the connection
+                                if matches!(e, crate::Error::UnsupportedProtocolVersion(_)) {
+                                    break;
+                                }
                                 // Continue listening for more frames
                                 continue;
                             }
@@ -301,9 +1461,10 @@ impl WebSocketServer {
                             crate::message::Payload::Error(crate::message::ErrorPayload {
                                 error_code: 3,
                                 error_message: "Text messages are not supported. Use binary format.".to_string(),
+                                ..Default::default()
                             })
                         );
-                        if let Ok(binary) = error_message.to_binary() {
+                        if let Ok(binary) = error_message.to_binary_with(peer_framing_in.options(frame_options)) {
                             let _ = ws_sender_in.lock().await.send(WsMessage::Binary(binary)).await;
                         }
                     }
@@ -313,17 +1474,44 @@ impl WebSocketServer {
                     }
                     Ok(WsMessage::Ping(data)) => {
                         debug!("[WEBSOCKET_IN] Received ping");
+                        *last_heartbeat_in.lock().unwrap() = tokio::time::Instant::now();
+                        if let Some(id) = client_id_in.lock().await.as_deref() {
+                            session_manager_clone.touch(id).await;
+                        }
                         if let Err(e) = ws_sender_in.lock().await.send(frame_handlers::ping::handle_ping(data).await).await {
                             error!("[WEBSOCKET] Failed to send pong: {}", e);
                             break;
                         }
                     }
+                    Ok(WsMessage::Pong(data)) => {
+                        *last_heartbeat_in.lock().unwrap() = tokio::time::Instant::now();
+                        if let Some(id) = client_id_in.lock().await.as_deref() {
+                            session_manager_clone.touch(id).await;
+                        }
+                        let warmup = {
+                            let mut pending = pending_warmup.lock().unwrap();
+                            match pending.as_ref() {
+                                Some(warmup) if warmup.nonce == data => pending.take(),
+                                _ => None,
+                            }
+                        };
+                        if let Some(warmup) = warmup {
+                            connections_clone.write().await.insert(warmup.client_id.clone(), tx_clone.clone());
+                            info!("[CONNECTION] Client {} answered the warm-up ping and was added to connections map", warmup.client_id);
+                            if tx_clone.push(warmup.ack).is_err() {
+                                break;
+                            }
+                        } else {
+                            debug!("[WEBSOCKET_IN] Received pong");
+                        }
+                    }
                     Err(e) => {
                         error!("[WEBSOCKET] WebSocket error: {}", e);
                         break;
                     }
-                    _ => {
-                        warn!("[WEBSOCKET] Unsupported message type");
+                    Ok(WsMessage::Frame(_)) => {
+                        // Raw frames are only produced when writing; reads yield reassembled messages
+                        warn!("[WEBSOCKET] Unexpected raw frame");
                     }
                 }
             }
@@ -331,36 +1519,123 @@ impl WebSocketServer {
         });
         let ws_sender_out = ws_sender.clone();
         let client_id_out = client_id.clone();
-        let outgoing_task = tokio::spawn(async move {
+        let mut outgoing_task = spawn(async move {
             info!("[WEBSOCKET] Starting outgoing message processing task");
-            while let Some(message) = rx.recv().await {
+            while let Some(message) = rx.pop().await {
                 // Debug logging for outgoing message
                 debug!("[WEBSOCKET_OUT] Sending message: type={:?}, uuid={}, client_id={:?}", 
                     message.message_type, message.uuid, client_id_out.lock().await.as_deref());
                 
-                if let Ok(binary) = message.to_binary() {
+                if let Ok(binary) = message.to_binary_with(peer_framing_out.options(frame_options)) {
                     if let Err(e) = ws_sender_out.lock().await.send(WsMessage::Binary(binary)).await {
                         error!("[WEBSOCKET] Failed to send message: {}", e);
                         break;
                     }
                 }
             }
+            if let Some(frame) = rx.take_close_frame() {
+                let _ = ws_sender_out.lock().await.send(WsMessage::Close(Some(frame))).await;
+            }
             info!("[WEBSOCKET] Outgoing message processing task ended");
         });
+        let ping_interval = std::time::Duration::from_secs(self.config.server.ping_interval_secs);
+        let ws_sender_ping = ws_sender.clone();
+        let pinger = (!ping_interval.is_zero()).then(|| spawn(async move {
+            let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + ping_interval, ping_interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = ws_sender_ping.lock().await.send(WsMessage::Ping(Vec::new())).await {
+                    debug!("[WEBSOCKET_OUT] Failed to send keepalive ping: {}", e);
+                    break;
+                }
+            }
+        }));
+        let incoming_abort = incoming_task.abort_handle();
+        let watchdog_abort = heartbeat_watchdog.abort_handle();
+        let mut heartbeat_timed_out = false;
         tokio::select! {
             _ = incoming_task => {
                 info!("[WEBSOCKET] Incoming task completed");
             },
-            _ = outgoing_task => {
+            _ = &mut outgoing_task => {
                 info!("[WEBSOCKET] Outgoing task completed");
             },
+            _ = shutting_down => {
+                let id = client_id.lock().await.clone();
+                info!("[SHUTDOWN] Closing connection for client {:?}", id);
+                let mut ws_sender = ws_sender.lock().await;
+                if let Some(id) = id {
+                    let disconnect = Message::new(
+                        MessageType::Disconnect,
+                        Payload::Disconnect(crate::message::DisconnectPayload {
+                            client_id: id,
+                            reason: SHUTDOWN_REASON.to_string(),
+                        }),
+                    );
+                    if let Ok(binary) = disconnect.to_binary_with(peer_framing.options(frame_options)) {
+                        let _ = ws_sender.send(WsMessage::Binary(binary)).await;
+                    }
+                }
+                let _ = ws_sender.send(WsMessage::Close(Some(CloseFrame {
+                    code: CloseCode::Away,
+                    reason: SHUTDOWN_REASON.into(),
+                }))).await;
+            },
+            _ = heartbeat_watchdog => {
+                let id = client_id.lock().await.clone();
+                warn!("[CONNECTION] No heartbeat from {:?} for {:?}, closing connection", id, heartbeat_timeout);
+                heartbeat_timed_out = true;
+                // Stop reading, so the socket closes even if the client never answers the close
+                incoming_abort.abort();
+                if let Some(id) = id {
+                    let disconnect = Message::new(
+                        MessageType::Disconnect,
+                        Payload::Disconnect(crate::message::DisconnectPayload {
+                            client_id: id,
+                            reason: HEARTBEAT_TIMEOUT_REASON.to_string(),
+                        }),
+                    );
+                    let _ = tx.push(disconnect);
+                }
+                // Queued frames go out first, then the close frame, without racing the writer
+                tx.close_after_drain(CloseFrame { code: CloseCode::Policy, reason: HEARTBEAT_TIMEOUT_REASON.into() });
+                if tokio::time::timeout(CLOSE_DRAIN_TIMEOUT, &mut outgoing_task).await.is_err() {
+                    warn!("[CONNECTION] Timed-out connection did not drain within {:?}", CLOSE_DRAIN_TIMEOUT);
+                }
+            },
         }
-        if let Some(id) = client_id.lock().await.as_ref() {
-            info!("[CONNECTION] Client {} disconnecting", id);
-            session_manager.handle_disconnect(id).await?;
-            let mut connections = connections.write().await;
-            connections.remove(id);
-            info!("[CONNECTION] Client {} removed from connections map", id);
+        watchdog_abort.abort();
+        if let Some(pinger) = pinger {
+            pinger.abort();
+        }
+        self.metrics.record_connection_closed();
+        tx.close();
+        if tx.dropped_count() > 0 {
+            warn!("[WEBSOCKET_OUT] Dropped {} outbound frames for client {:?}", tx.dropped_count(), client_id.lock().await.as_deref());
+        }
+        if let Some(id) = client_id.lock().await.clone() {
+            // A reconnect on another socket may already own the entry and the session
+            let owns_entry = connections.read().await.get(&id).is_none_or(|entry| Arc::ptr_eq(entry, &tx));
+            let grace_secs = self.current_config().session.reconnect_grace_secs;
+            if tx.is_draining() && !heartbeat_timed_out {
+                info!("[CONNECTION] Client {} was evicted or its session expired", id);
+            } else if !owns_entry {
+                info!("[CONNECTION] Client {} already reconnected on another socket", id);
+            } else if grace_secs > 0 && !heartbeat_timed_out {
+                connections.write().await.remove(&id);
+                info!("[CONNECTION] Client {} dropped, keeping its session for {}s", id, grace_secs);
+                if let Some(session) = session_manager.get_session(&id).await {
+                    let grace = std::time::Duration::from_secs(grace_secs);
+                    spawn(Self::expire_session_after_grace(session_manager, id, session.session_id, grace));
+                }
+            } else {
+                info!("[CONNECTION] Client {} disconnecting", id);
+                let disconnect_result = session_manager.handle_disconnect(&id).await;
+                // Drop the connection entry even if session cleanup failed, so it can't leak
+                connections.write().await.remove(&id);
+                info!("[CONNECTION] Client {} removed from connections map", id);
+                disconnect_result?;
+            }
         } else {
             info!("[CONNECTION] Client disconnected without being authenticated");
         }
@@ -368,53 +1643,372 @@ impl WebSocketServer {
         Ok(())
     }
 
+    /// Tear down a dropped client's session unless a reconnect took it over within `grace`
+    async fn expire_session_after_grace(session_manager: Arc<SessionManager>, client_id: String, session_id: String, grace: std::time::Duration) {
+        tokio::time::sleep(grace).await;
+        let still_stale = session_manager.get_session(&client_id).await.is_some_and(|session| session.session_id == session_id);
+        if still_stale {
+            info!("[CONNECTION] Reconnect grace for {} expired, ending session {}", client_id, session_id);
+            if let Err(e) = session_manager.handle_disconnect(&client_id).await {
+                error!("[CONNECTION] Failed to end session {} for {}: {}", session_id, client_id, e);
+            }
+        }
+    }
+
+    /// Notify and close the connection of a client evicted from the full client store, and end
+    /// its session
+    async fn disconnect_evicted_client(connections: &RwLock<HashMap<String, Arc<OutboundQueue>>>, session_manager: &SessionManager, client_id: &str) {
+        if let Some(evicted) = connections.write().await.remove(client_id) {
+            info!(client_id = %client_id, outcome = "client_evicted", "[CONNECTION] Disconnecting {}: evicted from the client store", client_id);
+            let disconnect = Message::new(
+                MessageType::Disconnect,
+                Payload::Disconnect(crate::message::DisconnectPayload {
+                    client_id: client_id.to_string(),
+                    reason: CLIENT_EVICTED_REASON.to_string(),
+                }),
+            );
+            if let Err(e) = evicted.push(disconnect) {
+                warn!("[CONNECTION] Failed to notify evicted client {}: {}", client_id, e);
+            }
+            evicted.close_after_drain(CloseFrame { code: CloseCode::Policy, reason: CLIENT_EVICTED_REASON.into() });
+        }
+        if let Err(e) = session_manager.handle_disconnect(client_id).await {
+            warn!("[CONNECTION] Failed to end the session of evicted client {}: {}", client_id, e);
+        }
+    }
+
+    /// Make `tx` the connection relays and server pushes for `client_id` are delivered to
+    async fn register_connection(connections: &RwLock<HashMap<String, Arc<OutboundQueue>>>, client_id: &str, tx: &Arc<OutboundQueue>) {
+        connections.write().await.insert(client_id.to_string(), tx.clone());
+        info!("[CONNECTION] Client {} added to connections map", client_id);
+        info!("[CONNECTION] Client {} connected successfully", client_id);
+    }
+
+    /// Takes ownership so signal messages can be relayed without copying their payload
     async fn handle_message(
-        message: &Message,
+        message: Message,
         context: MessageHandlerContext<'_>,
     ) -> Result<(), crate::Error> {
         // Debug logging for message handling
         debug!("[MESSAGE_HANDLER] Processing message: type={:?}, uuid={}", 
             message.message_type, message.uuid);
+
+        if !context.role_message_allowlist.is_empty() {
+            let client_id = context.client_id.lock().await.clone();
+            if let Some(id) = client_id {
+                // A message naming a room is judged by the role held there; any other message must
+                // be allowed for every role the client holds
+                let roles = match message.payload.room_id() {
+                    Some(room_id) => context.session_manager.client_role(room_id, &id).await.into_iter().collect(),
+                    None => context.session_manager.client_roles(&id).await,
+                };
+                // Check what the payload actually is, not what the header claims
+                let kind = message.payload.kind();
+                let forbidden = roles.into_iter().find(|role| {
+                    context.role_message_allowlist.get(role).is_some_and(|types| !types.contains(&kind))
+                });
+                if let Some(role) = forbidden {
+                    warn!("[MESSAGE_HANDLER] Rejected {:?} from {} with role {}", kind, id, role);
+                    let error_message = Message::new(
+                        crate::message::MessageType::Error,
+                        crate::message::Payload::Error(crate::message::ErrorPayload {
+                            error_code: 8,
+                            error_message: format!("Forbidden: role {role} may not send {kind:?}"),
+                            ..Default::default()
+                        }),
+                    );
+                    context.tx.push(error_message)?;
+                    return Ok(());
+                }
+            }
+        }
+
+        if let Some(required_capabilities) = context.required_capabilities {
+            let kind = message.payload.kind();
+            if let Some(required) = required_capabilities.get(&kind) {
+                let granted = context.capabilities.lock().unwrap().iter().any(|capability| capability == required);
+                if !granted {
+                    warn!("[MESSAGE_HANDLER] Rejected {:?} from a socket without the {} capability", kind, required);
+                    let error_message = Message::new(
+                        crate::message::MessageType::Error,
+                        crate::message::Payload::Error(crate::message::ErrorPayload {
+                            error_code: 8,
+                            error_message: format!("Forbidden: {kind:?} requires the {required} capability"),
+                            ..Default::default()
+                        }),
+                    );
+                    context.tx.push(error_message)?;
+                    return Ok(());
+                }
+            }
+        }
         
+        // Register and room requests are checked by their handlers, which reply under their ack type
+        let checked_by_handler = matches!(message.payload,
+            Payload::Register(_) | Payload::Unregister(_) | Payload::WebRTCRoomCreate(_) | Payload::WebRTCRoomJoin(_) | Payload::WebRTCRoomLeave(_));
+        if !checked_by_handler {
+            if let Err(e) = message.payload.validate_ids(context.max_id_length) {
+                warn!("[MESSAGE_HANDLER] Rejected {:?} with an invalid id: {}", message.payload.kind(), e);
+                let error_message = Message::new(
+                    crate::message::MessageType::Error,
+                    crate::message::Payload::Error(crate::message::ErrorPayload::invalid_payload(&e)),
+                );
+                context.tx.push(error_message)?;
+                return Ok(());
+            }
+        }
+
         match &message.payload {
             Payload::Connect(payload) => {
                 debug!("[MESSAGE_HANDLER] Handling Connect request for client: {}", payload.client_id);
+                let previous_client_id = context.client_id.lock().await.clone();
+                let was_connected = previous_client_id.is_some();
+                if let Some(previous) = &previous_client_id {
+                    if context.duplicate_connect_policy == DuplicateConnectPolicy::Reject {
+                        warn!("[CONNECTION] Rejected duplicate Connect on socket already connected as {}", previous);
+                        let error_message = Message::new(
+                            crate::message::MessageType::Error,
+                            crate::message::Payload::Error(crate::message::ErrorPayload {
+                                error_code: 5,
+                                error_message: format!("Already connected as {previous}"),
+                                ..Default::default()
+                            }),
+                        );
+                        context.tx.push(error_message)?;
+                        return Ok(());
+                    }
+                    info!("[CONNECTION] Replacing session for {} with a new Connect", previous);
+                }
+                let held_elsewhere = context.connections.read().await.get(&payload.client_id)
+                    .is_some_and(|existing| !Arc::ptr_eq(existing, context.tx) && !existing.is_closed());
+                // Each client id maps to one socket, so at most one other session can be active
+                let active_sessions = usize::from(held_elsewhere);
+                let over_limit = context.max_sessions_per_client > 0 && active_sessions >= context.max_sessions_per_client;
+                if over_limit && context.session_limit_policy == SessionLimitPolicy::Reject {
+                    context.metrics.record_duplicate_session_rejected();
+                    warn!(client_id = %payload.client_id, outcome = "session_limit_rejected",
+                        "[CONNECTION] Rejected Connect for {}: {} active session(s), limit {}", payload.client_id, active_sessions, context.max_sessions_per_client);
+                    let error_message = Message::new(
+                        crate::message::MessageType::Error,
+                        crate::message::Payload::Error(crate::message::ErrorPayload {
+                            error_code: 17,
+                            error_message: format!("{} already has {} active session(s), the most allowed by max_sessions_per_client",
+                                payload.client_id, active_sessions),
+                            ..Default::default()
+                        }),
+                    );
+                    context.tx.push(error_message)?;
+                    return Ok(());
+                }
+                if over_limit {
+                    if let Some(evicted) = context.connections.write().await.remove(&payload.client_id) {
+                        info!(client_id = %payload.client_id, outcome = "session_evicted",
+                            "[CONNECTION] Evicting the oldest session of {} for a new Connect", payload.client_id);
+                        let disconnect = Message::new(
+                            MessageType::Disconnect,
+                            Payload::Disconnect(crate::message::DisconnectPayload {
+                                client_id: payload.client_id.clone(),
+                                reason: SESSION_EVICTED_REASON.to_string(),
+                            }),
+                        );
+                        if let Err(e) = evicted.push(disconnect) {
+                            warn!("[CONNECTION] Failed to notify the evicted session of {}: {}", payload.client_id, e);
+                        }
+                        evicted.close_after_drain(CloseFrame { code: CloseCode::Policy, reason: SESSION_EVICTED_REASON.into() });
+                    }
+                } else if held_elsewhere && !context.reconnect_grace.is_zero() {
+                    context.metrics.record_duplicate_session_rejected();
+                    warn!(client_id = %payload.client_id, outcome = "duplicate_session_rejected",
+                        "[CONNECTION] Rejected Connect for {}: its session is held by another open socket", payload.client_id);
+                    let error_message = Message::new(
+                        crate::message::MessageType::Error,
+                        crate::message::Payload::Error(crate::message::ErrorPayload {
+                            error_code: 13,
+                            error_message: format!("{} already has an active session", payload.client_id),
+                            ..Default::default()
+                        }),
+                    );
+                    context.tx.push(error_message)?;
+                    return Ok(());
+                }
+                // Only a session whose socket is gone is stale; one still held by a live socket is not
+                let stale_session = if held_elsewhere || previous_client_id.as_deref() == Some(payload.client_id.as_str()) {
+                    None
+                } else {
+                    context.session_manager.get_session(&payload.client_id).await
+                };
                 let response = context.session_manager.handle_connect(payload.client_id.clone(), payload.auth_token.clone()).await?;
                 if let Payload::ConnectAck(ack) = &response.payload {
                     if ack.status == "success" {
+                        if let Some(stale) = stale_session {
+                            context.metrics.record_session_replaced();
+                            info!(client_id = %payload.client_id, stale_session_id = %stale.session_id, outcome = "stale_session_replaced",
+                                "[CONNECTION] Reconnect of {} replaced stale session {}", payload.client_id, stale.session_id);
+                        }
+                        // A same-id reconnect overwrites its entries below; a different id leaves the old ones behind
+                        if let Some(previous) = previous_client_id.filter(|previous| *previous != payload.client_id) {
+                            context.session_manager.handle_disconnect(&previous).await?;
+                            context.connections.write().await.remove(&previous);
+                            info!("[CONNECTION] Client {} removed from connections map", previous);
+                        }
                         *context.client_id.lock().await = Some(payload.client_id.clone());
-                        let mut connections = context.connections.write().await;
-                        connections.insert(payload.client_id.clone(), context.tx.clone());
-                        info!("[CONNECTION] Client {} added to connections map", payload.client_id);
-                        info!("[CONNECTION] Client {} connected successfully", payload.client_id);
+                        if context.require_warmup_pong {
+                            info!("[CONNECTION] Client {} authenticated, awaiting warm-up pong", payload.client_id);
+                            *context.pending_warmup.lock().unwrap() = Some(PendingWarmup {
+                                client_id: payload.client_id.clone(),
+                                nonce: uuid::Uuid::new_v4().as_bytes().to_vec(),
+                                ack: response,
+                                deadline: tokio::time::Instant::now() + context.warmup_pong_timeout,
+                                ping_sent: false,
+                            });
+                            return Ok(());
+                        }
+                        if context.connect_ack_order == ConnectAckOrder::BeforePersist {
+                            debug!("[MESSAGE_HANDLER] Sending ConnectAck response for client: {}", payload.client_id);
+                            context.tx.push(response)?;
+                            Self::register_connection(context.connections, &payload.client_id, context.tx).await;
+                            return Ok(());
+                        }
+                        // Registered before the ack is queued, so the client and its peers find it connected
+                        Self::register_connection(context.connections, &payload.client_id, context.tx).await;
                     } else {
                         warn!("[CONNECTION] Client {} connection failed: {}", payload.client_id, ack.status);
                     }
                 }
+                // A socket that never authenticated has nothing left to do once its Connect is refused
+                let refused = !was_connected && matches!(response.payload, Payload::Error(_));
                 debug!("[MESSAGE_HANDLER] Sending ConnectAck response for client: {}", payload.client_id);
-                context.tx.send(response).await.map_err(|e| crate::Error::Connection(e.to_string()))?;
+                context.tx.push(response)?;
+                if refused {
+                    info!("[CONNECTION] Closing socket after failed authentication of {}", payload.client_id);
+                    context.tx.close_after_drain(CloseFrame { code: CloseCode::Policy, reason: AUTH_FAILED_REASON.into() });
+                }
+            }
+            Payload::ServerInfo(_) => {
+                debug!("[MESSAGE_HANDLER] Handling ServerInfo request");
+                context.tx.push(Message::new(
+                    MessageType::ServerInfoAck,
+                    Payload::ServerInfoAck(context.server_info.clone()),
+                ))?;
+            }
+            Payload::WhereAmI(_) => {
+                debug!("[MESSAGE_HANDLER] Handling WhereAmI request");
+                let Some(id) = context.client_id.lock().await.clone() else {
+                    warn!("[MESSAGE_HANDLER] Rejected WhereAmI from unauthenticated connection");
+                    let error_message = Message::new(
+                        crate::message::MessageType::Error,
+                        crate::message::Payload::Error(crate::message::ErrorPayload {
+                            error_code: 1,
+                            error_message: "Connect before querying rooms".to_string(),
+                            ..Default::default()
+                        }),
+                    );
+                    context.tx.push(error_message)?;
+                    return Ok(());
+                };
+                match context.where_am_i_handler.handle_where_am_i(&id).await {
+                    Ok(response) => {
+                        debug!("[MESSAGE_HANDLER] Sending WhereAmIAck response");
+                        context.tx.push(response)?;
+                    }
+                    Err(e) => {
+                        error!("Failed to handle WhereAmI message: {}", e);
+                        let error_message = Message::new(
+                            crate::message::MessageType::Error,
+                            crate::message::Payload::Error(crate::message::ErrorPayload {
+                                error_code: 1,
+                                error_message: format!("Internal server error: {e}"),
+                                ..Default::default()
+                            }),
+                        );
+                        context.tx.push(error_message)?;
+                    }
+                }
+            }
+            Payload::ClientStatusQuery(query) => {
+                debug!("[MESSAGE_HANDLER] Handling ClientStatusQuery for client: {}", query.client_id);
+                let Some(id) = context.client_id.lock().await.clone() else {
+                    warn!("[MESSAGE_HANDLER] Rejected ClientStatusQuery from unauthenticated connection");
+                    let error_message = Message::new(
+                        crate::message::MessageType::Error,
+                        crate::message::Payload::Error(crate::message::ErrorPayload {
+                            error_code: 1,
+                            error_message: "Connect before querying client status".to_string(),
+                            ..Default::default()
+                        }),
+                    );
+                    context.tx.push(error_message)?;
+                    return Ok(());
+                };
+                // Presence is visible to the client itself, its room peers and admins
+                let authorized = id == query.client_id
+                    || context.webrtc_room_list_handler.is_admin(&id)
+                    || context.session_manager.share_room(&id, &query.client_id).await;
+                if !authorized {
+                    warn!("[MESSAGE_HANDLER] Rejected ClientStatusQuery from {} for {}, which shares no room with it", id, query.client_id);
+                    let error_message = Message::new(
+                        crate::message::MessageType::Error,
+                        crate::message::Payload::Error(crate::message::ErrorPayload {
+                            error_code: 8,
+                            error_message: "Forbidden: client status is only visible to room peers and admin clients".to_string(),
+                            ..Default::default()
+                        }),
+                    );
+                    context.tx.push(error_message)?;
+                    return Ok(());
+                }
+                let online = context.connections.read().await.contains_key(&query.client_id);
+                let last_seen = context.session_manager.last_seen(&query.client_id).await;
+                context.tx.push(Message::new(
+                    MessageType::ClientStatusQueryAck,
+                    Payload::ClientStatusQueryAck(crate::message::ClientStatusQueryAckPayload {
+                        client_id: query.client_id.clone(),
+                        online,
+                        last_seen,
+                    }),
+                ))?;
             }
             Payload::Disconnect(_payload) => {
                 debug!("[MESSAGE_HANDLER] Handling Disconnect request");
-                if let Some(id) = context.client_id.lock().await.as_ref() {
-                    context.session_manager.handle_disconnect(id).await?;
-                    let mut connections = context.connections.write().await;
-                    connections.remove(id);
+                // A Connect still waiting for its warm-up pong is cancelled, so a late pong
+                // cannot add the client to the connections map after it has left
+                if let Some(warmup) = context.pending_warmup.lock().unwrap().take() {
+                    info!("[CONNECTION] Client {} disconnected before answering the warm-up ping", warmup.client_id);
+                }
+                // Forget the id so later messages and the socket's close are not attributed to it
+                let Some(id) = context.client_id.lock().await.take() else {
+                    return Ok(());
+                };
+                let mut connections = context.connections.write().await;
+                // A reconnect on another socket may already own the entry and the session
+                if connections.get(&id).is_none_or(|entry| Arc::ptr_eq(entry, context.tx)) {
+                    connections.remove(&id);
+                    drop(connections);
+                    context.session_manager.handle_disconnect(&id).await?;
+                    info!("[CONNECTION] Client {} disconnected and removed from connections map", id);
+                } else {
+                    info!("[CONNECTION] Ignored Disconnect for {}: it already reconnected on another socket", id);
                 }
             }
             Payload::Heartbeat(_) => {
                 debug!("[MESSAGE_HANDLER] Handling Heartbeat request");
                 if let Some(id) = context.client_id.lock().await.as_ref() {
                     let response = context.session_manager.handle_heartbeat(id.clone()).await?;
-                    context.tx.send(response).await.map_err(|e| crate::Error::Connection(e.to_string()))?;
+                    context.tx.push(response)?;
                 }
             }
-            Payload::Register(_) => {
+            Payload::Register(register) => {
                 debug!("[MESSAGE_HANDLER] Handling Register request");
                 match context.register_handler.handle_register(message.clone()).await {
                     Ok(response) => {
+                        if matches!(response.payload, Payload::RegisterAck(_)) {
+                            *context.capabilities.lock().unwrap() = register.capabilities.clone().unwrap_or_default();
+                        }
                         debug!("[MESSAGE_HANDLER] Sending RegisterAck response");
-                        context.tx.send(response).await.map_err(|e| crate::Error::Connection(e.to_string()))?;
+                        context.tx.push(response)?;
+                        for evicted_id in context.register_handler.take_evicted_clients().await {
+                            Self::disconnect_evicted_client(context.connections, context.session_manager, &evicted_id).await;
+                        }
                     }
                     Err(e) => {
                         error!("Failed to handle register message: {}", e);
@@ -423,9 +2017,10 @@ impl WebSocketServer {
                             crate::message::Payload::Error(crate::message::ErrorPayload {
                                 error_code: 1,
                                 error_message: format!("Internal server error: {e}"),
+                                ..Default::default()
                             }),
                         );
-                        context.tx.send(error_message).await.map_err(|e| crate::Error::Connection(e.to_string()))?;
+                        context.tx.push(error_message)?;
                     }
                 }
             }
@@ -434,7 +2029,7 @@ impl WebSocketServer {
                 match context.register_handler.handle_unregister(message.clone()).await {
                     Ok(response) => {
                         debug!("[MESSAGE_HANDLER] Sending UnregisterAck response");
-                        context.tx.send(response).await.map_err(|e| crate::Error::Connection(e.to_string()))?;
+                        context.tx.push(response)?;
                     }
                     Err(e) => {
                         error!("Failed to handle unregister message: {}", e);
@@ -443,24 +2038,89 @@ impl WebSocketServer {
                             crate::message::Payload::Error(crate::message::ErrorPayload {
                                 error_code: 1,
                                 error_message: format!("Internal server error: {e}"),
+                                ..Default::default()
                             }),
                         );
-                        context.tx.send(error_message).await.map_err(|e| crate::Error::Connection(e.to_string()))?;
+                        context.tx.push(error_message)?;
                     }
                 }
             }
-            Payload::SignalOffer(_) | Payload::SignalAnswer(_) | Payload::SignalIceCandidate(_) => {
+            Payload::SignalOffer(signal) | Payload::SignalAnswer(signal) | Payload::SignalIceCandidate(signal) => {
                 debug!("[MESSAGE_HANDLER] Handling Signal message: type={:?}", message.message_type);
                 if let Some(id) = context.client_id.lock().await.as_ref() {
-                    context.session_manager.route_message(id.clone(), message.clone()).await?;
+                    if context.validate_signal_base64 && !signal.has_base64_signal_data() {
+                        warn!("[MESSAGE_HANDLER] Rejected signal from {} with non-base64 signal_data", id);
+                        let error_message = Message::new(
+                            crate::message::MessageType::Error,
+                            crate::message::Payload::Error(crate::message::ErrorPayload {
+                                error_code: 7,
+                                error_message: "signal_data must be valid base64".to_string(),
+                                ..Default::default()
+                            }),
+                        );
+                        context.tx.push(error_message)?;
+                    } else if context.session_manager.signals_as_observer(id, &signal.target_client_id, signal.room_id.as_deref()).await {
+                        warn!("[MESSAGE_HANDLER] Rejected signal from observer {}", id);
+                        let error_message = Message::new(
+                            crate::message::MessageType::Error,
+                            crate::message::Payload::Error(crate::message::ErrorPayload {
+                                error_code: 4,
+                                error_message: "Observers cannot send signal messages".to_string(),
+                                ..Default::default()
+                            }),
+                        );
+                        context.tx.push(error_message)?;
+                    } else {
+                        context.session_manager.route_message(id.clone(), message).await?;
+                    }
+                }
+            }
+            Payload::AppRelay(relay) => {
+                debug!("[MESSAGE_HANDLER] Handling AppRelay message for room {}", relay.room_id);
+                let Some(id) = context.client_id.lock().await.clone() else {
+                    warn!("[MESSAGE_HANDLER] Rejected AppRelay from unauthenticated connection");
+                    let error_message = Message::new(
+                        crate::message::MessageType::Error,
+                        crate::message::Payload::Error(crate::message::ErrorPayload {
+                            error_code: 1,
+                            error_message: "Connect before relaying app messages".to_string(),
+                            ..Default::default()
+                        }),
+                    );
+                    context.tx.push(error_message)?;
+                    return Ok(());
+                };
+                let rejection = if context.app_relay_max_bytes > 0 && relay.data.len() > context.app_relay_max_bytes {
+                    Some((9, format!("App message of {} bytes exceeds the {} byte limit", relay.data.len(), context.app_relay_max_bytes)))
+                } else if !context.app_relay_window.lock().unwrap().try_acquire(context.app_relay_max_per_sec) {
+                    Some((10, format!("App messages limited to {} per second", context.app_relay_max_per_sec)))
+                } else {
+                    context.session_manager.route_message(id.clone(), message).await.err().map(|e| (1, e.to_string()))
+                };
+                if let Some((error_code, error_message)) = rejection {
+                    warn!("[MESSAGE_HANDLER] Rejected AppRelay from {}: {}", id, error_message);
+                    context.tx.push(Message::new(
+                        crate::message::MessageType::Error,
+                        crate::message::Payload::Error(crate::message::ErrorPayload { error_code, error_message, ..Default::default() }),
+                    ))?;
                 }
             }
-            Payload::WebRTCRoomCreate(_) => {
+            Payload::WebRTCRoomCreate(create) => {
                 debug!("[MESSAGE_HANDLER] Handling WebRTCRoomCreate request");
                 match context.webrtc_room_create_handler.handle_room_create(message.clone()).await {
                     Ok(response) => {
+                        if let Payload::WebRTCRoomCreateAck(ack) = &response.payload {
+                            let member = context.client_id.lock().await.clone();
+                            if let (200, Some(room_id), Some(member)) = (ack.status, &ack.room_id, member) {
+                                // Roles belong to the socket's authenticated id, not the id the payload names
+                                context.session_manager.set_member_role(room_id, &member, &create.role).await;
+                                if let Some(offer_sdp) = &create.offer_sdp {
+                                    context.session_manager.record_offer_sdp(room_id, offer_sdp).await;
+                                }
+                            }
+                        }
                         debug!("[MESSAGE_HANDLER] Sending WebRTCRoomCreateAck response");
-                        context.tx.send(response).await.map_err(|e| crate::Error::Connection(e.to_string()))?;
+                        context.tx.push(response)?;
                     }
                     Err(e) => {
                         error!("Failed to handle WebRTC room create message: {}", e);
@@ -469,18 +2129,31 @@ impl WebSocketServer {
                             crate::message::Payload::Error(crate::message::ErrorPayload {
                                 error_code: 1,
                                 error_message: format!("Internal server error: {e}"),
+                                ..Default::default()
                             }),
                         );
-                        context.tx.send(error_message).await.map_err(|e| crate::Error::Connection(e.to_string()))?;
+                        context.tx.push(error_message)?;
                     }
                 }
             }
-            Payload::WebRTCRoomJoin(_) => {
+            Payload::WebRTCRoomJoin(join) => {
                 debug!("[MESSAGE_HANDLER] Handling WebRTCRoomJoin request");
                 match context.webrtc_room_join_handler.handle_room_join(message.clone()).await {
                     Ok(response) => {
+                        let member = context.client_id.lock().await.clone();
+                        if let (Payload::WebRTCRoomJoinAck(ack), Some(member)) = (&response.payload, member) {
+                            if ack.status == 200 {
+                                if join.role.eq_ignore_ascii_case("observer") {
+                                    context.session_manager.add_observer(&join.room_id, &member).await;
+                                }
+                                context.session_manager.set_member_role(&join.room_id, &member, &join.role).await;
+                                context.session_manager
+                                    .announce_peer(MessageType::PeerJoined, &join.room_id, &member, &join.role.to_ascii_lowercase())
+                                    .await;
+                            }
+                        }
                         debug!("[MESSAGE_HANDLER] Sending WebRTCRoomJoinAck response");
-                        context.tx.send(response).await.map_err(|e| crate::Error::Connection(e.to_string()))?;
+                        context.tx.push(response)?;
                     }
                     Err(e) => {
                         error!("Failed to handle WebRTC room join message: {}", e);
@@ -489,18 +2162,25 @@ impl WebSocketServer {
                             crate::message::Payload::Error(crate::message::ErrorPayload {
                                 error_code: 1,
                                 error_message: format!("Internal server error: {e}"),
+                                ..Default::default()
                             }),
                         );
-                        context.tx.send(error_message).await.map_err(|e| crate::Error::Connection(e.to_string()))?;
+                        context.tx.push(error_message)?;
                     }
                 }
             }
-            Payload::WebRTCRoomLeave(_) => {
+            Payload::WebRTCRoomLeave(leave) => {
                 debug!("[MESSAGE_HANDLER] Handling WebRTCRoomLeave request");
                 match context.webrtc_room_leave_handler.handle_room_leave(message.clone()).await {
                     Ok(response) => {
+                        let member = context.client_id.lock().await.clone();
+                        if let (Payload::WebRTCRoomLeaveAck(_), Some(member)) = (&response.payload, member) {
+                            if let Some(role) = context.session_manager.remove_member(&leave.room_id, &member).await {
+                                context.session_manager.announce_peer(MessageType::PeerLeft, &leave.room_id, &member, &role).await;
+                            }
+                        }
                         debug!("[MESSAGE_HANDLER] Sending WebRTCRoomLeaveAck response");
-                        context.tx.send(response).await.map_err(|e| crate::Error::Connection(e.to_string()))?;
+                        context.tx.push(response)?;
                     }
                     Err(e) => {
                         error!("Failed to handle WebRTC room leave message: {}", e);
@@ -509,9 +2189,58 @@ impl WebSocketServer {
                             crate::message::Payload::Error(crate::message::ErrorPayload {
                                 error_code: 1,
                                 error_message: format!("Internal server error: {e}"),
+                                ..Default::default()
                             }),
                         );
-                        context.tx.send(error_message).await.map_err(|e| crate::Error::Connection(e.to_string()))?;
+                        context.tx.push(error_message)?;
+                    }
+                }
+            }
+            Payload::WebRTCRoomList(ref request) => {
+                debug!("[MESSAGE_HANDLER] Handling WebRTCRoomList request");
+                let client_id = context.client_id.lock().await.clone();
+                let Some(client_id) = client_id else {
+                    warn!("[MESSAGE_HANDLER] Rejected WebRTCRoomList from unauthenticated connection");
+                    let error_message = Message::new(
+                        crate::message::MessageType::Error,
+                        crate::message::Payload::Error(crate::message::ErrorPayload {
+                            error_code: 1,
+                            error_message: "Connect before listing rooms".to_string(),
+                            ..Default::default()
+                        }),
+                    );
+                    context.tx.push(error_message)?;
+                    return Ok(());
+                };
+                if !context.webrtc_room_list_handler.is_admin(&client_id) {
+                    warn!("[MESSAGE_HANDLER] Rejected WebRTCRoomList from non-admin client {}", client_id);
+                    let error_message = Message::new(
+                        crate::message::MessageType::Error,
+                        crate::message::Payload::Error(crate::message::ErrorPayload {
+                            error_code: 8,
+                            error_message: "Forbidden: listing rooms requires an admin client".to_string(),
+                            ..Default::default()
+                        }),
+                    );
+                    context.tx.push(error_message)?;
+                    return Ok(());
+                }
+                match context.webrtc_room_list_handler.handle_room_list(request).await {
+                    Ok(response) => {
+                        debug!("[MESSAGE_HANDLER] Sending WebRTCRoomListAck response");
+                        context.tx.push(response)?;
+                    }
+                    Err(e) => {
+                        error!("Failed to handle WebRTC room list message: {}", e);
+                        let error_message = Message::new(
+                            crate::message::MessageType::Error,
+                            crate::message::Payload::Error(crate::message::ErrorPayload {
+                                error_code: 1,
+                                error_message: format!("Internal server error: {e}"),
+                                ..Default::default()
+                            }),
+                        );
+                        context.tx.push(error_message)?;
                     }
                 }
             }
@@ -522,18 +2251,56 @@ impl WebSocketServer {
         Ok(())
     }
 
-    async fn message_routing_task(
+    /// Deliver routed messages to their clients' outbound queues. A client whose queue
+    /// is closed is dead: its connections entry is evicted and its session cleaned up.
+    pub async fn message_routing_task(
         mut receiver: tokio::sync::mpsc::Receiver<(String, Message)>,
-        _session_manager: Arc<SessionManager>,
-        connections: Arc<RwLock<HashMap<String, tokio::sync::mpsc::Sender<Message>>>>,
+        session_manager: Arc<SessionManager>,
+        connections: Arc<RwLock<HashMap<String, Arc<OutboundQueue>>>>,
     ) {
         while let Some((client_id, message)) = receiver.recv().await {
-            let connections = connections.read().await;
-            if let Some(tx) = connections.get(&client_id) {
-                if let Err(e) = tx.send(message).await {
-                    error!("Failed to send message to client {}: {}", client_id, e);
+            let tx = connections.read().await.get(&client_id).cloned();
+            let Some(tx) = tx else { continue };
+            if let Err(e) = tx.push(message) {
+                error!("Failed to send message to client {}: {}", client_id, e);
+                if tx.is_closed() {
+                    Self::evict_dead_client(&client_id, &tx, &session_manager, &connections).await;
                 }
             }
         }
     }
-} 
\ No newline at end of file
+
+    async fn evict_dead_client(
+        client_id: &str,
+        tx: &Arc<OutboundQueue>,
+        session_manager: &SessionManager,
+        connections: &RwLock<HashMap<String, Arc<OutboundQueue>>>,
+    ) {
+        {
+            let mut connections = connections.write().await;
+            // A reconnect may already have replaced the dead entry
+            if !connections.get(client_id).is_some_and(|current| Arc::ptr_eq(current, tx)) {
+                return;
+            }
+            connections.remove(client_id);
+        }
+        info!("[CONNECTION] Evicted dead client {} from connections map", client_id);
+        if let Err(e) = session_manager.handle_disconnect(client_id).await {
+            error!("Failed to clean up session for client {}: {}", client_id, e);
+        }
+    }
+} 
+
+/// Write a complete `Connection: close` HTTP/1.1 response for the plain-HTTP endpoints
+async fn write_http_response(
+    stream: &mut TcpStream,
+    status_line: &str,
+    content_type: &str,
+    body: &str,
+) -> std::io::Result<()> {
+    let response = format!(
+        "HTTP/1.1 {status_line}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await
+}
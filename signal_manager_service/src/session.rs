@@ -1,7 +1,10 @@
-use crate::message::{Message, MessageType, Payload, ConnectAckPayload, ErrorPayload};
+use crate::message::{Message, MessageType, Payload, ConnectAckPayload, ErrorPayload, PeerPayload};
 use crate::auth::AuthManager;
-use std::collections::HashMap;
+use crate::metrics::Metrics;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use tokio::sync::RwLock;
 use tokio::sync::mpsc::{self, Sender, Receiver};
 use uuid::Uuid;
@@ -13,12 +16,63 @@ pub struct ClientSession {
     pub session_id: String,
     pub connected_at: std::time::Instant,
     pub last_heartbeat: std::time::Instant,
+    /// ICE candidates this session has had relayed so far
+    pub ice_candidates_relayed: u64,
+    /// ICE candidates from this session dropped after it reached the cap
+    pub ice_candidates_dropped: u64,
+}
+
+/// In-memory state tracked per room while signals are relayed through it
+#[derive(Debug, Clone, Default)]
+pub struct RoomState {
+    /// Last sequence number assigned per sender client
+    pub sequence_counters: HashMap<String, u64>,
+    /// Read-only members that may receive but not send signal messages
+    pub observers: HashSet<String>,
+    /// Role each member created or joined the room with ("sender", "receiver" or "observer")
+    pub roles: HashMap<String, String>,
+}
+
+impl RoomState {
+    /// Advance and return the next sequence number for `sender_id`, starting at 1
+    pub fn next_sequence(&mut self, sender_id: &str) -> u64 {
+        let counter = self.sequence_counters.entry(sender_id.to_string()).or_insert(0);
+        *counter += 1;
+        *counter
+    }
+}
+
+/// Most ICE candidates an `SdpRecord` keeps; later candidates are still relayed, just not recorded
+pub const MAX_RECORDED_ICE_CANDIDATES: usize = 100;
+
+/// Session description and ICE candidates exchanged in a room, kept for support tooling
+/// when `webrtc.persist_sdp` is enabled until the room ends. Never included in acks sent to clients.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SdpRecord {
+    /// Latest offer, from room creation or a relayed `SignalOffer`
+    pub offer_sdp: Option<String>,
+    /// Latest relayed `SignalAnswer`
+    pub answer_sdp: Option<String>,
+    /// Relayed ICE candidates in order, at most `MAX_RECORDED_ICE_CANDIDATES`; candidates
+    /// dropped by the room cap are not kept
+    pub ice_candidates: Vec<String>,
 }
 
 pub struct SessionManager {
     sessions: Arc<RwLock<HashMap<String, ClientSession>>>,
+    rooms: Arc<RwLock<HashMap<String, RoomState>>>,
+    /// Keyed like `rooms`, and only for rooms there; dropped with the room's state
+    sdp_records: Arc<RwLock<HashMap<String, SdpRecord>>>,
+    /// When each client without a session disconnected, in milliseconds since the Unix epoch
+    disconnected_at: Arc<RwLock<HashMap<String, u64>>>,
     auth_manager: Arc<AuthManager>,
     message_sender: Sender<(String, Message)>,
+    max_ice_candidates_per_room: u64,
+    persist_sdp: bool,
+    metrics: Arc<Metrics>,
+    /// Advertised in every ConnectAck so clients can pace their heartbeats; 0 leaves it out
+    heartbeat_interval: u64,
+    session_timeout: AtomicU64,
 }
 
 impl SessionManager {
@@ -27,13 +81,56 @@ impl SessionManager {
         
         let manager = Self {
             sessions: Arc::new(RwLock::new(HashMap::new())),
+            rooms: Arc::new(RwLock::new(HashMap::new())),
+            sdp_records: Arc::new(RwLock::new(HashMap::new())),
+            disconnected_at: Arc::new(RwLock::new(HashMap::new())),
             auth_manager,
             message_sender: tx,
+            max_ice_candidates_per_room: 0,
+            persist_sdp: false,
+            metrics: Arc::new(Metrics::new()),
+            heartbeat_interval: 0,
+            session_timeout: AtomicU64::new(0),
         };
         
         (manager, rx)
     }
 
+    /// Cap the ICE candidates relayed per room over its lifetime; 0 means unlimited
+    pub fn with_max_ice_candidates_per_room(mut self, max: u64) -> Self {
+        self.max_ice_candidates_per_room = max;
+        self
+    }
+
+    /// Record the SDP and ICE candidates exchanged in each room, readable with `sdp_record`
+    pub fn with_persist_sdp(mut self, persist_sdp: bool) -> Self {
+        self.persist_sdp = persist_sdp;
+        self
+    }
+
+    /// Count authentication failures in `metrics` instead of a private set of counters
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Heartbeat interval, in seconds, advertised to clients in their ConnectAck
+    pub fn with_heartbeat_interval(mut self, secs: u64) -> Self {
+        self.heartbeat_interval = secs;
+        self
+    }
+
+    /// Session timeout, in seconds, advertised to clients in their ConnectAck; 0 means none
+    pub fn with_session_timeout(self, secs: u64) -> Self {
+        self.set_session_timeout(secs);
+        self
+    }
+
+    /// Change the advertised session timeout; sessions already connected keep the value they were sent
+    pub fn set_session_timeout(&self, secs: u64) {
+        self.session_timeout.store(secs, Ordering::Relaxed);
+    }
+
     pub async fn handle_connect(&self, client_id: String, auth_token: String) -> Result<Message, crate::Error> {
         info!("[AUTH] Attempting to authenticate client: {}", client_id);
         
@@ -45,21 +142,25 @@ impl SessionManager {
             }
             Ok(false) => {
                 warn!("[AUTH] Authentication failed for client: {}", client_id);
+                self.metrics.record_auth_failure();
                 return Ok(Message::new(
                     MessageType::Error,
                     Payload::Error(ErrorPayload {
                         error_code: 1,
                         error_message: "Authentication failed".to_string(),
+                        ..Default::default()
                     })
                 ));
             }
             Err(e) => {
                 error!("[AUTH] Authentication error for client {}: {}", client_id, e);
+                self.metrics.record_auth_failure();
                 return Ok(Message::new(
                     MessageType::Error,
                     Payload::Error(ErrorPayload {
                         error_code: 1,
                         error_message: format!("Authentication error: {}", e),
+                        ..Default::default()
                     })
                 ));
             }
@@ -72,12 +173,15 @@ impl SessionManager {
             session_id: session_id.clone(),
             connected_at: std::time::Instant::now(),
             last_heartbeat: std::time::Instant::now(),
+            ice_candidates_relayed: 0,
+            ice_candidates_dropped: 0,
         };
 
         {
             let mut sessions = self.sessions.write().await;
             sessions.insert(client_id.clone(), session);
         }
+        self.disconnected_at.write().await.remove(&client_id);
 
         info!("[SESSION] Client {} connected with session {}", client_id, session_id);
 
@@ -86,6 +190,8 @@ impl SessionManager {
             Payload::ConnectAck(ConnectAckPayload {
                 status: "success".to_string(),
                 session_id,
+                heartbeat_interval: Some(self.heartbeat_interval).filter(|secs| *secs > 0),
+                session_timeout: Some(self.session_timeout.load(Ordering::Relaxed)).filter(|secs| *secs > 0),
             })
         ))
     }
@@ -95,21 +201,96 @@ impl SessionManager {
             let mut sessions = self.sessions.write().await;
             if sessions.remove(client_id).is_some() {
                 info!("Client {} disconnected", client_id);
+                self.disconnected_at.write().await.insert(client_id.to_string(), crate::message::now_millis());
             }
         }
+        self.leave_rooms(&[client_id]).await;
         Ok(())
     }
 
-    pub async fn handle_heartbeat(&self, client_id: String) -> Result<Message, crate::Error> {
+    /// Drop `client_ids` from every room's observers and roles, telling the remaining members.
+    /// Rooms left without members are forgotten.
+    async fn leave_rooms(&self, client_ids: &[&str]) {
+        let mut departures = Vec::new();
         {
-            let mut sessions = self.sessions.write().await;
-            if let Some(session) = sessions.get_mut(&client_id) {
+            let mut rooms = self.rooms.write().await;
+            for (room_id, room) in rooms.iter_mut() {
+                for client_id in client_ids {
+                    room.observers.remove(*client_id);
+                    if let Some(role) = room.roles.remove(*client_id) {
+                        departures.push((room_id.clone(), client_id.to_string(), role));
+                    }
+                }
+            }
+            let emptied: Vec<String> = departures.iter()
+                .map(|(room_id, _, _)| room_id.clone())
+                .filter(|room_id| rooms.get(room_id).is_some_and(|room| room.roles.is_empty()))
+                .collect();
+            self.forget_rooms(&mut rooms, &emptied).await;
+        }
+        for (room_id, client_id, role) in departures {
+            self.announce_peer(MessageType::PeerLeft, &room_id, &client_id, &role).await;
+        }
+    }
+
+    /// Push a `PeerJoined` or `PeerLeft` for `client_id` to the other members of `room_id`
+    pub async fn announce_peer(&self, message_type: MessageType, room_id: &str, client_id: &str, role: &str) {
+        let payload = PeerPayload { room_id: room_id.to_string(), client_id: client_id.to_string(), role: role.to_string() };
+        let message = match message_type {
+            MessageType::PeerJoined => Message::new(message_type, Payload::PeerJoined(payload)),
+            MessageType::PeerLeft => Message::new(message_type, Payload::PeerLeft(payload)),
+            other => {
+                warn!("[SESSION] Not a peer announcement: {:?}", other);
+                return;
+            }
+        };
+        if let Err(e) = self.route_message(client_id.to_string(), message).await {
+            error!("Failed to announce {:?} for {} in room {}: {}", message_type, client_id, room_id, e);
+        }
+    }
+
+    /// Wall-clock time the client was last seen, in milliseconds since the Unix epoch: its last
+    /// heartbeat (or connect) while it has a session, otherwise when it disconnected
+    pub async fn last_seen(&self, client_id: &str) -> Option<u64> {
+        if let Some(session) = self.sessions.read().await.get(client_id) {
+            return Some(wall_clock_millis(session.last_heartbeat));
+        }
+        self.disconnected_at.read().await.get(client_id).copied()
+    }
+
+    /// Forget the last-seen time of clients that disconnected more than `max_age` ago, and
+    /// return how many were dropped
+    pub async fn prune_disconnected(&self, max_age: std::time::Duration) -> usize {
+        let cutoff = crate::message::now_millis().saturating_sub(u64::try_from(max_age.as_millis()).unwrap_or(u64::MAX));
+        let mut disconnected_at = self.disconnected_at.write().await;
+        let before = disconnected_at.len();
+        disconnected_at.retain(|_, at| *at >= cutoff);
+        before - disconnected_at.len()
+    }
+
+    /// Whether `client_id` and `other_id` are members of at least one common room
+    pub async fn share_room(&self, client_id: &str, other_id: &str) -> bool {
+        let rooms = self.rooms.read().await;
+        rooms.values().any(|room| room.roles.contains_key(client_id) && room.roles.contains_key(other_id))
+    }
+
+    /// Record liveness for the client's session without answering, as for a transport Ping or
+    /// Pong. Returns false if the client has no session.
+    pub async fn touch(&self, client_id: &str) -> bool {
+        match self.sessions.write().await.get_mut(client_id) {
+            Some(session) => {
                 session.last_heartbeat = std::time::Instant::now();
-                debug!("Heartbeat from client {}", client_id);
-            } else {
-                return Err(crate::Error::ClientNotFound(client_id));
+                true
             }
+            None => false,
         }
+    }
+
+    pub async fn handle_heartbeat(&self, client_id: String) -> Result<Message, crate::Error> {
+        if !self.touch(&client_id).await {
+            return Err(crate::Error::ClientNotFound(client_id));
+        }
+        debug!("Heartbeat from client {}", client_id);
 
         Ok(Message::new(
             MessageType::HeartbeatAck,
@@ -122,27 +303,106 @@ impl SessionManager {
         ))
     }
 
-    pub async fn route_message(&self, from_client_id: String, message: Message) -> Result<(), crate::Error> {
-        match &message.payload {
+    pub async fn route_message(&self, from_client_id: String, mut message: Message) -> Result<(), crate::Error> {
+        match &mut message.payload {
             Payload::SignalOffer(payload) | Payload::SignalAnswer(payload) | Payload::SignalIceCandidate(payload) => {
-                let target_client_id = &payload.target_client_id;
+                let target_client_id = payload.target_client_id.clone();
+
+                // Observers are read-only members of their rooms
+                if self.signals_as_observer(&from_client_id, &target_client_id, payload.room_id.as_deref()).await {
+                    return Err(crate::Error::Session(format!("Observer {from_client_id} cannot send signal messages")));
+                }
                 
-                // Check if target client exists
+                // Check if target client exists, and count ICE candidates against the sender's
+                // session whatever room the payload names
                 {
-                    let sessions = self.sessions.read().await;
-                    if !sessions.contains_key(target_client_id) {
-                        return Err(crate::Error::ClientNotFound(target_client_id.clone()));
+                    let mut sessions = self.sessions.write().await;
+                    if !sessions.contains_key(&target_client_id) {
+                        return Err(crate::Error::ClientNotFound(target_client_id));
+                    }
+                    if message.message_type == MessageType::SignalIceCandidate {
+                        let session = sessions
+                            .get_mut(&from_client_id)
+                            .ok_or_else(|| crate::Error::ClientNotFound(from_client_id.clone()))?;
+                        if self.max_ice_candidates_per_room > 0 && session.ice_candidates_relayed >= self.max_ice_candidates_per_room {
+                            session.ice_candidates_dropped += 1;
+                            warn!("[SESSION] Dropped ICE candidate from {}: cap of {} reached ({} dropped)",
+                                from_client_id, self.max_ice_candidates_per_room, session.ice_candidates_dropped);
+                            return Ok(());
+                        }
+                        session.ice_candidates_relayed += 1;
+                    }
+                }
+
+                // Stamp the per-(room, sender) sequence number so recipients can detect reordering/loss.
+                // Only rooms the sender is a member of are touched; other room ids are relayed as sent.
+                if let Some(room_id) = payload.room_id.clone() {
+                    let mut rooms = self.rooms.write().await;
+                    if let Some(room) = rooms.get_mut(&room_id).filter(|room| room.roles.contains_key(&from_client_id)) {
+                        payload.sequence = Some(room.next_sequence(&from_client_id));
+
+                        if self.persist_sdp {
+                            let mut records = self.sdp_records.write().await;
+                            let record = records.entry(room_id).or_default();
+                            let signal_data = payload.signal_data.clone();
+                            match message.message_type {
+                                MessageType::SignalOffer => record.offer_sdp = Some(signal_data),
+                                MessageType::SignalAnswer => record.answer_sdp = Some(signal_data),
+                                _ if record.ice_candidates.len() < MAX_RECORDED_ICE_CANDIDATES => record.ice_candidates.push(signal_data),
+                                _ => {}
+                            }
+                        }
                     }
                 }
 
                 // Route the message to the target client
-                if let Err(e) = self.message_sender.send((target_client_id.clone(), message.clone())).await {
+                if let Err(e) = self.message_sender.send((target_client_id.clone(), message)).await {
                     error!("Failed to route message to {}: {}", target_client_id, e);
                     return Err(crate::Error::Connection("Failed to route message".to_string()));
                 }
 
                 debug!("Routed message from {} to {}", from_client_id, target_client_id);
             }
+            Payload::PeerJoined(payload) | Payload::PeerLeft(payload) => {
+                let room_id = payload.room_id.clone();
+                let members: Vec<String> = {
+                    let rooms = self.rooms.read().await;
+                    rooms
+                        .get(&room_id)
+                        .map(|room| room.roles.keys().filter(|id| **id != payload.client_id).cloned().collect())
+                        .unwrap_or_default()
+                };
+
+                // Best-effort: a disconnect handled on the routing task itself must not wait on its own full queue
+                for member in members {
+                    if let Err(e) = self.message_sender.try_send((member.clone(), message.clone())) {
+                        warn!("Dropped {:?} for {} in room {}: {}", message.message_type, member, room_id, e);
+                    }
+                }
+                debug!("Announced {:?} of {} in room {}", message.message_type, from_client_id, room_id);
+            }
+            Payload::AppRelay(payload) => {
+                let room_id = payload.room_id.clone();
+                let members: Vec<String> = {
+                    let rooms = self.rooms.read().await;
+                    let room = rooms
+                        .get(&room_id)
+                        .filter(|room| room.roles.contains_key(&from_client_id))
+                        .ok_or_else(|| crate::Error::Session(format!("{from_client_id} is not a member of room {room_id}")))?;
+                    if room.observers.contains(&from_client_id) {
+                        return Err(crate::Error::Session(format!("Observer {from_client_id} cannot send app messages")));
+                    }
+                    room.roles.keys().filter(|id| **id != from_client_id).cloned().collect()
+                };
+                payload.from_client_id = Some(from_client_id.clone());
+
+                for member in members {
+                    if let Err(e) = self.message_sender.send((member.clone(), message.clone())).await {
+                        error!("Failed to relay app message to {}: {}", member, e);
+                    }
+                }
+                debug!("Relayed app message from {} in room {}", from_client_id, room_id);
+            }
             _ => {
                 warn!("Unexpected message type for routing: {:?}", message.message_type);
             }
@@ -150,26 +410,150 @@ impl SessionManager {
         Ok(())
     }
 
+    pub async fn get_room_state(&self, room_id: &str) -> Option<RoomState> {
+        let rooms = self.rooms.read().await;
+        rooms.get(room_id).cloned()
+    }
+
+    /// Mark `client_id` as a read-only observer of `room_id`
+    pub async fn add_observer(&self, room_id: &str, client_id: &str) {
+        let mut rooms = self.rooms.write().await;
+        rooms.entry(room_id.to_string()).or_default().observers.insert(client_id.to_string());
+        info!("[SESSION] Client {} observing room {}", client_id, room_id);
+    }
+
+    pub async fn remove_observer(&self, room_id: &str, client_id: &str) {
+        let mut rooms = self.rooms.write().await;
+        if let Some(room) = rooms.get_mut(room_id) {
+            room.observers.remove(client_id);
+        }
+    }
+
+    /// Record the role `client_id` holds in `room_id`
+    pub async fn set_member_role(&self, room_id: &str, client_id: &str, role: &str) {
+        let mut rooms = self.rooms.write().await;
+        rooms.entry(room_id.to_string()).or_default().roles.insert(client_id.to_string(), role.to_ascii_lowercase());
+        debug!("[SESSION] Client {} is {} in room {}", client_id, role, room_id);
+    }
+
+    /// Forget `client_id`'s role and observer status in `room_id`, returning the role it held.
+    /// The room's state and SDP record go with its last member.
+    pub async fn remove_member(&self, room_id: &str, client_id: &str) -> Option<String> {
+        let mut rooms = self.rooms.write().await;
+        let room = rooms.get_mut(room_id)?;
+        room.observers.remove(client_id);
+        let role = room.roles.remove(client_id);
+        if room.roles.is_empty() {
+            self.forget_rooms(&mut rooms, &[room_id.to_string()]).await;
+        }
+        role
+    }
+
+    /// Role `client_id` holds in `room_id`, if it is a member
+    pub async fn client_role(&self, room_id: &str, client_id: &str) -> Option<String> {
+        let rooms = self.rooms.read().await;
+        rooms.get(room_id)?.roles.get(client_id).cloned()
+    }
+
+    /// Every distinct role `client_id` holds across the rooms it is a member of, sorted
+    pub async fn client_roles(&self, client_id: &str) -> Vec<String> {
+        let rooms = self.rooms.read().await;
+        let roles: std::collections::BTreeSet<String> = rooms.values()
+            .filter_map(|room| room.roles.get(client_id).cloned())
+            .collect();
+        roles.into_iter().collect()
+    }
+
+    /// Whether `client_id` is observing `room_id`
+    pub async fn is_observer(&self, room_id: &str, client_id: &str) -> bool {
+        let rooms = self.rooms.read().await;
+        rooms.get(room_id).is_some_and(|room| room.observers.contains(client_id))
+    }
+
+    /// Whether a signal from `client_id` to `target_id` is sent as an observer: of `room_id`
+    /// when the signal names one, otherwise of any room the target is a member of
+    pub async fn signals_as_observer(&self, client_id: &str, target_id: &str, room_id: Option<&str>) -> bool {
+        let rooms = self.rooms.read().await;
+        match room_id {
+            Some(room_id) => rooms.get(room_id).is_some_and(|room| room.observers.contains(client_id)),
+            None => rooms.values().any(|room| room.observers.contains(client_id) && room.roles.contains_key(target_id)),
+        }
+    }
+
+    /// Forget an ended room: its relay state and its SDP record
+    pub async fn remove_room_state(&self, room_id: &str) {
+        let mut rooms = self.rooms.write().await;
+        self.forget_rooms(&mut rooms, &[room_id.to_string()]).await;
+    }
+
+    async fn forget_rooms(&self, rooms: &mut HashMap<String, RoomState>, room_ids: &[String]) {
+        if room_ids.is_empty() {
+            return;
+        }
+        let mut records = self.sdp_records.write().await;
+        for room_id in room_ids {
+            if rooms.remove(room_id).is_some() {
+                debug!("Removed room state for {}", room_id);
+            }
+            records.remove(room_id);
+        }
+    }
+
+    /// Record the offer a room was created with; does nothing unless SDP persistence is
+    /// enabled and the room has members
+    pub async fn record_offer_sdp(&self, room_id: &str, offer_sdp: &str) {
+        if self.persist_sdp {
+            let rooms = self.rooms.read().await;
+            if rooms.contains_key(room_id) {
+                let mut records = self.sdp_records.write().await;
+                records.entry(room_id.to_string()).or_default().offer_sdp = Some(offer_sdp.to_string());
+            }
+        }
+    }
+
+    /// SDP and ICE candidates recorded for `room_id`, for support tooling
+    pub async fn sdp_record(&self, room_id: &str) -> Option<SdpRecord> {
+        self.sdp_records.read().await.get(room_id).cloned()
+    }
+
+    pub async fn remove_sdp_record(&self, room_id: &str) -> Option<SdpRecord> {
+        self.sdp_records.write().await.remove(room_id)
+    }
+
+    pub async fn get_session(&self, client_id: &str) -> Option<ClientSession> {
+        self.sessions.read().await.get(client_id).cloned()
+    }
+
     pub async fn get_active_sessions(&self) -> Vec<ClientSession> {
         let sessions = self.sessions.read().await;
         sessions.values().cloned().collect()
     }
 
-    pub async fn cleanup_expired_sessions(&self, max_age: std::time::Duration) {
+    /// End every session whose last heartbeat (or connect) is older than `max_age`, as
+    /// `handle_disconnect` would, and return the client ids whose sessions ended
+    pub async fn cleanup_expired_sessions(&self, max_age: std::time::Duration) -> Vec<String> {
         let now = std::time::Instant::now();
-        let mut sessions = self.sessions.write().await;
-        
-        let expired: Vec<String> = sessions
-            .iter()
-            .filter(|(_, session)| now.duration_since(session.last_heartbeat) > max_age)
-            .map(|(client_id, _)| client_id.clone())
-            .collect();
+        let expired: Vec<String> = {
+            let mut sessions = self.sessions.write().await;
+            let expired: Vec<String> = sessions
+                .iter()
+                .filter(|(_, session)| now.duration_since(session.last_heartbeat) > max_age)
+                .map(|(client_id, _)| client_id.clone())
+                .collect();
 
-        for client_id in expired {
-            if sessions.remove(&client_id).is_some() {
-                info!("Removed expired session for client {}", client_id);
+            let mut disconnected_at = self.disconnected_at.write().await;
+            for client_id in &expired {
+                if let Some(session) = sessions.remove(client_id) {
+                    info!("Removed expired session for client {}", client_id);
+                    disconnected_at.insert(client_id.clone(), wall_clock_millis(session.last_heartbeat));
+                }
             }
+            expired
+        };
+        if !expired.is_empty() {
+            self.leave_rooms(&expired.iter().map(String::as_str).collect::<Vec<_>>()).await;
         }
+        expired
     }
 
     pub async fn broadcast_message(&self, message: Message, exclude_client: Option<&str>) -> Result<(), crate::Error> {
@@ -188,4 +572,9 @@ impl SessionManager {
 
         Ok(())
     }
-} 
\ No newline at end of file
+} 
+
+/// Convert a past `Instant` to milliseconds since the Unix epoch
+fn wall_clock_millis(instant: std::time::Instant) -> u64 {
+    crate::message::now_millis().saturating_sub(instant.elapsed().as_millis() as u64)
+}
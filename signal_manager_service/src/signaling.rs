@@ -0,0 +1,93 @@
+use crate::cloudflare::{CloudflareSession, WebRTCConnectionInfo};
+use serde_json::Value;
+use crate::config::{Config, SignalingProviderKind};
+use crate::message::IceServer;
+use async_trait::async_trait;
+use std::sync::Arc;
+use tracing::warn;
+
+pub type ProviderResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Provider session a client was placed in by `create_session` or `add_track`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProviderSession {
+    /// Session recorded on the room, when the provider has one
+    pub session_id: Option<String>,
+    /// Provider-specific details, passed to the client as the ack's `connection_info`
+    pub connection_info: Value,
+}
+
+impl From<WebRTCConnectionInfo> for ProviderSession {
+    fn from(info: WebRTCConnectionInfo) -> Self {
+        Self {
+            session_id: info.session_id.clone(),
+            connection_info: serde_json::to_value(info).unwrap_or_default(),
+        }
+    }
+}
+
+/// Media backend the WebRTC room handlers open and close sessions on
+#[async_trait]
+pub trait SignalingProvider: Send + Sync {
+    /// Open a session for a sender publishing `offer_sdp` into `room_id`
+    async fn create_session(&self, room_id: &str, client_id: &str, offer_sdp: String) -> ProviderResult<ProviderSession>;
+
+    /// Attach a receiver or observer in `room_id` to the tracks published on `session_id`
+    async fn add_track(&self, room_id: &str, client_id: &str, session_id: &str) -> ProviderResult<ProviderSession>;
+
+    /// Close `session_id` once its client has left `room_id`
+    async fn close_session(&self, session_id: &str, room_id: &str) -> ProviderResult<()>;
+
+    /// ICE servers, typically short-lived TURN relays, that clients should use for their sessions
+    async fn ice_servers(&self) -> ProviderResult<Vec<IceServer>> {
+        Ok(Vec::new())
+    }
+
+    /// Whether the backend is reachable and accepts the configured credentials, for `/readyz`
+    async fn check_health(&self) -> ProviderResult<bool> {
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl SignalingProvider for CloudflareSession {
+    async fn create_session(&self, room_id: &str, client_id: &str, offer_sdp: String) -> ProviderResult<ProviderSession> {
+        Ok(self.create_room_with_sender(room_id, client_id, offer_sdp).await?.into())
+    }
+
+    async fn add_track(&self, room_id: &str, client_id: &str, session_id: &str) -> ProviderResult<ProviderSession> {
+        Ok(self.join_room_as_receiver(room_id, client_id, session_id).await?.into())
+    }
+
+    async fn close_session(&self, session_id: &str, room_id: &str) -> ProviderResult<()> {
+        self.terminate_session(session_id, room_id).await
+    }
+
+    async fn ice_servers(&self) -> ProviderResult<Vec<IceServer>> {
+        Ok(self.ice_servers().await?.into_iter().map(IceServer::from).collect())
+    }
+
+    async fn check_health(&self) -> ProviderResult<bool> {
+        self.validate_credentials().await
+    }
+}
+
+/// `turn_servers` for a room ack. A provider that cannot supply ICE servers leaves
+/// the field out rather than failing the room operation.
+pub async fn turn_servers(provider: &dyn SignalingProvider) -> Option<Vec<IceServer>> {
+    match provider.ice_servers().await {
+        Ok(servers) if !servers.is_empty() => Some(servers),
+        Ok(_) => None,
+        Err(e) => {
+            warn!("Failed to fetch ICE servers from signaling provider: {}", e);
+            None
+        }
+    }
+}
+
+/// Build the provider selected by `webrtc.provider`
+pub fn create_provider(config: Arc<Config>) -> ProviderResult<Arc<dyn SignalingProvider>> {
+    match config.webrtc.provider {
+        SignalingProviderKind::Cloudflare => Ok(Arc::new(CloudflareSession::new(config)?)),
+    }
+}
@@ -0,0 +1,169 @@
+// With neither backend compiled in the acceptor is uninhabited and its methods ignore their arguments
+#![cfg_attr(not(any(feature = "native-tls", feature = "rustls")), allow(unused_variables))]
+
+#[cfg(feature = "native-tls")]
+use std::io::Read;
+use std::pin::Pin;
+#[cfg(feature = "rustls")]
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+#[cfg(feature = "rustls")]
+use rustls::pki_types::pem::PemObject;
+#[cfg(feature = "rustls")]
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+
+use crate::config::{ServerConfig, TlsBackend};
+
+/// Terminates TLS for accepted sockets with the backend chosen by `server.tls_backend`.
+/// Each backend is compiled in only with its cargo feature, `native-tls` or `rustls`.
+#[derive(Clone)]
+pub enum TlsAcceptor {
+    #[cfg(feature = "native-tls")]
+    NativeTls(tokio_native_tls::TlsAcceptor),
+    #[cfg(feature = "rustls")]
+    Rustls(tokio_rustls::TlsAcceptor),
+}
+
+/// A socket after a successful TLS handshake
+pub enum TlsStream {
+    #[cfg(feature = "native-tls")]
+    NativeTls(tokio_native_tls::TlsStream<TcpStream>),
+    #[cfg(feature = "rustls")]
+    Rustls(Box<tokio_rustls::server::TlsStream<TcpStream>>),
+}
+
+impl TlsAcceptor {
+    /// Load the certificate and key at `tls_cert_path` and `tls_key_path` for `tls_backend`.
+    /// native-tls takes a single certificate and a PKCS#8 key; rustls takes a PEM chain,
+    /// leaf first, and a PKCS#8, PKCS#1 or SEC1 key. Selecting a backend whose cargo feature
+    /// is disabled is a configuration error.
+    pub fn from_config(config: &ServerConfig) -> Result<Self, crate::Error> {
+        if config.tls_cert_path.is_empty() || config.tls_key_path.is_empty() {
+            return Err(crate::Error::Config(config::ConfigError::NotFound(
+                "TLS certificate or key path not configured".to_string()
+            )));
+        }
+        match config.tls_backend {
+            #[cfg(feature = "native-tls")]
+            TlsBackend::NativeTls => Self::native_tls(&config.tls_cert_path, &config.tls_key_path),
+            #[cfg(feature = "rustls")]
+            TlsBackend::Rustls => Self::rustls(&config.tls_cert_path, &config.tls_key_path),
+            #[allow(unreachable_patterns)]
+            backend => Err(crate::Error::Config(config::ConfigError::Message(format!(
+                "TLS backend {backend:?} is not compiled in; rebuild with its cargo feature enabled"
+            )))),
+        }
+    }
+
+    #[cfg(feature = "native-tls")]
+    fn native_tls(cert_path: &str, key_path: &str) -> Result<Self, crate::Error> {
+        let cert_data = read_file(cert_path)?;
+        let key_data = read_file(key_path)?;
+
+        let identity = native_tls::Identity::from_pkcs8(&cert_data, &key_data)
+            .map_err(|e| crate::Error::Config(config::ConfigError::NotFound(e.to_string())))?;
+        let acceptor = native_tls::TlsAcceptor::builder(identity)
+            .build()
+            .map_err(|e| crate::Error::Config(config::ConfigError::NotFound(e.to_string())))?;
+        Ok(Self::NativeTls(tokio_native_tls::TlsAcceptor::from(acceptor)))
+    }
+
+    #[cfg(feature = "rustls")]
+    fn rustls(cert_path: &str, key_path: &str) -> Result<Self, crate::Error> {
+        let chain = CertificateDer::pem_file_iter(cert_path)
+            .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| pem_error(cert_path, e))?;
+        if chain.is_empty() {
+            return Err(crate::Error::Config(config::ConfigError::Message(format!(
+                "No certificates found in {cert_path}"
+            ))));
+        }
+        let key = PrivateKeyDer::from_pem_file(key_path).map_err(|e| pem_error(key_path, e))?;
+
+        let server_config = rustls::ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(chain, key)
+            .map_err(|e| crate::Error::Config(config::ConfigError::Message(format!("Invalid TLS certificate or key: {e}"))))?;
+        Ok(Self::Rustls(tokio_rustls::TlsAcceptor::from(Arc::new(server_config))))
+    }
+
+    /// Which backend this acceptor uses
+    pub fn backend(&self) -> TlsBackend {
+        match *self {
+            #[cfg(feature = "native-tls")]
+            Self::NativeTls(_) => TlsBackend::NativeTls,
+            #[cfg(feature = "rustls")]
+            Self::Rustls(_) => TlsBackend::Rustls,
+        }
+    }
+
+    /// Run the server side of the TLS handshake on `stream`
+    pub async fn accept(&self, stream: TcpStream) -> std::io::Result<TlsStream> {
+        match *self {
+            #[cfg(feature = "native-tls")]
+            Self::NativeTls(ref acceptor) => acceptor.accept(stream).await
+                .map(TlsStream::NativeTls)
+                .map_err(std::io::Error::other),
+            #[cfg(feature = "rustls")]
+            Self::Rustls(ref acceptor) => acceptor.accept(stream).await.map(|stream| TlsStream::Rustls(Box::new(stream))),
+        }
+    }
+}
+
+#[cfg(feature = "native-tls")]
+fn read_file(path: &str) -> Result<Vec<u8>, crate::Error> {
+    let mut data = Vec::new();
+    std::fs::File::open(path)
+        .map_err(|e| crate::Error::Io(std::io::Error::other(e)))?
+        .read_to_end(&mut data)
+        .map_err(crate::Error::Io)?;
+    Ok(data)
+}
+
+#[cfg(feature = "rustls")]
+fn pem_error(path: &str, error: rustls::pki_types::pem::Error) -> crate::Error {
+    crate::Error::Config(config::ConfigError::Message(format!("Failed to read PEM file {path}: {error}")))
+}
+
+impl AsyncRead for TlsStream {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        match *self.get_mut() {
+            #[cfg(feature = "native-tls")]
+            Self::NativeTls(ref mut stream) => Pin::new(stream).poll_read(cx, buf),
+            #[cfg(feature = "rustls")]
+            Self::Rustls(ref mut stream) => Pin::new(stream.as_mut()).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for TlsStream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+        match *self.get_mut() {
+            #[cfg(feature = "native-tls")]
+            Self::NativeTls(ref mut stream) => Pin::new(stream).poll_write(cx, buf),
+            #[cfg(feature = "rustls")]
+            Self::Rustls(ref mut stream) => Pin::new(stream.as_mut()).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match *self.get_mut() {
+            #[cfg(feature = "native-tls")]
+            Self::NativeTls(ref mut stream) => Pin::new(stream).poll_flush(cx),
+            #[cfg(feature = "rustls")]
+            Self::Rustls(ref mut stream) => Pin::new(stream.as_mut()).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match *self.get_mut() {
+            #[cfg(feature = "native-tls")]
+            Self::NativeTls(ref mut stream) => Pin::new(stream).poll_shutdown(cx),
+            #[cfg(feature = "rustls")]
+            Self::Rustls(ref mut stream) => Pin::new(stream.as_mut()).poll_shutdown(cx),
+        }
+    }
+}
@@ -1,2 +1,40 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use uuid::Uuid;
+
 pub mod register;
-pub mod unregister; 
\ No newline at end of file
+pub mod unregister;
+
+/// Body of a 400 response to a register or unregister request, which both response types read back
+#[derive(Serialize)]
+struct FieldErrorResponse<'a> {
+    version: &'a str,
+    status: u16,
+    message: &'a str,
+    field: &'a str,
+    details: BTreeMap<&'static str, &'a str>,
+}
+
+/// A 400 response naming the request `field` that failed validation and why
+pub(crate) fn field_error_response(frame_id: Uuid, message: &str, field: &str, reason: &str) -> (Uuid, String) {
+    let response = FieldErrorResponse {
+        version: register::CURRENT_VERSION,
+        status: 400,
+        message,
+        field,
+        details: BTreeMap::from([("reason", reason)]),
+    };
+    let response_json = serde_json::to_string(&response)
+        .unwrap_or_else(|_| format!("{{\"version\":\"{}\",\"status\":500}}", register::CURRENT_VERSION));
+    (frame_id, response_json)
+}
+
+/// Why a required string field of a type 2 request is unusable, or `None` when it is a string
+pub(crate) fn string_field_problem(value: Option<&serde_json::Value>) -> Option<&'static str> {
+    match value {
+        None | Some(serde_json::Value::Null) => Some("is required"),
+        Some(serde_json::Value::String(_)) => None,
+        Some(_) => Some("must be a string"),
+    }
+}
@@ -1,14 +1,18 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use uuid::Uuid;
 use std::sync::Arc;
 use tracing::{error, info, warn};
 
 use crate::config::get_config;
 use crate::database::{
-    FirestoreRepositoryFactory, RegistrationPayload as DbRegistrationPayload, RepositoryFactory,
+    DatabaseResult, FirestoreRepositoryFactory, RegistrationPayload as DbRegistrationPayload, RepositoryFactory,
     ClientRepository,
 };
 use crate::config::Config;
+use crate::events::{self, EventClient, EventMessage, NoopEventClient};
+use crate::type_two_handlers::unregister::{handle_unregister_with_repositories, UnregisterRepositories};
+use super::{field_error_response, string_field_problem};
 
 pub const CURRENT_VERSION: &str = "1.0.0";
 
@@ -22,24 +26,72 @@ pub struct RegisterPayload {
     pub metadata: Option<serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct RegisterResponse {
     pub version: String,
     pub status: u16,
     pub message: Option<String>,
     pub client_id: Option<String>,
     pub session_id: Option<String>,
+    /// Request field a 400 response's validation failure refers to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    /// Why `field` was rejected, under "reason"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<BTreeMap<String, String>>,
 }
 
 // Test helper struct for integration tests
 #[derive(Clone)]
 pub struct RegisterHandler {
     config: Arc<Config>,
+    repositories: Option<UnregisterRepositories>,
+    /// Client store shared by every request this handler and its clones serve, so
+    /// `database.max_clients` counts every registration; created on first use
+    clients: Arc<tokio::sync::OnceCell<Arc<dyn ClientRepository + Send + Sync>>>,
+    event_client: Arc<dyn EventClient>,
 }
 
 impl RegisterHandler {
     pub fn new(config: Arc<Config>) -> Self {
-        Self { config }
+        Self { config, repositories: None, clients: Arc::default(), event_client: Arc::new(NoopEventClient) }
+    }
+
+    /// The client repository registrations and unregistrations share
+    async fn client_repository(&self) -> DatabaseResult<Arc<dyn ClientRepository + Send + Sync>> {
+        if let Some(repositories) = &self.repositories {
+            return Ok(repositories.clients.clone());
+        }
+        self.clients
+            .get_or_try_init(|| async { FirestoreRepositoryFactory::new(self.config.clone()).create_client_repository().await })
+            .await
+            .cloned()
+    }
+
+    /// Ids of the clients the store evicted to make room since the last call, for the server
+    /// to disconnect
+    pub async fn take_evicted_clients(&self) -> Vec<String> {
+        let repository = match &self.repositories {
+            Some(repositories) => Some(&repositories.clients),
+            None => self.clients.get(),
+        };
+        match repository {
+            Some(repository) => repository.take_evicted().await,
+            None => Vec::new(),
+        }
+    }
+
+    /// Register and unregister against `repositories` instead of the Firestore-backed ones;
+    /// registration only uses `clients`
+    pub fn with_repositories(mut self, repositories: UnregisterRepositories) -> Self {
+        self.repositories = Some(repositories);
+        self
+    }
+
+    /// Publish registration lifecycle events on `event_client` instead of dropping them
+    pub fn with_event_client(mut self, event_client: Arc<dyn EventClient>) -> Self {
+        self.event_client = event_client;
+        self
     }
 
     pub async fn handle_register(&self, message: crate::message::Message) -> Result<crate::message::Message, Box<dyn std::error::Error + Send + Sync>> {
@@ -48,11 +100,13 @@ impl RegisterHandler {
             crate::message::Payload::Register(payload) => payload,
             _ => return Err("Invalid message type".into()),
         };
+        if let Err(e) = message.payload.validate_ids(self.config.server.max_id_length) {
+            warn!("[REGISTER] Rejected invalid payload: {}", e);
+            return Ok(crate::webrtc_handlers::invalid_payload_response(crate::message::MessageType::RegisterAck, e));
+        }
 
-        // Create repository when needed
-        let factory = FirestoreRepositoryFactory::new(self.config.clone());
-        let repository = match factory.create_client_repository().await {
-            Ok(repo) => repo,
+        let repository = match self.client_repository().await {
+            Ok(repository) => repository,
             Err(e) => {
                 error!("Failed to create repository: {}", e);
                 return Err("Database connection failed".into());
@@ -60,7 +114,7 @@ impl RegisterHandler {
         };
 
         let raw_payload = serde_json::to_value(payload)?;
-        let (_, response_json) = handle_register_internal(frame_id, raw_payload, repository.clone()).await;
+        let (_, response_json) = handle_register_internal(frame_id, raw_payload, repository, &self.event_client).await;
         
         let response_payload: RegisterResponse = serde_json::from_str(&response_json)?;
         
@@ -81,8 +135,11 @@ impl RegisterHandler {
             })
         } else {
             crate::message::Payload::Error(crate::message::ErrorPayload {
-                error_code: response_payload.status as u8,
+                error_code: crate::message::ErrorPayload::code_for_status(response_payload.status),
                 error_message: response_payload.message.unwrap_or_else(|| "Unknown error".to_string()),
+                field: response_payload.field,
+                details: response_payload.details,
+                status: Some(response_payload.status),
             })
         };
 
@@ -98,19 +155,32 @@ impl RegisterHandler {
             crate::message::Payload::Unregister(payload) => payload,
             _ => return Err("Invalid message type".into()),
         };
+        if let Err(e) = message.payload.validate_ids(self.config.server.max_id_length) {
+            warn!("[UNREGISTER] Rejected invalid payload: {}", e);
+            return Ok(crate::webrtc_handlers::invalid_payload_response(crate::message::MessageType::UnregisterAck, e));
+        }
 
-        // Create repository when needed
-        let factory = FirestoreRepositoryFactory::new(self.config.clone());
-        let repository = match factory.create_client_repository().await {
-            Ok(repo) => repo,
-            Err(e) => {
-                error!("Failed to create repository: {}", e);
-                return Err("Database connection failed".into());
+        // Create repositories when needed
+        let repositories = match &self.repositories {
+            Some(repositories) => repositories.clone(),
+            None => {
+                let factory = FirestoreRepositoryFactory::new(self.config.clone());
+                let repositories = match UnregisterRepositories::from_factory(&factory).await {
+                    Ok(repositories) => self.client_repository().await.map(|clients| UnregisterRepositories { clients, ..repositories }),
+                    Err(e) => Err(e),
+                };
+                match repositories {
+                    Ok(repos) => repos,
+                    Err(e) => {
+                        error!("Failed to create repositories: {}", e);
+                        return Err("Database connection failed".into());
+                    }
+                }
             }
         };
 
         let raw_payload = serde_json::to_value(payload)?;
-        let (_, response_json) = handle_unregister_internal(frame_id, raw_payload, repository.clone()).await;
+        let (_, response_json) = handle_unregister_with_repositories(frame_id, raw_payload, &repositories, &self.event_client).await;
         
         let response_payload: UnregisterResponse = serde_json::from_str(&response_json)?;
         
@@ -130,8 +200,11 @@ impl RegisterHandler {
             })
         } else {
             crate::message::Payload::Error(crate::message::ErrorPayload {
-                error_code: response_payload.status as u8,
+                error_code: crate::message::ErrorPayload::code_for_status(response_payload.status),
                 error_message: response_payload.message.unwrap_or_else(|| "Unknown error".to_string()),
+                field: response_payload.field,
+                details: response_payload.details,
+                status: Some(response_payload.status),
             })
         };
 
@@ -148,12 +221,17 @@ pub struct UnregisterResponse {
     pub status: u16,
     pub message: Option<String>,
     pub client_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<BTreeMap<String, String>>,
 }
 
 async fn handle_register_internal(
     frame_id: Uuid, 
     raw_payload: serde_json::Value,
-    repository: Arc<dyn ClientRepository + Send + Sync>
+    repository: Arc<dyn ClientRepository + Send + Sync>,
+    event_client: &Arc<dyn EventClient>,
 ) -> (Uuid, String) {
     // Validate and parse JSON payload
     let version = raw_payload.get("version");
@@ -161,19 +239,15 @@ async fn handle_register_internal(
     let auth_token = raw_payload.get("auth_token");
 
     // Check required fields and types
-    if version.is_none() || !version.unwrap().is_string() {
-        return error_response(frame_id, 400, "Missing or invalid 'version' field");
-    }
-    if client_id.is_none() || !client_id.unwrap().is_string() {
-        return error_response(frame_id, 400, "Missing or invalid 'client_id' field");
-    }
-    if auth_token.is_none() || !auth_token.unwrap().is_string() {
-        return error_response(frame_id, 400, "Missing or invalid 'auth_token' field");
+    for (field, value) in [("version", version), ("client_id", client_id), ("auth_token", auth_token)] {
+        if let Some(reason) = string_field_problem(value) {
+            return field_error_response(frame_id, &format!("Missing or invalid '{field}' field"), field, reason);
+        }
     }
 
     let version_str = version.unwrap().as_str().unwrap();
     if version_str > CURRENT_VERSION {
-        return error_response(frame_id, 400, "Unsupported version: newer than server");
+        return field_error_response(frame_id, "Unsupported version: newer than server", "version", "is newer than the server");
     }
 
     // Parse the payload into RegisterPayload
@@ -186,10 +260,10 @@ async fn handle_register_internal(
 
     // Validate again for empty strings
     if payload.client_id.trim().is_empty() {
-        return error_response(frame_id, 400, "Client ID is required");
+        return field_error_response(frame_id, "Client ID is required", "client_id", "must not be empty");
     }
     if payload.auth_token.trim().is_empty() {
-        return error_response(frame_id, 400, "Auth token is required");
+        return field_error_response(frame_id, "Auth token is required", "auth_token", "must not be empty");
     }
 
     let db_payload = DbRegistrationPayload {
@@ -200,16 +274,22 @@ async fn handle_register_internal(
         metadata: payload.metadata,
     };
 
-    match repository.create_client(db_payload).await {
+    // Registering again with the same token refreshes the registration instead of failing
+    match repository.upsert_client(db_payload).await {
         Ok(client) => {
             info!("Successfully registered client: {}", client.client_id);
             let session_id = Uuid::new_v4().to_string();
+            events::publish_in_background(event_client, EventMessage::new(events::CLIENT_REGISTERED, serde_json::json!({
+                "client_id": client.client_id,
+                "session_id": session_id,
+            })));
             let response = RegisterResponse {
                 version: CURRENT_VERSION.to_string(),
                 status: 200,
                 message: Some("Registration successful".to_string()),
                 client_id: Some(client.client_id),
                 session_id: Some(session_id),
+                ..Default::default()
             };
             let response_json = serde_json::to_string(&response).unwrap_or_else(|_| format!("{{\"version\":\"{CURRENT_VERSION}\",\"status\":500}}"));
             (frame_id, response_json)
@@ -220,6 +300,8 @@ async fn handle_register_internal(
                 crate::database::DatabaseError::Validation(_) => 409,
                 crate::database::DatabaseError::Authentication(_) => 401,
                 crate::database::DatabaseError::Connection(_) => 503,
+                crate::database::DatabaseError::Unavailable(_) => 503,
+                crate::database::DatabaseError::Capacity(_) => 503,
                 _ => 500,
             };
             let response = RegisterResponse {
@@ -228,90 +310,7 @@ async fn handle_register_internal(
                 message: Some(format!("Registration failed: {e}")),
                 client_id: None,
                 session_id: None,
-            };
-            let response_json = serde_json::to_string(&response).unwrap_or_else(|_| format!("{{\"version\":\"{CURRENT_VERSION}\",\"status\":500}}"));
-            (frame_id, response_json)
-        }
-    }
-}
-
-async fn handle_unregister_internal(
-    frame_id: Uuid, 
-    raw_payload: serde_json::Value,
-    repository: Arc<dyn ClientRepository + Send + Sync>
-) -> (Uuid, String) {
-    // Validate and parse JSON payload
-    let version = raw_payload.get("version");
-    let client_id = raw_payload.get("client_id");
-    let auth_token = raw_payload.get("auth_token");
-
-    // Check required fields and types
-    if version.is_none() || !version.unwrap().is_string() {
-        return error_response(frame_id, 400, "Missing or invalid 'version' field");
-    }
-    if client_id.is_none() || !client_id.unwrap().is_string() {
-        return error_response(frame_id, 400, "Missing or invalid 'client_id' field");
-    }
-    if auth_token.is_none() || !auth_token.unwrap().is_string() {
-        return error_response(frame_id, 400, "Missing or invalid 'auth_token' field");
-    }
-
-    let version_str = version.unwrap().as_str().unwrap();
-    if version_str > CURRENT_VERSION {
-        return error_response(frame_id, 400, "Unsupported version: newer than server");
-    }
-
-    // Parse the payload into UnregisterPayload
-    let payload: crate::message::UnregisterPayload = match serde_json::from_value(raw_payload) {
-        Ok(p) => p,
-        Err(_) => return error_response(frame_id, 400, "Malformed unregister payload"),
-    };
-
-    info!("Processing unregister request for client: {}", payload.client_id);
-
-    // Validate again for empty strings
-    if payload.client_id.trim().is_empty() {
-        return error_response(frame_id, 400, "Client ID is required");
-    }
-    if payload.auth_token.trim().is_empty() {
-        return error_response(frame_id, 400, "Auth token is required");
-    }
-
-    // Validate auth before deleting
-    match repository.validate_auth(&payload.client_id, &payload.auth_token).await {
-        Ok(true) => {},
-        Ok(false) => {
-            return error_response(frame_id, 401, "Invalid client_id or auth_token");
-        }
-        Err(e) => {
-            error!("Failed to validate auth: {}", e);
-            return error_response(frame_id, 500, "Database error during auth validation");
-        }
-    }
-
-    match repository.delete_client(&payload.client_id).await {
-        Ok(true) => {
-            info!("Successfully unregistered client: {}", payload.client_id);
-            let response = UnregisterResponse {
-                version: CURRENT_VERSION.to_string(),
-                status: 200,
-                message: Some("Unregistration successful".to_string()),
-                client_id: Some(payload.client_id),
-            };
-            let response_json = serde_json::to_string(&response).unwrap_or_else(|_| format!("{{\"version\":\"{CURRENT_VERSION}\",\"status\":500}}"));
-            (frame_id, response_json)
-        }
-        Ok(false) => {
-            error!("Client not found for unregistration: {}", payload.client_id);
-            error_response(frame_id, 404, "Client not found")
-        }
-        Err(e) => {
-            error!("Failed to unregister client: {}", e);
-            let response = UnregisterResponse {
-                version: CURRENT_VERSION.to_string(),
-                status: 500,
-                message: Some(format!("Unregistration failed: {e}")),
-                client_id: None,
+                ..Default::default()
             };
             let response_json = serde_json::to_string(&response).unwrap_or_else(|_| format!("{{\"version\":\"{CURRENT_VERSION}\",\"status\":500}}"));
             (frame_id, response_json)
@@ -332,7 +331,7 @@ pub async fn handle_register(frame_id: Uuid, raw_payload: serde_json::Value) ->
         }
     };
 
-    handle_register_internal(frame_id, raw_payload, repository).await
+    handle_register_internal(frame_id, raw_payload, repository, &events::shared_event_client(config)).await
 }
 
 fn error_response(frame_id: Uuid, status: u16, message: &str) -> (Uuid, String) {
@@ -342,7 +341,8 @@ fn error_response(frame_id: Uuid, status: u16, message: &str) -> (Uuid, String)
         message: Some(message.to_string()),
         client_id: None,
         session_id: None,
+        ..Default::default()
     };
     let response_json = serde_json::to_string(&response).unwrap_or_else(|_| format!("{{\"version\":\"{CURRENT_VERSION}\",\"status\":500}}"));
     (frame_id, response_json)
-} 
\ No newline at end of file
+}
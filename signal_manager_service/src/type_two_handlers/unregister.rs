@@ -1,15 +1,24 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use uuid::Uuid;
 use std::sync::Arc;
 use tracing::{error, info};
 
 use crate::config::get_config;
+use crate::events::{self, EventClient, EventMessage};
 use crate::database::{
-    FirestoreRepositoryFactory, RepositoryFactory, ClientRepository,
+    FirestoreRepositoryFactory, RepositoryFactory, ClientRepository, ClientInRoomRepository,
+    WebRTCRoomRepository, WebRTCClientRepository, WebRTCRoomStatus, DatabaseError, DatabaseResult,
 };
+use super::{field_error_response, string_field_problem};
 
 pub const CURRENT_VERSION: &str = "1.0.0";
 
+/// Reason recorded when a client's room memberships end because it unregistered
+const UNREGISTER_REASON: &str = "Client unregistered";
+/// Termination reason of rooms whose sender unregistered
+const OWNER_UNREGISTERED_REASON: &str = "Owner unregistered";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UnregisterPayload {
     pub version: String,
@@ -17,38 +26,68 @@ pub struct UnregisterPayload {
     pub auth_token: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct UnregisterResponse {
     pub version: String,
     pub status: u16,
     pub message: Option<String>,
     pub client_id: Option<String>,
+    /// Request field a 400 response's validation failure refers to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    /// Why `field` was rejected, under "reason"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<BTreeMap<String, String>>,
 }
 
-async fn handle_unregister_internal(
-    frame_id: Uuid, 
+/// Repositories touched when a client unregisters
+#[derive(Clone)]
+pub struct UnregisterRepositories {
+    pub clients: Arc<dyn ClientRepository + Send + Sync>,
+    pub clients_in_rooms: Arc<dyn ClientInRoomRepository + Send + Sync>,
+    pub webrtc_rooms: Arc<dyn WebRTCRoomRepository + Send + Sync>,
+    pub webrtc_clients: Arc<dyn WebRTCClientRepository + Send + Sync>,
+}
+
+impl UnregisterRepositories {
+    pub async fn from_factory(factory: &dyn RepositoryFactory) -> DatabaseResult<Self> {
+        Ok(Self {
+            clients: factory.create_client_repository().await?,
+            clients_in_rooms: factory.create_client_in_room_repository().await?,
+            webrtc_rooms: factory.create_webrtc_room_repository().await?,
+            webrtc_clients: factory.create_webrtc_client_repository().await?,
+        })
+    }
+}
+
+/// Unregister a client, removing its room memberships and terminating rooms it owns, and
+/// publish each of those transitions on `event_client`.
+///
+/// The client record is deleted last so that a failed cleanup can be retried with the
+/// same credentials. Unregistering a client that does not exist, including one an earlier
+/// request already unregistered, is answered with 404.
+pub async fn handle_unregister_with_repositories(
+    frame_id: Uuid,
     raw_payload: serde_json::Value,
-    repository: Arc<dyn ClientRepository + Send + Sync>
+    repositories: &UnregisterRepositories,
+    event_client: &Arc<dyn EventClient>,
 ) -> (Uuid, String) {
+    let repository = &repositories.clients;
     // Validate and parse JSON payload
     let version = raw_payload.get("version");
     let client_id = raw_payload.get("client_id");
     let auth_token = raw_payload.get("auth_token");
 
     // Check required fields and types
-    if version.is_none() || !version.unwrap().is_string() {
-        return error_response(frame_id, 400, "Missing or invalid 'version' field");
-    }
-    if client_id.is_none() || !client_id.unwrap().is_string() {
-        return error_response(frame_id, 400, "Missing or invalid 'client_id' field");
-    }
-    if auth_token.is_none() || !auth_token.unwrap().is_string() {
-        return error_response(frame_id, 400, "Missing or invalid 'auth_token' field");
+    for (field, value) in [("version", version), ("client_id", client_id), ("auth_token", auth_token)] {
+        if let Some(reason) = string_field_problem(value) {
+            return field_error_response(frame_id, &format!("Missing or invalid '{field}' field"), field, reason);
+        }
     }
 
     let version_str = version.unwrap().as_str().unwrap();
     if version_str > CURRENT_VERSION {
-        return error_response(frame_id, 400, "Unsupported version: newer than server");
+        return field_error_response(frame_id, "Unsupported version: newer than server", "version", "is newer than the server");
     }
 
     // Parse the payload into UnregisterPayload
@@ -61,17 +100,27 @@ async fn handle_unregister_internal(
 
     // Validate again for empty strings
     if payload.client_id.trim().is_empty() {
-        return error_response(frame_id, 400, "Client ID is required");
+        return field_error_response(frame_id, "Client ID is required", "client_id", "must not be empty");
     }
     if payload.auth_token.trim().is_empty() {
-        return error_response(frame_id, 400, "Auth token is required");
+        return field_error_response(frame_id, "Auth token is required", "auth_token", "must not be empty");
     }
 
     // Validate auth before deleting
     match repository.validate_auth(&payload.client_id, &payload.auth_token).await {
         Ok(true) => {},
         Ok(false) => {
-            return error_response(frame_id, 401, "Invalid client_id or auth_token");
+            return match repository.client_exists(&payload.client_id).await {
+                Ok(false) => {
+                    info!("Unregister for unknown client: {}", payload.client_id);
+                    error_response(frame_id, 404, "Client not registered")
+                }
+                Ok(true) => error_response(frame_id, 401, "Invalid client_id or auth_token"),
+                Err(e) => {
+                    error!("Failed to check client existence: {}", e);
+                    error_response(frame_id, 500, "Database error during auth validation")
+                }
+            };
         }
         Err(e) => {
             error!("Failed to validate auth: {}", e);
@@ -79,21 +128,22 @@ async fn handle_unregister_internal(
         }
     }
 
+    if let Err(e) = cleanup_room_state(&payload.client_id, repositories, event_client).await {
+        error!("Failed to clean up room state for client {}: {}", payload.client_id, e);
+        return error_response(frame_id, 500, "Failed to clean up room state");
+    }
+
     match repository.delete_client(&payload.client_id).await {
         Ok(true) => {
             info!("Successfully unregistered client: {}", payload.client_id);
-            let response = UnregisterResponse {
-                version: CURRENT_VERSION.to_string(),
-                status: 200,
-                message: Some("Unregistration successful".to_string()),
-                client_id: Some(payload.client_id),
-            };
-            let response_json = serde_json::to_string(&response).unwrap_or_else(|_| format!("{{\"version\":\"{CURRENT_VERSION}\",\"status\":500}}"));
-            (frame_id, response_json)
+            events::publish_in_background(event_client, EventMessage::new(events::CLIENT_UNREGISTERED, serde_json::json!({
+                "client_id": payload.client_id,
+            })));
+            success_response(frame_id, "Unregistration successful", payload.client_id)
         }
         Ok(false) => {
-            error!("Client not found for unregistration: {}", payload.client_id);
-            error_response(frame_id, 404, "Client not found")
+            info!("Client already unregistered: {}", payload.client_id);
+            success_response(frame_id, "Client already unregistered", payload.client_id)
         }
         Err(e) => {
             error!("Failed to unregister client: {}", e);
@@ -102,6 +152,7 @@ async fn handle_unregister_internal(
                 status: 500,
                 message: Some(format!("Unregistration failed: {e}")),
                 client_id: None,
+                ..Default::default()
             };
             let response_json = serde_json::to_string(&response).unwrap_or_else(|_| format!("{{\"version\":\"{CURRENT_VERSION}\",\"status\":500}}"));
             (frame_id, response_json)
@@ -114,15 +165,69 @@ pub async fn handle_unregister(frame_id: Uuid, raw_payload: serde_json::Value) -
     let config = get_config();
     let config_arc = Arc::new(config.clone());
     let factory = FirestoreRepositoryFactory::new(config_arc);
-    let repository = match factory.create_client_repository().await {
-        Ok(repo) => repo,
+    let repositories = match UnregisterRepositories::from_factory(&factory).await {
+        Ok(repos) => repos,
         Err(e) => {
-            error!("Failed to create repository: {}", e);
+            error!("Failed to create repositories: {}", e);
             return error_response(frame_id, 500, "Database connection failed");
         }
     };
 
-    handle_unregister_internal(frame_id, raw_payload, repository).await
+    handle_unregister_with_repositories(frame_id, raw_payload, &repositories, &events::shared_event_client(config)).await
+}
+
+/// Remove every room membership held by the client and terminate the rooms it sends in.
+/// Records that have already been removed are skipped, so repeated calls are harmless.
+async fn cleanup_room_state(
+    client_id: &str,
+    repositories: &UnregisterRepositories,
+    event_client: &Arc<dyn EventClient>,
+) -> DatabaseResult<()> {
+    let memberships = repositories.clients_in_rooms.get_rooms_for_client(client_id).await?;
+    for membership in memberships {
+        repositories.clients_in_rooms.remove_client_from_room(&membership.id).await?;
+        info!("Removed client {} from room {}", client_id, membership.room_id);
+        events::publish_in_background(event_client, EventMessage::new(events::CLIENT_LEFT_ROOM, serde_json::json!({
+            "client_id": client_id,
+            "room_id": membership.room_id,
+            "reason": UNREGISTER_REASON,
+        })));
+    }
+
+    let rooms = repositories.webrtc_rooms.get_rooms_by_client_id(client_id).await?;
+    for room in rooms {
+        if room.sender_client_id.as_deref() != Some(client_id) || room.status == WebRTCRoomStatus::Terminated {
+            continue;
+        }
+        match repositories.webrtc_rooms.terminate_room(&room.room_id, OWNER_UNREGISTERED_REASON).await {
+            Ok(()) => {
+                info!("Terminated room {} owned by unregistered client {}", room.room_id, client_id);
+                events::publish_in_background(event_client, EventMessage::new(events::ROOM_TERMINATED, serde_json::json!({
+                    "room_id": room.room_id,
+                    "reason": OWNER_UNREGISTERED_REASON,
+                })));
+            }
+            Err(DatabaseError::NotFound(_)) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    match repositories.webrtc_clients.delete_client(client_id).await {
+        Ok(()) | Err(DatabaseError::NotFound(_)) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+fn success_response(frame_id: Uuid, message: &str, client_id: String) -> (Uuid, String) {
+    let response = UnregisterResponse {
+        version: CURRENT_VERSION.to_string(),
+        status: 200,
+        message: Some(message.to_string()),
+        client_id: Some(client_id),
+        ..Default::default()
+    };
+    let response_json = serde_json::to_string(&response).unwrap_or_else(|_| format!("{{\"version\":\"{CURRENT_VERSION}\",\"status\":500}}"));
+    (frame_id, response_json)
 }
 
 fn error_response(frame_id: Uuid, status: u16, message: &str) -> (Uuid, String) {
@@ -131,7 +236,8 @@ fn error_response(frame_id: Uuid, status: u16, message: &str) -> (Uuid, String)
         status,
         message: Some(message.to_string()),
         client_id: None,
+        ..Default::default()
     };
     let response_json = serde_json::to_string(&response).unwrap_or_else(|_| format!("{{\"version\":\"{CURRENT_VERSION}\",\"status\":500}}"));
     (frame_id, response_json)
-} 
\ No newline at end of file
+}
@@ -1,7 +1,22 @@
 pub mod room_create;
+pub mod room_expiry;
 pub mod room_join;
 pub mod room_leave;
+pub mod room_list;
+pub mod where_am_i;
 
 pub use room_create::WebRTCRoomCreateHandler;
 pub use room_join::WebRTCRoomJoinHandler;
-pub use room_leave::WebRTCRoomLeaveHandler; 
\ No newline at end of file
+pub use room_leave::WebRTCRoomLeaveHandler;
+pub use room_list::WebRTCRoomListHandler;
+pub use where_am_i::WhereAmIHandler;
+/// Error sent under `ack_type` when a request fails `Payload::validate`
+pub(crate) fn invalid_payload_response(
+    ack_type: crate::message::MessageType,
+    err: crate::Error,
+) -> crate::message::Message {
+    crate::message::Message::new(
+        ack_type,
+        crate::message::Payload::Error(crate::message::ErrorPayload::invalid_payload(&err)),
+    )
+}
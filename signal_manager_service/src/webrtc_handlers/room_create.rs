@@ -4,12 +4,14 @@ use std::sync::Arc;
 use tracing::{error, info, warn, debug};
 
 use crate::config::get_config;
+use crate::events::{self, EventClient, EventMessage, NoopEventClient};
 use crate::database::{
-    FirestoreRepositoryFactory, RepositoryFactory, WebRTCRoomRepository, WebRTCClientRepository,
-    WebRTCRoomCreationPayload, WebRTCClientRegistrationPayload, ClientRole as DbClientRole,
+    DatabaseResult, FirestoreRepositoryFactory, RepositoryFactory, WebRTCRoomRepository, WebRTCClientRepository,
+    WebRTCRoomCreationPayload, WebRTCClientRegistrationPayload, ClientRole as DbClientRole, DEFAULT_MAX_PARTICIPANTS,
 };
-use crate::cloudflare::{CloudflareSession, models::*};
 use crate::config::Config;
+use crate::message::IceServer;
+use crate::signaling::{turn_servers, SignalingProvider};
 
 pub const CURRENT_VERSION: &str = "1.0.0";
 
@@ -20,6 +22,8 @@ pub struct WebRTCRoomCreatePayload {
     pub auth_token: String,
     pub role: String, // "sender" or "receiver"
     pub offer_sdp: Option<String>, // Required for sender
+    #[serde(default)]
+    pub max_participants: Option<u32>,
     pub metadata: Option<serde_json::Value>,
 }
 
@@ -33,19 +37,61 @@ pub struct WebRTCRoomCreateResponse {
     pub app_id: Option<String>,
     pub stun_url: Option<String>,
     pub connection_info: Option<serde_json::Value>,
+    #[serde(default)]
+    pub turn_servers: Option<Vec<IceServer>>,
+}
+
+/// Repositories written when a client creates a room
+#[derive(Clone)]
+pub struct RoomCreateRepositories {
+    pub webrtc_rooms: Arc<dyn WebRTCRoomRepository + Send + Sync>,
+    pub webrtc_clients: Arc<dyn WebRTCClientRepository + Send + Sync>,
+}
+
+impl RoomCreateRepositories {
+    pub async fn from_factory(factory: &dyn RepositoryFactory) -> DatabaseResult<Self> {
+        Ok(Self {
+            webrtc_rooms: factory.create_webrtc_room_repository().await?,
+            webrtc_clients: factory.create_webrtc_client_repository().await?,
+        })
+    }
 }
 
 #[derive(Clone)]
 pub struct WebRTCRoomCreateHandler {
     config: Arc<Config>,
+    repositories: Option<RoomCreateRepositories>,
+    provider: Option<Arc<dyn SignalingProvider>>,
+    event_client: Arc<dyn EventClient>,
 }
 
 impl WebRTCRoomCreateHandler {
     pub fn new(config: Arc<Config>) -> Self {
-        Self { config }
+        Self { config, repositories: None, provider: None, event_client: Arc::new(NoopEventClient) }
+    }
+
+    /// Handle creates against `repositories` instead of the Firestore-backed ones
+    pub fn with_repositories(mut self, repositories: RoomCreateRepositories) -> Self {
+        self.repositories = Some(repositories);
+        self
+    }
+
+    /// Open sender sessions on `provider` instead of the one `webrtc.provider` selects
+    pub fn with_provider(mut self, provider: Arc<dyn SignalingProvider>) -> Self {
+        self.provider = Some(provider);
+        self
     }
 
-    pub async fn handle_room_create(&self, message: crate::message::Message) -> Result<crate::message::Message, Box<dyn std::error::Error + Send + Sync>> {
+    /// Publish room creation events on `event_client` instead of dropping them
+    pub fn with_event_client(mut self, event_client: Arc<dyn EventClient>) -> Self {
+        self.event_client = event_client;
+        self
+    }
+
+    pub async fn handle_room_create(
+        &self,
+        message: crate::message::Message,
+    ) -> Result<crate::message::Message, Box<dyn std::error::Error + Send + Sync>> {
         let frame_id = message.uuid;
         debug!("[WEBRTC_ROOM_CREATE] Starting room creation request: frame_id={}", frame_id);
         
@@ -53,31 +99,42 @@ impl WebRTCRoomCreateHandler {
             crate::message::Payload::WebRTCRoomCreate(payload) => payload,
             _ => return Err("Invalid message type".into()),
         };
+        if let Err(e) = payload.validate()
+            .and_then(|_| payload.validate_max_participants(self.config.webrtc.max_room_participants))
+            .and_then(|_| message.payload.validate_ids(self.config.server.max_id_length)) {
+            warn!("[WEBRTC_ROOM_CREATE] Rejected invalid payload: {}", e);
+            return Ok(super::invalid_payload_response(crate::message::MessageType::WebRTCRoomCreateAck, e));
+        }
 
         debug!("[WEBRTC_ROOM_CREATE] Room creation payload: client_id={}, role={}", payload.client_id, payload.role);
 
         // Create repositories
-        let factory = FirestoreRepositoryFactory::new(self.config.clone());
-        let room_repository = match factory.create_webrtc_room_repository().await {
-            Ok(repo) => {
-                debug!("[WEBRTC_ROOM_CREATE] Room repository created successfully");
-                repo
-            },
-            Err(e) => {
-                error!("Failed to create room repository: {}", e);
-                return Err("Database connection failed".into());
+        let repositories = match &self.repositories {
+            Some(repositories) => repositories.clone(),
+            None => {
+                let factory = FirestoreRepositoryFactory::new(self.config.clone());
+                match RoomCreateRepositories::from_factory(&factory).await {
+                    Ok(repositories) => {
+                        debug!("[WEBRTC_ROOM_CREATE] Repositories created successfully");
+                        repositories
+                    }
+                    Err(e) => {
+                        error!("Failed to create room create repositories: {}", e);
+                        return Err("Database connection failed".into());
+                    }
+                }
             }
         };
 
-        let client_repository = match factory.create_webrtc_client_repository().await {
-            Ok(repo) => {
-                debug!("[WEBRTC_ROOM_CREATE] Client repository created successfully");
-                repo
+        let provider = match &self.provider {
+            Some(provider) => provider.clone(),
+            None => match crate::signaling::create_provider(self.config.clone()) {
+                Ok(provider) => provider,
+                Err(e) => {
+                    error!("Failed to create signaling provider: {}", e);
+                    return Err("Signaling provider unavailable".into());
+                }
             },
-            Err(e) => {
-                error!("Failed to create client repository: {}", e);
-                return Err("Database connection failed".into());
-            }
         };
 
         let raw_payload = serde_json::to_value(payload)?;
@@ -85,8 +142,10 @@ impl WebRTCRoomCreateHandler {
         let (_, response_json) = handle_room_create_internal(
             frame_id, 
             raw_payload, 
-            room_repository.clone(), 
-            client_repository.clone()
+            repositories.webrtc_rooms, 
+            repositories.webrtc_clients,
+            provider.as_ref(),
+            &self.event_client,
         ).await;
         
         let response_payload: WebRTCRoomCreateResponse = serde_json::from_str(&response_json)?;
@@ -111,12 +170,15 @@ impl WebRTCRoomCreateHandler {
                 app_id: response_payload.app_id,
                 stun_url: response_payload.stun_url,
                 connection_info: response_payload.connection_info,
+                turn_servers: response_payload.turn_servers,
             })
         } else {
             debug!("[WEBRTC_ROOM_CREATE] Creating error response");
             crate::message::Payload::Error(crate::message::ErrorPayload {
-                error_code: response_payload.status as u8,
+                error_code: crate::message::ErrorPayload::code_for_status(response_payload.status),
                 error_message: response_payload.message.unwrap_or_else(|| "Unknown error".to_string()),
+                status: Some(response_payload.status),
+                ..Default::default()
             })
         };
 
@@ -137,6 +199,8 @@ async fn handle_room_create_internal(
     raw_payload: serde_json::Value,
     room_repository: Arc<dyn WebRTCRoomRepository + Send + Sync>,
     client_repository: Arc<dyn WebRTCClientRepository + Send + Sync>,
+    provider: &dyn SignalingProvider,
+    event_client: &Arc<dyn EventClient>,
 ) -> (Uuid, String) {
     debug!("[WEBRTC_ROOM_CREATE_INTERNAL] Starting internal room creation: frame_id={}", frame_id);
     
@@ -199,24 +263,24 @@ async fn handle_room_create_internal(
     }
 
     // Generate room ID
-    let room_id = CloudflareSession::generate_room_id();
+    let room_id = Uuid::new_v4().to_string();
     debug!("[WEBRTC_ROOM_CREATE_INTERNAL] Generated room ID: {}", room_id);
     
-    // Create Cloudflare session if sender
+    // Create provider session if sender
     let mut session_id = None;
     let mut connection_info = None;
     
     if client_role == DbClientRole::Sender {
-        debug!("[WEBRTC_ROOM_CREATE_INTERNAL] Creating Cloudflare session for sender");
-        match create_cloudflare_session(&room_id, &payload.client_id, payload.offer_sdp.unwrap()).await {
+        debug!("[WEBRTC_ROOM_CREATE_INTERNAL] Creating provider session for sender");
+        match provider.create_session(&room_id, &payload.client_id, payload.offer_sdp.unwrap()).await {
             Ok(info) => {
-                session_id = info.session_id.clone();
-                connection_info = Some(serde_json::to_value(info).unwrap());
-                debug!("[WEBRTC_ROOM_CREATE_INTERNAL] Cloudflare session created: session_id={:?}", session_id);
+                session_id = info.session_id;
+                connection_info = Some(info.connection_info);
+                debug!("[WEBRTC_ROOM_CREATE_INTERNAL] Provider session created: session_id={:?}", session_id);
             }
             Err(e) => {
-                error!("Failed to create Cloudflare session: {}", e);
-                return error_response(frame_id, 500, "Failed to create Cloudflare session");
+                error!("Failed to create provider session: {}", e);
+                return error_response(frame_id, 500, "Failed to create session");
             }
         }
     }
@@ -228,6 +292,7 @@ async fn handle_room_create_internal(
         sender_client_id: if client_role == DbClientRole::Sender { Some(payload.client_id.clone()) } else { None },
         receiver_client_id: if client_role == DbClientRole::Receiver { Some(payload.client_id.clone()) } else { None },
         session_id: session_id.clone(),
+        max_participants: payload.max_participants,
         metadata: payload.metadata.clone(),
     };
 
@@ -252,7 +317,8 @@ async fn handle_room_create_internal(
     };
 
     debug!("[WEBRTC_ROOM_CREATE_INTERNAL] Registering client in database: client_id={}, room_id={}", payload.client_id, room_id);
-    match client_repository.register_client(client_payload).await {
+    // The room is new, so its creator is always admitted
+    match client_repository.admit_client(client_payload, payload.max_participants.unwrap_or(DEFAULT_MAX_PARTICIPANTS)).await {
         Ok(_) => {
             info!("Registered WebRTC client: {} in room: {}", payload.client_id, room_id);
         }
@@ -261,6 +327,12 @@ async fn handle_room_create_internal(
             return error_response(frame_id, 500, "Failed to register client in database");
         }
     }
+    events::publish_in_background(event_client, EventMessage::new(events::ROOM_CREATED, serde_json::json!({
+        "room_id": room_id,
+        "client_id": payload.client_id,
+        "role": payload.role.to_ascii_lowercase(),
+        "session_id": session_id,
+    })));
 
     // Create success response
     let response = WebRTCRoomCreateResponse {
@@ -272,6 +344,7 @@ async fn handle_room_create_internal(
         app_id: Some(get_config().cloudflare.app_id.clone()),
         stun_url: Some(get_config().cloudflare.stun_url.clone()),
         connection_info,
+        turn_servers: turn_servers(provider).await,
     };
 
     let response_json = serde_json::to_string(&response).unwrap();
@@ -279,17 +352,6 @@ async fn handle_room_create_internal(
     (frame_id, response_json)
 }
 
-async fn create_cloudflare_session(
-    room_id: &str,
-    client_id: &str,
-    offer_sdp: String,
-) -> Result<WebRTCConnectionInfo, Box<dyn std::error::Error + Send + Sync>> {
-    let config = get_config();
-    let session_manager = CloudflareSession::new(Arc::new(config.clone()))?;
-    
-    session_manager.create_room_with_sender(room_id, client_id, offer_sdp).await
-}
-
 fn error_response(frame_id: Uuid, status: u16, message: &str) -> (Uuid, String) {
     debug!("[WEBRTC_ROOM_CREATE_INTERNAL] Creating error response: frame_id={}, status={}, message={}", frame_id, status, message);
     let response = WebRTCRoomCreateResponse {
@@ -301,6 +363,7 @@ fn error_response(frame_id: Uuid, status: u16, message: &str) -> (Uuid, String)
         app_id: None,
         stun_url: None,
         connection_info: None,
+        turn_servers: None,
     };
     
     let response_json = serde_json::to_string(&response).unwrap();
@@ -0,0 +1,125 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use chrono::{DateTime, Duration, Utc};
+use tracing::{info, warn};
+
+use crate::database::{
+    ClientInRoomRepository, ClientInTerminatedRoom, ClientInTerminatedRoomRepository, DatabaseError, DatabaseResult,
+    RepositoryFactory, WebRTCClientRepository, WebRTCRoom, WebRTCRoomRepository, WebRTCRoomStatus,
+};
+
+/// Termination reason recorded for rooms that outlive `webrtc.max_room_lifetime_secs`
+pub const EXPIRY_REASON: &str = "Maximum room lifetime exceeded";
+
+/// Termination reason recorded for sender-only rooms that outlive `webrtc.answer_timeout_secs`
+pub const ANSWER_TIMEOUT_REASON: &str = "No receiver answered in time";
+
+/// Repositories read and updated by the room lifetime sweep
+#[derive(Clone)]
+pub struct RoomExpiryRepositories {
+    pub webrtc_rooms: Arc<dyn WebRTCRoomRepository + Send + Sync>,
+    pub webrtc_clients: Arc<dyn WebRTCClientRepository + Send + Sync>,
+    pub clients_in_rooms: Arc<dyn ClientInRoomRepository + Send + Sync>,
+    pub clients_in_terminated_rooms: Arc<dyn ClientInTerminatedRoomRepository + Send + Sync>,
+}
+
+impl RoomExpiryRepositories {
+    pub async fn from_factory(factory: &dyn RepositoryFactory) -> DatabaseResult<Self> {
+        Ok(Self {
+            webrtc_rooms: factory.create_webrtc_room_repository().await?,
+            webrtc_clients: factory.create_webrtc_client_repository().await?,
+            clients_in_rooms: factory.create_client_in_room_repository().await?,
+            clients_in_terminated_rooms: factory.create_client_in_terminated_room_repository().await?,
+        })
+    }
+}
+
+/// A room terminated by the sweep, with the clients that were part of it and the
+/// provider sessions still open for them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpiredRoom {
+    pub room_id: String,
+    pub participants: Vec<String>,
+    pub session_ids: Vec<String>,
+}
+
+/// Terminate every non-terminated room created more than `max_lifetime` before `now`,
+/// regardless of activity. Rooms removed concurrently are skipped.
+pub async fn terminate_expired_rooms(
+    repositories: &RoomExpiryRepositories,
+    max_lifetime: Duration,
+    now: DateTime<Utc>,
+) -> DatabaseResult<Vec<ExpiredRoom>> {
+    let rooms = repositories.webrtc_rooms.get_rooms_created_before(now - max_lifetime).await?;
+    terminate_rooms(repositories, rooms, EXPIRY_REASON).await
+}
+
+/// Terminate every non-terminated room created more than `answer_timeout` before `now`
+/// that has a sender but still no receiver.
+pub async fn terminate_unanswered_rooms(
+    repositories: &RoomExpiryRepositories,
+    answer_timeout: Duration,
+    now: DateTime<Utc>,
+) -> DatabaseResult<Vec<ExpiredRoom>> {
+    let rooms = repositories.webrtc_rooms.get_rooms_created_before(now - answer_timeout).await?
+        .into_iter()
+        .filter(|room| room.sender_client_id.is_some() && room.receiver_client_id.is_none())
+        .collect();
+    terminate_rooms(repositories, rooms, ANSWER_TIMEOUT_REASON).await
+}
+
+async fn terminate_rooms(
+    repositories: &RoomExpiryRepositories,
+    rooms: Vec<WebRTCRoom>,
+    reason: &str,
+) -> DatabaseResult<Vec<ExpiredRoom>> {
+    let mut expired = Vec::new();
+    for room in rooms.into_iter().filter(|room| room.status != WebRTCRoomStatus::Terminated) {
+        let expired_room = room_members(repositories, &room).await?;
+        match repositories.webrtc_rooms.terminate_room(&room.room_id, reason).await {
+            Ok(()) => {
+                info!("[ROOM_EXPIRY] Terminated room {} created at {}: {}", room.room_id, room.created_at, reason);
+                record_forced_terminations(repositories, &room, &expired_room.participants, reason).await;
+                expired.push(expired_room);
+            }
+            Err(DatabaseError::NotFound(_)) => warn!("[ROOM_EXPIRY] Room {} disappeared before termination", room.room_id),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(expired)
+}
+
+/// Record each participant as removed by the server; failures are logged, not fatal
+async fn record_forced_terminations(repositories: &RoomExpiryRepositories, room: &WebRTCRoom, participants: &[String], reason: &str) {
+    for client_id in participants {
+        // Participants known only from the room record are taken to have joined when it was created
+        let joined_at = match repositories.webrtc_clients.get_client_by_id(client_id).await {
+            Ok(Some(client)) if client.room_id == room.room_id => client.joined_at,
+            _ => room.created_at,
+        };
+        let record = ClientInTerminatedRoom::forced_termination(client_id.clone(), room.room_id.clone(), joined_at, reason);
+        if let Err(e) = repositories.clients_in_terminated_rooms.create_client_in_terminated_room(record).await {
+            warn!("[ROOM_EXPIRY] Failed to record termination of {} in room {}: {}", client_id, room.room_id, e);
+        }
+    }
+}
+
+async fn room_members(repositories: &RoomExpiryRepositories, room: &WebRTCRoom) -> DatabaseResult<ExpiredRoom> {
+    let mut participants: BTreeSet<String> = room.sender_client_id.iter()
+        .chain(room.receiver_client_id.iter())
+        .cloned()
+        .collect();
+    let mut session_ids: BTreeSet<String> = room.get_session_id().map(str::to_string).into_iter().collect();
+    for client in repositories.webrtc_clients.get_clients_by_room_id(&room.room_id).await? {
+        session_ids.extend(client.get_session_id().map(str::to_string));
+        participants.insert(client.client_id);
+    }
+    for membership in repositories.clients_in_rooms.get_clients_in_room(&room.room_id).await? {
+        participants.insert(membership.client_id);
+    }
+    Ok(ExpiredRoom {
+        room_id: room.room_id.clone(),
+        participants: participants.into_iter().collect(),
+        session_ids: session_ids.into_iter().collect(),
+    })
+}
@@ -1,15 +1,20 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use std::sync::Arc;
 use tracing::{error, info, warn};
 
+use crate::auth::AuthManager;
 use crate::config::get_config;
+use crate::events::{self, EventClient, EventMessage, NoopEventClient};
 use crate::database::{
-    FirestoreRepositoryFactory, RepositoryFactory, WebRTCRoomRepository, WebRTCClientRepository,
-    WebRTCClientRegistrationPayload, ClientRole as DbClientRole,
+    ClientInRoom, ClientInRoomRepository, DatabaseResult, FirestoreRepositoryFactory, RepositoryFactory, WebRTCRoomRepository,
+    WebRTCClientRepository, WebRTCClientRegistrationPayload, ClientRole as DbClientRole, WebRTCClient,
 };
-use crate::cloudflare::{CloudflareSession, models::*};
+use crate::message::RoomParticipant;
 use crate::config::Config;
+use crate::message::IceServer;
+use crate::signaling::{turn_servers, SignalingProvider};
 
 pub const CURRENT_VERSION: &str = "1.0.0";
 
@@ -19,7 +24,7 @@ pub struct WebRTCRoomJoinPayload {
     pub client_id: String,
     pub auth_token: String,
     pub room_id: String,
-    pub role: String, // "sender" or "receiver"
+    pub role: String, // "sender", "receiver" or "observer"
     pub offer_sdp: Option<String>, // Required for sender
     pub metadata: Option<serde_json::Value>,
 }
@@ -34,49 +39,118 @@ pub struct WebRTCRoomJoinResponse {
     pub app_id: Option<String>,
     pub stun_url: Option<String>,
     pub connection_info: Option<serde_json::Value>,
+    #[serde(default)]
+    pub turn_servers: Option<Vec<IceServer>>,
+    #[serde(default)]
+    pub participants: Option<Vec<RoomParticipant>>,
+}
+
+/// Repositories read and written when a client joins a room
+#[derive(Clone)]
+pub struct RoomJoinRepositories {
+    pub webrtc_rooms: Arc<dyn WebRTCRoomRepository + Send + Sync>,
+    pub webrtc_clients: Arc<dyn WebRTCClientRepository + Send + Sync>,
+    pub clients_in_rooms: Arc<dyn ClientInRoomRepository + Send + Sync>,
+}
+
+impl RoomJoinRepositories {
+    pub async fn from_factory(factory: &dyn RepositoryFactory) -> DatabaseResult<Self> {
+        Ok(Self {
+            webrtc_rooms: factory.create_webrtc_room_repository().await?,
+            webrtc_clients: factory.create_webrtc_client_repository().await?,
+            clients_in_rooms: factory.create_client_in_room_repository().await?,
+        })
+    }
 }
 
 #[derive(Clone)]
 pub struct WebRTCRoomJoinHandler {
     config: Arc<Config>,
+    repositories: Option<RoomJoinRepositories>,
+    provider: Option<Arc<dyn SignalingProvider>>,
+    auth_manager: Arc<AuthManager>,
+    event_client: Arc<dyn EventClient>,
 }
 
 impl WebRTCRoomJoinHandler {
     pub fn new(config: Arc<Config>) -> Self {
-        Self { config }
+        let auth_manager = Arc::new(AuthManager::new(config.clone()));
+        Self { config, repositories: None, provider: None, auth_manager, event_client: Arc::new(NoopEventClient) }
+    }
+
+    /// Handle joins against `repositories` instead of the Firestore-backed ones
+    pub fn with_repositories(mut self, repositories: RoomJoinRepositories) -> Self {
+        self.repositories = Some(repositories);
+        self
+    }
+
+    /// Open and attach to sessions on `provider` instead of the one `webrtc.provider` selects
+    pub fn with_provider(mut self, provider: Arc<dyn SignalingProvider>) -> Self {
+        self.provider = Some(provider);
+        self
+    }
+
+    /// Publish join events on `event_client` instead of dropping them
+    pub fn with_event_client(mut self, event_client: Arc<dyn EventClient>) -> Self {
+        self.event_client = event_client;
+        self
     }
 
-    pub async fn handle_room_join(&self, message: crate::message::Message) -> Result<crate::message::Message, Box<dyn std::error::Error + Send + Sync>> {
+    /// Check the tokens of rejoining clients with `auth_manager` instead of one built from the config
+    pub fn with_auth_manager(mut self, auth_manager: Arc<AuthManager>) -> Self {
+        self.auth_manager = auth_manager;
+        self
+    }
+
+    pub async fn handle_room_join(
+        &self,
+        message: crate::message::Message,
+    ) -> Result<crate::message::Message, Box<dyn std::error::Error + Send + Sync>> {
         let frame_id = message.uuid;
         let payload = match &message.payload {
             crate::message::Payload::WebRTCRoomJoin(payload) => payload,
             _ => return Err("Invalid message type".into()),
         };
+        if let Err(e) = payload.validate().and_then(|_| message.payload.validate_ids(self.config.server.max_id_length)) {
+            warn!("[WEBRTC_ROOM_JOIN] Rejected invalid payload: {}", e);
+            return Ok(super::invalid_payload_response(crate::message::MessageType::WebRTCRoomJoinAck, e));
+        }
 
         // Create repositories
-        let factory = FirestoreRepositoryFactory::new(self.config.clone());
-        let room_repository = match factory.create_webrtc_room_repository().await {
-            Ok(repo) => repo,
-            Err(e) => {
-                error!("Failed to create room repository: {}", e);
-                return Err("Database connection failed".into());
+        let repositories = match &self.repositories {
+            Some(repositories) => repositories.clone(),
+            None => {
+                let factory = FirestoreRepositoryFactory::new(self.config.clone());
+                match RoomJoinRepositories::from_factory(&factory).await {
+                    Ok(repositories) => repositories,
+                    Err(e) => {
+                        error!("Failed to create room join repositories: {}", e);
+                        return Err("Database connection failed".into());
+                    }
+                }
             }
         };
 
-        let client_repository = match factory.create_webrtc_client_repository().await {
-            Ok(repo) => repo,
-            Err(e) => {
-                error!("Failed to create client repository: {}", e);
-                return Err("Database connection failed".into());
-            }
+        let provider = match &self.provider {
+            Some(provider) => provider.clone(),
+            None => match crate::signaling::create_provider(self.config.clone()) {
+                Ok(provider) => provider,
+                Err(e) => {
+                    error!("Failed to create signaling provider: {}", e);
+                    return Err("Signaling provider unavailable".into());
+                }
+            },
         };
 
         let raw_payload = serde_json::to_value(payload)?;
         let (_, response_json) = handle_room_join_internal(
             frame_id, 
             raw_payload, 
-            room_repository.clone(), 
-            client_repository.clone()
+            &repositories,
+            provider.as_ref(),
+            &self.auth_manager,
+            self.config.webrtc.rejoin_grace_secs,
+            &self.event_client,
         ).await;
         
         let response_payload: WebRTCRoomJoinResponse = serde_json::from_str(&response_json)?;
@@ -100,11 +174,15 @@ impl WebRTCRoomJoinHandler {
                 app_id: response_payload.app_id,
                 stun_url: response_payload.stun_url,
                 connection_info: response_payload.connection_info,
+                turn_servers: response_payload.turn_servers,
+                participants: response_payload.participants,
             })
         } else {
             crate::message::Payload::Error(crate::message::ErrorPayload {
-                error_code: response_payload.status as u8,
+                error_code: crate::message::ErrorPayload::code_for_status(response_payload.status),
                 error_message: response_payload.message.unwrap_or_else(|| "Unknown error".to_string()),
+                status: Some(response_payload.status),
+                ..Default::default()
             })
         };
 
@@ -118,9 +196,15 @@ impl WebRTCRoomJoinHandler {
 async fn handle_room_join_internal(
     frame_id: Uuid, 
     raw_payload: serde_json::Value,
-    room_repository: Arc<dyn WebRTCRoomRepository + Send + Sync>,
-    client_repository: Arc<dyn WebRTCClientRepository + Send + Sync>,
+    repositories: &RoomJoinRepositories,
+    provider: &dyn SignalingProvider,
+    auth_manager: &AuthManager,
+    rejoin_grace_secs: u64,
+    event_client: &Arc<dyn EventClient>,
 ) -> (Uuid, String) {
+    let room_repository = &repositories.webrtc_rooms;
+    let client_repository = &repositories.webrtc_clients;
+
     // Validate and parse JSON payload
     let version = raw_payload.get("version");
     let client_id = raw_payload.get("client_id");
@@ -159,19 +243,12 @@ async fn handle_room_join_internal(
     info!("Processing WebRTC room join request for client: {} in room: {} with role: {}", 
         payload.client_id, payload.room_id, payload.role);
 
-    // Validate role
-    let role_str = payload.role.to_lowercase();
-    let client_role = match role_str.as_str() {
-        "sender" => DbClientRole::Sender,
-        "receiver" => DbClientRole::Receiver,
-        _ => return error_response(frame_id, 400, "Invalid role: must be 'sender' or 'receiver'"),
+    // Validate role and offer_sdp
+    let client_role = match validate_join_role(&payload.role, payload.offer_sdp.is_some()) {
+        Ok(role) => role,
+        Err(message) => return error_response(frame_id, 400, message),
     };
 
-    // Validate offer_sdp for sender
-    if client_role == DbClientRole::Sender && payload.offer_sdp.is_none() {
-        return error_response(frame_id, 400, "Offer SDP is required for sender role");
-    }
-
     // Check if room exists
     let room = match room_repository.get_room_by_id(&payload.room_id).await {
         Ok(Some(room)) => room,
@@ -188,7 +265,7 @@ async fn handle_room_join_internal(
     }
 
     // Check if client is already in the room
-    let existing_clients = match client_repository.get_clients_by_room_id(&payload.room_id).await {
+    let mut existing_clients = match client_repository.get_clients_by_room_id(&payload.room_id).await {
         Ok(clients) => clients,
         Err(e) => {
             error!("Failed to get clients from database: {}", e);
@@ -196,75 +273,193 @@ async fn handle_room_join_internal(
         }
     };
 
-    for client in &existing_clients {
-        if client.get_client_id() == payload.client_id {
+    // A client already in the room may be reconnecting after its socket dropped
+    if let Some(existing) = existing_clients.iter().find(|client| client.get_client_id() == payload.client_id) {
+        let memberships = match repositories.clients_in_rooms.get_clients_in_room(&payload.room_id).await {
+            Ok(memberships) => memberships,
+            Err(e) => {
+                error!("Failed to get room memberships from database: {}", e);
+                return error_response(frame_id, 500, "Database error");
+            }
+        };
+        let Some(membership) = memberships.into_iter().find(|membership| membership.client_id == payload.client_id) else {
+            return error_response(frame_id, 409, "Client already in room");
+        };
+        if rejoin_grace_secs == 0 {
             return error_response(frame_id, 409, "Client already in room");
         }
+        match auth_manager.authenticate(&payload.client_id, &payload.auth_token).await {
+            Ok(true) => {}
+            Ok(false) => {
+                warn!("Rejected rejoin of client {} to room {}: invalid token", payload.client_id, payload.room_id);
+                return error_response(frame_id, 409, "Client already in room");
+            }
+            Err(e) => {
+                error!("Failed to authenticate rejoining client: {}", e);
+                return error_response(frame_id, 500, "Authentication error");
+            }
+        }
+        if existing.get_role() != &client_role {
+            return error_response(frame_id, 409, "Client already in room with another role");
+        }
+
+        if within_rejoin_window(&membership, rejoin_grace_secs, Utc::now()) {
+            if let Err(e) = repositories.clients_in_rooms.update_client_last_activity(&membership.id).await {
+                error!("Failed to update room membership activity: {}", e);
+                return error_response(frame_id, 500, "Database error");
+            }
+            info!("Client {} rejoined room {} as {}", payload.client_id, payload.room_id, payload.role);
+            let response = WebRTCRoomJoinResponse {
+                version: CURRENT_VERSION.to_string(),
+                status: 200,
+                message: Some("Rejoined room successfully".to_string()),
+                room_id: Some(payload.room_id.clone()),
+                session_id: existing.get_session_id().map(str::to_string),
+                app_id: Some(get_config().cloudflare.app_id.clone()),
+                stun_url: Some(get_config().cloudflare.stun_url.clone()),
+                connection_info: None,
+                turn_servers: turn_servers(provider).await,
+                participants: Some(room_participants(&existing_clients, &payload.client_id)),
+            };
+            return (frame_id, serde_json::to_string(&response).unwrap());
+        }
+
+        // The membership went quiet too long ago; replace it with a fresh join
+        info!("Rejoin window of client {} in room {} expired, joining afresh", payload.client_id, payload.room_id);
+        if let Err(e) = repositories.clients_in_rooms.remove_client_from_room(&membership.id).await {
+            error!("Failed to remove expired room membership: {}", e);
+            return error_response(frame_id, 500, "Database error");
+        }
+        if let Err(e) = client_repository.delete_client(&payload.client_id).await {
+            error!("Failed to remove expired client record: {}", e);
+            return error_response(frame_id, 500, "Database error");
+        }
+        existing_clients.retain(|client| client.get_client_id() != payload.client_id);
+    }
+
+    // Claim a place in the room before any provider work; observers watch without taking one
+    let client_payload = WebRTCClientRegistrationPayload {
+        client_id: payload.client_id.clone(),
+        room_id: payload.room_id.clone(),
+        role: client_role.clone(),
+        session_id: None,
+        metadata: payload.metadata.clone(),
+    };
+    match client_repository.admit_client(client_payload, room.max_participants).await {
+        Ok(Some(_)) => {
+            info!("Registered WebRTC client: {} in room: {}", payload.client_id, payload.room_id);
+        }
+        Ok(None) => {
+            warn!("Rejected join of client {} to full room {} (max {} participants)", payload.client_id, payload.room_id, room.max_participants);
+            return error_response(frame_id, 409, "Room is full");
+        }
+        Err(e) => {
+            error!("Failed to register client in database: {}", e);
+            return error_response(frame_id, 500, "Failed to register client in database");
+        }
     }
 
-    // Handle Cloudflare session
+    // Handle provider session
     let mut _session_id = None;
     let mut _connection_info = None;
 
     if client_role == DbClientRole::Sender {
-        // Create new Cloudflare session for sender
-        match create_cloudflare_session(&payload.room_id, &payload.client_id, payload.offer_sdp.unwrap()).await {
+        // Create new provider session for sender
+        match provider.create_session(&payload.room_id, &payload.client_id, payload.offer_sdp.unwrap()).await {
             Ok(info) => {
-                _session_id = info.session_id.clone();
-                _connection_info = Some(serde_json::to_value(info).unwrap());
+                _session_id = info.session_id;
+                _connection_info = Some(info.connection_info);
             }
             Err(e) => {
-                error!("Failed to create Cloudflare session: {}", e);
-                return error_response(frame_id, 500, "Failed to create Cloudflare session");
+                error!("Failed to create provider session: {}", e);
+                release_place(client_repository, &payload.client_id).await;
+                return error_response(frame_id, 500, "Failed to create session");
+            }
+        }
+    } else if client_role == DbClientRole::Observer {
+        // Observers attach to the existing session when there is one; otherwise they only receive signaling
+        if let Some(existing_session_id) = room.get_session_id() {
+            match provider.add_track(&payload.room_id, &payload.client_id, existing_session_id).await {
+                Ok(info) => {
+                    _session_id = info.session_id;
+                    _connection_info = Some(info.connection_info);
+                }
+                Err(e) => {
+                    error!("Failed to join provider session: {}", e);
+                    release_place(client_repository, &payload.client_id).await;
+                    return error_response(frame_id, 500, "Failed to join session");
+                }
             }
         }
     } else {
         // For receiver, join existing session
         if let Some(existing_session_id) = room.get_session_id() {
-            match join_cloudflare_session(&payload.room_id, &payload.client_id, existing_session_id).await {
+            match provider.add_track(&payload.room_id, &payload.client_id, existing_session_id).await {
                 Ok(info) => {
-                    _session_id = info.session_id.clone();
-                    _connection_info = Some(serde_json::to_value(info).unwrap());
+                    _session_id = info.session_id;
+                    _connection_info = Some(info.connection_info);
                 }
                 Err(e) => {
-                    error!("Failed to join Cloudflare session: {}", e);
-                    return error_response(frame_id, 500, "Failed to join Cloudflare session");
+                    error!("Failed to join provider session: {}", e);
+                    release_place(client_repository, &payload.client_id).await;
+                    return error_response(frame_id, 500, "Failed to join session");
                 }
             }
         } else {
+            release_place(client_repository, &payload.client_id).await;
             return error_response(frame_id, 400, "No active session in room");
         }
     }
 
-    // Update room in database
-    if client_role == DbClientRole::Sender {
-        if let Err(e) = room_repository.set_sender_client_id(&payload.room_id, &payload.client_id).await {
-            error!("Failed to set sender client ID: {}", e);
+    if let Some(session_id) = &_session_id {
+        if let Err(e) = client_repository.set_session_id(&payload.client_id, session_id).await {
+            error!("Failed to set client session ID: {}", e);
+            release_place(client_repository, &payload.client_id).await;
             return error_response(frame_id, 500, "Database error");
         }
-    } else if let Err(e) = room_repository.set_receiver_client_id(&payload.room_id, &payload.client_id).await {
-        error!("Failed to set receiver client ID: {}", e);
-        return error_response(frame_id, 500, "Database error");
     }
 
-    // Register client in database
-    let client_payload = WebRTCClientRegistrationPayload {
-        client_id: payload.client_id.clone(),
-        room_id: payload.room_id.clone(),
-        role: client_role,
-        session_id: _session_id.clone(),
-        metadata: payload.metadata,
-    };
-
-    match client_repository.register_client(client_payload).await {
-        Ok(_) => {
-            info!("Registered WebRTC client: {} in room: {}", payload.client_id, payload.room_id);
+    // Update room in database; observers are tracked only through their client record
+    match client_role {
+        DbClientRole::Sender => {
+            if let Err(e) = room_repository.set_sender_client_id(&payload.room_id, &payload.client_id).await {
+                error!("Failed to set sender client ID: {}", e);
+                release_place(client_repository, &payload.client_id).await;
+                return error_response(frame_id, 500, "Database error");
+            }
         }
-        Err(e) => {
-            error!("Failed to register client in database: {}", e);
-            return error_response(frame_id, 500, "Failed to register client in database");
+        DbClientRole::Receiver => {
+            if let Err(e) = room_repository.set_receiver_client_id(&payload.room_id, &payload.client_id).await {
+                error!("Failed to set receiver client ID: {}", e);
+                release_place(client_repository, &payload.client_id).await;
+                return error_response(frame_id, 500, "Database error");
+            }
+        }
+        DbClientRole::Observer => {}
+    }
+
+    // Record the membership a later rejoin re-attaches to, replacing any left behind by an
+    // earlier join of this client that was not cleaned up
+    match repositories.clients_in_rooms.get_clients_in_room(&payload.room_id).await {
+        Ok(memberships) => {
+            for stale in memberships.iter().filter(|membership| membership.client_id == payload.client_id) {
+                if let Err(e) = repositories.clients_in_rooms.remove_client_from_room(&stale.id).await {
+                    warn!("Failed to remove stale membership {} of client {}: {}", stale.id, payload.client_id, e);
+                }
+            }
         }
+        Err(e) => warn!("Failed to get memberships of room {}: {}", payload.room_id, e),
+    }
+    let membership = ClientInRoom::new(payload.client_id.clone(), payload.room_id.clone(), Vec::new(), payload.metadata);
+    if let Err(e) = repositories.clients_in_rooms.create_client_in_room(membership).await {
+        error!("Failed to record room membership in database: {}", e);
+        return error_response(frame_id, 500, "Failed to register client in database");
     }
+    events::publish_in_background(event_client, EventMessage::new(events::CLIENT_JOINED_ROOM, serde_json::json!({
+        "client_id": payload.client_id,
+        "room_id": payload.room_id,
+        "role": payload.role.to_ascii_lowercase(),
+    })));
 
     // Create success response
     let response = WebRTCRoomJoinResponse {
@@ -276,32 +471,57 @@ async fn handle_room_join_internal(
         app_id: Some(get_config().cloudflare.app_id.clone()),
         stun_url: Some(get_config().cloudflare.stun_url.clone()),
         connection_info: _connection_info,
+        turn_servers: turn_servers(provider).await,
+        participants: Some(room_participants(&existing_clients, &payload.client_id)),
     };
 
     let response_json = serde_json::to_string(&response).unwrap();
     (frame_id, response_json)
 }
 
-async fn create_cloudflare_session(
-    room_id: &str,
-    client_id: &str,
-    offer_sdp: String,
-) -> Result<WebRTCConnectionInfo, Box<dyn std::error::Error + Send + Sync>> {
-    let config = get_config();
-    let session_manager = CloudflareSession::new(Arc::new(config.clone()))?;
-    
-    session_manager.create_room_with_sender(room_id, client_id, offer_sdp).await
+/// Give back the place `client_id` was admitted to when its join fails part-way
+async fn release_place(client_repository: &Arc<dyn WebRTCClientRepository + Send + Sync>, client_id: &str) {
+    if let Err(e) = client_repository.delete_client(client_id).await {
+        warn!("Failed to release room place of client {}: {}", client_id, e);
+    }
 }
 
-async fn join_cloudflare_session(
-    room_id: &str,
-    client_id: &str,
-    session_id: &str,
-) -> Result<WebRTCConnectionInfo, Box<dyn std::error::Error + Send + Sync>> {
-    let config = get_config();
-    let session_manager = CloudflareSession::new(Arc::new(config.clone()))?;
-    
-    session_manager.join_room_as_receiver(room_id, client_id, session_id).await
+/// Whether `membership` was last active no more than `grace_secs` before `now`
+fn within_rejoin_window(membership: &ClientInRoom, grace_secs: u64, now: DateTime<Utc>) -> bool {
+    // A last_activity ahead of `now` (clock skew between instances) counts as within the window
+    u64::try_from(now.signed_duration_since(membership.last_activity).num_seconds()).map_or(true, |elapsed| elapsed <= grace_secs)
+}
+
+/// Parse the requested join role, requiring an offer SDP only for senders
+pub fn validate_join_role(role: &str, has_offer_sdp: bool) -> Result<DbClientRole, &'static str> {
+    let client_role = match role.to_lowercase().as_str() {
+        "sender" => DbClientRole::Sender,
+        "receiver" => DbClientRole::Receiver,
+        "observer" => DbClientRole::Observer,
+        _ => return Err("Invalid role: must be 'sender', 'receiver' or 'observer'"),
+    };
+
+    if client_role == DbClientRole::Sender && !has_offer_sdp {
+        return Err("Offer SDP is required for sender role");
+    }
+
+    Ok(client_role)
+}
+
+/// Participants already in the room, excluding the joining client
+pub fn room_participants(clients: &[WebRTCClient], joining_client_id: &str) -> Vec<RoomParticipant> {
+    clients
+        .iter()
+        .filter(|client| client.get_client_id() != joining_client_id)
+        .map(|client| RoomParticipant {
+            client_id: client.get_client_id().to_string(),
+            role: match client.get_role() {
+                DbClientRole::Sender => "sender".to_string(),
+                DbClientRole::Receiver => "receiver".to_string(),
+                DbClientRole::Observer => "observer".to_string(),
+            },
+        })
+        .collect()
 }
 
 fn error_response(frame_id: Uuid, status: u16, message: &str) -> (Uuid, String) {
@@ -314,6 +534,8 @@ fn error_response(frame_id: Uuid, status: u16, message: &str) -> (Uuid, String)
         app_id: None,
         stun_url: None,
         connection_info: None,
+        turn_servers: None,
+        participants: None,
     };
     
     let response_json = serde_json::to_string(&response).unwrap();
@@ -3,15 +3,19 @@ use uuid::Uuid;
 use std::sync::Arc;
 use tracing::{error, info, warn};
 
-use crate::config::get_config;
 use crate::database::{
+    ClientInRoomRepository, ClientInTerminatedRoom, ClientInTerminatedRoomRepository, DatabaseResult,
     FirestoreRepositoryFactory, RepositoryFactory, WebRTCRoomRepository, WebRTCClientRepository,
 };
-use crate::cloudflare::CloudflareSession;
 use crate::config::Config;
+use crate::events::{self, EventClient, EventMessage, NoopEventClient};
+use crate::signaling::SignalingProvider;
 
 pub const CURRENT_VERSION: &str = "1.0.0";
 
+/// Termination reason of rooms closed because their last client left
+const EMPTY_ROOM_REASON: &str = "Room empty";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WebRTCRoomLeavePayload {
     pub version: String,
@@ -30,47 +34,104 @@ pub struct WebRTCRoomLeaveResponse {
     pub client_id: Option<String>,
 }
 
+/// Repositories read and updated when a client leaves a room
+#[derive(Clone)]
+pub struct RoomLeaveRepositories {
+    pub webrtc_rooms: Arc<dyn WebRTCRoomRepository + Send + Sync>,
+    pub webrtc_clients: Arc<dyn WebRTCClientRepository + Send + Sync>,
+    pub clients_in_rooms: Arc<dyn ClientInRoomRepository + Send + Sync>,
+    pub clients_in_terminated_rooms: Arc<dyn ClientInTerminatedRoomRepository + Send + Sync>,
+}
+
+impl RoomLeaveRepositories {
+    pub async fn from_factory(factory: &dyn RepositoryFactory) -> DatabaseResult<Self> {
+        Ok(Self {
+            webrtc_rooms: factory.create_webrtc_room_repository().await?,
+            webrtc_clients: factory.create_webrtc_client_repository().await?,
+            clients_in_rooms: factory.create_client_in_room_repository().await?,
+            clients_in_terminated_rooms: factory.create_client_in_terminated_room_repository().await?,
+        })
+    }
+}
+
 #[derive(Clone)]
 pub struct WebRTCRoomLeaveHandler {
     config: Arc<Config>,
+    repositories: Option<RoomLeaveRepositories>,
+    provider: Option<Arc<dyn SignalingProvider>>,
+    event_client: Arc<dyn EventClient>,
 }
 
 impl WebRTCRoomLeaveHandler {
     pub fn new(config: Arc<Config>) -> Self {
-        Self { config }
+        Self { config, repositories: None, provider: None, event_client: Arc::new(NoopEventClient) }
+    }
+
+    /// Handle leaves against `repositories` instead of the Firestore-backed ones
+    pub fn with_repositories(mut self, repositories: RoomLeaveRepositories) -> Self {
+        self.repositories = Some(repositories);
+        self
+    }
+
+    /// Close sessions on `provider` instead of the one `webrtc.provider` selects
+    pub fn with_provider(mut self, provider: Arc<dyn SignalingProvider>) -> Self {
+        self.provider = Some(provider);
+        self
     }
 
-    pub async fn handle_room_leave(&self, message: crate::message::Message) -> Result<crate::message::Message, Box<dyn std::error::Error + Send + Sync>> {
+    /// Publish leave and room termination events on `event_client` instead of dropping them
+    pub fn with_event_client(mut self, event_client: Arc<dyn EventClient>) -> Self {
+        self.event_client = event_client;
+        self
+    }
+
+    pub async fn handle_room_leave(
+        &self,
+        message: crate::message::Message,
+    ) -> Result<crate::message::Message, Box<dyn std::error::Error + Send + Sync>> {
         let frame_id = message.uuid;
         let payload = match &message.payload {
             crate::message::Payload::WebRTCRoomLeave(payload) => payload,
             _ => return Err("Invalid message type".into()),
         };
+        if let Err(e) = payload.validate().and_then(|_| message.payload.validate_ids(self.config.server.max_id_length)) {
+            warn!("[WEBRTC_ROOM_LEAVE] Rejected invalid payload: {}", e);
+            return Ok(super::invalid_payload_response(crate::message::MessageType::WebRTCRoomLeaveAck, e));
+        }
 
         // Create repositories
-        let factory = FirestoreRepositoryFactory::new(self.config.clone());
-        let room_repository = match factory.create_webrtc_room_repository().await {
-            Ok(repo) => repo,
-            Err(e) => {
-                error!("Failed to create room repository: {}", e);
-                return Err("Database connection failed".into());
+        let repositories = match &self.repositories {
+            Some(repositories) => repositories.clone(),
+            None => {
+                let factory = FirestoreRepositoryFactory::new(self.config.clone());
+                match RoomLeaveRepositories::from_factory(&factory).await {
+                    Ok(repositories) => repositories,
+                    Err(e) => {
+                        error!("Failed to create room leave repositories: {}", e);
+                        return Err("Database connection failed".into());
+                    }
+                }
             }
         };
 
-        let client_repository = match factory.create_webrtc_client_repository().await {
-            Ok(repo) => repo,
-            Err(e) => {
-                error!("Failed to create client repository: {}", e);
-                return Err("Database connection failed".into());
-            }
+        let provider = match &self.provider {
+            Some(provider) => provider.clone(),
+            None => match crate::signaling::create_provider(self.config.clone()) {
+                Ok(provider) => provider,
+                Err(e) => {
+                    error!("Failed to create signaling provider: {}", e);
+                    return Err("Signaling provider unavailable".into());
+                }
+            },
         };
 
         let raw_payload = serde_json::to_value(payload)?;
         let (_, response_json) = handle_room_leave_internal(
             frame_id, 
             raw_payload, 
-            room_repository.clone(), 
-            client_repository.clone()
+            &repositories,
+            provider.as_ref(),
+            &self.event_client,
         ).await;
         
         let response_payload: WebRTCRoomLeaveResponse = serde_json::from_str(&response_json)?;
@@ -94,8 +155,10 @@ impl WebRTCRoomLeaveHandler {
             })
         } else {
             crate::message::Payload::Error(crate::message::ErrorPayload {
-                error_code: response_payload.status as u8,
+                error_code: crate::message::ErrorPayload::code_for_status(response_payload.status),
                 error_message: response_payload.message.unwrap_or_else(|| "Unknown error".to_string()),
+                status: Some(response_payload.status),
+                ..Default::default()
             })
         };
 
@@ -109,9 +172,12 @@ impl WebRTCRoomLeaveHandler {
 async fn handle_room_leave_internal(
     frame_id: Uuid, 
     raw_payload: serde_json::Value,
-    room_repository: Arc<dyn WebRTCRoomRepository + Send + Sync>,
-    client_repository: Arc<dyn WebRTCClientRepository + Send + Sync>,
+    repositories: &RoomLeaveRepositories,
+    provider: &dyn SignalingProvider,
+    event_client: &Arc<dyn EventClient>,
 ) -> (Uuid, String) {
+    let room_repository = &repositories.webrtc_rooms;
+    let client_repository = &repositories.webrtc_clients;
     // Validate and parse JSON payload
     let version = raw_payload.get("version");
     let client_id = raw_payload.get("client_id");
@@ -170,15 +236,15 @@ async fn handle_room_leave_internal(
         return error_response(frame_id, 400, "Client is not in the specified room");
     }
 
-    // Terminate Cloudflare session if client has one
+    // Close the provider session if client has one
     if let Some(session_id) = client.get_session_id() {
-        match terminate_cloudflare_session(session_id, &payload.room_id).await {
+        match provider.close_session(session_id, &payload.room_id).await {
             Ok(_) => {
-                info!("Terminated Cloudflare session: {} for room: {}", session_id, payload.room_id);
+                info!("Closed provider session: {} for room: {}", session_id, payload.room_id);
             }
             Err(e) => {
-                warn!("Failed to terminate Cloudflare session: {}", e);
-                // Continue with cleanup even if Cloudflare termination fails
+                warn!("Failed to close provider session: {}", e);
+                // Continue with cleanup even if closing the session fails
             }
         }
     }
@@ -187,6 +253,32 @@ async fn handle_room_leave_internal(
     match client_repository.remove_client_from_room(&payload.client_id, &payload.room_id).await {
         Ok(_) => {
             info!("Removed client: {} from room: {}", payload.client_id, payload.room_id);
+            // Drop the membership too, so a later join starts afresh and listings stop counting the client
+            match repositories.clients_in_rooms.get_clients_in_room(&payload.room_id).await {
+                Ok(memberships) => {
+                    for membership in memberships.iter().filter(|membership| membership.client_id == payload.client_id) {
+                        if let Err(e) = repositories.clients_in_rooms.remove_client_from_room(&membership.id).await {
+                            warn!("Failed to remove membership {} of client: {} in room: {}: {}", membership.id, payload.client_id, payload.room_id, e);
+                        }
+                    }
+                }
+                Err(e) => warn!("Failed to get memberships of room: {}: {}", payload.room_id, e),
+            }
+            let record = ClientInTerminatedRoom::voluntary_leave(
+                payload.client_id.clone(),
+                payload.room_id.clone(),
+                client.joined_at,
+                payload.reason.clone(),
+            );
+            events::publish_in_background(event_client, EventMessage::new(events::CLIENT_LEFT_ROOM, serde_json::json!({
+                "client_id": payload.client_id,
+                "room_id": payload.room_id,
+                "reason": record.termination_reason,
+            })));
+            if let Err(e) = repositories.clients_in_terminated_rooms.create_client_in_terminated_room(record).await {
+                // The client has left either way; only the history record is missing
+                warn!("Failed to record leave of client: {} from room: {}: {}", payload.client_id, payload.room_id, e);
+            }
         }
         Err(e) => {
             error!("Failed to remove client from room: {}", e);
@@ -205,9 +297,13 @@ async fn handle_room_leave_internal(
 
     if remaining_clients.is_empty() {
         // Terminate the room
-        match room_repository.terminate_room(&payload.room_id, "Room empty").await {
+        match room_repository.terminate_room(&payload.room_id, EMPTY_ROOM_REASON).await {
             Ok(_) => {
                 info!("Terminated empty room: {}", payload.room_id);
+                events::publish_in_background(event_client, EventMessage::new(events::ROOM_TERMINATED, serde_json::json!({
+                    "room_id": payload.room_id,
+                    "reason": EMPTY_ROOM_REASON,
+                })));
             }
             Err(e) => {
                 error!("Failed to terminate room: {}", e);
@@ -229,16 +325,6 @@ async fn handle_room_leave_internal(
     (frame_id, response_json)
 }
 
-async fn terminate_cloudflare_session(
-    session_id: &str,
-    room_id: &str,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let config = get_config();
-    let session_manager = CloudflareSession::new(Arc::new(config.clone()))?;
-    
-    session_manager.terminate_session(session_id, room_id).await
-}
-
 fn error_response(frame_id: Uuid, status: u16, message: &str) -> (Uuid, String) {
     let response = WebRTCRoomLeaveResponse {
         version: CURRENT_VERSION.to_string(),
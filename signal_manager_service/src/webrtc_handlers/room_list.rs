@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::database::{
+    ClientInRoomRepository, DatabaseResult, FirestoreRepositoryFactory, RepositoryFactory, WebRTCRoomRepository,
+    WebRTCRoomStatus,
+};
+use crate::message::{Message, MessageType, Payload, RoomSummary, WebRTCRoomListAckPayload, WebRTCRoomListPayload};
+
+/// Rooms per page when a request leaves `limit` at 0
+pub const DEFAULT_ROOM_LIST_LIMIT: u32 = 50;
+/// Largest page a single request can ask for
+pub const MAX_ROOM_LIST_LIMIT: u32 = 200;
+
+/// Repositories read when an operator lists rooms
+#[derive(Clone)]
+pub struct RoomListRepositories {
+    pub webrtc_rooms: Arc<dyn WebRTCRoomRepository + Send + Sync>,
+    pub clients_in_rooms: Arc<dyn ClientInRoomRepository + Send + Sync>,
+}
+
+impl RoomListRepositories {
+    pub async fn from_factory(factory: &dyn RepositoryFactory) -> DatabaseResult<Self> {
+        Ok(Self {
+            webrtc_rooms: factory.create_webrtc_room_repository().await?,
+            clients_in_rooms: factory.create_client_in_room_repository().await?,
+        })
+    }
+}
+
+/// The page of active rooms, oldest first, selected by `request.offset` and `request.limit`,
+/// each with the number of clients active in it
+pub async fn list_active_rooms(repositories: &RoomListRepositories, request: &WebRTCRoomListPayload) -> DatabaseResult<WebRTCRoomListAckPayload> {
+    let mut rooms = repositories.webrtc_rooms.get_active_rooms().await?;
+    rooms.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.room_id.cmp(&b.room_id)));
+
+    let total = rooms.len();
+    let offset = (request.offset as usize).min(total);
+    let limit = match request.limit {
+        0 => DEFAULT_ROOM_LIST_LIMIT,
+        limit => limit.min(MAX_ROOM_LIST_LIMIT),
+    };
+
+    let mut summaries = Vec::new();
+    for room in rooms.into_iter().skip(offset).take(limit as usize) {
+        let participant_count = repositories.clients_in_rooms.get_active_clients_in_room(&room.room_id).await?.len();
+        summaries.push(RoomSummary {
+            status: status_name(&room.status).to_string(),
+            participant_count: participant_count as u32,
+            created_at: room.created_at.timestamp_millis().max(0) as u64,
+            room_id: room.room_id,
+            sender_client_id: room.sender_client_id,
+            receiver_client_id: room.receiver_client_id,
+        });
+    }
+
+    let end = offset + summaries.len();
+    Ok(WebRTCRoomListAckPayload {
+        rooms: summaries,
+        total: total as u32,
+        next_offset: (end < total).then_some(end as u32),
+    })
+}
+
+fn status_name(status: &WebRTCRoomStatus) -> &'static str {
+    match status {
+        WebRTCRoomStatus::Active => "active",
+        WebRTCRoomStatus::Inactive => "inactive",
+        WebRTCRoomStatus::Terminated => "terminated",
+        WebRTCRoomStatus::Pending => "pending",
+    }
+}
+
+#[derive(Clone)]
+pub struct WebRTCRoomListHandler {
+    config: Arc<Config>,
+    repositories: Option<RoomListRepositories>,
+}
+
+impl WebRTCRoomListHandler {
+    pub fn new(config: Arc<Config>) -> Self {
+        Self { config, repositories: None }
+    }
+
+    /// List rooms from `repositories` instead of the Firestore-backed ones
+    pub fn with_repositories(mut self, repositories: RoomListRepositories) -> Self {
+        self.repositories = Some(repositories);
+        self
+    }
+
+    /// Whether `client_id` is one of the `security.admin_clients` allowed to list rooms
+    pub fn is_admin(&self, client_id: &str) -> bool {
+        self.config.security.admin_clients.iter().any(|admin| admin == client_id)
+    }
+
+    pub async fn handle_room_list(&self, request: &WebRTCRoomListPayload) -> DatabaseResult<Message> {
+        let page = match &self.repositories {
+            Some(repositories) => list_active_rooms(repositories, request).await?,
+            None => {
+                let factory = FirestoreRepositoryFactory::new(self.config.clone());
+                list_active_rooms(&RoomListRepositories::from_factory(&factory).await?, request).await?
+            }
+        };
+        Ok(Message::new(MessageType::WebRTCRoomListAck, Payload::WebRTCRoomListAck(page)))
+    }
+}
@@ -0,0 +1,85 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::database::{
+    ClientInRoomRepository, ClientRole as DbClientRole, DatabaseResult, FirestoreRepositoryFactory, RepositoryFactory,
+    WebRTCClientRepository, WebRTCClientStatus,
+};
+use crate::message::{ClientRoom, Message, MessageType, Payload, WhereAmIAckPayload};
+
+/// Repositories consulted when a client asks which rooms it is in
+#[derive(Clone)]
+pub struct WhereAmIRepositories {
+    pub clients_in_rooms: Arc<dyn ClientInRoomRepository + Send + Sync>,
+    pub webrtc_clients: Arc<dyn WebRTCClientRepository + Send + Sync>,
+}
+
+impl WhereAmIRepositories {
+    pub async fn from_factory(factory: &dyn RepositoryFactory) -> DatabaseResult<Self> {
+        Ok(Self {
+            clients_in_rooms: factory.create_client_in_room_repository().await?,
+            webrtc_clients: factory.create_webrtc_client_repository().await?,
+        })
+    }
+}
+
+/// Rooms `client_id` is currently in, ordered by room id. Memberships come from the
+/// client-in-room records; the role is taken from the client's WebRTC record for the
+/// room it joined, which also counts as a membership while it is not disconnected.
+pub async fn current_rooms(repositories: &WhereAmIRepositories, client_id: &str) -> DatabaseResult<Vec<ClientRoom>> {
+    let mut rooms: BTreeMap<String, Option<String>> = repositories.clients_in_rooms
+        .get_rooms_for_client(client_id)
+        .await?
+        .into_iter()
+        .map(|membership| (membership.room_id, None))
+        .collect();
+
+    if let Some(client) = repositories.webrtc_clients.get_client_by_id(client_id).await? {
+        if !client.room_id.is_empty() && client.status != WebRTCClientStatus::Disconnected {
+            let role = match client.role {
+                DbClientRole::Sender => "sender",
+                DbClientRole::Receiver => "receiver",
+                DbClientRole::Observer => "observer",
+            };
+            rooms.insert(client.room_id, Some(role.to_string()));
+        }
+    }
+
+    Ok(rooms.into_iter().map(|(room_id, role)| ClientRoom { room_id, role }).collect())
+}
+
+#[derive(Clone)]
+pub struct WhereAmIHandler {
+    config: Arc<Config>,
+    repositories: Option<WhereAmIRepositories>,
+}
+
+impl WhereAmIHandler {
+    pub fn new(config: Arc<Config>) -> Self {
+        Self { config, repositories: None }
+    }
+
+    /// Answer queries from `repositories` instead of the Firestore-backed ones
+    pub fn with_repositories(mut self, repositories: WhereAmIRepositories) -> Self {
+        self.repositories = Some(repositories);
+        self
+    }
+
+    pub async fn handle_where_am_i(&self, client_id: &str) -> DatabaseResult<Message> {
+        let rooms = match &self.repositories {
+            Some(repositories) => current_rooms(repositories, client_id).await?,
+            None => {
+                let factory = FirestoreRepositoryFactory::new(self.config.clone());
+                current_rooms(&WhereAmIRepositories::from_factory(&factory).await?, client_id).await?
+            }
+        };
+        Ok(Message::new(
+            MessageType::WhereAmIAck,
+            Payload::WhereAmIAck(WhereAmIAckPayload {
+                client_id: client_id.to_string(),
+                rooms,
+            }),
+        ))
+    }
+}
@@ -1,6 +1,11 @@
+use jsonwebtoken::{EncodingKey, Header};
 use signal_manager_service::auth::AuthManager;
-use signal_manager_service::config::Config;
+use signal_manager_service::config::{Config, JwtAlgorithm, SecondaryApiKey};
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const RS256_PRIVATE_KEY: &[u8] = include_bytes!("../fixtures/jwt/rs256_private.pem");
+const RS256_PUBLIC_KEY_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/jwt/rs256_public.pem");
 
 #[test]
 fn test_auth_manager() {
@@ -8,4 +13,122 @@ fn test_auth_manager() {
     let _auth_manager = AuthManager::new(Arc::new(config));
     // Test should pass since we're not actually calling async functions
     // In a real test, we'd use tokio::test or similar
-} 
\ No newline at end of file
+}
+
+fn jwt_config(algorithm: JwtAlgorithm) -> Config {
+    let mut config = Config::default();
+    config.auth.auth_method = "jwt".to_string();
+    config.auth.token_secret = "jwt-test-secret".to_string();
+    config.auth.jwt_algorithm = algorithm;
+    config.auth.jwt_public_key_path = Some(RS256_PUBLIC_KEY_PATH.to_string());
+    config
+}
+
+/// Sign a token for `sub` expiring `expires_in` seconds from now (negative for the past)
+fn sign(algorithm: JwtAlgorithm, sub: &str, expires_in: i64) -> String {
+    let key = match algorithm {
+        JwtAlgorithm::Hs256 => EncodingKey::from_secret(b"jwt-test-secret"),
+        JwtAlgorithm::Rs256 => EncodingKey::from_rsa_pem(RS256_PRIVATE_KEY).unwrap(),
+    };
+    sign_with(algorithm, &key, sub, expires_in)
+}
+
+fn sign_with(algorithm: JwtAlgorithm, key: &EncodingKey, sub: &str, expires_in: i64) -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+    let claims = serde_json::json!({ "sub": sub, "iat": now, "exp": now + expires_in });
+    let header = match algorithm {
+        JwtAlgorithm::Hs256 => Header::new(jsonwebtoken::Algorithm::HS256),
+        JwtAlgorithm::Rs256 => Header::new(jsonwebtoken::Algorithm::RS256),
+    };
+    jsonwebtoken::encode(&header, &claims, key).unwrap()
+}
+
+#[tokio::test]
+async fn test_jwt_auth_accepts_valid_token() {
+    for algorithm in [JwtAlgorithm::Hs256, JwtAlgorithm::Rs256] {
+        let auth_manager = AuthManager::new(Arc::new(jwt_config(algorithm)));
+        let token = sign(algorithm, "jwt_client", 600);
+        assert!(auth_manager.authenticate("jwt_client", &token).await.unwrap(), "{algorithm:?} token rejected");
+        // The token only authenticates the client named in its sub claim
+        assert!(!auth_manager.authenticate("other_client", &token).await.unwrap());
+    }
+}
+
+#[tokio::test]
+async fn test_jwt_auth_rejects_expired_token() {
+    for algorithm in [JwtAlgorithm::Hs256, JwtAlgorithm::Rs256] {
+        let auth_manager = AuthManager::new(Arc::new(jwt_config(algorithm)));
+        let expired = sign(algorithm, "jwt_client", -3600);
+        assert!(!auth_manager.authenticate("jwt_client", &expired).await.unwrap());
+
+        // Tokens outliving auth.token_expiry are refused as well
+        let long_lived = sign(algorithm, "jwt_client", 7200);
+        assert!(!auth_manager.authenticate("jwt_client", &long_lived).await.unwrap());
+    }
+}
+
+#[tokio::test]
+async fn test_jwt_auth_rejects_tampered_token() {
+    use base64::Engine;
+    let engine = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+
+    for algorithm in [JwtAlgorithm::Hs256, JwtAlgorithm::Rs256] {
+        let auth_manager = AuthManager::new(Arc::new(jwt_config(algorithm)));
+        let token = sign(algorithm, "jwt_client", 600);
+        let parts: Vec<&str> = token.split('.').collect();
+
+        // Swap the subject without re-signing
+        let mut claims: serde_json::Value = serde_json::from_slice(&engine.decode(parts[1]).unwrap()).unwrap();
+        claims["sub"] = "intruder".into();
+        let forged = format!("{}.{}.{}", parts[0], engine.encode(claims.to_string()), parts[2]);
+        assert!(!auth_manager.authenticate("intruder", &forged).await.unwrap());
+
+        // A signature that isn't from the configured key is refused too
+        let foreign = match algorithm {
+            JwtAlgorithm::Hs256 => sign_with(algorithm, &EncodingKey::from_secret(b"another-secret"), "jwt_client", 600),
+            JwtAlgorithm::Rs256 => format!("{}.{}.{}", parts[0], parts[1], engine.encode([0u8; 256])),
+        };
+        assert!(!auth_manager.authenticate("jwt_client", &foreign).await.unwrap());
+    }
+}
+
+#[tokio::test]
+async fn test_jwt_auth_without_public_key_rejects_every_token() {
+    let mut config = jwt_config(JwtAlgorithm::Rs256);
+    config.auth.jwt_public_key_path = None;
+    let auth_manager = AuthManager::new(Arc::new(config));
+    let token = sign(JwtAlgorithm::Rs256, "jwt_client", 600);
+    assert!(!auth_manager.authenticate("jwt_client", &token).await.unwrap());
+}
+
+fn rotating_config(valid_until: Option<chrono::DateTime<chrono::Utc>>) -> Config {
+    let mut config = Config::default();
+    config.auth.api_keys = vec!["rotating_client:new_token".to_string(), "other_client:other_token".to_string()];
+    config.auth.secondary_api_keys = vec![SecondaryApiKey {
+        client_id: "rotating_client".to_string(),
+        token: "old_token".to_string(),
+        valid_until,
+    }];
+    config
+}
+
+#[tokio::test]
+async fn test_primary_and_secondary_api_keys_both_authenticate() {
+    for valid_until in [None, Some(chrono::Utc::now() + chrono::Duration::hours(1))] {
+        let auth_manager = AuthManager::new(Arc::new(rotating_config(valid_until)));
+        assert!(auth_manager.authenticate("rotating_client", "new_token").await.unwrap());
+        assert!(auth_manager.authenticate("rotating_client", "old_token").await.unwrap());
+
+        // A secondary key belongs to its client only
+        assert!(!auth_manager.authenticate("other_client", "old_token").await.unwrap());
+        assert!(!auth_manager.authenticate("rotating_client", "other_token").await.unwrap());
+    }
+}
+
+#[tokio::test]
+async fn test_expired_secondary_api_key_is_rejected() {
+    let expired = chrono::Utc::now() - chrono::Duration::minutes(1);
+    let auth_manager = AuthManager::new(Arc::new(rotating_config(Some(expired))));
+    assert!(!auth_manager.authenticate("rotating_client", "old_token").await.unwrap());
+    assert!(auth_manager.authenticate("rotating_client", "new_token").await.unwrap());
+}
@@ -0,0 +1,53 @@
+use signal_manager_service::backoff::BackoffConfig;
+use std::time::Duration;
+
+fn config(jitter: f64) -> BackoffConfig {
+    BackoffConfig {
+        base_ms: 100,
+        max_ms: 1_000,
+        multiplier: 2.0,
+        jitter,
+        max_retries: 6,
+    }
+}
+
+#[test]
+fn test_backoff_sequence_without_jitter() {
+    let delays: Vec<Duration> = config(0.0).delays().collect();
+    let expected: Vec<Duration> = [100, 200, 400, 800, 1_000, 1_000].into_iter().map(Duration::from_millis).collect();
+    assert_eq!(delays, expected);
+}
+
+#[test]
+fn test_backoff_jitter_bounds() {
+    let backoff = config(0.5);
+    // The sample selects a point within +/- jitter of the nominal delay
+    assert_eq!(backoff.delay(1, 0.0), Duration::from_millis(100));
+    assert_eq!(backoff.delay(1, 0.5), Duration::from_millis(200));
+    assert_eq!(backoff.delay(1, 0.999_999), Duration::from_millis(299));
+
+    for _ in 0..50 {
+        for (attempt, delay) in backoff.delays().enumerate() {
+            let nominal = (100u64 << attempt).min(1_000) as f64;
+            let millis = delay.as_millis() as f64;
+            assert!(millis >= (nominal * 0.5).floor() && millis <= (nominal * 1.5).min(1_000.0),
+                "attempt {attempt}: {millis}ms outside jitter bounds of {nominal}ms");
+        }
+    }
+}
+
+#[test]
+fn test_backoff_never_exceeds_max() {
+    let backoff = config(1.0);
+    assert_eq!(backoff.delay(3, 0.999), Duration::from_millis(1_000));
+    assert_eq!(backoff.delay(u32::MAX, 0.999), Duration::from_millis(1_000));
+    assert!(backoff.delays().all(|delay| delay <= Duration::from_millis(1_000)));
+}
+
+#[test]
+fn test_backoff_retry_count() {
+    assert_eq!(config(0.2).delays().count(), 6);
+
+    let disabled = BackoffConfig { max_retries: 0, ..BackoffConfig::default() };
+    assert_eq!(disabled.delays().next(), None);
+}
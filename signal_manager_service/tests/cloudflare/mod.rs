@@ -0,0 +1,160 @@
+use crate::support::mock_http::MockHttpServer;
+use serde_json::Value;
+use signal_manager_service::cloudflare::{CloudflareClient, CloudflareClientTrait, RetriesExhausted};
+use signal_manager_service::config::Config;
+use std::sync::Arc;
+
+/// Config pointing the Cloudflare client at `base_url` with a TURN key configured
+fn turn_config(base_url: String, ttl_secs: u64, refresh_margin_secs: u64) -> Config {
+    let mut config = Config::default();
+    config.cloudflare.base_url = base_url;
+    config.cloudflare.turn.key_id = "turn-key-1".to_string();
+    config.cloudflare.turn.api_token = "turn-api-token".to_string();
+    config.cloudflare.turn.credential_ttl_secs = ttl_secs;
+    config.cloudflare.turn.refresh_margin_secs = refresh_margin_secs;
+    config
+}
+
+const TURN_RESPONSE: &str = r#"{"iceServers":{"urls":["stun:stun.cloudflare.com:3478","turn:turn.cloudflare.com:3478?transport=udp","turns:turn.cloudflare.com:5349?transport=tcp"],"username":"turn-user","credential":"turn-secret"}}"#;
+
+#[tokio::test]
+async fn test_generate_turn_credentials_returns_ice_servers() {
+    let server = MockHttpServer::start().await;
+    server.enqueue_response(201, TURN_RESPONSE);
+    let client = CloudflareClient::new(Arc::new(turn_config(server.url(), 3600, 60))).unwrap();
+
+    let credentials = client.generate_turn_credentials().await.unwrap();
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method, "POST");
+    assert_eq!(requests[0].path, "/turn/keys/turn-key-1/credentials/generate");
+    assert_eq!(requests[0].header("authorization"), Some("Bearer turn-api-token"));
+    let body: Value = serde_json::from_str(&requests[0].body).unwrap();
+    assert_eq!(body["ttl"], 3600);
+
+    // One RTCIceServer entry carrying every URL and the shared credentials
+    assert!(credentials.expires_at > chrono::Utc::now() + chrono::Duration::seconds(3500));
+    assert_eq!(serde_json::to_value(&credentials.ice_servers).unwrap(), serde_json::json!([{
+        "urls": [
+            "stun:stun.cloudflare.com:3478",
+            "turn:turn.cloudflare.com:3478?transport=udp",
+            "turns:turn.cloudflare.com:5349?transport=tcp",
+        ],
+        "username": "turn-user",
+        "credential": "turn-secret",
+    }]));
+}
+
+#[tokio::test]
+async fn test_turn_credentials_cached_until_near_expiry() {
+    let server = MockHttpServer::start().await;
+    server.enqueue_response(201, TURN_RESPONSE);
+    let client = CloudflareClient::new(Arc::new(turn_config(server.url(), 3600, 60))).unwrap();
+    let first = client.generate_turn_credentials().await.unwrap();
+    let second = client.generate_turn_credentials().await.unwrap();
+    assert_eq!(first, second);
+    assert_eq!(server.requests().len(), 1);
+
+    // Credentials already inside the refresh margin are minted again on every call
+    let server = MockHttpServer::start().await;
+    server.enqueue_response(201, TURN_RESPONSE);
+    server.enqueue_response(201, TURN_RESPONSE);
+    let client = CloudflareClient::new(Arc::new(turn_config(server.url(), 60, 60))).unwrap();
+    client.generate_turn_credentials().await.unwrap();
+    client.generate_turn_credentials().await.unwrap();
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn test_turn_credentials_served_from_cache_while_refreshing() {
+    let server = MockHttpServer::start().await;
+    server.enqueue_response(201, TURN_RESPONSE);
+    server.enqueue_response(201, TURN_RESPONSE);
+    // Minted credentials are always inside the refresh margin, but valid for another minute
+    let client = Arc::new(CloudflareClient::new(Arc::new(turn_config(server.url(), 60, 60))).unwrap());
+    let cached = client.generate_turn_credentials().await.unwrap();
+
+    server.delay_responses(std::time::Duration::from_secs(2));
+    let refreshing = tokio::spawn({
+        let client = client.clone();
+        async move { client.generate_turn_credentials().await.unwrap() }
+    });
+    while server.requests().len() < 2 {
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+
+    // A caller arriving mid-refresh gets the cached credentials without waiting for the request
+    let served = tokio::time::timeout(std::time::Duration::from_millis(500), client.generate_turn_credentials())
+        .await
+        .expect("Cached credentials were not served while refreshing")
+        .unwrap();
+    assert_eq!(served, cached);
+    assert!(refreshing.await.unwrap().expires_at >= cached.expires_at);
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn test_turn_credentials_api_error() {
+    let server = MockHttpServer::start().await;
+    server.enqueue_response(403, r#"{"errors":[{"message":"Forbidden"}]}"#);
+    let client = CloudflareClient::new(Arc::new(turn_config(server.url(), 3600, 60))).unwrap();
+
+    let error = client.generate_turn_credentials().await.unwrap_err();
+    assert!(error.to_string().contains("Forbidden"), "Unexpected error: {error}");
+}
+
+/// Config pointing the Cloudflare client at `base_url`, retrying `max_retries` times without delay
+fn retry_config(base_url: String, max_retries: u32) -> Config {
+    let mut config = Config::default();
+    config.cloudflare.base_url = base_url;
+    config.cloudflare.retry.base_ms = 1;
+    config.cloudflare.retry.jitter = 0.0;
+    config.cloudflare.retry.max_retries = max_retries;
+    config
+}
+
+const SESSION_RESPONSE: &str = r#"{"sessionId":"session_1","sessionDescription":{"type":"answer","sdp":"v=0 answer"}}"#;
+
+#[tokio::test]
+async fn test_create_session_retries_unavailable_then_succeeds() {
+    let server = MockHttpServer::start().await;
+    server.enqueue_response(503, "Service Unavailable");
+    server.enqueue_response(503, "Service Unavailable");
+    server.enqueue_response(201, SESSION_RESPONSE);
+    let client = CloudflareClient::new(Arc::new(retry_config(server.url(), 3))).unwrap();
+
+    let session = client.create_session("v=0 offer".to_string()).await.unwrap();
+    assert_eq!(session.session_id, "session_1");
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 3);
+    assert!(requests.iter().all(|request| request.method == "POST" && request.path.ends_with("/sessions/new")));
+}
+
+#[tokio::test]
+async fn test_create_session_does_not_retry_internal_error() {
+    // A 500 may come after Cloudflare created the session, so the POST is not repeated
+    let server = MockHttpServer::start().await;
+    server.enqueue_response(500, "Internal Server Error");
+    let client = CloudflareClient::new(Arc::new(retry_config(server.url(), 3))).unwrap();
+
+    let error = client.create_session("v=0 offer".to_string()).await.unwrap_err();
+    assert!(!error.is::<RetriesExhausted>());
+    assert!(error.to_string().contains("Internal Server Error"), "Unexpected error: {error}");
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_retries_exhausted_error() {
+    let server = MockHttpServer::start().await;
+    for _ in 0..3 {
+        server.enqueue_response(502, "Bad Gateway");
+    }
+    let client = CloudflareClient::new(Arc::new(retry_config(server.url(), 2))).unwrap();
+
+    let error = client.get_session("session_1").await.unwrap_err();
+    let exhausted = error.downcast_ref::<RetriesExhausted>().expect("RetriesExhausted error");
+    assert_eq!(exhausted.attempts, 3);
+    assert!(exhausted.last_error.contains("502"), "Unexpected error: {}", exhausted.last_error);
+    assert_eq!(server.requests().len(), 3);
+}
@@ -19,6 +19,7 @@ mock! {
         async fn terminate_session(&self, session_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
         async fn get_session(&self, session_id: &str) -> Result<Value, Box<dyn std::error::Error + Send + Sync>>;
         async fn validate_credentials(&self) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+        async fn generate_turn_credentials(&self) -> Result<TurnCredentials, Box<dyn std::error::Error + Send + Sync>>;
     }
 }
 
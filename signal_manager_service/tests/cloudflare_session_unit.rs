@@ -18,6 +18,7 @@ mock! {
         async fn terminate_session(&self, session_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
         async fn get_session(&self, session_id: &str) -> Result<Value, Box<dyn std::error::Error + Send + Sync>>;
         async fn validate_credentials(&self) -> Result<bool, Box<dyn std::error::Error + Send + Sync>>;
+        async fn generate_turn_credentials(&self) -> Result<TurnCredentials, Box<dyn std::error::Error + Send + Sync>>;
     }
 }
 
@@ -384,4 +385,31 @@ fn test_create_connection_info() {
     assert_eq!(connection_info.session_id, Some("test_session".to_string()));
     assert_eq!(connection_info.status, ConnectionStatus::Disconnected);
     assert_eq!(connection_info.app_id, "your-cloudflare-app-id");
-} 
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_session_ice_servers_require_turn_key() {
+    // Without a TURN key the client is never asked for credentials
+    let session = CloudflareSession::new_with_client(Arc::new(Config::default()), Box::new(MockMockCloudflareClient::new())).unwrap();
+    assert!(session.ice_servers().await.unwrap().is_empty());
+
+    let mut mock_client = MockMockCloudflareClient::new();
+    mock_client
+        .expect_generate_turn_credentials()
+        .times(1)
+        .returning(|| Ok(TurnCredentials {
+            ice_servers: vec![IceServer {
+                urls: vec!["turn:turn.cloudflare.com:3478?transport=udp".to_string()],
+                username: Some("turn-user".to_string()),
+                credential: Some("turn-secret".to_string()),
+            }],
+            expires_at: chrono::Utc::now() + chrono::Duration::hours(1),
+        }));
+    let mut config = Config::default();
+    config.cloudflare.turn.key_id = "turn-key-1".to_string();
+    let config = Arc::new(config);
+    let session = CloudflareSession::new_with_client(config, Box::new(mock_client)).unwrap();
+    let servers = session.ice_servers().await.unwrap();
+    assert_eq!(servers.len(), 1);
+    assert_eq!(servers[0].username.as_deref(), Some("turn-user"));
+}
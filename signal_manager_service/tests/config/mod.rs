@@ -9,10 +9,18 @@ fn test_config_default() {
     assert_eq!(config.server.port, 8080);
     assert_eq!(config.server.max_connections, 1000);
     assert_eq!(config.server.heartbeat_interval, 30);
+    assert_eq!(config.server.heartbeat_timeout_multiplier, 2);
     assert_eq!(config.server.tls_enabled, false);
     assert_eq!(config.server.read_buffer_size, 8192);
     assert_eq!(config.server.write_buffer_size, 8192);
     assert_eq!(config.server.max_message_size, 1048576);
+    assert_eq!(config.server.max_frame_size, 1048576);
+    assert_eq!(config.server.outbound_queue_depth, 100);
+    assert_eq!(config.server.outbound_overflow_policy, signal_manager_service::config::OverflowPolicy::DropNewest);
+    assert_eq!(config.server.duplicate_connect_policy, signal_manager_service::config::DuplicateConnectPolicy::Reject);
+    assert_eq!(config.server.max_clock_skew_ms, 30000);
+    assert_eq!(config.server.tls_handshake_timeout_secs, 10);
+    assert_eq!(config.server.max_id_length, 128);
     
 
     
@@ -39,6 +47,7 @@ fn test_config_default() {
     assert_eq!(config.session.session_timeout, 3600);
     assert_eq!(config.session.cleanup_interval, 300);
     assert_eq!(config.session.max_sessions_per_client, 1);
+    assert_eq!(config.session.session_limit_policy, signal_manager_service::config::SessionLimitPolicy::EvictOldest);
     
     // Test security config
     assert_eq!(config.security.rate_limit_enabled, true);
@@ -87,6 +96,21 @@ fn test_config_parse_api_keys() {
     assert_eq!(keys.get("test_client_2"), Some(&"test_token_2".to_string()));
 }
 
+#[test]
+fn test_config_parse_client_tokens_includes_secondary_keys() {
+    let mut config = Config::default();
+    config.auth.secondary_api_keys = vec![
+        serde_json::from_str(r#"{"client_id":"test_client_1","token":"old_token_1","valid_until":"2030-01-01T00:00:00Z"}"#).unwrap(),
+        serde_json::from_str(r#"{"client_id":"test_client_3","token":"token_3"}"#).unwrap(),
+    ];
+    let tokens = config.parse_client_tokens();
+
+    let client_1: Vec<_> = tokens["test_client_1"].iter().map(|t| (t.token.as_str(), t.valid_until.map(|v| v.to_rfc3339()))).collect();
+    assert_eq!(client_1, vec![("test_token_1", None), ("old_token_1", Some("2030-01-01T00:00:00+00:00".to_string()))]);
+    assert_eq!(tokens["test_client_3"].len(), 1);
+    assert_eq!(tokens["test_client_3"][0].valid_until, None);
+}
+
 #[test]
 fn test_global_config_access() {
     // Initialize config
@@ -209,4 +233,300 @@ fn test_metrics_config() {
     assert_eq!(config.metrics.host, "0.0.0.0");
     assert_eq!(config.metrics.connection_stats_interval, 120);
     assert_eq!(config.metrics.message_stats_interval, 60);
-} 
\ No newline at end of file
+}
+
+#[test]
+fn test_signaling_provider_selection() {
+    use signal_manager_service::config::SignalingProviderKind;
+    use std::sync::Arc;
+
+    assert_eq!(Config::default().webrtc.provider, SignalingProviderKind::Cloudflare);
+    assert_eq!(Config::load("app-config.toml").unwrap().webrtc.provider, SignalingProviderKind::Cloudflare);
+
+    assert_eq!(serde_json::from_str::<SignalingProviderKind>("\"cloudflare\"").unwrap(), SignalingProviderKind::Cloudflare);
+    assert!(serde_json::from_str::<SignalingProviderKind>("\"janus\"").is_err());
+
+    assert!(signal_manager_service::signaling::create_provider(Arc::new(Config::default())).is_ok());
+}
+
+/// Write `contents` to a uniquely named TOML file, returning its path
+fn write_config_file(contents: &str) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("sms-config-{}.toml", uuid::Uuid::new_v4()));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn test_environment_overrides_file_values_for_nested_fields() {
+    let path = write_config_file(r#"
+[server]
+host = "10.0.0.1"
+port = 7000
+
+[metrics]
+enabled = false
+port = 9999
+
+[events.publish_retry]
+max_retries = 1
+"#);
+    let environment = Config::environment().source(Some(
+        [
+            ("SMS_SERVER__PORT", "9000"),
+            ("SMS_METRICS__ENABLED", "true"),
+            ("SMS_CLOUDFLARE__APP_SECRET", "secret-from-env"),
+            ("SMS_EVENTS__PUBLISH_RETRY__MAX_RETRIES", "7"),
+            ("OTHER_SERVER__PORT", "1"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect(),
+    ));
+    let config = Config::load_with_environment(path.to_str().unwrap(), environment).unwrap();
+    std::fs::remove_file(&path).ok();
+
+    // Environment beats the file
+    assert_eq!(config.server.port, 9000);
+    assert!(config.metrics.enabled);
+    assert_eq!(config.cloudflare.app_secret, "secret-from-env");
+    assert_eq!(config.events.publish_retry.max_retries, 7);
+    // The file beats the defaults where the environment is silent
+    assert_eq!(config.server.host, "10.0.0.1");
+    assert_eq!(config.metrics.port, 9999);
+}
+
+#[test]
+fn test_environment_overrides_defaults_without_a_file() {
+    let environment = Config::environment().source(Some(
+        [("SMS_WEBRTC__REJOIN_GRACE_SECS".to_string(), "5".to_string())].into_iter().collect(),
+    ));
+    let config = Config::load_with_environment("/nonexistent/sms-config", environment).unwrap();
+
+    assert_eq!(config.webrtc.rejoin_grace_secs, 5);
+    let defaults = Config::default();
+    assert_eq!(config.session.session_timeout, defaults.session.session_timeout);
+    assert_eq!(config.security.required_capabilities, defaults.security.required_capabilities);
+}
+
+#[test]
+fn test_heartbeat_watchdog_is_on_unless_switched_off() {
+    let environment = || Config::environment().source(Some(Default::default()));
+    let config = Config::load_with_environment("app-config.toml", environment()).unwrap();
+    assert_eq!(config.server.heartbeat_timeout_multiplier, 2);
+
+    let path = write_config_file("[server]\nheartbeat_timeout_multiplier = 0\n");
+    let config = Config::load_with_environment(path.to_str().unwrap(), environment());
+    std::fs::remove_file(&path).ok();
+    assert_eq!(config.unwrap().server.heartbeat_timeout_multiplier, 0);
+}
+
+#[test]
+fn test_environment_overrides_config_file() {
+    // The overrides are injected rather than set on the process, which other tests share
+    let path = write_config_file("[webrtc]\nanswer_timeout_secs = 10\n");
+    let environment = Config::environment().source(Some(
+        [("SMS_WEBRTC__ANSWER_TIMEOUT_SECS".to_string(), "42".to_string())].into_iter().collect(),
+    ));
+    let config = Config::load_with_environment(path.to_str().unwrap(), environment);
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(config.unwrap().webrtc.answer_timeout_secs, 42);
+}
+
+/// Assert `config` fails validation with an error naming `field`
+fn assert_invalid(config: &Config, field: &str) {
+    match config.validate() {
+        Err(e) => assert!(e.to_string().starts_with(&format!("{field} ")), "Expected an error naming {field}, got: {e}"),
+        Ok(()) => panic!("Expected {field} to be rejected"),
+    }
+}
+
+#[test]
+fn test_default_and_shipped_configs_are_valid() {
+    Config::default().validate().unwrap();
+    Config::load_with_environment("app-config.toml", config::Environment::default().source(Some(Default::default())))
+        .unwrap()
+        .validate()
+        .unwrap();
+}
+
+#[test]
+fn test_validate_rejects_zero_port() {
+    let mut config = Config::default();
+    config.server.port = 0;
+    assert_invalid(&config, "server.port");
+}
+
+#[test]
+fn test_validate_rejects_tls_without_cert_or_key() {
+    let mut config = Config::default();
+    config.server.tls_enabled = true;
+    config.server.tls_key_path = "/path/to/key.pem".to_string();
+    assert_invalid(&config, "server.tls_cert_path");
+
+    config.server.tls_cert_path = "/path/to/cert.pem".to_string();
+    config.server.tls_key_path = " ".to_string();
+    assert_invalid(&config, "server.tls_key_path");
+
+    config.server.tls_key_path = "/path/to/key.pem".to_string();
+    config.validate().unwrap();
+
+    // Paths are not needed while TLS is off
+    config.server.tls_enabled = false;
+    config.server.tls_cert_path.clear();
+    config.server.tls_key_path.clear();
+    config.validate().unwrap();
+}
+
+#[test]
+fn test_validate_rejects_port_conflicts() {
+    let mut config = Config::default();
+    config.metrics.port = config.server.port;
+    assert_invalid(&config, "metrics.port");
+
+    // A disabled metrics endpoint does not bind its port
+    config.metrics.enabled = false;
+    config.validate().unwrap();
+
+    let mut config = Config::default();
+    config.server.readyz_port = config.server.port;
+    assert_invalid(&config, "server.readyz_port");
+
+    let mut config = Config::default();
+    config.server.readyz_port = config.metrics.port;
+    assert_invalid(&config, "server.readyz_port");
+}
+
+#[test]
+fn test_validate_rejects_zero_timeouts() {
+    for field in ["server.heartbeat_interval", "server.tls_handshake_timeout_secs", "auth.token_expiry", "session.cleanup_interval"] {
+        let mut config = Config::default();
+        match field {
+            "server.heartbeat_interval" => config.server.heartbeat_interval = 0,
+            "server.tls_handshake_timeout_secs" => config.server.tls_handshake_timeout_secs = 0,
+            "auth.token_expiry" => config.auth.token_expiry = 0,
+            _ => config.session.cleanup_interval = 0,
+        }
+        assert_invalid(&config, field);
+    }
+
+    let mut config = Config::default();
+    config.server.warmup_pong_timeout_ms = 0;
+    config.validate().unwrap();
+    config.server.require_warmup_pong = true;
+    assert_invalid(&config, "server.warmup_pong_timeout_ms");
+}
+
+#[test]
+fn test_validate_rejects_periods_past_the_maximum() {
+    use signal_manager_service::config::MAX_PERIOD_SECS;
+
+    for field in ["session.terminated_room_retention_secs", "webrtc.max_room_lifetime_secs", "webrtc.answer_timeout_secs"] {
+        for (secs, valid) in [(MAX_PERIOD_SECS, true), (MAX_PERIOD_SECS + 1, false), (u64::MAX, false)] {
+            let mut config = Config::default();
+            match field {
+                "session.terminated_room_retention_secs" => config.session.terminated_room_retention_secs = secs,
+                "webrtc.max_room_lifetime_secs" => config.webrtc.max_room_lifetime_secs = secs,
+                _ => config.webrtc.answer_timeout_secs = secs,
+            }
+            if valid {
+                config.validate().unwrap();
+            } else {
+                assert_invalid(&config, field);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_validate_rejects_missing_auth_keys() {
+    let mut config = Config::default();
+    config.auth.api_keys.clear();
+    assert_invalid(&config, "auth.api_keys");
+
+    config.auth.api_keys = vec!["test_client_1:test_token_1".to_string(), "test_client_2".to_string()];
+    assert_invalid(&config, "auth.api_keys[1]");
+
+    config.auth.api_keys = vec![":test_token_1".to_string()];
+    assert_invalid(&config, "auth.api_keys[0]");
+}
+
+#[test]
+fn test_validate_checks_jwt_keys() {
+    use signal_manager_service::config::JwtAlgorithm;
+
+    let mut config = Config::default();
+    config.auth.auth_method = "jwt".to_string();
+    config.auth.api_keys.clear();
+    // The shipped placeholder would let anyone who has read the defaults sign tokens
+    assert_invalid(&config, "auth.token_secret");
+    config.auth.token_secret = "a-real-secret".to_string();
+    config.validate().unwrap();
+
+    config.auth.token_secret.clear();
+    assert_invalid(&config, "auth.token_secret");
+
+    config.auth.jwt_algorithm = JwtAlgorithm::Rs256;
+    assert_invalid(&config, "auth.jwt_public_key_path");
+    config.auth.jwt_public_key_path = Some("/etc/signal-manager/jwt_public.pem".to_string());
+    config.validate().unwrap();
+}
+
+#[test]
+fn test_validate_rejects_codecs_without_json() {
+    use signal_manager_service::message::PayloadType;
+
+    let mut config = Config::default();
+    config.server.enabled_codecs = vec![PayloadType::Protobuf];
+    assert_invalid(&config, "server.enabled_codecs");
+}
+
+#[test]
+fn test_validate_rejects_room_participant_limit_below_the_default() {
+    let mut config = Config::default();
+    config.webrtc.max_room_participants = 1;
+    assert_invalid(&config, "webrtc.max_room_participants");
+    config.webrtc.max_room_participants = 2;
+    config.validate().unwrap();
+}
+
+/// Run the `validate` command over `path` and return its exit code and output
+fn run_validate_on(path: &str) -> (i32, String) {
+    let mut out = Vec::new();
+    let code = signal_manager_service::config::run_validate(Some(path), &mut out);
+    (code, String::from_utf8(out).unwrap())
+}
+
+#[test]
+fn test_validate_command_accepts_good_config() {
+    let path = write_config_file("[server]\nport = 9443\n\n[auth]\nauth_method = \"api_key\"\n");
+    let (code, out) = run_validate_on(path.to_str().unwrap());
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(code, 0, "Unexpected output:\n{out}");
+    assert!(out.starts_with("Configuration is valid\n"));
+    assert!(out.contains("server.address = 127.0.0.1:9443\n"));
+    assert!(out.contains("auth.auth_method = api_key (2 api keys, 0 secondary)\n"));
+    // Secrets stay out of the summary
+    assert!(!out.contains(&Config::default().auth.token_secret));
+    assert!(!out.contains("test_token_1"));
+}
+
+#[test]
+fn test_validate_command_rejects_bad_config() {
+    let path = write_config_file("[server]\ntls_enabled = true\n");
+    let (code, out) = run_validate_on(path.to_str().unwrap());
+    std::fs::remove_file(&path).ok();
+    assert_eq!(code, 1);
+    assert!(out.starts_with("Invalid configuration: server.tls_cert_path "), "Unexpected output:\n{out}");
+
+    let path = write_config_file("[server]\nport = \"not a port\"\n");
+    let (code, out) = run_validate_on(path.to_str().unwrap());
+    std::fs::remove_file(&path).ok();
+    assert_eq!(code, 1);
+    assert!(out.starts_with("Invalid configuration: "), "Unexpected output:\n{out}");
+
+    let (code, out) = run_validate_on("/nonexistent/sms-config.toml");
+    assert_eq!(code, 1);
+    assert_eq!(out, "Invalid configuration: /nonexistent/sms-config.toml is not a file\n");
+}
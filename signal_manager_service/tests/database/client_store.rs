@@ -0,0 +1,109 @@
+use signal_manager_service::config::{Config, EvictionPolicy};
+use signal_manager_service::database::{ClientRepository, DatabaseError, FirestoreClientRepository, RegistrationPayload};
+
+fn registration(client_id: &str) -> RegistrationPayload {
+    RegistrationPayload {
+        client_id: client_id.to_string(),
+        auth_token: format!("{client_id}_token"),
+        room_id: None,
+        capabilities: None,
+        metadata: None,
+    }
+}
+
+#[tokio::test]
+async fn test_client_store_rejects_registrations_past_cap() {
+    let mut config = Config::default();
+    config.database.max_clients = 3;
+    let repo = FirestoreClientRepository::new(&config).await.unwrap();
+
+    for i in 0..3 {
+        repo.create_client(registration(&format!("client_{i}"))).await.unwrap();
+    }
+    assert_eq!(repo.client_count().await, 3);
+
+    let result = repo.create_client(registration("client_3")).await;
+    assert!(matches!(result, Err(DatabaseError::Capacity(_))), "Expected capacity error, got {:?}", result);
+    assert_eq!(repo.client_count().await, 3);
+    assert!(!repo.client_exists("client_3").await.unwrap());
+
+    // Deleting a client frees a slot
+    assert!(repo.delete_client("client_0").await.unwrap());
+    repo.create_client(registration("client_3")).await.unwrap();
+    assert_eq!(repo.client_count().await, 3);
+}
+
+#[tokio::test]
+async fn test_client_store_unlimited_when_cap_is_zero() {
+    let mut config = Config::default();
+    config.database.max_clients = 0;
+    let repo = FirestoreClientRepository::new(&config).await.unwrap();
+
+    for i in 0..10 {
+        repo.create_client(registration(&format!("client_{i}"))).await.unwrap();
+    }
+    assert_eq!(repo.client_count().await, 10);
+}
+
+#[tokio::test]
+async fn test_client_store_evicts_least_recently_seen_under_lru() {
+    let mut config = Config::default();
+    config.database.max_clients = 3;
+    config.database.eviction_policy = EvictionPolicy::Lru;
+    let repo = FirestoreClientRepository::new(&config).await.unwrap();
+
+    for i in 0..3 {
+        repo.create_client(registration(&format!("client_{i}"))).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    }
+    // client_0 was registered first but seen since, leaving client_1 as the oldest
+    assert!(repo.update_last_seen("client_0").await.unwrap());
+
+    repo.create_client(registration("client_3")).await.unwrap();
+    assert_eq!(repo.client_count().await, 3);
+    assert!(!repo.client_exists("client_1").await.unwrap());
+    for id in ["client_0", "client_2", "client_3"] {
+        assert!(repo.client_exists(id).await.unwrap(), "{id} should still be stored");
+    }
+    assert_eq!(repo.take_evicted().await, vec!["client_1".to_string()]);
+    assert!(repo.take_evicted().await.is_empty());
+}
+
+#[tokio::test]
+async fn test_client_store_upsert_inserts_then_replaces() {
+    let mut config = Config::default();
+    config.database.max_clients = 1;
+    let repo = FirestoreClientRepository::new(&config).await.unwrap();
+
+    let first = repo.upsert_client(registration("client_0")).await.unwrap();
+    assert_eq!(first.last_seen, None);
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+
+    // Replacing a stored client needs no free slot, and keeps its id and registration time
+    let updated = repo.upsert_client(RegistrationPayload {
+        capabilities: Some(vec!["webrtc".to_string()]),
+        ..registration("client_0")
+    }).await.unwrap();
+    assert_eq!(updated.id, first.id);
+    assert_eq!(updated.registered_at, first.registered_at);
+    assert_eq!(updated.capabilities, vec!["webrtc"]);
+    assert!(updated.last_seen.is_some_and(|seen| seen > first.registered_at));
+
+    let stored = repo.get_client("client_0").await.unwrap().unwrap();
+    assert_eq!(stored.registered_at, first.registered_at);
+    assert_eq!(stored.capabilities, vec!["webrtc"]);
+    assert_eq!(repo.client_count().await, 1);
+}
+
+#[tokio::test]
+async fn test_client_store_upsert_rejects_a_different_token() {
+    let repo = FirestoreClientRepository::new(&Config::default()).await.unwrap();
+    repo.upsert_client(registration("client_0")).await.unwrap();
+
+    let result = repo.upsert_client(RegistrationPayload {
+        auth_token: "someone_else".to_string(),
+        ..registration("client_0")
+    }).await;
+    assert!(matches!(result, Err(DatabaseError::Authentication(_))), "Expected authentication error, got {:?}", result);
+    assert!(repo.validate_auth("client_0", "client_0_token").await.unwrap());
+}
@@ -15,12 +15,36 @@ fn create_integration_test_config() -> Config {
             port: 8080,
             max_connections: 1000,
             heartbeat_interval: 30,
+            heartbeat_timeout_multiplier: 2,
+            ping_interval_secs: 0,
             tls_enabled: false,
             tls_cert_path: "".to_string(),
             tls_key_path: "".to_string(),
+            tls_backend: signal_manager_service::config::TlsBackend::NativeTls,
             read_buffer_size: 8192,
             write_buffer_size: 8192,
             max_message_size: 1048576,
+            max_frame_size: 1048576,
+            outbound_queue_depth: 100,
+            outbound_overflow_policy: signal_manager_service::config::OverflowPolicy::DropNewest,
+            prioritize_control_frames: true,
+            duplicate_connect_policy: signal_manager_service::config::DuplicateConnectPolicy::Reject,
+            connect_ack_order: signal_manager_service::config::ConnectAckOrder::AfterPersist,
+            connection_limit_policy: signal_manager_service::config::ConnectionLimitPolicy::Reject,
+            max_clock_skew_ms: 30000,
+            tls_handshake_timeout_secs: 10,
+            readyz_port: 0,
+            require_warmup_pong: false,
+            warmup_pong_timeout_ms: 5000,
+            max_concurrent_handshakes: 64,
+            max_queued_handshakes: 1024,
+            compression_threshold_bytes: 0,
+            frame_checksums: false,
+            enabled_codecs: signal_manager_service::message::PayloadType::SUPPORTED.to_vec(),
+            max_id_length: 128,
+            shutdown_grace_secs: 10,
+            slow_handler_threshold_ms: 1000,
+            instance_id: "test-instance".to_string(),
         },
         auth: signal_manager_service::config::AuthConfig {
             token_secret: "test-secret".to_string(),
@@ -30,6 +54,10 @@ fn create_integration_test_config() -> Config {
                 "test_client_1:test_token_1".to_string(),
                 "test_client_2:test_token_2".to_string(),
             ],
+            secondary_api_keys: Vec::new(),
+            hash_tokens_at_rest: false,
+            jwt_algorithm: signal_manager_service::config::JwtAlgorithm::Hs256,
+            jwt_public_key_path: None,
         },
         logging: signal_manager_service::config::LoggingConfig {
             level: "info".to_string(),
@@ -50,12 +78,22 @@ fn create_integration_test_config() -> Config {
             session_timeout: 3600,
             cleanup_interval: 300,
             max_sessions_per_client: 1,
+            session_limit_policy: signal_manager_service::config::SessionLimitPolicy::Reject,
+            terminated_room_retention_secs: 0,
+            reconnect_grace_secs: 0,
+            last_seen_retention_secs: 86400,
         },
         security: signal_manager_service::config::SecurityConfig {
             rate_limit_enabled: true,
             max_messages_per_minute: 100,
             max_connections_per_ip: 10,
             allowed_origins: vec!["*".to_string()],
+            validate_signal_base64: false,
+            role_message_allowlist: std::collections::HashMap::new(),
+            strict_payload_validation: false,
+            enforce_capabilities: false,
+            required_capabilities: std::collections::HashMap::new(),
+            admin_clients: Vec::new(),
         },
         gcp: signal_manager_service::config::GcpConfig {
             credentials_path: "".to_string(),
@@ -72,7 +110,12 @@ fn create_integration_test_config() -> Config {
             app_secret: "test-app-secret".to_string(),
             base_url: "https://api.cloudflare.com/client/v4".to_string(),
             stun_url: "stun:stun.cloudflare.com:3478".to_string(),
+            retry: signal_manager_service::backoff::BackoffConfig::default(),
+            turn: signal_manager_service::config::TurnConfig::default(),
         },
+        events: signal_manager_service::config::EventsConfig::default(),
+        webrtc: signal_manager_service::config::WebRTCConfig::default(),
+        database: signal_manager_service::config::DatabaseConfig::default(),
     }
 }
 
@@ -2,4 +2,7 @@
 pub mod repository;
 // pub mod firestore;
 // pub mod integration;
-pub mod simple; 
\ No newline at end of file
+pub mod simple;
+pub mod client_store;
+pub mod token_hash;
+pub mod reconnect;
@@ -0,0 +1,108 @@
+use signal_manager_service::database::{DatabaseError, DatabaseResult, ReconnectingClient};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Stand-in for a database connection that can be cut from the outside
+struct FakeConnection {
+    dropped: Arc<AtomicBool>,
+}
+
+impl FakeConnection {
+    fn query(&self) -> DatabaseResult<&'static str> {
+        if self.dropped.load(Ordering::SeqCst) {
+            Err(DatabaseError::Unavailable("connection reset by peer".to_string()))
+        } else {
+            Ok("row")
+        }
+    }
+}
+
+/// Connector whose connections all share `dropped`, and which fails while `refuse` is set
+fn fake_client(dropped: Arc<AtomicBool>, refuse: Arc<AtomicBool>) -> ReconnectingClient<FakeConnection> {
+    ReconnectingClient::new(move || {
+        let dropped = dropped.clone();
+        let refuse = refuse.clone();
+        async move {
+            if refuse.load(Ordering::SeqCst) {
+                return Err(DatabaseError::Connection("connection refused".to_string()));
+            }
+            // A fresh connection starts healthy
+            dropped.store(false, Ordering::SeqCst);
+            Ok(FakeConnection { dropped })
+        }
+    })
+}
+
+async fn run_query(db: &ReconnectingClient<FakeConnection>) -> DatabaseResult<&'static str> {
+    let client = db.client().await?;
+    match client.query() {
+        Ok(row) => Ok(row),
+        Err(e) => Err(db.fail(&client, e).await),
+    }
+}
+
+#[tokio::test]
+async fn test_reconnects_after_dropped_connection() {
+    let dropped = Arc::new(AtomicBool::new(false));
+    let db = fake_client(dropped.clone(), Arc::new(AtomicBool::new(false)));
+
+    assert_eq!(run_query(&db).await.unwrap(), "row");
+    assert_eq!(db.connects(), 1);
+
+    dropped.store(true, Ordering::SeqCst);
+    let err = run_query(&db).await.unwrap_err();
+    assert!(matches!(err, DatabaseError::Unavailable(_)));
+    assert!(err.is_retryable());
+
+    // The next operation connects again instead of reusing the dead client
+    assert_eq!(run_query(&db).await.unwrap(), "row");
+    assert_eq!(db.connects(), 2);
+}
+
+#[tokio::test]
+async fn test_non_retryable_error_keeps_connection() {
+    let db = fake_client(Arc::new(AtomicBool::new(false)), Arc::new(AtomicBool::new(false)));
+    let client = db.client().await.unwrap();
+
+    let err = db.fail(&client, DatabaseError::Validation("bad field".to_string())).await;
+    assert!(matches!(err, DatabaseError::Validation(_)));
+    assert!(!err.is_retryable());
+
+    let again = db.client().await.unwrap();
+    assert!(Arc::ptr_eq(&client, &again));
+    assert_eq!(db.connects(), 1);
+}
+
+#[tokio::test]
+async fn test_failed_connect_is_retried() {
+    let refuse = Arc::new(AtomicBool::new(true));
+    let db = fake_client(Arc::new(AtomicBool::new(false)), refuse.clone());
+
+    let err = run_query(&db).await.unwrap_err();
+    assert!(matches!(err, DatabaseError::Unavailable(_)));
+    assert_eq!(db.connects(), 0);
+
+    refuse.store(false, Ordering::SeqCst);
+    assert_eq!(run_query(&db).await.unwrap(), "row");
+    assert_eq!(db.connects(), 1);
+}
+
+#[tokio::test]
+async fn test_stale_failure_does_not_drop_newer_connection() {
+    let attempts = Arc::new(AtomicU64::new(0));
+    let counted = attempts.clone();
+    let db = ReconnectingClient::new(move || {
+        counted.fetch_add(1, Ordering::SeqCst);
+        async move { Ok(FakeConnection { dropped: Arc::new(AtomicBool::new(false)) }) }
+    });
+
+    let stale = db.client().await.unwrap();
+    db.fail(&stale, DatabaseError::Unavailable("timeout".to_string())).await;
+    let fresh = db.client().await.unwrap();
+
+    // A late failure reported on the old client leaves the replacement in place
+    db.fail(&stale, DatabaseError::Unavailable("timeout".to_string())).await;
+    let current = db.client().await.unwrap();
+    assert!(Arc::ptr_eq(&fresh, &current));
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+}
@@ -13,7 +13,7 @@ use signal_manager_service::database::{
     ClientInTerminatedRoomRepository, ClientInTerminatedRoom,
     ClientInRoomStatus, ClientTerminationStatus,
     WebRTCRoomRepository, WebRTCClientRepository,
-    WebRTCRoom, WebRTCClient, WebRTCRoomCreationPayload, WebRTCClientRegistrationPayload,
+    WebRTCRoom, WebRTCClient, WebRTCRoomCreationPayload, WebRTCClientRegistrationPayload, DEFAULT_MAX_PARTICIPANTS,
     WebRTCRoomStatus, WebRTCClientStatus, ClientRole,
     DatabaseError,
 };
@@ -54,6 +54,12 @@ pub struct MockWebRTCClientRepository {
 /// Mock repository factory for testing
 pub struct MockRepositoryFactory;
 
+/// Repository factory whose every repository fails to connect
+pub struct UnreachableRepositoryFactory;
+
+/// Room repository that panics on every call, for exercising handler panic isolation
+pub struct PanickingWebRTCRoomRepository;
+
 impl MockClientRepository {
     pub fn new() -> Self {
         Self {
@@ -68,6 +74,11 @@ impl MockTerminatedRoomRepository {
             terminated_rooms: Arc::new(Mutex::new(HashMap::new())),
         }
     }
+
+    /// Store a record as-is, so tests can control `terminated_at`
+    pub async fn insert(&self, room: TerminatedRoom) {
+        self.terminated_rooms.lock().await.insert(room.room_id.clone(), room);
+    }
 }
 
 impl MockRoomCreatedRepository {
@@ -143,6 +154,41 @@ impl ClientRepository for MockClientRepository {
         Ok(client)
     }
 
+    async fn upsert_client(&self, payload: RegistrationPayload) -> DatabaseResult<RegisteredClient> {
+        let mut clients = self.clients.lock().await;
+
+        let existing = clients.get(&payload.client_id).cloned();
+        if existing.as_ref().is_some_and(|existing| existing.auth_token != payload.auth_token) {
+            return Err(signal_manager_service::database::DatabaseError::Authentication(
+                format!("Client {} is registered with a different token", payload.client_id)
+            ));
+        }
+
+        let client = if let Some(room_id) = payload.room_id {
+            RegisteredClient::new_with_room(
+                payload.client_id.clone(),
+                payload.auth_token,
+                room_id,
+                payload.capabilities.unwrap_or_default(),
+                payload.metadata.unwrap_or_default(),
+            )
+        } else {
+            RegisteredClient::new(
+                payload.client_id.clone(),
+                payload.auth_token,
+                payload.capabilities.unwrap_or_default(),
+                payload.metadata.unwrap_or_default(),
+            )
+        };
+        let client = match existing {
+            Some(existing) => client.replacing(&existing),
+            None => client,
+        };
+
+        clients.insert(payload.client_id, client.clone());
+        Ok(client)
+    }
+
     async fn get_client(&self, client_id: &str) -> DatabaseResult<Option<RegisteredClient>> {
         let clients = self.clients.lock().await;
         Ok(clients.get(client_id).cloned())
@@ -258,6 +304,13 @@ impl TerminatedRoomRepository for MockTerminatedRoomRepository {
         
         Ok(result)
     }
+
+    async fn delete_terminated_rooms_before(&self, cutoff: chrono::DateTime<chrono::Utc>) -> DatabaseResult<usize> {
+        let mut rooms = self.terminated_rooms.lock().await;
+        let before = rooms.len();
+        rooms.retain(|_, room| room.terminated_at >= cutoff);
+        Ok(before - rooms.len())
+    }
 }
 
 #[async_trait]
@@ -349,6 +402,15 @@ impl ClientInRoomRepository for MockClientInRoomRepository {
         Ok(result)
     }
 
+    async fn get_rooms_for_client(&self, client_id: &str) -> DatabaseResult<Vec<ClientInRoom>> {
+        let clients = self.clients_in_room.lock().await;
+        let result: Vec<_> = clients.values()
+            .filter(|c| c.client_id == client_id)
+            .cloned()
+            .collect();
+        Ok(result)
+    }
+
     async fn list_clients_in_rooms(&self) -> DatabaseResult<Vec<ClientInRoom>> {
         let clients = self.clients_in_room.lock().await;
         Ok(clients.values().cloned().collect())
@@ -531,6 +593,13 @@ impl ClientInTerminatedRoomRepository for MockClientInTerminatedRoomRepository {
             .collect();
         Ok(result)
     }
+
+    async fn delete_clients_in_terminated_rooms_before(&self, cutoff: chrono::DateTime<chrono::Utc>) -> DatabaseResult<usize> {
+        let mut clients = self.clients_in_terminated_room.lock().await;
+        let before = clients.len();
+        clients.retain(|_, c| c.left_at >= cutoff);
+        Ok(before - clients.len())
+    }
 }
 
 #[async_trait]
@@ -564,6 +633,92 @@ impl RepositoryFactory for MockRepositoryFactory {
     }
 }
 
+#[async_trait]
+impl RepositoryFactory for UnreachableRepositoryFactory {
+    async fn create_client_repository(&self) -> DatabaseResult<Arc<dyn ClientRepository + Send + Sync>> {
+        Err(DatabaseError::Connection("repository unreachable".to_string()))
+    }
+
+    async fn create_terminated_room_repository(&self) -> DatabaseResult<Arc<dyn TerminatedRoomRepository + Send + Sync>> {
+        Err(DatabaseError::Connection("repository unreachable".to_string()))
+    }
+
+    async fn create_room_created_repository(&self) -> DatabaseResult<Arc<dyn RoomCreatedRepository + Send + Sync>> {
+        Err(DatabaseError::Connection("repository unreachable".to_string()))
+    }
+
+    async fn create_client_in_room_repository(&self) -> DatabaseResult<Arc<dyn ClientInRoomRepository + Send + Sync>> {
+        Err(DatabaseError::Connection("repository unreachable".to_string()))
+    }
+
+    async fn create_client_in_terminated_room_repository(&self) -> DatabaseResult<Arc<dyn ClientInTerminatedRoomRepository + Send + Sync>> {
+        Err(DatabaseError::Connection("repository unreachable".to_string()))
+    }
+
+    async fn create_webrtc_room_repository(&self) -> DatabaseResult<Arc<dyn WebRTCRoomRepository + Send + Sync>> {
+        Err(DatabaseError::Connection("repository unreachable".to_string()))
+    }
+
+    async fn create_webrtc_client_repository(&self) -> DatabaseResult<Arc<dyn WebRTCClientRepository + Send + Sync>> {
+        Err(DatabaseError::Connection("repository unreachable".to_string()))
+    }
+}
+
+#[async_trait]
+impl WebRTCRoomRepository for PanickingWebRTCRoomRepository {
+    async fn create_room(&self, _payload: WebRTCRoomCreationPayload) -> Result<WebRTCRoom, DatabaseError> {
+        panic!("room repository exploded")
+    }
+
+    async fn get_room_by_id(&self, _room_id: &str) -> Result<Option<WebRTCRoom>, DatabaseError> {
+        panic!("room repository exploded")
+    }
+
+    async fn get_room_by_uuid(&self, _room_uuid: &str) -> Result<Option<WebRTCRoom>, DatabaseError> {
+        panic!("room repository exploded")
+    }
+
+    async fn update_room_status(&self, _room_id: &str, _status: WebRTCRoomStatus) -> Result<(), DatabaseError> {
+        panic!("room repository exploded")
+    }
+
+    async fn set_sender_client_id(&self, _room_id: &str, _client_id: &str) -> Result<(), DatabaseError> {
+        panic!("room repository exploded")
+    }
+
+    async fn set_receiver_client_id(&self, _room_id: &str, _client_id: &str) -> Result<(), DatabaseError> {
+        panic!("room repository exploded")
+    }
+
+    async fn set_session_id(&self, _room_id: &str, _session_id: &str) -> Result<(), DatabaseError> {
+        panic!("room repository exploded")
+    }
+
+    async fn get_active_rooms(&self) -> Result<Vec<WebRTCRoom>, DatabaseError> {
+        panic!("room repository exploded")
+    }
+
+    async fn get_rooms_created_before(&self, _cutoff: chrono::DateTime<chrono::Utc>) -> Result<Vec<WebRTCRoom>, DatabaseError> {
+        panic!("room repository exploded")
+    }
+
+    async fn get_rooms_by_client_id(&self, _client_id: &str) -> Result<Vec<WebRTCRoom>, DatabaseError> {
+        panic!("room repository exploded")
+    }
+
+    async fn terminate_room(&self, _room_id: &str, _reason: &str) -> Result<(), DatabaseError> {
+        panic!("room repository exploded")
+    }
+
+    async fn delete_room(&self, _room_id: &str) -> Result<(), DatabaseError> {
+        panic!("room repository exploded")
+    }
+
+    async fn get_room_count(&self) -> Result<usize, DatabaseError> {
+        panic!("room repository exploded")
+    }
+}
+
 #[async_trait]
 impl WebRTCRoomRepository for MockWebRTCRoomRepository {
     async fn create_room(&self, payload: WebRTCRoomCreationPayload) -> Result<WebRTCRoom, DatabaseError> {
@@ -576,7 +731,7 @@ impl WebRTCRoomRepository for MockWebRTCRoomRepository {
             payload.receiver_client_id.clone(),
             payload.session_id.clone(),
             payload.metadata.clone(),
-        );
+        ).with_max_participants(payload.max_participants.unwrap_or(DEFAULT_MAX_PARTICIPANTS));
         
         rooms.insert(room.room_id.clone(), room.clone());
         Ok(room)
@@ -637,6 +792,11 @@ impl WebRTCRoomRepository for MockWebRTCRoomRepository {
         Ok(rooms.values().filter(|r| r.is_active()).cloned().collect())
     }
     
+    async fn get_rooms_created_before(&self, cutoff: chrono::DateTime<chrono::Utc>) -> Result<Vec<WebRTCRoom>, DatabaseError> {
+        let rooms = self.rooms.lock().await;
+        Ok(rooms.values().filter(|r| r.created_at < cutoff).cloned().collect())
+    }
+    
     async fn get_rooms_by_client_id(&self, client_id: &str) -> Result<Vec<WebRTCRoom>, DatabaseError> {
         let rooms = self.rooms.lock().await;
         Ok(rooms.values()
@@ -688,6 +848,26 @@ impl WebRTCClientRepository for MockWebRTCClientRepository {
         Ok(client)
     }
     
+    async fn admit_client(&self, payload: WebRTCClientRegistrationPayload, max_participants: u32) -> Result<Option<WebRTCClient>, DatabaseError> {
+        let mut clients = self.clients.lock().await;
+        
+        let mut client = WebRTCClient::new(
+            payload.client_id,
+            payload.room_id,
+            payload.role,
+            payload.session_id,
+            payload.metadata,
+        );
+        client.update_status(WebRTCClientStatus::Active);
+        
+        let room_clients: Vec<WebRTCClient> = clients.values().filter(|c| c.room_id == client.room_id).cloned().collect();
+        if !client.fits_in_room(&room_clients, max_participants) {
+            return Ok(None);
+        }
+        clients.insert(client.client_id.clone(), client.clone());
+        Ok(Some(client))
+    }
+    
     async fn get_client_by_id(&self, client_id: &str) -> Result<Option<WebRTCClient>, DatabaseError> {
         let clients = self.clients.lock().await;
         Ok(clients.get(client_id).cloned())
@@ -758,6 +938,8 @@ impl WebRTCClientRepository for MockWebRTCClientRepository {
         let mut clients = self.clients.lock().await;
         if let Some(client) = clients.get_mut(client_id) {
             if client.room_id == room_id {
+                // Like the Firestore repository, the client no longer belongs to any room
+                client.room_id = String::new();
                 client.update_status(WebRTCClientStatus::Inactive);
                 Ok(())
             } else {
@@ -810,6 +992,30 @@ async fn test_mock_repository_create_client() {
     assert_eq!(client.metadata, serde_json::json!({"version": "1.0"}));
 }
 
+#[tokio::test]
+async fn test_mock_repository_upsert_client_preserves_registration_time() {
+    let repo = MockClientRepository::new();
+    let payload = |capabilities: &[&str]| RegistrationPayload {
+        client_id: "test_client".to_string(),
+        auth_token: "test_token".to_string(),
+        capabilities: Some(capabilities.iter().map(|c| c.to_string()).collect()),
+        metadata: None,
+        room_id: None,
+    };
+
+    let first = repo.upsert_client(payload(&["websocket"])).await.unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    let updated = repo.upsert_client(payload(&["websocket", "webrtc"])).await.unwrap();
+
+    assert_eq!(updated.id, first.id);
+    assert_eq!(updated.registered_at, first.registered_at);
+    assert_eq!(updated.capabilities, vec!["websocket", "webrtc"]);
+    assert_eq!(repo.list_clients(None).await.unwrap().len(), 1);
+
+    let takeover = repo.upsert_client(RegistrationPayload { auth_token: "other_token".to_string(), ..payload(&[]) }).await;
+    assert!(matches!(takeover, Err(DatabaseError::Authentication(_))));
+}
+
 #[tokio::test]
 async fn test_mock_repository_create_duplicate_client() {
     let repo = MockClientRepository::new();
@@ -2360,4 +2566,117 @@ async fn test_repository_factory_client_in_terminated_room() {
 
     let result = repo.create_client_in_terminated_room(client_in_terminated_room).await;
     assert!(result.is_ok());
-} 
\ No newline at end of file
+} 
+#[tokio::test]
+async fn test_room_report_aggregates_client_sessions() {
+    use signal_manager_service::database::get_room_report;
+
+    let rooms = MockTerminatedRoomRepository::new();
+    let clients = MockClientInTerminatedRoomRepository::new();
+    rooms.create_terminated_room(TerminationPayload {
+        room_id: "report_room".to_string(),
+        room_data: serde_json::json!({}),
+        termination_reason: Some("Room expired".to_string()),
+        terminated_by: Some("server".to_string()),
+        metadata: None,
+    }).await.unwrap();
+
+    let left_at = Utc::now();
+    for (client_id, minutes) in [("client_b", 10), ("client_a", 30), ("client_c", 20)] {
+        clients.create_client_in_terminated_room(ClientInTerminatedRoom::new_with_left_at(
+            client_id.to_string(),
+            "report_room".to_string(),
+            left_at - chrono::Duration::minutes(minutes),
+            left_at,
+            "Room expired".to_string(),
+            "server".to_string(),
+            ClientTerminationStatus::Disconnected,
+            vec![],
+            None,
+        )).await.unwrap();
+    }
+    // A client of another room must not be counted
+    clients.create_client_in_terminated_room(ClientInTerminatedRoom::new_with_left_at(
+        "client_elsewhere".to_string(),
+        "other_room".to_string(),
+        left_at - chrono::Duration::hours(5),
+        left_at,
+        "Room expired".to_string(),
+        "server".to_string(),
+        ClientTerminationStatus::Disconnected,
+        vec![],
+        None,
+    )).await.unwrap();
+
+    let report = get_room_report(&rooms, &clients, "report_room").await.unwrap();
+    assert_eq!(report.room.room_id, "report_room");
+    let client_ids: Vec<_> = report.clients.iter().map(|client| client.client_id.as_str()).collect();
+    assert_eq!(client_ids, vec!["client_a", "client_c", "client_b"]);
+    assert_eq!(report.total_session_duration, std::time::Duration::from_secs(60 * 60));
+    assert_eq!(report.average_session_duration, Some(std::time::Duration::from_secs(20 * 60)));
+
+    // A room nobody joined has no average
+    rooms.create_terminated_room(TerminationPayload {
+        room_id: "empty_room".to_string(),
+        room_data: serde_json::json!({}),
+        termination_reason: None,
+        terminated_by: None,
+        metadata: None,
+    }).await.unwrap();
+    let report = get_room_report(&rooms, &clients, "empty_room").await.unwrap();
+    assert!(report.clients.is_empty());
+    assert_eq!(report.total_session_duration, std::time::Duration::ZERO);
+    assert_eq!(report.average_session_duration, None);
+
+    assert!(matches!(get_room_report(&rooms, &clients, "missing_room").await, Err(DatabaseError::NotFound(_))));
+}
+
+#[tokio::test]
+async fn test_retention_sweep_deletes_only_expired_records() {
+    use signal_manager_service::database::{delete_terminated_records_before, RetentionRepositories, RetentionSweep};
+
+    let rooms = Arc::new(MockTerminatedRoomRepository::new());
+    let clients = Arc::new(MockClientInTerminatedRoomRepository::new());
+    let now = Utc::now();
+    for (room_id, age_days) in [("old_room", 40), ("recent_room", 1)] {
+        let terminated_at = now - chrono::Duration::days(age_days);
+        rooms.insert(TerminatedRoom::new_with_timestamps(
+            room_id.to_string(),
+            serde_json::json!({}),
+            terminated_at,
+            Some("Room expired".to_string()),
+            Some("server".to_string()),
+            None,
+        )).await;
+        clients.create_client_in_terminated_room(ClientInTerminatedRoom::new_with_left_at(
+            format!("client_of_{}", room_id),
+            room_id.to_string(),
+            terminated_at - chrono::Duration::minutes(10),
+            terminated_at,
+            "Room expired".to_string(),
+            "server".to_string(),
+            ClientTerminationStatus::Disconnected,
+            vec![],
+            None,
+        )).await.unwrap();
+    }
+
+    let repositories = RetentionRepositories {
+        terminated_rooms: rooms.clone(),
+        terminated_room_clients: clients.clone(),
+    };
+    let sweep = delete_terminated_records_before(&repositories, now - chrono::Duration::days(30)).await.unwrap();
+    assert_eq!(sweep, RetentionSweep { rooms_deleted: 1, clients_deleted: 1 });
+
+    assert!(rooms.get_terminated_room("old_room").await.unwrap().is_none());
+    assert!(rooms.get_terminated_room("recent_room").await.unwrap().is_some());
+    let remaining: Vec<_> = clients.list_clients_in_terminated_rooms().await.unwrap()
+        .into_iter()
+        .map(|client| client.client_id)
+        .collect();
+    assert_eq!(remaining, vec!["client_of_recent_room".to_string()]);
+
+    // A second sweep with the same cutoff finds nothing left to delete
+    let sweep = delete_terminated_records_before(&repositories, now - chrono::Duration::days(30)).await.unwrap();
+    assert_eq!(sweep, RetentionSweep::default());
+}
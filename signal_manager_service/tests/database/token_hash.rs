@@ -0,0 +1,80 @@
+use signal_manager_service::config::Config;
+use signal_manager_service::database::token_hash::{hash_token, is_hashed, verify_token};
+use signal_manager_service::database::{ClientRepository, FirestoreClientRepository, RegistrationPayload};
+
+fn registration(client_id: &str, auth_token: &str) -> RegistrationPayload {
+    RegistrationPayload {
+        client_id: client_id.to_string(),
+        auth_token: auth_token.to_string(),
+        room_id: None,
+        capabilities: None,
+        metadata: None,
+    }
+}
+
+#[test]
+fn test_hash_token_is_salted() {
+    let first = hash_token("secret_token");
+    let second = hash_token("secret_token");
+
+    assert!(is_hashed(&first));
+    assert_ne!(first, second);
+    assert!(!first.contains("secret_token"));
+    assert!(verify_token(&first, "secret_token"));
+    assert!(verify_token(&second, "secret_token"));
+    assert!(!verify_token(&first, "wrong_token"));
+}
+
+#[test]
+fn test_verify_token_plaintext_and_malformed() {
+    assert!(verify_token("plain_token", "plain_token"));
+    assert!(!verify_token("plain_token", "other_token"));
+    assert!(!verify_token("sha256$nothex$00", "plain_token"));
+    assert!(!verify_token("sha256$missing_digest", "plain_token"));
+}
+
+#[tokio::test]
+async fn test_repository_stores_hashed_token() {
+    let mut config = Config::default();
+    config.auth.hash_tokens_at_rest = true;
+    let repo = FirestoreClientRepository::new(&config).await.unwrap();
+
+    let client = repo.create_client(registration("hashed_client", "secret_token")).await.unwrap();
+    assert!(is_hashed(&client.auth_token));
+    assert_ne!(client.auth_token, "secret_token");
+
+    let stored = repo.get_client("hashed_client").await.unwrap().unwrap();
+    assert!(is_hashed(&stored.auth_token));
+
+    // Auth still works with the plaintext token
+    assert!(repo.validate_auth("hashed_client", "secret_token").await.unwrap());
+    assert!(!repo.validate_auth("hashed_client", "wrong_token").await.unwrap());
+    assert!(!repo.validate_auth("hashed_client", &stored.auth_token).await.unwrap());
+
+    let by_token = repo.get_client_by_token("secret_token").await.unwrap().unwrap();
+    assert_eq!(by_token.client_id, "hashed_client");
+    assert!(repo.get_client_by_token("wrong_token").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_repository_upsert_matches_hashed_token() {
+    let mut config = Config::default();
+    config.auth.hash_tokens_at_rest = true;
+    let repo = FirestoreClientRepository::new(&config).await.unwrap();
+
+    let first = repo.upsert_client(registration("hashed_client", "secret_token")).await.unwrap();
+    let again = repo.upsert_client(registration("hashed_client", "secret_token")).await.unwrap();
+    assert_eq!(again.registered_at, first.registered_at);
+    assert!(is_hashed(&again.auth_token));
+    assert!(repo.upsert_client(registration("hashed_client", "wrong_token")).await.is_err());
+}
+
+#[tokio::test]
+async fn test_repository_stores_plaintext_when_disabled() {
+    let config = Config::default();
+    let repo = FirestoreClientRepository::new(&config).await.unwrap();
+
+    let client = repo.create_client(registration("plain_client", "secret_token")).await.unwrap();
+    assert_eq!(client.auth_token, "secret_token");
+    assert!(repo.validate_auth("plain_client", "secret_token").await.unwrap());
+}
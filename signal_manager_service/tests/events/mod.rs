@@ -0,0 +1,506 @@
+use serde_json::json;
+use signal_manager_service::config::Config;
+use signal_manager_service::events::{create_event_client, EventClient, EventMessage, InMemoryEventClient};
+
+#[tokio::test]
+async fn test_in_memory_publish_and_read_back() {
+    let client = InMemoryEventClient::new(10);
+    client.publish(EventMessage::new("client_registered", json!({"client_id": "c1"}))).await.unwrap();
+    client.publish(EventMessage::new("room_created", json!({"room_id": "r1"}))).await.unwrap();
+
+    let events = client.events();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].event_type, "client_registered");
+    assert_eq!(events[0].data["client_id"], "c1");
+    assert_eq!(events[1].event_type, "room_created");
+
+    let recent = client.recent_events(1);
+    assert_eq!(recent.len(), 1);
+    assert_eq!(recent[0].event_type, "room_created");
+}
+
+#[tokio::test]
+async fn test_in_memory_ring_buffer_evicts_oldest() {
+    let client = InMemoryEventClient::new(3);
+    for i in 0..5 {
+        client.publish(EventMessage::new("heartbeat", json!({"seq": i}))).await.unwrap();
+    }
+
+    let seqs: Vec<i64> = client.events().iter().map(|e| e.data["seq"].as_i64().unwrap()).collect();
+    assert_eq!(seqs, vec![2, 3, 4]);
+    assert_eq!(client.recent_events(10).len(), 3);
+}
+
+#[tokio::test]
+async fn test_event_client_selected_by_config() {
+    let config = Config::default();
+    assert_eq!(config.events.backend, "memory");
+    let client = create_event_client(&config).expect("Memory backend available");
+    client.publish(EventMessage::new("room_created", json!({}))).await.unwrap();
+
+    let mut unsupported = Config::default();
+    unsupported.events.backend = "kafka".to_string();
+    assert!(create_event_client(&unsupported).is_err());
+}
+
+#[tokio::test]
+async fn test_published_events_carry_configured_instance_id() {
+    let mut config = Config::default();
+    config.server.instance_id = "signal-manager-eu-1".to_string();
+    assert!(!Config::default().server.instance_id.is_empty());
+
+    let client = InMemoryEventClient::new(10).with_instance_id(&config.server.instance_id);
+    client.publish(EventMessage::new("room_created", json!({"room_id": "r1"}))).await.unwrap();
+    assert_eq!(client.events()[0].instance_id.as_deref(), Some("signal-manager-eu-1"));
+
+    // Events that already name an instance keep it
+    let forwarded = EventMessage::new("room_created", json!({})).attributed_to("signal-manager-us-1");
+    client.publish(forwarded).await.unwrap();
+    assert_eq!(client.events()[1].instance_id.as_deref(), Some("signal-manager-us-1"));
+}
+
+/// Records events after a delay that shrinks with each one, so per-event tasks would finish in reverse
+struct SlowFirstEventClient {
+    delays_ms: std::sync::Mutex<Vec<u64>>,
+    received: InMemoryEventClient,
+}
+
+#[async_trait::async_trait]
+impl EventClient for SlowFirstEventClient {
+    async fn publish(&self, event: EventMessage) -> Result<(), signal_manager_service::Error> {
+        let delay = self.delays_ms.lock().unwrap().pop().unwrap_or(0);
+        tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+        self.received.publish(event).await
+    }
+}
+
+#[tokio::test]
+async fn test_ordered_client_publishes_events_in_order() {
+    use signal_manager_service::events::{publish_in_background, OrderedEventClient};
+
+    let sink = std::sync::Arc::new(SlowFirstEventClient {
+        delays_ms: std::sync::Mutex::new(vec![0, 20, 40]),
+        received: InMemoryEventClient::new(10),
+    });
+    let client: std::sync::Arc<dyn EventClient> = OrderedEventClient::new(sink.clone());
+    for event_type in ["room_created", "client_joined_room", "client_left_room"] {
+        publish_in_background(&client, EventMessage::new(event_type, json!({"room_id": "r1"})));
+    }
+
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(5);
+    while sink.received.len() < 3 && tokio::time::Instant::now() < deadline {
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    let order: Vec<String> = sink.received.events().into_iter().map(|e| e.event_type).collect();
+    assert_eq!(order, vec!["room_created", "client_joined_room", "client_left_room"]);
+}
+
+#[tokio::test]
+async fn test_ordered_client_flush_waits_for_queued_events() {
+    use signal_manager_service::events::{publish_in_background, OrderedEventClient};
+
+    let sink = std::sync::Arc::new(SlowFirstEventClient {
+        delays_ms: std::sync::Mutex::new(vec![0, 20, 40]),
+        received: InMemoryEventClient::new(10),
+    });
+    let client: std::sync::Arc<dyn EventClient> = OrderedEventClient::new(sink.clone());
+    for seq in 0..3 {
+        publish_in_background(&client, EventMessage::new("room_created", json!({"seq": seq})));
+    }
+
+    // As on shutdown: everything published before the flush is delivered by the time it returns
+    assert!(client.flush().await.is_success());
+    assert_eq!(sink.received.len(), 3);
+}
+
+mod gcp_pubsub {
+    use super::*;
+    use crate::support::mock_http::MockHttpServer;
+    use base64::Engine;
+    use async_trait::async_trait;
+    use signal_manager_service::events::{
+        AccessToken, BatchPublisher, GcpPubSubClient, ServiceAccountTokenSource, StaticTokenSource, TokenSource,
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Issues "token-1", "token-2", ... each valid for `lifetime`
+    struct MockTokenSource {
+        fetches: AtomicUsize,
+        lifetime: chrono::Duration,
+    }
+
+    impl MockTokenSource {
+        fn new(lifetime: chrono::Duration) -> Arc<Self> {
+            Arc::new(Self { fetches: AtomicUsize::new(0), lifetime })
+        }
+
+        fn fetches(&self) -> usize {
+            self.fetches.load(Ordering::SeqCst)
+        }
+    }
+
+    #[async_trait]
+    impl TokenSource for MockTokenSource {
+        async fn token(&self) -> Result<AccessToken, signal_manager_service::Error> {
+            let n = self.fetches.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok(AccessToken::new(format!("token-{n}"), Some(chrono::Utc::now() + self.lifetime)))
+        }
+    }
+
+    fn pubsub_config(endpoint: String) -> Config {
+        let mut config = Config::default();
+        config.gcp.project_id = "test-project".to_string();
+        config.events.backend = "gcp_pubsub".to_string();
+        config.events.pubsub_endpoint = endpoint;
+        config.events.default_topic = "default-events".to_string();
+        config.events.topics.insert("room_created".to_string(), "room-events".to_string());
+        config
+    }
+
+    #[tokio::test]
+    async fn test_topic_mapping_with_default_fallback() {
+        let server = MockHttpServer::start().await;
+        let config = pubsub_config(server.url());
+        let client = GcpPubSubClient::new(&config, Arc::new(StaticTokenSource::new("test-token"))).unwrap();
+
+        assert_eq!(client.topic_for("room_created"), "room-events");
+        assert_eq!(client.topic_for("client_registered"), "default-events");
+
+        client.publish(EventMessage::new("room_created", json!({"room_id": "r1"}))).await.unwrap();
+        client.publish(EventMessage::new("client_registered", json!({"client_id": "c1"}))).await.unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].path, "/projects/test-project/topics/room-events:publish");
+        assert_eq!(requests[1].path, "/projects/test-project/topics/default-events:publish");
+        assert_eq!(requests[0].header("authorization"), Some("Bearer test-token"));
+
+        // The event is carried base64-encoded in the Pub/Sub message data
+        let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+        let data = body["messages"][0]["data"].as_str().unwrap();
+        let decoded = base64::engine::general_purpose::STANDARD.decode(data).unwrap();
+        let event: EventMessage = serde_json::from_slice(&decoded).unwrap();
+        assert_eq!(event.event_type, "room_created");
+        assert_eq!(body["messages"][0]["attributes"]["event_type"], "room_created");
+    }
+
+    #[tokio::test]
+    async fn test_published_event_carries_instance_id() {
+        let server = MockHttpServer::start().await;
+        server.enqueue_response(200, r#"{"access_token": "sa-token", "expires_in": 3599, "token_type": "Bearer"}"#);
+        let key_path = std::env::temp_dir().join(format!("sa-key-{}.json", uuid::Uuid::new_v4()));
+        let key = json!({
+            "type": "service_account",
+            "client_email": "events@test-project.iam.gserviceaccount.com",
+            "private_key": include_str!("../fixtures/service_account_key.pem"),
+            "token_uri": format!("{}/token", server.url()),
+        });
+        std::fs::write(&key_path, key.to_string()).unwrap();
+        let mut config = pubsub_config(server.url());
+        config.gcp.credentials_path = key_path.to_str().unwrap().to_string();
+        config.server.instance_id = "signal-manager-eu-1".to_string();
+
+        // Built the way the server builds it
+        let client = create_event_client(&config).unwrap();
+        client.publish(EventMessage::new("room_created", json!({"room_id": "r1"}))).await.unwrap();
+        assert!(client.flush().await.is_success());
+        std::fs::remove_file(&key_path).ok();
+
+        let requests = server.requests();
+        let publish = requests.iter().find(|request| request.path.ends_with(":publish")).expect("No publish request");
+        let body: serde_json::Value = serde_json::from_str(&publish.body).unwrap();
+        assert_eq!(body["messages"][0]["attributes"]["instance_id"], "signal-manager-eu-1");
+        let data = body["messages"][0]["data"].as_str().unwrap();
+        let event: EventMessage = serde_json::from_slice(&base64::engine::general_purpose::STANDARD.decode(data).unwrap()).unwrap();
+        assert_eq!(event.instance_id.as_deref(), Some("signal-manager-eu-1"));
+    }
+
+    #[tokio::test]
+    async fn test_publish_failure_surfaces_error() {
+        let server = MockHttpServer::start().await;
+        server.enqueue_response(403, r#"{"error": "forbidden"}"#);
+        let client = GcpPubSubClient::new(&pubsub_config(server.url()), Arc::new(StaticTokenSource::new("t"))).unwrap();
+
+        let result = client.publish(EventMessage::new("room_created", json!({}))).await;
+        assert!(matches!(result, Err(signal_manager_service::Error::PublishError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_publish_retries_transient_failures_with_backoff() {
+        let server = MockHttpServer::start().await;
+        server.enqueue_response(503, r#"{"error": "unavailable"}"#);
+        server.enqueue_response(429, r#"{"error": "rate limited"}"#);
+        let mut config = pubsub_config(server.url());
+        config.events.publish_retry.base_ms = 1;
+        config.events.publish_retry.max_retries = 2;
+        let client = GcpPubSubClient::new(&config, Arc::new(StaticTokenSource::new("t"))).unwrap();
+
+        client.publish(EventMessage::new("room_created", json!({}))).await.unwrap();
+        assert_eq!(server.requests().len(), 3);
+
+        // Retries are exhausted after max_retries
+        for _ in 0..3 {
+            server.enqueue_response(503, r#"{"error": "unavailable"}"#);
+        }
+        let result = client.publish(EventMessage::new("room_created", json!({}))).await;
+        assert!(matches!(result, Err(signal_manager_service::Error::PublishError(_))));
+        assert_eq!(server.requests().len(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_token_is_cached_until_close_to_expiry() {
+        let server = MockHttpServer::start().await;
+        let config = pubsub_config(server.url());
+
+        let long_lived = MockTokenSource::new(chrono::Duration::hours(1));
+        let client = GcpPubSubClient::new(&config, long_lived.clone()).unwrap();
+        client.publish(EventMessage::new("room_created", json!({}))).await.unwrap();
+        client.publish(EventMessage::new("room_created", json!({}))).await.unwrap();
+        assert_eq!(long_lived.fetches(), 1);
+
+        // Tokens inside the refresh margin are replaced before the next publish
+        let expiring = MockTokenSource::new(chrono::Duration::seconds(10));
+        let client = GcpPubSubClient::new(&config, expiring.clone()).unwrap();
+        client.publish(EventMessage::new("room_created", json!({}))).await.unwrap();
+        client.publish(EventMessage::new("room_created", json!({}))).await.unwrap();
+        assert_eq!(expiring.fetches(), 2);
+
+        let requests = server.requests();
+        assert_eq!(requests[2].header("authorization"), Some("Bearer token-1"));
+        assert_eq!(requests[3].header("authorization"), Some("Bearer token-2"));
+    }
+
+    #[tokio::test]
+    async fn test_publish_retries_with_refreshed_token_after_expired_auth() {
+        let server = MockHttpServer::start().await;
+        server.enqueue_response(401, r#"{"error": {"status": "UNAUTHENTICATED"}}"#);
+        let token_source = MockTokenSource::new(chrono::Duration::hours(1));
+        let client = GcpPubSubClient::new(&pubsub_config(server.url()), token_source.clone()).unwrap();
+
+        client.publish(EventMessage::new("room_created", json!({}))).await.unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].header("authorization"), Some("Bearer token-1"));
+        assert_eq!(requests[1].header("authorization"), Some("Bearer token-2"));
+        assert_eq!(token_source.fetches(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_service_account_token_source_exchanges_signed_jwt() {
+        let server = MockHttpServer::start().await;
+        server.enqueue_response(200, r#"{"access_token": "sa-token", "expires_in": 3599, "token_type": "Bearer"}"#);
+
+        let key_path = std::env::temp_dir().join(format!("sa-key-{}.json", uuid::Uuid::new_v4()));
+        let key = json!({
+            "type": "service_account",
+            "client_email": "events@test-project.iam.gserviceaccount.com",
+            "private_key": include_str!("../fixtures/service_account_key.pem"),
+            "token_uri": format!("{}/token", server.url()),
+        });
+        std::fs::write(&key_path, key.to_string()).unwrap();
+
+        let source = ServiceAccountTokenSource::from_file(key_path.to_str().unwrap()).unwrap();
+        let token = source.token().await.unwrap();
+        std::fs::remove_file(&key_path).ok();
+
+        assert_eq!(token.token, "sa-token");
+        assert!(token.expires_at.unwrap() > chrono::Utc::now() + chrono::Duration::minutes(59));
+
+        let requests = server.requests();
+        assert_eq!(requests[0].path, "/token");
+        assert!(requests[0].body.contains("grant_type=urn%3Aietf%3Aparams%3Aoauth%3Agrant-type%3Ajwt-bearer"));
+        let assertion = requests[0].body.split("assertion=").nth(1).unwrap();
+        assert_eq!(assertion.split('.').count(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_batch_is_published_per_topic_in_order() {
+        let server = MockHttpServer::start().await;
+        let client = GcpPubSubClient::new(&pubsub_config(server.url()), Arc::new(StaticTokenSource::new("t"))).unwrap();
+
+        let events = vec![
+            EventMessage::new("room_created", json!({"room_id": "r1"})),
+            EventMessage::new("client_registered", json!({"client_id": "c1"})),
+            EventMessage::new("room_created", json!({"room_id": "r2"})),
+        ];
+        let ids: Vec<String> = events.iter().map(|e| e.id.clone()).collect();
+        let result = client.publish_batch(events).await;
+        assert!(result.is_success());
+        assert_eq!(result.published, ids);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].path, "/projects/test-project/topics/room-events:publish");
+        assert_eq!(requests[1].path, "/projects/test-project/topics/default-events:publish");
+        let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+        let event_ids: Vec<&str> = body["messages"].as_array().unwrap().iter()
+            .map(|m| m["attributes"]["event_id"].as_str().unwrap())
+            .collect();
+        assert_eq!(event_ids, vec![ids[0].as_str(), ids[2].as_str()]);
+    }
+
+    #[tokio::test]
+    async fn test_batch_reports_events_of_failed_topic() {
+        let server = MockHttpServer::start().await;
+        server.enqueue_response(403, r#"{"error": "forbidden"}"#);
+        let client = GcpPubSubClient::new(&pubsub_config(server.url()), Arc::new(StaticTokenSource::new("t"))).unwrap();
+
+        let events = vec![
+            EventMessage::new("room_created", json!({})),
+            EventMessage::new("client_registered", json!({})),
+            EventMessage::new("room_created", json!({})),
+        ];
+        let ids: Vec<String> = events.iter().map(|e| e.id.clone()).collect();
+        let result = client.publish_batch(events).await;
+
+        assert_eq!(result.published, vec![ids[1].clone()]);
+        let failed: Vec<&str> = result.failed.iter().map(|f| f.event_id.as_str()).collect();
+        assert_eq!(failed, vec![ids[0].as_str(), ids[2].as_str()]);
+        assert_eq!(result.failed[0].event_type, "room_created");
+        assert!(result.failed[0].error.contains("403"));
+    }
+
+    #[test]
+    fn test_service_account_token_source_missing_file() {
+        let result = ServiceAccountTokenSource::from_file("/nonexistent/key.json");
+        assert!(matches!(result, Err(signal_manager_service::Error::Auth(_))));
+    }
+}
+
+mod batching {
+    use super::*;
+    use async_trait::async_trait;
+    use signal_manager_service::events::{BatchPublisher, BatchingEventClient, EventResult, FailedEvent};
+    use signal_manager_service::server::WebSocketServer;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    /// Records each batch it is handed, failing events whose type is `fail_type`
+    #[derive(Default)]
+    struct RecordingPublisher {
+        batches: Mutex<Vec<Vec<EventMessage>>>,
+        fail_type: Option<&'static str>,
+    }
+
+    impl RecordingPublisher {
+        fn batch_sizes(&self) -> Vec<usize> {
+            self.batches.lock().unwrap().iter().map(Vec::len).collect()
+        }
+
+        fn published_seqs(&self) -> Vec<i64> {
+            self.batches.lock().unwrap().iter().flatten().map(|e| e.data["seq"].as_i64().unwrap()).collect()
+        }
+
+        async fn wait_for_batches(&self, count: usize) {
+            for _ in 0..100 {
+                if self.batches.lock().unwrap().len() >= count {
+                    return;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+            panic!("Expected {count} batches, got {:?}", self.batch_sizes());
+        }
+    }
+
+    #[async_trait]
+    impl BatchPublisher for RecordingPublisher {
+        async fn publish_batch(&self, events: Vec<EventMessage>) -> EventResult {
+            let mut result = EventResult::default();
+            for event in &events {
+                if Some(event.event_type.as_str()) == self.fail_type {
+                    result.failed.push(FailedEvent {
+                        event_id: event.id.clone(),
+                        event_type: event.event_type.clone(),
+                        error: "rejected".to_string(),
+                    });
+                } else {
+                    result.published.push(event.id.clone());
+                }
+            }
+            self.batches.lock().unwrap().push(events);
+            result
+        }
+    }
+
+    fn seq_event(seq: i64) -> EventMessage {
+        EventMessage::new("heartbeat", json!({"seq": seq}))
+    }
+
+    #[tokio::test]
+    async fn test_batches_flush_when_max_messages_accumulate() {
+        let publisher = Arc::new(RecordingPublisher::default());
+        let client = BatchingEventClient::new(publisher.clone(), 3, Duration::ZERO);
+
+        for seq in 0..7 {
+            client.publish(seq_event(seq)).await.unwrap();
+        }
+        assert_eq!(publisher.batch_sizes(), vec![3, 3]);
+        assert_eq!(client.pending(), 1);
+
+        let result = client.flush().await;
+        assert!(result.is_success());
+        assert_eq!(result.published.len(), 1);
+        assert_eq!(publisher.batch_sizes(), vec![3, 3, 1]);
+        assert_eq!(publisher.published_seqs(), (0..7).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_batches_flush_on_interval() {
+        let publisher = Arc::new(RecordingPublisher::default());
+        let client = BatchingEventClient::new(publisher.clone(), 100, Duration::from_millis(50));
+
+        client.publish(seq_event(0)).await.unwrap();
+        client.publish(seq_event(1)).await.unwrap();
+        assert!(publisher.batch_sizes().is_empty());
+
+        publisher.wait_for_batches(1).await;
+        assert_eq!(publisher.batch_sizes(), vec![2]);
+        assert_eq!(client.pending(), 0);
+
+        // Idle intervals publish nothing
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(publisher.batch_sizes(), vec![2]);
+    }
+
+    #[tokio::test]
+    async fn test_partial_batch_failure_is_reported() {
+        let publisher = Arc::new(RecordingPublisher { fail_type: Some("room_created"), ..Default::default() });
+        let client = BatchingEventClient::new(publisher.clone(), 3, Duration::ZERO);
+
+        let rejected = EventMessage::new("room_created", json!({}));
+        let rejected_id = rejected.id.clone();
+        client.publish(seq_event(0)).await.unwrap();
+        client.publish(rejected).await.unwrap();
+        let result = client.publish(seq_event(2)).await;
+        assert!(matches!(result, Err(signal_manager_service::Error::PublishError(_))));
+
+        client.publish(EventMessage::new("room_created", json!({}))).await.unwrap();
+        let result = client.flush().await;
+        assert!(!result.is_success());
+        assert!(result.published.is_empty());
+        assert_eq!(result.failed.len(), 1);
+        assert_ne!(result.failed[0].event_id, rejected_id);
+        assert_eq!(result.failed[0].error, "rejected");
+    }
+
+    #[tokio::test]
+    async fn test_server_shutdown_flushes_partial_batch() {
+        let publisher = Arc::new(RecordingPublisher::default());
+        let client = BatchingEventClient::new(publisher.clone(), 100, Duration::ZERO);
+        let server = WebSocketServer::new(Config::default()).unwrap().with_event_client(client.clone());
+
+        client.publish(seq_event(0)).await.unwrap();
+        client.publish(seq_event(1)).await.unwrap();
+        assert!(publisher.batch_sizes().is_empty());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        server.serve_with_shutdown(listener, async {}).await.unwrap();
+
+        assert_eq!(publisher.batch_sizes(), vec![2]);
+        assert_eq!(publisher.published_seqs(), vec![0, 1]);
+        assert_eq!(client.pending(), 0);
+    }
+}
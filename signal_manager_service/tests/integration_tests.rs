@@ -2,10 +2,17 @@
 mod message;
 mod config;
 mod auth;
+mod backoff;
 mod protocol;
 mod server;
+mod outbound;
+mod webrtc_handlers;
+mod type_two_handlers;
+mod events;
+mod support;
 mod database;
 mod cloudflare_session_unit;
+mod cloudflare;
 
 // The modules are automatically discovered by Rust's test runner
 // No need to re-export them explicitly 
\ No newline at end of file
@@ -45,4 +45,183 @@ fn test_payload_types() {
     assert_eq!(PayloadType::Text as u8, 0x03);
     assert_eq!(PayloadType::Protobuf as u8, 0x04);
     assert_eq!(PayloadType::Cbor as u8, 0x05);
-} 
\ No newline at end of file
+}
+
+#[test]
+fn test_payload_kind_matches_message_type() {
+    use signal_manager_service::message::{HeartbeatPayload, SignalPayload};
+
+    let payload = Payload::Connect(ConnectPayload {
+        client_id: "test_client".to_string(),
+        auth_token: "test_token".to_string(),
+    });
+    let message = Message::new(MessageType::Connect, payload);
+    assert_eq!(message.payload.kind(), message.message_type);
+
+    let heartbeat = Message::new(MessageType::Heartbeat, Payload::Heartbeat(HeartbeatPayload { timestamp: 0 }));
+    assert_eq!(heartbeat.payload.kind(), MessageType::Heartbeat);
+
+    let mismatched = Message::new(MessageType::SignalOffer, Payload::SignalAnswer(SignalPayload {
+        target_client_id: "peer".to_string(),
+        signal_data: "sdp".to_string(),
+        room_id: None,
+        sequence: None,
+    }));
+    assert_eq!(mismatched.payload.kind(), MessageType::SignalAnswer);
+    assert_ne!(mismatched.payload.kind(), mismatched.message_type);
+}
+
+#[test]
+fn test_signal_data_base64_check() {
+    use signal_manager_service::message::SignalPayload;
+
+    let signal = |signal_data: &str| SignalPayload {
+        target_client_id: "peer".to_string(),
+        signal_data: signal_data.to_string(),
+        room_id: None,
+        sequence: None,
+    };
+    assert!(signal("YmFzZTY0X2VuY29kZWRfc2lnbmFsX2RhdGE=").has_base64_signal_data());
+    assert!(signal("").has_base64_signal_data());
+    assert!(!signal("not base64!").has_base64_signal_data());
+    assert!(!signal("YWJj=").has_base64_signal_data());
+}
+
+fn connect_frame(client_id: &str, auth_token: &str) -> Vec<u8> {
+    Message::new(
+        MessageType::Connect,
+        Payload::Connect(ConnectPayload {
+            client_id: client_id.to_string(),
+            auth_token: auth_token.to_string(),
+        }),
+    )
+    .to_binary()
+    .unwrap()
+}
+
+#[test]
+fn test_strict_parse_rejects_empty_fields() {
+    let frame = connect_frame("", "test_token");
+    // Lenient parsing still accepts the structurally valid payload
+    assert!(Message::from_binary(&frame).is_ok());
+    match Message::from_binary_strict(&frame) {
+        Err(signal_manager_service::Error::InvalidPayload { field, .. }) => {
+            assert_eq!(field, "client_id")
+        }
+        other => panic!("Expected InvalidPayload, got {:?}", other),
+    }
+
+    match Message::from_binary_strict(&connect_frame("test_client", "  ")) {
+        Err(signal_manager_service::Error::InvalidPayload { field, .. }) => {
+            assert_eq!(field, "auth_token")
+        }
+        other => panic!("Expected InvalidPayload, got {:?}", other),
+    }
+
+    assert!(Message::from_binary_strict(&connect_frame("test_client", "test_token")).is_ok());
+}
+
+#[test]
+fn test_strict_parse_validates_every_codec() {
+    use signal_manager_service::message::PayloadType;
+
+    for payload_type in [PayloadType::Binary, PayloadType::Text, PayloadType::Protobuf, PayloadType::Cbor, PayloadType::JsonGzip] {
+        let connect = Message {
+            payload_type,
+            ..Message::new(MessageType::Connect, Payload::Connect(ConnectPayload {
+                client_id: "test_client".to_string(),
+                auth_token: "  ".to_string(),
+            }))
+        };
+        let frame = connect.to_binary().unwrap();
+        assert!(Message::from_binary(&frame).is_ok(), "{:?} frame failed to decode", payload_type);
+        match Message::from_binary_strict(&frame) {
+            Err(signal_manager_service::Error::InvalidPayload { field, .. }) => assert_eq!(field, "auth_token"),
+            other => panic!("Expected InvalidPayload for {:?}, got {:?}", payload_type, other),
+        }
+    }
+}
+
+#[test]
+fn test_strict_parse_checks_room_roles() {
+    use signal_manager_service::message::{WebRTCRoomCreatePayload, WebRTCRoomJoinPayload};
+
+    let create = |role: &str, offer_sdp: Option<&str>| {
+        Message::new(
+            MessageType::WebRTCRoomCreate,
+            Payload::WebRTCRoomCreate(WebRTCRoomCreatePayload {
+                version: "1.0.0".to_string(),
+                client_id: "test_client".to_string(),
+                auth_token: "test_token".to_string(),
+                role: role.to_string(),
+                offer_sdp: offer_sdp.map(str::to_string),
+                max_participants: None,
+                metadata: None,
+            }),
+        )
+        .to_binary()
+        .unwrap()
+    };
+    let field_of = |frame: &[u8]| match Message::from_binary_strict(frame) {
+        Err(signal_manager_service::Error::InvalidPayload { field, .. }) => field,
+        other => panic!("Expected InvalidPayload, got {:?}", other),
+    };
+
+    assert_eq!(field_of(&create("observer", None)), "role");
+    assert_eq!(field_of(&create("sender", None)), "offer_sdp");
+    assert!(Message::from_binary_strict(&create("Sender", Some("sdp"))).is_ok());
+    assert!(Message::from_binary_strict(&create("receiver", None)).is_ok());
+
+    let join = Message::new(
+        MessageType::WebRTCRoomJoin,
+        Payload::WebRTCRoomJoin(WebRTCRoomJoinPayload {
+            version: "1.0.0".to_string(),
+            client_id: "test_client".to_string(),
+            auth_token: "test_token".to_string(),
+            room_id: String::new(),
+            role: "observer".to_string(),
+            offer_sdp: None,
+            metadata: None,
+        }),
+    )
+    .to_binary()
+    .unwrap();
+    assert_eq!(field_of(&join), "room_id");
+}
+
+#[test]
+fn test_validate_id_limits_length_and_firestore_forms() {
+    use signal_manager_service::message::validate_id;
+
+    let reason_of = |value: &str| match validate_id("room_id", value, 16) {
+        Err(signal_manager_service::Error::InvalidPayload { field, reason }) => {
+            assert_eq!(field, "room_id");
+            reason
+        }
+        other => panic!("Expected InvalidPayload for {:?}, got {:?}", value, other),
+    };
+
+    assert!(validate_id("room_id", &"a".repeat(16), 16).is_ok());
+    assert_eq!(reason_of(&"a".repeat(17)), "must be at most 16 bytes");
+    assert_eq!(reason_of("rooms/room_1"), "must not contain '/'");
+    assert_eq!(reason_of(".."), "is a reserved id");
+    assert_eq!(reason_of("__room__"), "is a reserved id");
+    assert!(validate_id("room_id", "__room", 16).is_ok());
+    // 0 lifts the length limit but not the character rules
+    assert!(validate_id("room_id", &"a".repeat(2000), 0).is_ok());
+    assert!(validate_id("room_id", "a/b", 0).is_err());
+}
+
+#[test]
+fn test_validate_ids_checks_connect_client_id() {
+    let connect = |client_id: &str| Payload::Connect(ConnectPayload {
+        client_id: client_id.to_string(),
+        auth_token: "test_token".to_string(),
+    });
+
+    assert!(connect("test_client").validate_ids(128).is_ok());
+    assert!(connect(&"c".repeat(129)).validate_ids(128).is_err());
+    assert!(connect("tenant/client").validate_ids(128).is_err());
+    // Messages without ids are not checked
+    assert!(Payload::ServerInfo(Default::default()).validate_ids(1).is_ok());
+}
@@ -0,0 +1,130 @@
+use signal_manager_service::config::OverflowPolicy;
+use signal_manager_service::message::{HeartbeatAckPayload, HeartbeatPayload, Message, MessageType, Payload, ServerInfoPayload};
+use signal_manager_service::outbound::OutboundQueue;
+
+fn frame(n: u64) -> Message {
+    Message::new(MessageType::Heartbeat, Payload::Heartbeat(HeartbeatPayload { timestamp: n }))
+}
+
+fn timestamp(message: &Message) -> u64 {
+    match &message.payload {
+        Payload::Heartbeat(p) => p.timestamp,
+        other => panic!("Unexpected payload: {:?}", other),
+    }
+}
+
+fn bulk() -> Message {
+    Message::new(MessageType::ServerInfo, Payload::ServerInfo(ServerInfoPayload::default()))
+}
+
+fn heartbeat_ack(n: u64) -> Message {
+    Message::new(MessageType::HeartbeatAck, Payload::HeartbeatAck(HeartbeatAckPayload { timestamp: n }))
+}
+
+async fn drain(queue: &OutboundQueue) -> Vec<u64> {
+    let mut out = Vec::new();
+    while !queue.is_empty() {
+        out.push(timestamp(&queue.pop().await.unwrap()));
+    }
+    out
+}
+
+#[tokio::test]
+async fn test_drop_newest_keeps_queued_frames() {
+    let queue = OutboundQueue::new(3, OverflowPolicy::DropNewest);
+    for n in 0..5 {
+        assert!(queue.push(frame(n)).is_ok());
+    }
+
+    assert_eq!(queue.dropped_count(), 2);
+    assert_eq!(drain(&queue).await, vec![0, 1, 2]);
+}
+
+#[tokio::test]
+async fn test_drop_oldest_keeps_latest_frames() {
+    let queue = OutboundQueue::new(3, OverflowPolicy::DropOldest);
+    for n in 0..5 {
+        assert!(queue.push(frame(n)).is_ok());
+    }
+
+    assert_eq!(queue.dropped_count(), 2);
+    assert_eq!(drain(&queue).await, vec![2, 3, 4]);
+}
+
+#[tokio::test]
+async fn test_disconnect_closes_queue_on_overflow() {
+    let queue = OutboundQueue::new(3, OverflowPolicy::Disconnect);
+    for n in 0..3 {
+        assert!(queue.push(frame(n)).is_ok());
+    }
+
+    assert!(queue.push(frame(3)).is_err());
+    assert!(queue.is_closed());
+    assert_eq!(queue.dropped_count(), 1);
+    assert!(queue.pop().await.is_none());
+    assert!(queue.push(frame(4)).is_err());
+}
+
+#[tokio::test]
+async fn test_pop_waits_for_push() {
+    let queue = std::sync::Arc::new(OutboundQueue::new(3, OverflowPolicy::DropNewest));
+    let consumer = {
+        let queue = queue.clone();
+        tokio::spawn(async move { queue.pop().await.map(|m| timestamp(&m)) })
+    };
+
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    queue.push(frame(7)).unwrap();
+    assert_eq!(consumer.await.unwrap(), Some(7));
+}
+
+#[tokio::test]
+async fn test_control_frame_overtakes_bulk_flood() {
+    let queue = OutboundQueue::new(50, OverflowPolicy::DropNewest);
+    for _ in 0..200 {
+        queue.push(bulk()).unwrap();
+    }
+    queue.push(heartbeat_ack(9)).unwrap();
+
+    // The queue is full and shedding bulk frames, but the ack displaces one and jumps ahead
+    assert_eq!(queue.dropped_count(), 151);
+    assert_eq!(queue.len(), 50);
+    let next = tokio::time::timeout(std::time::Duration::from_millis(100), queue.pop()).await.unwrap().unwrap();
+    assert!(matches!(next.payload, Payload::HeartbeatAck(HeartbeatAckPayload { timestamp: 9 })), "Unexpected frame: {:?}", next);
+}
+
+#[tokio::test]
+async fn test_control_and_bulk_frames_share_one_budget() {
+    let queue = OutboundQueue::new(4, OverflowPolicy::DropNewest);
+    for n in 0..2 {
+        queue.push(heartbeat_ack(n)).unwrap();
+        queue.push(bulk()).unwrap();
+    }
+    // Full with two of each: a further bulk frame is dropped rather than buffered in its lane
+    queue.push(bulk()).unwrap();
+    assert_eq!(queue.len(), 4);
+    assert_eq!(queue.dropped_count(), 1);
+
+    // Control frames never grow the queue past its budget either
+    for n in 2..10 {
+        queue.push(heartbeat_ack(n)).unwrap();
+    }
+    assert_eq!(queue.len(), 4);
+    for _ in 0..4 {
+        assert_eq!(queue.pop().await.unwrap().message_type, MessageType::HeartbeatAck);
+    }
+}
+
+#[tokio::test]
+async fn test_without_control_priority_frames_keep_push_order() {
+    let queue = OutboundQueue::new(10, OverflowPolicy::DropNewest).with_control_priority(false);
+    for _ in 0..3 {
+        queue.push(bulk()).unwrap();
+    }
+    queue.push(heartbeat_ack(9)).unwrap();
+
+    for _ in 0..3 {
+        assert_eq!(queue.pop().await.unwrap().message_type, MessageType::ServerInfo);
+    }
+    assert_eq!(queue.pop().await.unwrap().message_type, MessageType::HeartbeatAck);
+}
@@ -1,6 +1,8 @@
 use signal_manager_service::message::{
     Message, MessageType, Payload, PayloadType, ConnectPayload, ConnectAckPayload,
-    SignalPayload, ErrorPayload, HeartbeatPayload
+    SignalPayload, ErrorPayload, HeartbeatPayload, AppRelayPayload, WebRTCRoomCreatePayload,
+    RegisterPayload,
+    PAYLOAD_TYPE_ZSTD_FLAG,
 };
 
 #[test]
@@ -16,7 +18,7 @@ fn test_protocol_message_structure() {
     
     // Validate protocol structure:
     // [Start Byte (1 byte)] [Message Type (1 byte)] [Message UUID (16 bytes)] 
-    // [Payload Type (1 byte)] [Payload Length (2 bytes)] [Payload (N bytes)]
+    // [Payload Type (1 byte)] [Payload Length (4 bytes)] [Payload (N bytes)]
     
     // Start byte validation
     assert_eq!(binary[0], signal_manager_service::message::START_BYTE);
@@ -30,12 +32,12 @@ fn test_protocol_message_structure() {
     // Payload type validation
     assert_eq!(binary[18], PayloadType::Json as u8);
     
-    // Payload length validation (2 bytes, big endian)
-    let payload_length = u16::from_be_bytes([binary[19], binary[20]]) as usize;
-    assert_eq!(payload_length, binary[21..].len());
+    // Payload length validation (4 bytes, big endian)
+    let payload_length = u32::from_be_bytes(binary[19..23].try_into().unwrap()) as usize;
+    assert_eq!(payload_length, binary[23..].len());
     
-    // Total message length should be 21 + payload_length
-    assert_eq!(binary.len(), 21 + payload_length);
+    // Total message length should be 23 + payload_length
+    assert_eq!(binary.len(), 23 + payload_length);
 }
 
 #[test]
@@ -46,6 +48,15 @@ fn test_protocol_message_types() {
     assert_eq!(MessageType::Disconnect as u8, 0x03);
     assert_eq!(MessageType::Heartbeat as u8, 0x04);
     assert_eq!(MessageType::HeartbeatAck as u8, 0x05);
+    assert_eq!(MessageType::ServerInfo as u8, 0x06);
+    assert_eq!(MessageType::ServerInfoAck as u8, 0x07);
+    assert_eq!(MessageType::WhereAmI as u8, 0x08);
+    assert_eq!(MessageType::WhereAmIAck as u8, 0x09);
+    assert_eq!(MessageType::ClientStatusQuery as u8, 0x0A);
+    assert_eq!(MessageType::ClientStatusQueryAck as u8, 0x0B);
+    assert_eq!(MessageType::WebRTCRoomList as u8, 0x36);
+    assert_eq!(MessageType::WebRTCRoomListAck as u8, 0x37);
+    assert_eq!(MessageType::AppRelay as u8, 0x40);
     assert_eq!(MessageType::SignalOffer as u8, 0x10);
     assert_eq!(MessageType::SignalAnswer as u8, 0x11);
     assert_eq!(MessageType::SignalIceCandidate as u8, 0x12);
@@ -60,6 +71,7 @@ fn test_protocol_payload_types() {
     assert_eq!(PayloadType::Text as u8, 0x03);
     assert_eq!(PayloadType::Protobuf as u8, 0x04);
     assert_eq!(PayloadType::Cbor as u8, 0x05);
+    assert_eq!(PayloadType::JsonGzip as u8, 0x06);
 }
 
 #[test]
@@ -96,6 +108,8 @@ fn test_protocol_connect_ack_message() {
     let payload = Payload::ConnectAck(ConnectAckPayload {
         status: "success".to_string(),
         session_id: "session_123".to_string(),
+        heartbeat_interval: Some(30),
+        session_timeout: None,
     });
     
     let message = Message::new(MessageType::ConnectAck, payload);
@@ -116,6 +130,8 @@ fn test_protocol_signal_message() {
     let payload = Payload::SignalOffer(SignalPayload {
         target_client_id: "target_client".to_string(),
         signal_data: "base64_encoded_signal_data".to_string(),
+        room_id: None,
+        sequence: None,
     });
     
     let message = Message::new(MessageType::SignalOffer, payload);
@@ -136,6 +152,7 @@ fn test_protocol_error_message() {
     let payload = Payload::Error(ErrorPayload {
         error_code: 1,
         error_message: "Authentication failed".to_string(),
+        ..Default::default()
     });
     
     let message = Message::new(MessageType::Error, payload);
@@ -173,23 +190,23 @@ fn test_protocol_heartbeat_message() {
 #[test]
 fn test_protocol_invalid_message_handling() {
     // Test handling of invalid start byte
-    let invalid_data = vec![0x00; 22]; // Wrong start byte
+    let invalid_data = vec![0x00; 24]; // Wrong start byte
     assert!(Message::from_binary(&invalid_data).is_err());
     
     // Test handling of message too short
-    let short_data = vec![0xAA, 0x01]; // Only start byte and message type
+    let short_data = vec![0xAC, 0x01]; // Only start byte and message type
     assert!(Message::from_binary(&short_data).is_err());
     
     // Test handling of invalid message type
-    let mut invalid_type_data = vec![0xAA, 0x99]; // Invalid message type
-    invalid_type_data.extend_from_slice(&vec![0x00; 20]); // Rest of required bytes
+    let mut invalid_type_data = vec![0xAC, 0x99]; // Invalid message type
+    invalid_type_data.extend_from_slice(&vec![0x00; 22]); // Rest of required bytes
     assert!(Message::from_binary(&invalid_type_data).is_err());
     
     // Test handling of invalid payload type
-    let mut invalid_payload_data = vec![0xAA, 0x01]; // Valid start and message type
+    let mut invalid_payload_data = vec![0xAC, 0x01]; // Valid start and message type
     invalid_payload_data.extend_from_slice(&vec![0x00; 16]); // UUID
     invalid_payload_data.push(0x99); // Invalid payload type
-    invalid_payload_data.extend_from_slice(&[0x00, 0x00]); // Payload length
+    invalid_payload_data.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]); // Payload length
     assert!(Message::from_binary(&invalid_payload_data).is_err());
 }
 
@@ -204,15 +221,13 @@ fn test_protocol_payload_length_validation() {
     let mut binary = message.to_binary().expect("Failed to serialize");
     
     // Test with mismatched payload length
-    binary[19] = 0xFF;
-    binary[20] = 0xFF; // Set payload length to maximum
+    binary[19..23].copy_from_slice(&[0xFF; 4]); // Set payload length to maximum
     
     assert!(Message::from_binary(&binary).is_err());
     
     // Test with zero payload length
     let mut zero_length_binary = message.to_binary().expect("Failed to serialize");
-    zero_length_binary[19] = 0x00;
-    zero_length_binary[20] = 0x00;
+    zero_length_binary[19..23].copy_from_slice(&[0x00; 4]);
     
     // This should still work for empty payloads
     let _result = Message::from_binary(&zero_length_binary);
@@ -255,6 +270,770 @@ fn test_protocol_message_size_limits() {
     assert_eq!(deserialized.message_type, MessageType::Connect);
     
     // Verify payload length is correctly encoded
-    let payload_length = u16::from_be_bytes([binary[19], binary[20]]) as usize;
-    assert_eq!(payload_length, binary[21..].len());
-} 
\ No newline at end of file
+    let payload_length = u32::from_be_bytes(binary[19..23].try_into().unwrap()) as usize;
+    assert_eq!(payload_length, binary[23..].len());
+} 
+#[test]
+fn test_protocol_created_at_round_trip() {
+    let created_at = 1_700_000_000_123u64;
+    let message = Message::new(
+        MessageType::Heartbeat,
+        Payload::Heartbeat(HeartbeatPayload { timestamp: 1_700_000_000 }),
+    ).with_created_at(created_at);
+
+    let binary = message.to_binary().expect("Failed to serialize");
+    assert_eq!(binary[0], signal_manager_service::message::START_BYTE_TIMESTAMPED);
+    assert_eq!(&binary[18..26], &created_at.to_be_bytes());
+    assert_eq!(binary[26], PayloadType::Json as u8);
+    let payload_length = u32::from_be_bytes(binary[27..31].try_into().unwrap()) as usize;
+    assert_eq!(binary.len(), 31 + payload_length);
+
+    let decoded = Message::from_binary(&binary).expect("Failed to deserialize");
+    assert_eq!(decoded.uuid, message.uuid);
+    assert_eq!(decoded.created_at, Some(created_at));
+    assert!(matches!(decoded.payload, Payload::Heartbeat(HeartbeatPayload { timestamp: 1_700_000_000 })));
+}
+
+#[test]
+fn test_protocol_untimestamped_frame_has_no_created_at() {
+    let message = Message::new(
+        MessageType::Heartbeat,
+        Payload::Heartbeat(HeartbeatPayload { timestamp: 1 }),
+    );
+    let binary = message.to_binary().expect("Failed to serialize");
+    assert_eq!(binary[0], signal_manager_service::message::START_BYTE);
+
+    let decoded = Message::from_binary(&binary).expect("Failed to deserialize");
+    assert_eq!(decoded.created_at, None);
+    assert_eq!(decoded.latency_ms(signal_manager_service::message::now_millis()), None);
+}
+
+#[test]
+fn test_protocol_created_at_latency() {
+    let message = Message::new(
+        MessageType::Heartbeat,
+        Payload::Heartbeat(HeartbeatPayload { timestamp: 1 }),
+    ).with_created_at(10_000);
+
+    assert_eq!(message.latency_ms(10_250), Some(250));
+    assert_eq!(message.clock_skew_ms(10_250), Some(250));
+    // A sender clock ahead of the server has no latency, only skew
+    assert_eq!(message.latency_ms(9_000), None);
+    assert_eq!(message.clock_skew_ms(9_000), Some(1_000));
+
+    // Client-controlled timestamps at the extremes must not overflow
+    let far_future = message.clone().with_created_at(u64::MAX);
+    assert_eq!(far_future.latency_ms(10_000), None);
+    assert_eq!(far_future.clock_skew_ms(10_000), Some(u64::MAX - 10_000));
+    let epoch = message.with_created_at(0);
+    assert_eq!(epoch.latency_ms(u64::MAX), Some(u64::MAX));
+
+    let sent = Message::new(
+        MessageType::Heartbeat,
+        Payload::Heartbeat(HeartbeatPayload { timestamp: 1 }),
+    ).with_created_at(signal_manager_service::message::now_millis());
+    let received = Message::from_binary(&sent.to_binary().unwrap()).unwrap();
+    let latency = received.latency_ms(signal_manager_service::message::now_millis()).unwrap();
+    assert!((0..5_000).contains(&latency), "unexpected latency {latency}ms");
+}
+
+#[test]
+fn test_protocol_timestamped_frame_too_short() {
+    let message = Message::new(
+        MessageType::Heartbeat,
+        Payload::Heartbeat(HeartbeatPayload { timestamp: 1 }),
+    ).with_created_at(10_000);
+    let binary = message.to_binary().unwrap();
+    assert!(Message::from_binary(&binary[..25]).is_err());
+}
+
+#[test]
+fn test_protocol_all_message_types_round_trip() {
+    for message_type in MessageType::ALL {
+        assert_eq!(MessageType::from_u8(message_type as u8).unwrap(), message_type);
+    }
+}
+
+#[test]
+fn test_protocol_message_type_bytes_are_unique_and_complete() {
+    // A new type must take an unused byte and be listed in `MessageType::ALL`
+    let assigned: std::collections::HashSet<u8> = MessageType::ALL.iter().map(|t| *t as u8).collect();
+    assert_eq!(assigned.len(), MessageType::ALL.len(), "Two message types share a byte value");
+
+    for byte in 0..=u8::MAX {
+        match MessageType::from_u8(byte) {
+            Ok(message_type) => assert!(assigned.contains(&byte), "{:?} (0x{:02X}) decodes but is missing from MessageType::ALL", message_type, byte),
+            Err(signal_manager_service::Error::InvalidMessageType(value)) => assert_eq!(value, byte),
+            Err(e) => panic!("Unexpected error for 0x{:02X}: {}", byte, e),
+        }
+    }
+}
+
+#[test]
+fn test_protocol_app_relay_binary_payload_bytes() {
+    let mut message = Message::new(MessageType::AppRelay, Payload::AppRelay(AppRelayPayload {
+        room_id: "room".to_string(),
+        from_client_id: Some("alice".to_string()),
+        data: vec![0x00, 0xAA, 0xFF],
+    }));
+    message.payload_type = PayloadType::Binary;
+    let binary = message.to_binary().unwrap();
+
+    assert_eq!(binary[1], 0x40);
+    assert_eq!(binary[18], PayloadType::Binary as u8);
+    assert_eq!(u32::from_be_bytes(binary[19..23].try_into().unwrap()), 14);
+    assert_eq!(&binary[23..], &[4, b'r', b'o', b'o', b'm', 5, b'a', b'l', b'i', b'c', b'e', 0x00, 0xAA, 0xFF]);
+
+    let decoded = Message::from_binary(&binary).unwrap();
+    match decoded.payload {
+        Payload::AppRelay(payload) => {
+            assert_eq!(payload.room_id, "room");
+            assert_eq!(payload.from_client_id.as_deref(), Some("alice"));
+            assert_eq!(payload.data, vec![0x00, 0xAA, 0xFF]);
+        }
+        other => panic!("Expected AppRelay payload, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_protocol_app_relay_binary_without_sender() {
+    let mut message = Message::new(MessageType::AppRelay, Payload::AppRelay(AppRelayPayload {
+        room_id: "r".to_string(),
+        from_client_id: None,
+        data: Vec::new(),
+    }));
+    message.payload_type = PayloadType::Binary;
+    let binary = message.to_binary().unwrap();
+    assert_eq!(&binary[23..], &[1, b'r', 0]);
+
+    match Message::from_binary(&binary).unwrap().payload {
+        Payload::AppRelay(payload) => {
+            assert_eq!(payload.from_client_id, None);
+            assert!(payload.data.is_empty());
+        }
+        other => panic!("Expected AppRelay payload, got {:?}", other),
+    }
+
+    // Sender length running past the end of the payload
+    let mut truncated = binary.clone();
+    truncated[25] = 9;
+    assert!(Message::from_binary(&truncated).is_err());
+}
+
+#[test]
+fn test_protocol_register_binary_round_trips_capabilities_and_metadata() {
+    let payload = RegisterPayload {
+        version: "1.0.0".to_string(),
+        client_id: "client".to_string(),
+        auth_token: "token".to_string(),
+        capabilities: Some(vec!["websocket".to_string(), "heartbeat".to_string()]),
+        metadata: Some(serde_json::json!({ "device": "laptop" })),
+    };
+    let mut message = Message::new(MessageType::Register, Payload::Register(payload.clone()));
+    message.payload_type = PayloadType::Binary;
+    let binary = message.to_binary().unwrap();
+    assert_eq!(&binary[23..29], &[5, b'1', b'.', b'0', b'.', b'0']);
+
+    match Message::from_binary(&binary).unwrap().payload {
+        Payload::Register(decoded) => {
+            assert_eq!(decoded.client_id, payload.client_id);
+            assert_eq!(decoded.auth_token, payload.auth_token);
+            assert_eq!(decoded.capabilities, payload.capabilities);
+            assert_eq!(decoded.metadata, payload.metadata);
+        }
+        other => panic!("Expected Register payload, got {:?}", other),
+    }
+
+    // A capability length running past the end of the payload
+    let mut truncated = binary.clone();
+    let count_at = 23 + 6 + 7 + 6;
+    assert_eq!(binary[count_at], 2);
+    truncated[count_at + 1] = 200;
+    assert!(Message::from_binary(&truncated).is_err());
+}
+
+#[test]
+fn test_protocol_cbor_round_trips_every_field() {
+    let payload = SignalPayload {
+        target_client_id: "peer".to_string(),
+        signal_data: "v=0\r\n".to_string(),
+        room_id: Some("room".to_string()),
+        sequence: Some(7),
+    };
+    let mut message = Message::new(MessageType::SignalOffer, Payload::SignalOffer(payload.clone()));
+    message.payload_type = PayloadType::Cbor;
+    let binary = message.to_binary().unwrap();
+    assert_eq!(binary[18], PayloadType::Cbor as u8);
+
+    let decoded = Message::from_binary(&binary).unwrap();
+    assert_eq!(decoded.payload_type, PayloadType::Cbor);
+    match decoded.payload {
+        Payload::SignalOffer(decoded) => {
+            assert_eq!(decoded.target_client_id, payload.target_client_id);
+            assert_eq!(decoded.signal_data, payload.signal_data);
+            assert_eq!(decoded.room_id, payload.room_id);
+            assert_eq!(decoded.sequence, payload.sequence);
+        }
+        other => panic!("Expected SignalOffer payload, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_protocol_app_relay_binary_rejects_ids_over_255_bytes() {
+    for (room_id, from_client_id, field) in [
+        ("r".repeat(256), None, "room_id"),
+        ("r".to_string(), Some("a".repeat(256)), "from_client_id"),
+    ] {
+        let mut message = Message::new(MessageType::AppRelay, Payload::AppRelay(AppRelayPayload {
+            room_id,
+            from_client_id,
+            data: vec![0x01],
+        }));
+        message.payload_type = PayloadType::Binary;
+        match message.to_binary() {
+            Err(signal_manager_service::Error::InvalidPayload { field: rejected, .. }) => assert_eq!(rejected, field),
+            other => panic!("Expected {field} to be rejected, got {:?}", other.map(|b| b.len())),
+        }
+    }
+
+    // 255 bytes still fits the length prefix
+    let mut message = Message::new(MessageType::AppRelay, Payload::AppRelay(AppRelayPayload {
+        room_id: "r".repeat(255),
+        from_client_id: None,
+        data: Vec::new(),
+    }));
+    message.payload_type = PayloadType::Binary;
+    match Message::from_binary(&message.to_binary().unwrap()).unwrap().payload {
+        Payload::AppRelay(payload) => assert_eq!(payload.room_id.len(), 255),
+        other => panic!("Expected AppRelay payload, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_protocol_app_relay_json_data_is_base64() {
+    let message = Message::new(MessageType::AppRelay, Payload::AppRelay(AppRelayPayload {
+        room_id: "room".to_string(),
+        from_client_id: None,
+        data: b"hello".to_vec(),
+    }));
+    let binary = message.to_binary().unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&binary[23..]).unwrap();
+    assert_eq!(json["AppRelay"]["data"], "aGVsbG8=");
+    assert!(json["AppRelay"].get("from_client_id").is_none());
+
+    match Message::from_binary(&binary).unwrap().payload {
+        Payload::AppRelay(payload) => assert_eq!(payload.data, b"hello"),
+        other => panic!("Expected AppRelay payload, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_protocol_large_json_payload_compressed_round_trip() {
+    let offer = Message::new(
+        MessageType::SignalOffer,
+        Payload::SignalOffer(SignalPayload {
+            target_client_id: "peer".to_string(),
+            signal_data: "v=0\r\na=candidate:1 1 udp 2122260223 192.0.2.1 54400 typ host\r\n".repeat(100),
+            room_id: Some("room".to_string()),
+            sequence: Some(7),
+        }),
+    );
+    let uncompressed = offer.to_binary().unwrap();
+    let compressed = offer.to_binary_compressed(1024).unwrap();
+
+    assert_eq!(compressed[18], PayloadType::Json as u8 | PAYLOAD_TYPE_ZSTD_FLAG);
+    assert!(compressed.len() < uncompressed.len() / 4, "{} bytes compressed vs {}", compressed.len(), uncompressed.len());
+
+    let decoded = Message::from_binary(&compressed).unwrap();
+    assert_eq!(decoded.uuid, offer.uuid);
+    assert_eq!(decoded.payload_type, PayloadType::Json);
+    match (decoded.payload, offer.payload) {
+        (Payload::SignalOffer(decoded), Payload::SignalOffer(original)) => {
+            assert_eq!(decoded.signal_data, original.signal_data);
+            assert_eq!(decoded.room_id, original.room_id);
+            assert_eq!(decoded.sequence, original.sequence);
+        }
+        other => panic!("Expected SignalOffer payloads, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_protocol_small_payload_stays_uncompressed() {
+    let connect = Message::new(
+        MessageType::Connect,
+        Payload::Connect(ConnectPayload {
+            client_id: "client".to_string(),
+            auth_token: "token".to_string(),
+        }),
+    );
+    assert_eq!(connect.to_binary_compressed(1024).unwrap(), connect.to_binary().unwrap());
+    // A zero threshold disables compression whatever the size
+    assert_eq!(connect.to_binary_compressed(0).unwrap()[18], PayloadType::Json as u8);
+}
+
+#[test]
+fn test_protocol_compressed_payload_inflating_past_limit_rejected() {
+    use std::io::Write;
+    use signal_manager_service::message::MAX_DECOMPRESSED_PAYLOAD;
+
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+    encoder.write_all(&vec![b' '; MAX_DECOMPRESSED_PAYLOAD + 1]).unwrap();
+    let bomb = encoder.finish().unwrap();
+
+    let mut frame = vec![signal_manager_service::message::START_BYTE, MessageType::Connect as u8];
+    frame.extend_from_slice(uuid::Uuid::new_v4().as_bytes());
+    frame.push(PayloadType::JsonGzip as u8);
+    frame.extend_from_slice(&(bomb.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&bomb);
+    assert!(matches!(Message::from_binary(&frame), Err(signal_manager_service::Error::MessageParse(_))));
+}
+
+#[test]
+fn test_protocol_compressed_payload_limited_to_max_message_size() {
+    use signal_manager_service::config::Config;
+    use signal_manager_service::message::FrameOptions;
+
+    let mut config = Config::default();
+    config.server.max_message_size = 4096;
+    let options = FrameOptions::from_config(&config);
+    let offer = |size: usize| Message::new(
+        MessageType::SignalOffer,
+        Payload::SignalOffer(SignalPayload {
+            target_client_id: "peer".to_string(),
+            signal_data: "a".repeat(size),
+            room_id: None,
+            sequence: None,
+        }),
+    );
+
+    // Both frames are tiny, but only one stays within the limit once inflated
+    let within = offer(2048).to_binary_gzipped(1).unwrap();
+    assert_eq!(within[18], PayloadType::JsonGzip as u8);
+    Message::from_binary_with(&within, options).unwrap();
+    for bomb in [offer(8192).to_binary_gzipped(1).unwrap(), offer(8192).to_binary_compressed(1).unwrap()] {
+        assert!(bomb.len() < 4096);
+        assert!(matches!(Message::from_binary_with(&bomb, options), Err(signal_manager_service::Error::MessageParse(_))));
+    }
+}
+
+#[test]
+fn test_protocol_frame_options_compress_only_as_negotiated() {
+    use signal_manager_service::message::{Compression, FrameOptions, PeerFraming};
+
+    let offer = Message::new(
+        MessageType::SignalOffer,
+        Payload::SignalOffer(SignalPayload {
+            target_client_id: "peer".to_string(),
+            signal_data: "a=candidate:1 1 udp 2122260223 192.0.2.1 54400 typ host\r\n".repeat(20),
+            room_id: None,
+            sequence: None,
+        }),
+    );
+    let options = FrameOptions { compression_threshold: 16, ..FrameOptions::default() };
+    let peer = PeerFraming::default();
+    assert_eq!(peer.compression(), Compression::None);
+    assert_eq!(offer.to_binary_with(peer.options(options)).unwrap(), offer.to_binary().unwrap());
+
+    // An uncompressed frame from the peer says nothing about what it decodes
+    peer.observe(&offer.to_binary().unwrap());
+    assert_eq!(peer.compression(), Compression::None);
+
+    peer.observe(&offer.to_binary_gzipped(16).unwrap());
+    assert_eq!(peer.compression(), Compression::Gzip);
+    let frame = offer.to_binary_with(peer.options(options)).unwrap();
+    assert_eq!(frame[18], PayloadType::JsonGzip as u8);
+    // JsonGzip only carries JSON, so other encodings go out uncompressed
+    let protobuf = Message { payload_type: PayloadType::Protobuf, ..offer.clone() };
+    assert_eq!(protobuf.to_binary_with(peer.options(options)).unwrap()[18], PayloadType::Protobuf as u8);
+
+    peer.observe(&offer.to_binary_compressed(16).unwrap());
+    assert_eq!(peer.compression(), Compression::Zstd);
+    let frame = offer.to_binary_with(peer.options(options)).unwrap();
+    assert_eq!(frame[18], PayloadType::Json as u8 | PAYLOAD_TYPE_ZSTD_FLAG);
+    assert_eq!(protobuf.to_binary_with(peer.options(options)).unwrap()[18], PayloadType::Protobuf as u8 | PAYLOAD_TYPE_ZSTD_FLAG);
+}
+
+#[test]
+fn test_protocol_200kb_offer_compresses_and_round_trips() {
+    use signal_manager_service::config::Config;
+
+    let threshold = Config::default().server.compression_threshold_bytes;
+    assert_eq!(threshold, 4096);
+
+    // About 200 KB of SDP: one candidate line per host candidate, each slightly different
+    let signal_data: String = (0..3500)
+        .map(|i| format!("a=candidate:{} 1 udp 2122260223 192.0.2.{} {} typ host\r\n", i, i % 250, 50000 + i))
+        .collect();
+    assert!(signal_data.len() > 200 * 1024, "{} bytes of SDP", signal_data.len());
+    let offer = Message::new(
+        MessageType::SignalOffer,
+        Payload::SignalOffer(SignalPayload {
+            target_client_id: "peer".to_string(),
+            signal_data: signal_data.clone(),
+            room_id: Some("room".to_string()),
+            sequence: Some(1),
+        }),
+    );
+
+    let uncompressed = offer.to_binary().unwrap();
+    let compressed = offer.to_binary_compressed(threshold).unwrap();
+    let decoded = Message::from_binary(&compressed).unwrap();
+
+    assert_eq!(compressed[18], PayloadType::Json as u8 | PAYLOAD_TYPE_ZSTD_FLAG);
+    let length = u32::from_be_bytes(compressed[19..23].try_into().unwrap()) as usize;
+    assert_eq!(compressed.len(), 23 + length);
+    assert!(compressed.len() < uncompressed.len() / 4, "{} bytes compressed vs {}", compressed.len(), uncompressed.len());
+
+    assert_eq!(decoded.payload_type, PayloadType::Json);
+    match decoded.payload {
+        Payload::SignalOffer(decoded) => assert_eq!(decoded.signal_data, signal_data),
+        other => panic!("Expected SignalOffer payload, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_protocol_compression_flag_applies_to_any_payload_type() {
+    let connect = Message::new(
+        MessageType::Connect,
+        Payload::Connect(ConnectPayload {
+            client_id: "client_".repeat(20),
+            auth_token: "token_".repeat(20),
+        }),
+    );
+    let protobuf = Message { payload_type: PayloadType::Protobuf, ..connect };
+    let compressed = protobuf.to_binary_compressed(16).unwrap();
+    assert_eq!(compressed[18], PayloadType::Protobuf as u8 | PAYLOAD_TYPE_ZSTD_FLAG);
+
+    let decoded = Message::from_binary(&compressed).unwrap();
+    assert_eq!(decoded.payload_type, PayloadType::Protobuf);
+    match decoded.payload {
+        Payload::Connect(decoded) => assert_eq!(decoded.client_id, "client_".repeat(20)),
+        other => panic!("Expected Connect payload, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_protocol_zstd_payload_inflating_past_limit_rejected() {
+    use signal_manager_service::message::MAX_DECOMPRESSED_PAYLOAD;
+
+    let bomb = zstd::bulk::compress(&vec![b' '; MAX_DECOMPRESSED_PAYLOAD + 1], 19).unwrap();
+
+    let mut frame = vec![signal_manager_service::message::START_BYTE, MessageType::Connect as u8];
+    frame.extend_from_slice(uuid::Uuid::new_v4().as_bytes());
+    frame.push(PayloadType::Json as u8 | PAYLOAD_TYPE_ZSTD_FLAG);
+    frame.extend_from_slice(&(bomb.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&bomb);
+    assert!(matches!(Message::from_binary(&frame), Err(signal_manager_service::Error::MessageParse(_))));
+}
+
+#[test]
+fn test_protocol_protobuf_connect_matches_json() {
+    let connect = Message::new(
+        MessageType::Connect,
+        Payload::Connect(ConnectPayload {
+            client_id: "proto_client".to_string(),
+            auth_token: "proto_token".to_string(),
+        }),
+    );
+    let json = Message::from_binary(&connect.to_binary().unwrap()).unwrap();
+    let protobuf_frame = Message { payload_type: PayloadType::Protobuf, ..connect }.to_binary().unwrap();
+    assert_eq!(protobuf_frame[18], PayloadType::Protobuf as u8);
+    let protobuf = Message::from_binary(&protobuf_frame).unwrap();
+
+    match (json.payload, protobuf.payload) {
+        (Payload::Connect(json), Payload::Connect(protobuf)) => {
+            assert_eq!(protobuf.client_id, json.client_id);
+            assert_eq!(protobuf.auth_token, json.auth_token);
+        }
+        other => panic!("Expected Connect payloads, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_protocol_protobuf_signal_and_room_create_round_trip() {
+    let answer = Message {
+        payload_type: PayloadType::Protobuf,
+        ..Message::new(
+            MessageType::SignalAnswer,
+            Payload::SignalAnswer(SignalPayload {
+                target_client_id: "peer".to_string(),
+                signal_data: "v=0".to_string(),
+                room_id: Some("room".to_string()),
+                sequence: Some(3),
+            }),
+        )
+    };
+    match Message::from_binary(&answer.to_binary().unwrap()).unwrap().payload {
+        Payload::SignalAnswer(p) => {
+            assert_eq!(p.target_client_id, "peer");
+            assert_eq!(p.signal_data, "v=0");
+            assert_eq!(p.room_id.as_deref(), Some("room"));
+            assert_eq!(p.sequence, Some(3));
+        }
+        other => panic!("Expected SignalAnswer, got {:?}", other),
+    }
+
+    let create = Message {
+        payload_type: PayloadType::Protobuf,
+        ..Message::new(
+            MessageType::WebRTCRoomCreate,
+            Payload::WebRTCRoomCreate(WebRTCRoomCreatePayload {
+                version: "1.0.0".to_string(),
+                client_id: "creator".to_string(),
+                auth_token: "token".to_string(),
+                role: "sender".to_string(),
+                offer_sdp: Some("v=0".to_string()),
+                max_participants: None,
+                metadata: Some(serde_json::json!({ "codec": "opus" })),
+            }),
+        )
+    };
+    match Message::from_binary(&create.to_binary().unwrap()).unwrap().payload {
+        Payload::WebRTCRoomCreate(p) => {
+            assert_eq!(p.client_id, "creator");
+            assert_eq!(p.role, "sender");
+            assert_eq!(p.offer_sdp.as_deref(), Some("v=0"));
+            assert_eq!(p.metadata, Some(serde_json::json!({ "codec": "opus" })));
+        }
+        other => panic!("Expected WebRTCRoomCreate, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_protocol_protobuf_unmapped_payload_rejected() {
+    let heartbeat = Message {
+        payload_type: PayloadType::Protobuf,
+        ..Message::new(MessageType::Heartbeat, Payload::Heartbeat(HeartbeatPayload { timestamp: 1 }))
+    };
+    assert!(matches!(heartbeat.to_binary(), Err(signal_manager_service::Error::MessageParse(_))));
+
+    let mut frame = vec![signal_manager_service::message::START_BYTE, MessageType::Heartbeat as u8];
+    frame.extend_from_slice(uuid::Uuid::new_v4().as_bytes());
+    frame.extend_from_slice(&[PayloadType::Protobuf as u8, 0, 0, 0, 0]);
+    assert!(matches!(Message::from_binary(&frame), Err(signal_manager_service::Error::MessageParse(_))));
+}
+
+#[test]
+fn test_protocol_corrupt_uuid_rejected() {
+    use signal_manager_service::message::parse_uuid;
+
+    // A UUID region of the wrong size cannot form a UUID
+    assert!(matches!(parse_uuid(&[0x11; 15]), Err(signal_manager_service::Error::InvalidUuid(_))));
+
+    // Any 16 bytes can: zeroed bytes, and bytes whose variant bits are not RFC 4122, decode as sent
+    let message = Message::new(MessageType::Heartbeat, Payload::Heartbeat(HeartbeatPayload { timestamp: 1 }));
+    let binary = message.to_binary().unwrap();
+    for uuid_bytes in [[0x00; 16], [0x11; 16]] {
+        let mut frame = binary.clone();
+        frame[2..18].copy_from_slice(&uuid_bytes);
+        assert_eq!(Message::from_binary(&frame).unwrap().uuid.as_bytes(), &uuid_bytes);
+    }
+    assert_eq!(Message::from_binary(&binary).unwrap().uuid, message.uuid);
+}
+
+#[test]
+fn test_protocol_large_offer_sdp_round_trip() {
+    let offer_sdp: String = "a=candidate:1 1 udp 2130706431 10.0.0.1 54400 typ host\r\n"
+        .chars()
+        .cycle()
+        .take(100 * 1024)
+        .collect();
+    let message = Message::new(
+        MessageType::WebRTCRoomCreate,
+        Payload::WebRTCRoomCreate(WebRTCRoomCreatePayload {
+            version: "1.0.0".to_string(),
+            client_id: "creator".to_string(),
+            auth_token: "token".to_string(),
+            role: "sender".to_string(),
+            offer_sdp: Some(offer_sdp.clone()),
+            max_participants: None,
+            metadata: None,
+        }),
+    );
+
+    let binary = message.to_binary().unwrap();
+    let payload_length = u32::from_be_bytes(binary[19..23].try_into().unwrap()) as usize;
+    assert!(payload_length > u16::MAX as usize);
+    assert_eq!(payload_length, binary[23..].len());
+
+    match Message::from_binary(&binary).unwrap().payload {
+        Payload::WebRTCRoomCreate(p) => assert_eq!(p.offer_sdp.as_deref(), Some(offer_sdp.as_str())),
+        other => panic!("Expected WebRTCRoomCreate payload, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_protocol_rejects_legacy_two_byte_length_frames() {
+    use signal_manager_service::message::{LEGACY_START_BYTE, LEGACY_START_BYTE_TIMESTAMPED};
+
+    let payload = br#"{"Heartbeat":{"timestamp":1}}"#;
+    for start in [LEGACY_START_BYTE, LEGACY_START_BYTE_TIMESTAMPED] {
+        let mut frame = vec![start, MessageType::Heartbeat as u8];
+        frame.extend_from_slice(uuid::Uuid::new_v4().as_bytes());
+        if start == LEGACY_START_BYTE_TIMESTAMPED {
+            frame.extend_from_slice(&1_700_000_000_000u64.to_be_bytes());
+        }
+        frame.push(PayloadType::Json as u8);
+        frame.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+        frame.extend_from_slice(payload);
+
+        match Message::from_binary(&frame) {
+            Err(signal_manager_service::Error::UnsupportedProtocolVersion(byte)) => assert_eq!(byte, start),
+            other => panic!("Expected UnsupportedProtocolVersion, got {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn test_protocol_checksum_trailer_round_trip() {
+    use signal_manager_service::message::{FrameOptions, CHECKSUM_LENGTH};
+
+    let options = FrameOptions { checksum: true, ..FrameOptions::default() };
+    let message = Message::new(MessageType::Connect, Payload::Connect(ConnectPayload {
+        client_id: "test".to_string(),
+        auth_token: "token".to_string(),
+    }));
+
+    let plain = message.to_binary().unwrap();
+    let binary = message.to_binary_with(options).unwrap();
+    assert_eq!(binary.len(), plain.len() + CHECKSUM_LENGTH);
+    assert_eq!(&binary[..plain.len()], &plain[..]);
+    assert_eq!(&binary[plain.len()..], &crc32fast::hash(&plain).to_be_bytes());
+
+    let decoded = Message::from_binary_with(&binary, options).unwrap();
+    assert_eq!(decoded.uuid, message.uuid);
+    assert!(matches!(decoded.payload, Payload::Connect(ConnectPayload { ref client_id, .. }) if client_id == "test"));
+}
+
+#[test]
+fn test_protocol_checksum_trailer_optional_during_rollout() {
+    use signal_manager_service::message::{FrameOptions, PeerFraming};
+
+    let options = FrameOptions { checksum: true, ..FrameOptions::default() };
+    let message = Message::new(MessageType::Connect, Payload::Connect(ConnectPayload {
+        client_id: "test".to_string(),
+        auth_token: "token".to_string(),
+    }));
+
+    // A peer that doesn't send trailers yet is accepted, and not sent any
+    let plain = message.to_binary().unwrap();
+    assert_eq!(Message::from_binary_with(&plain, options).unwrap().uuid, message.uuid);
+    let peer = PeerFraming::default();
+    peer.observe(&plain);
+    assert!(!peer.checksum());
+    assert_eq!(message.to_binary_with(peer.options(options)).unwrap(), plain);
+
+    // Once it sends one, replies carry one too
+    peer.observe(&message.to_binary_with(options).unwrap());
+    assert!(peer.checksum());
+    assert_eq!(message.to_binary_with(peer.options(options)).unwrap(), message.to_binary_with(options).unwrap());
+    // Unless checksums are off on this side
+    assert_eq!(message.to_binary_with(peer.options(FrameOptions::default())).unwrap(), plain);
+}
+
+#[test]
+fn test_protocol_checksum_detects_flipped_byte() {
+    use signal_manager_service::message::FrameOptions;
+
+    let options = FrameOptions { checksum: true, ..FrameOptions::default() };
+    let message = Message::new(MessageType::Connect, Payload::Connect(ConnectPayload {
+        client_id: "test".to_string(),
+        auth_token: "token".to_string(),
+    }));
+    let binary = message.to_binary_with(options).unwrap();
+
+    // A flipped bit in the client id still parses as JSON, so only the checksum catches it
+    let mut flipped = binary.clone();
+    let index = flipped.windows(6).position(|w| w == b"\"test\"").unwrap() + 1;
+    flipped[index] ^= 0x01;
+    match Message::from_binary_with(&flipped, options) {
+        Err(signal_manager_service::Error::ChecksumMismatch { expected, actual }) => {
+            assert_eq!(expected, u32::from_be_bytes(binary[binary.len() - 4..].try_into().unwrap()));
+            assert_ne!(expected, actual);
+        }
+        other => panic!("Expected ChecksumMismatch, got {:?}", other),
+    }
+
+    // A frame cut short is reported as truncation, not corruption
+    assert!(matches!(
+        Message::from_binary_with(&binary[..binary.len() - 2], options),
+        Err(signal_manager_service::Error::PayloadLengthMismatch { .. })
+    ));
+}
+
+#[test]
+fn test_protocol_disabled_codec_rejected_on_encode_and_decode() {
+    use signal_manager_service::message::{CodecSet, FrameOptions};
+
+    let options = FrameOptions {
+        enabled_codecs: CodecSet::from_payload_types(&[PayloadType::Json, PayloadType::Cbor, PayloadType::Protobuf]),
+        ..FrameOptions::default()
+    };
+    let mut message = Message::new(MessageType::Connect, Payload::Connect(ConnectPayload {
+        client_id: "test".to_string(),
+        auth_token: "token".to_string(),
+    }));
+
+    for disabled in [PayloadType::Text, PayloadType::Binary] {
+        message.payload_type = disabled;
+        match message.to_binary_with(options) {
+            Err(signal_manager_service::Error::CodecDisabled(payload_type)) => assert_eq!(payload_type, disabled),
+            other => panic!("Expected CodecDisabled on encode, got {:?}", other),
+        }
+
+        // A frame produced without the restriction is refused before its payload is read
+        let binary = message.to_binary().unwrap();
+        match Message::from_binary_with(&binary, options) {
+            Err(e @ signal_manager_service::Error::CodecDisabled(_)) => {
+                assert_eq!(e.to_string(), format!("Payload type {:?} is disabled on this server", disabled));
+            }
+            other => panic!("Expected CodecDisabled on decode, got {:?}", other),
+        }
+        assert!(Message::from_binary(&binary).is_ok());
+    }
+
+    message.payload_type = PayloadType::Json;
+    let binary = message.to_binary_with(options).unwrap();
+    assert_eq!(Message::from_binary_with(&binary, options).unwrap().uuid, message.uuid);
+}
+
+#[test]
+fn test_protocol_room_list_round_trip() {
+    use signal_manager_service::message::{RoomSummary, WebRTCRoomListAckPayload, WebRTCRoomListPayload};
+
+    let request = Message::new(MessageType::WebRTCRoomList, Payload::WebRTCRoomList(WebRTCRoomListPayload { offset: 50, limit: 25 }));
+    let binary = request.to_binary().expect("Failed to serialize");
+    assert_eq!(binary[1], 0x36);
+    match Message::from_binary(&binary).expect("Failed to deserialize").payload {
+        Payload::WebRTCRoomList(payload) => assert_eq!(payload, WebRTCRoomListPayload { offset: 50, limit: 25 }),
+        other => panic!("Unexpected payload: {:?}", other),
+    }
+
+    let ack = WebRTCRoomListAckPayload {
+        rooms: vec![RoomSummary {
+            room_id: "room_1".to_string(),
+            status: "active".to_string(),
+            sender_client_id: Some("sender_client".to_string()),
+            receiver_client_id: None,
+            participant_count: 1,
+            created_at: 1_717_243_200_000,
+        }],
+        total: 51,
+        next_offset: Some(51),
+    };
+    let binary = Message::new(MessageType::WebRTCRoomListAck, Payload::WebRTCRoomListAck(ack.clone())).to_binary().expect("Failed to serialize");
+    assert_eq!(binary[1], 0x37);
+    match Message::from_binary(&binary).expect("Failed to deserialize").payload {
+        Payload::WebRTCRoomListAck(payload) => assert_eq!(payload, ack),
+        other => panic!("Unexpected payload: {:?}", other),
+    }
+}
+
+#[test]
+fn test_protocol_room_list_request_defaults_to_first_page() {
+    use signal_manager_service::message::WebRTCRoomListPayload;
+
+    // Requests from clients that predate pagination carry an empty object
+    let payload: WebRTCRoomListPayload = serde_json::from_str("{}").unwrap();
+    assert_eq!(payload, WebRTCRoomListPayload { offset: 0, limit: 0 });
+}
@@ -0,0 +1,78 @@
+use super::harness::{connect_authenticated, connect_client, spawn_test_server_instance, wait_until, TestClient};
+use futures_util::StreamExt;
+use signal_manager_service::config::{Config, ConnectionLimitPolicy};
+use signal_manager_service::server::CAPACITY_REASON;
+use tokio::time::{timeout, Duration};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+async fn expect_capacity_close(client: &mut TestClient) {
+    match timeout(Duration::from_secs(5), client.next()).await {
+        Ok(Some(Ok(WsMessage::Close(Some(frame))))) => {
+            assert_eq!(frame.code, CloseCode::Again);
+            assert_eq!(frame.reason, CAPACITY_REASON);
+        }
+        other => panic!("Expected a 1013 close frame, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_connection_beyond_limit_is_rejected() {
+    let mut config = Config::default();
+    config.server.max_connections = 2;
+    let (addr, server, server_handle) = spawn_test_server_instance(config).await;
+    let limiter = server.connection_limiter();
+
+    let first = connect_authenticated(addr, "test_client_1", "test_token_1").await;
+    let _second = connect_authenticated(addr, "test_client_2", "test_token_2").await;
+    assert_eq!(limiter.live(), 2);
+
+    let mut third = connect_client(addr).await;
+    expect_capacity_close(&mut third).await;
+    assert_eq!(limiter.live(), 2);
+
+    // Once a connection closes its slot is free for the next client
+    drop(first);
+    wait_until("the closed connection to release its slot", || limiter.live() == 1).await;
+    let _fourth = connect_authenticated(addr, "test_client_1", "test_token_1").await;
+    assert_eq!(limiter.live(), 2);
+
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_queue_policy_admits_socket_when_slot_frees() {
+    let mut config = Config::default();
+    config.server.max_connections = 1;
+    config.server.connection_limit_policy = ConnectionLimitPolicy::Queue;
+    let (addr, server, server_handle) = spawn_test_server_instance(config).await;
+    let limiter = server.connection_limiter();
+
+    let first = connect_authenticated(addr, "test_client_1", "test_token_1").await;
+    let queued_client = tokio::spawn(connect_authenticated(addr, "test_client_2", "test_token_2"));
+    wait_until("a queued connection", || limiter.queued() == 1).await;
+    assert_eq!(limiter.live(), 1);
+
+    drop(first);
+    let _second = timeout(Duration::from_secs(5), queued_client).await.expect("Queued client never connected").unwrap();
+    assert_eq!(limiter.queued(), 0);
+    assert_eq!(limiter.live(), 1);
+
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_queue_policy_rejects_after_wait() {
+    let mut config = Config::default();
+    config.server.max_connections = 1;
+    config.server.connection_limit_policy = ConnectionLimitPolicy::Queue;
+    config.server.tls_handshake_timeout_secs = 1;
+    let (addr, server, server_handle) = spawn_test_server_instance(config).await;
+
+    let _first = connect_authenticated(addr, "test_client_1", "test_token_1").await;
+    let mut waiting = connect_client(addr).await;
+    expect_capacity_close(&mut waiting).await;
+    assert_eq!(server.connection_limiter().queued(), 0);
+
+    server_handle.abort();
+}
@@ -0,0 +1,89 @@
+use super::harness::{connect_client, recv_message, spawn_test_server};
+use futures_util::{SinkExt, StreamExt};
+use signal_manager_service::{
+    config::Config,
+    message::{ConnectPayload, Message, MessageType, Payload},
+};
+use tokio::time::{timeout, Duration};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::{Data, OpCode};
+use tokio_tungstenite::tungstenite::protocol::frame::Frame;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+/// Split `data` into a binary frame followed by continuation frames of `chunk` bytes
+fn fragments(data: &[u8], chunk: usize) -> Vec<WsMessage> {
+    let chunks: Vec<_> = data.chunks(chunk).collect();
+    chunks
+        .iter()
+        .enumerate()
+        .map(|(i, part)| {
+            let opcode = if i == 0 { OpCode::Data(Data::Binary) } else { OpCode::Data(Data::Continue) };
+            WsMessage::Frame(Frame::message(part.to_vec(), opcode, i == chunks.len() - 1))
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_fragmented_binary_message_is_reassembled() {
+    let (addr, server_handle) = spawn_test_server(Config::default()).await;
+    let mut client = connect_client(addr).await;
+
+    let connect = Message::new(
+        MessageType::Connect,
+        Payload::Connect(ConnectPayload {
+            client_id: "test_client_1".to_string(),
+            auth_token: "test_token_1".to_string(),
+        }),
+    );
+    let frames = fragments(&connect.to_binary().unwrap(), 3);
+    assert!(frames.len() > 10);
+    for frame in frames {
+        client.send(frame).await.expect("Failed to send fragment");
+    }
+
+    match recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::ConnectAck(ack), uuid, .. }) => {
+            assert_eq!(ack.status, "success");
+            assert_ne!(uuid, connect.uuid);
+        }
+        other => panic!("Expected ConnectAck for the reassembled Connect, got {:?}", other),
+    }
+
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_fragmented_message_over_max_message_size_is_refused() {
+    let mut config = Config::default();
+    config.server.max_message_size = 256;
+    let (addr, server_handle) = spawn_test_server(config).await;
+    let mut client = connect_client(addr).await;
+
+    // Every fragment is under the limit but the reassembled message is not
+    for frame in fragments(&[0xAA; 400], 100) {
+        client.send(frame).await.expect("Failed to send fragment");
+    }
+    match recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::Error(error), .. }) => assert_eq!(error.error_code, 9),
+        other => panic!("Expected message size error, got {:?}", other),
+    }
+
+    // Reassembled past the protocol layer's slack as well, the connection is dropped
+    for frame in fragments(&vec![0xAA; 128 * 1024], 4096) {
+        if client.send(frame).await.is_err() {
+            break;
+        }
+    }
+
+    let closed = timeout(Duration::from_secs(5), async {
+        loop {
+            match client.next().await {
+                None | Some(Err(_)) | Some(Ok(WsMessage::Close(_))) => return,
+                Some(Ok(_)) => continue,
+            }
+        }
+    })
+    .await;
+    assert!(closed.is_ok(), "Server kept the connection open after an oversized fragmented message");
+
+    server_handle.abort();
+}
@@ -0,0 +1,83 @@
+use super::harness::{connect_client, spawn_test_server_instance, wait_until};
+use signal_manager_service::config::Config;
+use tokio::io::AsyncReadExt;
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Duration};
+
+const FLOOD_CLIENTS: usize = 40;
+
+#[tokio::test]
+async fn test_handshake_flood_respects_concurrency_cap() {
+    let mut config = Config::default();
+    config.server.max_concurrent_handshakes = 3;
+    let (addr, server, server_handle) = spawn_test_server_instance(config).await;
+
+    let clients = futures_util::future::join_all((0..FLOOD_CLIENTS).map(|_| connect_client(addr))).await;
+    assert_eq!(clients.len(), FLOOD_CLIENTS);
+
+    let limiter = server.handshake_limiter();
+    assert!(limiter.peak_in_flight() >= 1);
+    assert!(limiter.peak_in_flight() <= 3, "peak of {} handshakes exceeds the cap", limiter.peak_in_flight());
+    wait_until("handshake slots to be released", || limiter.in_flight() == 0).await;
+
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_handshake_queue_overflow_closes_socket() {
+    let mut config = Config::default();
+    config.server.max_concurrent_handshakes = 2;
+    config.server.max_queued_handshakes = 1;
+    let (addr, server, server_handle) = spawn_test_server_instance(config).await;
+    let limiter = server.handshake_limiter();
+
+    // Raw sockets that never send an upgrade request hold both slots
+    let mut stalled = Vec::new();
+    for _ in 0..2 {
+        stalled.push(TcpStream::connect(addr).await.unwrap());
+    }
+    wait_until("both slots to be taken", || limiter.in_flight() == 2).await;
+
+    // The next client waits in the queue
+    let queued_client = tokio::spawn(connect_client(addr));
+    wait_until("a queued handshake", || limiter.queued() == 1).await;
+
+    // With the queue full, another socket is closed without a handshake
+    let mut rejected = TcpStream::connect(addr).await.unwrap();
+    let mut buf = [0u8; 16];
+    let read = timeout(Duration::from_secs(5), rejected.read(&mut buf)).await.expect("Rejected socket was left open");
+    assert!(matches!(read, Ok(0) | Err(_)), "Expected the rejected socket to be closed, got {:?}", read);
+    assert!(limiter.peak_in_flight() <= 2);
+
+    // Freeing a slot lets the queued client finish its handshake
+    drop(stalled.pop());
+    timeout(Duration::from_secs(5), queued_client).await.expect("Queued client never connected").unwrap();
+
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_idle_sockets_give_their_handshake_slots_back() {
+    let mut config = Config::default();
+    config.server.max_concurrent_handshakes = 2;
+    config.server.tls_handshake_timeout_secs = 1;
+    let (addr, server, server_handle) = spawn_test_server_instance(config).await;
+    let limiter = server.handshake_limiter();
+
+    // Plain sockets that never send an upgrade request take every slot
+    let mut idle = Vec::new();
+    for _ in 0..2 {
+        idle.push(TcpStream::connect(addr).await.unwrap());
+    }
+    wait_until("both slots to be taken", || limiter.in_flight() == 2).await;
+
+    // Once the upgrade times out they are closed and a new client gets through
+    let mut buf = [0u8; 16];
+    for socket in &mut idle {
+        let read = timeout(Duration::from_secs(5), socket.read(&mut buf)).await.expect("Idle socket was left open");
+        assert!(matches!(read, Ok(0) | Err(_)), "Expected the idle socket to be closed, got {:?}", read);
+    }
+    timeout(Duration::from_secs(5), connect_client(addr)).await.expect("Client never connected");
+
+    server_handle.abort();
+}
@@ -0,0 +1,90 @@
+//! Ephemeral-port test harness: binds `127.0.0.1:0` so tests can run in parallel
+//! without fixed-port conflicts.
+
+use futures_util::{SinkExt, StreamExt};
+use signal_manager_service::{
+    config::Config,
+    message::{ConnectPayload, Message, MessageType, Payload},
+    server::WebSocketServer,
+};
+use std::net::SocketAddr;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tokio::time::{timeout, Duration};
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+pub type TestClient = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Start a server on an ephemeral port and return its address and task handle
+pub async fn spawn_test_server(config: Config) -> (SocketAddr, JoinHandle<()>) {
+    let (addr, _, handle) = spawn_test_server_instance(config).await;
+    (addr, handle)
+}
+
+/// Like `spawn_test_server`, also returning a handle to the server for state inspection
+pub async fn spawn_test_server_instance(config: Config) -> (SocketAddr, WebSocketServer, JoinHandle<()>) {
+    spawn_server(WebSocketServer::new(config).expect("Failed to create server")).await
+}
+
+/// Serve an already-built server on an ephemeral port, e.g. one with injected repositories
+pub async fn spawn_server(server: WebSocketServer) -> (SocketAddr, WebSocketServer, JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("Failed to bind ephemeral port");
+    let addr = listener.local_addr().unwrap();
+    let serving = server.clone();
+    let handle = tokio::spawn(async move {
+        let _ = serving.serve(listener).await;
+    });
+    (addr, server, handle)
+}
+
+pub async fn connect_client(addr: SocketAddr) -> TestClient {
+    let (ws_stream, _) = connect_async(format!("ws://{}", addr)).await.expect("Failed to connect");
+    ws_stream
+}
+
+pub async fn send_message(client: &mut TestClient, message: Message) {
+    let binary = message.to_binary().expect("Failed to serialize");
+    client.send(WsMessage::Binary(binary)).await.expect("Failed to send");
+}
+
+/// Receive the next protocol message, or `None` if nothing arrives within `wait`
+pub async fn recv_message(client: &mut TestClient, wait: Duration) -> Option<Message> {
+    loop {
+        match timeout(wait, client.next()).await {
+            Ok(Some(Ok(WsMessage::Binary(data)))) => {
+                return Some(Message::from_binary(&data).expect("Failed to parse server frame"));
+            }
+            Ok(Some(Ok(WsMessage::Ping(_)))) | Ok(Some(Ok(WsMessage::Pong(_)))) => continue,
+            _ => return None,
+        }
+    }
+}
+
+/// Connect and authenticate a client, asserting the server acknowledges it
+pub async fn connect_authenticated(addr: SocketAddr, client_id: &str, auth_token: &str) -> TestClient {
+    let mut client = connect_client(addr).await;
+    send_message(&mut client, Message::new(
+        MessageType::Connect,
+        Payload::Connect(ConnectPayload {
+            client_id: client_id.to_string(),
+            auth_token: auth_token.to_string(),
+        }),
+    )).await;
+    match recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::ConnectAck(ack), .. }) => assert_eq!(ack.status, "success"),
+        other => panic!("Expected ConnectAck for {}, got {:?}", client_id, other),
+    }
+    client
+}
+
+/// Poll `condition` until it holds, failing the test after a few seconds
+pub async fn wait_until(what: &str, condition: impl Fn() -> bool) {
+    timeout(Duration::from_secs(5), async {
+        while !condition() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("Timed out waiting for {what}"));
+}
@@ -0,0 +1,136 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use signal_manager_service::config::Config;
+use signal_manager_service::events::InMemoryEventClient;
+use signal_manager_service::health::HealthStatus;
+use signal_manager_service::server::WebSocketServer;
+use signal_manager_service::signaling::{ProviderResult, ProviderSession, SignalingProvider};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use super::harness;
+use crate::database::repository::{MockRepositoryFactory, UnreachableRepositoryFactory};
+
+/// Provider that only answers health probes, counting them
+#[derive(Default)]
+struct ProbeCountingProvider {
+    rejects_credentials: bool,
+    probes: AtomicUsize,
+}
+
+#[async_trait::async_trait]
+impl SignalingProvider for ProbeCountingProvider {
+    async fn create_session(&self, _room_id: &str, _client_id: &str, _offer_sdp: String) -> ProviderResult<ProviderSession> {
+        Err("not used by health tests".into())
+    }
+
+    async fn add_track(&self, _room_id: &str, _client_id: &str, _session_id: &str) -> ProviderResult<ProviderSession> {
+        Err("not used by health tests".into())
+    }
+
+    async fn close_session(&self, _session_id: &str, _room_id: &str) -> ProviderResult<()> {
+        Ok(())
+    }
+
+    async fn check_health(&self) -> ProviderResult<bool> {
+        self.probes.fetch_add(1, Ordering::SeqCst);
+        Ok(!self.rejects_credentials)
+    }
+}
+
+#[tokio::test]
+async fn test_health_report_marks_failed_repository_degraded() {
+    let server = WebSocketServer::new(Config::default())
+        .expect("Failed to create server")
+        .with_health_repository_factory(Arc::new(UnreachableRepositoryFactory))
+        .with_signaling_provider(Arc::new(ProbeCountingProvider::default()));
+
+    // Nothing is listening yet
+    let report = server.health_report().await;
+    assert_eq!(report.listener.status, HealthStatus::Down);
+    assert!(!report.is_ready());
+
+    let (addr, server, handle) = harness::spawn_server(server).await;
+    let _client = harness::connect_client(addr).await;
+    let report = server.health_report().await;
+    assert_eq!(report.listener.status, HealthStatus::Ok);
+    assert_eq!(report.routing_task.status, HealthStatus::Ok);
+    assert_eq!(report.event_publisher.status, HealthStatus::Ok);
+    assert_eq!(report.repositories.status, HealthStatus::Degraded);
+    assert!(report.repositories.detail.as_deref().unwrap().contains("repository unreachable"));
+    assert_eq!(report.cloudflare.status, HealthStatus::Ok);
+    assert!(report.status >= HealthStatus::Degraded);
+    assert!(report.is_ready());
+
+    // /readyz renders the same report
+    let readyz_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let readyz_addr = readyz_listener.local_addr().unwrap();
+    let readyz_server = server.clone();
+    let readyz = tokio::spawn(async move { readyz_server.serve_readyz(readyz_listener).await });
+
+    let mut stream = TcpStream::connect(readyz_addr).await.unwrap();
+    stream.write_all(b"GET /readyz HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "unexpected response: {response}");
+    let body: serde_json::Value = serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+    assert_eq!(body["repositories"]["status"], "degraded");
+    assert_eq!(body["listener"]["status"], "ok");
+
+    readyz.abort();
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_test_mode_routing_reported_degraded() {
+    let server = WebSocketServer::new_for_test(Config::default())
+        .expect("Failed to create test-mode server")
+        .with_health_repository_factory(Arc::new(MockRepositoryFactory))
+        .with_signaling_provider(Arc::new(ProbeCountingProvider::default()));
+    let (addr, server, handle) = harness::spawn_server(server).await;
+    let _client = harness::connect_client(addr).await;
+    let report = server.health_report().await;
+    assert_eq!(report.routing_task.status, HealthStatus::Degraded);
+    assert_eq!(report.repositories.status, HealthStatus::Ok);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_unbuildable_event_publisher_reported_degraded() {
+    let mut config = Config::default();
+    config.events.backend = "carrier_pigeon".to_string();
+    let server = WebSocketServer::new_for_test(config)
+        .expect("Failed to create test-mode server")
+        .with_health_repository_factory(Arc::new(MockRepositoryFactory))
+        .with_signaling_provider(Arc::new(ProbeCountingProvider::default()));
+
+    // Reports on the client the server was built with, on every probe
+    for _ in 0..2 {
+        let report = server.health_report().await;
+        assert_eq!(report.event_publisher.status, HealthStatus::Degraded);
+        assert!(report.event_publisher.detail.as_deref().unwrap().contains("Unsupported events backend: carrier_pigeon"));
+    }
+
+    // An injected client is the one lifecycle events go to
+    let server = server.with_event_client(Arc::new(InMemoryEventClient::new(8)));
+    assert_eq!(server.health_report().await.event_publisher.status, HealthStatus::Ok);
+}
+
+#[tokio::test]
+async fn test_dependency_probes_are_cached_between_reports() {
+    let provider = Arc::new(ProbeCountingProvider { rejects_credentials: true, ..Default::default() });
+    let server = WebSocketServer::new_for_test(Config::default())
+        .expect("Failed to create test-mode server")
+        .with_health_repository_factory(Arc::new(MockRepositoryFactory))
+        .with_signaling_provider(provider.clone());
+
+    let reports = futures_util::future::join_all((0..5).map(|_| server.health_report())).await;
+    for report in &reports {
+        assert_eq!(report.cloudflare.status, HealthStatus::Degraded);
+        assert!(report.cloudflare.detail.as_deref().unwrap().contains("rejected the configured credentials"));
+        assert_eq!(report.repositories.status, HealthStatus::Ok);
+    }
+    server.health_report().await;
+    assert_eq!(provider.probes.load(Ordering::SeqCst), 1, "Each report probed the provider again");
+}
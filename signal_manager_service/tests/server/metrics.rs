@@ -0,0 +1,322 @@
+use super::harness::{connect_authenticated, connect_client, recv_message, send_message, spawn_test_server_instance};
+use futures_util::SinkExt;
+use signal_manager_service::{
+    config::Config,
+    message::{ConnectPayload, HeartbeatPayload, Message, MessageType, Payload, ServerInfoPayload},
+    server::WebSocketServer,
+};
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::Duration;
+
+const CLIENTS: usize = 20;
+const HEARTBEATS_PER_CLIENT: usize = 7;
+const SERVER_INFOS_PER_CLIENT: usize = 3;
+
+/// Fetch `/metrics` from a metrics endpoint serving `server` and return the response body
+async fn scrape(server: &WebSocketServer) -> String {
+    let metrics_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let metrics_addr = metrics_listener.local_addr().unwrap();
+    let metrics_server = server.clone();
+    let metrics_task = tokio::spawn(async move { metrics_server.serve_metrics(metrics_listener).await });
+
+    let mut stream = TcpStream::connect(metrics_addr).await.unwrap();
+    stream.write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    metrics_task.abort();
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "unexpected response: {response}");
+    response.split("\r\n\r\n").nth(1).unwrap().to_string()
+}
+
+fn counter(body: &str, series: &str) -> u64 {
+    body.lines()
+        .find_map(|line| line.strip_prefix(series)?.strip_prefix(' '))
+        .unwrap_or_else(|| panic!("series {series} missing from:\n{body}"))
+        .parse()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_metrics_counters_exact_under_concurrency() {
+    let mut config = Config::default();
+    // Every client connects from localhost, which the per-address limit would cap
+    config.security.rate_limit_enabled = false;
+    for i in 0..CLIENTS {
+        config.auth.api_keys.push(format!("metrics_client_{}:metrics_token_{}", i, i));
+    }
+    let (addr, server, server_handle) = spawn_test_server_instance(config).await;
+
+    let sessions = (0..CLIENTS).map(|i| async move {
+        let mut client = connect_authenticated(addr, &format!("metrics_client_{}", i), &format!("metrics_token_{}", i)).await;
+        for _ in 0..HEARTBEATS_PER_CLIENT {
+            send_message(&mut client, Message::new(
+                MessageType::Heartbeat,
+                Payload::Heartbeat(HeartbeatPayload { timestamp: 0 }),
+            )).await;
+        }
+        for _ in 0..SERVER_INFOS_PER_CLIENT {
+            send_message(&mut client, Message::new(
+                MessageType::ServerInfo,
+                Payload::ServerInfo(ServerInfoPayload {}),
+            )).await;
+        }
+        // Every request is answered, so all of them have been counted once the replies are in
+        for _ in 0..HEARTBEATS_PER_CLIENT + SERVER_INFOS_PER_CLIENT {
+            assert!(recv_message(&mut client, Duration::from_secs(5)).await.is_some(), "metrics_client_{} missed a reply", i);
+        }
+        client
+    });
+    let _clients = futures_util::future::join_all(sessions).await;
+
+    let body = scrape(&server).await;
+    let body = body.as_str();
+
+    let messages = |message_type: &str| counter(body, &format!("signal_manager_messages_received_total{{type=\"{message_type}\"}}"));
+    assert_eq!(counter(body, "signal_manager_connections_total"), CLIENTS as u64);
+    assert_eq!(messages("Connect"), CLIENTS as u64);
+    assert_eq!(messages("Heartbeat"), (CLIENTS * HEARTBEATS_PER_CLIENT) as u64);
+    assert_eq!(messages("ServerInfo"), (CLIENTS * SERVER_INFOS_PER_CLIENT) as u64);
+    assert_eq!(messages("SignalOffer"), 0);
+    assert_eq!(server.metrics().messages_received(MessageType::Heartbeat), (CLIENTS * HEARTBEATS_PER_CLIENT) as u64);
+
+    let dispatches = |message_type: &str| counter(body, &format!("signal_manager_handler_duration_seconds_count{{type=\"{message_type}\"}}"));
+    assert!(body.contains("# TYPE signal_manager_handler_duration_seconds histogram"));
+    assert_eq!(dispatches("Heartbeat"), (CLIENTS * HEARTBEATS_PER_CLIENT) as u64);
+    assert_eq!(dispatches("ServerInfo"), (CLIENTS * SERVER_INFOS_PER_CLIENT) as u64);
+    assert_eq!(
+        counter(body, "signal_manager_handler_duration_seconds_bucket{type=\"Heartbeat\",le=\"+Inf\"}"),
+        (CLIENTS * HEARTBEATS_PER_CLIENT) as u64
+    );
+
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_metrics_report_active_connections_parse_errors_and_auth_failures() {
+    let (addr, server, server_handle) = spawn_test_server_instance(Config::default()).await;
+
+    let _client = connect_authenticated(addr, "test_client_1", "test_token_1").await;
+    let mut other = connect_client(addr).await;
+
+    // Not a protocol frame
+    other.send(WsMessage::Binary(vec![0u8; 32])).await.unwrap();
+    match recv_message(&mut other, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::Error(error), .. }) => assert_eq!(error.error_code, 2),
+        reply => panic!("Expected malformed message error, got {:?}", reply),
+    }
+
+    // A nil UUID is taken as sent, not counted as a parse error
+    let mut frame = Message::new(MessageType::ServerInfo, Payload::ServerInfo(ServerInfoPayload {})).to_binary().unwrap();
+    frame[2..18].fill(0);
+    other.send(WsMessage::Binary(frame)).await.unwrap();
+    match recv_message(&mut other, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::ServerInfoAck(_), .. }) => {}
+        reply => panic!("Expected ServerInfoAck, got {:?}", reply),
+    }
+
+    let body = scrape(&server).await;
+    assert_eq!(counter(&body, "signal_manager_connections_active"), 2);
+    assert_eq!(counter(&body, "signal_manager_parse_errors_total"), 1);
+    assert_eq!(counter(&body, "signal_manager_parse_errors_by_reason_total{reason=\"malformed\"}"), 1);
+    assert_eq!(counter(&body, "signal_manager_parse_errors_by_reason_total{reason=\"invalid_uuid\"}"), 0);
+    assert_eq!(counter(&body, "signal_manager_auth_failures_total"), 0);
+    assert!(body.contains("# TYPE signal_manager_connections_active gauge"));
+
+    // A refused Connect is counted, and the server closes the socket
+    send_message(&mut other, Message::new(MessageType::Connect, Payload::Connect(ConnectPayload {
+        client_id: "test_client_2".to_string(),
+        auth_token: "wrong_token".to_string(),
+    }))).await;
+    match recv_message(&mut other, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::Error(error), .. }) => assert_eq!(error.error_code, 1),
+        reply => panic!("Expected authentication error, got {:?}", reply),
+    }
+
+    // The gauge drops once the server has finished with a closed socket
+    drop(other);
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while server.metrics().connections_active() != 1 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Closed connection was still counted as active");
+    let body = scrape(&server).await;
+    assert_eq!(counter(&body, "signal_manager_connections_active"), 1);
+    assert_eq!(counter(&body, "signal_manager_auth_failures_total"), 1);
+
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_frame_size_histogram_buckets_inbound_frames() {
+    let (addr, server, server_handle) = spawn_test_server_instance(Config::default()).await;
+    let mut client = connect_client(addr).await;
+
+    // Not protocol frames: each is counted before parsing, then answered with a malformed message error
+    let sizes = [10, 64, 65, 2000, 5000];
+    for size in sizes {
+        client.send(WsMessage::Binary(vec![0u8; size])).await.unwrap();
+        match recv_message(&mut client, Duration::from_secs(5)).await {
+            Some(Message { payload: Payload::Error(error), .. }) => assert_eq!(error.error_code, 2),
+            reply => panic!("Expected malformed message error, got {:?}", reply),
+        }
+    }
+
+    // 64 is the top of the first bucket; 65 starts the second
+    assert_eq!(server.metrics().frame_sizes(), vec![2, 1, 0, 1, 1, 0, 0, 0, 0]);
+    let body = scrape(&server).await;
+    assert!(body.contains("# TYPE signal_manager_frame_size_bytes histogram"));
+    assert_eq!(counter(&body, "signal_manager_frame_size_bytes_bucket{le=\"64\"}"), 2);
+    assert_eq!(counter(&body, "signal_manager_frame_size_bytes_bucket{le=\"1024\"}"), 3);
+    assert_eq!(counter(&body, "signal_manager_frame_size_bytes_bucket{le=\"16384\"}"), 5);
+    assert_eq!(counter(&body, "signal_manager_frame_size_bytes_bucket{le=\"+Inf\"}"), 5);
+    assert_eq!(counter(&body, "signal_manager_frame_size_bytes_sum"), sizes.iter().sum::<usize>() as u64);
+    assert_eq!(counter(&body, "signal_manager_frame_size_bytes_count"), 5);
+
+    server_handle.abort();
+}
+
+#[test]
+fn test_stats_sampler_reports_deltas_between_ticks() {
+    use signal_manager_service::metrics::{ConnectionStats, Metrics, StatsSampler};
+
+    let metrics = Metrics::new();
+    let mut connections = StatsSampler::new();
+    let mut messages = StatsSampler::new();
+    assert_eq!(connections.connection_stats(&metrics), ConnectionStats::default());
+    assert_eq!(messages.message_stats(&metrics).total, 0);
+
+    for _ in 0..3 {
+        metrics.record_connection();
+    }
+    metrics.record_connection_closed();
+    metrics.record_message(MessageType::Heartbeat);
+    metrics.record_message(MessageType::Heartbeat);
+    metrics.record_message(MessageType::Connect);
+    metrics.record_frame_size(100);
+    metrics.record_frame_size(2_000_000);
+
+    assert_eq!(connections.connection_stats(&metrics), ConnectionStats { active: 2, opened: 3, closed: 1 });
+    let stats = messages.message_stats(&metrics);
+    assert_eq!(stats.total, 3);
+    assert_eq!(stats.by_type, vec![(MessageType::Connect, 1), (MessageType::Heartbeat, 2)]);
+    assert_eq!(stats.frame_sizes, vec![0, 1, 0, 0, 0, 0, 0, 0, 1]);
+
+    // The next tick only counts what happened since this one
+    metrics.record_connection_closed();
+    metrics.record_message(MessageType::Heartbeat);
+    assert_eq!(connections.connection_stats(&metrics), ConnectionStats { active: 1, opened: 0, closed: 1 });
+    let stats = messages.message_stats(&metrics);
+    assert_eq!(stats.total, 1);
+    assert_eq!(stats.by_type, vec![(MessageType::Heartbeat, 1)]);
+    assert!(stats.frame_sizes.iter().all(|count| *count == 0));
+
+    // Samplers are independent: a fresh one counts from start-up
+    assert_eq!(StatsSampler::new().connection_stats(&metrics), ConnectionStats { active: 1, opened: 3, closed: 2 });
+}
+
+/// Log output captured from a subscriber installed with `tracing::subscriber::set_default`
+#[derive(Clone, Default)]
+struct CapturedLogs(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+impl std::io::Write for CapturedLogs {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl CapturedLogs {
+    fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.lock().unwrap()).into_owned()
+    }
+}
+
+#[tokio::test]
+async fn test_slow_dispatch_is_logged_and_timed() {
+    use signal_manager_service::metrics::{time_dispatch, Metrics, HANDLER_DURATION_BUCKETS_MS};
+
+    let logs = CapturedLogs::default();
+    let writer = logs.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(move || writer.clone())
+        .with_ansi(false)
+        .finish();
+    let _subscriber = tracing::subscriber::set_default(subscriber);
+
+    let metrics = Metrics::new();
+    let threshold = Duration::from_millis(20);
+    let slow_uuid = uuid::Uuid::new_v4();
+    let slow_handler = async {
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        "created"
+    };
+    let output = time_dispatch(MessageType::WebRTCRoomCreate, slow_uuid, threshold, Some(&metrics), slow_handler).await;
+    assert_eq!(output, "created");
+
+    let fast_uuid = uuid::Uuid::new_v4();
+    time_dispatch(MessageType::Heartbeat, fast_uuid, threshold, Some(&metrics), async {}).await;
+
+    let logs = logs.contents();
+    let warnings: Vec<&str> = logs.lines().filter(|line| line.contains("Slow handler")).collect();
+    assert_eq!(warnings.len(), 1, "Expected one slow-handler warning in:\n{logs}");
+    assert!(warnings[0].contains("WARN"));
+    assert!(warnings[0].contains("message_type=WebRTCRoomCreate"), "Untagged warning: {}", warnings[0]);
+    assert!(warnings[0].contains(&format!("uuid={slow_uuid}")), "Untagged warning: {}", warnings[0]);
+    assert!(!logs.contains(&fast_uuid.to_string()));
+
+    // The slow dispatch took at least 60ms, so it is past the 50ms bucket; the fast one is in the first
+    let slow_counts = metrics.handler_durations(MessageType::WebRTCRoomCreate);
+    let past_50ms = HANDLER_DURATION_BUCKETS_MS.iter().position(|bound| *bound == 100).unwrap();
+    assert_eq!(slow_counts.iter().sum::<u64>(), 1);
+    assert_eq!(slow_counts[past_50ms..].iter().sum::<u64>(), 1, "Unexpected buckets: {:?}", slow_counts);
+    assert_eq!(metrics.handler_durations(MessageType::Heartbeat)[0], 1);
+    assert!(metrics.render().contains("signal_manager_handler_duration_seconds_count{type=\"WebRTCRoomCreate\"} 1"));
+}
+
+#[tokio::test]
+async fn test_connection_logs_carry_the_instance_id_field() {
+    let logs = CapturedLogs::default();
+    let writer = logs.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(move || writer.clone())
+        .with_ansi(false)
+        .finish();
+    let _subscriber = tracing::subscriber::set_default(subscriber);
+
+    let mut config = Config::default();
+    config.server.instance_id = "signal-manager-eu-1".to_string();
+    let (addr, _server, handle) = spawn_test_server_instance(config).await;
+    // Logged from the connection's own task, not the accept loop
+    let _client = connect_authenticated(addr, "test_client_1", "test_token_1").await;
+    handle.abort();
+
+    let logs = logs.contents();
+    let connected: Vec<&str> = logs.lines().filter(|line| line.contains("Client test_client_1 connected successfully")).collect();
+    assert_eq!(connected.len(), 1, "Expected one connect line in:\n{logs}");
+    assert!(connected[0].contains("instance{instance_id=signal-manager-eu-1}"), "Untagged line: {}", connected[0]);
+}
+
+#[test]
+fn test_handler_duration_buckets_respect_le_bounds() {
+    use signal_manager_service::metrics::{Metrics, HANDLER_DURATION_BUCKETS_MS};
+
+    let metrics = Metrics::new();
+    metrics.record_handler_duration(MessageType::Heartbeat, Duration::from_micros(1000));
+    metrics.record_handler_duration(MessageType::Heartbeat, Duration::from_micros(1900));
+    metrics.record_handler_duration(MessageType::Heartbeat, Duration::from_micros(5001));
+
+    // Exactly 1ms is within le="0.001"; 1.9ms is past it and 5.001ms is past le="0.005"
+    let counts = metrics.handler_durations(MessageType::Heartbeat);
+    let bucket = |bound: u64| HANDLER_DURATION_BUCKETS_MS.iter().position(|b| *b == bound).unwrap();
+    assert_eq!(counts[bucket(1)], 1);
+    assert_eq!(counts[bucket(5)], 1);
+    assert_eq!(counts[bucket(10)], 1);
+}
@@ -1,7 +1,21 @@
+mod harness;
+mod connection_limit;
+mod fragmentation;
+mod handshake_limit;
+mod health;
+mod metrics;
+mod origin;
+mod rate_limit;
+mod reload;
+mod relay_load;
+mod shutdown;
+#[cfg(all(feature = "native-tls", feature = "rustls"))]
+mod tls;
+
 use signal_manager_service::{
     server::WebSocketServer,
     config::Config,
-    message::{Message, MessageType, Payload, ConnectPayload, SignalPayload},
+    message::{Message, MessageType, Payload, PayloadType, ConnectPayload, SignalPayload},
     auth::AuthManager,
     session::SessionManager,
 };
@@ -96,7 +110,7 @@ async fn test_message_serialization_for_server() {
 async fn test_protocol_message_handling() {
     // Test that the server can handle different message types
     let config = Config::default();
-    let _server = WebSocketServer::new(config).expect("Failed to create server");
+    let _server = WebSocketServer::new_for_test(config).expect("Failed to create server");
     
     // Test message type handling
     let message_types = vec![
@@ -121,6 +135,8 @@ async fn test_protocol_message_handling() {
                 Payload::SignalOffer(SignalPayload {
                     target_client_id: "target".to_string(),
                     signal_data: "data".to_string(),
+                    room_id: None,
+                    sequence: None,
                 })
             }
             MessageType::Disconnect => Payload::Disconnect(signal_manager_service::message::DisconnectPayload {
@@ -140,7 +156,7 @@ async fn test_protocol_message_handling() {
 async fn test_server_error_handling() {
     // Test server error handling for malformed messages
     let config = Config::default();
-    let _server = WebSocketServer::new(config).expect("Failed to create server");
+    let _server = WebSocketServer::new_for_test(config).expect("Failed to create server");
     
     // Test with invalid binary data
     let invalid_data = vec![0x00, 0x01, 0x02]; // Invalid protocol data
@@ -180,6 +196,8 @@ async fn test_message_routing_logic() {
     let signal_payload = Payload::SignalOffer(SignalPayload {
         target_client_id: "nonexistent_client".to_string(),
         signal_data: "test_data".to_string(),
+        room_id: None,
+        sequence: None,
     });
     
     let message = Message::new(MessageType::SignalOffer, signal_payload);
@@ -189,6 +207,111 @@ async fn test_message_routing_logic() {
     assert!(result.is_err());
 }
 
+#[tokio::test]
+async fn test_prune_disconnected_forgets_old_last_seen_times() {
+    let config = Config::default();
+    let auth_manager = Arc::new(AuthManager::new(Arc::new(config)));
+    let (session_manager, _receiver) = SessionManager::new(auth_manager);
+
+    session_manager.handle_connect("test_client_1".to_string(), "test_token_1".to_string()).await.unwrap();
+    session_manager.handle_disconnect("test_client_1").await.unwrap();
+    assert!(session_manager.last_seen("test_client_1").await.is_some());
+
+    assert_eq!(session_manager.prune_disconnected(std::time::Duration::from_secs(60)).await, 0);
+    assert!(session_manager.last_seen("test_client_1").await.is_some());
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(20)).await;
+    assert_eq!(session_manager.prune_disconnected(std::time::Duration::from_millis(10)).await, 1);
+    assert_eq!(session_manager.last_seen("test_client_1").await, None);
+}
+
+#[tokio::test]
+async fn test_room_relay_sequence_numbers() {
+    // Relayed signals carry monotonically increasing per-(room, sender) sequence numbers
+    let config = Config::default();
+    let auth_manager = Arc::new(AuthManager::new(Arc::new(config)));
+    let (session_manager, mut receiver) = SessionManager::new(auth_manager);
+
+    session_manager.handle_connect("test_client_1".to_string(), "test_token_1".to_string()).await.unwrap();
+    session_manager.handle_connect("test_client_2".to_string(), "test_token_2".to_string()).await.unwrap();
+    session_manager.set_member_role("room_1", "test_client_1", "sender").await;
+    session_manager.set_member_role("room_1", "test_client_2", "receiver").await;
+
+    let signal = |target: &str, data: &str| {
+        Message::new(MessageType::SignalIceCandidate, Payload::SignalIceCandidate(SignalPayload {
+            target_client_id: target.to_string(),
+            signal_data: data.to_string(),
+            room_id: Some("room_1".to_string()),
+            sequence: None,
+        }))
+    };
+
+    for i in 0..3 {
+        session_manager.route_message("test_client_1".to_string(), signal("test_client_2", &format!("c1_{}", i))).await.unwrap();
+        session_manager.route_message("test_client_2".to_string(), signal("test_client_1", &format!("c2_{}", i))).await.unwrap();
+    }
+
+    let mut last_seen: std::collections::HashMap<String, u64> = std::collections::HashMap::new();
+    for _ in 0..6 {
+        let (target, message) = receiver.recv().await.expect("Relayed message");
+        let sender = if target == "test_client_2" { "test_client_1" } else { "test_client_2" };
+        let sequence = match message.payload {
+            Payload::SignalIceCandidate(payload) => payload.sequence.expect("Sequence number assigned"),
+            other => panic!("Unexpected payload: {:?}", other),
+        };
+        let previous = last_seen.insert(sender.to_string(), sequence).unwrap_or(0);
+        assert_eq!(sequence, previous + 1);
+    }
+
+    let room_state = session_manager.get_room_state("room_1").await.expect("Room state");
+    assert_eq!(room_state.sequence_counters.get("test_client_1"), Some(&3));
+    assert_eq!(room_state.sequence_counters.get("test_client_2"), Some(&3));
+}
+
+#[tokio::test]
+async fn test_observer_receives_but_cannot_send_signals() {
+    let config = Config::default();
+    let auth_manager = Arc::new(AuthManager::new(Arc::new(config)));
+    let (session_manager, mut receiver) = SessionManager::new(auth_manager);
+
+    session_manager.handle_connect("test_client_1".to_string(), "test_token_1".to_string()).await.unwrap();
+    session_manager.handle_connect("test_client_2".to_string(), "test_token_2".to_string()).await.unwrap();
+    session_manager.set_member_role("room_1", "test_client_1", "sender").await;
+    session_manager.add_observer("room_1", "test_client_2").await;
+    session_manager.set_member_role("room_1", "test_client_2", "observer").await;
+    assert!(session_manager.is_observer("room_1", "test_client_2").await);
+    assert!(!session_manager.is_observer("room_2", "test_client_2").await);
+
+    let signal = |target: &str, room_id: Option<&str>| {
+        Message::new(MessageType::SignalOffer, Payload::SignalOffer(SignalPayload {
+            target_client_id: target.to_string(),
+            signal_data: "sdp".to_string(),
+            room_id: room_id.map(str::to_string),
+            sequence: None,
+        }))
+    };
+
+    // Signals towards the observer are delivered
+    session_manager.route_message("test_client_1".to_string(), signal("test_client_2", Some("room_1"))).await.unwrap();
+    let (target, _) = receiver.recv().await.expect("Relayed message");
+    assert_eq!(target, "test_client_2");
+
+    // Signals from the observer are rejected in its room, and outside any room towards its peers
+    for room_id in [Some("room_1"), None] {
+        let result = session_manager.route_message("test_client_2".to_string(), signal("test_client_1", room_id)).await;
+        assert!(matches!(result, Err(signal_manager_service::Error::Session(_))));
+        assert!(receiver.try_recv().is_err());
+    }
+
+    // Observing one room does not make it read-only in another
+    session_manager.route_message("test_client_2".to_string(), signal("test_client_1", Some("room_2"))).await.unwrap();
+    let (target, _) = receiver.recv().await.expect("Relayed message");
+    assert_eq!(target, "test_client_1");
+
+    session_manager.handle_disconnect("test_client_2").await.unwrap();
+    assert!(!session_manager.is_observer("room_1", "test_client_2").await);
+}
+
 #[tokio::test]
 async fn test_heartbeat_handling() {
     // Test heartbeat message handling
@@ -253,7 +376,7 @@ async fn test_session_cleanup() {
 #[test]
 fn test_protocol_constants() {
     // Test that protocol constants are correctly defined
-    assert_eq!(signal_manager_service::message::START_BYTE, 0xAA);
+    assert_eq!(signal_manager_service::message::START_BYTE, 0xAC);
     
     // Test message type values
     assert_eq!(MessageType::Connect as u8, 0x01);
@@ -271,7 +394,7 @@ fn test_protocol_constants() {
 async fn test_server_component_integration() {
     // Test that all server components work together
     let config = Config::default();
-    let _server = WebSocketServer::new(config);
+    let _server = WebSocketServer::new_for_test(config);
     
     // Verify server was created successfully
     // The server creation should not panic
@@ -319,53 +442,2487 @@ async fn test_websocket_server_accepts_connection() {
 
 #[tokio::test]
 async fn test_server_handles_invalid_frames() {
-    use tokio_tungstenite::connect_async;
+    use tokio_tungstenite::tungstenite::Message as WsMessage;
+    use futures_util::SinkExt;
+    use tokio::time::Duration;
+
+    let (addr, server_handle) = harness::spawn_test_server(Config::default()).await;
+    let mut client = harness::connect_client(addr).await;
+
+    // Send invalid frame structure
+    let invalid_data = vec![0x00, 0x01, 0x02, 0x03]; // Invalid protocol data
+    client.send(WsMessage::Binary(invalid_data)).await.expect("Failed to send invalid frame");
+
+    match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::Error(error), .. }) => assert_eq!(error.error_code, 2),
+        other => panic!("Expected malformed frame error, got {:?}", other),
+    }
+
+    // The same connection must still process valid frames
+    let valid_message = Message::new(
+        MessageType::Connect,
+        Payload::Connect(ConnectPayload {
+            client_id: "test_client_1".to_string(),
+            auth_token: "test_token_1".to_string(),
+        })
+    );
+    harness::send_message(&mut client, valid_message).await;
+
+    match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::ConnectAck(ack), .. }) => assert_eq!(ack.status, "success"),
+        other => panic!("Expected ConnectAck after recovering from invalid frame, got {:?}", other),
+    }
+
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_server_frame_checksums() {
+    use signal_manager_service::message::FrameOptions;
     use tokio_tungstenite::tungstenite::Message as WsMessage;
     use futures_util::{SinkExt, StreamExt};
-    use tokio::time::{sleep, Duration};
+    use tokio::time::{timeout, Duration};
 
-    // Use a fixed port for testing
     let mut config = Config::default();
-    config.server.port = 8082; // Use a different port to avoid conflicts
-    let server = WebSocketServer::new(config).unwrap();
+    config.server.frame_checksums = true;
+    let options = FrameOptions::from_config(&config);
+    let (addr, server_handle) = harness::spawn_test_server(config).await;
+    let mut client = harness::connect_client(addr).await;
 
-    // Start the server in the background
-    let server_handle = tokio::spawn(async move {
-        server.run().await.unwrap();
-    });
+    async fn recv(client: &mut harness::TestClient, options: FrameOptions) -> Message {
+        loop {
+            match timeout(Duration::from_secs(5), client.next()).await {
+                Ok(Some(Ok(WsMessage::Binary(data)))) => {
+                    return Message::from_binary_with(&data, options).expect("Server frame failed its checksum");
+                }
+                Ok(Some(Ok(WsMessage::Ping(_)))) | Ok(Some(Ok(WsMessage::Pong(_)))) => continue,
+                other => panic!("Expected a binary frame, got {:?}", other),
+            }
+        }
+    }
 
-    // Wait for server to start
-    sleep(Duration::from_millis(500)).await;
+    let connect = Message::new(
+        MessageType::Connect,
+        Payload::Connect(ConnectPayload {
+            client_id: "test_client_1".to_string(),
+            auth_token: "test_token_1".to_string(),
+        })
+    );
+    let mut corrupted = connect.to_binary_with(options).unwrap();
+    corrupted[2] ^= 0x80;
+    client.send(WsMessage::Binary(corrupted)).await.expect("Failed to send corrupted frame");
+    match recv(&mut client, options).await {
+        Message { payload: Payload::Error(error), .. } => {
+            assert_eq!(error.error_code, 2);
+            assert!(error.error_message.contains("Checksum mismatch"), "{}", error.error_message);
+        }
+        other => panic!("Expected checksum error, got {:?}", other),
+    }
 
-    // Connect to the server
-    let url = "ws://127.0.0.1:8082";
-    let (ws_stream, _) = connect_async(url).await.expect("Failed to connect");
-    let (mut write, _read) = ws_stream.split();
+    client.send(WsMessage::Binary(connect.to_binary_with(options).unwrap())).await.expect("Failed to send Connect");
+    match recv(&mut client, options).await {
+        Message { payload: Payload::ConnectAck(ack), .. } => assert_eq!(ack.status, "success"),
+        other => panic!("Expected ConnectAck, got {:?}", other),
+    }
 
-    // Send invalid frame structure
-    let invalid_data = vec![0x00, 0x01, 0x02, 0x03]; // Invalid protocol data
-    write.send(WsMessage::Binary(invalid_data)).await.expect("Failed to send invalid frame");
+    // A client that doesn't send trailers yet is still served, without trailers
+    let mut plain_client = harness::connect_client(addr).await;
+    let connect = Message::new(
+        MessageType::Connect,
+        Payload::Connect(ConnectPayload {
+            client_id: "test_client_2".to_string(),
+            auth_token: "test_token_2".to_string(),
+        })
+    );
+    plain_client.send(WsMessage::Binary(connect.to_binary().unwrap())).await.expect("Failed to send Connect");
+    let ack = loop {
+        match timeout(Duration::from_secs(5), plain_client.next()).await {
+            Ok(Some(Ok(WsMessage::Binary(data)))) => break data,
+            Ok(Some(Ok(WsMessage::Ping(_)))) | Ok(Some(Ok(WsMessage::Pong(_)))) => continue,
+            other => panic!("Expected a binary frame, got {:?}", other),
+        }
+    };
+    let payload_length = u32::from_be_bytes(ack[19..23].try_into().unwrap()) as usize;
+    assert_eq!(ack.len(), 23 + payload_length, "Reply to a client without trailers carried one");
+    match Message::from_binary(&ack).unwrap().payload {
+        Payload::ConnectAck(ack) => assert_eq!(ack.status, "success"),
+        other => panic!("Expected ConnectAck, got {:?}", other),
+    }
+
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_server_compresses_replies_only_for_clients_that_compress() {
+    use signal_manager_service::message::{PayloadType, PAYLOAD_TYPE_ZSTD_FLAG};
+    use tokio_tungstenite::tungstenite::Message as WsMessage;
+    use futures_util::{SinkExt, StreamExt};
+    use tokio::time::{timeout, Duration};
+
+    let mut config = Config::default();
+    config.server.compression_threshold_bytes = 16;
+    config.auth.api_keys.push("test_client_3:test_token_3".to_string());
+    let (addr, server_handle) = harness::spawn_test_server(config).await;
+
+    let connect = |client_id: &str, auth_token: &str| Message::new(
+        MessageType::Connect,
+        Payload::Connect(ConnectPayload {
+            client_id: client_id.to_string(),
+            auth_token: auth_token.to_string(),
+        }),
+    );
+    let clients = [
+        ("test_client_1", connect("test_client_1", "test_token_1").to_binary().unwrap(), PayloadType::Json as u8),
+        ("test_client_2", connect("test_client_2", "test_token_2").to_binary_gzipped(16).unwrap(), PayloadType::JsonGzip as u8),
+        ("test_client_3", connect("test_client_3", "test_token_3").to_binary_compressed(16).unwrap(), PayloadType::Json as u8 | PAYLOAD_TYPE_ZSTD_FLAG),
+    ];
+    for (client_id, frame, expected_type) in clients {
+        let mut client = harness::connect_client(addr).await;
+        client.send(WsMessage::Binary(frame)).await.expect("Failed to send Connect");
+        let ack = loop {
+            match timeout(Duration::from_secs(5), client.next()).await {
+                Ok(Some(Ok(WsMessage::Binary(data)))) => break data,
+                Ok(Some(Ok(WsMessage::Ping(_)))) | Ok(Some(Ok(WsMessage::Pong(_)))) => continue,
+                other => panic!("Expected a binary frame, got {:?}", other),
+            }
+        };
+        assert_eq!(ack[18], expected_type, "ConnectAck payload type for {client_id}");
+        match Message::from_binary(&ack).unwrap().payload {
+            Payload::ConnectAck(ack) => assert_eq!(ack.status, "success"),
+            other => panic!("Expected ConnectAck, got {:?}", other),
+        }
+    }
+
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_server_closes_legacy_protocol_connections() {
+    use tokio_tungstenite::tungstenite::Message as WsMessage;
+    use futures_util::{SinkExt, StreamExt};
+    use tokio::time::{timeout, Duration};
+
+    let (addr, server_handle) = harness::spawn_test_server(Config::default()).await;
+    let mut client = harness::connect_client(addr).await;
+
+    // A Connect framed with the old start byte and 2-byte payload length
+    let payload = br#"{"Connect":{"client_id":"test_client_1","auth_token":"test_token_1"}}"#;
+    let mut frame = vec![signal_manager_service::message::LEGACY_START_BYTE, MessageType::Connect as u8];
+    frame.extend_from_slice(uuid::Uuid::new_v4().as_bytes());
+    frame.push(signal_manager_service::message::PayloadType::Json as u8);
+    frame.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+    frame.extend_from_slice(payload);
+    client.send(WsMessage::Binary(frame)).await.expect("Failed to send legacy frame");
+
+    match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::Error(error), .. }) => assert_eq!(error.error_code, 14),
+        other => panic!("Expected unsupported protocol version error, got {:?}", other),
+    }
+    match timeout(Duration::from_secs(5), client.next()).await {
+        Ok(None) | Ok(Some(Err(_))) | Ok(Some(Ok(WsMessage::Close(_)))) => {}
+        other => panic!("Expected the server to close the connection, got {:?}", other),
+    }
+
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_server_rejects_text_envelopes_without_closing() {
+    use tokio_tungstenite::tungstenite::Message as WsMessage;
+    use futures_util::SinkExt;
+    use tokio::time::Duration;
 
-    // Wait a moment for processing
-    sleep(Duration::from_millis(100)).await;
+    let (addr, server_handle) = harness::spawn_test_server(Config::default()).await;
+    let mut client = harness::connect_client(addr).await;
+
+    // The server has no type-2 JSON text path: every text envelope, well-formed or not,
+    // gets the same binary error and the connection stays open
+    let envelopes = [
+        serde_json::json!({ "data": { "type": "REGISTER" } }).to_string(),
+        serde_json::json!({ "type": 2, "data": { "type": "NOT_A_TYPE" } }).to_string(),
+        r#"{"type": 2, "data": {"type": "REGISTER", "#.to_string(),
+        serde_json::json!({ "type": 2, "data": { "type": "REGISTER", "padding": "x".repeat(64 * 1024) } }).to_string(),
+    ];
+    for envelope in envelopes {
+        client.send(WsMessage::Text(envelope)).await.expect("Failed to send text frame");
+        match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+            Some(Message { payload: Payload::Error(error), .. }) => assert_eq!(error.error_code, 3),
+            other => panic!("Expected text frame error, got {:?}", other),
+        }
+    }
 
-    // Send a valid frame to test that connection is still alive
     let valid_message = Message::new(
         MessageType::Connect,
         Payload::Connect(ConnectPayload {
-            client_id: "test_client".to_string(),
+            client_id: "test_client_1".to_string(),
             auth_token: "test_token_1".to_string(),
         })
     );
-    let valid_binary = valid_message.to_binary().unwrap();
-    write.send(WsMessage::Binary(valid_binary)).await.expect("Failed to send valid frame");
+    harness::send_message(&mut client, valid_message).await;
 
-    // Wait for response
-    sleep(Duration::from_millis(100)).await;
+    match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::ConnectAck(ack), .. }) => assert_eq!(ack.status, "success"),
+        other => panic!("Expected ConnectAck after rejected text frames, got {:?}", other),
+    }
 
-    // Clean up
-    drop(server_handle);
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_server_rejects_oversized_frames() {
+    use tokio_tungstenite::tungstenite::Message as WsMessage;
+    use futures_util::{SinkExt, StreamExt};
+    use tokio::time::{timeout, Duration};
+
+    // A small protocol-level frame limit, served on an ephemeral port
+    let mut config = Config::default();
+    config.server.max_message_size = 1024;
+    config.server.max_frame_size = 1024;
+    let (addr, server_handle) = harness::spawn_test_server(config).await;
+
+    let (mut write, mut read) = harness::connect_client(addr).await.split();
+
+    // Frame larger than the configured protocol max and its slack
+    write.send(WsMessage::Binary(vec![0u8; 128 * 1024])).await.expect("Failed to send oversized frame");
+
+    // tungstenite rejects the frame and the server drops the connection
+    let result = timeout(Duration::from_secs(2), read.next()).await
+        .expect("Server did not close the connection");
+    server_handle.abort();
+
+    match result {
+        None | Some(Err(_)) | Some(Ok(WsMessage::Close(_))) => {}
+        Some(Ok(other)) => panic!("Expected connection close, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_message_size_limit_boundary() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio::time::{timeout, Duration};
+    use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+    let mut config = Config::default();
+    config.server.max_message_size = 1024;
+    let (addr, handle) = harness::spawn_test_server(config).await;
+    let mut client = harness::connect_client(addr).await;
+
+    // A message of exactly the limit reaches the parser, which reports it malformed
+    client.send(WsMessage::Binary(vec![0u8; 1024])).await.expect("Failed to send frame");
+    match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::Error(error), .. }) => assert_eq!(error.error_code, 2),
+        other => panic!("Expected malformed message error, got {:?}", other),
+    }
+
+    // One byte more is answered with a size error and the connection stays open
+    client.send(WsMessage::Binary(vec![0u8; 1025])).await.expect("Failed to send frame");
+    match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::Error(error), .. }) => assert_eq!(error.error_code, 9),
+        other => panic!("Expected message size error, got {:?}", other),
+    }
+    client.send(WsMessage::Binary(vec![0u8; 1024])).await.expect("Connection closed after the size error");
+    match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::Error(error), .. }) => assert_eq!(error.error_code, 2),
+        other => panic!("Expected malformed message error, got {:?}", other),
+    }
+
+    // Far past the limit, the protocol layer refuses the message and closes the connection
+    let _ = client.send(WsMessage::Binary(vec![0u8; 1024 * 1024])).await;
+    let result = timeout(Duration::from_secs(5), client.next()).await.expect("Server did not close the connection");
+    match result {
+        None | Some(Err(_)) | Some(Ok(WsMessage::Close(_))) => {}
+        Some(Ok(other)) => panic!("Expected connection close, got {:?}", other),
+    }
+
+    handle.abort();
+}
+
+async fn send_connect(client: &mut harness::TestClient, client_id: &str, auth_token: &str) -> Option<Message> {
+    harness::send_message(client, Message::new(
+        MessageType::Connect,
+        Payload::Connect(ConnectPayload {
+            client_id: client_id.to_string(),
+            auth_token: auth_token.to_string(),
+        }),
+    )).await;
+    harness::recv_message(client, tokio::time::Duration::from_secs(5)).await
+}
+
+#[tokio::test]
+async fn test_connect_with_wrong_token_is_refused_and_closed() {
+    use futures_util::StreamExt;
+    use signal_manager_service::server::AUTH_FAILED_REASON;
+    use tokio::time::{timeout, Duration};
+    use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+    use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+    let (addr, server, handle) = harness::spawn_test_server_instance(Config::default()).await;
+    let mut client = harness::connect_client(addr).await;
+
+    match send_connect(&mut client, "test_client_1", "wrong_token").await {
+        Some(Message { message_type: MessageType::Error, payload: Payload::Error(error), .. }) => {
+            assert_eq!(error.error_code, 1);
+            assert_eq!(error.error_message, "Authentication failed");
+        }
+        other => panic!("Expected authentication error, got {:?}", other),
+    }
+    match timeout(Duration::from_secs(5), client.next()).await {
+        Ok(Some(Ok(WsMessage::Close(Some(frame))))) => {
+            assert_eq!(frame.code, CloseCode::Policy);
+            assert_eq!(frame.reason, AUTH_FAILED_REASON);
+        }
+        other => panic!("Expected a 1008 close frame, got {:?}", other),
+    }
+
+    assert!(!server.is_connected("test_client_1").await);
+    assert!(server.active_sessions().await.is_empty());
+    assert_eq!(server.metrics().auth_failures(), 1);
+
+    // The refusal leaves nothing behind that would stop the right token from working
+    let _client = harness::connect_authenticated(addr, "test_client_1", "test_token_1").await;
+    assert!(server.is_connected("test_client_1").await);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_message_pipelined_behind_connect_is_handled() {
+    use signal_manager_service::config::ConnectAckOrder;
+    use signal_manager_service::message::HeartbeatPayload;
+    use tokio::time::Duration;
+
+    for order in [ConnectAckOrder::AfterPersist, ConnectAckOrder::BeforePersist] {
+        let mut config = Config::default();
+        config.server.connect_ack_order = order;
+        let (addr, server, handle) = harness::spawn_test_server_instance(config).await;
+        let mut client = harness::connect_client(addr).await;
+
+        // The heartbeat goes out before the ack arrives, so it relies on the session being ready
+        harness::send_message(&mut client, Message::new(MessageType::Connect, Payload::Connect(ConnectPayload {
+            client_id: "test_client_1".to_string(),
+            auth_token: "test_token_1".to_string(),
+        }))).await;
+        harness::send_message(&mut client, Message::new(MessageType::Heartbeat, Payload::Heartbeat(HeartbeatPayload { timestamp: 1 }))).await;
+
+        let session_id = match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+            Some(Message { payload: Payload::ConnectAck(ack), .. }) => {
+                assert_eq!(ack.status, "success");
+                ack.session_id
+            }
+            other => panic!("Expected ConnectAck with {:?}, got {:?}", order, other),
+        };
+        // Checked the moment the ack arrives: with the default order the session and the
+        // connection already exist
+        assert!(server.active_sessions().await.iter().any(|session| session.session_id == session_id));
+        if order == ConnectAckOrder::AfterPersist {
+            assert!(server.is_connected("test_client_1").await);
+        }
+        match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+            Some(Message { payload: Payload::HeartbeatAck(_), .. }) => {}
+            other => panic!("Expected HeartbeatAck with {:?}, got {:?}", order, other),
+        }
+        handle.abort();
+    }
+}
+
+#[tokio::test]
+async fn test_acked_client_is_reachable_by_peers() {
+    use tokio::time::Duration;
+
+    let (addr, handle) = harness::spawn_test_server(Config::default()).await;
+    let mut peer = harness::connect_authenticated(addr, "test_client_1", "test_token_1").await;
+    let mut client = harness::connect_client(addr).await;
+    assert!(matches!(send_connect(&mut client, "test_client_2", "test_token_2").await,
+        Some(Message { payload: Payload::ConnectAck(_), .. })));
+
+    // With the default ack order the connection is registered before the ack is sent
+    harness::send_message(&mut peer, Message::new(MessageType::SignalOffer, Payload::SignalOffer(SignalPayload {
+        target_client_id: "test_client_2".to_string(),
+        signal_data: "offer".to_string(),
+        room_id: None,
+        sequence: None,
+    }))).await;
+    match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::SignalOffer(signal), .. }) => assert_eq!(signal.signal_data, "offer"),
+        other => panic!("Expected the peer's offer, got {:?}", other),
+    }
+    assert!(harness::recv_message(&mut peer, Duration::from_millis(200)).await.is_none());
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_duplicate_connect_rejected_by_default() {
+    let (addr, server, handle) = harness::spawn_test_server_instance(Config::default()).await;
+    let mut client = harness::connect_client(addr).await;
+
+    let first = send_connect(&mut client, "test_client_1", "test_token_1").await;
+    let first_session = match first {
+        Some(Message { payload: Payload::ConnectAck(ack), .. }) => ack.session_id,
+        other => panic!("Expected ConnectAck, got {:?}", other),
+    };
+
+    match send_connect(&mut client, "test_client_1", "test_token_1").await {
+        Some(Message { payload: Payload::Error(error), .. }) => assert_eq!(error.error_code, 5),
+        other => panic!("Expected duplicate Connect to be rejected, got {:?}", other),
+    }
+
+    let sessions = server.active_sessions().await;
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].session_id, first_session);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_duplicate_connect_replaces_session() {
+    let mut config = Config::default();
+    config.server.duplicate_connect_policy = signal_manager_service::config::DuplicateConnectPolicy::Replace;
+    let (addr, server, handle) = harness::spawn_test_server_instance(config).await;
+    let mut client = harness::connect_client(addr).await;
+
+    assert!(matches!(send_connect(&mut client, "test_client_1", "test_token_1").await,
+        Some(Message { payload: Payload::ConnectAck(_), .. })));
+
+    // Reconnecting as another identity on the same socket must not leave the first session behind
+    let replaced = match send_connect(&mut client, "test_client_2", "test_token_2").await {
+        Some(Message { payload: Payload::ConnectAck(ack), .. }) => ack.session_id,
+        other => panic!("Expected ConnectAck, got {:?}", other),
+    };
+
+    let sessions = server.active_sessions().await;
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].client_id, "test_client_2");
+    assert_eq!(sessions[0].session_id, replaced);
+    assert_eq!(server.dropped_frames("test_client_1").await, None);
+    assert_eq!(server.dropped_frames("test_client_2").await, Some(0));
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_reconnect_within_grace_replaces_stale_session() {
+    let mut config = Config::default();
+    config.session.reconnect_grace_secs = 30;
+    let (addr, server, handle) = harness::spawn_test_server_instance(config).await;
+    let mut client = harness::connect_client(addr).await;
+
+    let stale_session = match send_connect(&mut client, "test_client_1", "test_token_1").await {
+        Some(Message { payload: Payload::ConnectAck(ack), .. }) => ack.session_id,
+        other => panic!("Expected ConnectAck, got {:?}", other),
+    };
+    drop(client);
+
+    // The socket is gone but the session is kept for the grace window
+    tokio::time::timeout(tokio::time::Duration::from_secs(5), async {
+        while server.is_connected("test_client_1").await {
+            tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+        }
+    }).await.expect("Dropped socket was never removed from the connections map");
+    assert_eq!(server.active_sessions().await.len(), 1);
+
+    let mut client = harness::connect_client(addr).await;
+    let session = match send_connect(&mut client, "test_client_1", "test_token_1").await {
+        Some(Message { payload: Payload::ConnectAck(ack), .. }) => ack.session_id,
+        other => panic!("Expected the reconnect to be accepted, got {:?}", other),
+    };
+    assert_ne!(session, stale_session);
+    assert_eq!(server.metrics().sessions_replaced(), 1);
+    assert_eq!(server.metrics().duplicate_sessions_rejected(), 0);
+    assert!(server.is_connected("test_client_1").await);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_connect_while_session_open_elsewhere_is_rejected() {
+    let mut config = Config::default();
+    config.session.reconnect_grace_secs = 30;
+    // Without a session limit, which would evict the first socket instead
+    config.session.max_sessions_per_client = 0;
+    let (addr, server, handle) = harness::spawn_test_server_instance(config).await;
+    let mut first = harness::connect_client(addr).await;
+    assert!(matches!(send_connect(&mut first, "test_client_1", "test_token_1").await,
+        Some(Message { payload: Payload::ConnectAck(_), .. })));
+
+    let mut second = harness::connect_client(addr).await;
+    match send_connect(&mut second, "test_client_1", "test_token_1").await {
+        Some(Message { payload: Payload::Error(error), .. }) => assert_eq!(error.error_code, 13),
+        other => panic!("Expected the duplicate session to be rejected, got {:?}", other),
+    }
+    assert_eq!(server.metrics().duplicate_sessions_rejected(), 1);
+    assert_eq!(server.metrics().sessions_replaced(), 0);
+    assert!(server.metrics().render().contains("signal_manager_duplicate_sessions_rejected_total 1"));
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_connect_taking_over_a_live_session_is_not_counted_as_replaced() {
+    let mut config = Config::default();
+    config.session.reconnect_grace_secs = 0;
+    config.session.max_sessions_per_client = 0;
+    let (addr, server, handle) = harness::spawn_test_server_instance(config).await;
+    let mut first = harness::connect_client(addr).await;
+    assert!(matches!(send_connect(&mut first, "test_client_1", "test_token_1").await,
+        Some(Message { payload: Payload::ConnectAck(_), .. })));
+
+    let mut second = harness::connect_client(addr).await;
+    assert!(matches!(send_connect(&mut second, "test_client_1", "test_token_1").await,
+        Some(Message { payload: Payload::ConnectAck(_), .. })));
+    assert_eq!(server.metrics().sessions_replaced(), 0);
+    assert_eq!(server.metrics().duplicate_sessions_rejected(), 0);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_connect_over_session_limit_is_rejected() {
+    use signal_manager_service::config::SessionLimitPolicy;
+
+    let mut config = Config::default();
+    config.session.session_limit_policy = SessionLimitPolicy::Reject;
+    let (addr, server, handle) = harness::spawn_test_server_instance(config).await;
+    let mut first = harness::connect_client(addr).await;
+    let first_session = match send_connect(&mut first, "test_client_1", "test_token_1").await {
+        Some(Message { payload: Payload::ConnectAck(ack), .. }) => ack.session_id,
+        other => panic!("Expected ConnectAck, got {:?}", other),
+    };
+
+    let mut second = harness::connect_client(addr).await;
+    match send_connect(&mut second, "test_client_1", "test_token_1").await {
+        Some(Message { payload: Payload::Error(error), .. }) => {
+            assert_eq!(error.error_code, 17);
+            assert!(error.error_message.contains("max_sessions_per_client"), "Unexpected error: {}", error.error_message);
+        }
+        other => panic!("Expected the Connect over the session limit to be rejected, got {:?}", other),
+    }
+
+    // The first socket keeps its session and still gets answers
+    let sessions = server.active_sessions().await;
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].session_id, first_session);
+    assert!(matches!(send_connect(&mut first, "test_client_1", "test_token_1").await,
+        Some(Message { payload: Payload::Error(error), .. }) if error.error_code == 5));
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_connect_over_session_limit_evicts_oldest() {
+    use futures_util::StreamExt;
+    use signal_manager_service::config::SessionLimitPolicy;
+    use signal_manager_service::server::SESSION_EVICTED_REASON;
+    use tokio::time::{timeout, Duration};
+    use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+    use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+    assert_eq!(Config::default().session.session_limit_policy, SessionLimitPolicy::EvictOldest);
+    let (addr, server, handle) = harness::spawn_test_server_instance(Config::default()).await;
+    let mut oldest = harness::connect_client(addr).await;
+    assert!(matches!(send_connect(&mut oldest, "test_client_1", "test_token_1").await,
+        Some(Message { payload: Payload::ConnectAck(_), .. })));
+
+    let mut newest = harness::connect_client(addr).await;
+    let session = match send_connect(&mut newest, "test_client_1", "test_token_1").await {
+        Some(Message { payload: Payload::ConnectAck(ack), .. }) => ack.session_id,
+        other => panic!("Expected the new Connect to be accepted, got {:?}", other),
+    };
+
+    match harness::recv_message(&mut oldest, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::Disconnect(disconnect), .. }) => {
+            assert_eq!(disconnect.client_id, "test_client_1");
+            assert_eq!(disconnect.reason, SESSION_EVICTED_REASON);
+        }
+        other => panic!("Expected Disconnect, got {:?}", other),
+    }
+    match timeout(Duration::from_secs(5), oldest.next()).await {
+        Ok(Some(Ok(WsMessage::Close(Some(frame))))) => {
+            assert_eq!(frame.code, CloseCode::Policy);
+            assert_eq!(frame.reason, SESSION_EVICTED_REASON);
+        }
+        other => panic!("Expected a 1008 close frame, got {:?}", other),
+    }
+
+    // Tearing down the evicted socket must leave the new session in place
+    let limiter = server.connection_limiter();
+    timeout(Duration::from_secs(5), async {
+        while limiter.live() > 1 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Evicted socket never released its slot");
+    let sessions = server.active_sessions().await;
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].session_id, session);
+    assert!(server.is_connected("test_client_1").await);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_register_evicting_a_connected_client_closes_its_socket() {
+    use futures_util::StreamExt;
+    use signal_manager_service::config::EvictionPolicy;
+    use signal_manager_service::server::CLIENT_EVICTED_REASON;
+    use tokio::time::{timeout, Duration};
+    use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+    use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+    let mut config = Config::default();
+    config.database.max_clients = 1;
+    config.database.eviction_policy = EvictionPolicy::Lru;
+    let (addr, server, handle) = harness::spawn_test_server_instance(config).await;
+    let mut evicted = harness::connect_authenticated(addr, "test_client_1", "test_token_1").await;
+    register_with_capabilities(&mut evicted, "test_client_1", "test_token_1", &[]).await;
+    let mut newest = harness::connect_authenticated(addr, "test_client_2", "test_token_2").await;
+    register_with_capabilities(&mut newest, "test_client_2", "test_token_2", &[]).await;
+
+    match harness::recv_message(&mut evicted, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::Disconnect(disconnect), .. }) => {
+            assert_eq!(disconnect.client_id, "test_client_1");
+            assert_eq!(disconnect.reason, CLIENT_EVICTED_REASON);
+        }
+        other => panic!("Expected Disconnect, got {:?}", other),
+    }
+    match timeout(Duration::from_secs(5), evicted.next()).await {
+        Ok(Some(Ok(WsMessage::Close(Some(frame))))) => {
+            assert_eq!(frame.code, CloseCode::Policy);
+            assert_eq!(frame.reason, CLIENT_EVICTED_REASON);
+        }
+        other => panic!("Expected a 1008 close frame, got {:?}", other),
+    }
+    assert!(!server.is_connected("test_client_1").await);
+    assert!(server.is_connected("test_client_2").await);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_connection_without_heartbeats_is_closed() {
+    use futures_util::StreamExt;
+    use signal_manager_service::message::HeartbeatPayload;
+    use signal_manager_service::server::HEARTBEAT_TIMEOUT_REASON;
+    use tokio::time::{timeout, Duration};
+    use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+    use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+    let mut config = Config::default();
+    config.server.heartbeat_interval = 1;
+    config.server.heartbeat_timeout_multiplier = 2;
+    config.session.reconnect_grace_secs = 30;
+    let (addr, server, handle) = harness::spawn_test_server_instance(config).await;
+    let mut client = harness::connect_authenticated(addr, "test_client_1", "test_token_1").await;
 
-    // The test passes if we reach here without panicking
-    // The server should have logged a warning about the invalid frame but kept the connection open
-} 
\ No newline at end of file
+    // Heartbeating keeps the connection open past the timeout
+    for _ in 0..6 {
+        harness::send_message(&mut client, Message::new(
+            MessageType::Heartbeat,
+            Payload::Heartbeat(HeartbeatPayload { timestamp: 0 }),
+        )).await;
+        assert!(matches!(harness::recv_message(&mut client, Duration::from_secs(5)).await,
+            Some(Message { payload: Payload::HeartbeatAck(_), .. })));
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+    assert!(server.is_connected("test_client_1").await);
+
+    // Once heartbeats stop, the server closes the socket within two intervals
+    match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::Disconnect(disconnect), .. }) => {
+            assert_eq!(disconnect.client_id, "test_client_1");
+            assert_eq!(disconnect.reason, HEARTBEAT_TIMEOUT_REASON);
+        }
+        other => panic!("Expected Disconnect, got {:?}", other),
+    }
+    match timeout(Duration::from_secs(5), client.next()).await {
+        Ok(Some(Ok(WsMessage::Close(Some(frame))))) => {
+            assert_eq!(frame.code, CloseCode::Policy);
+            assert_eq!(frame.reason, HEARTBEAT_TIMEOUT_REASON);
+        }
+        other => panic!("Expected a 1008 close frame, got {:?}", other),
+    }
+
+    // The session ends at once rather than waiting out the reconnect grace
+    timeout(Duration::from_secs(5), async {
+        while server.is_connected("test_client_1").await || !server.active_sessions().await.is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Timed-out client was not disconnected");
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_client_pings_keep_connection_alive() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio::time::{timeout, Duration};
+    use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+    let mut config = Config::default();
+    config.server.heartbeat_interval = 1;
+    config.server.heartbeat_timeout_multiplier = 2;
+    config.session.session_timeout = 1;
+    config.session.cleanup_interval = 1;
+    let (addr, server, handle) = harness::spawn_test_server_instance(config).await;
+    let mut client = harness::connect_authenticated(addr, "test_client_1", "test_token_1").await;
+
+    // Transport pings alone, with no Heartbeat messages, for well past the timeout
+    for _ in 0..6 {
+        client.send(WsMessage::Ping(b"keepalive".to_vec())).await.unwrap();
+        match timeout(Duration::from_secs(5), client.next()).await {
+            Ok(Some(Ok(WsMessage::Pong(data)))) => assert_eq!(data, b"keepalive"),
+            other => panic!("Expected Pong, got {:?}", other),
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+    assert!(server.is_connected("test_client_1").await);
+    assert!(server.active_sessions().await.iter().any(|session| session.client_id == "test_client_1"),
+        "Ping-only client lost its session to the idle sweep");
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_server_pings_keep_responsive_connection_alive() {
+    use futures_util::StreamExt;
+    use tokio::time::{timeout, Duration, Instant};
+    use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+    let mut config = Config::default();
+    config.server.heartbeat_interval = 1;
+    config.server.heartbeat_timeout_multiplier = 2;
+    config.server.ping_interval_secs = 1;
+    config.session.session_timeout = 2;
+    config.session.cleanup_interval = 1;
+    let (addr, server, handle) = harness::spawn_test_server_instance(config).await;
+    let mut client = harness::connect_authenticated(addr, "test_client_1", "test_token_1").await;
+
+    // The client only reads, which answers each server ping with a pong
+    let mut pings = 0;
+    let until = Instant::now() + Duration::from_millis(3500);
+    while let Ok(next) = timeout(until.saturating_duration_since(Instant::now()), client.next()).await {
+        match next {
+            Some(Ok(WsMessage::Ping(_))) => pings += 1,
+            other => panic!("Expected only pings, got {:?}", other),
+        }
+    }
+    assert!(pings >= 3, "Expected a ping every second, got {pings}");
+    assert!(server.is_connected("test_client_1").await);
+    assert!(server.active_sessions().await.iter().any(|session| session.client_id == "test_client_1"),
+        "Pong-only client lost its session to the idle sweep");
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_server_rejects_timestamp_outside_clock_skew() {
+    let (addr, handle) = harness::spawn_test_server(Config::default()).await;
+    let mut client = harness::connect_client(addr).await;
+
+    let connect = Message::new(
+        MessageType::Connect,
+        Payload::Connect(ConnectPayload {
+            client_id: "test_client_1".to_string(),
+            auth_token: "test_token_1".to_string(),
+        }),
+    );
+    let now = signal_manager_service::message::now_millis();
+
+    harness::send_message(&mut client, connect.clone().with_created_at(now - 120_000)).await;
+    match harness::recv_message(&mut client, tokio::time::Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::Error(error), .. }) => assert_eq!(error.error_code, 6),
+        other => panic!("Expected skewed message to be rejected, got {:?}", other),
+    }
+
+    harness::send_message(&mut client, connect.with_created_at(now)).await;
+    match harness::recv_message(&mut client, tokio::time::Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::ConnectAck(ack), .. }) => assert_eq!(ack.status, "success"),
+        other => panic!("Expected ConnectAck for timestamped Connect, got {:?}", other),
+    }
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_ice_candidates_capped_per_session() {
+    let config = Config::default();
+    let auth_manager = Arc::new(AuthManager::new(Arc::new(config)));
+    let (session_manager, mut receiver) = SessionManager::new(auth_manager);
+    let session_manager = session_manager.with_max_ice_candidates_per_room(3);
+
+    session_manager.handle_connect("test_client_1".to_string(), "test_token_1".to_string()).await.unwrap();
+    session_manager.handle_connect("test_client_2".to_string(), "test_token_2".to_string()).await.unwrap();
+    session_manager.set_member_role("room_1", "test_client_1", "sender").await;
+    session_manager.set_member_role("room_1", "test_client_2", "receiver").await;
+
+    let candidate = |target: &str, room_id: Option<&str>| {
+        Message::new(MessageType::SignalIceCandidate, Payload::SignalIceCandidate(SignalPayload {
+            target_client_id: target.to_string(),
+            signal_data: "candidate".to_string(),
+            room_id: room_id.map(str::to_string),
+            sequence: None,
+        }))
+    };
+
+    // Leaving out the room id, or naming another room, does not get around the sender's cap
+    for room_id in [Some("room_1"), None, Some("room_2"), Some("room_1"), None] {
+        session_manager.route_message("test_client_1".to_string(), candidate("test_client_2", room_id)).await.unwrap();
+    }
+    for _ in 0..3 {
+        receiver.recv().await.expect("Relayed candidate");
+    }
+    assert!(receiver.try_recv().is_err());
+
+    let session = session_manager.get_session("test_client_1").await.expect("Session");
+    assert_eq!(session.ice_candidates_relayed, 3);
+    assert_eq!(session.ice_candidates_dropped, 2);
+
+    // Other senders have caps of their own
+    session_manager.route_message("test_client_2".to_string(), candidate("test_client_1", Some("room_1"))).await.unwrap();
+    assert!(receiver.recv().await.is_some());
+
+    // Offers and answers are not counted against the cap
+    session_manager.route_message("test_client_1".to_string(), Message::new(
+        MessageType::SignalOffer,
+        Payload::SignalOffer(SignalPayload {
+            target_client_id: "test_client_2".to_string(),
+            signal_data: "sdp".to_string(),
+            room_id: Some("room_1".to_string()),
+            sequence: None,
+        }),
+    )).await.unwrap();
+    assert!(receiver.recv().await.is_some());
+}
+
+#[tokio::test]
+async fn test_signals_naming_foreign_rooms_leave_room_state_alone() {
+    let auth_manager = Arc::new(AuthManager::new(Arc::new(Config::default())));
+    let (session_manager, mut receiver) = SessionManager::new(auth_manager);
+    let session_manager = session_manager.with_persist_sdp(true);
+    session_manager.handle_connect("test_client_1".to_string(), "test_token_1".to_string()).await.unwrap();
+    session_manager.handle_connect("test_client_2".to_string(), "test_token_2".to_string()).await.unwrap();
+    session_manager.set_member_role("room_1", "test_client_2", "receiver").await;
+
+    for room_id in ["room_1", "made_up_room"] {
+        session_manager.route_message("test_client_1".to_string(), Message::new(
+            MessageType::SignalOffer,
+            Payload::SignalOffer(SignalPayload {
+                target_client_id: "test_client_2".to_string(),
+                signal_data: "sdp".to_string(),
+                room_id: Some(room_id.to_string()),
+                sequence: None,
+            }),
+        )).await.unwrap();
+        // Still relayed, but without a sequence number from a room the sender is not in
+        match receiver.recv().await {
+            Some((_, Message { payload: Payload::SignalOffer(payload), .. })) => assert_eq!(payload.sequence, None),
+            other => panic!("Expected relayed SignalOffer, got {:?}", other),
+        }
+    }
+
+    assert!(session_manager.get_room_state("made_up_room").await.is_none());
+    let room_state = session_manager.get_room_state("room_1").await.expect("Room state");
+    assert!(room_state.sequence_counters.is_empty());
+    assert_eq!(session_manager.sdp_record("room_1").await, None);
+    assert_eq!(session_manager.sdp_record("made_up_room").await, None);
+}
+
+#[tokio::test]
+async fn test_room_sdp_persisted_when_enabled() {
+    use signal_manager_service::session::SdpRecord;
+
+    let signal = |message_type: MessageType, data: &str| {
+        let payload = SignalPayload {
+            target_client_id: "test_client_2".to_string(),
+            signal_data: data.to_string(),
+            room_id: Some("room_1".to_string()),
+            sequence: None,
+        };
+        let payload = match message_type {
+            MessageType::SignalOffer => Payload::SignalOffer(payload),
+            MessageType::SignalAnswer => Payload::SignalAnswer(payload),
+            _ => Payload::SignalIceCandidate(payload),
+        };
+        Message::new(message_type, payload)
+    };
+
+    for persist_sdp in [true, false] {
+        let auth_manager = Arc::new(AuthManager::new(Arc::new(Config::default())));
+        let (session_manager, mut receiver) = SessionManager::new(auth_manager);
+        let session_manager = session_manager.with_persist_sdp(persist_sdp);
+        session_manager.handle_connect("test_client_1".to_string(), "test_token_1".to_string()).await.unwrap();
+        session_manager.handle_connect("test_client_2".to_string(), "test_token_2".to_string()).await.unwrap();
+        session_manager.set_member_role("room_1", "test_client_2", "receiver").await;
+
+        // As recorded by the server when the room create is acknowledged
+        session_manager.record_offer_sdp("room_1", "v=0 offer").await;
+        session_manager.route_message("test_client_2".to_string(), signal(MessageType::SignalAnswer, "v=0 answer")).await.unwrap();
+        session_manager.route_message("test_client_2".to_string(), signal(MessageType::SignalIceCandidate, "candidate:1")).await.unwrap();
+        session_manager.route_message("test_client_2".to_string(), signal(MessageType::SignalIceCandidate, "candidate:2")).await.unwrap();
+
+        // Relayed signals are unchanged and carry nothing from the record
+        for _ in 0..3 {
+            let (_, relayed) = receiver.recv().await.expect("Relayed signal");
+            let json = serde_json::to_string(&relayed.payload).unwrap();
+            assert!(!json.contains("offer_sdp") && !json.contains("v=0 offer"));
+        }
+
+        if persist_sdp {
+            assert_eq!(session_manager.sdp_record("room_1").await, Some(SdpRecord {
+                offer_sdp: Some("v=0 offer".to_string()),
+                answer_sdp: Some("v=0 answer".to_string()),
+                ice_candidates: vec!["candidate:1".to_string(), "candidate:2".to_string()],
+            }));
+            // The record ends with the room
+            session_manager.remove_room_state("room_1").await;
+            assert_eq!(session_manager.sdp_record("room_1").await, None);
+        } else {
+            assert_eq!(session_manager.sdp_record("room_1").await, None);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_room_sdp_recorded_through_the_room_handlers() {
+    use crate::database::repository::{
+        MockClientInRoomRepository, MockClientInTerminatedRoomRepository, MockWebRTCClientRepository, MockWebRTCRoomRepository,
+    };
+    use crate::webrtc_handlers::MockSignalingProvider;
+    use signal_manager_service::database::{WebRTCRoomRepository, WebRTCRoomStatus};
+    use signal_manager_service::message::{WebRTCRoomCreatePayload, WebRTCRoomJoinPayload, WebRTCRoomLeavePayload};
+    use signal_manager_service::session::MAX_RECORDED_ICE_CANDIDATES;
+    use signal_manager_service::webrtc_handlers::room_create::RoomCreateRepositories;
+    use signal_manager_service::webrtc_handlers::room_join::RoomJoinRepositories;
+    use signal_manager_service::webrtc_handlers::room_leave::RoomLeaveRepositories;
+
+    let webrtc_rooms = Arc::new(MockWebRTCRoomRepository::new());
+    let webrtc_clients = Arc::new(MockWebRTCClientRepository::new());
+    let clients_in_rooms = Arc::new(MockClientInRoomRepository::new());
+    let mut config = Config::default();
+    config.webrtc.persist_sdp = true;
+    let server = WebSocketServer::new(config)
+        .expect("Failed to create server")
+        .with_signaling_provider(Arc::new(MockSignalingProvider::default()))
+        .with_room_create_repositories(RoomCreateRepositories {
+            webrtc_rooms: webrtc_rooms.clone(),
+            webrtc_clients: webrtc_clients.clone(),
+        })
+        .with_room_join_repositories(RoomJoinRepositories {
+            webrtc_rooms: webrtc_rooms.clone(),
+            webrtc_clients: webrtc_clients.clone(),
+            clients_in_rooms: clients_in_rooms.clone(),
+        })
+        .with_room_leave_repositories(RoomLeaveRepositories {
+            webrtc_rooms: webrtc_rooms.clone(),
+            webrtc_clients,
+            clients_in_rooms,
+            clients_in_terminated_rooms: Arc::new(MockClientInTerminatedRoomRepository::new()),
+        });
+    let (addr, server, handle) = harness::spawn_server(server).await;
+    let session_manager = server.session_manager();
+    let wait = tokio::time::Duration::from_secs(5);
+
+    let mut sender = harness::connect_authenticated(addr, "test_client_1", "test_token_1").await;
+    harness::send_message(&mut sender, Message::new(MessageType::WebRTCRoomCreate, Payload::WebRTCRoomCreate(WebRTCRoomCreatePayload {
+        version: "1.0.0".to_string(),
+        client_id: "test_client_1".to_string(),
+        auth_token: "test_token_1".to_string(),
+        role: "sender".to_string(),
+        offer_sdp: Some("v=0 offer".to_string()),
+        max_participants: None,
+        metadata: None,
+    }))).await;
+    let room_id = match harness::recv_message(&mut sender, wait).await {
+        Some(Message { payload: Payload::WebRTCRoomCreateAck(ack), .. }) => ack.room_id.expect("Created room id"),
+        other => panic!("Expected WebRTCRoomCreateAck, got {:?}", other),
+    };
+    assert_eq!(session_manager.sdp_record(&room_id).await.and_then(|record| record.offer_sdp).as_deref(), Some("v=0 offer"));
+    webrtc_rooms.update_room_status(&room_id, WebRTCRoomStatus::Active).await.unwrap();
+
+    let mut receiver = harness::connect_authenticated(addr, "test_client_2", "test_token_2").await;
+    harness::send_message(&mut receiver, Message::new(MessageType::WebRTCRoomJoin, Payload::WebRTCRoomJoin(WebRTCRoomJoinPayload {
+        version: "1.0.0".to_string(),
+        client_id: "test_client_2".to_string(),
+        auth_token: "test_token_2".to_string(),
+        room_id: room_id.clone(),
+        role: "receiver".to_string(),
+        offer_sdp: None,
+        metadata: None,
+    }))).await;
+    match harness::recv_message(&mut receiver, wait).await {
+        Some(Message { payload: Payload::WebRTCRoomJoinAck(ack), .. }) => assert_eq!(ack.status, 200),
+        other => panic!("Expected WebRTCRoomJoinAck, got {:?}", other),
+    }
+
+    // Past the cap, candidates are still relayed but no longer recorded
+    let signal = |message_type: MessageType, data: String| {
+        let payload = SignalPayload {
+            target_client_id: "test_client_1".to_string(),
+            signal_data: data,
+            room_id: Some(room_id.clone()),
+            sequence: None,
+        };
+        let payload = match message_type {
+            MessageType::SignalAnswer => Payload::SignalAnswer(payload),
+            _ => Payload::SignalIceCandidate(payload),
+        };
+        Message::new(message_type, payload)
+    };
+    harness::send_message(&mut receiver, signal(MessageType::SignalAnswer, "v=0 answer".to_string())).await;
+    let candidates = MAX_RECORDED_ICE_CANDIDATES + 5;
+    for i in 0..candidates {
+        harness::send_message(&mut receiver, signal(MessageType::SignalIceCandidate, format!("candidate:{i}"))).await;
+    }
+    let mut relayed = 0;
+    while relayed < candidates {
+        match harness::recv_message(&mut sender, wait).await {
+            Some(Message { payload: Payload::SignalIceCandidate(_), .. }) => relayed += 1,
+            Some(_) => continue,
+            None => panic!("Only {relayed} of {candidates} candidates relayed"),
+        }
+    }
+    let record = session_manager.sdp_record(&room_id).await.expect("Room SDP record");
+    assert_eq!(record.answer_sdp.as_deref(), Some("v=0 answer"));
+    assert_eq!(record.ice_candidates.len(), MAX_RECORDED_ICE_CANDIDATES);
+    assert_eq!(record.ice_candidates.last().map(String::as_str), Some(format!("candidate:{}", MAX_RECORDED_ICE_CANDIDATES - 1).as_str()));
+
+    // The record goes when the last member leaves and the room ends
+    for (client, client_id, token) in [(&mut receiver, "test_client_2", "test_token_2"), (&mut sender, "test_client_1", "test_token_1")] {
+        assert!(session_manager.sdp_record(&room_id).await.is_some());
+        harness::send_message(client, Message::new(MessageType::WebRTCRoomLeave, Payload::WebRTCRoomLeave(WebRTCRoomLeavePayload {
+            version: "1.0.0".to_string(),
+            client_id: client_id.to_string(),
+            auth_token: token.to_string(),
+            room_id: room_id.clone(),
+            reason: None,
+        }))).await;
+        loop {
+            match harness::recv_message(client, wait).await {
+                Some(Message { payload: Payload::WebRTCRoomLeaveAck(ack), .. }) => {
+                    assert_eq!(ack.status, 200);
+                    break;
+                }
+                Some(_) => continue,
+                None => panic!("Expected WebRTCRoomLeaveAck for {client_id}"),
+            }
+        }
+    }
+    assert_eq!(session_manager.sdp_record(&room_id).await, None);
+    assert!(session_manager.get_room_state(&room_id).await.is_none());
+
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_server_info_reports_running_config() {
+    let mut config = Config::default();
+    config.server.max_message_size = 65536;
+    config.server.max_frame_size = 1048576;
+    let expected = WebSocketServer::server_info(&config);
+    let (addr, handle) = harness::spawn_test_server(config).await;
+    let mut client = harness::connect_client(addr).await;
+
+    // Capabilities are available before Connect
+    harness::send_message(&mut client, Message::new(
+        MessageType::ServerInfo,
+        Payload::ServerInfo(signal_manager_service::message::ServerInfoPayload::default()),
+    )).await;
+
+    let info = match harness::recv_message(&mut client, tokio::time::Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::ServerInfoAck(info), .. }) => info,
+        other => panic!("Expected ServerInfoAck, got {:?}", other),
+    };
+    assert_eq!(info.max_message_size, 65536);
+    assert_eq!(info.max_frame_size, 65536);
+    assert_eq!(info.payload_types, vec![PayloadType::Binary, PayloadType::Json, PayloadType::Text, PayloadType::Protobuf, PayloadType::Cbor, PayloadType::JsonGzip]);
+    assert_eq!(info.payload_types, expected.payload_types);
+    assert!(info.message_types.contains(&MessageType::Connect));
+    assert!(info.message_types.contains(&MessageType::ServerInfoAck));
+    assert!(!info.tls_enabled);
+    // Compression is on by default above 4096 bytes
+    assert!(info.compression_enabled);
+    assert_eq!(info.server_version, expected.server_version);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_routing_evicts_dead_client() {
+    use signal_manager_service::config::OverflowPolicy;
+    use signal_manager_service::outbound::OutboundQueue;
+    use std::collections::HashMap;
+    use tokio::sync::RwLock;
+
+    let config = Config::default();
+    let auth_manager = Arc::new(AuthManager::new(Arc::new(config)));
+    let (session_manager, receiver) = SessionManager::new(auth_manager);
+    let session_manager = Arc::new(session_manager);
+
+    session_manager.handle_connect("test_client_1".to_string(), "test_token_1".to_string()).await.unwrap();
+    session_manager.handle_connect("test_client_2".to_string(), "test_token_2".to_string()).await.unwrap();
+
+    let live = Arc::new(OutboundQueue::new(10, OverflowPolicy::DropNewest));
+    let dead = Arc::new(OutboundQueue::new(10, OverflowPolicy::DropNewest));
+    // The client's outgoing task has gone away
+    dead.close();
+    let connections = Arc::new(RwLock::new(HashMap::from([
+        ("test_client_1".to_string(), live.clone()),
+        ("test_client_2".to_string(), dead),
+    ])));
+
+    let routing = tokio::spawn(WebSocketServer::message_routing_task(
+        receiver,
+        session_manager.clone(),
+        connections.clone(),
+    ));
+
+    session_manager.route_message("test_client_1".to_string(), Message::new(
+        MessageType::SignalOffer,
+        Payload::SignalOffer(SignalPayload {
+            target_client_id: "test_client_2".to_string(),
+            signal_data: "sdp".to_string(),
+            room_id: None,
+            sequence: None,
+        }),
+    )).await.unwrap();
+
+    tokio::time::timeout(tokio::time::Duration::from_secs(5), async {
+        while connections.read().await.contains_key("test_client_2") {
+            tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+        }
+    }).await.expect("Dead client was not evicted");
+
+    assert!(connections.read().await.contains_key("test_client_1"));
+    let sessions = session_manager.get_active_sessions().await;
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].client_id, "test_client_1");
+    assert!(live.is_empty());
+    routing.abort();
+}
+
+#[tokio::test]
+async fn test_retention_sweep_reuses_its_repositories_across_ticks() {
+    use crate::database::repository::{MockClientInTerminatedRoomRepository, MockTerminatedRoomRepository};
+    use signal_manager_service::database::{RetentionRepositories, TerminatedRoom, TerminatedRoomRepository};
+    use tokio::time::{sleep, Duration, Instant};
+
+    let mut config = Config::default();
+    config.session.terminated_room_retention_secs = 1;
+    config.session.cleanup_interval = 1;
+    let rooms = Arc::new(MockTerminatedRoomRepository::new());
+    let server = WebSocketServer::new(config).unwrap().with_retention_repositories(RetentionRepositories {
+        terminated_rooms: rooms.clone(),
+        terminated_room_clients: Arc::new(MockClientInTerminatedRoomRepository::new()),
+    });
+    let (_addr, _server, handle) = harness::spawn_server(server).await;
+
+    // Each record is seeded after the previous sweep, so both sweeps must prune the same repository
+    for room_id in ["first_room", "second_room"] {
+        rooms.insert(TerminatedRoom::new_with_timestamps(
+            room_id.to_string(),
+            serde_json::json!({}),
+            chrono::Utc::now() - chrono::Duration::minutes(5),
+            None,
+            None,
+            None,
+        )).await;
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while rooms.get_terminated_room(room_id).await.unwrap().is_some() {
+            assert!(Instant::now() < deadline, "{room_id} was never swept");
+            sleep(Duration::from_millis(100)).await;
+        }
+    }
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_room_lifetime_sweep_terminates_and_notifies() {
+    use crate::database::repository::{
+        MockClientInRoomRepository, MockClientInTerminatedRoomRepository, MockWebRTCClientRepository, MockWebRTCRoomRepository,
+    };
+    use signal_manager_service::database::{
+        ClientInRoom, ClientTerminationStatus, WebRTCRoomCreationPayload, WebRTCRoomStatus, SERVER_TERMINATOR,
+    };
+    use signal_manager_service::webrtc_handlers::room_expiry::{RoomExpiryRepositories, EXPIRY_REASON};
+
+    let mut config = Config::default();
+    config.webrtc.max_room_lifetime_secs = 1;
+    let (addr, server, handle) = harness::spawn_test_server_instance(config).await;
+    let mut sender = harness::connect_authenticated(addr, "test_client_1", "test_token_1").await;
+    let mut receiver = harness::connect_authenticated(addr, "test_client_2", "test_token_2").await;
+
+    let repositories = RoomExpiryRepositories {
+        webrtc_rooms: Arc::new(MockWebRTCRoomRepository::new()),
+        webrtc_clients: Arc::new(MockWebRTCClientRepository::new()),
+        clients_in_rooms: Arc::new(MockClientInRoomRepository::new()),
+        clients_in_terminated_rooms: Arc::new(MockClientInTerminatedRoomRepository::new()),
+    };
+    repositories.webrtc_rooms.create_room(WebRTCRoomCreationPayload {
+        room_id: "room_1".to_string(),
+        app_id: "app".to_string(),
+        sender_client_id: Some("test_client_1".to_string()),
+        receiver_client_id: None,
+        session_id: None,
+        max_participants: None,
+        metadata: None,
+    }).await.unwrap();
+    repositories.clients_in_rooms.create_client_in_room(
+        ClientInRoom::new("test_client_2".to_string(), "room_1".to_string(), vec![], None)
+    ).await.unwrap();
+
+    // Still within its lifetime
+    assert!(server.expire_rooms(&repositories).await.unwrap().is_empty());
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(1100)).await;
+    let expired = server.expire_rooms(&repositories).await.unwrap();
+    assert_eq!(expired.len(), 1);
+    assert_eq!(expired[0].room_id, "room_1");
+    assert_eq!(expired[0].participants, vec!["test_client_1".to_string(), "test_client_2".to_string()]);
+
+    let room = repositories.webrtc_rooms.get_room_by_id("room_1").await.unwrap().unwrap();
+    assert_eq!(room.status, WebRTCRoomStatus::Terminated);
+
+    // Every participant is recorded as removed by the server, not as having left
+    let mut records = repositories.clients_in_terminated_rooms.get_clients_from_terminated_room("room_1").await.unwrap();
+    records.sort_by(|a, b| a.client_id.cmp(&b.client_id));
+    assert_eq!(records.iter().map(|r| r.client_id.as_str()).collect::<Vec<_>>(), vec!["test_client_1", "test_client_2"]);
+    for record in &records {
+        assert_eq!(record.final_status, ClientTerminationStatus::Disconnected);
+        assert_eq!(record.termination_reason, EXPIRY_REASON);
+        assert_eq!(record.terminated_by, SERVER_TERMINATOR);
+    }
+
+    for (client, client_id) in [(&mut sender, "test_client_1"), (&mut receiver, "test_client_2")] {
+        match harness::recv_message(client, tokio::time::Duration::from_secs(5)).await {
+            Some(Message { payload: Payload::WebRTCRoomLeaveAck(ack), .. }) => {
+                assert_eq!(ack.room_id.as_deref(), Some("room_1"));
+                assert_eq!(ack.client_id.as_deref(), Some(client_id));
+                assert_eq!(ack.message.as_deref(), Some(EXPIRY_REASON));
+            }
+            other => panic!("Expected expiry notification for {}, got {:?}", client_id, other),
+        }
+    }
+
+    // Terminated rooms are not swept again
+    assert!(server.expire_rooms(&repositories).await.unwrap().is_empty());
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_room_expiry_closes_provider_sessions() {
+    use crate::database::repository::{
+        MockClientInRoomRepository, MockClientInTerminatedRoomRepository, MockWebRTCClientRepository, MockWebRTCRoomRepository,
+    };
+    use crate::webrtc_handlers::MockSignalingProvider;
+    use signal_manager_service::database::{ClientRole, WebRTCClientRegistrationPayload, WebRTCRoomCreationPayload};
+    use signal_manager_service::webrtc_handlers::room_expiry::RoomExpiryRepositories;
+
+    let provider = Arc::new(MockSignalingProvider::default());
+    let mut config = Config::default();
+    config.webrtc.max_room_lifetime_secs = 1;
+    let server = WebSocketServer::new(config)
+        .expect("Failed to create server")
+        .with_signaling_provider(provider.clone());
+
+    let repositories = RoomExpiryRepositories {
+        webrtc_rooms: Arc::new(MockWebRTCRoomRepository::new()),
+        webrtc_clients: Arc::new(MockWebRTCClientRepository::new()),
+        clients_in_rooms: Arc::new(MockClientInRoomRepository::new()),
+        clients_in_terminated_rooms: Arc::new(MockClientInTerminatedRoomRepository::new()),
+    };
+    repositories.webrtc_rooms.create_room(WebRTCRoomCreationPayload {
+        room_id: "room_1".to_string(),
+        app_id: "app".to_string(),
+        sender_client_id: Some("sender".to_string()),
+        receiver_client_id: Some("receiver".to_string()),
+        session_id: Some("session_sender".to_string()),
+        max_participants: None,
+        metadata: None,
+    }).await.unwrap();
+    for (client_id, role, session_id) in [("sender", ClientRole::Sender, "session_sender"), ("receiver", ClientRole::Receiver, "session_receiver")] {
+        repositories.webrtc_clients.register_client(WebRTCClientRegistrationPayload {
+            client_id: client_id.to_string(),
+            room_id: "room_1".to_string(),
+            role,
+            session_id: Some(session_id.to_string()),
+            metadata: None,
+        }).await.unwrap();
+    }
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(1100)).await;
+    let expired = server.expire_rooms(&repositories).await.unwrap();
+    assert_eq!(expired.len(), 1);
+
+    // The room's session is shared with its sender, so each session is closed once
+    assert_eq!(provider.closed(), vec![
+        ("session_receiver".to_string(), "room_1".to_string()),
+        ("session_sender".to_string(), "room_1".to_string()),
+    ]);
+}
+
+#[tokio::test]
+async fn test_answer_timeout_terminates_sender_only_room() {
+    use crate::database::repository::{
+        MockClientInRoomRepository, MockClientInTerminatedRoomRepository, MockWebRTCClientRepository, MockWebRTCRoomRepository,
+    };
+    use signal_manager_service::database::{WebRTCRoomCreationPayload, WebRTCRoomStatus};
+    use signal_manager_service::webrtc_handlers::room_expiry::{RoomExpiryRepositories, ANSWER_TIMEOUT_REASON};
+
+    let mut config = Config::default();
+    config.webrtc.answer_timeout_secs = 1;
+    let (addr, server, handle) = harness::spawn_test_server_instance(config).await;
+    let mut sender = harness::connect_authenticated(addr, "test_client_1", "test_token_1").await;
+
+    let repositories = RoomExpiryRepositories {
+        webrtc_rooms: Arc::new(MockWebRTCRoomRepository::new()),
+        webrtc_clients: Arc::new(MockWebRTCClientRepository::new()),
+        clients_in_rooms: Arc::new(MockClientInRoomRepository::new()),
+        clients_in_terminated_rooms: Arc::new(MockClientInTerminatedRoomRepository::new()),
+    };
+    for (room_id, receiver) in [("room_1", None), ("room_2", Some("test_client_2".to_string()))] {
+        repositories.webrtc_rooms.create_room(WebRTCRoomCreationPayload {
+            room_id: room_id.to_string(),
+            app_id: "app".to_string(),
+            sender_client_id: Some("test_client_1".to_string()),
+            receiver_client_id: receiver,
+            session_id: None,
+            max_participants: None,
+            metadata: None,
+        }).await.unwrap();
+    }
+
+    // Still within the answer window
+    assert!(server.expire_unanswered_rooms(&repositories).await.unwrap().is_empty());
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(1100)).await;
+    let expired = server.expire_unanswered_rooms(&repositories).await.unwrap();
+    assert_eq!(expired.len(), 1);
+    assert_eq!(expired[0].room_id, "room_1");
+    assert_eq!(expired[0].participants, vec!["test_client_1".to_string()]);
+
+    let room = repositories.webrtc_rooms.get_room_by_id("room_1").await.unwrap().unwrap();
+    assert_eq!(room.status, WebRTCRoomStatus::Terminated);
+    // A room the receiver joined is left alone
+    let answered = repositories.webrtc_rooms.get_room_by_id("room_2").await.unwrap().unwrap();
+    assert_ne!(answered.status, WebRTCRoomStatus::Terminated);
+
+    match harness::recv_message(&mut sender, tokio::time::Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::WebRTCRoomLeaveAck(ack), .. }) => {
+            assert_eq!(ack.room_id.as_deref(), Some("room_1"));
+            assert_eq!(ack.client_id.as_deref(), Some("test_client_1"));
+            assert_eq!(ack.message.as_deref(), Some(ANSWER_TIMEOUT_REASON));
+        }
+        other => panic!("Expected answer timeout notification, got {:?}", other),
+    }
+
+    assert!(server.expire_unanswered_rooms(&repositories).await.unwrap().is_empty());
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_where_am_i_reports_joined_room() {
+    use crate::database::repository::{MockClientInRoomRepository, MockWebRTCClientRepository};
+    use signal_manager_service::database::{ClientInRoom, ClientRole, WebRTCClientRegistrationPayload};
+    use signal_manager_service::message::{ClientRoom, WhereAmIPayload};
+    use signal_manager_service::server::WebSocketServer;
+    use signal_manager_service::webrtc_handlers::where_am_i::WhereAmIRepositories;
+
+    let repositories = WhereAmIRepositories {
+        clients_in_rooms: Arc::new(MockClientInRoomRepository::new()),
+        webrtc_clients: Arc::new(MockWebRTCClientRepository::new()),
+    };
+    let server = WebSocketServer::new(Config::default())
+        .expect("Failed to create server")
+        .with_where_am_i_repositories(repositories.clone());
+    let (addr, _, handle) = harness::spawn_server(server).await;
+    let mut client = harness::connect_authenticated(addr, "test_client_1", "test_token_1").await;
+
+    let query = || Message::new(MessageType::WhereAmI, Payload::WhereAmI(WhereAmIPayload {}));
+
+    // Not in any room yet
+    harness::send_message(&mut client, query()).await;
+    match harness::recv_message(&mut client, tokio::time::Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::WhereAmIAck(ack), .. }) => {
+            assert_eq!(ack.client_id, "test_client_1");
+            assert!(ack.rooms.is_empty());
+        }
+        other => panic!("Expected WhereAmIAck, got {:?}", other),
+    }
+
+    // Record a join as the room join handler does
+    repositories.webrtc_clients.register_client(WebRTCClientRegistrationPayload {
+        client_id: "test_client_1".to_string(),
+        room_id: "room_1".to_string(),
+        role: ClientRole::Sender,
+        session_id: None,
+        metadata: None,
+    }).await.unwrap();
+    repositories.clients_in_rooms.create_client_in_room(
+        ClientInRoom::new("test_client_1".to_string(), "room_1".to_string(), vec![], None)
+    ).await.unwrap();
+    repositories.clients_in_rooms.create_client_in_room(
+        ClientInRoom::new("test_client_2".to_string(), "room_2".to_string(), vec![], None)
+    ).await.unwrap();
+
+    harness::send_message(&mut client, query()).await;
+    match harness::recv_message(&mut client, tokio::time::Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::WhereAmIAck(ack), .. }) => {
+            assert_eq!(ack.rooms, vec![ClientRoom { room_id: "room_1".to_string(), role: Some("sender".to_string()) }]);
+        }
+        other => panic!("Expected WhereAmIAck, got {:?}", other),
+    }
+
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_client_status_query_reports_online_and_offline_clients() {
+    use signal_manager_service::message::{ClientStatusQueryAckPayload, ClientStatusQueryPayload, DisconnectPayload};
+
+    async fn query(client: &mut harness::TestClient, client_id: &str) -> ClientStatusQueryAckPayload {
+        harness::send_message(client, Message::new(
+            MessageType::ClientStatusQuery,
+            Payload::ClientStatusQuery(ClientStatusQueryPayload { client_id: client_id.to_string() }),
+        )).await;
+        match harness::recv_message(client, tokio::time::Duration::from_secs(5)).await {
+            Some(Message { payload: Payload::ClientStatusQueryAck(ack), .. }) => ack,
+            other => panic!("Expected ClientStatusQueryAck, got {:?}", other),
+        }
+    }
+
+    let mut config = Config::default();
+    config.auth.api_keys.push("test_client_3:test_token_3".to_string());
+    config.security.admin_clients = vec!["test_client_3".to_string()];
+    let (addr, server, handle) = harness::spawn_test_server_instance(config).await;
+
+    // Only connected clients may query
+    let mut anonymous = harness::connect_client(addr).await;
+    harness::send_message(&mut anonymous, Message::new(
+        MessageType::ClientStatusQuery,
+        Payload::ClientStatusQuery(ClientStatusQueryPayload { client_id: "test_client_2".to_string() }),
+    )).await;
+    match harness::recv_message(&mut anonymous, tokio::time::Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::Error(error), .. }) => assert_eq!(error.error_code, 1),
+        other => panic!("Expected error for unauthenticated query, got {:?}", other),
+    }
+
+    let mut client = harness::connect_authenticated(addr, "test_client_1", "test_token_1").await;
+    let mut peer = harness::connect_authenticated(addr, "test_client_2", "test_token_2").await;
+    let mut admin = harness::connect_authenticated(addr, "test_client_3", "test_token_3").await;
+
+    // Clients outside the target's rooms are refused
+    harness::send_message(&mut client, Message::new(
+        MessageType::ClientStatusQuery,
+        Payload::ClientStatusQuery(ClientStatusQueryPayload { client_id: "test_client_2".to_string() }),
+    )).await;
+    match harness::recv_message(&mut client, tokio::time::Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::Error(error), .. }) => assert_eq!(error.error_code, 8),
+        other => panic!("Expected error for a query outside the client's rooms, got {:?}", other),
+    }
+
+    server.session_manager().set_member_role("room_1", "test_client_1", "sender").await;
+    server.session_manager().set_member_role("room_1", "test_client_2", "receiver").await;
+    let online = query(&mut client, "test_client_2").await;
+    assert_eq!(online.client_id, "test_client_2");
+    assert!(online.online);
+    let connected_at = online.last_seen.expect("Online client has a last-seen time");
+    assert!(connected_at <= signal_manager_service::message::now_millis());
+    assert!(query(&mut client, "test_client_1").await.online);
+
+    let unknown = query(&mut admin, "never_connected").await;
+    assert!(!unknown.online);
+    assert_eq!(unknown.last_seen, None);
+
+    // The peer leaves the room as it disconnects, so only the admin can still see it
+    harness::send_message(&mut peer, Message::new(
+        MessageType::Disconnect,
+        Payload::Disconnect(DisconnectPayload { client_id: "test_client_2".to_string(), reason: "done".to_string() }),
+    )).await;
+    let mut offline = query(&mut admin, "test_client_2").await;
+    for _ in 0..50 {
+        if !offline.online {
+            break;
+        }
+        tokio::time::sleep(tokio::time::Duration::from_millis(20)).await;
+        offline = query(&mut admin, "test_client_2").await;
+    }
+    assert!(!offline.online);
+    assert!(offline.last_seen.expect("Disconnected client keeps its last-seen time") >= connected_at);
+
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_room_leave_records_voluntary_disconnect() {
+    use crate::database::repository::{
+        MockClientInRoomRepository, MockClientInTerminatedRoomRepository, MockWebRTCClientRepository, MockWebRTCRoomRepository,
+    };
+    use signal_manager_service::database::{
+        ClientRole, ClientTerminationStatus, WebRTCClientRegistrationPayload, WebRTCRoomCreationPayload, VOLUNTARY_LEAVE_REASON,
+    };
+    use signal_manager_service::message::WebRTCRoomLeavePayload;
+    use signal_manager_service::server::WebSocketServer;
+    use signal_manager_service::webrtc_handlers::room_leave::RoomLeaveRepositories;
+
+    let repositories = RoomLeaveRepositories {
+        webrtc_rooms: Arc::new(MockWebRTCRoomRepository::new()),
+        webrtc_clients: Arc::new(MockWebRTCClientRepository::new()),
+        clients_in_rooms: Arc::new(MockClientInRoomRepository::new()),
+        clients_in_terminated_rooms: Arc::new(MockClientInTerminatedRoomRepository::new()),
+    };
+    repositories.webrtc_rooms.create_room(WebRTCRoomCreationPayload {
+        room_id: "room_1".to_string(),
+        app_id: "app".to_string(),
+        sender_client_id: Some("test_client_1".to_string()),
+        receiver_client_id: Some("test_client_2".to_string()),
+        session_id: None,
+        max_participants: None,
+        metadata: None,
+    }).await.unwrap();
+    for (client_id, role) in [("test_client_1", ClientRole::Sender), ("test_client_2", ClientRole::Receiver)] {
+        repositories.webrtc_clients.register_client(WebRTCClientRegistrationPayload {
+            client_id: client_id.to_string(),
+            room_id: "room_1".to_string(),
+            role,
+            session_id: None,
+            metadata: None,
+        }).await.unwrap();
+    }
+
+    let server = WebSocketServer::new(Config::default())
+        .expect("Failed to create server")
+        .with_room_leave_repositories(repositories.clone());
+    let (addr, _, handle) = harness::spawn_server(server).await;
+
+    for (client_id, token, reason) in [
+        ("test_client_1", "test_token_1", Some("Call ended".to_string())),
+        ("test_client_2", "test_token_2", None),
+    ] {
+        let mut client = harness::connect_authenticated(addr, client_id, token).await;
+        harness::send_message(&mut client, Message::new(
+            MessageType::WebRTCRoomLeave,
+            Payload::WebRTCRoomLeave(WebRTCRoomLeavePayload {
+                version: "1.0.0".to_string(),
+                client_id: client_id.to_string(),
+                auth_token: token.to_string(),
+                room_id: "room_1".to_string(),
+                reason,
+            }),
+        )).await;
+        match harness::recv_message(&mut client, tokio::time::Duration::from_secs(5)).await {
+            Some(Message { payload: Payload::WebRTCRoomLeaveAck(ack), .. }) => assert_eq!(ack.status, 200),
+            other => panic!("Expected WebRTCRoomLeaveAck, got {:?}", other),
+        }
+    }
+
+    let mut records = repositories.clients_in_terminated_rooms.get_clients_from_terminated_room("room_1").await.unwrap();
+    records.sort_by(|a, b| a.client_id.cmp(&b.client_id));
+    assert_eq!(records.len(), 2);
+    for record in &records {
+        assert_eq!(record.final_status, ClientTerminationStatus::VoluntaryDisconnect);
+        assert_eq!(record.terminated_by, record.client_id);
+    }
+    assert_eq!(records[0].termination_reason, "Call ended");
+    // No reason given falls back to the default
+    assert_eq!(records[1].termination_reason, VOLUNTARY_LEAVE_REASON);
+
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_room_members_are_told_when_peers_join_and_leave() {
+    use crate::database::repository::{MockClientInRoomRepository, MockWebRTCClientRepository, MockWebRTCRoomRepository};
+    use signal_manager_service::database::{WebRTCRoomCreationPayload, WebRTCRoomStatus};
+    use signal_manager_service::message::{PeerPayload, WebRTCRoomJoinPayload};
+    use signal_manager_service::webrtc_handlers::room_join::RoomJoinRepositories;
+
+    let repositories = RoomJoinRepositories {
+        webrtc_rooms: Arc::new(MockWebRTCRoomRepository::new()),
+        webrtc_clients: Arc::new(MockWebRTCClientRepository::new()),
+        clients_in_rooms: Arc::new(MockClientInRoomRepository::new()),
+    };
+    repositories.webrtc_rooms.create_room(WebRTCRoomCreationPayload {
+        room_id: "room_1".to_string(),
+        app_id: "app".to_string(),
+        sender_client_id: None,
+        receiver_client_id: None,
+        session_id: None,
+        max_participants: None,
+        metadata: None,
+    }).await.unwrap();
+    repositories.webrtc_rooms.update_room_status("room_1", WebRTCRoomStatus::Active).await.unwrap();
+
+    let server = WebSocketServer::new(Config::default())
+        .expect("Failed to create server")
+        .with_room_join_repositories(repositories);
+    let (addr, server, handle) = harness::spawn_server(server).await;
+
+    let join = |client_id: &str, token: &str| Message::new(MessageType::WebRTCRoomJoin, Payload::WebRTCRoomJoin(WebRTCRoomJoinPayload {
+        version: "1.0.0".to_string(),
+        client_id: client_id.to_string(),
+        auth_token: token.to_string(),
+        room_id: "room_1".to_string(),
+        role: "observer".to_string(),
+        offer_sdp: None,
+        metadata: None,
+    }));
+    let wait = tokio::time::Duration::from_secs(5);
+
+    let mut first = harness::connect_authenticated(addr, "test_client_1", "test_token_1").await;
+    harness::send_message(&mut first, join("test_client_1", "test_token_1")).await;
+    match harness::recv_message(&mut first, wait).await {
+        Some(Message { payload: Payload::WebRTCRoomJoinAck(ack), .. }) => assert_eq!(ack.status, 200),
+        other => panic!("Expected WebRTCRoomJoinAck, got {:?}", other),
+    }
+
+    // Roles belong to the socket's authenticated id, whatever client id the join names
+    let mut second = harness::connect_authenticated(addr, "test_client_2", "test_token_2").await;
+    harness::send_message(&mut second, join("test_client_1", "test_token_1")).await;
+    match harness::recv_message(&mut second, wait).await {
+        Some(Message { payload: Payload::WebRTCRoomJoinAck(ack), .. }) => assert_eq!(ack.status, 200),
+        other => panic!("Expected WebRTCRoomJoinAck, got {:?}", other),
+    }
+    assert_eq!(server.session_manager().client_role("room_1", "test_client_2").await.as_deref(), Some("observer"));
+    assert!(server.session_manager().is_observer("room_1", "test_client_2").await);
+
+    let peer = PeerPayload {
+        room_id: "room_1".to_string(),
+        client_id: "test_client_2".to_string(),
+        role: "observer".to_string(),
+    };
+    match harness::recv_message(&mut first, wait).await {
+        Some(Message { message_type: MessageType::PeerJoined, payload: Payload::PeerJoined(joined), .. }) => assert_eq!(joined, peer),
+        other => panic!("Expected PeerJoined, got {:?}", other),
+    }
+    // The joiner is not told about itself
+    assert!(harness::recv_message(&mut second, tokio::time::Duration::from_millis(200)).await.is_none());
+
+    // Dropping the socket ends the session and takes the client out of the room
+    drop(second);
+    match harness::recv_message(&mut first, wait).await {
+        Some(Message { message_type: MessageType::PeerLeft, payload: Payload::PeerLeft(left), .. }) => assert_eq!(left, peer),
+        other => panic!("Expected PeerLeft, got {:?}", other),
+    }
+
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_where_am_i_requires_connect() {
+    use signal_manager_service::message::WhereAmIPayload;
+
+    let (addr, handle) = harness::spawn_test_server(Config::default()).await;
+    let mut client = harness::connect_client(addr).await;
+
+    harness::send_message(&mut client, Message::new(MessageType::WhereAmI, Payload::WhereAmI(WhereAmIPayload {}))).await;
+    match harness::recv_message(&mut client, tokio::time::Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::Error(error), .. }) => assert_eq!(error.error_code, 1),
+        other => panic!("Expected error for unauthenticated WhereAmI, got {:?}", other),
+    }
+
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_server_test_mode_spawns_no_background_tasks() {
+    use signal_manager_service::server::WebSocketServer;
+
+    let server = WebSocketServer::new(Config::default()).expect("Failed to create server");
+    assert_eq!(server.running_background_tasks(), 1);
+
+    let mut config = Config::default();
+    config.webrtc.max_room_lifetime_secs = 60;
+    let test_server = WebSocketServer::new_for_test(config).expect("Failed to create test-mode server");
+    assert_eq!(test_server.running_background_tasks(), 0);
+
+    // Serving does not start the room sweeper either
+    let (addr, _, handle) = harness::spawn_server(test_server.clone()).await;
+    let _client = harness::connect_authenticated(addr, "test_client_1", "test_token_1").await;
+    assert_eq!(test_server.running_background_tasks(), 0);
+
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_signal_base64_validation() {
+    use tokio::time::Duration;
+
+    let mut config = Config::default();
+    config.security.validate_signal_base64 = true;
+    let (addr, handle) = harness::spawn_test_server(config).await;
+    let mut sender = harness::connect_authenticated(addr, "test_client_1", "test_token_1").await;
+    let mut receiver = harness::connect_authenticated(addr, "test_client_2", "test_token_2").await;
+
+    let offer = |signal_data: &str| Message::new(MessageType::SignalOffer, Payload::SignalOffer(SignalPayload {
+        target_client_id: "test_client_2".to_string(),
+        signal_data: signal_data.to_string(),
+        room_id: None,
+        sequence: None,
+    }));
+
+    harness::send_message(&mut sender, offer("dj0wDQpvPS0gMCAwIElOIElQNA==")).await;
+    match harness::recv_message(&mut receiver, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::SignalOffer(payload), .. }) => assert_eq!(payload.signal_data, "dj0wDQpvPS0gMCAwIElOIElQNA=="),
+        other => panic!("Expected relayed offer, got {:?}", other),
+    }
+
+    harness::send_message(&mut sender, offer("v=0\r\no=- 0 0 IN IP4")).await;
+    match harness::recv_message(&mut sender, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::Error(error), .. }) => assert_eq!(error.error_code, 7),
+        other => panic!("Expected base64 validation error, got {:?}", other),
+    }
+    assert!(harness::recv_message(&mut receiver, Duration::from_millis(200)).await.is_none());
+
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_room_list_returns_active_rooms() {
+    use crate::database::repository::{MockClientInRoomRepository, MockWebRTCRoomRepository};
+    use signal_manager_service::database::{
+        ClientInRoom, WebRTCRoomCreationPayload, WebRTCRoomStatus,
+    };
+    use signal_manager_service::message::WebRTCRoomListPayload;
+    use signal_manager_service::server::WebSocketServer;
+    use signal_manager_service::webrtc_handlers::room_list::RoomListRepositories;
+
+    let repositories = RoomListRepositories {
+        webrtc_rooms: Arc::new(MockWebRTCRoomRepository::new()),
+        clients_in_rooms: Arc::new(MockClientInRoomRepository::new()),
+    };
+    for (room_id, status) in [("room_1", WebRTCRoomStatus::Active), ("room_2", WebRTCRoomStatus::Terminated)] {
+        repositories.webrtc_rooms.create_room(WebRTCRoomCreationPayload {
+            room_id: room_id.to_string(),
+            app_id: "app".to_string(),
+            sender_client_id: Some("test_client_2".to_string()),
+            receiver_client_id: None,
+            session_id: None,
+            max_participants: None,
+            metadata: None,
+        }).await.unwrap();
+        repositories.webrtc_rooms.update_room_status(room_id, status).await.unwrap();
+    }
+    repositories.clients_in_rooms.create_client_in_room(
+        ClientInRoom::new("test_client_2".to_string(), "room_1".to_string(), vec![], None)
+    ).await.unwrap();
+
+    let mut config = Config::default();
+    config.security.admin_clients = vec!["test_client_1".to_string()];
+    let server = WebSocketServer::new(config)
+        .expect("Failed to create server")
+        .with_room_list_repositories(repositories);
+    let (addr, _, handle) = harness::spawn_server(server).await;
+    let mut admin = harness::connect_authenticated(addr, "test_client_1", "test_token_1").await;
+    let mut other = harness::connect_authenticated(addr, "test_client_2", "test_token_2").await;
+
+    harness::send_message(&mut admin, Message::new(MessageType::WebRTCRoomList, Payload::WebRTCRoomList(WebRTCRoomListPayload::default()))).await;
+    match harness::recv_message(&mut admin, tokio::time::Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::WebRTCRoomListAck(ack), .. }) => {
+            assert_eq!(ack.total, 1);
+            assert_eq!(ack.next_offset, None);
+            assert_eq!(ack.rooms.len(), 1);
+            assert_eq!(ack.rooms[0].room_id, "room_1");
+            assert_eq!(ack.rooms[0].status, "active");
+            assert_eq!(ack.rooms[0].participant_count, 1);
+            assert_eq!(ack.rooms[0].sender_client_id.as_deref(), Some("test_client_2"));
+        }
+        other => panic!("Expected WebRTCRoomListAck, got {:?}", other),
+    }
+
+    // Clients not listed in security.admin_clients are refused
+    harness::send_message(&mut other, Message::new(MessageType::WebRTCRoomList, Payload::WebRTCRoomList(WebRTCRoomListPayload::default()))).await;
+    match harness::recv_message(&mut other, tokio::time::Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::Error(error), .. }) => {
+            assert_eq!(error.error_code, 8);
+            assert!(error.error_message.contains("admin"), "Unexpected error: {}", error.error_message);
+        }
+        other => panic!("Expected forbidden error, got {:?}", other),
+    }
+
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_handler_panic_cleans_up_connection() {
+    use crate::database::repository::{MockClientInRoomRepository, PanickingWebRTCRoomRepository};
+    use signal_manager_service::message::WebRTCRoomListPayload;
+    use signal_manager_service::server::WebSocketServer;
+    use signal_manager_service::webrtc_handlers::room_list::RoomListRepositories;
+    use tokio::time::Duration;
+
+    let mut config = Config::default();
+    config.security.admin_clients = vec!["test_client_1".to_string()];
+    let server = WebSocketServer::new(config)
+        .expect("Failed to create server")
+        .with_room_list_repositories(RoomListRepositories {
+            webrtc_rooms: Arc::new(PanickingWebRTCRoomRepository),
+            clients_in_rooms: Arc::new(MockClientInRoomRepository::new()),
+        });
+    let (addr, server, handle) = harness::spawn_server(server).await;
+    let mut client = harness::connect_authenticated(addr, "test_client_1", "test_token_1").await;
+    assert!(server.is_connected("test_client_1").await);
+
+    harness::send_message(&mut client, Message::new(MessageType::WebRTCRoomList, Payload::WebRTCRoomList(WebRTCRoomListPayload::default()))).await;
+    match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::Error(error), .. }) => assert_eq!(error.error_code, 1),
+        other => panic!("Expected internal error, got {:?}", other),
+    }
+
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    while server.is_connected("test_client_1").await {
+        assert!(tokio::time::Instant::now() < deadline, "connection entry was not removed after handler panic");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert!(server.active_sessions().await.iter().all(|session| session.client_id != "test_client_1"));
+
+    // The server keeps serving other clients
+    let _other = harness::connect_authenticated(addr, "test_client_2", "test_token_2").await;
+    assert!(server.is_connected("test_client_2").await);
+
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_role_message_allowlist() {
+    use signal_manager_service::message::{HeartbeatPayload, WebRTCRoomCreatePayload};
+    use tokio::time::Duration;
+
+    let mut config = Config::default();
+    config.security.role_message_allowlist.insert(
+        "observer".to_string(),
+        vec![MessageType::Heartbeat, MessageType::WebRTCRoomLeave],
+    );
+    let (addr, server, handle) = harness::spawn_test_server_instance(config).await;
+    let mut client = harness::connect_authenticated(addr, "test_client_1", "test_token_1").await;
+    server.session_manager().set_member_role("room_1", "test_client_1", "observer").await;
+
+    let create = Message::new(MessageType::WebRTCRoomCreate, Payload::WebRTCRoomCreate(WebRTCRoomCreatePayload {
+        version: "1.0.0".to_string(),
+        client_id: "test_client_1".to_string(),
+        auth_token: "test_token_1".to_string(),
+        role: "sender".to_string(),
+        offer_sdp: Some("sdp".to_string()),
+        max_participants: None,
+        metadata: None,
+    }));
+    harness::send_message(&mut client, create).await;
+    match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::Error(error), .. }) => {
+            assert_eq!(error.error_code, 8);
+            assert!(error.error_message.contains("observer"));
+        }
+        other => panic!("Expected forbidden error, got {:?}", other),
+    }
+
+    harness::send_message(&mut client, Message::new(MessageType::Heartbeat, Payload::Heartbeat(HeartbeatPayload { timestamp: 1 }))).await;
+    match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::HeartbeatAck(_), .. }) => {}
+        other => panic!("Expected HeartbeatAck, got {:?}", other),
+    }
+
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_role_message_allowlist_uses_the_role_held_in_the_named_room() {
+    use signal_manager_service::message::{AppRelayPayload, HeartbeatPayload};
+    use tokio::time::Duration;
+
+    let app_relay = |room_id: &str| Message::new(MessageType::AppRelay, Payload::AppRelay(AppRelayPayload {
+        room_id: room_id.to_string(),
+        from_client_id: None,
+        data: vec![1],
+    }));
+
+    let mut config = Config::default();
+    config.security.role_message_allowlist.insert("observer".to_string(), vec![MessageType::Heartbeat]);
+    let (addr, server, handle) = harness::spawn_test_server_instance(config).await;
+    let mut client = harness::connect_authenticated(addr, "test_client_1", "test_token_1").await;
+    server.session_manager().set_member_role("room_1", "test_client_1", "observer").await;
+    server.session_manager().set_member_role("room_2", "test_client_1", "sender").await;
+    assert_eq!(server.session_manager().client_role("room_1", "test_client_1").await.as_deref(), Some("observer"));
+    assert_eq!(server.session_manager().client_role("room_2", "test_client_1").await.as_deref(), Some("sender"));
+    assert_eq!(server.session_manager().client_roles("test_client_1").await, vec!["observer", "sender"]);
+
+    // The observer role only restricts what is sent into room_1
+    harness::send_message(&mut client, app_relay("room_1")).await;
+    match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::Error(error), .. }) => {
+            assert_eq!(error.error_code, 8);
+            assert!(error.error_message.contains("observer"));
+        }
+        other => panic!("Expected forbidden error, got {:?}", other),
+    }
+    harness::send_message(&mut client, app_relay("room_2")).await;
+    harness::send_message(&mut client, Message::new(MessageType::Heartbeat, Payload::Heartbeat(HeartbeatPayload { timestamp: 1 }))).await;
+    match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::HeartbeatAck(_), .. }) => {}
+        other => panic!("Expected the room_2 relay to pass and the HeartbeatAck to follow, got {:?}", other),
+    }
+
+    handle.abort();
+}
+
+/// Register `client_id` on `client` with `capabilities` and wait for the ack
+async fn register_with_capabilities(client: &mut harness::TestClient, client_id: &str, auth_token: &str, capabilities: &[&str]) {
+    use signal_manager_service::message::RegisterPayload;
+
+    harness::send_message(client, Message::new(MessageType::Register, Payload::Register(RegisterPayload {
+        version: "1.0.0".to_string(),
+        client_id: client_id.to_string(),
+        auth_token: auth_token.to_string(),
+        capabilities: Some(capabilities.iter().map(|c| c.to_string()).collect()),
+        metadata: None,
+    }))).await;
+    match harness::recv_message(client, tokio::time::Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::RegisterAck(_), .. }) => {}
+        other => panic!("Expected RegisterAck, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_capability_required_for_room_create() {
+    use signal_manager_service::message::{ServerInfoPayload, WebRTCRoomCreatePayload};
+    use tokio::time::Duration;
+
+    let mut config = Config::default();
+    config.security.enforce_capabilities = true;
+    let (addr, handle) = harness::spawn_test_server(config).await;
+    let mut client = harness::connect_authenticated(addr, "test_client_1", "test_token_1").await;
+    register_with_capabilities(&mut client, "test_client_1", "test_token_1", &["websocket", "heartbeat"]).await;
+
+    harness::send_message(&mut client, Message::new(MessageType::WebRTCRoomCreate, Payload::WebRTCRoomCreate(WebRTCRoomCreatePayload {
+        version: "1.0.0".to_string(),
+        client_id: "test_client_1".to_string(),
+        auth_token: "test_token_1".to_string(),
+        role: "sender".to_string(),
+        offer_sdp: Some("sdp".to_string()),
+        max_participants: None,
+        metadata: None,
+    }))).await;
+    match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::Error(error), .. }) => {
+            assert_eq!(error.error_code, 8);
+            assert!(error.error_message.contains("webrtc capability"), "Unexpected error: {}", error.error_message);
+        }
+        other => panic!("Expected forbidden error, got {:?}", other),
+    }
+
+    // Message types without a required capability are unaffected
+    harness::send_message(&mut client, Message::new(MessageType::ServerInfo, Payload::ServerInfo(ServerInfoPayload::default()))).await;
+    match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::ServerInfoAck(_), .. }) => {}
+        other => panic!("Expected ServerInfoAck, got {:?}", other),
+    }
+
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_configured_capability_admits_registered_client() {
+    use signal_manager_service::message::ServerInfoPayload;
+    use tokio::time::Duration;
+
+    let server_info = || Message::new(MessageType::ServerInfo, Payload::ServerInfo(ServerInfoPayload::default()));
+
+    let mut config = Config::default();
+    config.security.enforce_capabilities = true;
+    config.security.required_capabilities.insert(MessageType::ServerInfo, "diagnostics".to_string());
+    let (addr, handle) = harness::spawn_test_server(config).await;
+    let mut client = harness::connect_authenticated(addr, "test_client_1", "test_token_1").await;
+
+    // Before Register the socket has declared no capabilities
+    harness::send_message(&mut client, server_info()).await;
+    match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::Error(error), .. }) => assert_eq!(error.error_code, 8),
+        other => panic!("Expected forbidden error, got {:?}", other),
+    }
+
+    register_with_capabilities(&mut client, "test_client_1", "test_token_1", &["diagnostics"]).await;
+    harness::send_message(&mut client, server_info()).await;
+    match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::ServerInfoAck(_), .. }) => {}
+        other => panic!("Expected ServerInfoAck, got {:?}", other),
+    }
+
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_app_relay_reaches_room_peers() {
+    use signal_manager_service::message::AppRelayPayload;
+    use tokio::time::Duration;
+
+    let app_relay = |room_id: &str, data: &[u8]| Message::new(MessageType::AppRelay, Payload::AppRelay(AppRelayPayload {
+        room_id: room_id.to_string(),
+        from_client_id: None,
+        data: data.to_vec(),
+    }));
+
+    let mut config = Config::default();
+    config.auth.api_keys.push("test_client_3:test_token_3".to_string());
+    let (addr, server, handle) = harness::spawn_test_server_instance(config).await;
+    let mut sender = harness::connect_authenticated(addr, "test_client_1", "test_token_1").await;
+    let mut receiver = harness::connect_authenticated(addr, "test_client_2", "test_token_2").await;
+    let mut outsider = harness::connect_authenticated(addr, "test_client_3", "test_token_3").await;
+    server.session_manager().set_member_role("room_1", "test_client_1", "sender").await;
+    server.session_manager().set_member_role("room_1", "test_client_2", "receiver").await;
+    server.session_manager().set_member_role("room_2", "test_client_3", "sender").await;
+
+    harness::send_message(&mut sender, app_relay("room_1", &[1, 2, 3])).await;
+    match harness::recv_message(&mut receiver, Duration::from_secs(5)).await {
+        Some(Message { message_type: MessageType::AppRelay, payload: Payload::AppRelay(relay), .. }) => {
+            assert_eq!(relay.room_id, "room_1");
+            assert_eq!(relay.from_client_id.as_deref(), Some("test_client_1"));
+            assert_eq!(relay.data, vec![1, 2, 3]);
+        }
+        other => panic!("Expected relayed app message, got {:?}", other),
+    }
+    // Not echoed to the sender, and not seen outside the room
+    assert!(harness::recv_message(&mut sender, Duration::from_millis(200)).await.is_none());
+    assert!(harness::recv_message(&mut outsider, Duration::from_millis(200)).await.is_none());
+
+    // Only members may relay into a room
+    harness::send_message(&mut outsider, app_relay("room_1", b"intrude")).await;
+    match harness::recv_message(&mut outsider, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::Error(error), .. }) => {
+            assert_eq!(error.error_code, 1);
+            assert!(error.error_message.contains("not a member"));
+        }
+        other => panic!("Expected membership error, got {:?}", other),
+    }
+    assert!(harness::recv_message(&mut receiver, Duration::from_millis(200)).await.is_none());
+
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_app_relay_size_and_rate_limits() {
+    use signal_manager_service::message::AppRelayPayload;
+    use tokio::time::Duration;
+
+    let app_relay = |data: Vec<u8>| Message::new(MessageType::AppRelay, Payload::AppRelay(AppRelayPayload {
+        room_id: "room_1".to_string(),
+        from_client_id: None,
+        data,
+    }));
+
+    let mut config = Config::default();
+    config.webrtc.app_relay_max_bytes = 8;
+    config.webrtc.app_relay_max_per_sec = 2;
+    let (addr, server, handle) = harness::spawn_test_server_instance(config).await;
+    let mut sender = harness::connect_authenticated(addr, "test_client_1", "test_token_1").await;
+    let mut receiver = harness::connect_authenticated(addr, "test_client_2", "test_token_2").await;
+    server.session_manager().set_member_role("room_1", "test_client_1", "sender").await;
+    server.session_manager().set_member_role("room_1", "test_client_2", "receiver").await;
+
+    harness::send_message(&mut sender, app_relay(vec![0; 9])).await;
+    match harness::recv_message(&mut sender, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::Error(error), .. }) => assert_eq!(error.error_code, 9),
+        other => panic!("Expected size limit error, got {:?}", other),
+    }
+
+    for _ in 0..3 {
+        harness::send_message(&mut sender, app_relay(vec![0; 8])).await;
+    }
+    match harness::recv_message(&mut sender, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::Error(error), .. }) => assert_eq!(error.error_code, 10),
+        other => panic!("Expected rate limit error, got {:?}", other),
+    }
+    for _ in 0..2 {
+        match harness::recv_message(&mut receiver, Duration::from_secs(5)).await {
+            Some(Message { payload: Payload::AppRelay(relay), .. }) => assert_eq!(relay.data.len(), 8),
+            other => panic!("Expected relayed app message, got {:?}", other),
+        }
+    }
+    assert!(harness::recv_message(&mut receiver, Duration::from_millis(200)).await.is_none());
+
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_connect_and_register_reject_invalid_ids() {
+    use signal_manager_service::message::RegisterPayload;
+    use tokio::time::Duration;
+
+    let mut config = Config::default();
+    config.server.max_id_length = 16;
+    let (addr, server, handle) = harness::spawn_test_server_instance(config).await;
+    let mut client = harness::connect_client(addr).await;
+
+    for client_id in ["c".repeat(17), "tenant/client".to_string()] {
+        harness::send_message(&mut client, Message::new(MessageType::Connect, Payload::Connect(ConnectPayload {
+            client_id: client_id.clone(),
+            auth_token: "test_token_1".to_string(),
+        }))).await;
+        match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+            Some(Message { message_type: MessageType::Error, payload: Payload::Error(error), .. }) => {
+                assert_eq!(error.error_code, 11);
+                assert_eq!(error.status, Some(400));
+                assert!(error.error_message.contains("client_id"));
+                assert_eq!(error.field.as_deref(), Some("client_id"));
+            }
+            other => panic!("Expected validation error, got {:?}", other),
+        }
+        assert!(!server.is_connected(&client_id).await);
+    }
+
+    harness::send_message(&mut client, Message::new(MessageType::Register, Payload::Register(RegisterPayload {
+        version: "1.0.0".to_string(),
+        client_id: "x".repeat(17),
+        auth_token: "test_token_1".to_string(),
+        capabilities: None,
+        metadata: None,
+    }))).await;
+    match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { message_type: MessageType::RegisterAck, payload: Payload::Error(error), .. }) => {
+            assert_eq!(error.error_code, 11);
+            assert_eq!(error.status, Some(400));
+            assert!(error.error_message.contains("at most 16 bytes"));
+            assert_eq!(error.field.as_deref(), Some("client_id"));
+            assert_eq!(error.details.unwrap()["reason"], "must be at most 16 bytes");
+        }
+        other => panic!("Expected validation error, got {:?}", other),
+    }
+
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_relay_and_query_payloads_reject_invalid_ids() {
+    use signal_manager_service::message::{AppRelayPayload, ClientStatusQueryPayload, SignalPayload};
+    use tokio::time::Duration;
+
+    let mut config = Config::default();
+    config.server.max_id_length = 16;
+    let (addr, _server, handle) = harness::spawn_test_server_instance(config).await;
+    let mut client = harness::connect_authenticated(addr, "test_client_1", "test_token_1").await;
+
+    let signal = |target_client_id: &str, room_id: Option<&str>| Payload::SignalOffer(SignalPayload {
+        target_client_id: target_client_id.to_string(),
+        signal_data: "c2Rw".to_string(),
+        room_id: room_id.map(str::to_string),
+        sequence: None,
+    });
+    let requests = [
+        (MessageType::SignalOffer, signal("tenant/client", None), "target_client_id"),
+        (MessageType::SignalOffer, signal("test_client_2", Some(&"r".repeat(17))), "room_id"),
+        (MessageType::AppRelay, Payload::AppRelay(AppRelayPayload {
+            room_id: "rooms/room_1".to_string(),
+            from_client_id: None,
+            data: b"hello".to_vec(),
+        }), "room_id"),
+        (MessageType::ClientStatusQuery, Payload::ClientStatusQuery(ClientStatusQueryPayload {
+            client_id: "__client__".to_string(),
+        }), "client_id"),
+    ];
+    for (message_type, payload, field) in requests {
+        harness::send_message(&mut client, Message::new(message_type, payload)).await;
+        match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+            Some(Message { message_type: MessageType::Error, payload: Payload::Error(error), .. }) => {
+                assert_eq!(error.error_code, 11);
+                assert_eq!(error.status, Some(400));
+                assert_eq!(error.field.as_deref(), Some(field));
+            }
+            other => panic!("Expected validation error for {:?}, got {:?}", message_type, other),
+        }
+    }
+
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_strict_payload_validation_rejects_empty_connect() {
+    use tokio::time::Duration;
+
+    let mut config = Config::default();
+    config.security.strict_payload_validation = true;
+    let (addr, handle) = harness::spawn_test_server(config).await;
+    let mut client = harness::connect_client(addr).await;
+
+    harness::send_message(
+        &mut client,
+        Message::new(
+            MessageType::Connect,
+            Payload::Connect(ConnectPayload {
+                client_id: String::new(),
+                auth_token: "test_token_1".to_string(),
+            }),
+        ),
+    )
+    .await;
+    match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message {
+            payload: Payload::Error(error),
+            ..
+        }) => {
+            assert_eq!(error.error_code, 11);
+            assert!(error.error_message.contains("client_id"));
+            assert_eq!(error.field.as_deref(), Some("client_id"));
+        }
+        other => panic!("Expected validation error, got {:?}", other),
+    }
+
+    // The connection stays usable for a valid Connect
+    harness::send_message(
+        &mut client,
+        Message::new(
+            MessageType::Connect,
+            Payload::Connect(ConnectPayload {
+                client_id: "test_client_1".to_string(),
+                auth_token: "test_token_1".to_string(),
+            }),
+        ),
+    )
+    .await;
+    match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message {
+            payload: Payload::ConnectAck(_),
+            ..
+        }) => {}
+        other => panic!("Expected ConnectAck, got {:?}", other),
+    }
+
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_warmup_pong_admits_answering_client() {
+    let mut config = Config::default();
+    config.server.require_warmup_pong = true;
+    let (addr, server, handle) = harness::spawn_test_server_instance(config).await;
+
+    // The test client answers pings automatically while it waits for the ConnectAck
+    let _client = harness::connect_authenticated(addr, "test_client_1", "test_token_1").await;
+    assert!(server.is_connected("test_client_1").await);
+
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_warmup_pong_rejects_silent_client() {
+    use tokio::time::Duration;
+
+    let mut config = Config::default();
+    config.server.require_warmup_pong = true;
+    config.server.warmup_pong_timeout_ms = 200;
+    let (addr, server, handle) = harness::spawn_test_server_instance(config).await;
+    let mut client = harness::connect_client(addr).await;
+    harness::send_message(&mut client, Message::new(MessageType::Connect, Payload::Connect(ConnectPayload {
+        client_id: "test_client_1".to_string(),
+        auth_token: "test_token_1".to_string(),
+    }))).await;
+
+    // Not reading the socket means the ping is never answered
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!server.is_connected("test_client_1").await);
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    assert!(!server.is_connected("test_client_1").await);
+    assert!(server.active_sessions().await.iter().all(|session| session.client_id != "test_client_1"));
+
+    match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::Error(error), .. }) => assert_eq!(error.error_code, 12),
+        other => panic!("Expected warm-up error instead of ConnectAck, got {:?}", other),
+    }
+
+    handle.abort();
+}
+
+/// Send Connect and Disconnect for `test_client_1` back to back, without waiting for the ConnectAck
+async fn connect_then_disconnect(client: &mut harness::TestClient) {
+    harness::send_message(client, Message::new(MessageType::Connect, Payload::Connect(ConnectPayload {
+        client_id: "test_client_1".to_string(),
+        auth_token: "test_token_1".to_string(),
+    }))).await;
+    harness::send_message(client, Message::new(MessageType::Disconnect, Payload::Disconnect(signal_manager_service::message::DisconnectPayload {
+        client_id: "test_client_1".to_string(),
+        reason: "done".to_string(),
+    }))).await;
+}
+
+#[tokio::test]
+async fn test_connect_then_immediate_disconnect_leaves_no_entry() {
+    use tokio::time::Duration;
+
+    let (addr, server, handle) = harness::spawn_test_server_instance(Config::default()).await;
+    let mut client = harness::connect_client(addr).await;
+    connect_then_disconnect(&mut client).await;
+
+    match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::ConnectAck(ack), .. }) => assert_eq!(ack.status, "success"),
+        other => panic!("Expected ConnectAck, got {:?}", other),
+    }
+    // A follow-up request is answered only after the Disconnect has been handled
+    harness::send_message(&mut client, Message::new(MessageType::ServerInfo, Payload::ServerInfo(signal_manager_service::message::ServerInfoPayload::default()))).await;
+    assert!(matches!(harness::recv_message(&mut client, Duration::from_secs(5)).await, Some(Message { payload: Payload::ServerInfoAck(_), .. })));
+    assert!(!server.is_connected("test_client_1").await);
+    assert!(server.active_sessions().await.iter().all(|session| session.client_id != "test_client_1"));
+
+    // The socket is no longer attributed to the client, so it is told to Connect first
+    harness::send_message(&mut client, Message::new(MessageType::WhereAmI, Payload::WhereAmI(Default::default()))).await;
+    match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::Error(error), .. }) => assert_eq!(error.error_code, 1),
+        other => panic!("Expected unauthenticated error, got {:?}", other),
+    }
+
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_disconnect_cancels_pending_warmup_connect() {
+    use tokio::time::Duration;
+
+    let mut config = Config::default();
+    config.server.require_warmup_pong = true;
+    let (addr, server, handle) = harness::spawn_test_server_instance(config).await;
+    let mut client = harness::connect_client(addr).await;
+    connect_then_disconnect(&mut client).await;
+
+    // Reading answers the warm-up ping, which now arrives after the Disconnect
+    assert!(harness::recv_message(&mut client, Duration::from_millis(500)).await.is_none(), "No ConnectAck should follow a cancelled Connect");
+    assert!(!server.is_connected("test_client_1").await);
+    assert!(server.active_sessions().await.iter().all(|session| session.client_id != "test_client_1"));
+
+    // The client can still connect normally afterwards
+    let _client = harness::connect_authenticated(addr, "test_client_1", "test_token_1").await;
+    assert!(server.is_connected("test_client_1").await);
+
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_idle_session_is_reaped_after_timeout() {
+    use futures_util::StreamExt;
+    use signal_manager_service::message::HeartbeatPayload;
+    use signal_manager_service::server::SESSION_TIMEOUT_REASON;
+    use tokio::time::{timeout, Duration};
+    use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+    use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+    let mut config = Config::default();
+    config.session.session_timeout = 1;
+    config.session.cleanup_interval = 1;
+    let (addr, server, handle) = harness::spawn_test_server_instance(config).await;
+    let mut idle = harness::connect_authenticated(addr, "test_client_1", "test_token_1").await;
+    let mut active = harness::connect_authenticated(addr, "test_client_2", "test_token_2").await;
+
+    // Only the second client keeps heartbeating
+    let reaped = timeout(Duration::from_secs(5), async {
+        while server.is_connected("test_client_1").await {
+            harness::send_message(&mut active, Message::new(
+                MessageType::Heartbeat,
+                Payload::Heartbeat(HeartbeatPayload { timestamp: 0 }),
+            )).await;
+            assert!(harness::recv_message(&mut active, Duration::from_secs(1)).await.is_some(), "Heartbeat went unanswered");
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+    })
+    .await;
+    assert!(reaped.is_ok(), "Idle session was never reaped");
+
+    match harness::recv_message(&mut idle, Duration::from_secs(1)).await {
+        Some(Message { payload: Payload::Disconnect(disconnect), .. }) => {
+            assert_eq!(disconnect.client_id, "test_client_1");
+            assert_eq!(disconnect.reason, SESSION_TIMEOUT_REASON);
+        }
+        other => panic!("Expected Disconnect, got {:?}", other),
+    }
+    match timeout(Duration::from_secs(5), idle.next()).await {
+        Ok(Some(Ok(WsMessage::Close(Some(frame))))) => {
+            assert_eq!(frame.code, CloseCode::Policy);
+            assert_eq!(frame.reason, SESSION_TIMEOUT_REASON);
+        }
+        other => panic!("Expected a 1008 close frame, got {:?}", other),
+    }
+    let sessions = server.active_sessions().await;
+    assert!(sessions.iter().all(|session| session.client_id != "test_client_1"));
+    assert!(sessions.iter().any(|session| session.client_id == "test_client_2"));
+    assert!(server.is_connected("test_client_2").await);
+
+    // The same id can connect again and keeps its new session through the expired socket's teardown
+    let _fresh = harness::connect_authenticated(addr, "test_client_1", "test_token_1").await;
+    drop(idle);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(server.is_connected("test_client_1").await);
+    assert!(server.active_sessions().await.iter().any(|session| session.client_id == "test_client_1"));
+
+    handle.abort();
+}
+
+#[tokio::test]
+async fn test_disabled_codec_frames_are_rejected_by_server() {
+    use futures_util::SinkExt;
+    use tokio::time::Duration;
+    use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+    let mut config = Config::default();
+    config.server.enabled_codecs = vec![PayloadType::Json, PayloadType::Protobuf];
+    let (addr, handle) = harness::spawn_test_server(config).await;
+    let mut client = harness::connect_client(addr).await;
+
+    let mut connect = Message::new(MessageType::Connect, Payload::Connect(ConnectPayload {
+        client_id: "test_client_1".to_string(),
+        auth_token: "test_token_1".to_string(),
+    }));
+    connect.payload_type = PayloadType::Text;
+    client.send(WsMessage::Binary(connect.to_binary().unwrap())).await.unwrap();
+    match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::Error(error), .. }) => {
+            assert_eq!(error.error_code, 15);
+            assert!(error.error_message.contains("Text"), "Unexpected error: {}", error.error_message);
+        }
+        other => panic!("Expected disabled codec error, got {:?}", other),
+    }
+
+    // Capabilities only list what is enabled, and JSON still works
+    harness::send_message(&mut client, Message::new(
+        MessageType::ServerInfo,
+        Payload::ServerInfo(signal_manager_service::message::ServerInfoPayload::default()),
+    )).await;
+    match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::ServerInfoAck(info), .. }) => {
+            assert_eq!(info.payload_types, vec![PayloadType::Json, PayloadType::Protobuf]);
+        }
+        other => panic!("Expected ServerInfoAck, got {:?}", other),
+    }
+
+    handle.abort();
+}
+
+#[test]
+fn test_enabled_codecs_must_include_json() {
+    let mut config = Config::default();
+    config.server.enabled_codecs = vec![PayloadType::Protobuf];
+    match WebSocketServer::new(config) {
+        Err(e) => assert!(e.to_string().contains("enabled_codecs must include JSON"), "Unexpected error: {}", e),
+        Ok(_) => panic!("Server built without JSON enabled"),
+    }
+}
+
+#[tokio::test]
+async fn test_connect_ack_carries_heartbeat_settings() {
+    use tokio::time::Duration;
+
+    let mut config = Config::default();
+    config.server.heartbeat_interval = 12;
+    config.session.session_timeout = 300;
+    let (addr, server, handle) = harness::spawn_test_server_instance(config.clone()).await;
+
+    let connect = |client_id: &str, auth_token: &str| Message::new(MessageType::Connect, Payload::Connect(ConnectPayload {
+        client_id: client_id.to_string(),
+        auth_token: auth_token.to_string(),
+    }));
+    let mut client = harness::connect_client(addr).await;
+    harness::send_message(&mut client, connect("test_client_1", "test_token_1")).await;
+    match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::ConnectAck(ack), .. }) => {
+            assert_eq!(ack.heartbeat_interval, Some(12));
+            assert_eq!(ack.session_timeout, Some(300));
+        }
+        other => panic!("Expected ConnectAck, got {:?}", other),
+    }
+
+    // A reloaded timeout is advertised to the next client; 0 means sessions never expire
+    let mut reloaded = config;
+    reloaded.session.session_timeout = 0;
+    server.reload_config(&reloaded);
+    let mut client = harness::connect_client(addr).await;
+    harness::send_message(&mut client, connect("test_client_2", "test_token_2")).await;
+    match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::ConnectAck(ack), .. }) => {
+            assert_eq!(ack.heartbeat_interval, Some(12));
+            assert_eq!(ack.session_timeout, None);
+        }
+        other => panic!("Expected ConnectAck, got {:?}", other),
+    }
+
+    handle.abort();
+}
@@ -0,0 +1,79 @@
+use super::harness::{recv_message, send_message, spawn_test_server, TestClient};
+use signal_manager_service::config::Config;
+use signal_manager_service::message::{ConnectPayload, Message, MessageType, Payload};
+use signal_manager_service::server::origin_allowed;
+use std::net::SocketAddr;
+use tokio::time::Duration;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::StatusCode;
+use tokio_tungstenite::tungstenite::Error as WsError;
+
+fn restricted_config() -> Config {
+    let mut config = Config::default();
+    config.security.allowed_origins = vec!["https://app.example.com".to_string()];
+    config
+}
+
+async fn connect_with_origin(addr: SocketAddr, origin: &str) -> Result<TestClient, WsError> {
+    let mut request = format!("ws://{}", addr).into_client_request().unwrap();
+    request.headers_mut().insert("Origin", origin.parse().unwrap());
+    tokio_tungstenite::connect_async(request).await.map(|(ws_stream, _)| ws_stream)
+}
+
+#[tokio::test]
+async fn test_allowed_origin_is_upgraded() {
+    let (addr, server) = spawn_test_server(restricted_config()).await;
+
+    let mut client = connect_with_origin(addr, "https://APP.example.com/").await.expect("Allowed origin was refused");
+    send_message(&mut client, Message::new(
+        MessageType::Connect,
+        Payload::Connect(ConnectPayload {
+            client_id: "test_client_1".to_string(),
+            auth_token: "test_token_1".to_string(),
+        }),
+    )).await;
+    match recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::ConnectAck(ack), .. }) => assert_eq!(ack.status, "success"),
+        other => panic!("Expected ConnectAck, got {:?}", other),
+    }
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_disallowed_origin_is_refused_with_403() {
+    let (addr, server) = spawn_test_server(restricted_config()).await;
+
+    match connect_with_origin(addr, "https://evil.example.com").await {
+        Err(WsError::Http(response)) => {
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+            assert_eq!(response.body().as_deref(), Some("Origin not allowed".as_bytes()));
+        }
+        Ok(_) => panic!("Disallowed origin was upgraded"),
+        Err(e) => panic!("Expected a 403 response, got {e}"),
+    }
+
+    // The refusal does not affect clients that send no Origin header
+    let (_client, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr)).await.expect("Client without an Origin was refused");
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_wildcard_allows_any_origin() {
+    let (addr, server) = spawn_test_server(Config::default()).await;
+    connect_with_origin(addr, "https://anywhere.example.org").await.expect("Wildcard origin list refused an origin");
+    server.abort();
+}
+
+#[test]
+fn test_origin_allowed_matching() {
+    let allowed = vec!["https://app.example.com/".to_string(), "http://localhost:3000".to_string()];
+    assert!(origin_allowed(&allowed, None));
+    assert!(origin_allowed(&allowed, Some("https://app.example.com")));
+    assert!(origin_allowed(&allowed, Some("HTTP://LOCALHOST:3000")));
+    assert!(!origin_allowed(&allowed, Some("http://localhost:3001")));
+    assert!(!origin_allowed(&allowed, Some("https://app.example.com.evil.net")));
+    assert!(!origin_allowed(&[], Some("https://app.example.com")));
+    assert!(origin_allowed(&["*".to_string()], Some("null")));
+}
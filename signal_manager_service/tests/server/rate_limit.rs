@@ -0,0 +1,112 @@
+use super::harness::{connect_authenticated, connect_client, recv_message, send_message, spawn_test_server, spawn_test_server_instance, TestClient};
+use futures_util::StreamExt;
+use signal_manager_service::config::Config;
+use signal_manager_service::message::{Message, MessageType, Payload, ServerInfoPayload};
+use signal_manager_service::server::ADDRESS_LIMIT_REASON;
+use tokio::time::{timeout, Duration};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+/// Send `count` ServerInfo requests, then count the acks and rate-limit errors that come back
+async fn blast_server_info(client: &mut TestClient, count: usize) -> (usize, usize) {
+    for _ in 0..count {
+        send_message(client, Message::new(MessageType::ServerInfo, Payload::ServerInfo(ServerInfoPayload::default()))).await;
+    }
+    let (mut acks, mut limited) = (0, 0);
+    for _ in 0..count {
+        match recv_message(client, Duration::from_secs(5)).await {
+            Some(Message { payload: Payload::ServerInfoAck(_), .. }) => acks += 1,
+            Some(Message { payload: Payload::Error(error), .. }) => {
+                assert_eq!(error.error_code, 10);
+                assert!(error.error_message.contains("5 messages per minute"), "Unexpected error: {}", error.error_message);
+                limited += 1;
+            }
+            other => panic!("Expected ServerInfoAck or Error, got {:?}", other),
+        }
+    }
+    (acks, limited)
+}
+
+#[tokio::test]
+async fn test_message_flood_is_throttled_per_client() {
+    let mut config = Config::default();
+    config.security.rate_limit_enabled = true;
+    config.security.max_messages_per_minute = 5;
+    let (addr, server, server_handle) = spawn_test_server_instance(config).await;
+
+    // Connect is counted against the peer address; the client gets a bucket of its own afterwards
+    let mut client = connect_authenticated(addr, "test_client_1", "test_token_1").await;
+    assert_eq!(blast_server_info(&mut client, 12).await, (5, 7));
+
+    // Another client is not affected by the first one's flood
+    let mut other = connect_authenticated(addr, "test_client_2", "test_token_2").await;
+    assert_eq!(blast_server_info(&mut other, 5).await, (5, 0));
+    assert_eq!(server.message_rate_limiter().tracked_keys(), 3);
+
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_unauthenticated_messages_are_throttled_per_address() {
+    let mut config = Config::default();
+    config.security.rate_limit_enabled = true;
+    config.security.max_messages_per_minute = 5;
+    let (addr, server_handle) = spawn_test_server(config).await;
+
+    let mut first = connect_client(addr).await;
+    assert_eq!(blast_server_info(&mut first, 8).await, (5, 3));
+
+    // A second socket from the same address shares the exhausted bucket
+    let mut second = connect_client(addr).await;
+    assert_eq!(blast_server_info(&mut second, 2).await, (0, 2));
+
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_rate_limit_disabled_admits_every_message() {
+    let mut config = Config::default();
+    config.security.rate_limit_enabled = false;
+    config.security.max_messages_per_minute = 5;
+    let (addr, server_handle) = spawn_test_server(config).await;
+
+    let mut client = connect_client(addr).await;
+    assert_eq!(blast_server_info(&mut client, 20).await, (20, 0));
+
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_connections_beyond_per_ip_limit_are_rejected() {
+    let mut config = Config::default();
+    config.security.rate_limit_enabled = true;
+    config.security.max_connections_per_ip = 2;
+    let (addr, server, server_handle) = spawn_test_server_instance(config).await;
+    let limiter = server.connection_limiter();
+
+    let first = connect_client(addr).await;
+    let _second = connect_client(addr).await;
+
+    let mut third = connect_client(addr).await;
+    match timeout(Duration::from_secs(5), third.next()).await {
+        Ok(Some(Ok(WsMessage::Close(Some(frame))))) => {
+            assert_eq!(frame.code, CloseCode::Again);
+            assert_eq!(frame.reason, ADDRESS_LIMIT_REASON);
+        }
+        other => panic!("Expected a 1013 close frame, got {:?}", other),
+    }
+    assert_eq!(limiter.live_from(addr.ip()), 2);
+
+    // Closing a connection lets the address connect again
+    drop(first);
+    timeout(Duration::from_secs(5), async {
+        while limiter.live_from(addr.ip()) > 1 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("Closed connection never released its slot");
+    let _fourth = connect_authenticated(addr, "test_client_1", "test_token_1").await;
+
+    server_handle.abort();
+}
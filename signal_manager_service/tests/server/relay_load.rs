@@ -0,0 +1,89 @@
+use super::harness::{connect_authenticated, recv_message, send_message, spawn_test_server};
+use signal_manager_service::{
+    config::Config,
+    message::{Message, MessageType, Payload, SignalPayload},
+};
+use tokio::time::Duration;
+
+const CLIENT_PAIRS: usize = 25;
+
+#[tokio::test]
+async fn test_relay_many_pairs_without_cross_talk() {
+    let mut config = Config::default();
+    // Every client connects from localhost, which the per-address limit would cap
+    config.security.rate_limit_enabled = false;
+    for i in 0..CLIENT_PAIRS * 2 {
+        config.auth.api_keys.push(format!("load_client_{}:load_token_{}", i, i));
+    }
+    let (addr, server_handle) = spawn_test_server(config).await;
+
+    // Connect every client before any signals are sent so all targets have sessions
+    let mut clients = Vec::new();
+    for i in 0..CLIENT_PAIRS * 2 {
+        let client_id = format!("load_client_{}", i);
+        clients.push(connect_authenticated(addr, &client_id, &format!("load_token_{}", i)).await);
+    }
+
+    // Partners are (0,1), (2,3), ...; each client sends a uniquely tagged offer to its partner
+    let partner = |i: usize| i ^ 1;
+    let sends = clients.iter_mut().enumerate().map(|(i, client)| async move {
+        send_message(client, Message::new(
+            MessageType::SignalOffer,
+            Payload::SignalOffer(SignalPayload {
+                target_client_id: format!("load_client_{}", partner(i)),
+                signal_data: format!("offer_from_load_client_{}", i),
+                room_id: None,
+                sequence: None,
+            }),
+        )).await;
+    });
+    futures_util::future::join_all(sends).await;
+
+    let receives = clients.iter_mut().enumerate().map(|(i, client)| async move {
+        let received = recv_message(client, Duration::from_secs(5)).await;
+        let extra = recv_message(client, Duration::from_millis(200)).await;
+        (i, received, extra)
+    });
+    for (i, received, extra) in futures_util::future::join_all(receives).await {
+        match received {
+            Some(Message { payload: Payload::SignalOffer(payload), .. }) => {
+                assert_eq!(payload.target_client_id, format!("load_client_{}", i));
+                assert_eq!(payload.signal_data, format!("offer_from_load_client_{}", partner(i)));
+            }
+            other => panic!("load_client_{} expected its partner's offer, got {:?}", i, other),
+        }
+        assert!(extra.is_none(), "load_client_{} received an unexpected message: {:?}", i, extra);
+    }
+
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_relay_large_sdp_intact() {
+    let (addr, server_handle) = spawn_test_server(Config::default()).await;
+    let mut sender = connect_authenticated(addr, "test_client_1", "test_token_1").await;
+    let mut receiver = connect_authenticated(addr, "test_client_2", "test_token_2").await;
+
+    // Close to the u16 payload length limit once JSON framing is added
+    let sdp: String = (0..60_000).map(|i| char::from(b'a' + (i % 26) as u8)).collect();
+    for _ in 0..10 {
+        send_message(&mut sender, Message::new(
+            MessageType::SignalOffer,
+            Payload::SignalOffer(SignalPayload {
+                target_client_id: "test_client_2".to_string(),
+                signal_data: sdp.clone(),
+                room_id: None,
+                sequence: None,
+            }),
+        )).await;
+    }
+
+    for _ in 0..10 {
+        match recv_message(&mut receiver, Duration::from_secs(5)).await {
+            Some(Message { payload: Payload::SignalOffer(payload), .. }) => assert_eq!(payload.signal_data, sdp),
+            other => panic!("Expected relayed offer, got {:?}", other),
+        }
+    }
+
+    server_handle.abort();
+}
@@ -0,0 +1,97 @@
+use super::harness::{connect_authenticated, recv_message, send_message, spawn_test_server_instance};
+use signal_manager_service::config::Config;
+use signal_manager_service::message::{Message, MessageType, Payload, ServerInfoPayload};
+use signal_manager_service::server::{WebSocketServer, SESSION_TIMEOUT_REASON};
+use tokio::time::{timeout, Duration};
+
+#[tokio::test]
+async fn test_reloaded_rate_limit_applies_to_open_connections() {
+    let mut config = Config::default();
+    config.security.rate_limit_enabled = true;
+    let (addr, server, server_handle) = spawn_test_server_instance(config.clone()).await;
+    let mut client = connect_authenticated(addr, "test_client_1", "test_token_1").await;
+
+    let mut reloaded = config;
+    reloaded.security.max_messages_per_minute = 3;
+    let report = server.reload_config(&reloaded);
+    assert_eq!(report.applied, vec!["security.max_messages_per_minute: 1000 -> 3".to_string()]);
+    assert!(report.ignored.is_empty());
+    assert_eq!(server.current_config().security.max_messages_per_minute, 3);
+    assert_eq!(server.message_rate_limiter().max_per_minute(), 3);
+
+    for _ in 0..5 {
+        send_message(&mut client, Message::new(MessageType::ServerInfo, Payload::ServerInfo(ServerInfoPayload::default()))).await;
+    }
+    let (mut acks, mut limited) = (0, 0);
+    for _ in 0..5 {
+        match recv_message(&mut client, Duration::from_secs(5)).await {
+            Some(Message { payload: Payload::ServerInfoAck(_), .. }) => acks += 1,
+            Some(Message { payload: Payload::Error(error), .. }) => {
+                assert_eq!(error.error_code, 10);
+                assert!(error.error_message.contains("3 messages per minute"), "Unexpected error: {}", error.error_message);
+                limited += 1;
+            }
+            other => panic!("Expected ServerInfoAck or Error, got {:?}", other),
+        }
+    }
+    assert_eq!((acks, limited), (3, 2));
+
+    server_handle.abort();
+}
+
+#[tokio::test]
+async fn test_reload_keeps_settings_that_need_a_restart() {
+    let server = WebSocketServer::new_for_test(Config::default()).unwrap();
+
+    let mut reloaded = Config::default();
+    reloaded.server.port = 9999;
+    reloaded.server.max_connections = 5;
+    reloaded.logging.level = "debug".to_string();
+    reloaded.session.reconnect_grace_secs = 30;
+    let report = server.reload_config(&reloaded);
+
+    assert_eq!(report.applied, vec![
+        "logging.level: \"info\" -> \"debug\"".to_string(),
+        "session.reconnect_grace_secs: 0 -> 30".to_string(),
+    ]);
+    assert_eq!(report.ignored, vec!["server.max_connections".to_string(), "server.port".to_string()]);
+
+    let current = server.current_config();
+    assert_eq!(current.logging.level, "debug");
+    assert_eq!(current.session.reconnect_grace_secs, 30);
+    assert_eq!(current.server.port, 8080);
+    assert_eq!(current.server.max_connections, 1000);
+
+    // Reloading the same file again changes nothing further
+    let report = server.reload_config(&reloaded);
+    assert!(report.applied.is_empty());
+    assert_eq!(report.ignored.len(), 2);
+}
+
+#[tokio::test]
+async fn test_reloaded_session_timeout_enables_idle_sweep() {
+    let mut config = Config::default();
+    config.session.session_timeout = 0;
+    config.session.cleanup_interval = 1;
+    let (addr, server, server_handle) = spawn_test_server_instance(config.clone()).await;
+    let mut idle = connect_authenticated(addr, "test_client_1", "test_token_1").await;
+
+    let mut reloaded = config;
+    reloaded.session.session_timeout = 1;
+    server.reload_config(&reloaded);
+
+    let reaped = timeout(Duration::from_secs(5), async {
+        while server.is_connected("test_client_1").await {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await;
+    assert!(reaped.is_ok(), "Idle session was never reaped after the reload");
+
+    match recv_message(&mut idle, Duration::from_secs(1)).await {
+        Some(Message { payload: Payload::Disconnect(disconnect), .. }) => assert_eq!(disconnect.reason, SESSION_TIMEOUT_REASON),
+        other => panic!("Expected Disconnect, got {:?}", other),
+    }
+
+    server_handle.abort();
+}
@@ -0,0 +1,77 @@
+use futures_util::StreamExt;
+use signal_manager_service::config::Config;
+use signal_manager_service::message::{Message, Payload};
+use signal_manager_service::server::{WebSocketServer, SHUTDOWN_REASON};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio::time::{timeout, Duration};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+use super::harness;
+
+/// Serve `server` on an ephemeral port until the returned sender fires
+async fn serve_until_triggered(server: WebSocketServer) -> (std::net::SocketAddr, oneshot::Sender<()>, JoinHandle<Result<(), signal_manager_service::Error>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("Failed to bind ephemeral port");
+    let addr = listener.local_addr().unwrap();
+    let (trigger, shutdown) = oneshot::channel::<()>();
+    let handle = tokio::spawn(async move {
+        server.serve_with_shutdown(listener, async {
+            let _ = shutdown.await;
+        }).await
+    });
+    (addr, trigger, handle)
+}
+
+async fn expect_close(client: &mut harness::TestClient) {
+    match timeout(Duration::from_secs(5), client.next()).await {
+        Ok(Some(Ok(WsMessage::Close(Some(frame))))) => {
+            assert_eq!(frame.code, CloseCode::Away);
+            assert_eq!(frame.reason, SHUTDOWN_REASON);
+        }
+        other => panic!("Expected close frame, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_shutdown_disconnects_clients_and_returns() {
+    let server = WebSocketServer::new(Config::default()).expect("Failed to create server");
+    let (addr, trigger, handle) = serve_until_triggered(server.clone()).await;
+
+    let mut client = harness::connect_authenticated(addr, "test_client_1", "test_token_1").await;
+    let mut anonymous = harness::connect_client(addr).await;
+    assert!(server.is_connected("test_client_1").await);
+
+    trigger.send(()).unwrap();
+
+    // Authenticated clients are told why before the socket closes
+    match harness::recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::Disconnect(disconnect), .. }) => {
+            assert_eq!(disconnect.client_id, "test_client_1");
+            assert_eq!(disconnect.reason, SHUTDOWN_REASON);
+        }
+        other => panic!("Expected Disconnect, got {:?}", other),
+    }
+    expect_close(&mut client).await;
+    expect_close(&mut anonymous).await;
+
+    let result = timeout(Duration::from_secs(5), handle).await.expect("Server did not stop").unwrap();
+    assert!(result.is_ok());
+    assert!(!server.is_connected("test_client_1").await);
+    assert_eq!(server.running_background_tasks(), 0);
+    assert!(TcpStream::connect(addr).await.is_err(), "Listener still accepting after shutdown");
+}
+
+#[tokio::test]
+async fn test_shutdown_without_connections_returns_immediately() {
+    let mut config = Config::default();
+    config.server.shutdown_grace_secs = 30;
+    let server = WebSocketServer::new(config).expect("Failed to create server");
+    let (_, trigger, handle) = serve_until_triggered(server).await;
+
+    trigger.send(()).unwrap();
+    // Nothing to wait for, so the grace period is not spent
+    let result = timeout(Duration::from_secs(5), handle).await.expect("Server did not stop").unwrap();
+    assert!(result.is_ok());
+}
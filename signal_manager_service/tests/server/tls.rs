@@ -0,0 +1,162 @@
+//! End-to-end TLS: server with `tls_enabled = true` and a client trusting the self-signed fixture.
+//! Fixtures were generated with:
+//! `openssl req -x509 -newkey rsa:2048 -nodes -keyout key.pem -out cert.pem -days 36500
+//!  -subj "/CN=localhost" -addext "subjectAltName=DNS:localhost,IP:127.0.0.1"`
+
+use super::harness::{recv_message, send_message, spawn_test_server, TestClient};
+use signal_manager_service::config::{Config, TlsBackend};
+use signal_manager_service::message::{ConnectPayload, Message, MessageType, Payload};
+use std::net::SocketAddr;
+use tokio::time::Duration;
+use tokio_tungstenite::{connect_async_tls_with_config, Connector};
+
+const CERT_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/tls/cert.pem");
+const KEY_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/tls/key.pem");
+
+fn tls_config() -> Config {
+    let mut config = Config::default();
+    config.server.tls_enabled = true;
+    config.server.tls_cert_path = CERT_PATH.to_string();
+    config.server.tls_key_path = KEY_PATH.to_string();
+    config
+}
+
+fn rustls_config() -> Config {
+    let mut config = tls_config();
+    config.server.tls_backend = TlsBackend::Rustls;
+    config
+}
+
+async fn connect_tls_client(addr: SocketAddr) -> TestClient {
+    let cert = std::fs::read(CERT_PATH).expect("Failed to read certificate fixture");
+    let connector = native_tls::TlsConnector::builder()
+        .add_root_certificate(native_tls::Certificate::from_pem(&cert).expect("Invalid certificate fixture"))
+        .build()
+        .expect("Failed to build TLS connector");
+
+    let (ws_stream, _) = connect_async_tls_with_config(
+        format!("wss://{}", addr),
+        None,
+        false,
+        Some(Connector::NativeTls(connector)),
+    )
+    .await
+    .expect("TLS WebSocket handshake failed");
+    ws_stream
+}
+
+#[tokio::test]
+async fn test_tls_connect_round_trip() {
+    let (addr, server) = spawn_test_server(tls_config()).await;
+    let mut client = connect_tls_client(addr).await;
+
+    send_message(&mut client, Message::new(
+        MessageType::Connect,
+        Payload::Connect(ConnectPayload {
+            client_id: "test_client_1".to_string(),
+            auth_token: "test_token_1".to_string(),
+        }),
+    )).await;
+
+    match recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::ConnectAck(ack), .. }) => assert_eq!(ack.status, "success"),
+        other => panic!("Expected ConnectAck over TLS, got {:?}", other),
+    }
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_tls_server_rejects_plain_websocket() {
+    let (addr, server) = spawn_test_server(tls_config()).await;
+
+    let result = tokio::time::timeout(
+        Duration::from_secs(5),
+        tokio_tungstenite::connect_async(format!("ws://{}", addr)),
+    ).await;
+    assert!(!matches!(result, Ok(Ok(_))), "Plain WebSocket should not upgrade on a TLS listener");
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_tls_server_drops_stalled_handshake() {
+    use tokio::io::AsyncReadExt;
+
+    let mut config = tls_config();
+    config.server.tls_handshake_timeout_secs = 1;
+    let (addr, server) = spawn_test_server(config).await;
+
+    // Open a raw TCP connection and never send a ClientHello
+    let mut socket = tokio::net::TcpStream::connect(addr).await.expect("Failed to connect");
+    let mut buf = [0u8; 16];
+    let read = tokio::time::timeout(Duration::from_secs(5), socket.read(&mut buf)).await
+        .expect("Server kept the stalled handshake open past the timeout");
+    assert!(matches!(read, Ok(0) | Err(_)), "Expected the server to close the socket, got {:?}", read);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_tls_server_drops_socket_that_never_upgrades() {
+    use tokio::io::AsyncReadExt;
+
+    let mut config = tls_config();
+    config.server.tls_handshake_timeout_secs = 1;
+    let (addr, server) = spawn_test_server(config).await;
+
+    // Complete the TLS handshake, then never send the HTTP upgrade request
+    let cert = std::fs::read(CERT_PATH).expect("Failed to read certificate fixture");
+    let connector = native_tls::TlsConnector::builder()
+        .add_root_certificate(native_tls::Certificate::from_pem(&cert).expect("Invalid certificate fixture"))
+        .build()
+        .expect("Failed to build TLS connector");
+    let socket = tokio::net::TcpStream::connect(addr).await.expect("Failed to connect");
+    let mut tls = tokio_native_tls::TlsConnector::from(connector).connect("localhost", socket).await
+        .expect("TLS handshake failed");
+
+    let mut buf = [0u8; 16];
+    let read = tokio::time::timeout(Duration::from_secs(5), tls.read(&mut buf)).await
+        .expect("Server kept the socket open past the handshake timeout");
+    assert!(matches!(read, Ok(0) | Err(_)), "Expected the server to close the socket, got {:?}", read);
+
+    server.abort();
+}
+
+#[tokio::test]
+async fn test_rustls_backend_connect_round_trip() {
+    let (addr, server) = spawn_test_server(rustls_config()).await;
+    let mut client = connect_tls_client(addr).await;
+
+    send_message(&mut client, Message::new(
+        MessageType::Connect,
+        Payload::Connect(ConnectPayload {
+            client_id: "test_client_1".to_string(),
+            auth_token: "test_token_1".to_string(),
+        }),
+    )).await;
+
+    match recv_message(&mut client, Duration::from_secs(5)).await {
+        Some(Message { payload: Payload::ConnectAck(ack), .. }) => assert_eq!(ack.status, "success"),
+        other => panic!("Expected ConnectAck over rustls, got {:?}", other),
+    }
+
+    server.abort();
+}
+
+#[test]
+fn test_rustls_acceptor_loads_pem_pair() {
+    use signal_manager_service::tls::TlsAcceptor;
+
+    let config = rustls_config();
+    let acceptor = TlsAcceptor::from_config(&config.server).expect("Failed to load PEM fixtures with rustls");
+    assert_eq!(acceptor.backend(), TlsBackend::Rustls);
+
+    // The key file holds no certificate, and the certificate file no key
+    let mut swapped = config.server.clone();
+    swapped.tls_cert_path = KEY_PATH.to_string();
+    assert!(TlsAcceptor::from_config(&swapped).is_err());
+    swapped.tls_cert_path = CERT_PATH.to_string();
+    swapped.tls_key_path = CERT_PATH.to_string();
+    assert!(TlsAcceptor::from_config(&swapped).is_err());
+}
@@ -0,0 +1,290 @@
+use std::sync::{Arc, Mutex};
+use serde_json::json;
+use uuid::Uuid;
+
+use signal_manager_service::events::{EventClient, EventMessage, NoopEventClient};
+
+use signal_manager_service::database::{
+    ClientInRoom, ClientRole, RegistrationPayload, WebRTCClientRegistrationPayload,
+    WebRTCRoomCreationPayload, WebRTCRoomStatus,
+};
+use signal_manager_service::type_two_handlers::unregister::{
+    handle_unregister_with_repositories, UnregisterRepositories, UnregisterResponse,
+};
+
+use crate::database::repository::{
+    MockClientInRoomRepository, MockClientRepository, MockWebRTCClientRepository,
+    MockWebRTCRoomRepository,
+};
+
+fn mock_repositories() -> UnregisterRepositories {
+    UnregisterRepositories {
+        clients: Arc::new(MockClientRepository::new()),
+        clients_in_rooms: Arc::new(MockClientInRoomRepository::new()),
+        webrtc_rooms: Arc::new(MockWebRTCRoomRepository::new()),
+        webrtc_clients: Arc::new(MockWebRTCClientRepository::new()),
+    }
+}
+
+async fn unregister(repositories: &UnregisterRepositories, client_id: &str, auth_token: &str) -> UnregisterResponse {
+    let payload = json!({
+        "version": "1.0.0",
+        "client_id": client_id,
+        "auth_token": auth_token,
+    });
+    let events: Arc<dyn EventClient> = Arc::new(NoopEventClient);
+    let (_, response_json) = handle_unregister_with_repositories(Uuid::new_v4(), payload, repositories, &events).await;
+    serde_json::from_str(&response_json).unwrap()
+}
+
+#[tokio::test]
+async fn test_unregister_cleans_up_room_state() {
+    let repositories = mock_repositories();
+
+    repositories.clients.create_client(RegistrationPayload {
+        client_id: "owner".to_string(),
+        auth_token: "owner_token".to_string(),
+        room_id: None,
+        capabilities: None,
+        metadata: None,
+    }).await.unwrap();
+
+    repositories.webrtc_rooms.create_room(WebRTCRoomCreationPayload {
+        room_id: "room_1".to_string(),
+        app_id: "app".to_string(),
+        sender_client_id: Some("owner".to_string()),
+        receiver_client_id: Some("viewer".to_string()),
+        session_id: None,
+        max_participants: None,
+        metadata: None,
+    }).await.unwrap();
+    repositories.webrtc_clients.register_client(WebRTCClientRegistrationPayload {
+        client_id: "owner".to_string(),
+        room_id: "room_1".to_string(),
+        role: ClientRole::Sender,
+        session_id: None,
+        metadata: None,
+    }).await.unwrap();
+    repositories.clients_in_rooms.create_client_in_room(
+        ClientInRoom::new("owner".to_string(), "room_1".to_string(), vec![], None)
+    ).await.unwrap();
+    repositories.clients_in_rooms.create_client_in_room(
+        ClientInRoom::new("viewer".to_string(), "room_1".to_string(), vec![], None)
+    ).await.unwrap();
+
+    let response = unregister(&repositories, "owner", "owner_token").await;
+    assert_eq!(response.status, 200);
+    assert_eq!(response.client_id.as_deref(), Some("owner"));
+
+    assert!(!repositories.clients.client_exists("owner").await.unwrap());
+    assert!(!repositories.clients_in_rooms.client_exists_in_room("owner", "room_1").await.unwrap());
+    assert!(repositories.clients_in_rooms.client_exists_in_room("viewer", "room_1").await.unwrap());
+    assert!(repositories.webrtc_clients.get_client_by_id("owner").await.unwrap().is_none());
+
+    let room = repositories.webrtc_rooms.get_room_by_id("room_1").await.unwrap().unwrap();
+    assert_eq!(room.status, WebRTCRoomStatus::Terminated);
+
+    // Once the client is gone, a repeated unregister reports it as not registered
+    let retry = unregister(&repositories, "owner", "owner_token").await;
+    assert_eq!(retry.status, 404);
+    assert_eq!(retry.client_id, None);
+}
+
+#[tokio::test]
+async fn test_unregister_keeps_rooms_owned_by_others() {
+    let repositories = mock_repositories();
+
+    repositories.clients.create_client(RegistrationPayload {
+        client_id: "viewer".to_string(),
+        auth_token: "viewer_token".to_string(),
+        room_id: None,
+        capabilities: None,
+        metadata: None,
+    }).await.unwrap();
+    repositories.webrtc_rooms.create_room(WebRTCRoomCreationPayload {
+        room_id: "room_1".to_string(),
+        app_id: "app".to_string(),
+        sender_client_id: Some("owner".to_string()),
+        receiver_client_id: Some("viewer".to_string()),
+        session_id: None,
+        max_participants: None,
+        metadata: None,
+    }).await.unwrap();
+
+    let response = unregister(&repositories, "viewer", "viewer_token").await;
+    assert_eq!(response.status, 200);
+
+    let room = repositories.webrtc_rooms.get_room_by_id("room_1").await.unwrap().unwrap();
+    assert_ne!(room.status, WebRTCRoomStatus::Terminated);
+}
+
+#[tokio::test]
+async fn test_unregister_rejects_wrong_token() {
+    let repositories = mock_repositories();
+
+    repositories.clients.create_client(RegistrationPayload {
+        client_id: "owner".to_string(),
+        auth_token: "owner_token".to_string(),
+        room_id: None,
+        capabilities: None,
+        metadata: None,
+    }).await.unwrap();
+
+    let response = unregister(&repositories, "owner", "wrong_token").await;
+    assert_eq!(response.status, 401);
+    assert!(repositories.clients.client_exists("owner").await.unwrap());
+}
+
+/// Event client that keeps every event it is handed, in publish order
+#[derive(Default)]
+struct RecordingEventClient {
+    events: Mutex<Vec<EventMessage>>,
+}
+
+impl RecordingEventClient {
+    /// The recorded events once there are `count` of them; events are published in the background
+    async fn wait_for(&self, count: usize) -> Vec<EventMessage> {
+        for _ in 0..100 {
+            let events = self.events.lock().unwrap().clone();
+            if events.len() >= count {
+                return events;
+            }
+            tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+        }
+        panic!("Expected {} events, got {:?}", count, self.events.lock().unwrap());
+    }
+}
+
+#[async_trait::async_trait]
+impl EventClient for RecordingEventClient {
+    async fn publish(&self, event: EventMessage) -> Result<(), signal_manager_service::Error> {
+        self.events.lock().unwrap().push(event);
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_register_and_unregister_publish_lifecycle_events() {
+    use signal_manager_service::config::Config;
+    use signal_manager_service::events::{CLIENT_REGISTERED, CLIENT_UNREGISTERED};
+    use signal_manager_service::message::{Message, MessageType, Payload, RegisterPayload, UnregisterPayload};
+    use signal_manager_service::type_two_handlers::register::RegisterHandler;
+
+    let recorder = Arc::new(RecordingEventClient::default());
+    let handler = RegisterHandler::new(Arc::new(Config::default()))
+        .with_repositories(mock_repositories())
+        .with_event_client(recorder.clone());
+
+    let registered = handler.handle_register(Message::new(MessageType::Register, Payload::Register(RegisterPayload {
+        version: "1.0.0".to_string(),
+        client_id: "client_1".to_string(),
+        auth_token: "token_1".to_string(),
+        capabilities: None,
+        metadata: None,
+    }))).await.unwrap();
+    let session_id = match registered.payload {
+        Payload::RegisterAck(ack) => ack.session_id.expect("Session id present"),
+        other => panic!("Expected RegisterAck, got {:?}", other),
+    };
+    let events = recorder.wait_for(1).await;
+    assert_eq!(events[0].event_type, CLIENT_REGISTERED);
+    assert_eq!(events[0].data, json!({ "client_id": "client_1", "session_id": session_id }));
+
+    let unregistered = handler.handle_unregister(Message::new(MessageType::Unregister, Payload::Unregister(UnregisterPayload {
+        version: "1.0.0".to_string(),
+        client_id: "client_1".to_string(),
+        auth_token: "token_1".to_string(),
+    }))).await.unwrap();
+    assert!(matches!(unregistered.payload, Payload::UnregisterAck(_)), "Unexpected reply: {:?}", unregistered.payload);
+
+    let events = recorder.wait_for(2).await;
+    let types: Vec<&str> = events.iter().map(|event| event.event_type.as_str()).collect();
+    assert_eq!(types, vec![CLIENT_REGISTERED, CLIENT_UNREGISTERED]);
+    assert_eq!(events[1].data, json!({ "client_id": "client_1" }));
+    assert!(events[0].timestamp <= events[1].timestamp);
+}
+
+#[tokio::test]
+async fn test_unregister_validation_names_the_offending_field() {
+    let repositories = mock_repositories();
+    let events: Arc<dyn EventClient> = Arc::new(NoopEventClient);
+    let cases = [
+        (json!({"version": "1.0.0", "auth_token": "t"}), "client_id", "is required"),
+        (json!({"version": "1.0.0", "client_id": 7, "auth_token": "t"}), "client_id", "must be a string"),
+        (json!({"version": "9.0.0", "client_id": "c", "auth_token": "t"}), "version", "is newer than the server"),
+        (json!({"version": "1.0.0", "client_id": "c", "auth_token": "  "}), "auth_token", "must not be empty"),
+    ];
+
+    for (payload, field, reason) in cases {
+        let (_, response_json) = handle_unregister_with_repositories(Uuid::new_v4(), payload, &repositories, &events).await;
+        let response: UnregisterResponse = serde_json::from_str(&response_json).unwrap();
+        assert_eq!(response.status, 400);
+        assert_eq!(response.field.as_deref(), Some(field));
+        assert_eq!(response.details.unwrap()["reason"], reason);
+    }
+
+    // Non-validation responses carry no field
+    let response = unregister(&repositories, "missing_client", "t").await;
+    assert_eq!(response.status, 404);
+    assert!(response.field.is_none() && response.details.is_none());
+    assert!(!serde_json::to_string(&response).unwrap().contains("field"));
+}
+
+#[tokio::test]
+async fn test_register_validation_error_payload_names_the_field() {
+    use signal_manager_service::config::Config;
+    use signal_manager_service::message::{Message, MessageType, Payload, RegisterPayload};
+    use signal_manager_service::type_two_handlers::register::RegisterHandler;
+
+    let handler = RegisterHandler::new(Arc::new(Config::default())).with_repositories(mock_repositories());
+    let reply = handler.handle_register(Message::new(MessageType::Register, Payload::Register(RegisterPayload {
+        version: "1.0.0".to_string(),
+        client_id: "client_1".to_string(),
+        auth_token: " ".to_string(),
+        capabilities: None,
+        metadata: None,
+    }))).await.unwrap();
+
+    match reply.payload {
+        Payload::Error(error) => {
+            assert_eq!(error.error_code, 11);
+            assert_eq!(error.error_message, "Auth token is required");
+            assert_eq!(error.field.as_deref(), Some("auth_token"));
+            assert_eq!(error.details.unwrap()["reason"], "must not be empty");
+        }
+        other => panic!("Expected Error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_register_handler_counts_every_registration_against_max_clients() {
+    use signal_manager_service::config::Config;
+    use signal_manager_service::message::{Message, MessageType, Payload, RegisterPayload};
+    use signal_manager_service::type_two_handlers::register::RegisterHandler;
+
+    let register = |client_id: &str| Message::new(MessageType::Register, Payload::Register(RegisterPayload {
+        version: "1.0.0".to_string(),
+        client_id: client_id.to_string(),
+        auth_token: format!("{client_id}_token"),
+        capabilities: None,
+        metadata: None,
+    }));
+
+    let mut config = Config::default();
+    config.database.max_clients = 1;
+    let handler = RegisterHandler::new(Arc::new(config));
+    // Each connection serves requests from its own clone of the handler
+    let other_connection = handler.clone();
+
+    let first = handler.handle_register(register("client_1")).await.unwrap();
+    assert!(matches!(first.payload, Payload::RegisterAck(_)), "Unexpected reply: {:?}", first.payload);
+
+    let second = other_connection.handle_register(register("client_2")).await.unwrap();
+    match second.payload {
+        Payload::Error(error) => {
+            assert_eq!(error.status, Some(503));
+            assert!(error.error_message.contains("full"), "Unexpected error: {}", error.error_message);
+        }
+        other => panic!("Expected capacity error, got {:?}", other),
+    }
+}
@@ -0,0 +1,695 @@
+use signal_manager_service::database::{ClientRole, WebRTCClient};
+use signal_manager_service::message::{
+    IceServer, Message, MessageType, Payload, RoomParticipant, WebRTCRoomJoinAckPayload,
+};
+use crate::database::repository::{MockWebRTCClientRepository, MockWebRTCRoomRepository};
+use signal_manager_service::signaling::{ProviderResult, ProviderSession, SignalingProvider};
+use signal_manager_service::webrtc_handlers::room_create::RoomCreateRepositories;
+use signal_manager_service::webrtc_handlers::room_join::{room_participants, validate_join_role, RoomJoinRepositories};
+
+#[test]
+fn test_room_participants_excludes_joiner() {
+    let clients = vec![
+        WebRTCClient::new("sender_client".to_string(), "room_1".to_string(), ClientRole::Sender, Some("session_1".to_string()), None),
+        WebRTCClient::new("receiver_client".to_string(), "room_1".to_string(), ClientRole::Receiver, None, None),
+    ];
+
+    let participants = room_participants(&clients, "receiver_client");
+    assert_eq!(participants, vec![RoomParticipant {
+        client_id: "sender_client".to_string(),
+        role: "sender".to_string(),
+    }]);
+
+    assert!(room_participants(&[], "receiver_client").is_empty());
+}
+
+#[test]
+fn test_join_ack_carries_participants() {
+    let clients = vec![
+        WebRTCClient::new("sender_client".to_string(), "room_1".to_string(), ClientRole::Sender, Some("session_1".to_string()), None),
+    ];
+    let ack = Message::new(MessageType::WebRTCRoomJoinAck, Payload::WebRTCRoomJoinAck(WebRTCRoomJoinAckPayload {
+        version: "1.0.0".to_string(),
+        status: 200,
+        message: Some("Joined room successfully".to_string()),
+        room_id: Some("room_1".to_string()),
+        session_id: Some("session_1".to_string()),
+        app_id: None,
+        stun_url: None,
+        connection_info: None,
+        turn_servers: None,
+        participants: Some(room_participants(&clients, "receiver_client")),
+    }));
+
+    let decoded = Message::from_binary(&ack.to_binary().unwrap()).unwrap();
+    match decoded.payload {
+        Payload::WebRTCRoomJoinAck(payload) => {
+            let participants = payload.participants.expect("Participants present");
+            assert_eq!(participants.len(), 1);
+            assert_eq!(participants[0].client_id, "sender_client");
+            assert_eq!(participants[0].role, "sender");
+        }
+        other => panic!("Unexpected payload: {:?}", other),
+    }
+}
+
+#[test]
+fn test_observer_join_does_not_require_offer_sdp() {
+    assert_eq!(validate_join_role("observer", false), Ok(ClientRole::Observer));
+    assert_eq!(validate_join_role("Observer", true), Ok(ClientRole::Observer));
+    assert_eq!(validate_join_role("receiver", false), Ok(ClientRole::Receiver));
+    assert!(validate_join_role("sender", false).is_err());
+    assert!(validate_join_role("spectator", false).is_err());
+}
+
+#[test]
+fn test_room_participants_reports_observers() {
+    let clients = vec![
+        WebRTCClient::new("sender_client".to_string(), "room_1".to_string(), ClientRole::Sender, Some("session_1".to_string()), None),
+        WebRTCClient::new("observer_client".to_string(), "room_1".to_string(), ClientRole::Observer, None, None),
+    ];
+
+    let participants = room_participants(&clients, "receiver_client");
+    assert_eq!(participants[1], RoomParticipant {
+        client_id: "observer_client".to_string(),
+        role: "observer".to_string(),
+    });
+}
+
+#[tokio::test]
+async fn test_room_create_handler_rejects_empty_client_id() {
+    use signal_manager_service::config::Config;
+    use signal_manager_service::message::WebRTCRoomCreatePayload;
+    use signal_manager_service::webrtc_handlers::WebRTCRoomCreateHandler;
+    use std::sync::Arc;
+
+    let handler = WebRTCRoomCreateHandler::new(Arc::new(Config::default()));
+    let request = Message::new(
+        MessageType::WebRTCRoomCreate,
+        Payload::WebRTCRoomCreate(WebRTCRoomCreatePayload {
+            version: "1.0.0".to_string(),
+            client_id: String::new(),
+            auth_token: "test_token".to_string(),
+            role: "receiver".to_string(),
+            offer_sdp: None,
+            max_participants: None,
+            metadata: None,
+        }),
+    );
+
+    // Rejected before any repository is touched
+    let response = handler.handle_room_create(request).await.unwrap();
+    assert_eq!(response.message_type, MessageType::WebRTCRoomCreateAck);
+    match response.payload {
+        Payload::Error(error) => {
+            assert_eq!(error.error_code, 11);
+            assert!(error.error_message.contains("client_id"));
+        }
+        other => panic!("Expected validation error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_room_join_handler_rejects_overlong_and_illegal_room_ids() {
+    use signal_manager_service::config::Config;
+    use signal_manager_service::message::WebRTCRoomJoinPayload;
+    use signal_manager_service::webrtc_handlers::WebRTCRoomJoinHandler;
+    use std::sync::Arc;
+
+    let mut config = Config::default();
+    config.server.max_id_length = 16;
+    let handler = WebRTCRoomJoinHandler::new(Arc::new(config));
+    let join = |room_id: String| Message::new(MessageType::WebRTCRoomJoin, Payload::WebRTCRoomJoin(WebRTCRoomJoinPayload {
+        version: "1.0.0".to_string(),
+        client_id: "test_client".to_string(),
+        auth_token: "test_token".to_string(),
+        room_id,
+        role: "receiver".to_string(),
+        offer_sdp: None,
+        metadata: None,
+    }));
+
+    // Rejected before any repository is touched
+    for (room_id, reason) in [("r".repeat(17), "at most 16 bytes"), ("rooms/room_1".to_string(), "'/'")] {
+        let response = handler.handle_room_join(join(room_id)).await.unwrap();
+        assert_eq!(response.message_type, MessageType::WebRTCRoomJoinAck);
+        match response.payload {
+            Payload::Error(error) => {
+                assert_eq!(error.error_code, 11);
+                assert_eq!(error.status, Some(400));
+                assert!(error.error_message.contains("room_id"), "Unexpected error: {}", error.error_message);
+                assert!(error.error_message.contains(reason), "Unexpected error: {}", error.error_message);
+            }
+            other => panic!("Expected validation error, got {:?}", other),
+        }
+    }
+}
+
+/// Provider that hands out `mock_session_N` ids and `ice_servers`, or fails every call while `fail` is set
+#[derive(Default)]
+pub struct MockSignalingProvider {
+    fail: bool,
+    created: std::sync::Mutex<Vec<(String, String, String)>>,
+    closed: std::sync::Mutex<Vec<(String, String)>>,
+    ice_servers: Vec<IceServer>,
+}
+
+impl MockSignalingProvider {
+    /// `(session_id, room_id)` of every `close_session` call, in order
+    pub fn closed(&self) -> Vec<(String, String)> {
+        self.closed.lock().unwrap().clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl SignalingProvider for MockSignalingProvider {
+    async fn create_session(&self, room_id: &str, client_id: &str, offer_sdp: String) -> ProviderResult<ProviderSession> {
+        if self.fail {
+            return Err("provider unreachable".into());
+        }
+        let mut created = self.created.lock().unwrap();
+        created.push((room_id.to_string(), client_id.to_string(), offer_sdp));
+        Ok(ProviderSession {
+            session_id: Some(format!("mock_session_{}", created.len())),
+            connection_info: serde_json::json!({ "room_id": room_id, "client_id": client_id }),
+        })
+    }
+
+    async fn add_track(&self, room_id: &str, client_id: &str, session_id: &str) -> ProviderResult<ProviderSession> {
+        if self.fail {
+            return Err("provider unreachable".into());
+        }
+        Ok(ProviderSession {
+            session_id: Some(session_id.to_string()),
+            connection_info: serde_json::json!({ "room_id": room_id, "client_id": client_id }),
+        })
+    }
+
+    async fn close_session(&self, session_id: &str, room_id: &str) -> ProviderResult<()> {
+        self.closed.lock().unwrap().push((session_id.to_string(), room_id.to_string()));
+        Ok(())
+    }
+
+    async fn ice_servers(&self) -> ProviderResult<Vec<IceServer>> {
+        Ok(self.ice_servers.clone())
+    }
+}
+
+/// Room create handler backed by in-memory repositories and `provider`
+fn room_create_handler(provider: std::sync::Arc<MockSignalingProvider>) -> (signal_manager_service::webrtc_handlers::WebRTCRoomCreateHandler, RoomCreateRepositories) {
+    use signal_manager_service::config::Config;
+    use signal_manager_service::webrtc_handlers::WebRTCRoomCreateHandler;
+    use std::sync::Arc;
+
+    let repositories = RoomCreateRepositories {
+        webrtc_rooms: Arc::new(MockWebRTCRoomRepository::new()),
+        webrtc_clients: Arc::new(MockWebRTCClientRepository::new()),
+    };
+    let handler = WebRTCRoomCreateHandler::new(Arc::new(Config::default()))
+        .with_repositories(repositories.clone())
+        .with_provider(provider);
+    (handler, repositories)
+}
+
+fn sender_room_create() -> Message {
+    use signal_manager_service::message::WebRTCRoomCreatePayload;
+
+    Message::new(MessageType::WebRTCRoomCreate, Payload::WebRTCRoomCreate(WebRTCRoomCreatePayload {
+        version: "1.0.0".to_string(),
+        client_id: "sender_client".to_string(),
+        auth_token: "test_token".to_string(),
+        role: "sender".to_string(),
+        offer_sdp: Some("v=0 mock offer".to_string()),
+        max_participants: None,
+        metadata: None,
+    }))
+}
+
+#[tokio::test]
+async fn test_room_create_opens_session_on_configured_provider() {
+    let provider = std::sync::Arc::new(MockSignalingProvider::default());
+    let (handler, repositories) = room_create_handler(provider.clone());
+
+    let response = handler.handle_room_create(sender_room_create()).await.unwrap();
+    let ack = match response.payload {
+        Payload::WebRTCRoomCreateAck(ack) => ack,
+        other => panic!("Expected WebRTCRoomCreateAck, got {:?}", other),
+    };
+    assert_eq!(ack.status, 200);
+    assert_eq!(ack.session_id.as_deref(), Some("mock_session_1"));
+    assert!(ack.turn_servers.is_none());
+    let room_id = ack.room_id.expect("Room id present");
+    // The provider's own connection details reach the client untouched
+    assert_eq!(ack.connection_info, Some(serde_json::json!({ "room_id": room_id, "client_id": "sender_client" })));
+
+    // The provider saw the sender's offer, and the room and client carry its session
+    assert_eq!(*provider.created.lock().unwrap(), vec![(room_id.clone(), "sender_client".to_string(), "v=0 mock offer".to_string())]);
+    let room = repositories.webrtc_rooms.get_room_by_id(&room_id).await.unwrap().expect("Room stored");
+    assert_eq!(room.get_session_id(), Some("mock_session_1"));
+    let client = repositories.webrtc_clients.get_client_by_id("sender_client").await.unwrap().expect("Client stored");
+    assert_eq!(client.get_session_id(), Some("mock_session_1"));
+}
+
+#[tokio::test]
+async fn test_room_create_provider_failure_stores_nothing() {
+    let provider = std::sync::Arc::new(MockSignalingProvider { fail: true, ..Default::default() });
+    let (handler, repositories) = room_create_handler(provider);
+
+    let response = handler.handle_room_create(sender_room_create()).await.unwrap();
+    match response.payload {
+        Payload::Error(error) => assert_eq!(error.error_message, "Failed to create session"),
+        other => panic!("Expected Error, got {:?}", other),
+    }
+    assert_eq!(repositories.webrtc_rooms.get_room_count().await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_room_create_ack_carries_provider_turn_servers() {
+    let turn = IceServer {
+        urls: vec!["turn:turn.cloudflare.com:3478?transport=udp".to_string()],
+        username: Some("turn-user".to_string()),
+        credential: Some("turn-secret".to_string()),
+    };
+    let provider = std::sync::Arc::new(MockSignalingProvider { ice_servers: vec![turn.clone()], ..Default::default() });
+    let (handler, _) = room_create_handler(provider);
+
+    let response = handler.handle_room_create(sender_room_create()).await.unwrap();
+    let decoded = Message::from_binary(&response.to_binary().unwrap()).unwrap();
+    match decoded.payload {
+        Payload::WebRTCRoomCreateAck(ack) => assert_eq!(ack.turn_servers, Some(vec![turn])),
+        other => panic!("Expected WebRTCRoomCreateAck, got {:?}", other),
+    }
+}
+
+fn room_join(room_id: &str, client_id: &str, role: &str) -> Message {
+    use signal_manager_service::message::WebRTCRoomJoinPayload;
+
+    Message::new(MessageType::WebRTCRoomJoin, Payload::WebRTCRoomJoin(WebRTCRoomJoinPayload {
+        version: "1.0.0".to_string(),
+        client_id: client_id.to_string(),
+        auth_token: "test_token".to_string(),
+        room_id: room_id.to_string(),
+        role: role.to_string(),
+        offer_sdp: None,
+        metadata: None,
+    }))
+}
+
+#[tokio::test]
+async fn test_room_join_rejects_participants_beyond_cap() {
+    use signal_manager_service::config::Config;
+    use signal_manager_service::database::{WebRTCRoomStatus, DEFAULT_MAX_PARTICIPANTS};
+    use signal_manager_service::webrtc_handlers::WebRTCRoomJoinHandler;
+    use std::sync::Arc;
+
+    let provider = Arc::new(MockSignalingProvider::default());
+    let (create_handler, repositories) = room_create_handler(provider.clone());
+    let join_handler = WebRTCRoomJoinHandler::new(Arc::new(Config::default()))
+        .with_repositories(RoomJoinRepositories {
+            webrtc_rooms: repositories.webrtc_rooms.clone(),
+            webrtc_clients: repositories.webrtc_clients.clone(),
+            clients_in_rooms: std::sync::Arc::new(crate::database::repository::MockClientInRoomRepository::new()),
+        })
+        .with_provider(provider);
+
+    let room_id = match create_handler.handle_room_create(sender_room_create()).await.unwrap().payload {
+        Payload::WebRTCRoomCreateAck(ack) => ack.room_id.expect("Room id present"),
+        other => panic!("Expected WebRTCRoomCreateAck, got {:?}", other),
+    };
+    repositories.webrtc_rooms.update_room_status(&room_id, WebRTCRoomStatus::Active).await.unwrap();
+    let room = repositories.webrtc_rooms.get_room_by_id(&room_id).await.unwrap().expect("Room stored");
+    assert_eq!(room.max_participants, DEFAULT_MAX_PARTICIPANTS);
+
+    // The sender and one receiver fill the room
+    match join_handler.handle_room_join(room_join(&room_id, "receiver_client", "receiver")).await.unwrap().payload {
+        Payload::WebRTCRoomJoinAck(ack) => assert_eq!(ack.status, 200),
+        other => panic!("Expected WebRTCRoomJoinAck, got {:?}", other),
+    }
+    match join_handler.handle_room_join(room_join(&room_id, "third_client", "receiver")).await.unwrap().payload {
+        Payload::Error(error) => {
+            assert_eq!(error.error_message, "Room is full");
+            assert_eq!(error.error_code, 19);
+        }
+        other => panic!("Expected room full error, got {:?}", other),
+    }
+    match join_handler.handle_room_join(room_join("missing_room", "third_client", "receiver")).await.unwrap().payload {
+        Payload::Error(error) => assert_eq!((error.error_code, error.error_message.as_str()), (18, "Room not found")),
+        other => panic!("Expected room not found error, got {:?}", other),
+    }
+    assert!(repositories.webrtc_clients.get_client_by_id("third_client").await.unwrap().is_none());
+
+    // Observers do not take a participant place
+    match join_handler.handle_room_join(room_join(&room_id, "observer_client", "observer")).await.unwrap().payload {
+        Payload::WebRTCRoomJoinAck(ack) => assert_eq!(ack.status, 200),
+        other => panic!("Expected WebRTCRoomJoinAck, got {:?}", other),
+    }
+}
+
+/// An active room created by `sender_client`, and a join handler sharing its repositories that
+/// accepts `test_token` for `receiver_client`
+async fn rejoin_fixture(rejoin_grace_secs: u64) -> (signal_manager_service::webrtc_handlers::WebRTCRoomJoinHandler, RoomJoinRepositories, String) {
+    use crate::database::repository::MockClientInRoomRepository;
+    use signal_manager_service::auth::AuthManager;
+    use signal_manager_service::config::Config;
+    use signal_manager_service::database::WebRTCRoomStatus;
+    use signal_manager_service::webrtc_handlers::WebRTCRoomJoinHandler;
+    use std::sync::Arc;
+
+    let provider = Arc::new(MockSignalingProvider::default());
+    let (create_handler, create_repositories) = room_create_handler(provider.clone());
+    let room_id = match create_handler.handle_room_create(sender_room_create()).await.unwrap().payload {
+        Payload::WebRTCRoomCreateAck(ack) => ack.room_id.expect("Room id present"),
+        other => panic!("Expected WebRTCRoomCreateAck, got {:?}", other),
+    };
+    create_repositories.webrtc_rooms.update_room_status(&room_id, WebRTCRoomStatus::Active).await.unwrap();
+
+    let mut config = Config::default();
+    config.webrtc.rejoin_grace_secs = rejoin_grace_secs;
+    let config = Arc::new(config);
+    let auth_manager = Arc::new(AuthManager::new(config.clone()));
+    auth_manager.add_valid_token("receiver_client".to_string(), "test_token".to_string()).await;
+    let repositories = RoomJoinRepositories {
+        webrtc_rooms: create_repositories.webrtc_rooms,
+        webrtc_clients: create_repositories.webrtc_clients,
+        clients_in_rooms: Arc::new(MockClientInRoomRepository::new()),
+    };
+    let handler = WebRTCRoomJoinHandler::new(config)
+        .with_repositories(repositories.clone())
+        .with_provider(provider)
+        .with_auth_manager(auth_manager);
+    (handler, repositories, room_id)
+}
+
+#[tokio::test]
+async fn test_room_join_reattaches_client_rejoining_within_grace_window() {
+    let (handler, repositories, room_id) = rejoin_fixture(60).await;
+
+    match handler.handle_room_join(room_join(&room_id, "receiver_client", "receiver")).await.unwrap().payload {
+        Payload::WebRTCRoomJoinAck(ack) => assert_eq!(ack.message.as_deref(), Some("Joined room successfully")),
+        other => panic!("Expected WebRTCRoomJoinAck, got {:?}", other),
+    }
+    let memberships = repositories.clients_in_rooms.get_clients_in_room(&room_id).await.unwrap();
+    assert_eq!(memberships.len(), 1);
+    let joined = memberships[0].clone();
+
+    // The socket dropped; joining again re-attaches to the same membership
+    tokio::time::sleep(tokio::time::Duration::from_millis(5)).await;
+    match handler.handle_room_join(room_join(&room_id, "receiver_client", "receiver")).await.unwrap().payload {
+        Payload::WebRTCRoomJoinAck(ack) => {
+            assert_eq!(ack.status, 200);
+            assert_eq!(ack.message.as_deref(), Some("Rejoined room successfully"));
+            assert_eq!(ack.session_id.as_deref(), Some("mock_session_1"));
+            assert_eq!(ack.participants.unwrap().len(), 1);
+        }
+        other => panic!("Expected WebRTCRoomJoinAck, got {:?}", other),
+    }
+    let memberships = repositories.clients_in_rooms.get_clients_in_room(&room_id).await.unwrap();
+    assert_eq!(memberships.len(), 1);
+    assert_eq!(memberships[0].id, joined.id);
+    assert!(memberships[0].last_activity > joined.last_activity);
+
+    // A different role, or a token that does not authenticate, is still a duplicate
+    match handler.handle_room_join(room_join(&room_id, "receiver_client", "observer")).await.unwrap().payload {
+        Payload::Error(error) => assert_eq!(error.error_message, "Client already in room with another role"),
+        other => panic!("Expected duplicate error, got {:?}", other),
+    }
+    let mut forged = room_join(&room_id, "receiver_client", "receiver");
+    if let Payload::WebRTCRoomJoin(payload) = &mut forged.payload {
+        payload.auth_token = "stolen_token".to_string();
+    }
+    match handler.handle_room_join(forged).await.unwrap().payload {
+        Payload::Error(error) => assert_eq!(error.error_message, "Client already in room"),
+        other => panic!("Expected duplicate error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_room_join_after_grace_window_creates_new_membership() {
+    let (handler, repositories, room_id) = rejoin_fixture(60).await;
+
+    match handler.handle_room_join(room_join(&room_id, "receiver_client", "receiver")).await.unwrap().payload {
+        Payload::WebRTCRoomJoinAck(ack) => assert_eq!(ack.status, 200),
+        other => panic!("Expected WebRTCRoomJoinAck, got {:?}", other),
+    }
+    let mut stale = repositories.clients_in_rooms.get_clients_in_room(&room_id).await.unwrap().remove(0);
+    stale.last_activity -= chrono::Duration::seconds(61);
+    repositories.clients_in_rooms.update_client_in_room(&stale.id, stale.clone()).await.unwrap();
+
+    match handler.handle_room_join(room_join(&room_id, "receiver_client", "receiver")).await.unwrap().payload {
+        Payload::WebRTCRoomJoinAck(ack) => {
+            assert_eq!(ack.status, 200);
+            assert_eq!(ack.message.as_deref(), Some("Joined room successfully"));
+        }
+        other => panic!("Expected WebRTCRoomJoinAck, got {:?}", other),
+    }
+    let memberships = repositories.clients_in_rooms.get_clients_in_room(&room_id).await.unwrap();
+    assert_eq!(memberships.len(), 1);
+    assert_ne!(memberships[0].id, stale.id);
+    assert!(repositories.webrtc_clients.get_client_by_id("receiver_client").await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn test_room_leave_drops_membership_so_a_rejoin_starts_afresh() {
+    use crate::database::repository::MockClientInTerminatedRoomRepository;
+    use signal_manager_service::config::Config;
+    use signal_manager_service::message::WebRTCRoomLeavePayload;
+    use signal_manager_service::webrtc_handlers::room_leave::RoomLeaveRepositories;
+    use signal_manager_service::webrtc_handlers::WebRTCRoomLeaveHandler;
+    use std::sync::Arc;
+
+    let (join_handler, repositories, room_id) = rejoin_fixture(60).await;
+    let leave_handler = WebRTCRoomLeaveHandler::new(Arc::new(Config::default()))
+        .with_repositories(RoomLeaveRepositories {
+            webrtc_rooms: repositories.webrtc_rooms.clone(),
+            webrtc_clients: repositories.webrtc_clients.clone(),
+            clients_in_rooms: repositories.clients_in_rooms.clone(),
+            clients_in_terminated_rooms: Arc::new(MockClientInTerminatedRoomRepository::new()),
+        })
+        .with_provider(Arc::new(MockSignalingProvider::default()));
+    let leave = Message::new(MessageType::WebRTCRoomLeave, Payload::WebRTCRoomLeave(WebRTCRoomLeavePayload {
+        version: "1.0.0".to_string(),
+        client_id: "receiver_client".to_string(),
+        auth_token: "test_token".to_string(),
+        room_id: room_id.clone(),
+        reason: None,
+    }));
+
+    match join_handler.handle_room_join(room_join(&room_id, "receiver_client", "receiver")).await.unwrap().payload {
+        Payload::WebRTCRoomJoinAck(ack) => assert_eq!(ack.status, 200),
+        other => panic!("Expected WebRTCRoomJoinAck, got {:?}", other),
+    }
+    match leave_handler.handle_room_leave(leave).await.unwrap().payload {
+        Payload::WebRTCRoomLeaveAck(ack) => assert_eq!(ack.status, 200),
+        other => panic!("Expected WebRTCRoomLeaveAck, got {:?}", other),
+    }
+    assert!(repositories.clients_in_rooms.get_active_clients_in_room(&room_id).await.unwrap().is_empty());
+
+    // Joining again is a fresh join with a single membership, not a rejoin of the one that left
+    match join_handler.handle_room_join(room_join(&room_id, "receiver_client", "receiver")).await.unwrap().payload {
+        Payload::WebRTCRoomJoinAck(ack) => {
+            assert_eq!(ack.status, 200);
+            assert_eq!(ack.message.as_deref(), Some("Joined room successfully"));
+        }
+        other => panic!("Expected WebRTCRoomJoinAck, got {:?}", other),
+    }
+    assert_eq!(repositories.clients_in_rooms.get_active_clients_in_room(&room_id).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_room_join_without_grace_window_rejects_rejoin() {
+    let (handler, _repositories, room_id) = rejoin_fixture(0).await;
+
+    match handler.handle_room_join(room_join(&room_id, "receiver_client", "receiver")).await.unwrap().payload {
+        Payload::WebRTCRoomJoinAck(ack) => assert_eq!(ack.status, 200),
+        other => panic!("Expected WebRTCRoomJoinAck, got {:?}", other),
+    }
+    match handler.handle_room_join(room_join(&room_id, "receiver_client", "receiver")).await.unwrap().payload {
+        Payload::Error(error) => assert_eq!(error.error_message, "Client already in room"),
+        other => panic!("Expected duplicate error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_room_create_rejects_zero_max_participants() {
+    let (handler, repositories) = room_create_handler(std::sync::Arc::new(MockSignalingProvider::default()));
+    let mut message = sender_room_create();
+    if let Payload::WebRTCRoomCreate(payload) = &mut message.payload {
+        payload.max_participants = Some(0);
+    }
+
+    match handler.handle_room_create(message).await.unwrap().payload {
+        Payload::Error(error) => {
+            assert_eq!(error.error_code, 11);
+            assert!(error.error_message.contains("max_participants"), "Unexpected error: {}", error.error_message);
+        }
+        other => panic!("Expected validation error, got {:?}", other),
+    }
+    assert_eq!(repositories.webrtc_rooms.get_room_count().await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_room_create_rejects_max_participants_above_the_limit() {
+    let (handler, repositories) = room_create_handler(std::sync::Arc::new(MockSignalingProvider::default()));
+    let limit = signal_manager_service::config::Config::default().webrtc.max_room_participants;
+    for (max_participants, accepted) in [(limit, true), (limit + 1, false), (u32::MAX, false)] {
+        let mut message = sender_room_create();
+        if let Payload::WebRTCRoomCreate(payload) = &mut message.payload {
+            payload.max_participants = Some(max_participants);
+        }
+        match handler.handle_room_create(message).await.unwrap().payload {
+            Payload::WebRTCRoomCreateAck(ack) => assert!(accepted, "Accepted max_participants {}: {:?}", max_participants, ack),
+            Payload::Error(error) => {
+                assert!(!accepted, "Rejected max_participants {}: {}", max_participants, error.error_message);
+                assert_eq!(error.error_code, 11);
+                assert!(error.error_message.contains("max_participants"), "Unexpected error: {}", error.error_message);
+            }
+            other => panic!("Unexpected response {:?}", other),
+        }
+    }
+    assert_eq!(repositories.webrtc_rooms.get_room_count().await.unwrap(), 1);
+}
+
+/// An active room created by `sender_client` with the default cap, and a join handler sharing its repositories
+async fn capped_room_fixture() -> (signal_manager_service::webrtc_handlers::WebRTCRoomJoinHandler, RoomCreateRepositories, String) {
+    use signal_manager_service::config::Config;
+    use signal_manager_service::database::WebRTCRoomStatus;
+    use signal_manager_service::webrtc_handlers::WebRTCRoomJoinHandler;
+    use std::sync::Arc;
+
+    let provider = Arc::new(MockSignalingProvider::default());
+    let (create_handler, repositories) = room_create_handler(provider.clone());
+    let join_handler = WebRTCRoomJoinHandler::new(Arc::new(Config::default()))
+        .with_repositories(RoomJoinRepositories {
+            webrtc_rooms: repositories.webrtc_rooms.clone(),
+            webrtc_clients: repositories.webrtc_clients.clone(),
+            clients_in_rooms: Arc::new(crate::database::repository::MockClientInRoomRepository::new()),
+        })
+        .with_provider(provider);
+    let room_id = match create_handler.handle_room_create(sender_room_create()).await.unwrap().payload {
+        Payload::WebRTCRoomCreateAck(ack) => ack.room_id.expect("Room id present"),
+        other => panic!("Expected WebRTCRoomCreateAck, got {:?}", other),
+    };
+    repositories.webrtc_rooms.update_room_status(&room_id, WebRTCRoomStatus::Active).await.unwrap();
+    (join_handler, repositories, room_id)
+}
+
+#[tokio::test]
+async fn test_room_cap_counts_only_active_participants() {
+    use signal_manager_service::database::{WebRTCClientRegistrationPayload, WebRTCClientStatus};
+
+    let (join_handler, repositories, room_id) = capped_room_fixture().await;
+    // A pending record and a receiver that has since disconnected hold no place
+    repositories.webrtc_clients.register_client(WebRTCClientRegistrationPayload {
+        client_id: "pending_client".to_string(),
+        room_id: room_id.clone(),
+        role: ClientRole::Receiver,
+        session_id: None,
+        metadata: None,
+    }).await.unwrap();
+    match join_handler.handle_room_join(room_join(&room_id, "gone_client", "receiver")).await.unwrap().payload {
+        Payload::WebRTCRoomJoinAck(ack) => assert_eq!(ack.status, 200),
+        other => panic!("Expected WebRTCRoomJoinAck, got {:?}", other),
+    }
+    let admitted = repositories.webrtc_clients.get_client_by_id("gone_client").await.unwrap().expect("Client stored");
+    assert!(admitted.is_active());
+    repositories.webrtc_clients.update_client_status("gone_client", WebRTCClientStatus::Disconnected).await.unwrap();
+
+    match join_handler.handle_room_join(room_join(&room_id, "receiver_client", "receiver")).await.unwrap().payload {
+        Payload::WebRTCRoomJoinAck(ack) => assert_eq!(ack.status, 200),
+        other => panic!("Expected WebRTCRoomJoinAck, got {:?}", other),
+    }
+    match join_handler.handle_room_join(room_join(&room_id, "third_client", "receiver")).await.unwrap().payload {
+        Payload::Error(error) => assert_eq!(error.error_message, "Room is full"),
+        other => panic!("Expected room full error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_concurrent_joins_never_overfill_a_room() {
+    let (join_handler, repositories, room_id) = capped_room_fixture().await;
+
+    // The sender holds one of the two places; of ten receivers racing for the other, one wins
+    let joins = (0..10).map(|n| {
+        let join_handler = join_handler.clone();
+        let message = room_join(&room_id, &format!("receiver_{n}"), "receiver");
+        tokio::spawn(async move { join_handler.handle_room_join(message).await.unwrap() })
+    }).collect::<Vec<_>>();
+    let mut joined = 0;
+    for join in futures::future::join_all(joins).await {
+        match join.unwrap().payload {
+            Payload::WebRTCRoomJoinAck(_) => joined += 1,
+            Payload::Error(error) => assert_eq!(error.error_message, "Room is full"),
+            other => panic!("Unexpected response {:?}", other),
+        }
+    }
+    assert_eq!(joined, 1);
+    let participants = repositories.webrtc_clients.get_clients_by_room_id(&room_id).await.unwrap();
+    assert_eq!(participants.iter().filter(|client| client.holds_participant_slot()).count(), 2);
+}
+
+#[tokio::test]
+async fn test_room_list_handler_pages_active_rooms() {
+    use crate::database::repository::MockClientInRoomRepository;
+    use signal_manager_service::config::Config;
+    use signal_manager_service::database::{ClientInRoom, WebRTCRoomCreationPayload, WebRTCRoomStatus};
+    use signal_manager_service::message::WebRTCRoomListPayload;
+    use signal_manager_service::webrtc_handlers::room_list::RoomListRepositories;
+    use signal_manager_service::webrtc_handlers::WebRTCRoomListHandler;
+    use std::sync::Arc;
+
+    let repositories = RoomListRepositories {
+        webrtc_rooms: Arc::new(MockWebRTCRoomRepository::new()),
+        clients_in_rooms: Arc::new(MockClientInRoomRepository::new()),
+    };
+    for room_id in ["room_1", "room_2", "room_3"] {
+        repositories.webrtc_rooms.create_room(WebRTCRoomCreationPayload {
+            room_id: room_id.to_string(),
+            app_id: "app".to_string(),
+            sender_client_id: Some(format!("{room_id}_sender")),
+            receiver_client_id: None,
+            session_id: None,
+            max_participants: None,
+            metadata: None,
+        }).await.unwrap();
+        tokio::time::sleep(tokio::time::Duration::from_millis(2)).await;
+    }
+    repositories.webrtc_rooms.update_room_status("room_1", WebRTCRoomStatus::Active).await.unwrap();
+    repositories.webrtc_rooms.update_room_status("room_2", WebRTCRoomStatus::Active).await.unwrap();
+    for (client_id, room_id) in [("room_1_sender", "room_1"), ("room_1_receiver", "room_1"), ("room_2_sender", "room_2")] {
+        repositories.clients_in_rooms.create_client_in_room(
+            ClientInRoom::new(client_id.to_string(), room_id.to_string(), vec![], None)
+        ).await.unwrap();
+    }
+
+    let mut config = Config::default();
+    config.security.admin_clients = vec!["operator".to_string()];
+    let handler = WebRTCRoomListHandler::new(Arc::new(config)).with_repositories(repositories);
+    assert!(handler.is_admin("operator"));
+    assert!(!handler.is_admin("room_1_sender"));
+
+    let list = |offset, limit| {
+        let handler = handler.clone();
+        async move {
+            match handler.handle_room_list(&WebRTCRoomListPayload { offset, limit }).await.unwrap().payload {
+                Payload::WebRTCRoomListAck(ack) => ack,
+                other => panic!("Expected WebRTCRoomListAck, got {:?}", other),
+            }
+        }
+    };
+
+    // Both active rooms, oldest first; the pending room is left out
+    let ack = list(0, 0).await;
+    assert_eq!(ack.total, 2);
+    assert_eq!(ack.next_offset, None);
+    let rooms: Vec<(&str, &str, u32)> = ack.rooms.iter().map(|room| (room.room_id.as_str(), room.status.as_str(), room.participant_count)).collect();
+    assert_eq!(rooms, vec![("room_1", "active", 2), ("room_2", "active", 1)]);
+
+    // One room per page
+    let first = list(0, 1).await;
+    assert_eq!(first.rooms.iter().map(|room| room.room_id.as_str()).collect::<Vec<_>>(), vec!["room_1"]);
+    assert_eq!(first.next_offset, Some(1));
+    let second = list(1, 1).await;
+    assert_eq!(second.rooms.iter().map(|room| room.room_id.as_str()).collect::<Vec<_>>(), vec!["room_2"]);
+    assert_eq!(second.next_offset, None);
+    assert!(list(5, 1).await.rooms.is_empty());
+}
@@ -0,0 +1,82 @@
+//! Wire-format diagnostics: encode a JSON message description into the exact
+//! bytes produced by `Message::to_binary`, and decode hex frames back to JSON.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use signal_manager_service::message::{Message, MessageType, Payload, PayloadType};
+use uuid::Uuid;
+
+/// Human-editable description of a frame. `uuid` and `payload_type` are optional
+/// when encoding; a random UUID and JSON payload type are used by default.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrameDescription {
+    pub message_type: MessageType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload_type: Option<PayloadType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>,
+    pub payload: Payload,
+}
+
+impl From<Message> for FrameDescription {
+    fn from(message: Message) -> Self {
+        Self {
+            message_type: message.message_type,
+            uuid: Some(message.uuid),
+            payload_type: Some(message.payload_type),
+            created_at: message.created_at,
+            payload: message.payload,
+        }
+    }
+}
+
+impl From<FrameDescription> for Message {
+    fn from(description: FrameDescription) -> Self {
+        Message {
+            message_type: description.message_type,
+            uuid: description.uuid.unwrap_or_else(Uuid::new_v4),
+            payload_type: description.payload_type.unwrap_or(PayloadType::Json),
+            payload: description.payload,
+            created_at: description.created_at,
+        }
+    }
+}
+
+/// Encode a JSON frame description into a hex string of the binary frame
+pub fn encode(json: &str) -> Result<String> {
+    let description: FrameDescription = serde_json::from_str(json)
+        .context("Invalid JSON message description")?;
+    let binary = Message::from(description).to_binary()
+        .map_err(|e| anyhow::anyhow!("Failed to encode frame: {}", e))?;
+    Ok(to_hex(&binary))
+}
+
+/// Decode a hex string of a binary frame into a pretty-printed JSON description
+pub fn decode(hex: &str) -> Result<String> {
+    let bytes = from_hex(hex)?;
+    let message = Message::from_binary(&bytes)
+        .map_err(|e| anyhow::anyhow!("Failed to decode frame: {}", e))?;
+    let json = serde_json::to_string_pretty(&FrameDescription::from(message))?;
+    Ok(json)
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Parse hex, ignoring whitespace and an optional `0x` prefix
+pub fn from_hex(hex: &str) -> Result<Vec<u8>> {
+    let cleaned: String = hex.trim().trim_start_matches("0x").chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    if !cleaned.len().is_multiple_of(2) {
+        anyhow::bail!("Hex string has an odd number of digits");
+    }
+    (0..cleaned.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&cleaned[i..i + 2], 16)
+            .with_context(|| format!("Invalid hex byte at offset {}", i)))
+        .collect()
+}
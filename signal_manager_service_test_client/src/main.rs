@@ -1,4 +1,5 @@
 use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
 use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
@@ -6,6 +7,27 @@ use tokio::time::timeout;
 use tokio_tungstenite::{connect_async, tungstenite::Message};
 use tracing::{error, info};
 use uuid::Uuid;
+use signal_manager_service_test_client::frame_dump;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Dump the binary wire format of a message, or decode a frame back to JSON
+    Frame {
+        /// JSON message description to encode into hex bytes
+        #[arg(long, conflicts_with = "decode", required_unless_present = "decode")]
+        encode: Option<String>,
+        /// Hex-encoded binary frame to decode into JSON
+        #[arg(long)]
+        decode: Option<String>,
+    },
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 struct RegisterPayload {
@@ -199,6 +221,16 @@ async fn test_unregister(ws_url: &str, client_id: &str, auth_token: &str) -> Res
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let args = Args::parse();
+    if let Some(Command::Frame { encode, decode }) = args.command {
+        if let Some(json) = encode {
+            println!("{}", frame_dump::encode(&json)?);
+        } else if let Some(hex) = decode {
+            println!("{}", frame_dump::decode(&hex)?);
+        }
+        return Ok(());
+    }
+
     tracing_subscriber::fmt::init();
     let ws_url = std::env::var("WS_URL").unwrap_or_else(|_| {
         "ws://localhost:8080/ws".to_string()
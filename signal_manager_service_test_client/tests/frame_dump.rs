@@ -0,0 +1,45 @@
+use signal_manager_service::message::{ConnectPayload, Message, MessageType, Payload};
+use signal_manager_service_test_client::frame_dump::{decode, encode, from_hex, FrameDescription};
+
+const SAMPLE: &str = r#"{
+    "message_type": "CONNECT",
+    "uuid": "6f1c2b3a-4d5e-4f60-8a7b-9c0d1e2f3a4b",
+    "payload_type": "JSON",
+    "payload": { "Connect": { "client_id": "test_client_1", "auth_token": "test_token_1" } }
+}"#;
+
+#[test]
+fn test_encode_matches_to_binary() {
+    let hex = encode(SAMPLE).expect("Failed to encode");
+
+    let description: FrameDescription = serde_json::from_str(SAMPLE).unwrap();
+    let expected = Message::from(description).to_binary().unwrap();
+    assert_eq!(from_hex(&hex).unwrap(), expected);
+}
+
+#[test]
+fn test_encode_decode_round_trip() {
+    let hex = encode(SAMPLE).expect("Failed to encode");
+    let json = decode(&hex).expect("Failed to decode");
+
+    let decoded: FrameDescription = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded.message_type, MessageType::Connect);
+    assert_eq!(decoded.uuid.unwrap().to_string(), "6f1c2b3a-4d5e-4f60-8a7b-9c0d1e2f3a4b");
+    match decoded.payload {
+        Payload::Connect(ConnectPayload { client_id, auth_token }) => {
+            assert_eq!(client_id, "test_client_1");
+            assert_eq!(auth_token, "test_token_1");
+        }
+        other => panic!("Unexpected payload: {:?}", other),
+    }
+
+    // Re-encoding the decoded description yields the same bytes
+    assert_eq!(encode(&json).unwrap(), hex);
+}
+
+#[test]
+fn test_decode_rejects_invalid_hex() {
+    assert!(decode("aa0").is_err());
+    assert!(decode("zz").is_err());
+    assert!(decode("aa01").is_err());
+}
@@ -1,24 +1,44 @@
 use crate::webrtcclient::{WebRTCClient, SDPOffer, RoomCreationParams};
-use crate::signalmanager::{SignalManagerClient, SignalManagerConfig, ConnectionState, WebRTCRoomCreatePayload};
+use crate::signalmanager::{SignalManagerClient, SignalManagerConfig, ConnectionState, RoomSummary, WebRTCRoomCreatePayload};
+use crate::error::CommandError;
+use crate::resume::{self, ResumeState};
 use crate::WebRTCRoomCreatePayloadWrapper;
-use log::{info, error, debug};
+use log::{info, error, debug, warn};
 use once_cell::sync::OnceCell;
 use std::sync::Arc;
 use tokio::sync::Mutex as TokioMutex;
-use tauri::Emitter;
+use tauri::{Emitter, Manager};
+use std::path::PathBuf;
 use std::sync::Mutex as StdMutex;
 
 // Global signal manager client - now re-initializable and async safe
 pub static SIGNAL_MANAGER: once_cell::sync::Lazy<TokioMutex<Option<Arc<TokioMutex<SignalManagerClient>>>>> = once_cell::sync::Lazy::new(|| TokioMutex::new(None));
 
+// Latest state reported by the client, readable while a connection attempt holds the client lock
+static SIGNAL_MANAGER_STATE: once_cell::sync::Lazy<StdMutex<ConnectionState>> = once_cell::sync::Lazy::new(|| StdMutex::new(ConnectionState::default()));
+
+// The task keeping the connection up, so connecting again never starts a second one and
+// disconnecting or resetting can stop it
+static KEEPER: once_cell::sync::Lazy<StdMutex<Option<tokio::task::JoinHandle<()>>>> = once_cell::sync::Lazy::new(|| StdMutex::new(None));
+
+// How long the connection keeper handles messages before letting other commands use the client
+const KEEP_CONNECTED_WINDOW: std::time::Duration = std::time::Duration::from_millis(200);
+
+fn resume_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, CommandError> {
+    app_handle.path().app_data_dir().map_err(|e| {
+        error!("[resume_dir] Failed to resolve app data dir: {}", e);
+        e.into()
+    })
+}
+
 // Tauri commands for WebRTC
 #[tauri::command]
-pub async fn generate_webrtc_offer() -> Result<SDPOffer, String> {
+pub async fn generate_webrtc_offer() -> Result<SDPOffer, CommandError> {
     info!("[generate_webrtc_offer] Generating WebRTC offer");
     let mut client = WebRTCClient::with_default_config();
     client.create_offer().await.map_err(|e| {
         error!("[generate_webrtc_offer] Failed to generate offer: {}", e);
-        e.to_string()
+        e.into()
     })
 }
 
@@ -27,24 +47,24 @@ pub async fn prepare_room_creation(
     client_id: String,
     auth_token: String,
     role: String,
-) -> Result<RoomCreationParams, String> {
+) -> Result<RoomCreationParams, CommandError> {
     info!("[prepare_room_creation] Preparing room creation for client_id: {}", client_id);
     let mut client = WebRTCClient::with_default_config();
     client.prepare_room_creation(client_id, auth_token, role)
         .await
         .map_err(|e| {
             error!("[prepare_room_creation] Failed to prepare room creation: {}", e);
-            e.to_string()
+            e.into()
         })
 }
 
 #[tauri::command]
-pub async fn cleanup_webrtc_connection() -> Result<(), String> {
+pub async fn cleanup_webrtc_connection() -> Result<(), CommandError> {
     info!("[cleanup_webrtc_connection] Cleaning up WebRTC connection");
     let mut client = WebRTCClient::with_default_config();
     client.reset_with_config(false).await.map_err(|e| {
         error!("[cleanup_webrtc_connection] Failed to cleanup WebRTC connection: {}", e);
-        e.to_string()
+        e.into()
     })
 }
 
@@ -53,7 +73,7 @@ pub async fn create_room_with_webrtc(
     client_id: String,
     auth_token: String,
     role: String,
-) -> Result<WebRTCRoomCreatePayloadWrapper, String> {
+) -> Result<WebRTCRoomCreatePayloadWrapper, CommandError> {
     info!("[create_room_with_webrtc] Creating room with WebRTC for client_id: {}", client_id);
     let mut client = WebRTCClient::with_default_config();
     
@@ -61,7 +81,7 @@ pub async fn create_room_with_webrtc(
         .await
         .map_err(|e| {
             error!("[create_room_with_webrtc] Failed to prepare room creation: {}", e);
-            e.to_string()
+            CommandError::from(e)
         })?;
     
     Ok(WebRTCRoomCreatePayloadWrapper {
@@ -81,15 +101,24 @@ pub async fn init_signal_manager(
     port: u16,
     client_id: String,
     auth_token: String,
+    reconnect_attempts: Option<u32>,
+    reconnect_delay: Option<u64>,
     app_handle: tauri::AppHandle,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     info!("[init_signal_manager] Initializing signal manager: url={}, port={}, client_id={}", url, port, client_id);
-    let config = SignalManagerConfig::new(url, port, client_id, auth_token);
+    let mut config = SignalManagerConfig::new(url, port, client_id, auth_token);
+    if let Some(reconnect_attempts) = reconnect_attempts {
+        config.reconnect_attempts = reconnect_attempts;
+    }
+    if let Some(reconnect_delay) = reconnect_delay {
+        config.reconnect_delay = reconnect_delay;
+    }
     let mut client = SignalManagerClient::new(config);
     // Set up state callback to emit events
     let app_handle_clone = app_handle.clone();
     client.set_state_callback(Box::new(move |state| {
         info!("SignalManager state changed: {:?}", state);
+        *SIGNAL_MANAGER_STATE.lock().unwrap() = state.clone();
         let _ = app_handle_clone.emit("signal-manager:state-changed", state.clone());
         match state.state_type {
             crate::signalmanager::types::ConnectionStateType::Connected => {
@@ -112,42 +141,74 @@ pub async fn init_signal_manager(
     let arc_client = Arc::new(TokioMutex::new(client));
     let mut global = SIGNAL_MANAGER.lock().await;
     if global.is_some() {
-        return Err("Signal manager already initialized".to_string());
+        return Err(CommandError::InvalidInput("Signal manager already initialized".to_string()));
     }
+    *SIGNAL_MANAGER_STATE.lock().unwrap() = ConnectionState::default();
     *global = Some(arc_client);
     app_handle.emit("signal-manager:initialized", ()).map_err(|e| {
         error!("[init_signal_manager] Failed to emit initialized event: {}", e);
-        e.to_string()
+        CommandError::from(e)
     })?;
     info!("[init_signal_manager] Signal manager initialized successfully");
     Ok(())
 }
 
 #[tauri::command]
-pub async fn connect_signal_manager() -> Result<(), String> {
+pub async fn connect_signal_manager() -> Result<(), CommandError> {
     info!("[connect_signal_manager] Connecting to signal manager");
     let global = SIGNAL_MANAGER.lock().await;
     let client = match &*global {
         Some(c) => c.clone(),
-        None => return Err("Signal manager not initialized".to_string()),
+        None => return Err(CommandError::NotConnected("Signal manager not initialized".to_string())),
     };
     drop(global);
+    let keeper = client.clone();
     let mut client = client.lock().await;
     match client.connect().await {
         Ok(()) => {
             info!("[connect_signal_manager] Successfully connected to signal manager");
+            let mut task = KEEPER.lock().unwrap();
+            if task.as_ref().is_none_or(|task| task.is_finished()) {
+                *task = Some(tokio::spawn(keep_connected(keeper)));
+            }
             Ok(())
         }
         Err(e) => {
             error!("[connect_signal_manager] Failed to connect to signal manager: {}", e);
-            Err(e.to_string())
+            Err(e.into())
+        }
+    }
+}
+
+// Handle incoming messages and reconnect after drops until the client is disconnected, reset or
+// gives up, releasing the client between windows, including while waiting to retry, so other
+// commands can use it
+async fn keep_connected(client: Arc<TokioMutex<SignalManagerClient>>) {
+    loop {
+        let result = client.lock().await.maintain(KEEP_CONNECTED_WINDOW).await;
+        match result {
+            Ok(true) => tokio::task::yield_now().await,
+            Ok(false) => break,
+            Err(e) => {
+                error!("[keep_connected] Gave up reconnecting to signal manager: {}", e);
+                break;
+            }
         }
     }
+    info!("[keep_connected] No longer keeping the signal manager connection up");
+}
+
+// Stop the connection keeper, cancelling any reconnect it has in progress
+fn stop_keeper() {
+    if let Some(task) = KEEPER.lock().unwrap().take() {
+        task.abort();
+    }
 }
 
 #[tauri::command]
-pub async fn disconnect_signal_manager() -> Result<(), String> {
+pub async fn disconnect_signal_manager(app_handle: tauri::AppHandle) -> Result<(), CommandError> {
     info!("[disconnect_signal_manager] Disconnecting from signal manager");
+    stop_keeper();
     let client = {
         let mut global = SIGNAL_MANAGER.lock().await;
         if let Some(client) = &*global {
@@ -157,9 +218,13 @@ pub async fn disconnect_signal_manager() -> Result<(), String> {
             match client.disconnect().await {
                 Ok(()) => {
                     info!("[disconnect_signal_manager] Successfully disconnected from signal manager");
+                    // A clean leave means there is nothing to resume on the next start
+                    if let Err(e) = resume::clear_resume_state(&resume_dir(&app_handle)?) {
+                        warn!("[disconnect_signal_manager] Failed to clear resume state: {}", e);
+                    }
                     if let Err(e) = client.reset().await {
                         error!("[disconnect_signal_manager] Failed to reset client state: {}", e);
-                        return Err(format!("Failed to reset client state: {}", e));
+                        return Err(CommandError::Signaling(format!("Failed to reset client state: {}", e)));
                     }
                     // Drop the client
                     let mut global = SIGNAL_MANAGER.lock().await;
@@ -168,19 +233,20 @@ pub async fn disconnect_signal_manager() -> Result<(), String> {
                 }
                 Err(e) => {
                     error!("[disconnect_signal_manager] Failed to disconnect from signal manager: {}", e);
-                    Err(e.to_string())
+                    Err(e.into())
                 }
             }
         } else {
-            Err("Signal manager not initialized".to_string())
+            Err(CommandError::NotConnected("Signal manager not initialized".to_string()))
         }
     };
     client
 }
 
 #[tauri::command]
-pub async fn reset_signal_manager() -> Result<(), String> {
+pub async fn reset_signal_manager() -> Result<(), CommandError> {
     info!("[reset_signal_manager] Resetting signal manager state");
+    stop_keeper();
     let client = {
         let mut global = SIGNAL_MANAGER.lock().await;
         if let Some(client) = &*global {
@@ -196,7 +262,7 @@ pub async fn reset_signal_manager() -> Result<(), String> {
                 }
                 Err(e) => {
                     error!("[reset_signal_manager] Failed to reset signal manager state: {}", e);
-                    Err(format!("Failed to reset signal manager state: {}", e))
+                    Err(CommandError::Signaling(format!("Failed to reset signal manager state: {}", e)))
                 }
             }
         } else {
@@ -208,15 +274,12 @@ pub async fn reset_signal_manager() -> Result<(), String> {
 }
 
 #[tauri::command]
-pub async fn get_signal_manager_state() -> Result<ConnectionState, String> {
-    let global = SIGNAL_MANAGER.lock().await;
-    let client = match &*global {
-        Some(c) => c.clone(),
-        None => return Err("Signal manager not initialized".to_string()),
-    };
-    drop(global);
-    let client = client.lock().await;
-    Ok(client.get_state())
+pub async fn get_signal_manager_state() -> Result<ConnectionState, CommandError> {
+    if SIGNAL_MANAGER.lock().await.is_none() {
+        return Err(CommandError::NotConnected("Signal manager not initialized".to_string()));
+    }
+    // Not the client's own copy: a connection attempt in progress holds the client lock
+    Ok(SIGNAL_MANAGER_STATE.lock().unwrap().clone())
 }
 
 #[tauri::command]
@@ -227,35 +290,88 @@ pub async fn send_room_create(
     role: String,
     offer_sdp: Option<String>,
     metadata: Option<serde_json::Value>,
-) -> Result<(Option<String>, Option<String>), String> {
+    app_handle: tauri::AppHandle,
+) -> Result<(Option<String>, Option<String>), CommandError> {
     info!("[send_room_create] Sending room create request for client_id: {}", client_id);
     let global = SIGNAL_MANAGER.lock().await;
     let client = match &*global {
         Some(c) => c.clone(),
-        None => return Err("Signal manager not initialized".to_string()),
+        None => return Err(CommandError::NotConnected("Signal manager not initialized".to_string())),
     };
     drop(global);
     let mut client = client.lock().await;
     let payload = WebRTCRoomCreatePayload {
         version,
-        client_id,
-        auth_token,
-        role,
+        client_id: client_id.clone(),
+        auth_token: auth_token.clone(),
+        role: role.clone(),
         offer_sdp,
         metadata,
     };
     match client.send_room_create(payload).await {
         Ok(result) => {
             info!("[send_room_create] Room created successfully: {:?}", result);
+            if let (Some(room_id), session_id) = &result {
+                let state = ResumeState {
+                    client_id,
+                    session_id: session_id.clone(),
+                    reconnect_token: auth_token,
+                    room_id: room_id.clone(),
+                    role,
+                };
+                if let Err(e) = resume::save_resume_state(&resume_dir(&app_handle)?, &state) {
+                    warn!("[send_room_create] Failed to persist resume state: {}", e);
+                }
+            }
             Ok(result)
         }
         Err(e) => {
             error!("[send_room_create] Failed to create room: {}", e);
-            Err(e.to_string())
+            Err(e.into())
         }
     }
 }
 
+#[tauri::command]
+pub async fn list_rooms() -> Result<Vec<RoomSummary>, CommandError> {
+    info!("[list_rooms] Listing active rooms");
+    let global = SIGNAL_MANAGER.lock().await;
+    let client = match &*global {
+        Some(c) => c.clone(),
+        None => return Err(CommandError::NotConnected("Signal manager not initialized".to_string())),
+    };
+    drop(global);
+    let mut client = client.lock().await;
+    match client.list_rooms().await {
+        Ok(rooms) => {
+            info!("[list_rooms] Listed {} active rooms", rooms.len());
+            Ok(rooms)
+        }
+        Err(e) => {
+            error!("[list_rooms] Failed to list rooms: {}", e);
+            Err(e.into())
+        }
+    }
+}
+
+// Session left behind by a previous run that didn't leave cleanly, if any
+#[tauri::command]
+pub async fn get_resume_state(app_handle: tauri::AppHandle) -> Result<Option<ResumeState>, CommandError> {
+    resume::load_resume_state(&resume_dir(&app_handle)?).map_err(|e| {
+        error!("[get_resume_state] Failed to load resume state: {}", e);
+        e.into()
+    })
+}
+
+#[tauri::command]
+pub async fn discard_resume_state(app_handle: tauri::AppHandle) -> Result<(), CommandError> {
+    info!("[discard_resume_state] Discarding saved resume state");
+    resume::clear_resume_state(&resume_dir(&app_handle)?).map_err(|e| {
+        error!("[discard_resume_state] Failed to clear resume state: {}", e);
+        e.into()
+    })
+}
+
 pub fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 } 
\ No newline at end of file
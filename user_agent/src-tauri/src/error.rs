@@ -0,0 +1,70 @@
+use crate::signalmanager::error::ERROR_CODE_FORBIDDEN;
+use crate::signalmanager::SignalManagerError;
+use crate::webrtcclient::WebRTCError;
+use serde::Serialize;
+use thiserror::Error;
+
+// Error returned by every Tauri command. Serialized as `{ "kind": ..., "message": ... }`
+// so the frontend can branch on the kind instead of parsing the message.
+#[derive(Error, Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", content = "message")]
+pub enum CommandError {
+    #[error("{0}")]
+    NotConnected(String),
+
+    #[error("{0}")]
+    Timeout(String),
+
+    #[error("{0}")]
+    WebRtc(String),
+
+    #[error("{0}")]
+    Signaling(String),
+
+    #[error("{0}")]
+    InvalidInput(String),
+
+    #[error("{0}")]
+    Forbidden(String),
+
+    #[error("{0}")]
+    Io(String),
+
+    #[error("{0}")]
+    Tauri(String),
+}
+
+impl From<SignalManagerError> for CommandError {
+    fn from(err: SignalManagerError) -> Self {
+        let message = err.to_string();
+        match err {
+            SignalManagerError::NotConnected => CommandError::NotConnected(message),
+            SignalManagerError::Timeout(_) => CommandError::Timeout(message),
+            SignalManagerError::InvalidConfig(_) => CommandError::InvalidInput(message),
+            SignalManagerError::Server(ERROR_CODE_FORBIDDEN, _) => CommandError::Forbidden(message),
+            _ => CommandError::Signaling(message),
+        }
+    }
+}
+
+impl From<WebRTCError> for CommandError {
+    fn from(err: WebRTCError) -> Self {
+        let message = err.to_string();
+        match err {
+            WebRTCError::InvalidConfig(_) => CommandError::InvalidInput(message),
+            _ => CommandError::WebRtc(message),
+        }
+    }
+}
+
+impl From<tauri::Error> for CommandError {
+    fn from(err: tauri::Error) -> Self {
+        CommandError::Tauri(err.to_string())
+    }
+}
+
+impl From<std::io::Error> for CommandError {
+    fn from(err: std::io::Error) -> Self {
+        CommandError::Io(err.to_string())
+    }
+}
@@ -4,6 +4,8 @@
 pub mod webrtcclient;
 pub mod signalmanager;
 pub mod commands;
+pub mod error;
+pub mod resume;
 
 use serde::{Deserialize, Serialize};
 use simplelog::{Config, LevelFilter, WriteLogger};
@@ -40,7 +42,10 @@ pub fn run() {
             commands::disconnect_signal_manager,
             commands::reset_signal_manager,
             commands::get_signal_manager_state,
-            commands::send_room_create
+            commands::send_room_create,
+            commands::list_rooms,
+            commands::get_resume_state,
+            commands::discard_resume_state
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+// File name of the resume state inside the app-data dir
+pub const RESUME_STATE_FILE: &str = "resume_state.json";
+
+// Everything needed to rejoin the last room after the app was killed mid-session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResumeState {
+    pub client_id: String,
+    pub session_id: Option<String>,
+    pub reconnect_token: String,
+    pub room_id: String,
+    pub role: String,
+}
+
+pub fn resume_state_path(dir: &Path) -> PathBuf {
+    dir.join(RESUME_STATE_FILE)
+}
+
+pub fn save_resume_state(dir: &Path, state: &ResumeState) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let json = serde_json::to_vec_pretty(state)?;
+    // Write to a temp file first so a crash mid-write can't leave a truncated file behind
+    let tmp_path = dir.join(format!("{}.tmp", RESUME_STATE_FILE));
+    let mut file = owner_only_file(&tmp_path)?;
+    file.write_all(&json)?;
+    file.sync_all()?;
+    fs::rename(tmp_path, resume_state_path(dir))
+}
+
+// The state holds the reconnect token, so only the owner may read it
+#[cfg(unix)]
+fn owner_only_file(path: &Path) -> io::Result<fs::File> {
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+    let file = fs::OpenOptions::new().write(true).create(true).truncate(true).mode(0o600).open(path)?;
+    // `mode` only applies on creation, so tighten a temp file left behind by an older build too
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    Ok(file)
+}
+
+#[cfg(not(unix))]
+fn owner_only_file(path: &Path) -> io::Result<fs::File> {
+    fs::File::create(path)
+}
+
+// Returns None when nothing was saved; an unreadable file is treated the same way
+pub fn load_resume_state(dir: &Path) -> io::Result<Option<ResumeState>> {
+    let data = match fs::read(resume_state_path(dir)) {
+        Ok(data) => data,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    match serde_json::from_slice(&data) {
+        Ok(state) => Ok(Some(state)),
+        Err(e) => {
+            log::warn!("[load_resume_state] Ignoring corrupt resume state: {}", e);
+            Ok(None)
+        }
+    }
+}
+
+pub fn clear_resume_state(dir: &Path) -> io::Result<()> {
+    match fs::remove_file(resume_state_path(dir)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
@@ -2,6 +2,7 @@ use crate::signalmanager::error::SignalManagerError;
 use crate::signalmanager::types::*;
 use crate::signalmanager::websocket::WebSocketClient;
 use crate::signalmanager::config::SignalManagerConfig;
+use std::collections::VecDeque;
 use std::time::Duration;
 use log::{info, error, debug, warn};
 
@@ -20,6 +21,17 @@ pub struct SignalManagerClient {
     state: ConnectionState,
     state_callback: Option<StateCallback>,
     last_room_response: RoomResponse,
+    last_room_list: Option<WebRTCRoomListAckPayload>,
+    // Last Error the server answered with, so a pending request can fail instead of timing out
+    last_error: Option<ErrorPayload>,
+    // Messages sent while there was no socket, flushed after the next Connect/Register
+    pending: VecDeque<Message>,
+    last_disconnect_reason: Option<String>,
+    active_room: Option<ActiveRoom>,
+    // Started with each session; ticks while incoming messages are being handled
+    heartbeat: Option<tokio::time::Interval>,
+    // Number of the next reconnect attempt after a drop and when it is due
+    next_attempt: Option<(u32, tokio::time::Instant)>,
 }
 
 impl SignalManagerClient {
@@ -31,6 +43,13 @@ impl SignalManagerClient {
             state: ConnectionState::default(),
             state_callback: None,
             last_room_response: None,
+            last_room_list: None,
+            last_error: None,
+            pending: VecDeque::new(),
+            last_disconnect_reason: None,
+            active_room: None,
+            heartbeat: None,
+            next_attempt: None,
         }
     }
 
@@ -68,32 +87,8 @@ impl SignalManagerClient {
             last_heartbeat: 0,
         });
 
-        // Create WebSocket connection
-        let url = self.config.websocket_url();
-        info!("[connect] Connecting to WebSocket at {}", url);
-        
-        let mut websocket = WebSocketClient::new(url);
-        match websocket.connect().await {
+        match self.open_session().await {
             Ok(()) => {
-                info!("[connect] WebSocket connection established");
-                self.websocket = Some(websocket);
-                
-                // Update state to connected
-                self.update_state(ConnectionState {
-                    state_type: ConnectionStateType::Connected,
-                    is_connected: true,
-                    is_connecting: false,
-                    is_reconnecting: false,
-                    reconnect_attempts: 0,
-                    current_retry_interval: 0,
-                    next_retry_time: None,
-                    last_heartbeat: 0,
-                });
-
-                // Send initial messages
-                self.send_connect().await?;
-                self.send_register().await?;
-                
                 info!("[connect] Successfully connected and registered");
                 Ok(())
             }
@@ -114,6 +109,197 @@ impl SignalManagerClient {
         }
     }
 
+    /// Handle incoming messages until the server closes the socket, then leave the client ready
+    /// for `reconnect`. Returns the reason from the server's Disconnect, if it sent one.
+    /// Sends a Heartbeat every `heartbeat_interval` seconds meanwhile, following the interval
+    /// the server advertises in its ConnectAck.
+    pub async fn run_until_disconnected(&mut self) -> Option<String> {
+        self.last_disconnect_reason = None;
+        self.pump(None).await;
+        self.connection_lost().await;
+        self.last_disconnect_reason.clone()
+    }
+
+    /// Keep the session up: handle incoming messages for up to `window`, then return so other
+    /// callers can take the client. If the socket drops meanwhile, or dropped earlier, reconnect
+    /// with the backoff `reconnect` uses, but only wait out the delay until the end of `window`
+    /// and make at most one attempt per call, so the client is never held for the whole backoff.
+    /// Returns false when there is no session to keep, because the client never connected, was
+    /// disconnected on request or reset; an error once it gives up reconnecting.
+    pub async fn maintain(&mut self, window: Duration) -> Result<bool, SignalManagerError> {
+        let until = tokio::time::Instant::now() + window;
+        if self.websocket.is_some() {
+            if !self.pump(Some(until)).await {
+                return Ok(true);
+            }
+            self.connection_lost().await;
+        } else if !self.state.is_reconnecting {
+            return Ok(false);
+        }
+        self.attempt_reconnect(Some(until)).await.map(|_| true)
+    }
+
+    // Handle incoming messages and send heartbeats until the socket closes (true) or `until` passes (false)
+    async fn pump(&mut self, until: Option<tokio::time::Instant>) -> bool {
+        loop {
+            let period = self.heartbeat_period();
+            if !matches!(&self.heartbeat, Some(timer) if timer.period() == period) {
+                self.heartbeat = Some(self.heartbeat_timer());
+            }
+            let (Some(websocket), Some(heartbeat)) = (&mut self.websocket, &mut self.heartbeat) else {
+                return true;
+            };
+            let deadline = async {
+                match until {
+                    Some(until) => tokio::time::sleep_until(until).await,
+                    None => std::future::pending().await,
+                }
+            };
+            // None on a heartbeat tick, Some(None) once the socket has closed
+            let received = tokio::select! {
+                received = websocket.receive() => Some(received.ok().flatten()),
+                _ = heartbeat.tick() => None,
+                _ = deadline => return false,
+            };
+            match received {
+                Some(Some(message)) => {
+                    if let Err(e) = self.handle_message(message).await {
+                        warn!("[pump] Failed to handle message: {}", e);
+                    }
+                }
+                Some(None) => return true,
+                None => {
+                    if let Err(e) = self.send(Message::heartbeat()) {
+                        warn!("[pump] Failed to send heartbeat: {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    // Close what is left of a dropped socket and report that the client will try to reconnect
+    async fn connection_lost(&mut self) {
+        if let Some(mut websocket) = self.websocket.take() {
+            websocket.close().await;
+        }
+        self.heartbeat = None;
+        self.next_attempt = None;
+
+        warn!("[connection_lost] Connection lost: {:?}", self.last_disconnect_reason);
+        self.update_state(ConnectionState {
+            state_type: ConnectionStateType::WasConnectedTryingToReconnect,
+            is_connected: false,
+            is_connecting: false,
+            is_reconnecting: true,
+            reconnect_attempts: 0,
+            current_retry_interval: 0,
+            next_retry_time: None,
+            last_heartbeat: 0,
+        });
+    }
+
+    /// Reopen the session after a drop, trying up to `reconnect_attempts` times. The first try is
+    /// immediate; each later one waits `reconnect_delay` seconds, doubled per failed try and
+    /// capped at `RETRY_INTERVALS.max`, then shortened by up to `reconnect_jitter`. Connect and
+    /// Register are re-sent, the active room is re-joined, then queued messages are sent.
+    pub async fn reconnect(&mut self) -> Result<(), SignalManagerError> {
+        while !self.attempt_reconnect(None).await? {}
+        Ok(())
+    }
+
+    // Wait for the next reconnect attempt, but not past `until`, then make it. True once
+    // reconnected; false while waiting or after a failed attempt with more to come.
+    async fn attempt_reconnect(&mut self, until: Option<tokio::time::Instant>) -> Result<bool, SignalManagerError> {
+        let (attempt, due) = match self.next_attempt {
+            Some(next) => next,
+            None if self.config.reconnect_attempts == 0 => {
+                self.update_state(ConnectionState::default());
+                return Err(SignalManagerError::NotConnected);
+            }
+            None => self.schedule_attempt(1),
+        };
+        if let Some(until) = until.filter(|until| due > *until) {
+            tokio::time::sleep_until(until).await;
+            return Ok(false);
+        }
+        tokio::time::sleep_until(due).await;
+
+        match self.open_session().await {
+            Ok(()) => {
+                info!("[reconnect] Reconnected after {} attempt(s)", attempt);
+                Ok(true)
+            }
+            Err(e) if attempt >= self.config.reconnect_attempts => {
+                warn!("[reconnect] Attempt {} failed: {}", attempt, e);
+                error!("[reconnect] Giving up after {} attempts", self.config.reconnect_attempts);
+                self.next_attempt = None;
+                self.update_state(ConnectionState::default());
+                Err(e)
+            }
+            Err(e) => {
+                warn!("[reconnect] Attempt {} failed: {}", attempt, e);
+                self.schedule_attempt(attempt + 1);
+                Ok(false)
+            }
+        }
+    }
+
+    // Report reconnect `attempt` and when it will be made
+    fn schedule_attempt(&mut self, attempt: u32) -> (u32, tokio::time::Instant) {
+        let retry_interval = self.retry_interval(attempt);
+        info!("[reconnect] Attempt {}/{} in {}ms", attempt, self.config.reconnect_attempts, retry_interval);
+        self.update_state(ConnectionState {
+            state_type: ConnectionStateType::WasConnectedTryingToReconnect,
+            is_connected: false,
+            is_connecting: false,
+            is_reconnecting: true,
+            reconnect_attempts: attempt,
+            current_retry_interval: retry_interval,
+            next_retry_time: Some(chrono::Utc::now().timestamp_millis() as u64 + retry_interval),
+            last_heartbeat: 0,
+        });
+        let next = (attempt, tokio::time::Instant::now() + Duration::from_millis(retry_interval));
+        self.next_attempt = Some(next);
+        next
+    }
+
+    /// Send `message` now, or queue it until the next connect or reconnect if there is no socket.
+    /// The queue holds at most `max_pending_messages`, dropping the oldest to make room.
+    pub fn send(&mut self, message: Message) -> Result<(), SignalManagerError> {
+        match &self.websocket {
+            Some(websocket) if self.state.is_connected => websocket.send(message),
+            _ => {
+                debug!("[send] Not connected, queueing {:?}", message.message_type);
+                if self.config.max_pending_messages == 0 {
+                    warn!("[send] Offline queue disabled, dropping {:?}", message.message_type);
+                    return Ok(());
+                }
+                while self.pending.len() >= self.config.max_pending_messages {
+                    if let Some(dropped) = self.pending.pop_front() {
+                        warn!("[send] Offline queue full, dropping queued {:?}", dropped.message_type);
+                    }
+                }
+                self.pending.push_back(message);
+                Ok(())
+            }
+        }
+    }
+
+    /// Seconds between heartbeats: the configured value until a ConnectAck says otherwise
+    pub fn heartbeat_interval(&self) -> u64 {
+        self.config.heartbeat_interval
+    }
+
+    /// Room re-joined after each reconnect: the last one created, until a disconnect or reset
+    pub fn active_room(&self) -> Option<&ActiveRoom> {
+        self.active_room.as_ref()
+    }
+
+    /// Reason given by the server in its last Disconnect, if any
+    pub fn last_disconnect_reason(&self) -> Option<&str> {
+        self.last_disconnect_reason.as_deref()
+    }
+
     pub async fn disconnect(&mut self) -> Result<(), SignalManagerError> {
         info!("[disconnect] Disconnecting from SignalManager");
         
@@ -138,6 +324,9 @@ impl SignalManagerClient {
         if let Some(mut websocket) = self.websocket.take() {
             websocket.close().await;
         }
+        self.active_room = None;
+        self.heartbeat = None;
+        self.next_attempt = None;
 
         // Update state to disconnected
         self.update_state(ConnectionState {
@@ -164,6 +353,7 @@ impl SignalManagerClient {
             self.last_room_response = None;
             
             // Send message
+            let (role, offer_sdp) = (payload.role.clone(), payload.offer_sdp.clone());
             let message = Message::room_create(payload);
             websocket.send(message)?;
             
@@ -178,6 +368,9 @@ impl SignalManagerClient {
                         // Check if we got our response
                         if let Some(response) = &self.last_room_response {
                             info!("[send_room_create] Received room creation response: {:?}", response);
+                            if let Some(room_id) = &response.0 {
+                                self.active_room = Some(ActiveRoom { room_id: room_id.clone(), role, offer_sdp });
+                            }
                             return Ok(response.clone());
                         }
                     }
@@ -195,6 +388,60 @@ impl SignalManagerClient {
         }
     }
 
+    pub async fn list_rooms(&mut self) -> Result<Vec<RoomSummary>, SignalManagerError> {
+        info!("[list_rooms] Requesting active room list");
+        
+        // The server answers in pages; keep asking until it reports the last one
+        let mut rooms = Vec::new();
+        let mut offset = Some(0);
+        while let Some(page_offset) = offset {
+            let page = self.list_rooms_page(page_offset).await?;
+            rooms.extend(page.rooms);
+            // Guard against a next_offset that would never reach the end
+            offset = page.next_offset.filter(|next| *next > page_offset);
+        }
+        
+        info!("[list_rooms] Received {} active rooms", rooms.len());
+        Ok(rooms)
+    }
+
+    async fn list_rooms_page(&mut self, offset: u32) -> Result<WebRTCRoomListAckPayload, SignalManagerError> {
+        if let Some(websocket) = &self.websocket {
+            // Clear previous response
+            self.last_room_list = None;
+            self.last_error = None;
+            
+            websocket.send(Message::room_list_page(offset))?;
+            
+            // Wait for response with timeout
+            let start_time = std::time::Instant::now();
+            while start_time.elapsed() < COMMAND_TIMEOUT {
+                if let Some(websocket) = &mut self.websocket {
+                    if let Ok(Some(msg)) = websocket.receive().await {
+                        self.handle_message(msg).await?;
+                        
+                        if let Some(page) = self.last_room_list.take() {
+                            debug!("[list_rooms] Received {} of {} rooms at offset {}", page.rooms.len(), page.total, offset);
+                            return Ok(page);
+                        }
+                        if let Some(refusal) = self.last_error.take() {
+                            warn!("[list_rooms] Server refused the room list: {} - {}", refusal.error_code, refusal.error_message);
+                            return Err(SignalManagerError::Server(refusal.error_code, refusal.error_message));
+                        }
+                    }
+                }
+                
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+            
+            warn!("[list_rooms] Timeout waiting for room list response");
+            Err(SignalManagerError::Timeout("Room list timeout".to_string()))
+        } else {
+            error!("[list_rooms] Not connected to WebSocket");
+            Err(SignalManagerError::NotConnected)
+        }
+    }
+
     pub fn get_state(&self) -> ConnectionState {
         self.state.clone()
     }
@@ -213,12 +460,84 @@ impl SignalManagerClient {
         self.state = ConnectionState::default();
         // Don't clear state_callback to preserve event emission
         self.last_room_response = None;
+        self.last_room_list = None;
+        self.pending.clear();
+        self.last_disconnect_reason = None;
+        self.active_room = None;
+        self.heartbeat = None;
+        self.next_attempt = None;
         
         info!("[reset] SignalManagerClient reset completed");
         Ok(())
     }
 
     // Private methods
+
+    // Open a socket, mark the client connected, then send Connect, Register and anything queued
+    async fn open_session(&mut self) -> Result<(), SignalManagerError> {
+        let url = self.config.websocket_url();
+        info!("[open_session] Connecting to WebSocket at {}", url);
+
+        let mut websocket = WebSocketClient::new(url);
+        websocket.connect().await?;
+        info!("[open_session] WebSocket connection established");
+        self.websocket = Some(websocket);
+        self.next_attempt = None;
+
+        self.update_state(ConnectionState {
+            state_type: ConnectionStateType::Connected,
+            is_connected: true,
+            is_connecting: false,
+            is_reconnecting: false,
+            reconnect_attempts: 0,
+            current_retry_interval: 0,
+            next_retry_time: None,
+            last_heartbeat: 0,
+        });
+
+        self.send_connect().await?;
+        self.send_register().await?;
+        self.send_room_rejoin()?;
+        self.flush_pending()
+    }
+
+    fn flush_pending(&mut self) -> Result<(), SignalManagerError> {
+        if let Some(websocket) = &self.websocket {
+            if !self.pending.is_empty() {
+                info!("[flush_pending] Sending {} queued message(s)", self.pending.len());
+            }
+            while let Some(message) = self.pending.pop_front() {
+                websocket.send(message)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn heartbeat_period(&self) -> Duration {
+        Duration::from_secs(self.config.heartbeat_interval.max(1))
+    }
+
+    // Ticks one period from now, so the first heartbeat is not sent straight after Connect
+    fn heartbeat_timer(&self) -> tokio::time::Interval {
+        let period = self.heartbeat_period();
+        tokio::time::interval_at(tokio::time::Instant::now() + period, period)
+    }
+
+    // Milliseconds to wait before reconnect attempt `attempt` (1-based)
+    fn retry_interval(&self, attempt: u32) -> u64 {
+        if attempt <= 1 {
+            return RETRY_INTERVALS.immediate;
+        }
+        let doublings = (attempt - 2).min(16);
+        let interval = self.config.reconnect_delay
+            .saturating_mul(1000)
+            .saturating_mul(1 << doublings)
+            .min(RETRY_INTERVALS.max);
+        // A random fraction in [0, 1) from the low 53 bits of a v4 UUID, which are all random
+        let random = (uuid::Uuid::new_v4().as_u128() as u64 & ((1 << 53) - 1)) as f64 / (1u64 << 53) as f64;
+        interval - (interval as f64 * self.config.reconnect_jitter.clamp(0.0, 1.0) * random) as u64
+    }
+
     async fn send_connect(&self) -> Result<(), SignalManagerError> {
         if let Some(websocket) = &self.websocket {
             let message = Message::connect(
@@ -243,6 +562,23 @@ impl SignalManagerClient {
         Ok(())
     }
 
+    fn send_room_rejoin(&self) -> Result<(), SignalManagerError> {
+        if let (Some(websocket), Some(room)) = (&self.websocket, &self.active_room) {
+            let message = Message::room_join(WebRTCRoomJoinPayload {
+                version: "1.0.0".to_string(),
+                client_id: self.config.client_id.clone(),
+                auth_token: self.config.auth_token.clone(),
+                room_id: room.room_id.clone(),
+                role: room.role.clone(),
+                offer_sdp: room.offer_sdp.clone(),
+                metadata: None,
+            });
+            websocket.send(message)?;
+            info!("[send_room_rejoin] Re-joining room {} as {}", room.room_id, room.role);
+        }
+        Ok(())
+    }
+
     async fn send_unregister(&self) -> Result<(), SignalManagerError> {
         if let Some(websocket) = &self.websocket {
             let message = Message::unregister(self.config.client_id.clone());
@@ -258,6 +594,12 @@ impl SignalManagerClient {
         match &message.payload {
             Payload::ConnectAck(ack) => {
                 info!("[handle_message] Received ConnectAck: {}", ack.status);
+                if let Some(interval) = ack.heartbeat_interval.filter(|secs| *secs > 0) {
+                    if interval != self.config.heartbeat_interval {
+                        info!("[handle_message] Server expects a heartbeat every {}s (was {}s)", interval, self.config.heartbeat_interval);
+                        self.config.heartbeat_interval = interval;
+                    }
+                }
             }
             Payload::RegisterAck(ack) => {
                 info!("[handle_message] Received RegisterAck: {}", ack.status);
@@ -265,14 +607,31 @@ impl SignalManagerClient {
             Payload::UnregisterAck(ack) => {
                 info!("[handle_message] Received UnregisterAck: {}", ack.status);
             }
+            Payload::Disconnect(disconnect) => {
+                warn!("[handle_message] Server is disconnecting us: {}", disconnect.reason);
+                self.last_disconnect_reason = Some(disconnect.reason.clone());
+            }
             Payload::WebRTCRoomCreateAck(ack) => {
                 info!("[handle_message] Received RoomCreateAck: room_id={:?}, session_id={:?}", 
                     ack.room_id, ack.session_id);
                 self.last_room_response = Some((ack.room_id.clone(), ack.session_id.clone()));
             }
+            Payload::WebRTCRoomJoinAck(ack) if ack.status == 200 => {
+                info!("[handle_message] Received RoomJoinAck: room_id={:?}", ack.room_id);
+            }
+            Payload::WebRTCRoomJoinAck(ack) => {
+                // The room is gone or refused us; stop re-joining it on every reconnect
+                warn!("[handle_message] Room join failed: {} {:?}", ack.status, ack.message);
+                self.active_room = None;
+            }
+            Payload::WebRTCRoomListAck(ack) => {
+                info!("[handle_message] Received RoomListAck: {} rooms", ack.rooms.len());
+                self.last_room_list = Some(ack.clone());
+            }
             Payload::Error(error_payload) => {
                 error!("[handle_message] Received Error: {} - {}", 
                     error_payload.error_code, error_payload.error_message);
+                self.last_error = Some(error_payload.clone());
             }
             _ => {
                 debug!("[handle_message] Unhandled message type: {:?}", message.message_type);
@@ -12,6 +12,21 @@ pub struct SignalManagerConfig {
     pub command_timeout: u64,
     pub reconnect_attempts: u32,
     pub reconnect_delay: u64,
+    // Fraction, 0.0 to 1.0, by which each delayed reconnect attempt is randomly shortened so
+    // clients dropped together don't all retry at the same moment
+    #[serde(default = "default_reconnect_jitter")]
+    pub reconnect_jitter: f64,
+    // Most messages held while offline; once full, the oldest is dropped for each new one
+    #[serde(default = "default_max_pending_messages")]
+    pub max_pending_messages: usize,
+}
+
+fn default_reconnect_jitter() -> f64 {
+    0.2
+}
+
+fn default_max_pending_messages() -> usize {
+    100
 }
 
 impl Default for SignalManagerConfig {
@@ -27,6 +42,8 @@ impl Default for SignalManagerConfig {
             command_timeout: 15,
             reconnect_attempts: 5,
             reconnect_delay: 1,
+            reconnect_jitter: default_reconnect_jitter(),
+            max_pending_messages: default_max_pending_messages(),
         }
     }
 }
@@ -49,6 +66,8 @@ impl SignalManagerConfig {
             command_timeout: 15,
             reconnect_attempts: 5,
             reconnect_delay: 1,
+            reconnect_jitter: default_reconnect_jitter(),
+            max_pending_messages: default_max_pending_messages(),
         }
     }
 
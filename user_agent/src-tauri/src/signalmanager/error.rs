@@ -43,8 +43,14 @@ pub enum SignalManagerError {
     
     #[error("Invalid configuration: {0}")]
     InvalidConfig(String),
+    
+    #[error("Server refused the request ({0}): {1}")]
+    Server(u8, String),
 }
 
+// Error code the server answers with when the client may not send a request
+pub const ERROR_CODE_FORBIDDEN: u8 = 8;
+
 impl From<tokio_tungstenite::tungstenite::Error> for SignalManagerError {
     fn from(err: tokio_tungstenite::tungstenite::Error) -> Self {
         SignalManagerError::WebSocketConnection(err.to_string())
@@ -72,7 +72,11 @@ impl Default for ConnectionState {
 }
 
 // Protocol constants
-pub const START_BYTE: u8 = 0xAA;
+pub const START_BYTE: u8 = 0xAC;
+// Set in the payload-type byte when the server zstd-compressed the payload
+pub const PAYLOAD_TYPE_ZSTD_FLAG: u8 = 0x80;
+// Compressed payloads inflating past this are rejected, as the server does by default
+pub const MAX_DECOMPRESSED_PAYLOAD: usize = 1 << 20;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
@@ -95,6 +99,8 @@ pub enum MessageType {
     WebRTCRoomJoinAck = 0x33,
     WebRTCRoomLeave = 0x34,
     WebRTCRoomLeaveAck = 0x35,
+    WebRTCRoomList = 0x36,
+    WebRTCRoomListAck = 0x37,
     Error = 0xFF,
 }
 
@@ -119,6 +125,11 @@ pub struct ConnectPayload {
 pub struct ConnectAckPayload {
     pub status: String,
     pub session_id: String,
+    // Sent by servers that advertise their keepalive settings, in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heartbeat_interval: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_timeout: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -204,6 +215,67 @@ pub struct WebRTCRoomCreateAckPayload {
     pub connection_info: Option<serde_json::Value>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebRTCRoomJoinPayload {
+    pub version: String,
+    pub client_id: String,
+    pub auth_token: String,
+    pub room_id: String,
+    pub role: String,
+    pub offer_sdp: Option<String>,
+    pub metadata: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebRTCRoomJoinAckPayload {
+    pub version: String,
+    pub status: u16,
+    pub message: Option<String>,
+    pub room_id: Option<String>,
+    pub session_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebRTCRoomListPayload {
+    // Active rooms to skip, oldest first
+    #[serde(default)]
+    pub offset: u32,
+    // Rooms to return; 0 means the server default page size
+    #[serde(default)]
+    pub limit: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebRTCRoomListAckPayload {
+    pub rooms: Vec<RoomSummary>,
+    // Active rooms across all pages
+    #[serde(default)]
+    pub total: u32,
+    // Offset of the following page; absent on the last page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_offset: Option<u32>,
+}
+
+// Room this client created or joined, re-joined with the same role after a reconnect
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActiveRoom {
+    pub room_id: String,
+    pub role: String,
+    pub offer_sdp: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomSummary {
+    pub room_id: String,
+    #[serde(default)]
+    pub status: String, // "active", "inactive", "terminated" or "pending"
+    pub sender_client_id: Option<String>,
+    pub receiver_client_id: Option<String>,
+    #[serde(default)]
+    pub participant_count: u32,
+    pub created_at: u64, // milliseconds since the Unix epoch
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Payload {
     Connect(ConnectPayload),
@@ -220,6 +292,10 @@ pub enum Payload {
     UnregisterAck(UnregisterAckPayload),
     WebRTCRoomCreate(WebRTCRoomCreatePayload),
     WebRTCRoomCreateAck(WebRTCRoomCreateAckPayload),
+    WebRTCRoomJoin(WebRTCRoomJoinPayload),
+    WebRTCRoomJoinAck(WebRTCRoomJoinAckPayload),
+    WebRTCRoomList(WebRTCRoomListPayload),
+    WebRTCRoomListAck(WebRTCRoomListAckPayload),
     Error(ErrorPayload),
 }
 
@@ -289,6 +365,19 @@ impl Message {
         Self::new(MessageType::WebRTCRoomCreate, Payload::WebRTCRoomCreate(payload))
     }
 
+    pub fn room_join(payload: WebRTCRoomJoinPayload) -> Self {
+        Self::new(MessageType::WebRTCRoomJoin, Payload::WebRTCRoomJoin(payload))
+    }
+
+    pub fn room_list() -> Self {
+        Self::room_list_page(0)
+    }
+
+    // Page of the room list starting at `offset`, in the server's default page size
+    pub fn room_list_page(offset: u32) -> Self {
+        Self::new(MessageType::WebRTCRoomList, Payload::WebRTCRoomList(WebRTCRoomListPayload { offset, limit: 0 }))
+    }
+
     // Binary serialization for server compatibility
     pub fn to_binary(&self) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
         let mut buffer = Vec::new();
@@ -308,8 +397,8 @@ impl Message {
         // Serialize payload as JSON
         let payload_bytes = serde_json::to_vec(&self.payload)?;
         
-        // Payload length (2 bytes, big endian)
-        let length = payload_bytes.len() as u16;
+        // Payload length (4 bytes, big endian)
+        let length = payload_bytes.len() as u32;
         buffer.extend_from_slice(&length.to_be_bytes());
         
         // Payload
@@ -319,7 +408,7 @@ impl Message {
     }
 
     pub fn from_binary(data: &[u8]) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
-        if data.len() < 22 {
+        if data.len() < 24 {
             return Err("Message too short".into());
         }
 
@@ -346,12 +435,16 @@ impl Message {
             0x33 => MessageType::WebRTCRoomJoinAck,
             0x34 => MessageType::WebRTCRoomLeave,
             0x35 => MessageType::WebRTCRoomLeaveAck,
+            0x36 => MessageType::WebRTCRoomList,
+            0x37 => MessageType::WebRTCRoomListAck,
             0xFF => MessageType::Error,
             _ => return Err("Unknown message type".into()),
         };
 
         let uuid = Uuid::from_slice(&data[2..18])?;
-        let payload_type = match data[18] {
+        // The high bit marks a zstd-compressed payload
+        let compressed = data[18] & PAYLOAD_TYPE_ZSTD_FLAG != 0;
+        let payload_type = match data[18] & !PAYLOAD_TYPE_ZSTD_FLAG {
             0x01 => PayloadType::Binary,
             0x02 => PayloadType::Json,
             0x03 => PayloadType::Text,
@@ -360,15 +453,19 @@ impl Message {
             _ => return Err("Unknown payload type".into()),
         };
         
-        let length_bytes = [data[19], data[20]];
-        let payload_length = u16::from_be_bytes(length_bytes) as usize;
+        let length_bytes = [data[19], data[20], data[21], data[22]];
+        let payload_length = u32::from_be_bytes(length_bytes) as usize;
         
-        if data.len() < 21 + payload_length {
+        if data.len() < 23 + payload_length {
             return Err("Message length mismatch".into());
         }
 
-        let payload_data = &data[21..21 + payload_length];
-        let payload = serde_json::from_slice(payload_data)?;
+        let payload_data = &data[23..23 + payload_length];
+        let payload = if compressed {
+            serde_json::from_slice(&decompress(payload_data)?)?
+        } else {
+            serde_json::from_slice(payload_data)?
+        };
 
         Ok(Self {
             message_type,
@@ -379,6 +476,19 @@ impl Message {
     }
 }
 
+// zstd-decompress `data`, refusing to inflate past MAX_DECOMPRESSED_PAYLOAD
+fn decompress(data: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    use std::io::Read;
+    let mut decompressed = Vec::new();
+    zstd::stream::read::Decoder::new(data)?
+        .take(MAX_DECOMPRESSED_PAYLOAD as u64 + 1)
+        .read_to_end(&mut decompressed)?;
+    if decompressed.len() > MAX_DECOMPRESSED_PAYLOAD {
+        return Err(format!("Compressed payload inflates past {} bytes", MAX_DECOMPRESSED_PAYLOAD).into());
+    }
+    Ok(decompressed)
+}
+
 impl MessageType {
     pub fn from_u8(value: u8) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
         match value {
@@ -400,6 +510,8 @@ impl MessageType {
             0x33 => Ok(MessageType::WebRTCRoomJoinAck),
             0x34 => Ok(MessageType::WebRTCRoomLeave),
             0x35 => Ok(MessageType::WebRTCRoomLeaveAck),
+            0x36 => Ok(MessageType::WebRTCRoomList),
+            0x37 => Ok(MessageType::WebRTCRoomListAck),
             0xFF => Ok(MessageType::Error),
             _ => Err("Unknown message type".into()),
         }
@@ -0,0 +1,73 @@
+use tauri_app_lib::error::CommandError;
+use tauri_app_lib::signalmanager::SignalManagerError;
+use tauri_app_lib::webrtcclient::WebRTCError;
+
+#[test]
+fn test_signal_manager_errors_map_to_command_errors() {
+    assert_eq!(
+        CommandError::from(SignalManagerError::NotConnected),
+        CommandError::NotConnected("Not connected".to_string())
+    );
+    assert_eq!(
+        CommandError::from(SignalManagerError::Timeout("Room list timeout".to_string())),
+        CommandError::Timeout("Connection timeout: Room list timeout".to_string())
+    );
+    assert_eq!(
+        CommandError::from(SignalManagerError::InvalidConfig("bad port".to_string())),
+        CommandError::InvalidInput("Invalid configuration: bad port".to_string())
+    );
+    assert!(matches!(
+        CommandError::from(SignalManagerError::WebSocketSend("closed".to_string())),
+        CommandError::Signaling(_)
+    ));
+    assert!(matches!(
+        CommandError::from(SignalManagerError::Deserialization("bad json".to_string())),
+        CommandError::Signaling(_)
+    ));
+    assert!(matches!(
+        CommandError::from(SignalManagerError::RoomCreation("rejected".to_string())),
+        CommandError::Signaling(_)
+    ));
+    assert_eq!(
+        CommandError::from(SignalManagerError::Server(8, "admin only".to_string())),
+        CommandError::Forbidden("Server refused the request (8): admin only".to_string())
+    );
+    assert!(matches!(
+        CommandError::from(SignalManagerError::Server(11, "bad payload".to_string())),
+        CommandError::Signaling(_)
+    ));
+}
+
+#[test]
+fn test_webrtc_errors_map_to_command_errors() {
+    assert_eq!(
+        CommandError::from(WebRTCError::OfferCreation("no codecs".to_string())),
+        CommandError::WebRtc("Failed to create offer: no codecs".to_string())
+    );
+    assert!(matches!(
+        CommandError::from(WebRTCError::PeerConnection("closed".to_string())),
+        CommandError::WebRtc(_)
+    ));
+    assert_eq!(
+        CommandError::from(WebRTCError::InvalidConfig("missing app id".to_string())),
+        CommandError::InvalidInput("Invalid configuration: missing app id".to_string())
+    );
+}
+
+#[test]
+fn test_io_and_tauri_errors_map_to_their_own_kinds() {
+    assert_eq!(
+        CommandError::from(std::io::Error::new(std::io::ErrorKind::PermissionDenied, "read-only")),
+        CommandError::Io("read-only".to_string())
+    );
+    assert!(matches!(
+        CommandError::from(tauri::Error::UnknownPath),
+        CommandError::Tauri(_)
+    ));
+}
+
+#[test]
+fn test_command_error_serializes_kind_and_message() {
+    let json = serde_json::to_value(CommandError::Timeout("Room list timeout".to_string())).unwrap();
+    assert_eq!(json, serde_json::json!({ "kind": "Timeout", "message": "Room list timeout" }));
+}
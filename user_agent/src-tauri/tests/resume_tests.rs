@@ -0,0 +1,60 @@
+use tauri_app_lib::resume::{
+    clear_resume_state, load_resume_state, resume_state_path, save_resume_state, ResumeState,
+};
+
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("tauri_app_{}_{}", name, uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn test_resume_state_round_trip() {
+    let dir = temp_dir("resume");
+    assert_eq!(load_resume_state(&dir).unwrap(), None);
+
+    let state = ResumeState {
+        client_id: "test_client".to_string(),
+        session_id: Some("session_1".to_string()),
+        reconnect_token: "test_token".to_string(),
+        room_id: "room_1".to_string(),
+        role: "sender".to_string(),
+    };
+    save_resume_state(&dir, &state).unwrap();
+    assert_eq!(load_resume_state(&dir).unwrap(), Some(state));
+
+    clear_resume_state(&dir).unwrap();
+    assert!(!resume_state_path(&dir).exists());
+    assert_eq!(load_resume_state(&dir).unwrap(), None);
+    // Clearing twice is fine
+    clear_resume_state(&dir).unwrap();
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_resume_state_is_readable_only_by_its_owner() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = temp_dir("resume_mode");
+    let state = ResumeState {
+        client_id: "test_client".to_string(),
+        session_id: None,
+        reconnect_token: "test_token".to_string(),
+        room_id: "room_1".to_string(),
+        role: "sender".to_string(),
+    };
+    save_resume_state(&dir, &state).unwrap();
+    let mode = std::fs::metadata(resume_state_path(&dir)).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_corrupt_resume_state_is_ignored() {
+    let dir = temp_dir("resume_corrupt");
+    std::fs::write(resume_state_path(&dir), b"{not json").unwrap();
+    assert_eq!(load_resume_state(&dir).unwrap(), None);
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,244 @@
+mod support;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use support::MockSignalManager;
+use tauri_app_lib::signalmanager::{
+    ConnectionState, ConnectionStateType, Message, MessageType, Payload, SignalManagerClient, SignalManagerConfig,
+    WebRTCRoomCreateAckPayload, WebRTCRoomCreatePayload,
+};
+
+fn client_for(mock: &MockSignalManager, reconnect_attempts: u32, reconnect_delay: u64) -> SignalManagerClient {
+    let mut config = SignalManagerConfig::new("127.0.0.1".to_string(), mock.port, "test_client".to_string(), "test_token".to_string());
+    config.reconnect_attempts = reconnect_attempts;
+    config.reconnect_delay = reconnect_delay;
+    // Exact delays, so tests can check the backoff
+    config.reconnect_jitter = 0.0;
+    SignalManagerClient::new(config)
+}
+
+// Record every state the client reports
+fn record_states(client: &mut SignalManagerClient) -> Arc<Mutex<Vec<ConnectionState>>> {
+    let states = Arc::new(Mutex::new(Vec::new()));
+    let recorded = states.clone();
+    client.set_state_callback(Box::new(move |state| recorded.lock().unwrap().push(state)));
+    states
+}
+
+async fn expect_types(mock: &mut MockSignalManager, connection: usize, expected: &[MessageType]) {
+    for message_type in expected {
+        let (on, message) = mock.next_message().await;
+        assert_eq!((on, message.message_type), (connection, *message_type));
+    }
+}
+
+#[tokio::test]
+async fn test_connect_drop_reconnect_resume_cycle() {
+    let mut mock = MockSignalManager::start().await;
+    let mut client = client_for(&mock, 5, 1);
+    let states = record_states(&mut client);
+
+    // Sent before the first connect: held until Connect and Register are out
+    client.send(Message::heartbeat()).unwrap();
+    client.connect().await.unwrap();
+    expect_types(&mut mock, 1, &[MessageType::Connect, MessageType::Register, MessageType::Heartbeat]).await;
+
+    // The server drops us with a reason, which the client reports
+    mock.drop_connection(Some("Server shutting down"));
+    assert_eq!(client.run_until_disconnected().await.as_deref(), Some("Server shutting down"));
+    assert_eq!(client.last_disconnect_reason(), Some("Server shutting down"));
+    assert_eq!(client.get_state().state_type, ConnectionStateType::WasConnectedTryingToReconnect);
+
+    // Sent while offline: queued for the resumed session
+    client.send(Message::room_list()).unwrap();
+
+    // The first two reconnect attempts are refused, so the third goes through after backing off
+    mock.refuse_next(2);
+    let attempts_before = mock.connection_attempts();
+    states.lock().unwrap().clear();
+    client.reconnect().await.unwrap();
+    assert_eq!(mock.connection_attempts() - attempts_before, 3);
+
+    let retries: Vec<(u32, u64)> = states.lock().unwrap().iter()
+        .filter(|state| state.state_type == ConnectionStateType::WasConnectedTryingToReconnect)
+        .map(|state| (state.reconnect_attempts, state.current_retry_interval))
+        .collect();
+    assert_eq!(retries, vec![(1, 0), (2, 1000), (3, 2000)]);
+    assert!(client.get_state().is_connected);
+
+    // The session resumes on the new socket before the queued message goes out
+    expect_types(&mut mock, 2, &[MessageType::Connect, MessageType::Register, MessageType::WebRTCRoomList]).await;
+}
+
+#[tokio::test]
+async fn test_drop_without_disconnect_has_no_reason() {
+    let mut mock = MockSignalManager::start().await;
+    let mut client = client_for(&mock, 1, 0);
+
+    client.connect().await.unwrap();
+    expect_types(&mut mock, 1, &[MessageType::Connect, MessageType::Register]).await;
+
+    mock.drop_connection(None);
+    assert_eq!(client.run_until_disconnected().await, None);
+    assert_eq!(client.last_disconnect_reason(), None);
+}
+
+#[tokio::test]
+async fn test_reconnect_gives_up_after_configured_attempts() {
+    let mut mock = MockSignalManager::start().await;
+    let mut client = client_for(&mock, 3, 0);
+
+    client.connect().await.unwrap();
+    expect_types(&mut mock, 1, &[MessageType::Connect, MessageType::Register]).await;
+    mock.drop_connection(Some("Session timed out"));
+    client.run_until_disconnected().await;
+
+    mock.refuse_next(10);
+    let attempts_before = mock.connection_attempts();
+    assert!(client.reconnect().await.is_err());
+    assert_eq!(mock.connection_attempts() - attempts_before, 3);
+    assert_eq!(client.get_state(), ConnectionState::default());
+
+    // Nothing sent while giving up is lost; it goes out once a connect succeeds
+    client.send(Message::heartbeat()).unwrap();
+    mock.refuse_next(0);
+    client.connect().await.unwrap();
+    expect_types(&mut mock, 2, &[MessageType::Connect, MessageType::Register, MessageType::Heartbeat]).await;
+}
+
+#[tokio::test]
+async fn test_offline_queue_drops_oldest_when_full() {
+    let mut mock = MockSignalManager::start().await;
+    let mut config = SignalManagerConfig::new("127.0.0.1".to_string(), mock.port, "test_client".to_string(), "test_token".to_string());
+    config.max_pending_messages = 2;
+    let mut client = SignalManagerClient::new(config);
+
+    // The heartbeat is the oldest of three, so it is dropped
+    client.send(Message::heartbeat()).unwrap();
+    client.send(Message::room_list()).unwrap();
+    client.send(Message::room_list()).unwrap();
+    client.connect().await.unwrap();
+    expect_types(&mut mock, 1, &[MessageType::Connect, MessageType::Register, MessageType::WebRTCRoomList, MessageType::WebRTCRoomList]).await;
+
+    // Sent live, it is the next frame: nothing else was left queued
+    client.send(Message::heartbeat()).unwrap();
+    expect_types(&mut mock, 1, &[MessageType::Heartbeat]).await;
+}
+
+#[tokio::test]
+async fn test_reconnect_delays_are_jittered() {
+    let mock = MockSignalManager::start().await;
+    let mut config = SignalManagerConfig::new("127.0.0.1".to_string(), mock.port, "test_client".to_string(), "test_token".to_string());
+    config.reconnect_attempts = 3;
+    config.reconnect_delay = 1;
+    config.reconnect_jitter = 0.5;
+    let mut client = SignalManagerClient::new(config);
+    let states = record_states(&mut client);
+
+    mock.refuse_next(10);
+    assert!(client.reconnect().await.is_err());
+
+    let retries: Vec<u64> = states.lock().unwrap().iter()
+        .filter(|state| state.state_type == ConnectionStateType::WasConnectedTryingToReconnect)
+        .map(|state| state.current_retry_interval)
+        .collect();
+    assert_eq!(retries.len(), 3);
+    assert_eq!(retries[0], 0);
+    assert!((500..=1000).contains(&retries[1]), "Second delay out of range: {}", retries[1]);
+    assert!((1000..=2000).contains(&retries[2]), "Third delay out of range: {}", retries[2]);
+}
+
+#[tokio::test]
+async fn test_maintain_releases_client_while_waiting_to_retry() {
+    let mut mock = MockSignalManager::start().await;
+    let mut client = client_for(&mock, 3, 30);
+
+    client.connect().await.unwrap();
+    expect_types(&mut mock, 1, &[MessageType::Connect, MessageType::Register]).await;
+
+    // The immediate first attempt is refused, leaving a 30s wait before the second
+    mock.refuse_next(10);
+    mock.drop_connection(None);
+    while mock.connection_attempts() < 2 {
+        assert!(client.maintain(Duration::from_millis(50)).await.unwrap());
+    }
+    assert_eq!(client.get_state().reconnect_attempts, 2);
+
+    // Each call returns once its window is over instead of sleeping through the backoff
+    let started = tokio::time::Instant::now();
+    assert!(client.maintain(Duration::from_millis(50)).await.unwrap());
+    assert!(started.elapsed() < Duration::from_secs(1), "maintain held the client for {:?}", started.elapsed());
+    assert_eq!(mock.connection_attempts(), 2);
+
+    // Disconnecting in the meantime cancels the pending attempt
+    client.disconnect().await.unwrap();
+    assert!(!client.maintain(Duration::from_millis(50)).await.unwrap());
+    assert_eq!(mock.connection_attempts(), 2);
+}
+
+#[tokio::test]
+async fn test_maintain_reconnects_and_rejoins_active_room() {
+    let mut mock = MockSignalManager::start().await;
+    let mut client = client_for(&mock, 5, 0);
+    let states = record_states(&mut client);
+
+    client.connect().await.unwrap();
+    expect_types(&mut mock, 1, &[MessageType::Connect, MessageType::Register]).await;
+
+    // Create a room, which the client remembers once the server acknowledges it
+    let create = client.send_room_create(WebRTCRoomCreatePayload {
+        version: "1.0.0".to_string(),
+        client_id: "test_client".to_string(),
+        auth_token: "test_token".to_string(),
+        role: "sender".to_string(),
+        offer_sdp: Some("v=0".to_string()),
+        metadata: None,
+    });
+    let acknowledge = async {
+        expect_types(&mut mock, 1, &[MessageType::WebRTCRoomCreate]).await;
+        mock.reply(Message::new(MessageType::WebRTCRoomCreateAck, Payload::WebRTCRoomCreateAck(WebRTCRoomCreateAckPayload {
+            version: "1.0.0".to_string(),
+            status: 200,
+            message: None,
+            room_id: Some("room_1".to_string()),
+            session_id: Some("session_1".to_string()),
+            app_id: None,
+            stun_url: None,
+            connection_info: None,
+        })));
+    };
+    let (created, ()) = tokio::join!(create, acknowledge);
+    assert_eq!(created.unwrap(), (Some("room_1".to_string()), Some("session_1".to_string())));
+    assert_eq!(client.active_room().map(|room| room.room_id.as_str()), Some("room_1"));
+
+    // A drop while the connection is maintained is recovered without the app calling reconnect
+    states.lock().unwrap().clear();
+    mock.drop_connection(None);
+    let rejoined = async {
+        expect_types(&mut mock, 2, &[MessageType::Connect, MessageType::Register]).await;
+        match mock.next_message().await {
+            (2, Message { payload: Payload::WebRTCRoomJoin(join), .. }) => {
+                assert_eq!(join.room_id, "room_1");
+                assert_eq!(join.role, "sender");
+                assert_eq!(join.offer_sdp.as_deref(), Some("v=0"));
+            }
+            other => panic!("Expected WebRTCRoomJoin on the new connection, got {:?}", other),
+        }
+    };
+    tokio::select! {
+        () = rejoined => {}
+        _ = async { while client.maintain(Duration::from_millis(50)).await.unwrap() {} } => {
+            panic!("Client stopped maintaining the connection")
+        }
+    }
+
+    let transitions: Vec<ConnectionStateType> = states.lock().unwrap().iter().map(|state| state.state_type.clone()).collect();
+    assert_eq!(transitions.first(), Some(&ConnectionStateType::WasConnectedTryingToReconnect));
+    assert_eq!(transitions.last(), Some(&ConnectionStateType::Connected));
+
+    // Once disconnected on request there is nothing left to maintain
+    client.disconnect().await.unwrap();
+    assert!(!client.maintain(Duration::from_millis(50)).await.unwrap());
+    assert!(client.active_room().is_none());
+}
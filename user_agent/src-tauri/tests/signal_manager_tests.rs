@@ -0,0 +1,145 @@
+use std::sync::Arc;
+
+use futures_util::{SinkExt, StreamExt};
+use tauri_app_lib::commands;
+use tauri_app_lib::error::CommandError;
+use tauri_app_lib::signalmanager::{
+    ErrorPayload, Message, MessageType, Payload, RoomSummary, SignalManagerClient, SignalManagerConfig,
+    WebRTCRoomListAckPayload,
+};
+use tokio::net::TcpListener;
+use tokio::sync::Mutex as TokioMutex;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+// Rooms the mock returns per WebRTCRoomList, small enough that tests span several pages
+const MOCK_PAGE_SIZE: usize = 2;
+
+// Minimal signal manager that answers every WebRTCRoomList with the requested page of `rooms`
+async fn spawn_mock_signal_manager(rooms: Vec<RoomSummary>) -> u16 {
+    spawn_mock_signal_manager_with(move |request| {
+        let Payload::WebRTCRoomList(page) = &request.payload else { unreachable!() };
+        let start = (page.offset as usize).min(rooms.len());
+        let end = (start + MOCK_PAGE_SIZE).min(rooms.len());
+        Payload::WebRTCRoomListAck(WebRTCRoomListAckPayload {
+            rooms: rooms[start..end].to_vec(),
+            total: rooms.len() as u32,
+            next_offset: (end < rooms.len()).then_some(end as u32),
+        })
+    })
+    .await
+}
+
+// Minimal signal manager that answers every WebRTCRoomList with `answer(request)`
+async fn spawn_mock_signal_manager_with<F>(answer: F) -> u16
+where
+    F: Fn(&Message) -> Payload + Clone + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let answer = answer.clone();
+            tokio::spawn(async move {
+                let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                while let Some(Ok(WsMessage::Binary(data))) = ws.next().await {
+                    let request = Message::from_binary(&data).unwrap();
+                    if request.message_type == MessageType::WebRTCRoomList {
+                        let payload = answer(&request);
+                        let message_type = match payload {
+                            Payload::Error(_) => MessageType::Error,
+                            _ => MessageType::WebRTCRoomListAck,
+                        };
+                        let ack = Message::new(message_type, payload);
+                        ws.send(WsMessage::Binary(ack.to_binary().unwrap())).await.unwrap();
+                    }
+                }
+            });
+        }
+    });
+    port
+}
+
+#[tokio::test]
+async fn test_list_rooms_command() {
+    // Three rooms span two pages of the mock
+    let rooms: Vec<RoomSummary> = (1..=3).map(|n| RoomSummary {
+        room_id: format!("room_{}", n),
+        status: "active".to_string(),
+        sender_client_id: Some(format!("sender_{}", n)),
+        receiver_client_id: None,
+        participant_count: 1,
+        created_at: 1_700_000_000_000 + n,
+    }).collect();
+    let port = spawn_mock_signal_manager(rooms.clone()).await;
+
+    let config = SignalManagerConfig::new("127.0.0.1".to_string(), port, "test_client".to_string(), "test_token".to_string());
+    let client = Arc::new(TokioMutex::new(SignalManagerClient::new(config)));
+    *commands::SIGNAL_MANAGER.lock().await = Some(client.clone());
+
+    // Not connected yet
+    let err = commands::list_rooms().await.unwrap_err();
+    assert_eq!(err, CommandError::NotConnected("Not connected".to_string()));
+
+    client.lock().await.connect().await.unwrap();
+    assert_eq!(commands::list_rooms().await.unwrap(), rooms);
+
+    commands::reset_signal_manager().await.unwrap();
+    assert_eq!(
+        commands::list_rooms().await.unwrap_err(),
+        CommandError::NotConnected("Signal manager not initialized".to_string())
+    );
+}
+
+#[tokio::test]
+async fn test_list_rooms_refused_by_server() {
+    let port = spawn_mock_signal_manager_with(|_| Payload::Error(ErrorPayload {
+        error_code: 8,
+        error_message: "Room listing is restricted to admin clients".to_string(),
+    }))
+    .await;
+
+    let config = SignalManagerConfig::new("127.0.0.1".to_string(), port, "test_client".to_string(), "test_token".to_string());
+    let mut client = SignalManagerClient::new(config);
+    client.connect().await.unwrap();
+
+    // Reported as soon as the Error arrives rather than after the command timeout
+    let err = tokio::time::timeout(std::time::Duration::from_secs(5), client.list_rooms())
+        .await
+        .expect("list_rooms waited for the timeout")
+        .unwrap_err();
+    assert_eq!(
+        CommandError::from(err),
+        CommandError::Forbidden("Server refused the request (8): Room listing is restricted to admin clients".to_string())
+    );
+}
+
+#[test]
+fn test_room_summary_defaults_missing_fields() {
+    let summary: RoomSummary = serde_json::from_str(
+        r#"{"room_id":"room_1","sender_client_id":null,"receiver_client_id":null,"created_at":1}"#,
+    )
+    .unwrap();
+    assert_eq!(summary.status, "");
+    assert_eq!(summary.participant_count, 0);
+}
+
+#[test]
+fn test_compressed_payload_inflating_past_limit_rejected() {
+    use tauri_app_lib::signalmanager::{MAX_DECOMPRESSED_PAYLOAD, PAYLOAD_TYPE_ZSTD_FLAG, START_BYTE};
+
+    let frame = |payload: &[u8]| {
+        let compressed = zstd::bulk::compress(payload, 19).unwrap();
+        let mut frame = vec![START_BYTE, MessageType::WebRTCRoomListAck as u8];
+        frame.extend_from_slice(uuid::Uuid::new_v4().as_bytes());
+        frame.push(0x02 | PAYLOAD_TYPE_ZSTD_FLAG);
+        frame.extend_from_slice(&(compressed.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&compressed);
+        frame
+    };
+
+    let ack = br#"{"WebRTCRoomListAck":{"rooms":[]}}"#;
+    assert!(Message::from_binary(&frame(ack)).is_ok());
+    let bomb = frame(&vec![b' '; MAX_DECOMPRESSED_PAYLOAD + 1]);
+    assert!(bomb.len() < 1024);
+    assert!(Message::from_binary(&bomb).is_err());
+}
@@ -0,0 +1,126 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use tauri_app_lib::signalmanager::{DisconnectPayload, Message, MessageType, Payload};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+use tokio_tungstenite::WebSocketStream;
+
+// Mock signal manager the tests drive by hand: it records every frame clients send, and can
+// refuse connection attempts, answer on the open connection or drop it on command
+pub struct MockSignalManager {
+    pub port: u16,
+    refuse: Arc<AtomicUsize>,
+    attempts: Arc<AtomicUsize>,
+    active: Arc<Mutex<Option<mpsc::UnboundedSender<Command>>>>,
+    received: mpsc::UnboundedReceiver<(usize, Message)>,
+}
+
+enum Command {
+    Send(Box<Message>),
+    // Close, sending a Disconnect with the reason first if given
+    Drop(Option<String>),
+}
+
+impl MockSignalManager {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let refuse = Arc::new(AtomicUsize::new(0));
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let active = Arc::new(Mutex::new(None));
+        let (received_tx, received) = mpsc::unbounded_channel();
+        tokio::spawn(Self::accept_loop(listener, refuse.clone(), attempts.clone(), active.clone(), received_tx));
+        Self { port, refuse, attempts, active, received }
+    }
+
+    // Close the next `count` connection attempts before the WebSocket handshake
+    pub fn refuse_next(&self, count: usize) {
+        self.refuse.store(count, Ordering::SeqCst);
+    }
+
+    // Send `message` to the client on the open connection
+    pub fn reply(&self, message: Message) {
+        let active = self.active.lock().unwrap();
+        active.as_ref().expect("No open connection to reply on").send(Command::Send(Box::new(message))).unwrap();
+    }
+
+    // Close the open connection, sending a Disconnect with `reason` first if given
+    pub fn drop_connection(&self, reason: Option<&str>) {
+        let active = self.active.lock().unwrap().take().expect("No open connection to drop");
+        active.send(Command::Drop(reason.map(str::to_string))).unwrap();
+    }
+
+    // TCP connections made so far, refused ones included
+    pub fn connection_attempts(&self) -> usize {
+        self.attempts.load(Ordering::SeqCst)
+    }
+
+    // Next frame a client sent, with the 1-based number of the connection it came in on
+    pub async fn next_message(&mut self) -> (usize, Message) {
+        tokio::time::timeout(Duration::from_secs(10), self.received.recv())
+            .await
+            .expect("Timed out waiting for a client frame")
+            .expect("Mock signal manager stopped")
+    }
+
+    async fn accept_loop(
+        listener: TcpListener,
+        refuse: Arc<AtomicUsize>,
+        attempts: Arc<AtomicUsize>,
+        active: Arc<Mutex<Option<mpsc::UnboundedSender<Command>>>>,
+        received: mpsc::UnboundedSender<(usize, Message)>,
+    ) {
+        let mut connections = 0;
+        while let Ok((stream, _)) = listener.accept().await {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            if refuse.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| left.checked_sub(1)).is_ok() {
+                drop(stream);
+                continue;
+            }
+            let Ok(ws) = tokio_tungstenite::accept_async(stream).await else { continue };
+            connections += 1;
+            let (command_tx, command_rx) = mpsc::unbounded_channel();
+            *active.lock().unwrap() = Some(command_tx);
+            tokio::spawn(Self::serve(ws, connections, command_rx, received.clone()));
+        }
+    }
+
+    async fn serve(
+        mut ws: WebSocketStream<TcpStream>,
+        connection: usize,
+        mut commands: mpsc::UnboundedReceiver<Command>,
+        received: mpsc::UnboundedSender<(usize, Message)>,
+    ) {
+        loop {
+            tokio::select! {
+                frame = ws.next() => match frame {
+                    Some(Ok(WsMessage::Binary(data))) => {
+                        let _ = received.send((connection, Message::from_binary(&data).unwrap()));
+                    }
+                    Some(Ok(_)) => {}
+                    _ => return,
+                },
+                Some(command) = commands.recv() => match command {
+                    Command::Send(message) => {
+                        let _ = ws.send(WsMessage::Binary(message.to_binary().unwrap())).await;
+                    }
+                    Command::Drop(reason) => {
+                        if let Some(reason) = reason {
+                            let disconnect = Message::new(
+                                MessageType::Disconnect,
+                                Payload::Disconnect(DisconnectPayload { client_id: String::new(), reason }),
+                            );
+                            let _ = ws.send(WsMessage::Binary(disconnect.to_binary().unwrap())).await;
+                        }
+                        let _ = ws.close(None).await;
+                        return;
+                    }
+                },
+            }
+        }
+    }
+}
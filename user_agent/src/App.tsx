@@ -25,6 +25,29 @@ interface RoomCreatedEvent {
   sessionId: string | null;
 }
 
+// Error shape returned by every Tauri command
+interface CommandError {
+  kind: 'NotConnected' | 'Timeout' | 'WebRtc' | 'Signaling' | 'InvalidInput' | 'Forbidden' | 'Io' | 'Tauri';
+  message: string;
+}
+
+const isCommandError = (err: unknown): err is CommandError =>
+  typeof err === 'object' && err !== null && 'kind' in err && 'message' in err;
+
+const describeError = (err: unknown, fallback: string): string => {
+  if (isCommandError(err)) return err.message;
+  if (err instanceof Error) return err.message;
+  return fallback;
+};
+
+interface ResumeState {
+  client_id: string;
+  session_id: string | null;
+  reconnect_token: string;
+  room_id: string;
+  role: string;
+}
+
 export default function App() {
   const [state, setState] = React.useState<ConnectionState>({
     state_type: 'disconnected_not_to_connect',
@@ -184,7 +207,7 @@ export default function App() {
 
       } catch (err) {
         console.error('Failed to set up event listeners:', err);
-        setError(`Failed to set up event listeners: ${err instanceof Error ? err.message : 'Unknown error'}`);
+        setError(`Failed to set up event listeners: ${describeError(err, 'Unknown error')}`);
       }
     };
 
@@ -196,6 +219,30 @@ export default function App() {
     };
   }, []);
 
+  // Offer to pick up a session the previous run didn't leave cleanly
+  React.useEffect(() => {
+    const offerResume = async () => {
+      try {
+        const saved = await invoke<ResumeState | null>('get_resume_state');
+        if (!saved) return;
+        if (window.confirm(`Resume room ${saved.room_id} as ${saved.role}?`)) {
+          setClientId(saved.client_id);
+          setAuthToken(saved.reconnect_token);
+          setRole(saved.role === 'receiver' ? 'receiver' : 'sender');
+          setCreatedRoomId(saved.room_id);
+          setCreatedSessionId(saved.session_id);
+          setRoomId(saved.room_id);
+        } else {
+          await invoke('discard_resume_state');
+        }
+      } catch (err) {
+        console.error('Failed to load resume state:', err);
+      }
+    };
+
+    offerResume();
+  }, []);
+
   // UI Event Handlers - Pure UI logic only
   const handleConnect = async () => {
     setIsConnecting(true);
@@ -210,7 +257,7 @@ export default function App() {
       });
       await invoke('connect_signal_manager');
     } catch (err) {
-      setError(`Connection failed: ${err instanceof Error ? err.message : 'Unknown error'}`);
+      setError(`Connection failed: ${describeError(err, 'Unknown error')}`);
       setIsConnecting(false);
     }
   };
@@ -232,7 +279,7 @@ export default function App() {
       // Reset all application state
       resetApplicationState();
     } catch (err) {
-      const errorMessage = err instanceof Error ? err.message : 'Unknown error';
+      const errorMessage = describeError(err, 'Unknown error');
       setError(`Disconnect failed: ${errorMessage}`);
       setIsDisconnecting(false);
       
@@ -254,7 +301,7 @@ export default function App() {
       setOfferSdp(offer.sdp);
       return offer;
     } catch (err) {
-      const errorMessage = err instanceof Error ? err.message : 'Failed to generate WebRTC offer';
+      const errorMessage = describeError(err, 'Failed to generate WebRTC offer');
       setWebrtcError(errorMessage);
       return null;
     } finally {